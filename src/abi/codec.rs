@@ -0,0 +1,622 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encoding and decoding of values in the Solidity ABI, as done by ABI coder
+//! v2, see the [specification][ref].
+//!
+//! Values are encoded as a tuple: the head has a word for each value, or the
+//! words of the value itself when it is static, and the dynamic values follow
+//! in the tail, their word in the head holding their offset from the start of
+//! the tuple. Arrays of dynamic types, structs holding strings and arrays of
+//! arrays nest this layout, each inner tuple starting its own offsets.
+//!
+//! [ref]: https://docs.soliditylang.org/en/latest/abi-spec.html#formal-specification-of-the-encoding
+
+use std::{fmt, iter, str::FromStr};
+
+use itertools::Itertools;
+use num_bigint::{BigInt, Sign};
+use num_traits::{Signed, ToPrimitive};
+use thiserror::Error;
+
+/// Size of a word of the encoding
+pub const WORD_SIZE: usize = 32;
+
+/// A type of the ABI, as written in canonical signatures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamType {
+    Address,
+    Bool,
+    Int(u16),
+    Uint(u16),
+    /// `bytes1` to `bytes32`
+    FixedBytes(u8),
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<ParamType>),
+}
+
+impl ParamType {
+    /// Whether the encoding of the type has a variable size, and goes in the
+    /// tail
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(elem, _) => elem.is_dynamic(),
+            ParamType::Tuple(types) => types.iter().any(ParamType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// The size the type takes in the head of a tuple
+    fn head_size(&self) -> usize {
+        match self {
+            ty if ty.is_dynamic() => WORD_SIZE,
+            ParamType::FixedArray(elem, len) => elem.head_size() * len,
+            ParamType::Tuple(types) => types.iter().map(ParamType::head_size).sum(),
+            _ => WORD_SIZE,
+        }
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamType::Address => f.write_str("address"),
+            ParamType::Bool => f.write_str("bool"),
+            ParamType::Int(bits) => write!(f, "int{bits}"),
+            ParamType::Uint(bits) => write!(f, "uint{bits}"),
+            ParamType::FixedBytes(len) => write!(f, "bytes{len}"),
+            ParamType::Bytes => f.write_str("bytes"),
+            ParamType::String => f.write_str("string"),
+            ParamType::Array(elem) => write!(f, "{elem}[]"),
+            ParamType::FixedArray(elem, len) => write!(f, "{elem}[{len}]"),
+            ParamType::Tuple(types) => write!(f, "({})", types.iter().join(",")),
+        }
+    }
+}
+
+/// Parse a canonical type, like `uint256[][2]` or `(address,string)[]`
+impl FromStr for ParamType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid ABI type '{s}'");
+
+        // The last dimension of an array is the outermost
+        if let Some(elem) = s.strip_suffix(']') {
+            let (elem, len) = elem.rsplit_once('[').ok_or_else(invalid)?;
+            let elem = Box::new(elem.parse()?);
+            return match len {
+                "" => Ok(ParamType::Array(elem)),
+                len => Ok(ParamType::FixedArray(elem, len.parse().map_err(|_| invalid())?)),
+            };
+        }
+
+        if let Some(fields) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            return split_fields(fields)
+                .into_iter()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map(ParamType::Tuple);
+        }
+
+        let bits = |digits: &str, max: u16, step: u16| match digits {
+            "" => Some(max),
+            digits => {
+                digits.parse().ok().filter(|bits| *bits > 0 && *bits <= max && bits % step == 0)
+            }
+        };
+
+        match s {
+            "address" => Ok(ParamType::Address),
+            "bool" => Ok(ParamType::Bool),
+            "bytes" => Ok(ParamType::Bytes),
+            "string" => Ok(ParamType::String),
+            _ => {
+                if let Some(digits) = s.strip_prefix("uint") {
+                    bits(digits, 256, 8).map(ParamType::Uint).ok_or_else(invalid)
+                } else if let Some(digits) = s.strip_prefix("int") {
+                    bits(digits, 256, 8).map(ParamType::Int).ok_or_else(invalid)
+                } else if let Some(digits) = s.strip_prefix("bytes") {
+                    bits(digits, 32, 1)
+                        .map(|len| ParamType::FixedBytes(len as u8))
+                        .ok_or_else(invalid)
+                } else {
+                    Err(invalid())
+                }
+            }
+        }
+    }
+}
+
+/// Split the fields of a tuple at the commas outside of nested tuples
+fn split_fields(fields: &str) -> Vec<&str> {
+    if fields.is_empty() {
+        return Vec::new();
+    }
+
+    let mut depth = 0;
+    let mut start = 0;
+    let mut parts = Vec::new();
+    for (i, c) in fields.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&fields[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&fields[start..]);
+    parts
+}
+
+/// A value of the ABI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Address([u8; 20]),
+    Bool(bool),
+    /// A signed integer, encoded in two's complement
+    Int(BigInt),
+    Uint(BigInt),
+    /// The bytes of a `bytesN` value
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    Array(Vec<Token>),
+    FixedArray(Vec<Token>),
+    Tuple(Vec<Token>),
+}
+
+impl Token {
+    fn is_dynamic(&self) -> bool {
+        match self {
+            Token::Bytes(_) | Token::String(_) | Token::Array(_) => true,
+            Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+                tokens.iter().any(Token::is_dynamic)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("the data ends before the value at offset {0}")]
+    Truncated(usize),
+
+    #[error("the offset or length at offset {0} is out of range")]
+    OutOfRange(usize),
+
+    #[error("the {1} at offset {0} has bits set outside of its type")]
+    Dirty(usize, ParamType),
+
+    #[error("the string at offset {0} is not valid UTF-8")]
+    InvalidUtf8(usize),
+}
+
+/// Encode the values as a tuple, like the arguments of a call. Integers are
+/// assumed to fit their type.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_tuple(tokens, &mut out);
+    out
+}
+
+fn encode_tuple(tokens: &[Token], out: &mut Vec<u8>) {
+    let head_size = tokens
+        .iter()
+        .map(|token| if token.is_dynamic() { WORD_SIZE } else { encoded_len(token) })
+        .sum::<usize>();
+
+    let mut tail = Vec::new();
+    for token in tokens {
+        match token.is_dynamic() {
+            true => {
+                out.extend(word_of_usize(head_size + tail.len()));
+                encode_token(token, &mut tail);
+            }
+            false => encode_token(token, out),
+        }
+    }
+    out.extend(tail);
+}
+
+fn encode_token(token: &Token, out: &mut Vec<u8>) {
+    match token {
+        Token::Address(address) => {
+            out.extend([0; 12]);
+            out.extend(address);
+        }
+        Token::Bool(value) => out.extend(word_of_usize(*value as usize)),
+        Token::Int(value) | Token::Uint(value) => out.extend(word_of_int(value)),
+        Token::FixedBytes(bytes) => {
+            out.extend(bytes);
+            out.extend(iter::repeat_n(0, WORD_SIZE - bytes.len()));
+        }
+        Token::Bytes(bytes) => encode_bytes(bytes, out),
+        Token::String(string) => encode_bytes(string.as_bytes(), out),
+        Token::Array(tokens) => {
+            out.extend(word_of_usize(tokens.len()));
+            encode_tuple(tokens, out);
+        }
+        Token::FixedArray(tokens) | Token::Tuple(tokens) => encode_tuple(tokens, out),
+    }
+}
+
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend(word_of_usize(bytes.len()));
+    out.extend(bytes);
+    out.extend(iter::repeat_n(0, padding(bytes.len())));
+}
+
+/// The size of the encoding of a static value
+fn encoded_len(token: &Token) -> usize {
+    match token {
+        Token::FixedArray(tokens) | Token::Tuple(tokens) => tokens.iter().map(encoded_len).sum(),
+        _ => WORD_SIZE,
+    }
+}
+
+fn padding(len: usize) -> usize {
+    (WORD_SIZE - len % WORD_SIZE) % WORD_SIZE
+}
+
+fn word_of_usize(value: usize) -> [u8; WORD_SIZE] {
+    let mut word = [0; WORD_SIZE];
+    word[WORD_SIZE - 8..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+/// The low 256 bits of the value in two's complement
+fn word_of_int(value: &BigInt) -> [u8; WORD_SIZE] {
+    let bytes = value.to_signed_bytes_be();
+    let fill = if value.is_negative() { 0xff } else { 0 };
+    let mut word = [fill; WORD_SIZE];
+    let len = bytes.len().min(WORD_SIZE);
+    word[WORD_SIZE - len..].copy_from_slice(&bytes[bytes.len() - len..]);
+    word
+}
+
+/// Decode a tuple of values of the types, rejecting values with bits set
+/// outside of their type, as ABI coder v2 does
+pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, DecodeError> {
+    Decoder { data }.tuple(types.iter(), 0)
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    /// Decode the values of a tuple starting at the offset
+    fn tuple<'t>(
+        &self,
+        types: impl Iterator<Item = &'t ParamType>,
+        start: usize,
+    ) -> Result<Vec<Token>, DecodeError> {
+        let mut tokens = Vec::new();
+        let mut head = start;
+
+        for ty in types {
+            let token = match ty.is_dynamic() {
+                true => {
+                    let offset = self.usize(head)?;
+                    let at = start.checked_add(offset).filter(|at| *at <= self.data.len());
+                    self.token(ty, at.ok_or(DecodeError::OutOfRange(head))?)?
+                }
+                false => self.token(ty, head)?,
+            };
+            tokens.push(token);
+            head += ty.head_size();
+        }
+
+        Ok(tokens)
+    }
+
+    fn token(&self, ty: &ParamType, at: usize) -> Result<Token, DecodeError> {
+        match ty {
+            ParamType::Address => {
+                let word = self.word(at)?;
+                if word[..12].iter().any(|b| *b != 0) {
+                    return Err(DecodeError::Dirty(at, ty.clone()));
+                }
+                Ok(Token::Address(word[12..].try_into().unwrap()))
+            }
+            ParamType::Bool => match self.uint(at, ty, 8)?.to_u8() {
+                Some(value @ (0 | 1)) => Ok(Token::Bool(value == 1)),
+                _ => Err(DecodeError::Dirty(at, ty.clone())),
+            },
+            ParamType::Uint(bits) => self.uint(at, ty, *bits).map(Token::Uint),
+            ParamType::Int(bits) => {
+                let word = self.word(at)?;
+                let value = BigInt::from_signed_bytes_be(word);
+                let limit = BigInt::from(1) << (bits - 1);
+                match value >= -limit.clone() && value < limit {
+                    true => Ok(Token::Int(value)),
+                    false => Err(DecodeError::Dirty(at, ty.clone())),
+                }
+            }
+            ParamType::FixedBytes(len) => {
+                let word = self.word(at)?;
+                let len = *len as usize;
+                if word[len..].iter().any(|b| *b != 0) {
+                    return Err(DecodeError::Dirty(at, ty.clone()));
+                }
+                Ok(Token::FixedBytes(word[..len].to_vec()))
+            }
+            ParamType::Bytes => Ok(Token::Bytes(self.bytes(at)?.to_vec())),
+            ParamType::String => {
+                let bytes = self.bytes(at)?;
+                let string =
+                    String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8(at))?;
+                Ok(Token::String(string))
+            }
+            ParamType::Array(elem) => {
+                let len = self.usize(at)?;
+                // Each element takes at least a word, which bounds the length
+                // before anything is allocated for it
+                let start = at + WORD_SIZE;
+                if len > (self.data.len() - start) / WORD_SIZE {
+                    return Err(DecodeError::OutOfRange(at));
+                }
+                self.tuple(iter::repeat_n(elem.as_ref(), len), start).map(Token::Array)
+            }
+            ParamType::FixedArray(elem, len) => {
+                self.tuple(iter::repeat_n(elem.as_ref(), *len), at).map(Token::FixedArray)
+            }
+            ParamType::Tuple(types) => self.tuple(types.iter(), at).map(Token::Tuple),
+        }
+    }
+
+    fn word(&self, at: usize) -> Result<&[u8], DecodeError> {
+        self.data.get(at..at + WORD_SIZE).ok_or(DecodeError::Truncated(at))
+    }
+
+    /// An unsigned integer, which must fit in the bits
+    fn uint(&self, at: usize, ty: &ParamType, bits: u16) -> Result<BigInt, DecodeError> {
+        let word = self.word(at)?;
+        let unused = (256 - bits as usize) / 8;
+        if word[..unused].iter().any(|b| *b != 0) {
+            return Err(DecodeError::Dirty(at, ty.clone()));
+        }
+        Ok(BigInt::from_bytes_be(Sign::Plus, word))
+    }
+
+    /// An offset or length, which must fit in the data
+    fn usize(&self, at: usize) -> Result<usize, DecodeError> {
+        let value = BigInt::from_bytes_be(Sign::Plus, self.word(at)?);
+        value
+            .to_usize()
+            .filter(|value| *value <= self.data.len())
+            .ok_or(DecodeError::OutOfRange(at))
+    }
+
+    /// The contents of `bytes` or `string`, after their length
+    fn bytes(&self, at: usize) -> Result<&[u8], DecodeError> {
+        let len = self.usize(at)?;
+        let start = at + WORD_SIZE;
+        self.data.get(start..start + len).ok_or(DecodeError::Truncated(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(words: &str) -> Vec<u8> {
+        let digits = words.split_whitespace().collect::<String>();
+        (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn types(signature: &str) -> Vec<ParamType> {
+        let ParamType::Tuple(types) = signature.parse().unwrap() else {
+            panic!("expected a tuple");
+        };
+        types
+    }
+
+    fn uint(value: u64) -> Token {
+        Token::Uint(value.into())
+    }
+
+    /// Check the encoding against the known one, and that it decodes back
+    fn check(signature: &str, tokens: Vec<Token>, expected: &str) {
+        let encoded = encode(&tokens);
+        assert_eq!(encoded, hex(expected), "encoding of {signature}");
+        assert_eq!(decode(&types(signature), &encoded), Ok(tokens), "decoding of {signature}");
+    }
+
+    #[test]
+    fn test_param_types() {
+        for ty in ["uint256[][2]", "(address,(string,bytes32)[])[3]", "int8", "bytes1", "()"] {
+            assert_eq!(ty.parse::<ParamType>().unwrap().to_string(), ty);
+        }
+        assert_eq!(
+            "uint8[][2]".parse(),
+            Ok(ParamType::FixedArray(Box::new(ParamType::Array(Box::new(ParamType::Uint(8)))), 2))
+        );
+        assert!("(uint256,string)[]".parse::<ParamType>().unwrap().is_dynamic());
+        assert!(!"(uint256,bytes32)[2]".parse::<ParamType>().unwrap().is_dynamic());
+
+        for ty in ["uint7", "uint264", "bytes33", "int0", "uint[x]", "(uint256"] {
+            assert!(ty.parse::<ParamType>().is_err(), "{ty}");
+        }
+    }
+
+    /// The examples of the Solidity documentation, as encoded by solc
+    #[test]
+    fn test_solc_vectors() {
+        check(
+            "(uint32,bool)",
+            vec![uint(69), Token::Bool(true)],
+            "0000000000000000000000000000000000000000000000000000000000000045
+             0000000000000000000000000000000000000000000000000000000000000001",
+        );
+
+        check(
+            "(bytes,bool,uint256[])",
+            vec![
+                Token::Bytes(b"dave".to_vec()),
+                Token::Bool(true),
+                Token::Array(vec![uint(1), uint(2), uint(3)]),
+            ],
+            "0000000000000000000000000000000000000000000000000000000000000060
+             0000000000000000000000000000000000000000000000000000000000000001
+             00000000000000000000000000000000000000000000000000000000000000a0
+             0000000000000000000000000000000000000000000000000000000000000004
+             6461766500000000000000000000000000000000000000000000000000000000
+             0000000000000000000000000000000000000000000000000000000000000003
+             0000000000000000000000000000000000000000000000000000000000000001
+             0000000000000000000000000000000000000000000000000000000000000002
+             0000000000000000000000000000000000000000000000000000000000000003",
+        );
+
+        check(
+            "(uint256,uint32[],bytes10,bytes)",
+            vec![
+                uint(0x123),
+                Token::Array(vec![uint(0x456), uint(0x789)]),
+                Token::FixedBytes(b"1234567890".to_vec()),
+                Token::Bytes(b"Hello, world!".to_vec()),
+            ],
+            "0000000000000000000000000000000000000000000000000000000000000123
+             0000000000000000000000000000000000000000000000000000000000000080
+             3132333435363738393000000000000000000000000000000000000000000000
+             00000000000000000000000000000000000000000000000000000000000000e0
+             0000000000000000000000000000000000000000000000000000000000000002
+             0000000000000000000000000000000000000000000000000000000000000456
+             0000000000000000000000000000000000000000000000000000000000000789
+             000000000000000000000000000000000000000000000000000000000000000d
+             48656c6c6f2c20776f726c642100000000000000000000000000000000000000",
+        );
+
+        // Multi-dimensional dynamic arrays, and arrays of strings
+        check(
+            "(uint256[][],string[])",
+            vec![
+                Token::Array(vec![
+                    Token::Array(vec![uint(1), uint(2)]),
+                    Token::Array(vec![uint(3)]),
+                ]),
+                Token::Array(vec![
+                    Token::String("one".into()),
+                    Token::String("two".into()),
+                    Token::String("three".into()),
+                ]),
+            ],
+            "0000000000000000000000000000000000000000000000000000000000000040
+             0000000000000000000000000000000000000000000000000000000000000140
+             0000000000000000000000000000000000000000000000000000000000000002
+             0000000000000000000000000000000000000000000000000000000000000040
+             00000000000000000000000000000000000000000000000000000000000000a0
+             0000000000000000000000000000000000000000000000000000000000000002
+             0000000000000000000000000000000000000000000000000000000000000001
+             0000000000000000000000000000000000000000000000000000000000000002
+             0000000000000000000000000000000000000000000000000000000000000001
+             0000000000000000000000000000000000000000000000000000000000000003
+             0000000000000000000000000000000000000000000000000000000000000003
+             0000000000000000000000000000000000000000000000000000000000000060
+             00000000000000000000000000000000000000000000000000000000000000a0
+             00000000000000000000000000000000000000000000000000000000000000e0
+             0000000000000000000000000000000000000000000000000000000000000003
+             6f6e650000000000000000000000000000000000000000000000000000000000
+             0000000000000000000000000000000000000000000000000000000000000003
+             74776f0000000000000000000000000000000000000000000000000000000000
+             0000000000000000000000000000000000000000000000000000000000000005
+             7468726565000000000000000000000000000000000000000000000000000000",
+        );
+    }
+
+    /// Structs holding strings, in a dynamic array, and static structs in a
+    /// fixed size array, inline in the head
+    #[test]
+    fn test_nested_tuples() {
+        check(
+            "((uint256,string)[])",
+            vec![Token::Array(vec![
+                Token::Tuple(vec![uint(1), Token::String("a".into())]),
+                Token::Tuple(vec![uint(2), Token::String("bc".into())]),
+            ])],
+            "0000000000000000000000000000000000000000000000000000000000000020
+             0000000000000000000000000000000000000000000000000000000000000002
+             0000000000000000000000000000000000000000000000000000000000000040
+             00000000000000000000000000000000000000000000000000000000000000c0
+             0000000000000000000000000000000000000000000000000000000000000001
+             0000000000000000000000000000000000000000000000000000000000000040
+             0000000000000000000000000000000000000000000000000000000000000001
+             6100000000000000000000000000000000000000000000000000000000000000
+             0000000000000000000000000000000000000000000000000000000000000002
+             0000000000000000000000000000000000000000000000000000000000000040
+             0000000000000000000000000000000000000000000000000000000000000002
+             6263000000000000000000000000000000000000000000000000000000000000",
+        );
+
+        check(
+            "((int8,bool)[2],address)",
+            vec![
+                Token::FixedArray(vec![
+                    Token::Tuple(vec![Token::Int((-1).into()), Token::Bool(false)]),
+                    Token::Tuple(vec![Token::Int(127.into()), Token::Bool(true)]),
+                ]),
+                Token::Address([0x11; 20]),
+            ],
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff
+             0000000000000000000000000000000000000000000000000000000000000000
+             000000000000000000000000000000000000000000000000000000000000007f
+             0000000000000000000000000000000000000000000000000000000000000001
+             0000000000000000000000001111111111111111111111111111111111111111",
+        );
+
+        // Arrays of arrays of structs holding bytes
+        let tokens = vec![Token::Array(vec![
+            Token::Array(vec![Token::Tuple(vec![Token::Bytes(vec![0xab; 40]), uint(7)])]),
+            Token::Array(Vec::new()),
+        ])];
+        let encoded = encode(&tokens);
+        assert_eq!(decode(&types("((bytes,uint64)[][])"), &encoded), Ok(tokens));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let types = types("(uint8,bool,string)");
+        let mut data = encode(&[uint(1), Token::Bool(true), Token::String("x".into())]);
+        assert!(decode(&types, &data).is_ok());
+
+        assert_eq!(decode(&types, &data[..128]), Err(DecodeError::Truncated(128)));
+
+        // A uint8 with bits above its 8, a bool other than 0 or 1
+        let mut dirty = data.clone();
+        dirty[30] = 1;
+        assert_eq!(decode(&types, &dirty), Err(DecodeError::Dirty(0, ParamType::Uint(8))));
+        let mut dirty = data.clone();
+        dirty[63] = 2;
+        assert_eq!(decode(&types, &dirty), Err(DecodeError::Dirty(32, ParamType::Bool)));
+
+        // An offset past the end, and an array longer than the data
+        data[95] = 0xff;
+        assert_eq!(decode(&types, &data), Err(DecodeError::OutOfRange(64)));
+        let mut data = encode(&[Token::Array(vec![uint(1)])]);
+        data[63] = 0x10;
+        assert_eq!(decode(&self::types("(uint256[])"), &data), Err(DecodeError::OutOfRange(32)));
+    }
+}
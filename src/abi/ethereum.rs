@@ -0,0 +1,467 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The Ethereum JSON ABI, see the [Solidity ABI specification][ref].
+//!
+//! [ref]: https://docs.soliditylang.org/en/latest/abi-spec.html#json
+
+use itertools::Itertools;
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    helpers::json::Json,
+    parser::ast as pt,
+    semantic::{
+        ast::{ArrayLength, Function, Parameter, Type},
+        context::Context,
+    },
+};
+
+/// Generate the JSON ABI of a contract
+pub fn gen_abi(contract_no: usize, ctx: &Context) -> Json {
+    let contract = &ctx.contracts[contract_no];
+    let mut abi = Vec::new();
+
+    for function_no in contract.all_functions.keys() {
+        let func = &ctx.functions[*function_no];
+
+        if !is_callable(contract_no, *function_no, func, ctx) {
+            continue;
+        }
+
+        let entry = match func.ty {
+            pt::FunctionTy::Constructor => Json::object([
+                ("type", "constructor".into()),
+                ("inputs", params(&func.params, false, ctx)),
+                ("stateMutability", func.mutability.to_string().into()),
+            ]),
+            pt::FunctionTy::Fallback | pt::FunctionTy::Receive => Json::object([
+                ("type", func.ty.to_string().into()),
+                ("stateMutability", func.mutability.to_string().into()),
+            ]),
+            _ => Json::object([
+                ("type", "function".into()),
+                ("name", func.id.name.as_str().into()),
+                ("inputs", params(&func.params, false, ctx)),
+                ("outputs", params(&func.returns, false, ctx)),
+                ("stateMutability", func.mutability.to_string().into()),
+            ]),
+        };
+
+        abi.push(entry);
+    }
+
+    let mut events = contract.emits_events.clone();
+    for (event_no, event) in ctx.events.iter().enumerate() {
+        if event.contract == Some(contract_no) && !events.contains(&event_no) {
+            events.push(event_no);
+        }
+    }
+
+    for event_no in events {
+        let event = &ctx.events[event_no];
+
+        abi.push(Json::object([
+            ("type", "event".into()),
+            ("name", event.id.name.as_str().into()),
+            ("inputs", params(&event.fields, true, ctx)),
+            ("anonymous", event.anonymous.into()),
+        ]));
+    }
+
+    for error in ctx.errors.iter().filter(|error| error.contract == Some(contract_no)) {
+        abi.push(Json::object([
+            ("type", "error".into()),
+            ("name", error.name.as_str().into()),
+            ("inputs", params(&error.fields, false, ctx)),
+        ]));
+    }
+
+    Json::Array(abi)
+}
+
+/// The functions of an interface which contribute to its ERC-165 interface id:
+/// those it declares itself, not those it inherits
+pub fn interface_functions(contract_no: usize, ctx: &Context) -> Vec<usize> {
+    ctx.contracts[contract_no]
+        .functions
+        .iter()
+        .copied()
+        .filter(|function_no| ctx.functions[*function_no].ty == pt::FunctionTy::Function)
+        .collect()
+}
+
+/// The ERC-165 interface id of an interface, `type(I).interfaceId`: the xor of
+/// the selectors of its functions
+pub fn interface_id(contract_no: usize, ctx: &Context) -> [u8; 4] {
+    let mut id = [0; 4];
+    for function_no in interface_functions(contract_no, ctx) {
+        for (byte, selector) in id.iter_mut().zip(ctx.functions[function_no].selector()) {
+            *byte ^= selector;
+        }
+    }
+    id
+}
+
+/// The ERC-165 interface ids of the interfaces a contract implements, itself
+/// included if it is one, by name
+pub fn gen_interface_ids(contract_no: usize, ctx: &Context) -> Json {
+    Json::object(
+        ctx.contract_bases(contract_no)
+            .into_iter()
+            .filter(|base_no| ctx.contracts[*base_no].is_interface())
+            .map(|base_no| {
+                let id = format!("0x{}", hex(&interface_id(base_no, ctx)));
+                (ctx.contracts[base_no].id.name.clone(), id.into())
+            }),
+    )
+}
+
+/// Is the function part of the external interface of the contract. Functions of
+/// base contracts are included, unless they are overridden or are constructors.
+pub(crate) fn is_callable(
+    contract_no: usize,
+    function_no: usize,
+    func: &Function,
+    ctx: &Context,
+) -> bool {
+    if !func.is_public() || func.ty == pt::FunctionTy::Modifier {
+        return false;
+    }
+
+    if func.is_constructor() && func.contract_no != Some(contract_no) {
+        return false;
+    }
+
+    match ctx.contracts[contract_no].virtual_functions.get(&func.signature) {
+        Some(overrides) => overrides.last() == Some(&function_no),
+        None => true,
+    }
+}
+
+fn params(params: &[Parameter<Type>], event: bool, ctx: &Context) -> Json {
+    Json::Array(
+        params
+            .iter()
+            .map(|param| {
+                let mut json = Json::object([
+                    ("name", param.name_as_str().into()),
+                    ("type", abi_type(&param.ty, ctx).into()),
+                    ("internalType", internal_type(&param.ty, ctx).into()),
+                ]);
+
+                if let Some(components) = components(&param.ty, ctx) {
+                    json.insert("components", components);
+                }
+
+                if event {
+                    json.insert("indexed", param.indexed.into());
+                }
+
+                json
+            })
+            .collect(),
+    )
+}
+
+/// The fields of a struct type, or of the element of an array of structs
+fn components(ty: &Type, ctx: &Context) -> Option<Json> {
+    match ty {
+        Type::Struct(struct_ty) => Some(params(&struct_ty.definition(ctx).fields, false, ctx)),
+        Type::Array(elem, _) | Type::Ref(elem) | Type::StorageRef(_, elem) => components(elem, ctx),
+        _ => None,
+    }
+}
+
+/// The canonical ABI type, as used in function signatures
+pub fn abi_type(ty: &Type, ctx: &Context) -> String {
+    match ty {
+        Type::Address(_) | Type::Contract(_) => "address".into(),
+        Type::Enum(_) => "uint8".into(),
+        Type::Struct(_) => "tuple".into(),
+        Type::Array(elem, dims) => format!("{}{}", abi_type(elem, ctx), array_dims(dims)),
+        Type::UserType(no) => abi_type(&ctx.user_types[*no].ty, ctx),
+        Type::ExternalFunction { .. } => "function".into(),
+        Type::Ref(ty) | Type::StorageRef(_, ty) => abi_type(ty, ctx),
+        _ => ty.to_string(ctx),
+    }
+}
+
+/// The canonical signature of a function, event or error, like
+/// `transfer(address,uint256)`. Structs are written as the tuple of their fields.
+pub fn signature(name: &str, params: &[Parameter<Type>], ctx: &Context) -> String {
+    format!("{name}({})", params.iter().map(|param| signature_type(&param.ty, ctx)).join(","))
+}
+
+/// The selector of a signature, the first four bytes of its Keccak-256 hash
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// The first topic of the logs of an event, unless it is anonymous: the
+/// Keccak-256 hash of its signature
+pub fn topic(signature: &str) -> [u8; 32] {
+    keccak256(signature.as_bytes())
+}
+
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+fn signature_type(ty: &Type, ctx: &Context) -> String {
+    match ty {
+        Type::Struct(struct_ty) => format!(
+            "({})",
+            struct_ty.definition(ctx).fields.iter().map(|f| signature_type(&f.ty, ctx)).join(",")
+        ),
+        Type::Array(elem, dims) => format!("{}{}", signature_type(elem, ctx), array_dims(dims)),
+        Type::Ref(ty) | Type::StorageRef(_, ty) => signature_type(ty, ctx),
+        _ => abi_type(ty, ctx),
+    }
+}
+
+/// The type as written in Solidity source, which tools use to reconstruct the
+/// original declaration
+fn internal_type(ty: &Type, ctx: &Context) -> String {
+    match ty {
+        Type::Array(elem, dims) => format!("{}{}", internal_type(elem, ctx), array_dims(dims)),
+        Type::UserType(no) => ctx.user_types[*no].to_string(),
+        Type::ExternalFunction { .. } => "function".into(),
+        Type::Ref(ty) | Type::StorageRef(_, ty) => internal_type(ty, ctx),
+        _ => ty.to_string(ctx),
+    }
+}
+
+/// The bytes in lower case hex digits, without prefix
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn array_dims(dims: &[ArrayLength]) -> String {
+    dims.iter()
+        .map(|dim| match dim {
+            ArrayLength::Fixed(len) => format!("[{len}]"),
+            _ => "[]".to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{
+        ast::{StructDecl, StructType, UserTypeDecl},
+        layout::tests::contract,
+    };
+
+    fn field(ty: Type) -> Parameter<Type> {
+        Parameter::new_default(ty)
+    }
+
+    fn declare_struct(ctx: &mut Context, name: &str, fields: Vec<Type>) -> Type {
+        ctx.structs.push(StructDecl {
+            tags: Vec::new(),
+            id: pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+            loc: pt::Loc::Builtin,
+            contract: None,
+            fields: fields.into_iter().map(field).collect(),
+            offsets: Vec::new(),
+            storage_offsets: Vec::new(),
+        });
+        Type::Struct(StructType::UserDefined(ctx.structs.len() - 1))
+    }
+
+    /// Selectors as listed by `solc --hashes`
+    #[test]
+    fn test_selectors() {
+        let vectors = [
+            ("totalSupply()", "18160ddd"),
+            ("balanceOf(address)", "70a08231"),
+            ("transfer(address,uint256)", "a9059cbb"),
+            ("allowance(address,address)", "dd62ed3e"),
+            ("approve(address,uint256)", "095ea7b3"),
+            ("transferFrom(address,address,uint256)", "23b872dd"),
+            ("supportsInterface(bytes4)", "01ffc9a7"),
+            ("safeTransferFrom(address,address,uint256,bytes)", "b88d4fde"),
+            ("onERC721Received(address,address,uint256,bytes)", "150b7a02"),
+            ("multicall(bytes[])", "ac9650d8"),
+            ("multicall(uint256,bytes[])", "5ae401dc"),
+            ("Error(string)", "08c379a0"),
+            ("Panic(uint256)", "4e487b71"),
+        ];
+        for (signature, expected) in vectors {
+            assert_eq!(hex(&selector(signature)), expected, "{signature}");
+        }
+
+        // The interface id of ERC-20 is the exclusive or of its selectors
+        let id = vectors[..6]
+            .iter()
+            .map(|(signature, _)| u32::from_be_bytes(selector(signature)))
+            .fold(0, |id, selector| id ^ selector);
+        assert_eq!(id, 0x36372b07);
+    }
+
+    #[test]
+    fn test_topics() {
+        let vectors = [
+            (
+                "Transfer(address,address,uint256)",
+                "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+            ),
+            (
+                "Approval(address,address,uint256)",
+                "8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925",
+            ),
+            (
+                "OwnershipTransferred(address,address)",
+                "8be0079c531659141344cd1fd0a4f28419497f9722a3daafe3b4186f6b6457e0",
+            ),
+            (
+                "ApprovalForAll(address,address,bool)",
+                "17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31",
+            ),
+        ];
+        for (signature, expected) in vectors {
+            assert_eq!(hex(&topic(signature)), expected, "{signature}");
+        }
+    }
+
+    /// Structs are expanded to the tuple of their fields and user types to
+    /// their underlying type, before hashing
+    #[test]
+    fn test_signature_types() {
+        let mut ctx = Context::new();
+        ctx.user_types.push(UserTypeDecl {
+            tags: Vec::new(),
+            loc: pt::Loc::Builtin,
+            name: "Price".into(),
+            ty: Type::Uint(160),
+            contract: None,
+        });
+
+        // `ISwapRouter.ExactInputSingleParams` of Uniswap v3
+        let params = declare_struct(
+            &mut ctx,
+            "ExactInputSingleParams",
+            vec![
+                Type::Address(false),
+                Type::Address(false),
+                Type::Uint(24),
+                Type::Address(true),
+                Type::Uint(256),
+                Type::Uint(256),
+                Type::Uint(256),
+                Type::UserType(0),
+            ],
+        );
+        let params = [field(Type::Ref(Box::new(params)))];
+        let signature = signature("exactInputSingle", &params, &ctx);
+        assert_eq!(
+            signature,
+            "exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))"
+        );
+        assert_eq!(hex(&selector(&signature)), "414bf389");
+
+        // Arrays of structs holding nested structs and arrays
+        let inner = declare_struct(&mut ctx, "Inner", vec![Type::String, Type::Contract(0)]);
+        let outer = declare_struct(
+            &mut ctx,
+            "Outer",
+            vec![Type::Array(Box::new(inner), vec![ArrayLength::Fixed(2u8.into())]), Type::Enum(0)],
+        );
+        let params = [
+            field(Type::Array(Box::new(outer.clone()), vec![ArrayLength::Dynamic])),
+            field(Type::StorageRef(false, Box::new(outer))),
+        ];
+        assert_eq!(
+            self::signature("f", &params, &ctx),
+            "f(((string,address)[2],uint8)[],((string,address)[2],uint8))"
+        );
+    }
+
+    fn interface(ctx: &mut Context, name: &str, bases: &[usize], functions: &[&str]) -> usize {
+        let mut interface = contract(name, bases, Vec::new());
+        interface.ty = pt::ContractTy::Interface(pt::Loc::Builtin);
+        ctx.contracts.push(interface);
+        let contract_no = ctx.contracts.len() - 1;
+
+        for signature in functions {
+            let (name, params) = signature.trim_end_matches(')').split_once('(').unwrap();
+            let params = params
+                .split(',')
+                .filter(|param| !param.is_empty())
+                .map(|param| match param {
+                    "address" => field(Type::Address(false)),
+                    "bytes4" => field(Type::Bytes(4)),
+                    _ => field(Type::Uint(256)),
+                })
+                .collect();
+            let func = Function::new(
+                pt::Loc::Builtin,
+                pt::Loc::Builtin,
+                pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+                Some(contract_no),
+                Vec::new(),
+                pt::FunctionTy::Function,
+                None,
+                pt::Visibility::External(None),
+                params,
+                Vec::new(),
+                ctx,
+            );
+            ctx.functions.push(func);
+            ctx.contracts[contract_no].functions.push(ctx.functions.len() - 1);
+        }
+
+        contract_no
+    }
+
+    /// Interface ids as given by `type(I).interfaceId` in solc
+    #[test]
+    fn test_interface_ids() {
+        let mut ctx = Context::new();
+        let erc165 = interface(&mut ctx, "IERC165", &[], &["supportsInterface(bytes4)"]);
+        let erc20 = interface(
+            &mut ctx,
+            "IERC20",
+            &[],
+            &[
+                "totalSupply()",
+                "balanceOf(address)",
+                "transfer(address,uint256)",
+                "allowance(address,address)",
+                "approve(address,uint256)",
+                "transferFrom(address,address,uint256)",
+            ],
+        );
+        // Inherited functions do not contribute
+        let burnable = interface(&mut ctx, "IBurnable", &[erc20], &["burn(uint256)"]);
+        ctx.contracts.push(contract("Token", &[erc165, burnable], Vec::new()));
+
+        assert_eq!(hex(&interface_id(erc165, &ctx)), "01ffc9a7");
+        assert_eq!(hex(&interface_id(erc20, &ctx)), "36372b07");
+        assert_eq!(interface_id(burnable, &ctx), selector("burn(uint256)"));
+
+        assert_eq!(
+            gen_interface_ids(3, &ctx).to_string(),
+            Json::object([
+                ("IERC165", "0x01ffc9a7".into()),
+                ("IERC20", "0x36372b07".into()),
+                ("IBurnable", "0x42966c68".into()),
+            ])
+            .to_string()
+        );
+    }
+}
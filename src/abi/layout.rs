@@ -0,0 +1,67 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The storage layout of a contract, like the `storageLayout` output of solc,
+//! with the storage class of each variable.
+
+use crate::{helpers::json::Json, semantic::context::Context};
+
+/// Generate the JSON storage layout of a contract
+pub fn gen_storage_layout(contract_no: usize, ctx: &Context) -> Json {
+    let storage = ctx.contracts[contract_no].layout.iter().map(|layout| {
+        let contract = &ctx.contracts[layout.contract_no];
+        Json::object([
+            ("contract", contract.id.name.as_str().into()),
+            ("label", contract.variables[layout.var_no].name.as_str().into()),
+            ("class", layout.storage_class.to_string().into()),
+            ("slot", layout.slot.to_string().into()),
+            ("offset", Json::Number(layout.offset.to_string())),
+            ("type", layout.ty.to_string(ctx).into()),
+        ])
+    });
+
+    Json::object([("storage", Json::Array(storage.collect()))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::ast as pt,
+        semantic::{
+            ast::Type,
+            layout::{
+                layout,
+                tests::{contract, variable},
+            },
+        },
+    };
+
+    #[test]
+    fn test_storage_layout() {
+        let mut ctx = Context::new();
+        let temporary = Some(pt::StorageType::Temporary(None));
+        ctx.contracts.push(contract(
+            "Counter",
+            &[],
+            vec![variable("count", Type::Uint(64), None), variable("lock", Type::Bool, temporary)],
+        ));
+        layout(0, &mut ctx);
+
+        assert_eq!(
+            gen_storage_layout(0, &ctx).to_string(),
+            r#"{"storage":[{"contract":"Counter","label":"count","class":"persistent","slot":"0","offset":0,"type":"uint64"},{"contract":"Counter","label":"lock","class":"temporary","slot":"0","offset":0,"type":"bool"}]}"#
+        );
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use clap::Args;
+
+use hmt_frontend_solidity::{
+    abi::{
+        ethereum::{gen_abi, gen_interface_ids},
+        layout::gen_storage_layout,
+    },
+    helpers::json::Json,
+};
+
+use super::InputArgs;
+
+/// Print the JSON ABI of the contracts in source files
+#[derive(Debug, Args)]
+pub struct AbiArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Only print the ABI of this contract
+    #[arg(long)]
+    pub contract: Option<String>,
+
+    /// Print the storage layout of the contracts instead of their ABI
+    #[arg(long)]
+    pub storage_layout: bool,
+
+    /// Print the ERC-165 interface ids of the interfaces the contracts
+    /// implement instead of their ABI
+    #[arg(long, conflicts_with = "storage_layout")]
+    pub interface_ids: bool,
+
+    /// Write the ABI to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+pub fn run(args: &AbiArgs) -> Result<()> {
+    let (config, mut resolver, files) = args.input.load()?;
+
+    let generate = if args.storage_layout {
+        gen_storage_layout
+    } else if args.interface_ids {
+        gen_interface_ids
+    } else {
+        gen_abi
+    };
+
+    let mut abis = Vec::new();
+    for file in &files {
+        let ctx = super::analyze(file, &mut resolver, &config.features)?;
+
+        // Only the contracts of the input file, not of its imports
+        let contracts = ctx.contracts.iter().enumerate().filter(|(_, contract)| {
+            contract.loc.no() == Some(0) &&
+                !contract.is_library() &&
+                args.contract.as_ref().is_none_or(|name| contract.id.name == *name)
+        });
+        abis.extend(contracts.map(|(contract_no, contract)| {
+            (contract.id.name.clone(), generate(contract_no, &ctx))
+        }));
+    }
+
+    let abi = match &args.contract {
+        Some(name) => match abis.into_iter().next() {
+            Some((_, abi)) => abi,
+            None => {
+                let paths = files.iter().map(|file| file.path.display().to_string());
+                bail!("Contract '{name}' not found in {}", paths.collect::<Vec<_>>().join(", "))
+            }
+        },
+        None => Json::object(abis),
+    };
+
+    match &args.output {
+        Some(path) => fs::write(path, format!("{abi:#}\n"))
+            .context(format!("Failed to write output file: {}", path.display()))?,
+        None => println!("{abi:#}"),
+    }
+
+    Ok(())
+}
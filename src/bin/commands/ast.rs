@@ -0,0 +1,36 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use clap::Args;
+
+use super::InputArgs;
+
+/// Print the parse trees of source files
+#[derive(Debug, Args)]
+pub struct AstArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+}
+
+pub fn run(args: &AstArgs) -> Result<()> {
+    let (_config, _resolver, files) = args.input.load()?;
+
+    for file in &files {
+        let ast = super::parse(file)?;
+        println!("{ast:#?}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use clap::Args;
+use hmt_frontend_solidity::{
+    semantic::pass::{Pass, PassManager},
+    stats::Stats,
+};
+
+use super::InputArgs;
+
+/// Check source files for errors, without generating code
+#[derive(Debug, Args)]
+pub struct CheckArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Print the number of tokens, parse tree nodes, declarations and symbols
+    /// of the input and its imports, and the peak memory use
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Stop the semantic analysis after the given pass, e.g. `types`
+    #[arg(long, value_name = "PASS")]
+    pub stop_after: Option<Pass>,
+
+    /// Print how long each pass of the semantic analysis took
+    #[arg(long)]
+    pub time_passes: bool,
+}
+
+pub fn run(args: &CheckArgs) -> Result<()> {
+    let (config, mut resolver, files) = args.input.load()?;
+
+    for file in &files {
+        let mut tree = super::parse(file)?;
+        super::select_features(file, &mut tree, &config)?;
+        super::lint(file, &tree, &config)?;
+        let passes = args.stop_after.map_or_else(PassManager::all, PassManager::until);
+        let ctx = super::analyze_passes(file, &mut resolver, passes, &config.features)?;
+
+        if args.time_passes {
+            for (pass, elapsed) in ctx.passes.totals() {
+                println!("{:<12} {elapsed:.2?}", pass.name());
+            }
+        }
+
+        if args.stats {
+            let sources = ctx
+                .files
+                .iter()
+                .filter_map(|file| resolver.get_contents_of_no(file.cache_no?))
+                .collect::<Vec<_>>();
+            println!("{}", Stats::collect(&ctx, sources.iter().map(AsRef::as_ref)));
+        }
+    }
+
+    Ok(())
+}
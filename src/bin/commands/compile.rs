@@ -0,0 +1,391 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use clap::Args;
+
+use hmt_frontend_solidity::{
+    codegen::{
+        archive::write_archive, gen_contracts, listing::Listing, metadata,
+        unroll::DEFAULT_UNROLL_BUDGET, Codegen, CodegenError, CodegenOptions,
+    },
+    config::Artifact,
+    deps::{self, Dependency},
+    diagnostics::Diagnostics,
+    helpers::json::Json,
+    parser::ast::SourceUnit,
+    resolver::{FileResolver, ResolvedFile},
+    runtime,
+    semantic::{
+        context::Context,
+        dump,
+        pass::{Pass, PassManager},
+    },
+};
+
+#[cfg(unix)]
+use hmt_frontend_solidity::{
+    codegen::mangle::Symbol,
+    jit::{self, Machine, Outcome},
+};
+
+use super::InputArgs;
+
+/// Compile source files to Cranelift IR
+#[derive(Debug, Args)]
+pub struct CompileArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Path to the output file
+    #[arg(long, required_unless_present_any = ["interpret", "emit", "out_dir", "archive"])]
+    pub output: Option<PathBuf>,
+
+    /// Write one object file per contract into this directory
+    #[arg(long, conflicts_with = "archive")]
+    pub out_dir: Option<PathBuf>,
+
+    /// Write the object files of the contracts into this static archive
+    #[arg(long)]
+    pub archive: Option<PathBuf>,
+
+    /// Artifact to produce instead of the IR, may be repeated: `ir`, `object`,
+    /// `clif`, `ast`, `ast-json`, `sema-json`, `imports`, `asm`, `deps`,
+    /// `make-deps` or `metadata`
+    #[arg(long)]
+    pub emit: Vec<Artifact>,
+
+    /// Run a call of a free function in the generated code after compiling, as
+    /// in `--interpret 'f(1, 2)'`, and print the values it returns
+    #[arg(long, value_name = "CALL")]
+    pub interpret: Option<String>,
+
+    /// Generate code for all the functions, even those no entry point reaches
+    #[arg(long)]
+    pub no_dce: bool,
+
+    /// Append the code hash of each contract to its object file, as the data
+    /// symbol `<contract>::code_hash`
+    #[arg(long)]
+    pub embed_code_hash: bool,
+
+    /// Also print AST to console
+    #[arg(long)]
+    pub print_ast: bool,
+}
+
+pub fn run(args: &CompileArgs) -> Result<()> {
+    let (mut config, mut resolver, files) = args.input.load()?;
+    for artifact in &args.emit {
+        if !config.artifacts.contains(artifact) {
+            config.artifacts.push(*artifact);
+        }
+    }
+    if args.print_ast {
+        config.artifacts.extend([Artifact::Ir, Artifact::Ast]);
+    }
+
+    if config.emits(Artifact::Ir) && config.emits(Artifact::Object) {
+        bail!("The IR and the object file cannot both be written to the output file");
+    }
+    let writes_output = config.emits(Artifact::Ir) || config.emits(Artifact::Object);
+    if files.len() > 1 && args.interpret.is_some() {
+        bail!("A call can only be interpreted for a single input");
+    }
+    if files.len() > 1 && writes_output && args.output.is_some() {
+        bail!("The output file can only be written for a single input, use --out-dir or --archive for several");
+    }
+
+    let options = CodegenOptions {
+        target: match &config.target {
+            Some(target) => {
+                target.parse().map_err(|e| anyhow!("Invalid target '{target}': {e}"))?
+            }
+            None => CodegenOptions::default().target,
+        },
+        opt_level: config.opt_level.unwrap_or_default(),
+        unroll_budget: config.unroll_budget.unwrap_or(DEFAULT_UNROLL_BUDGET),
+        dce: !args.no_dce,
+        disasm: config.emits(Artifact::Asm),
+        code_size_limit: config.code_size_limit,
+        embed_code_hash: args.embed_code_hash,
+    };
+
+    let emits_metadata = config.emits(Artifact::Metadata);
+    let emits_objects =
+        args.out_dir.is_some() || args.archive.is_some() || options.disasm || emits_metadata;
+    let mut listing = Listing::default();
+    let mut metadata = Vec::new();
+    let mut objects = Vec::new();
+
+    for file in &files {
+        let mut ast = super::parse(file)?;
+        super::select_features(file, &mut ast, &config)?;
+        super::lint(file, &ast, &config)?;
+
+        // Generate the AST representation if requested
+        if config.emits(Artifact::Ast) {
+            println!("{ast:#?}");
+        }
+        if config.emits(Artifact::AstJson) {
+            println!("{:#}", ast_json(&ast)?);
+        }
+
+        // The resolved declarations, for tooling which needs their types
+        let mut ctx = super::analyze(file, &mut resolver, &config.features)?;
+        if config.emits(Artifact::SemaJson) {
+            println!("{:#}", dump::to_json(&ctx));
+        }
+
+        // Generate the intermediate representation (IR) from the resolved
+        // functions and write it to the output file specified in the arguments
+        let mut generator = Codegen::with_options(&options)?;
+        codegen(generator.gen(&ctx), &ctx, &resolver, file)?;
+
+        if config.emits(Artifact::Ir) {
+            match &args.output {
+                Some(output) => generator.write(output),
+                None if args.interpret.is_none() &&
+                    args.out_dir.is_none() &&
+                    args.archive.is_none() =>
+                {
+                    bail!("An output file is needed to write the IR")
+                }
+                None => (),
+            }
+        }
+        if config.emits(Artifact::Clif) {
+            print!("{}", generator.ir());
+        }
+        if config.emits(Artifact::Object) {
+            let Some(output) = &args.output else {
+                bail!("An output file is needed to write the object file");
+            };
+            write(output, &generator.finish()?)?;
+        }
+
+        if emits_objects {
+            objects.extend(contract_objects(
+                &ctx,
+                file,
+                &resolver,
+                &options,
+                &mut listing,
+                &mut metadata,
+            )?);
+        }
+
+        if let Some(call) = &args.interpret {
+            interpret(call, &mut ctx, &options)
+                .with_context(|| format!("Failed to interpret `{call}`"))?;
+        }
+    }
+
+    if let Some(dir) = &args.out_dir {
+        fs::create_dir_all(dir)
+            .context(format!("Failed to create output directory: {}", dir.display()))?;
+        for (name, object) in &objects {
+            write(&dir.join(name), object)?;
+        }
+    }
+
+    if let Some(path) = &args.archive {
+        let mut archive = Vec::new();
+        write_archive(&mut archive, &objects)?;
+        write(path, &archive)?;
+    }
+
+    // The machine code of the contracts, for checking what they lower to
+    if options.disasm {
+        print!("{listing}");
+    }
+
+    // The code hashes of the contracts, for tooling to check the artifacts
+    if emits_metadata {
+        println!("{:#}", metadata::to_json(&metadata));
+    }
+
+    // The host functions the generated code may call, for runtime implementers
+    if config.emits(Artifact::Imports) {
+        println!("{:#}", runtime::manifest());
+    }
+
+    // The files read, for build systems and caches to know when to rebuild
+    if config.emits(Artifact::Deps) || config.emits(Artifact::MakeDeps) {
+        let deps = dependencies(args, &files, &mut resolver, &config.features)?;
+
+        if config.emits(Artifact::Deps) {
+            println!("{:#}", deps::to_json(&deps));
+        }
+        if config.emits(Artifact::MakeDeps) {
+            let targets = [&args.output, &args.archive, &args.out_dir]
+                .into_iter()
+                .flatten()
+                .map(PathBuf::as_path)
+                .collect::<Vec<_>>();
+            if targets.is_empty() {
+                bail!("An output file is needed as the target of the dependency rules");
+            }
+            print!("{}", deps::to_makefile(&targets, &deps));
+        }
+    }
+
+    Ok(())
+}
+
+/// The parse tree as JSON, which is serialized with serde
+#[cfg(feature = "serde")]
+fn ast_json(ast: &SourceUnit) -> Result<Json> {
+    Ok(hmt_frontend_solidity::helpers::json::ser::to_json(ast)?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn ast_json(_ast: &SourceUnit) -> Result<Json> {
+    bail!("The parse tree can only be printed as JSON with the `serde` feature")
+}
+
+/// The object files of the contracts of an input file, each after the
+/// contracts it creates, see [`gen_contracts`], adding their metadata
+fn contract_objects(
+    ctx: &Context,
+    file: &ResolvedFile,
+    resolver: &FileResolver,
+    options: &CodegenOptions,
+    listing: &mut Listing,
+    metadata: &mut Vec<metadata::Metadata>,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let contracts = ctx
+        .contracts
+        .iter()
+        .enumerate()
+        .filter(|(_, contract)| {
+            contract.loc.no() == Some(0) && (contract.instantiable || contract.is_library())
+        })
+        .map(|(no, _)| no)
+        .collect::<Vec<_>>();
+
+    let mut diagnostics = Diagnostics::default();
+    let order = gen_contracts(&contracts, ctx, options, listing, &mut diagnostics);
+    super::report(diagnostics.iter(), ctx, resolver, file)?;
+    let order = codegen(order, ctx, resolver, file)?;
+
+    let objects = order
+        .iter()
+        .map(|&no| {
+            let contract = &ctx.contracts[no];
+            (format!("{}.o", contract.id.name), contract.code.get().cloned().unwrap_or_default())
+        })
+        .collect();
+    metadata.extend(order.iter().filter_map(|&no| ctx.contracts[no].metadata.get().cloned()));
+
+    Ok(objects)
+}
+
+/// The inputs and the files they import, directly or not. An input read as a
+/// solc AST is the JSON file, not the source printed from it.
+fn dependencies(
+    args: &CompileArgs,
+    files: &[ResolvedFile],
+    resolver: &mut FileResolver,
+    features: &[String],
+) -> Result<Vec<Dependency>> {
+    // Resolving the imports loads them into the resolver
+    for file in files {
+        super::analyze_passes(file, resolver, PassManager::only(&[Pass::Imports]), features)?;
+    }
+    let mut deps = deps::dependencies(resolver);
+
+    if args.input.solc_ast {
+        let inputs = args.input.paths().filter(|path| path.as_os_str() != "-");
+        for (i, path) in inputs.enumerate() {
+            let json =
+                fs::read(path).context(format!("Failed to read input file: {}", path.display()))?;
+            // The printed source is registered under the path as given
+            deps.retain(|dep| dep.path != path.with_extension("sol"));
+            let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            deps.insert(i, Dependency::new(path, &json));
+        }
+    }
+
+    Ok(deps)
+}
+
+/// The result of the code generation, failing with the errors of the
+/// functions which cannot be generated written to stderr
+fn codegen<T>(
+    result: Result<T, CodegenError>,
+    ctx: &Context,
+    resolver: &FileResolver,
+    file: &ResolvedFile,
+) -> Result<T> {
+    match result {
+        Err(CodegenError::Unsupported(diagnostics)) => {
+            super::report(diagnostics.iter(), ctx, resolver, file)?;
+            let errors = diagnostics.errors().len();
+            bail!("Code generation of {} failed with {errors} errors", file.path.display())
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Run a call of a free function of the main file in the code generated for
+/// the host, printing the values it returns with their types. The host
+/// functions are those of the [`jit`] machine, which stands in for a runtime.
+#[cfg(unix)]
+fn interpret(call: &str, ctx: &mut Context, options: &CodegenOptions) -> Result<()> {
+    let (function_no, args) = jit::resolve_call(call, ctx)?;
+
+    // All the functions, for the host
+    let options = CodegenOptions {
+        target: CodegenOptions::default().target,
+        dce: false,
+        disasm: false,
+        embed_code_hash: false,
+        ..options.clone()
+    };
+    let mut generator = Codegen::with_options(&options)?;
+    generator.gen(ctx)?;
+    let mut machine = Machine::load(&generator.finish()?)?;
+
+    let func = &ctx.functions[function_no];
+    let symbol = Symbol::function(func, ctx);
+    match machine.call_function(&symbol, &args, func.returns.len())? {
+        Outcome::Returned(data) => {
+            for (word, param) in data.chunks_exact(32).zip(func.returns.iter()) {
+                let value = jit::format_value(word.try_into()?, &param.ty);
+                println!("{value}: {}", param.ty.to_string(ctx));
+            }
+            Ok(())
+        }
+        Outcome::Reverted(data) => {
+            let data = data.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+            bail!("The call reverted with 0x{data}")
+        }
+        Outcome::Trapped(signal) => bail!("The call trapped with signal {signal}"),
+    }
+}
+
+#[cfg(not(unix))]
+fn interpret(_call: &str, _ctx: &mut Context, _options: &CodegenOptions) -> Result<()> {
+    bail!("Running the generated code is only supported on Unix")
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<()> {
+    fs::write(path, contents).context(format!("Failed to write output file: {}", path.display()))
+}
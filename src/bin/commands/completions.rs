@@ -0,0 +1,210 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shell completion scripts generated from the command line definition.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use clap::{Arg, Args, Command, CommandFactory, ValueEnum};
+
+use crate::Cli;
+
+/// Print a shell completion script
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// The shell to generate the script for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+pub fn run(args: &CompletionsArgs) -> Result<()> {
+    let cmd = Cli::command();
+
+    let script = match args.shell {
+        Shell::Bash => bash(&cmd),
+        Shell::Zsh => zsh(&cmd),
+        Shell::Fish => fish(&cmd),
+    }?;
+
+    print!("{script}");
+
+    Ok(())
+}
+
+/// The options of a command, except the positional arguments
+fn options(cmd: &Command) -> impl Iterator<Item = &Arg> {
+    cmd.get_arguments().filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+}
+
+fn flags(arg: &Arg) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(short) = arg.get_short() {
+        flags.push(format!("-{short}"));
+    }
+    if let Some(long) = arg.get_long() {
+        flags.push(format!("--{long}"));
+    }
+    flags
+}
+
+fn help(arg: &Arg) -> String {
+    arg.get_help().map(|help| help.to_string()).unwrap_or_default()
+}
+
+fn possible_values(arg: &Arg) -> Vec<String> {
+    arg.get_possible_values().iter().map(|value| value.get_name().to_string()).collect()
+}
+
+fn function_name(cmd: &Command) -> String {
+    format!("_{}", cmd.get_name().replace('-', "_"))
+}
+
+fn bash(cmd: &Command) -> Result<String> {
+    let name = cmd.get_name();
+    let mut script = String::new();
+
+    writeln!(script, "{}() {{", function_name(cmd))?;
+    writeln!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(script, "    local opts")?;
+    writeln!(script, "    case \"${{COMP_WORDS[1]}}\" in")?;
+
+    for sub in cmd.get_subcommands() {
+        let mut words: Vec<String> = options(sub).flat_map(flags).collect();
+        words.extend(
+            sub.get_arguments().filter(|arg| arg.is_positional()).flat_map(possible_values),
+        );
+        writeln!(script, "        {}) opts=\"{}\" ;;", sub.get_name(), words.join(" "))?;
+    }
+
+    let mut words: Vec<String> = cmd.get_subcommands().map(|sub| sub.get_name().into()).collect();
+    words.extend(options(cmd).flat_map(flags));
+    writeln!(script, "        *) opts=\"{}\" ;;", words.join(" "))?;
+
+    writeln!(script, "    esac")?;
+    writeln!(script, "    COMPREPLY=($(compgen -W \"$opts\" -- \"$cur\"))")?;
+    writeln!(script, "}}")?;
+    writeln!(script, "complete -o default -F {} {name}", function_name(cmd))?;
+
+    Ok(script)
+}
+
+fn zsh(cmd: &Command) -> Result<String> {
+    let name = cmd.get_name();
+    let escape = |s: String| s.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]");
+    let mut script = String::new();
+
+    writeln!(script, "#compdef {name}")?;
+    writeln!(script)?;
+    writeln!(script, "{}() {{", function_name(cmd))?;
+    writeln!(script, "    local -a commands")?;
+    writeln!(script, "    commands=(")?;
+    for sub in cmd.get_subcommands() {
+        let about = sub.get_about().map(|about| about.to_string()).unwrap_or_default();
+        writeln!(script, "        '{}:{}'", sub.get_name(), escape(about).replace(':', "\\:"))?;
+    }
+    writeln!(script, "    )")?;
+    writeln!(script)?;
+    writeln!(script, "    if (( CURRENT == 2 )); then")?;
+    writeln!(script, "        _describe 'command' commands")?;
+    writeln!(script, "        return")?;
+    writeln!(script, "    fi")?;
+    writeln!(script)?;
+    writeln!(script, "    case $words[2] in")?;
+
+    for sub in cmd.get_subcommands() {
+        writeln!(script, "        {})", sub.get_name())?;
+        writeln!(script, "            _arguments \\")?;
+
+        for arg in options(sub) {
+            let value = if arg.get_action().takes_values() { ":value:_files" } else { "" };
+            for flag in flags(arg) {
+                writeln!(script, "                '{flag}[{}]{value}' \\", escape(help(arg)))?;
+            }
+        }
+
+        for arg in sub.get_arguments().filter(|arg| arg.is_positional()) {
+            let values = possible_values(arg);
+            if values.is_empty() {
+                writeln!(script, "                '*:file:_files' \\")?;
+            } else {
+                writeln!(script, "                ':{}:({})' \\", arg.get_id(), values.join(" "))?;
+            }
+        }
+
+        writeln!(script, "                && return")?;
+        writeln!(script, "            ;;")?;
+    }
+
+    writeln!(script, "    esac")?;
+    writeln!(script, "}}")?;
+    writeln!(script)?;
+    writeln!(script, "{} \"$@\"", function_name(cmd))?;
+
+    Ok(script)
+}
+
+fn fish(cmd: &Command) -> Result<String> {
+    let name = cmd.get_name();
+    let escape = |s: String| s.replace('\\', "\\\\").replace('\'', "\\'");
+    let mut script = String::new();
+
+    for sub in cmd.get_subcommands() {
+        let about = sub.get_about().map(|about| about.to_string()).unwrap_or_default();
+        writeln!(
+            script,
+            "complete -c {name} -n '__fish_use_subcommand' -f -a {} -d '{}'",
+            sub.get_name(),
+            escape(about)
+        )?;
+    }
+
+    for sub in cmd.get_subcommands() {
+        let condition = format!("__fish_seen_subcommand_from {}", sub.get_name());
+
+        for arg in options(sub) {
+            let mut line = format!("complete -c {name} -n '{condition}'");
+            if let Some(short) = arg.get_short() {
+                write!(line, " -s {short}")?;
+            }
+            if let Some(long) = arg.get_long() {
+                write!(line, " -l {long}")?;
+            }
+            if arg.get_action().takes_values() {
+                line.push_str(" -r");
+            }
+            writeln!(script, "{line} -d '{}'", escape(help(arg)))?;
+        }
+
+        for arg in sub.get_arguments().filter(|arg| arg.is_positional()) {
+            let values = possible_values(arg);
+            if !values.is_empty() {
+                writeln!(
+                    script,
+                    "complete -c {name} -n '{condition}' -f -a '{}'",
+                    values.join(" ")
+                )?;
+            }
+        }
+    }
+
+    Ok(script)
+}
@@ -0,0 +1,96 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use clap::Args;
+
+use hmt_frontend_solidity::{
+    config::{Config, FmtOptions},
+    formatter::{self, BraceStyle, Style},
+    lexer,
+    parser::trivia::Comments,
+    resolver::FileResolver,
+};
+
+/// Reprint source files in the canonical style
+#[derive(Debug, Args)]
+pub struct FmtArgs {
+    /// Files to format, or `-` to read from stdin and write to stdout
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Do not write the files, fail if any of them is not formatted
+    #[arg(long)]
+    pub check: bool,
+
+    /// Path to the config file, `hmt-solidity.toml` is searched for when not set
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Number of spaces per indentation level
+    #[arg(long)]
+    pub indent_width: Option<usize>,
+
+    /// Length after which lines are broken
+    #[arg(long)]
+    pub line_length: Option<usize>,
+
+    /// Placement of the opening brace of blocks
+    #[arg(long)]
+    pub brace_style: Option<BraceStyle>,
+}
+
+pub fn run(args: &FmtArgs) -> Result<()> {
+    let mut config = super::load_config(args.config.as_deref())?;
+    config.merge(Config {
+        fmt: FmtOptions {
+            indent_width: args.indent_width,
+            line_length: args.line_length,
+            brace_style: args.brace_style,
+        },
+        ..Default::default()
+    });
+
+    let style = Style::from(&config.fmt);
+    let mut resolver = FileResolver::default();
+    let mut unformatted = Vec::new();
+
+    for path in &args.files {
+        let file = super::load_input(path, &mut resolver)?;
+        let ast = super::parse(&file)?;
+        let comments = Comments::attach(&ast, lexer::comments(&file.contents, 0), &file.contents);
+        let formatted = formatter::format_tree(&ast, &comments, &style);
+
+        let changed = *file.contents != formatted;
+
+        if args.check {
+            if changed {
+                unformatted.push(path.display().to_string());
+            }
+        } else if path.as_os_str() == "-" {
+            print!("{formatted}");
+        } else if changed {
+            fs::write(path, formatted)
+                .context(format!("Failed to write file: {}", path.display()))?;
+        }
+    }
+
+    if !unformatted.is_empty() {
+        bail!("The following files are not formatted:\n{}", unformatted.join("\n"));
+    }
+
+    Ok(())
+}
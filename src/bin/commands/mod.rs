@@ -0,0 +1,335 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    env,
+    ffi::OsStr,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use clap::Args;
+
+use hmt_frontend_solidity::{
+    config::{Config, Lints, MAX_OPT_LEVEL},
+    diagnostics::{Diagnostic, Diagnostics, Level},
+    driver,
+    formatter::{self, Style},
+    lint,
+    parser::{self, ast::SourceUnit, from_solc_ast, trivia::Comments},
+    resolver::{FileResolver, ResolvedFile},
+    semantic::{collector, context::Context, pass::PassManager},
+};
+
+pub mod abi;
+pub mod ast;
+pub mod check;
+pub mod compile;
+pub mod completions;
+pub mod fmt;
+pub mod outline;
+pub mod repl;
+
+/// Name of the virtual file used when the source is read from stdin
+const STDIN_FILENAME: &str = "<stdin>";
+
+/// Options shared by the commands which compile a source file
+#[derive(Debug, Args)]
+pub struct InputArgs {
+    /// Path to an input file, or `-` to read from stdin, may be repeated
+    #[arg(long = "input", value_name = "INPUT", required_unless_present = "files")]
+    pub inputs: Vec<PathBuf>,
+
+    /// Input files, after those given with `--input`
+    #[arg(value_name = "FILE")]
+    pub files: Vec<PathBuf>,
+
+    /// Path to the config file, `hmt-solidity.toml` is searched for when not set
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Directory to search for imports, may be repeated
+    #[arg(short = 'I', long = "import-path")]
+    pub import_paths: Vec<PathBuf>,
+
+    /// Import remapping in the form `prefix=path`, may be repeated
+    #[arg(short = 'm', long = "import-map", value_parser = parse_import_map)]
+    pub import_maps: Vec<(String, PathBuf)>,
+
+    /// Target triple to generate code for, defaults to the host
+    #[arg(long)]
+    pub target: Option<String>,
+
+    /// Optimization level
+    #[arg(short = 'O', long, value_parser = clap::value_parser!(u8).range(0..=MAX_OPT_LEVEL as i64))]
+    pub opt_level: Option<u8>,
+
+    /// Most statements a loop may grow to when unrolled at optimization level 2
+    #[arg(long)]
+    pub unroll_budget: Option<usize>,
+
+    /// Warn about the contracts with more bytes of code, the limit of the
+    /// platform when not set
+    #[arg(long)]
+    pub code_size_limit: Option<usize>,
+
+    /// Switch on a lint, may be repeated
+    #[arg(long = "enable-lint")]
+    pub enable_lints: Vec<String>,
+
+    /// Switch off a lint, may be repeated
+    #[arg(long = "disable-lint")]
+    pub disable_lints: Vec<String>,
+
+    /// Switch on a feature, compiling the items marked with `@if(feature)`, may be repeated
+    #[arg(long = "feature")]
+    pub features: Vec<String>,
+
+    /// Read the input as the compact AST JSON of solc, as written by `solc --ast-compact-json`
+    #[arg(long)]
+    pub solc_ast: bool,
+}
+
+impl InputArgs {
+    /// Options given on the command line, which override the config file
+    fn to_config(&self) -> Config {
+        Config {
+            import_paths: self.import_paths.clone(),
+            remappings: self.import_maps.clone(),
+            target: self.target.clone(),
+            opt_level: self.opt_level,
+            unroll_budget: self.unroll_budget,
+            code_size_limit: self.code_size_limit,
+            lints: Lints { enable: self.enable_lints.clone(), disable: self.disable_lints.clone() },
+            artifacts: Vec::new(),
+            features: self.features.clone(),
+            fmt: Default::default(),
+        }
+    }
+
+    /// Load the project config and merge the command line options into it
+    pub fn load_config(&self) -> Result<Config> {
+        let mut config = load_config(self.config.as_deref())?;
+        config.merge(self.to_config());
+
+        Ok(config)
+    }
+
+    /// The paths of the input files, those given with `--input` first
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.inputs.iter().chain(&self.files).map(PathBuf::as_path)
+    }
+
+    /// Load the config and the input files, with the import paths of the
+    /// config set up in the resolver. The files share the resolver, so a file
+    /// imported by several inputs is read once.
+    pub fn load(&self) -> Result<(Config, FileResolver, Vec<ResolvedFile>)> {
+        let config = self.load_config()?;
+
+        if self.paths().filter(|path| path.as_os_str() == "-").count() > 1 {
+            bail!("The input can only be read from stdin once");
+        }
+
+        let mut resolver = driver::resolver(&config);
+        let files = self
+            .paths()
+            .map(|path| match self.solc_ast {
+                true => load_solc_ast(path, &mut resolver),
+                false => load_input(path, &mut resolver),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((config, resolver, files))
+    }
+}
+
+/// Load the config file at the path, or the one found from the working
+/// directory when no path is given
+pub fn load_config(path: Option<&Path>) -> Result<Config> {
+    let path = match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => Config::discover(&env::current_dir()?),
+    };
+
+    match path {
+        Some(path) => {
+            Config::load(&path).context(format!("Failed to load config file: {}", path.display()))
+        }
+        None => Ok(Config::default()),
+    }
+}
+
+fn parse_import_map(s: &str) -> Result<(String, PathBuf), String> {
+    match s.split_once('=') {
+        Some((map, path)) if !map.is_empty() => Ok((map.to_string(), PathBuf::from(path))),
+        _ => Err(format!("expected 'prefix=path', found '{s}'")),
+    }
+}
+
+/// Load the input file into the resolver. The path `-` reads the source from
+/// stdin, which is registered under the virtual filename `<stdin>`.
+pub fn load_input(input: &Path, resolver: &mut FileResolver) -> Result<ResolvedFile> {
+    if input.as_os_str() == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).context("Failed to read input from stdin")?;
+        resolver.set_file_contents(STDIN_FILENAME, source);
+
+        return resolver.resolve(None, OsStr::new(STDIN_FILENAME)).map_err(|e| anyhow!(e));
+    }
+
+    resolver
+        .resolve(None, input.as_os_str())
+        .map_err(|e| anyhow!(e))
+        .context(format!("Failed to read input file: {}", input.display()))
+}
+
+/// Load the solc AST JSON of the input into the resolver. The tree is printed
+/// back to Solidity, registered under the input path with a `.sol` extension,
+/// so that the rest of the pipeline reads it like any other source.
+pub fn load_solc_ast(input: &Path, resolver: &mut FileResolver) -> Result<ResolvedFile> {
+    let (json, path) = if input.as_os_str() == "-" {
+        let mut json = String::new();
+        io::stdin().read_to_string(&mut json).context("Failed to read input from stdin")?;
+        (json, PathBuf::from(STDIN_FILENAME))
+    } else {
+        let json = fs::read_to_string(input)
+            .context(format!("Failed to read input file: {}", input.display()))?;
+        (json, input.with_extension("sol"))
+    };
+
+    let tree = from_solc_ast::import(&json, 0).map_err(|diagnostics| {
+        let messages = diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+        anyhow!(
+            "Importing the solc AST of {} failed with {} errors:\n{}",
+            input.display(),
+            messages.len(),
+            messages.join("\n")
+        )
+    })?;
+
+    let source = formatter::format_tree(&tree, &Comments::default(), &Style::default());
+    let name = path.to_string_lossy();
+    resolver.set_file_contents(&name, source);
+
+    resolver.resolve(None, OsStr::new(name.as_ref())).map_err(|e| anyhow!(e))
+}
+
+/// Parse the Solidity source code into an abstract syntax tree (AST).
+/// If parsing fails, collect and format all diagnostics into error reports.
+pub fn parse(file: &ResolvedFile) -> Result<SourceUnit> {
+    parser::parse(&file.contents, 0).map_err(|diagnostics| {
+        let reports = diagnostics
+            .iter()
+            .map(|diagnostic| render(diagnostic, &file.contents))
+            .collect::<Result<Vec<_>>>();
+
+        match reports {
+            Ok(reports) => anyhow!(
+                "Parsing {} failed with {} errors:\n{}",
+                file.path.display(),
+                reports.len(),
+                reports.join("\n")
+            ),
+            Err(e) => e,
+        }
+    })
+}
+
+/// Remove the items excluded by the features of the config from the tree,
+/// see [`collector::select_features`]. An invalid condition is an error.
+pub fn select_features(file: &ResolvedFile, tree: &mut SourceUnit, config: &Config) -> Result<()> {
+    let mut diagnostics = Diagnostics::default();
+    let features = config.features.iter().cloned().collect();
+    collector::select_features(tree, &features, &mut diagnostics);
+
+    for diagnostic in diagnostics.iter() {
+        eprintln!("{}", render(diagnostic, &file.contents)?);
+    }
+
+    if diagnostics.any_errors() {
+        bail!("Selecting the features of {} failed", file.path.display());
+    }
+
+    Ok(())
+}
+
+/// Run the lints switched on in the config and write their warnings to stderr
+pub fn lint(file: &ResolvedFile, tree: &SourceUnit, config: &Config) -> Result<()> {
+    for diagnostic in lint::run(tree, config) {
+        eprintln!("{}", render(&diagnostic, &file.contents)?);
+    }
+
+    Ok(())
+}
+
+/// Run the semantic analysis of the file and its imports, with the features
+/// switched on. Warnings and errors are written to stderr, and an error is
+/// returned if there are any errors.
+pub fn analyze(
+    file: &ResolvedFile,
+    resolver: &mut FileResolver,
+    features: &[String],
+) -> Result<Context> {
+    analyze_passes(file, resolver, PassManager::all(), features)
+}
+
+/// Run the given passes of the semantic analysis, see [`analyze`]
+pub fn analyze_passes(
+    file: &ResolvedFile,
+    resolver: &mut FileResolver,
+    passes: PassManager,
+    features: &[String],
+) -> Result<Context> {
+    let ctx = driver::analyze_passes(file, resolver, passes, features);
+    report(ctx.diagnostics.with_levels(Level::Warning..), &ctx, resolver, file)?;
+
+    let errors = ctx.diagnostics.errors().len();
+    if errors > 0 {
+        bail!("Analysis of {} failed with {} errors", file.path.display(), errors);
+    }
+
+    Ok(ctx)
+}
+
+/// Write the diagnostics to stderr, each with the source of the file it is
+/// located in, the input file when it is not located in one
+pub fn report<'a>(
+    diagnostics: impl IntoIterator<Item = &'a Diagnostic>,
+    ctx: &Context,
+    resolver: &FileResolver,
+    file: &ResolvedFile,
+) -> Result<()> {
+    for diagnostic in diagnostics {
+        let source = diagnostic
+            .loc
+            .no()
+            .and_then(|no| ctx.files.get(no))
+            .and_then(|file| match file.cache_no {
+                Some(no) => resolver.get_contents_of_no(no),
+                None => resolver.get_builtin(&file.path),
+            })
+            .unwrap_or_else(|| file.contents.clone());
+
+        eprintln!("{}", render(diagnostic, &source)?);
+    }
+
+    Ok(())
+}
+
+/// Render a diagnostic as an ariadne report
+fn render(diagnostic: &Diagnostic, source: &str) -> Result<String> {
+    diagnostic.render(source).map_err(|e| anyhow!("Failed to generate error report: {}", e))
+}
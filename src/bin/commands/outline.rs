@@ -0,0 +1,49 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use clap::Args;
+
+use hmt_frontend_solidity::{
+    outline::{outline, to_json},
+    parser::visitor::Visitable,
+    semantic::{collector::AnnotationCollector, context::Context, file::File},
+};
+
+use super::InputArgs;
+
+/// Print the outlines of source files as JSON
+#[derive(Debug, Args)]
+pub struct OutlineArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+}
+
+pub fn run(args: &OutlineArgs) -> Result<()> {
+    let (_config, _resolver, files) = args.input.load()?;
+
+    for file in &files {
+        let mut tree = super::parse(file)?;
+
+        let mut ctx = Context::new();
+        let mut collector = AnnotationCollector::new(&mut ctx);
+        tree.visit(&mut collector)?;
+        let symbols = outline(&collector.collect());
+
+        let source = File::new(file.full_path.clone(), &file.contents, 0, None);
+        println!("{:#}", to_json(&symbols, &source));
+    }
+
+    Ok(())
+}
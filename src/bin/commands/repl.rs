@@ -0,0 +1,78 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::{bail, Result};
+use clap::Args;
+
+use hmt_frontend_solidity::repl::{ReplError, Session};
+
+/// Evaluate pure expressions and statements interactively
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    /// Input to evaluate before reading stdin, may be repeated. Stdin is not
+    /// read when set.
+    #[arg(short, long)]
+    pub eval: Vec<String>,
+}
+
+pub fn run(args: &ReplArgs) -> Result<()> {
+    let mut session = Session::new();
+
+    if !args.eval.is_empty() {
+        let mut failed = false;
+        for input in &args.eval {
+            failed |= eval(&mut session, input).is_err();
+        }
+        if failed {
+            bail!("evaluation failed");
+        }
+        return Ok(());
+    }
+
+    let interactive = io::stdin().is_terminal();
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        if interactive {
+            print!("> ");
+            io::stdout().flush()?;
+        }
+
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+
+        if !line.trim().is_empty() {
+            // Errors are reported and the session goes on
+            let _ = eval(&mut session, &line);
+        }
+    }
+
+    Ok(())
+}
+
+fn eval(session: &mut Session, input: &str) -> Result<(), ReplError> {
+    match session.eval(input) {
+        Ok(Some(value)) => println!("{value}: {}", value.type_name()),
+        Ok(None) => (),
+        Err(err) => {
+            eprintln!("error: {err}");
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Writes the log records of the compiler, like the timed stages of the
+//! pipeline, to stderr. `RUST_LOG` sets the levels as a comma separated list
+//! of `level` and `target=level`, e.g. `info,hmt_frontend_solidity::semantic=debug`,
+//! and each `--verbose` raises the level of the compiler.
+
+use std::{env, str::FromStr};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// The target of the records of the compiler
+const CRATE: &str = "hmt_frontend_solidity";
+
+struct Logger {
+    /// The level of targets without a directive
+    default: LevelFilter,
+    /// Levels of targets and their submodules
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Logger {
+    fn new(filters: &str, verbose: u8) -> Self {
+        let mut logger = Self { default: LevelFilter::Warn, targets: Vec::new() };
+
+        for directive in filters.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = LevelFilter::from_str(level) {
+                        logger.targets.push((target.to_string(), level));
+                    }
+                }
+                None => match LevelFilter::from_str(directive) {
+                    Ok(level) => logger.default = level,
+                    // A target alone logs everything
+                    Err(_) => logger.targets.push((directive.to_string(), LevelFilter::Trace)),
+                },
+            }
+        }
+
+        let level = match verbose {
+            0 => None,
+            1 => Some(LevelFilter::Debug),
+            _ => Some(LevelFilter::Trace),
+        };
+        if let Some(level) = level {
+            logger.targets.push((CRATE.to_string(), level));
+        }
+
+        logger
+    }
+
+    /// The level of the target, from the directive of the longest target it
+    /// is in, the last one when repeated
+    fn level(&self, target: &str) -> LevelFilter {
+        let within = |prefix: &str| {
+            target
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        };
+
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| within(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{:>5} {}: {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the logger, with the levels of `RUST_LOG` and `--verbose`
+pub fn init(verbose: u8) {
+    let logger = Logger::new(&env::var("RUST_LOG").unwrap_or_default(), verbose);
+
+    log::set_max_level(logger.max_level());
+    // Only fails when a logger is already installed
+    let _ = log::set_boxed_logger(Box::new(logger));
+}
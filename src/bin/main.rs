@@ -12,27 +12,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fs, path::PathBuf, process};
+use std::{env, ffi::OsString, process};
 
-use anyhow::{anyhow, Context, Result};
-use ariadne::{Report, Source};
-use clap::Parser;
+use anyhow::Result;
+use clap::{ArgAction, Parser, Subcommand};
 
-use hmt_frontend_solidity::{codegen::Codegen, diagnostics::ReportToStringExt, parser};
+mod commands;
+mod logger;
+
+use commands::{
+    abi::AbiArgs, ast::AstArgs, check::CheckArgs, compile::CompileArgs,
+    completions::CompletionsArgs, fmt::FmtArgs, outline::OutlineArgs, repl::ReplArgs,
+};
 
 #[derive(Debug, Parser)]
-pub struct Args {
-    /// Path to the input file
-    #[arg(long)]
-    pub input: PathBuf,
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
 
-    /// Path to the output file
-    #[arg(long)]
-    pub output: PathBuf,
+    /// Log the stages of the compiler and their durations, repeat for more
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+}
 
-    /// Also print AST to console
-    #[arg(long)]
-    pub print_ast: bool,
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    Compile(CompileArgs),
+    Check(CheckArgs),
+    Ast(AstArgs),
+    Abi(AbiArgs),
+    Fmt(FmtArgs),
+    Outline(OutlineArgs),
+    Repl(ReplArgs),
+    Completions(CompletionsArgs),
 }
 
 fn main() {
@@ -43,35 +56,31 @@ fn main() {
 }
 
 fn run() -> Result<()> {
-    let args = Args::parse();
+    let cli = Cli::parse_from(args());
+    logger::init(cli.verbose);
 
-    let source = fs::read_to_string(&args.input)
-        .context(format!("Failed to read input file: {}", args.input.display()))?;
+    match &cli.command {
+        Command::Compile(args) => commands::compile::run(args),
+        Command::Check(args) => commands::check::run(args),
+        Command::Ast(args) => commands::ast::run(args),
+        Command::Abi(args) => commands::abi::run(args),
+        Command::Fmt(args) => commands::fmt::run(args),
+        Command::Outline(args) => commands::outline::run(args),
+        Command::Repl(args) => commands::repl::run(args),
+        Command::Completions(args) => commands::completions::run(args),
+    }
+}
 
-    // Parse the Solidity source code into an abstract syntax tree (AST).
-    // If parsing fails, collect and format all diagnostics into error reports.
-    let mut ast = parser::parse(&source, 0).map_err(|diagnostices| {
-        let mut reports = Vec::new();
-        for diagnostic in diagnostices.iter() {
-            let report = Report::from(diagnostic);
-            match report.write_to_string(Source::from(&source)) {
-                Ok(report_string) => reports.push(report_string),
-                Err(e) => return anyhow!("Failed to generate error report: {}", e),
-            }
-        }
-        anyhow!("Parsing failed with {} errors:\n{}", reports.len(), reports.join("\n"))
-    })?;
+/// Earlier releases had no commands, so arguments starting with an option
+/// other than help or version are passed to `compile`.
+fn args() -> Vec<OsString> {
+    let mut args: Vec<OsString> = env::args_os().collect();
 
-    // Generate the AST representation if requested
-    if args.print_ast {
-        println!("{ast:#?}");
+    if let Some(first) = args.get(1).and_then(|arg| arg.to_str()) {
+        if first.starts_with('-') && !matches!(first, "-h" | "--help" | "-V" | "--version") {
+            args.insert(1, "compile".into());
+        }
     }
 
-    // Generate the intermediate representation (IR) from the AST
-    // and write it to the output file specified in the arguments
-    let mut generator = Codegen::new();
-    generator.gen(&mut ast);
-    generator.write(&args.output);
-
-    Ok(())
+    args
 }
@@ -0,0 +1,146 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Static archives (`.a`) of object files, in the GNU `ar` format.
+//!
+//! The archive has a symbol index, as linkers need one to pick the members
+//! defining the symbols they look for. Timestamps and owners are zero, so the
+//! same objects always give the same archive.
+
+use std::io::{self, Write};
+
+use cranelift::object::object::{self, Object, ObjectSymbol};
+
+const MAGIC: &[u8] = b"!<arch>\n";
+
+/// Names longer than this go into the table of long names
+const MAX_SHORT_NAME: usize = 15;
+
+const HEADER_SIZE: usize = 60;
+
+/// Write an archive holding the objects, given as their name and contents
+pub fn write_archive<W: Write>(out: &mut W, members: &[(String, Vec<u8>)]) -> io::Result<()> {
+    // The names of the members in the headers, long ones refer to the table
+    let mut long_names = Vec::new();
+    let names = members
+        .iter()
+        .map(|(name, _)| match name.len() > MAX_SHORT_NAME {
+            true => {
+                let offset = long_names.len();
+                long_names.extend_from_slice(name.as_bytes());
+                long_names.extend_from_slice(b"/\n");
+                format!("/{offset}")
+            }
+            false => format!("{name}/"),
+        })
+        .collect::<Vec<_>>();
+
+    let symbols = members.iter().map(|(_, data)| defined_symbols(data)).collect::<Vec<_>>();
+    let count = symbols.iter().map(Vec::len).sum::<usize>();
+    let index_size = 4 + 4 * count + symbols.iter().flatten().map(|s| s.len() + 1).sum::<usize>();
+
+    // The index refers to the members by the offset of their header
+    let mut offset = MAGIC.len() + HEADER_SIZE + padded(index_size);
+    if !long_names.is_empty() {
+        offset += HEADER_SIZE + padded(long_names.len());
+    }
+    let mut offsets = Vec::new();
+    for (_, data) in members {
+        offsets.push(offset);
+        offset += HEADER_SIZE + padded(data.len());
+    }
+
+    let mut index = Vec::with_capacity(index_size);
+    index.extend_from_slice(&(count as u32).to_be_bytes());
+    for (member, symbols) in symbols.iter().enumerate() {
+        for _ in symbols {
+            index.extend_from_slice(&(offsets[member] as u32).to_be_bytes());
+        }
+    }
+    for symbol in symbols.iter().flatten() {
+        index.extend_from_slice(symbol.as_bytes());
+        index.push(0);
+    }
+
+    out.write_all(MAGIC)?;
+    write_member(out, "/", &index)?;
+    if !long_names.is_empty() {
+        write_member(out, "//", &long_names)?;
+    }
+    for (name, (_, data)) in names.iter().zip(members) {
+        write_member(out, name, data)?;
+    }
+
+    Ok(())
+}
+
+fn write_member<W: Write>(out: &mut W, name: &str, data: &[u8]) -> io::Result<()> {
+    writeln!(out, "{name:<16}{:<12}{:<6}{:<6}{:<8o}{:<10}`", 0, 0, 0, 0o644, data.len())?;
+    out.write_all(data)?;
+
+    // Members start at even offsets
+    if data.len() % 2 == 1 {
+        out.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+fn padded(size: usize) -> usize {
+    size + size % 2
+}
+
+/// The global symbols defined by an object file, none when it cannot be read
+fn defined_symbols(data: &[u8]) -> Vec<String> {
+    let Ok(file) = object::File::parse(data) else {
+        return Vec::new();
+    };
+
+    file.symbols()
+        .filter(|symbol| symbol.is_global() && symbol.is_definition())
+        .filter_map(|symbol| symbol.name().ok().map(str::to_string))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_archive() {
+        let members = vec![
+            ("A.o".to_string(), b"abc".to_vec()),
+            ("AVeryLongContractName.o".to_string(), b"data".to_vec()),
+        ];
+
+        let mut archive = Vec::new();
+        write_archive(&mut archive, &members).unwrap();
+
+        assert!(archive.starts_with(MAGIC));
+        // An empty index: the members are not objects
+        assert_eq!(&archive[8..24], b"/               ");
+        assert_eq!(&archive[68..72], &[0, 0, 0, 0]);
+
+        let names = &archive[72..72 + HEADER_SIZE];
+        assert!(names.starts_with(b"//  "));
+        let table = &archive[132..132 + 26];
+        assert_eq!(table, b"AVeryLongContractName.o/\n\n");
+
+        let first = &archive[158..158 + HEADER_SIZE];
+        assert!(first.starts_with(b"A.o/ "));
+        assert_eq!(&archive[218..222], b"abc\n");
+        assert!(archive[222..].starts_with(b"/0 "));
+        assert_eq!(archive.len(), 222 + HEADER_SIZE + 4);
+    }
+}
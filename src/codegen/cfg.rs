@@ -0,0 +1,309 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The edges `break` and `continue` add to the control flow graph.
+//!
+//! A loop is lowered to a header block, the blocks of its body and an exit
+//! block after it. A `break` jumps to the exit of the innermost loop, and a
+//! `continue` to the step of a `for` loop, to the condition of a `while` loop
+//! and to the condition after the body of a `do while` loop. The semantic
+//! analysis rejects both outside of a loop, see
+//! [`LoopScopes`](crate::semantic::symtable::LoopScopes), so a jump without a
+//! target is an internal error here.
+//!
+//! The clauses of a `try`/`catch` belong to the loops around it. The body of a
+//! function spliced into a modifier at `_` does not: a `break` in the function
+//! cannot leave a loop of the modifier, so the loops are suspended while the
+//! function is lowered.
+
+use std::mem;
+
+use cranelift::prelude::{Block, FunctionBuilder};
+
+use crate::{
+    parser::ast as pt,
+    semantic::ast::{Recurse, Statement},
+};
+
+/// The blocks a loop jumps to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopBlocks {
+    /// The block entering the loop: the condition, or the body of a
+    /// `do while` loop
+    pub header: Block,
+    /// The block a `continue` jumps to
+    pub next: Block,
+    /// The block after the loop, which a `break` jumps to
+    pub exit: Block,
+}
+
+impl LoopBlocks {
+    /// Create the blocks of a loop statement, `None` for other statements. A
+    /// `for` loop without a step and a `while` loop continue at their header.
+    pub fn new(builder: &mut FunctionBuilder, stmt: &Statement) -> Option<Self> {
+        let header = match stmt {
+            Statement::For { .. } | Statement::While(..) | Statement::DoWhile(..) => {
+                builder.create_block()
+            }
+            _ => return None,
+        };
+
+        let next = match stmt {
+            Statement::For { next: None, .. } | Statement::While(..) => header,
+            _ => builder.create_block(),
+        };
+
+        Some(Self { header, next, exit: builder.create_block() })
+    }
+}
+
+/// The loops around the statement being lowered, innermost last
+#[derive(Debug, Default)]
+pub struct LoopTargets {
+    loops: Vec<LoopBlocks>,
+}
+
+impl LoopTargets {
+    /// Enter the body of a loop
+    pub fn enter(&mut self, blocks: LoopBlocks) {
+        self.loops.push(blocks);
+    }
+
+    /// Leave the body of a loop, giving its blocks back
+    pub fn leave(&mut self) -> LoopBlocks {
+        self.loops.pop().expect("internal error: not in a loop")
+    }
+
+    /// The block a `break` jumps to, `None` outside of a loop
+    pub fn break_target(&self) -> Option<Block> {
+        self.loops.last().map(|blocks| blocks.exit)
+    }
+
+    /// The block a `continue` jumps to, `None` outside of a loop
+    pub fn continue_target(&self) -> Option<Block> {
+        self.loops.last().map(|blocks| blocks.next)
+    }
+
+    /// Set the loops aside while lowering code which cannot jump out of them,
+    /// like the body of a function spliced into a modifier
+    pub fn suspend(&mut self) -> Vec<LoopBlocks> {
+        mem::take(&mut self.loops)
+    }
+
+    /// Restore the loops set aside by [`suspend`](Self::suspend)
+    pub fn resume(&mut self, loops: Vec<LoopBlocks>) {
+        debug_assert!(self.loops.is_empty(), "internal error: loop left open");
+        self.loops = loops;
+    }
+
+    /// The number of loops around the statement being lowered
+    pub fn depth(&self) -> usize {
+        self.loops.len()
+    }
+}
+
+/// A `break` or `continue`, with the loop it jumps out of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    pub loc: pt::Loc,
+    pub is_break: bool,
+    /// The location of the loop statement
+    pub target: pt::Loc,
+}
+
+/// Resolve each `break` and `continue` of the statements to the innermost loop
+/// around it, giving the location of the first one outside of any loop as the
+/// error. The body of the function at a `_` placeholder is resolved on its own.
+pub fn resolve_jumps(stmts: &[Statement]) -> Result<Vec<Jump>, pt::Loc> {
+    let mut jumps = Vec::new();
+    resolve(stmts, &mut Vec::new(), &mut jumps)?;
+    Ok(jumps)
+}
+
+fn resolve(
+    stmts: &[Statement],
+    loops: &mut Vec<pt::Loc>,
+    jumps: &mut Vec<Jump>,
+) -> Result<(), pt::Loc> {
+    for stmt in stmts {
+        match stmt {
+            Statement::Break(loc) | Statement::Continue(loc) => {
+                let target = *loops.last().ok_or(*loc)?;
+                let is_break = matches!(stmt, Statement::Break(_));
+                jumps.push(Jump { loc: *loc, is_break, target });
+            }
+            Statement::Block { statements, .. } => resolve(statements, loops, jumps)?,
+            Statement::If(_, _, _, then, otherwise) => {
+                resolve(then, loops, jumps)?;
+                resolve(otherwise, loops, jumps)?;
+            }
+            Statement::For { loc, init, body, .. } => {
+                resolve(init, loops, jumps)?;
+                loop_body(*loc, body, loops, jumps)?;
+            }
+            Statement::While(loc, _, _, body) | Statement::DoWhile(loc, _, body, _) => {
+                loop_body(*loc, body, loops, jumps)?;
+            }
+            Statement::TryCatch(_, _, try_catch) => {
+                resolve(&try_catch.ok_stmt, loops, jumps)?;
+                for clause in try_catch.errors.iter().chain(&try_catch.catch_all) {
+                    resolve(&clause.stmt, loops, jumps)?;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn loop_body(
+    loc: pt::Loc,
+    body: &[Statement],
+    loops: &mut Vec<pt::Loc>,
+    jumps: &mut Vec<Jump>,
+) -> Result<(), pt::Loc> {
+    loops.push(loc);
+    let result = resolve(body, loops, jumps);
+    loops.pop();
+    result
+}
+
+/// Whether the statements have no loop, so that their control flow graph has
+/// no cycle and no `break` or `continue` edge
+pub fn is_loop_free(stmts: &[Statement]) -> bool {
+    let mut loop_free = true;
+    for stmt in stmts {
+        stmt.recurse(&mut loop_free, |stmt, loop_free| {
+            if matches!(stmt, Statement::For { .. } | Statement::While(..) | Statement::DoWhile(..))
+            {
+                *loop_free = false;
+            }
+            *loop_free
+        });
+    }
+    loop_free
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::{
+        codegen::ir::{Function, UserFuncName},
+        prelude::{isa::CallConv, FunctionBuilderContext, Signature},
+    };
+
+    use super::*;
+    use crate::semantic::ast::{CatchClause, Expression, TryCatch, Type};
+
+    fn loc(start: usize) -> pt::Loc {
+        pt::Loc::File(0, start, start + 1)
+    }
+
+    fn truth() -> Expression {
+        Expression::BoolLiteral { loc: pt::Loc::Builtin, value: true }
+    }
+
+    fn while_loop(start: usize, body: Vec<Statement>) -> Statement {
+        Statement::While(loc(start), true, truth(), body)
+    }
+
+    #[test]
+    fn test_resolve_jumps() {
+        let try_catch = TryCatch {
+            expr: truth(),
+            returns: Vec::new(),
+            ok_stmt: vec![Statement::Continue(loc(3))],
+            errors: Vec::new(),
+            catch_all: Some(CatchClause {
+                param: None,
+                param_pos: None,
+                stmt: vec![Statement::Break(loc(4))],
+            }),
+        };
+        let inner = Statement::DoWhile(loc(5), true, vec![Statement::Break(loc(6))], truth());
+        let body = vec![while_loop(
+            0,
+            vec![
+                Statement::Break(loc(1)),
+                Statement::TryCatch(loc(2), true, try_catch),
+                inner,
+                Statement::Continue(loc(7)),
+            ],
+        )];
+
+        let jumps = resolve_jumps(&body).unwrap();
+        let targets = jumps.iter().map(|jump| (jump.loc, jump.is_break, jump.target));
+        assert_eq!(
+            targets.collect::<Vec<_>>(),
+            [
+                (loc(1), true, loc(0)),
+                (loc(3), false, loc(0)),
+                (loc(4), true, loc(0)),
+                (loc(6), true, loc(5)),
+                (loc(7), false, loc(0)),
+            ]
+        );
+        assert!(!is_loop_free(&body));
+
+        // A jump out of the loop around a placeholder is not resolved to it
+        let outside = vec![Statement::Underscore(loc(0)), Statement::Continue(loc(1))];
+        assert_eq!(resolve_jumps(&outside), Err(loc(1)));
+        assert!(is_loop_free(&outside));
+    }
+
+    #[test]
+    fn test_loop_targets() {
+        let mut func = Function::with_name_signature(
+            UserFuncName::default(),
+            Signature::new(CallConv::SystemV),
+        );
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut builder_ctx);
+
+        let for_loop = |next: Option<Expression>| Statement::For {
+            loc: loc(0),
+            reachable: true,
+            init: Vec::new(),
+            cond: None,
+            next,
+            body: Vec::new(),
+        };
+        let step = Expression::Variable { loc: pt::Loc::Builtin, ty: Type::Uint(8), var_no: 0 };
+
+        let outer = LoopBlocks::new(&mut builder, &while_loop(0, Vec::new())).unwrap();
+        assert_eq!(outer.next, outer.header);
+        let inner = LoopBlocks::new(&mut builder, &for_loop(Some(step))).unwrap();
+        assert_ne!(inner.next, inner.header);
+        assert_eq!(
+            LoopBlocks::new(&mut builder, &for_loop(None)).map(|b| b.next == b.header),
+            Some(true)
+        );
+        assert_eq!(LoopBlocks::new(&mut builder, &Statement::Break(loc(0))), None);
+
+        let mut targets = LoopTargets::default();
+        assert_eq!(targets.break_target(), None);
+        targets.enter(outer);
+        targets.enter(inner);
+        assert_eq!(targets.break_target(), Some(inner.exit));
+        assert_eq!(targets.continue_target(), Some(inner.next));
+
+        let suspended = targets.suspend();
+        assert_eq!(targets.continue_target(), None);
+        targets.resume(suspended);
+        assert_eq!(targets.depth(), 2);
+
+        assert_eq!(targets.leave(), inner);
+        assert_eq!(targets.break_target(), Some(outer.exit));
+    }
+}
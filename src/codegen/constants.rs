@@ -0,0 +1,139 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Constant data, like string literals and encoded revert reasons.
+//!
+//! Rather than stored by instructions wherever it is used, constant data is
+//! emitted once in the read-only data of the object and referenced by its
+//! symbol. The pool of a module deduplicates it: identical constants of any
+//! function or contract of the module share their symbol, the local data
+//! symbol `constant__<hash>`, the hash being the first bytes of the keccak256
+//! of the data. `constant` is a keyword, no function has that name.
+
+use std::collections::HashSet;
+
+use cranelift::module::{DataDescription, DataId, Linkage, Module, ModuleError};
+use indexmap::IndexMap;
+
+use crate::abi::ethereum;
+
+use super::mangle::Symbol;
+
+/// The name of constant data symbols
+pub const CONSTANT: &str = "constant";
+
+/// The constants defined in a module
+#[derive(Debug, Default)]
+pub struct ConstantPool {
+    constants: IndexMap<Vec<u8>, DataId>,
+    /// The hashes naming the constants
+    hashes: HashSet<[u8; 4]>,
+}
+
+impl ConstantPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The data of the constant, defined in the module on its first use
+    pub fn define<M: Module>(
+        &mut self,
+        module: &mut M,
+        bytes: &[u8],
+    ) -> Result<DataId, Box<ModuleError>> {
+        if let Some(id) = self.constants.get(bytes) {
+            return Ok(*id);
+        }
+
+        // Two constants with the same hash take the next free one
+        let mut hash = u32::from_be_bytes(ethereum::keccak256(bytes)[..4].try_into().unwrap());
+        while !self.hashes.insert(hash.to_be_bytes()) {
+            hash = hash.wrapping_add(1);
+        }
+        let symbol = Symbol {
+            contract: None,
+            name: CONSTANT.to_string(),
+            selector: Some(hash.to_be_bytes()),
+        };
+
+        let id = module
+            .declare_data(&symbol.mangle(), Linkage::Local, false, false)
+            .map_err(Box::new)?;
+        let mut data = DataDescription::new();
+        data.define(bytes.into());
+        module.define_data(id, &data).map_err(Box::new)?;
+
+        self.constants.insert(bytes.to_vec(), id);
+        Ok(id)
+    }
+
+    /// The number of distinct constants
+    pub fn len(&self) -> usize {
+        self.constants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constants.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{mangle::demangle, require::error_message, tests::analyze, Codegen};
+    use cranelift::object::ObjectModule;
+
+    fn defined(module: &ObjectModule) -> Vec<Symbol> {
+        module
+            .declarations()
+            .get_data_objects()
+            .filter_map(|(_, decl)| demangle(decl.name.as_ref()?))
+            .collect()
+    }
+
+    #[test]
+    fn test_deduplicate_constants() {
+        let source = r#"
+            contract Bank {
+                function withdraw(uint amount) public pure {
+                    require(amount < 10, "insufficient balance");
+                }
+            }
+            contract Vault {
+                function take(uint amount) public pure {
+                    require(amount < 20, "insufficient balance");
+                    if (amount == 5) revert("other");
+                }
+            }
+        "#;
+        let ctx = analyze(source);
+        let mut codegen = Codegen::new();
+
+        // The same reason, in two functions of two contracts
+        for contract_no in 0..ctx.contracts.len() {
+            codegen.gen_contract(&ctx, contract_no).unwrap();
+        }
+        assert_eq!(codegen.constants.len(), 2);
+        assert_eq!(codegen.ir().matches("symbol_value").count(), 3);
+
+        let symbols = defined(&codegen.module);
+        assert_eq!(symbols.len(), 2);
+        assert!(symbols.iter().all(|symbol| symbol.name == CONSTANT && symbol.contract.is_none()));
+        assert_ne!(symbols[0], symbols[1]);
+
+        let reason = error_message("insufficient balance");
+        let object = codegen.finish().unwrap();
+        assert_eq!(object.windows(reason.len()).filter(|data| *data == reason).count(), 1);
+    }
+}
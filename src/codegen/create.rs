@@ -0,0 +1,122 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Creation of contracts with `new`.
+//!
+//! The object of a created contract is embedded in the object of the contract
+//! creating it, as the local data symbol `<contract>::code`, which is why the
+//! created contracts are generated first. `new` passes the code, with the
+//! encoded arguments of the constructor, to the `create` host function, which
+//! deploys it and writes the address of the new contract.
+
+use cranelift::{
+    codegen::ir::{FuncRef, GlobalValue},
+    module::{DataDescription, DataId, Linkage, Module, ModuleError},
+    prelude::{types, FunctionBuilder, InstBuilder, StackSlotData, StackSlotKind, Type, Value},
+};
+
+use super::{dispatch::WORD_SIZE, mangle::Symbol};
+
+/// Define the code of a created contract as data of the module
+pub fn define_code<M: Module>(
+    module: &mut M,
+    contract: &str,
+    code: &[u8],
+) -> Result<DataId, Box<ModuleError>> {
+    let id = module
+        .declare_data(&Symbol::code(contract).mangle(), Linkage::Local, false, false)
+        .map_err(Box::new)?;
+
+    let mut data = DataDescription::new();
+    data.define(code.into());
+    module.define_data(id, &data).map_err(Box::new)?;
+
+    Ok(id)
+}
+
+/// Emit the creation of a contract from its code, declared in the function,
+/// with the value sent and the encoded arguments of its constructor. Gives the
+/// status returned by the host, and the address of its word holding the
+/// address of the new contract.
+#[allow(clippy::too_many_arguments)]
+pub fn emit_create(
+    builder: &mut FunctionBuilder,
+    pointer_type: Type,
+    create: FuncRef,
+    code: GlobalValue,
+    code_len: usize,
+    value: Value,
+    input: Value,
+    input_len: Value,
+) -> (Value, Value) {
+    let slot = builder.create_sized_stack_slot(StackSlotData::new(
+        StackSlotKind::ExplicitSlot,
+        WORD_SIZE as u32,
+        3,
+    ));
+    let address = builder.ins().stack_addr(pointer_type, slot, 0);
+
+    let code = builder.ins().symbol_value(pointer_type, code);
+    let code_len = builder.ins().iconst(types::I32, code_len as i64);
+    let call = builder.ins().call(create, &[code, code_len, value, input, input_len, address]);
+    let status = builder.inst_results(call)[0];
+
+    (status, address)
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::codegen::ir::{ExternalName, GlobalValueData};
+
+    use super::*;
+    use crate::codegen::{
+        tests::{build, entry, import, signature},
+        Codegen,
+    };
+
+    #[test]
+    fn test_define_code() {
+        let mut generator = Codegen::new();
+        let id = define_code(&mut generator.module, "Pool", b"\x7fELF").unwrap();
+
+        let declaration = generator.module.declarations().get_data_decl(id);
+        assert_eq!(declaration.name.as_deref(), Some("Pool::code"));
+        assert_eq!(declaration.linkage, Linkage::Local);
+        assert!(generator.finish().is_ok());
+    }
+
+    #[test]
+    fn test_emit_create() {
+        let params = [types::I64, types::I64, types::I32];
+        let func = build(signature(&params, &[types::I32]), |builder| {
+            let host = [types::I64, types::I32, types::I64, types::I64, types::I32, types::I64];
+            let create = import(builder, "create", &host, &[types::I32]);
+            let code = builder.create_global_value(GlobalValueData::Symbol {
+                name: ExternalName::testcase("Pool::code"),
+                offset: 0.into(),
+                colocated: true,
+                tls: false,
+            });
+
+            let [value, input, input_len] = entry(builder).try_into().unwrap();
+            let (status, _) =
+                emit_create(builder, types::I64, create, code, 4, value, input, input_len);
+            builder.ins().return_(&[status]);
+        });
+        let ir = func.display().to_string();
+        assert!(ir.contains("symbol_value.i64"), "{ir}");
+        assert!(ir.contains("iconst.i32 4"), "{ir}");
+        assert_eq!(ir.matches("call fn0").count(), 1, "{ir}");
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Elimination of the functions no entry point of a contract can reach.
+//!
+//! The entry points are the functions of the external interface of the
+//! contract, see [`is_callable`](crate::abi::ethereum::is_callable), its
+//! constructors, and the initializers of its state variables and those of its
+//! bases. Calls are followed as the semantic analysis resolved them: a call of
+//! a virtual function reaches the function overriding it last in the contract,
+//! and the modifiers, constructor arguments of the bases, user defined
+//! operators and constants used reach the functions they call too.
+
+use std::{collections::HashSet, mem};
+
+use crate::{
+    abi::ethereum,
+    parser::ast::FunctionTy,
+    semantic::{
+        ast::{DestructureField, Expression, Recurse, Statement},
+        context::Context,
+    },
+};
+
+use super::init;
+
+/// The functions found reachable so far, and those left to walk
+struct Reachability<'a> {
+    ctx: &'a Context,
+    /// The contract whose overrides virtual calls run, if any
+    contract_no: Option<usize>,
+    worklist: Vec<usize>,
+}
+
+impl Reachability<'_> {
+    /// The function a call runs, the last override of a virtual function
+    fn callee(&self, function_no: usize, signature: Option<&String>) -> usize {
+        signature
+            .zip(self.contract_no)
+            .and_then(|(signature, contract_no)| {
+                self.ctx.contracts[contract_no].virtual_functions.get(signature)
+            })
+            .and_then(|overrides| overrides.last().copied())
+            .unwrap_or(function_no)
+    }
+}
+
+/// The functions reachable from the entry points of the contract, by number
+pub fn reachable_functions(contract_no: usize, ctx: &Context) -> HashSet<usize> {
+    let contract = &ctx.contracts[contract_no];
+    let mut cx = Reachability { ctx, contract_no: Some(contract_no), worklist: Vec::new() };
+
+    cx.worklist.extend(
+        contract
+            .all_functions
+            .keys()
+            .copied()
+            .filter(|no| ethereum::is_callable(contract_no, *no, &ctx.functions[*no], ctx)),
+    );
+    cx.worklist.extend(
+        contract.functions.iter().copied().filter(|no| ctx.functions[*no].is_constructor()),
+    );
+
+    for base_no in ctx.contract_bases(contract_no) {
+        for var in &ctx.contracts[base_no].variables {
+            if let Some(initializer) = &var.initializer {
+                initializer.recurse(&mut cx, expression);
+            }
+        }
+        for base in &ctx.contracts[base_no].bases {
+            for arg in base.constructor.iter().flat_map(|(_, args)| args) {
+                arg.recurse(&mut cx, expression);
+            }
+        }
+    }
+
+    let mut reachable = HashSet::new();
+    while let Some(function_no) = cx.worklist.pop() {
+        if !reachable.insert(function_no) {
+            continue;
+        }
+
+        let func = &ctx.functions[function_no];
+        for modifier in &func.modifiers {
+            modifier.recurse(&mut cx, expression);
+        }
+        for (_, _, args) in func.bases.values() {
+            for arg in args {
+                arg.recurse(&mut cx, expression);
+            }
+        }
+        for stmt in &func.body {
+            stmt.recurse(&mut cx, statement);
+        }
+    }
+
+    reachable
+}
+
+/// The functions the function calls, itself or in its modifiers, which are
+/// lowered into it, with the overrides of the contract given for virtual
+/// calls, by number and without duplicates
+pub fn callees(function_no: usize, contract_no: Option<usize>, ctx: &Context) -> Vec<usize> {
+    let mut cx = Reachability { ctx, contract_no, worklist: Vec::new() };
+    let func = &ctx.functions[function_no];
+    for modifier in &func.modifiers {
+        modifier.recurse(&mut cx, expression);
+    }
+    // The calls in the arguments of the modifiers are callees too
+    let (modifiers, calls): (Vec<_>, _) = mem::take(&mut cx.worklist)
+        .into_iter()
+        .partition(|no| ctx.functions[*no].ty == FunctionTy::Modifier);
+    cx.worklist = calls;
+
+    for function_no in modifiers.iter().copied().chain([function_no]) {
+        for stmt in &ctx.functions[function_no].body {
+            stmt.recurse(&mut cx, statement);
+        }
+    }
+
+    unique(cx.worklist)
+}
+
+/// The functions the initializers of the state variables of the contract and
+/// its bases call, see [`callees`]
+pub fn initializer_callees(contract_no: usize, ctx: &Context) -> Vec<usize> {
+    let mut cx = Reachability { ctx, contract_no: Some(contract_no), worklist: Vec::new() };
+    for initializer in init::initializers(contract_no, ctx) {
+        initializer.recurse(&mut cx, expression);
+    }
+    unique(cx.worklist)
+}
+
+/// The functions in the order they were found, without duplicates
+fn unique(functions: Vec<usize>) -> Vec<usize> {
+    let mut unique = Vec::new();
+    for function_no in functions {
+        if !unique.contains(&function_no) {
+            unique.push(function_no);
+        }
+    }
+    unique
+}
+
+/// Whether the function is lowered on its own: modifiers are lowered into the
+/// functions they modify
+pub fn is_lowered(function_no: usize, ctx: &Context) -> bool {
+    let func = &ctx.functions[function_no];
+    func.has_body && func.ty != FunctionTy::Modifier
+}
+
+fn statement(stmt: &Statement, cx: &mut Reachability) -> bool {
+    for expr in expressions(stmt) {
+        expr.recurse(cx, expression);
+    }
+    true
+}
+
+fn expression(expr: &Expression, cx: &mut Reachability) -> bool {
+    match expr {
+        Expression::InternalFunction { function_no, signature, .. } => {
+            let callee = cx.callee(*function_no, signature.as_ref());
+            cx.worklist.push(callee);
+        }
+        Expression::UserDefinedOperator { function_no, .. } => cx.worklist.push(*function_no),
+        // The value of a constant is lowered where it is used
+        Expression::ConstantVariable { contract_no, var_no, .. } => {
+            let var = match contract_no {
+                Some(contract_no) => &cx.ctx.contracts[*contract_no].variables[*var_no],
+                None => &cx.ctx.constants[*var_no],
+            };
+            if let Some(initializer) = &var.initializer {
+                initializer.recurse(cx, expression);
+            }
+        }
+        _ => (),
+    }
+    true
+}
+
+/// The expressions of a statement, without those of the statements in it
+pub(super) fn expressions(stmt: &Statement) -> Vec<&Expression> {
+    match stmt {
+        Statement::VariableDecl(_, _, _, init) => init.iter().map(|init| init.as_ref()).collect(),
+        Statement::If(_, _, expr, ..) |
+        Statement::While(_, _, expr, _) |
+        Statement::DoWhile(_, _, _, expr) |
+        Statement::Expression(_, _, expr) |
+        Statement::Delete(_, _, expr) => vec![expr],
+        Statement::For { cond, next, .. } => cond.iter().chain(next).collect(),
+        Statement::Destructure(_, fields, expr) => fields
+            .iter()
+            .filter_map(|field| match field {
+                DestructureField::Expression(expr) => Some(expr),
+                _ => None,
+            })
+            .chain([expr])
+            .collect(),
+        Statement::Return(_, expr) => expr.iter().collect(),
+        Statement::Revert { args, .. } | Statement::Emit { args, .. } => args.iter().collect(),
+        Statement::TryCatch(_, _, try_catch) => vec![&try_catch.expr],
+        Statement::Block { .. } |
+        Statement::Assembly(..) |
+        Statement::Continue(_) |
+        Statement::Break(_) |
+        Statement::Underscore(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reachable_functions() {
+        let source = r#"
+function used() pure returns (uint) { return 1; }
+function unused() pure {}
+library L { function double(uint x) internal pure returns (uint) { return x * 2; } }
+contract Base {
+    function helper() internal virtual {}
+    function dead() private {}
+    modifier only() { _; }
+}
+contract C is Base {
+    uint x = init();
+    function init() internal pure returns (uint) { return used(); }
+    function f() public only { helper(); x = L.double(x); }
+    function helper() internal override {}
+}
+"#;
+        let ctx = super::super::tests::analyze(source);
+        let contract_no = ctx.contracts.iter().position(|c| c.id.name == "C").unwrap();
+
+        let mut names = reachable_functions(contract_no, &ctx)
+            .into_iter()
+            .map(|no| {
+                let func = &ctx.functions[no];
+                let contract = func.contract_no.map(|no| ctx.contracts[no].id.name.as_str());
+                (contract, func.id.name.as_str(), func.ty.to_string())
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+
+        // The call of `helper` reaches the override only
+        assert_eq!(
+            names,
+            [
+                (None, "used", "function".into()),
+                (Some("Base"), "only", "modifier".into()),
+                (Some("C"), "", "constructor".into()),
+                (Some("C"), "f", "function".into()),
+                (Some("C"), "helper", "function".into()),
+                (Some("C"), "init", "function".into()),
+                (Some("L"), "double", "function".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_callees() {
+        let source = r#"
+function used() pure returns (uint) { return 1; }
+contract C {
+    uint x = one();
+    modifier at(uint n) { require(n > 0); _; used(); }
+    function f() public at(one()) { helper(); helper(); }
+    function one() internal pure returns (uint) { return 1; }
+    function helper() internal view {}
+}
+"#;
+        let ctx = super::super::tests::analyze(source);
+        let f = ctx.functions.iter().position(|func| func.id.name == "f").unwrap();
+        let names = |functions: Vec<usize>| {
+            functions.into_iter().map(|no| ctx.functions[no].id.name.as_str()).collect::<Vec<_>>()
+        };
+
+        // The modifier is lowered in the function, and calls once each
+        let mut callees = names(callees(f, Some(0), &ctx));
+        callees.sort();
+        assert_eq!(callees, ["helper", "one", "used"]);
+        assert_eq!(names(initializer_callees(0, &ctx)), ["one"]);
+    }
+}
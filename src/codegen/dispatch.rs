@@ -0,0 +1,532 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The dispatcher, the external entry point of a contract.
+//!
+//! The runtime calls `<contract>::dispatch(input, input_len)` with the input of
+//! a call. The dispatcher reads the 4 byte selector at the start of the input
+//! and jumps to the public or external function with that selector. An input
+//! too short for a selector goes to the receive function when it is empty and
+//! to the fallback function otherwise, as does an unknown selector. Without a
+//! function to go to, the call reverts, as does a call sending value to a
+//! function which is not payable.
+//!
+//! A function gets a pointer for each of its parameters, decoded following the
+//! Solidity ABI: to its words in the head of the arguments for static types,
+//! and to its data, which starts with its length, for dynamic types. Contracts
+//! are passed as their address, and the call reverts unless the 12 bytes above
+//! the 20 bytes of an address are clear. The fallback and receive functions
+//! take no arguments. A function ends the call with its results through the
+//! `return` host function, when it returns to the dispatcher instead the call
+//! ends without data.
+
+use indexmap::IndexMap;
+
+use cranelift::{
+    codegen::{
+        ir::{Endianness, FuncRef, Function},
+        isa::CallConv,
+    },
+    frontend::Switch,
+    module::{Linkage, Module, ModuleError},
+    prelude::{
+        types, AbiParam, Block, FunctionBuilder, FunctionBuilderContext, InstBuilder, IntCC,
+        MemFlagsData, Signature, StackSlotData, StackSlotKind, Type, Value,
+    },
+};
+use num_traits::ToPrimitive;
+
+use crate::{
+    abi::ethereum,
+    parser::ast as pt,
+    runtime,
+    semantic::{ast, context::Context},
+};
+
+use super::{listing::Origin, mangle::Symbol};
+
+/// Size of the selector at the start of the input
+pub(super) const SELECTOR_SIZE: i64 = 4;
+
+/// Size of a word of the encoded arguments
+pub(super) const WORD_SIZE: i64 = 32;
+
+/// How a parameter is laid out in the head of the encoded arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    /// A value, or a struct or fixed size array of values, taking this many
+    /// words
+    Static(usize),
+    /// A type of variable size, whose word holds the offset of its data
+    Dynamic,
+    /// An `address` or a contract, in the low 20 bytes of its word
+    Address,
+}
+
+impl Param {
+    /// The layout of a parameter of the type
+    pub fn new(ty: &ast::Type, ctx: &Context) -> Self {
+        if matches!(ty, ast::Type::Address(_) | ast::Type::Contract(_)) {
+            return Param::Address;
+        }
+
+        match head_words(ty, ctx) {
+            Some(words) => Param::Static(words),
+            None => Param::Dynamic,
+        }
+    }
+
+    fn words(self) -> usize {
+        match self {
+            Param::Static(words) => words,
+            Param::Dynamic | Param::Address => 1,
+        }
+    }
+}
+
+/// A function the dispatcher may call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub symbol: Symbol,
+    pub params: Vec<Param>,
+    pub payable: bool,
+}
+
+impl Entry {
+    /// The signature of the function, a pointer for each parameter
+    fn signature(&self, call_conv: CallConv, pointer_type: Type) -> Signature {
+        let mut signature = Signature::new(call_conv);
+        signature.params.extend(self.params.iter().map(|_| AbiParam::new(pointer_type)));
+        signature
+    }
+
+    /// Size of the head of the encoded input, selector included
+    fn head_size(&self) -> i64 {
+        let words = self.params.iter().map(|param| param.words()).sum::<usize>();
+        SELECTOR_SIZE + WORD_SIZE * words as i64
+    }
+}
+
+/// The functions the dispatcher of a contract routes calls to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dispatcher {
+    pub contract: String,
+    /// The definition of the contract, for listings
+    pub origin: Option<Origin>,
+    /// The public and external functions, which all have a selector
+    pub functions: Vec<Entry>,
+    pub fallback: Option<Entry>,
+    pub receive: Option<Entry>,
+}
+
+impl Dispatcher {
+    /// The dispatcher of a contract, calling the functions of its external
+    /// interface, those of its bases included
+    pub fn new(contract_no: usize, ctx: &Context) -> Self {
+        let mut dispatcher = Self {
+            contract: ctx.contracts[contract_no].id.name.clone(),
+            origin: Origin::new(&ctx.contracts[contract_no].loc, ctx),
+            functions: Vec::new(),
+            fallback: None,
+            receive: None,
+        };
+
+        for function_no in ctx.contracts[contract_no].all_functions.keys() {
+            let func = &ctx.functions[*function_no];
+
+            if !ethereum::is_callable(contract_no, *function_no, func, ctx) {
+                continue;
+            }
+
+            let entry = |params: Vec<Param>| Entry {
+                symbol: Symbol::function(func, ctx),
+                params,
+                payable: func.is_payable(),
+            };
+
+            match func.ty {
+                pt::FunctionTy::Function => dispatcher.functions.push(entry(
+                    func.params.iter().map(|param| Param::new(&param.ty, ctx)).collect(),
+                )),
+                pt::FunctionTy::Fallback => dispatcher.fallback = Some(entry(Vec::new())),
+                pt::FunctionTy::Receive => dispatcher.receive = Some(entry(Vec::new())),
+                // Constructors only run when the contract is deployed
+                pt::FunctionTy::Constructor | pt::FunctionTy::Modifier => (),
+            }
+        }
+
+        dispatcher
+    }
+
+    /// The symbol of the dispatcher in the object file
+    pub fn symbol(&self) -> Symbol {
+        Symbol::dispatcher(&self.contract)
+    }
+
+    /// Define the dispatcher in the module, giving its IR, with the text of
+    /// its machine code when `disasm` is set. The functions it calls which
+    /// the module does not define are left to the linker.
+    pub fn emit<M: Module>(
+        &self,
+        module: &mut M,
+        disasm: bool,
+    ) -> Result<(Function, Option<String>), Box<ModuleError>> {
+        let call_conv = module.isa().default_call_conv();
+        let pointer_type = module.isa().pointer_type();
+        let big_endian = module.isa().endianness() == Endianness::Big;
+
+        let mut signature = Signature::new(call_conv);
+        signature.params.extend([AbiParam::new(pointer_type), AbiParam::new(types::I32)]);
+        let id = module
+            .declare_function(&self.symbol().mangle(), Linkage::Export, &signature)
+            .map_err(Box::new)?;
+
+        let imports = runtime::declare_imports(module)?;
+        let entries = self.functions.iter().chain(&self.fallback).chain(&self.receive);
+        let mut callees = Vec::new();
+        for entry in entries {
+            let signature = entry.signature(call_conv, pointer_type);
+            let callee = module
+                .declare_function(&entry.symbol.mangle(), Linkage::Import, &signature)
+                .map_err(Box::new)?;
+            callees.push(callee);
+        }
+
+        let mut ctx = module.make_context();
+        ctx.func.signature = signature;
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+
+        let host = ["return", "revert", "value_transferred"]
+            .into_iter()
+            .map(|name| (name, module.declare_func_in_func(imports[name], builder.func)))
+            .collect();
+        let mut callees = callees
+            .into_iter()
+            .map(|callee| module.declare_func_in_func(callee, builder.func))
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let entry_block = builder.create_block();
+        builder.append_block_params_for_function_params(entry_block);
+        builder.switch_to_block(entry_block);
+        let input = builder.block_params(entry_block)[0];
+        let input_len = builder.block_params(entry_block)[1];
+        let revert = builder.create_block();
+
+        let mut lowering =
+            Lowering { builder, pointer_type, big_endian, host, input, input_len, revert };
+
+        let cases = self
+            .functions
+            .iter()
+            .map(|entry| (entry, callees.next().unwrap(), lowering.builder.create_block()))
+            .collect::<Vec<_>>();
+        let fallback = self.fallback.as_ref().map(|entry| (entry, callees.next().unwrap()));
+        let receive = self.receive.as_ref().map(|entry| (entry, callees.next().unwrap()));
+
+        let no_selector = lowering.builder.create_block();
+        let selector_block = lowering.builder.create_block();
+        let fallback_block = lowering.builder.create_block();
+
+        let short =
+            lowering.builder.ins().icmp_imm_u(IntCC::UnsignedLessThan, input_len, SELECTOR_SIZE);
+        lowering.builder.ins().brif(short, no_selector, &[], selector_block, &[]);
+
+        // Plain transfers of value go to the receive function
+        lowering.builder.switch_to_block(no_selector);
+        match receive {
+            Some((entry, callee)) => {
+                let receive_block = lowering.builder.create_block();
+                let empty = lowering.builder.ins().icmp_imm_u(IntCC::Equal, input_len, 0);
+                lowering.builder.ins().brif(empty, receive_block, &[], fallback_block, &[]);
+
+                lowering.builder.switch_to_block(receive_block);
+                lowering.call(entry, callee);
+            }
+            None => {
+                lowering.builder.ins().jump(fallback_block, &[]);
+            }
+        }
+
+        lowering.builder.switch_to_block(selector_block);
+        let selector = load_u32(&mut lowering.builder, big_endian, input, 0);
+        let mut switch = Switch::new();
+        for (entry, _, block) in &cases {
+            let selector = entry.symbol.selector.expect("functions have a selector");
+            switch.set_entry(u32::from_be_bytes(selector).into(), *block);
+        }
+        switch.emit(&mut lowering.builder, selector, fallback_block);
+
+        for (entry, callee, block) in cases {
+            lowering.builder.switch_to_block(block);
+            lowering.call(entry, callee);
+        }
+
+        lowering.builder.switch_to_block(fallback_block);
+        match fallback {
+            Some((entry, callee)) => lowering.call(entry, callee),
+            None => {
+                lowering.builder.ins().jump(revert, &[]);
+            }
+        }
+
+        lowering.builder.switch_to_block(revert);
+        lowering.end("revert");
+
+        lowering.builder.seal_all_blocks();
+        lowering.builder.finalize(module.isa().frontend_config());
+
+        ctx.set_disasm(disasm);
+        module.define_function(id, &mut ctx).map_err(Box::new)?;
+        let asm = ctx.compiled_code().and_then(|code| code.vcode.clone());
+
+        Ok((ctx.func, asm))
+    }
+}
+
+/// The state of the dispatcher being built
+struct Lowering<'a> {
+    builder: FunctionBuilder<'a>,
+    pointer_type: Type,
+    big_endian: bool,
+    /// The host functions called, by name
+    host: IndexMap<&'static str, FuncRef>,
+    input: Value,
+    input_len: Value,
+    /// The block reverting the call
+    revert: Block,
+}
+
+impl Lowering<'_> {
+    /// Check the call, decode the arguments of the function and call it
+    fn call(&mut self, entry: &Entry, callee: FuncRef) {
+        if !entry.payable {
+            self.require_no_value();
+        }
+
+        let short = self.builder.ins().icmp_imm_u(
+            IntCC::UnsignedLessThan,
+            self.input_len,
+            entry.head_size(),
+        );
+        self.revert_if(short);
+
+        let mut args = Vec::new();
+        let mut offset = SELECTOR_SIZE;
+        for param in &entry.params {
+            let word = self.builder.ins().iadd_imm_u(self.input, offset);
+            args.push(match param {
+                Param::Static(_) => word,
+                Param::Dynamic => self.data(word),
+                Param::Address => {
+                    self.require_address(word);
+                    word
+                }
+            });
+            offset += WORD_SIZE * param.words() as i64;
+        }
+
+        self.builder.ins().call(callee, &args);
+        self.end("return");
+    }
+
+    /// The data of a dynamic parameter, at the offset in its word. The word
+    /// holding its length must be within the input.
+    fn data(&mut self, word: Value) -> Value {
+        // Offsets are 256 bit numbers, only those below 2^32 can be valid
+        let high = high_bits(&mut self.builder, word);
+        self.revert_if(high);
+
+        let offset = load_u32(&mut self.builder, self.big_endian, word, 28);
+        let offset = self.builder.ins().uextend(types::I64, offset);
+        let end = self.builder.ins().iadd_imm_u(offset, SELECTOR_SIZE + WORD_SIZE);
+        let input_len = self.builder.ins().uextend(types::I64, self.input_len);
+        let outside = self.builder.ins().icmp(IntCC::UnsignedGreaterThan, end, input_len);
+        self.revert_if(outside);
+
+        let offset = match self.pointer_type {
+            types::I64 => offset,
+            ty => self.builder.ins().ireduce(ty, offset),
+        };
+        let data = self.builder.ins().iadd(self.input, offset);
+        self.builder.ins().iadd_imm_u(data, SELECTOR_SIZE)
+    }
+
+    /// Revert when the word holds more than an address, in its low 20 bytes
+    fn require_address(&mut self, word: Value) {
+        let high = self.builder.ins().load(types::I64, flags(), word, 0);
+        let middle = self.builder.ins().load(types::I32, flags(), word, 8);
+        let middle = self.builder.ins().uextend(types::I64, middle);
+        let any = self.builder.ins().bor(high, middle);
+        self.revert_if(any);
+    }
+
+    /// Revert when the call sends value
+    fn require_no_value(&mut self) {
+        let slot = self.builder.create_sized_stack_slot(StackSlotData::new(
+            StackSlotKind::ExplicitSlot,
+            WORD_SIZE as u32,
+            3,
+        ));
+        let value = self.builder.ins().stack_addr(self.pointer_type, slot, 0);
+        let value_transferred = self.host["value_transferred"];
+        self.builder.ins().call(value_transferred, &[value]);
+
+        let mut any = self.builder.ins().load(types::I64, flags(), value, 0);
+        for offset in [8, 16, 24] {
+            let part = self.builder.ins().load(types::I64, flags(), value, offset);
+            any = self.builder.ins().bor(any, part);
+        }
+        self.revert_if(any);
+    }
+
+    /// Continue in a new block unless the condition holds
+    fn revert_if(&mut self, condition: Value) {
+        let next = self.builder.create_block();
+        self.builder.ins().brif(condition, self.revert, &[], next, &[]);
+        self.builder.switch_to_block(next);
+    }
+
+    /// End the call with the host function, without data
+    fn end(&mut self, name: &str) {
+        let data = self.builder.ins().iconst(self.pointer_type, 0);
+        let data_len = self.builder.ins().iconst(types::I32, 0);
+        let end = self.host[name];
+        self.builder.ins().call(end, &[data, data_len]);
+        self.builder.ins().return_(&[]);
+    }
+}
+
+/// The input may be unaligned, but is always readable
+pub(super) fn flags() -> MemFlagsData {
+    MemFlagsData::new().with_notrap()
+}
+
+/// Read a big endian 32 bit number
+pub(super) fn load_u32(
+    builder: &mut FunctionBuilder,
+    big_endian: bool,
+    ptr: Value,
+    offset: i32,
+) -> Value {
+    let value = builder.ins().load(types::I32, flags(), ptr, offset);
+    match big_endian {
+        true => value,
+        false => builder.ins().bswap(value),
+    }
+}
+
+/// The bits of a big endian 256 bit word above its low 32 bits, merged into
+/// a value which is zero when they all are
+pub(super) fn high_bits(builder: &mut FunctionBuilder, word: Value) -> Value {
+    let mut high = builder.ins().load(types::I32, flags(), word, 24);
+    high = builder.ins().uextend(types::I64, high);
+    for offset in [0, 8, 16] {
+        let part = builder.ins().load(types::I64, flags(), word, offset);
+        high = builder.ins().bor(high, part);
+    }
+    high
+}
+
+/// The words the type takes in the head of the encoded arguments, `None` for
+/// dynamic types
+fn head_words(ty: &ast::Type, ctx: &Context) -> Option<usize> {
+    match ty {
+        ast::Type::String | ast::Type::DynamicBytes => None,
+        ast::Type::Array(elem, dims) => {
+            dims.iter().try_fold(head_words(elem, ctx)?, |words, dim| match dim {
+                ast::ArrayLength::Fixed(len) => Some(words * len.to_usize()?),
+                _ => None,
+            })
+        }
+        ast::Type::Struct(struct_ty) => {
+            struct_ty.definition(ctx).fields.iter().map(|field| head_words(&field.ty, ctx)).sum()
+        }
+        ast::Type::UserType(no) => head_words(&ctx.user_types[*no].ty, ctx),
+        ast::Type::Ref(ty) | ast::Type::StorageRef(_, ty) => head_words(ty, ctx),
+        _ => Some(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::Codegen;
+    use cranelift::object::object::{self, Object, ObjectSymbol};
+
+    fn entry(name: &str, signature: &str, params: Vec<Param>, payable: bool) -> Entry {
+        Entry {
+            symbol: Symbol::new(Some("Token"), pt::FunctionTy::Function, name, signature),
+            params,
+            payable,
+        }
+    }
+
+    #[test]
+    fn test_emit_dispatcher() {
+        let receive = Entry {
+            symbol: Symbol::new(Some("Token"), pt::FunctionTy::Receive, "", ""),
+            params: Vec::new(),
+            payable: true,
+        };
+        let dispatcher = Dispatcher {
+            contract: "Token".into(),
+            origin: None,
+            functions: vec![
+                entry(
+                    "transfer",
+                    "transfer(address,uint256)",
+                    vec![Param::Address, Param::Static(1)],
+                    false,
+                ),
+                entry("setName", "setName(string)", vec![Param::Dynamic], false),
+                entry("deposit", "deposit()", Vec::new(), true),
+            ],
+            fallback: None,
+            receive: Some(receive),
+        };
+
+        let mut generator = Codegen::new();
+        generator.gen_dispatcher(&dispatcher).unwrap();
+
+        // Only the functions which are not payable check the value sent
+        let ir = generator.ir.clone();
+        assert_eq!(ir.matches("explicit_slot").count(), 2, "{ir}");
+
+        let object = generator.finish().unwrap();
+        let file = object::File::parse(&*object).unwrap();
+        let symbol = |name: &str| file.symbols().find(|s| s.name() == Ok(name)).unwrap();
+        assert!(symbol("Token::dispatch").is_definition());
+        assert!(symbol("Token::receive").is_undefined());
+        assert!(symbol("__hmt_revert").is_undefined());
+    }
+
+    #[test]
+    fn test_params() {
+        let ctx = Context::new();
+        let param = |ty: ast::Type| Param::new(&ty, &ctx);
+
+        // Contracts are passed as addresses
+        assert_eq!(param(ast::Type::Contract(0)), Param::Address);
+        assert_eq!(param(ast::Type::Address(true)), Param::Address);
+        assert_eq!(param(ast::Type::Uint(160)), Param::Static(1));
+        assert_eq!(param(ast::Type::String), Param::Dynamic);
+        let contracts = ast::Type::Array(
+            Box::new(ast::Type::Contract(0)),
+            vec![ast::ArrayLength::Fixed(2.into())],
+        );
+        assert_eq!(param(contracts), Param::Static(2));
+    }
+}
@@ -0,0 +1,2511 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lowering of the bodies of functions, as resolved by the semantic analysis.
+//!
+//! Values of the elementary types `bool`, `intN`, `uintN`, `address` and
+//! `bytesN`, and of the types standing for them, see [`value_type`], are held
+//! in [words](super::word): signed integers sign extended and the others zero
+//! extended, `bytesN` right aligned as in the resolved expressions, whose
+//! conversions are explicit. Each local variable is four Cranelift variables.
+//! Arithmetic is checked as in Solidity outside of `unchecked` blocks, trapping
+//! with [`TrapCode::INTEGER_OVERFLOW`], and a division by zero traps with
+//! [`TrapCode::INTEGER_DIVISION_BY_ZERO`]. `**` is a loop of checked
+//! multiplications, see [`power`](super::power).
+//!
+//! Branches and loops are lowered to blocks as described in
+//! [`cfg`](super::cfg), each block sealed as soon as all the jumps to it are
+//! known, so that the variables are resolved to SSA values along the way
+//! rather than all at once at the end. An `if`/`else if` chain comparing one
+//! variable against constants jumps to its branch at once, see
+//! [`switch`](super::switch).
+//!
+//! State variables are read and written through the storage host functions,
+//! at their slots in the layout of the contract being generated, see
+//! [`storage`](super::storage): `count += 1` loads the slot of `count`, adds 1
+//! and stores it back. A constant is replaced with its value. The elements of
+//! mappings with keys and values held in words, nested or not, are read and
+//! written at the keys hashed from their keys, which the getters of public
+//! mappings read too.
+//!
+//! The modifiers of a function are layers around its body: the body of the
+//! first modifier is lowered with the next layer at each placeholder `_`, and
+//! so on down to the body of the function, the arguments of a modifier being
+//! evaluated when it is entered. A `return` leaves its layer for the code after
+//! the placeholder of the layer around it, and ends the function in the
+//! outermost layer.
+//!
+//! A call of an internal or free function passes the limbs of its arguments
+//! and a stack slot its results are stored in, see [`signature`], calling
+//! the override of a virtual function the contract resolves. The functions
+//! called are declared in the function by the emitter, see
+//! [`dce::callees`](super::dce::callees).
+//!
+//! The call data is read through the `calldata_size` and `calldata_copy`
+//! host functions: `msg.sig` is its first 4 bytes, zero padded when it is
+//! shorter, and `msg.data` can be indexed and has a length, an index out of
+//! bounds trapping with [`INDEX_OUT_OF_BOUNDS`].
+//!
+//! `require` and `revert` end the call through the `revert` host function
+//! with the data of their error, see [`require`](super::require): the
+//! arguments of the error are encoded at compile time when they are literals,
+//! in constant data shared by the module, see [`constants`](super::constants),
+//! and must be of the types held in words otherwise.
+//!
+//! `new C{value: v}(args)` creates a contract whose code is embedded in the
+//! module, see [`create`](super::create), with the arguments of its
+//! constructor encoded as words and the value sent, reverting when the host
+//! fails to create it. It gives the address of the new contract.
+//!
+//! A call of an external function of another contract goes through the `call`
+//! host function with the selector of the function and the words of its
+//! arguments, as the data of an error. When the callee reverts, the call
+//! reverts with the same data; otherwise its results are decoded from the
+//! data it returned, as the dispatcher decodes parameters. In a `try`
+//! statement, the data a failed creation or call returns is copied to memory
+//! and selects the catch clause, see [`trycatch`](super::trycatch).
+//!
+//! Inline assembly is lowered in place, see [`yul`](super::yul): the local
+//! variables held in words are Yul variables of the block, and `x.slot` gives
+//! the slot of a state variable in the layout of the contract.
+//!
+//! The functions the dispatcher calls, see [`is_external`], follow its
+//! convention, see [`dispatch`](super::dispatch): they take a pointer to each
+//! parameter, revert when one is out of the range of its type, and end the
+//! call with their ABI encoded results through the `return` host function,
+//! `bytesN` being left aligned in the encoding. Internal and free functions
+//! take the limbs of the words of their parameters, then a pointer to the
+//! words of their results when they have any, which they store as in memory:
+//! four words do not fit in the return registers. Functions using other types,
+//! or statements and expressions not listed here, are not lowered yet.
+
+use std::{borrow::Cow, collections::HashMap, mem};
+
+use cranelift::{
+    codegen::{
+        ir::{self, BlockArg, FuncRef, GlobalValue},
+        isa::TargetFrontendConfig,
+    },
+    prelude::{
+        isa::CallConv, types, AbiParam, Block, FunctionBuilder, InstBuilder, IntCC, Signature,
+        TrapCode, Type, Value, Variable,
+    },
+};
+use indexmap::IndexMap;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Zero};
+
+use crate::{
+    abi::codec::Token,
+    emit::EmitterError,
+    helpers::CodeLocation,
+    parser::ast as pt,
+    runtime::{WordOp, MEMORY_SIZE},
+    semantic::{
+        ast::{
+            self, Builtin, CallArgs, Expression, Function, InlineAssembly, Layout, RetrieveType,
+            Statement, StorageClass, TryCatch,
+        },
+        context::Context,
+    },
+};
+
+use super::{
+    cfg::{LoopBlocks, LoopTargets},
+    create,
+    dispatch::WORD_SIZE,
+    init, memory,
+    power::Power,
+    require,
+    scratch::ScratchBuffers,
+    storage::{self, MappingKey, StateVariable},
+    switch::{self, Switch},
+    trycatch::{self, Catches, ReturnData, Targets, ERROR_SELECTOR},
+    unroll,
+    word::{self, Word},
+    yul::YulLowering,
+};
+
+/// Trap of an index out of the bounds of an array
+pub const INDEX_OUT_OF_BOUNDS: TrapCode = TrapCode::HEAP_OUT_OF_BOUNDS;
+
+/// Defines constant data in the module, giving its symbol in the function
+pub type DefineConstant<'a> =
+    dyn FnMut(&[u8], &mut ir::Function) -> Result<GlobalValue, EmitterError> + 'a;
+
+/// The elementary type of the words holding the values of a type, `None` for
+/// the types which are not held in a word: contracts are addresses, enums and
+/// user defined value types their underlying types, and the value sent with a
+/// call an unsigned integer
+pub(super) fn value_type(ty: &ast::Type, ctx: &Context) -> Option<ast::Type> {
+    match ty {
+        ast::Type::Bool | ast::Type::Int(_) | ast::Type::Uint(_) | ast::Type::Bytes(_) => {
+            Some(ty.clone())
+        }
+        ast::Type::Address(_) | ast::Type::Contract(_) => Some(ast::Type::Address(false)),
+        ast::Type::Enum(no) => value_type(&ctx.enums[*no].ty, ctx),
+        ast::Type::UserType(no) => value_type(&ctx.user_types[*no].ty, ctx),
+        ast::Type::Value => Some(ast::Type::Uint(8 * ctx.value_length as u16)),
+        _ => None,
+    }
+}
+
+/// The number of bits of the words of an elementary type, see [`value_type`]
+pub(super) fn bits(ty: &ast::Type) -> u16 {
+    match ty {
+        ast::Type::Bool => 1,
+        ast::Type::Address(_) => 160,
+        ast::Type::Int(bits) | ast::Type::Uint(bits) => *bits,
+        ast::Type::Bytes(n) => 8 * u16::from(*n),
+        ty => unreachable!("internal error: {ty:?} is not held in a word"),
+    }
+}
+
+/// Whether the dispatcher calls the function: the public and external
+/// functions of a contract, its constructors, fallback and receive functions
+pub fn is_external(func: &Function) -> bool {
+    func.contract_no.is_some() && (func.ty != pt::FunctionTy::Function || func.is_public())
+}
+
+/// The signature of the function: a pointer for each parameter for the
+/// dispatcher, the limbs of each parameter and a pointer to the results
+/// otherwise
+pub fn signature(func: &Function, call_conv: CallConv, pointer_type: Type) -> Signature {
+    let mut signature = Signature::new(call_conv);
+    if is_external(func) {
+        signature.params.extend(func.params.iter().map(|_| AbiParam::new(pointer_type)));
+    } else {
+        let limbs = (0..4 * func.params.len()).map(|_| AbiParam::new(types::I64));
+        signature.params.extend(limbs);
+        if !func.returns.is_empty() {
+            signature.params.push(AbiParam::new(pointer_type));
+        }
+    }
+    signature
+}
+
+/// What an expression refers to, which can be read and assigned to
+#[derive(Debug, Clone)]
+enum Place {
+    Local([Variable; 4]),
+    State(StateVariable),
+}
+
+/// The arithmetic operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+/// The comparison operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    Less,
+    More,
+    LessEqual,
+    MoreEqual,
+    Equal,
+    NotEqual,
+}
+
+/// A modifier of the function being lowered, with its arguments, or the
+/// function itself
+#[derive(Debug, Clone, Copy)]
+struct Layer<'a> {
+    function_no: usize,
+    args: &'a [Expression],
+}
+
+/// The state of the function being lowered. The host functions must contain
+/// `return`, `revert` and `word_op`, the storage functions for the state
+/// variables, `create` for `new`, and `memory` and the return data functions
+/// for `try`.
+pub struct FunctionLowering<'a, 'b> {
+    builder: &'a mut FunctionBuilder<'b>,
+    config: TargetFrontendConfig,
+    host: &'a IndexMap<&'static str, FuncRef>,
+    ctx: &'a Context,
+    /// Swap the bytes of the words in memory, on little endian targets
+    swap: bool,
+    /// The contract whose layout the state variables are read from
+    contract_no: Option<usize>,
+    external: bool,
+    /// Where an internal function stores its results
+    results: Option<Value>,
+    /// The variables holding the results, named or not
+    returns: Vec<(ast::Type, [Variable; 4])>,
+    /// The modifiers, outermost first, then the function
+    layers: Vec<Layer<'a>>,
+    /// The layer being lowered
+    layer: usize,
+    /// The block a `return` jumps to in each layer entered, after the
+    /// placeholder of the layer around it, `None` in the outermost layer
+    exits: Vec<Option<Block>>,
+    /// The local variables, by layer and number
+    vars: HashMap<(usize, usize), [Variable; 4]>,
+    /// The blocks `continue` and `break` jump to
+    loops: LoopTargets,
+    unchecked: bool,
+    /// The block reverting the call when a parameter is out of range or a
+    /// contract cannot be created
+    revert: Option<Block>,
+    /// The code of the contracts created with `new`, with its size, by name
+    codes: IndexMap<String, (GlobalValue, usize)>,
+    /// Defines constant data in the module, see [`with_constants`](Self::with_constants)
+    constants: Option<Box<DefineConstant<'a>>>,
+    /// Zero when loops are not unrolled, see [`unroll`]
+    unroll_budget: usize,
+    /// The function assigning the initializers of the state variables, which
+    /// the constructor calls first
+    initializers: Option<FuncRef>,
+    /// The functions the function calls, declared in the function, by number
+    functions: IndexMap<usize, FuncRef>,
+    /// The buffers of the encodings of the function, see [`scratch`]
+    scratch: ScratchBuffers,
+}
+
+impl<'a, 'b> FunctionLowering<'a, 'b> {
+    pub fn new(
+        builder: &'a mut FunctionBuilder<'b>,
+        config: TargetFrontendConfig,
+        host: &'a IndexMap<&'static str, FuncRef>,
+        ctx: &'a Context,
+        big_endian: bool,
+    ) -> Self {
+        Self {
+            builder,
+            config,
+            host,
+            ctx,
+            swap: !big_endian,
+            contract_no: None,
+            external: false,
+            results: None,
+            returns: Vec::new(),
+            layers: Vec::new(),
+            layer: 0,
+            exits: Vec::new(),
+            vars: HashMap::new(),
+            loops: LoopTargets::default(),
+            unchecked: false,
+            revert: None,
+            codes: IndexMap::new(),
+            constants: None,
+            unroll_budget: 0,
+            initializers: None,
+            functions: IndexMap::new(),
+            scratch: ScratchBuffers::default(),
+        }
+    }
+
+    /// Read the state variables from the layout of the contract, which is the
+    /// contract declaring the function or one inheriting it
+    pub fn with_contract(mut self, contract_no: usize) -> Self {
+        self.contract_no = Some(contract_no);
+        self
+    }
+
+    /// Give the code of the contracts the function may create, declared in
+    /// the function, see [`create::define_code`]
+    pub fn with_codes(mut self, codes: IndexMap<String, (GlobalValue, usize)>) -> Self {
+        self.codes = codes;
+        self
+    }
+
+    /// Define the constant data of the function, like the encoded reasons of
+    /// `require`, in the read-only data of the module, giving its symbol
+    /// declared in the function, see [`constants`](super::constants)
+    pub fn with_constants(
+        mut self,
+        define: impl FnMut(&[u8], &mut ir::Function) -> Result<GlobalValue, EmitterError> + 'a,
+    ) -> Self {
+        self.constants = Some(Box::new(define));
+        self
+    }
+
+    /// Unroll the loops which fit the budget, see [`unroll`]
+    pub fn with_unroll_budget(mut self, budget: usize) -> Self {
+        self.unroll_budget = budget;
+        self
+    }
+
+    /// Call the function assigning the initializers of the state variables,
+    /// see [`lower_initializers`](Self::lower_initializers), at the start of
+    /// the constructor
+    pub fn with_initializers(mut self, initializers: FuncRef) -> Self {
+        self.initializers = Some(initializers);
+        self
+    }
+
+    /// Give the internal functions the function may call, declared in the
+    /// function with their [`signature`], by number
+    pub fn with_functions(mut self, functions: IndexMap<usize, FuncRef>) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// Lower the assignments of the initializers of the state variables of
+    /// the contract of the layout and its bases, see [`init`], in the function
+    /// of the builder, which takes and returns nothing. All the blocks are
+    /// sealed, for the caller to finalize the function.
+    pub fn lower_initializers(mut self) -> Result<(), EmitterError> {
+        let ctx = self.ctx;
+        let Some(contract_no) = self.contract_no else {
+            return Ok(());
+        };
+
+        let entry = self.builder.create_block();
+        self.builder.switch_to_block(entry);
+        self.builder.seal_block(entry);
+        for initializer in init::initializers(contract_no, ctx) {
+            self.expression(&initializer).map_err(|err| err.located(initializer.loc()))?;
+        }
+        self.builder.ins().return_(&[]);
+
+        self.revert_block();
+        Ok(())
+    }
+
+    /// Lower the function in the function of the builder, whose signature
+    /// must be its [`signature`]. The constructor calls the initializers of
+    /// the state variables first, when given. All the blocks are sealed, for
+    /// the caller to finalize the function.
+    pub fn lower(mut self, function_no: usize) -> Result<(), EmitterError> {
+        let ctx = self.ctx;
+        let func = &ctx.functions[function_no];
+        self.external = is_external(func);
+
+        for modifier in &func.modifiers {
+            let Expression::InternalFunctionCall { function, args, .. } = modifier else {
+                return Err(self.unsupported(modifier));
+            };
+            let Expression::InternalFunction { function_no, signature, .. } = function.as_ref()
+            else {
+                return Err(self.unsupported(modifier));
+            };
+            let function_no = self.resolve_virtual(*function_no, signature.as_deref());
+            self.layers.push(Layer { function_no, args });
+        }
+        self.layers.push(Layer { function_no, args: &[] });
+        self.layer = self.layers.len() - 1;
+
+        let entry = self.builder.create_block();
+        self.builder.append_block_params_for_function_params(entry);
+        self.builder.switch_to_block(entry);
+        self.builder.seal_block(entry);
+        let args = self.builder.block_params(entry).to_vec();
+        if !self.external && !func.returns.is_empty() {
+            self.results = args.last().copied();
+        }
+
+        for (i, param) in func.params.iter().enumerate() {
+            let ty = self.value_type(&param.ty)?;
+            let word = match self.external {
+                true => self.param(&ty, args[i]),
+                false => Word(args[4 * i..4 * i + 4].try_into().unwrap()),
+            };
+            if let Some(Some(var_no)) = func.symtable.arguments.get(i) {
+                self.declare(*var_no, word);
+            }
+        }
+
+        // Accessors have no variables for their results
+        let named = func.symtable.returns.len() == func.returns.len();
+        for (i, param) in func.returns.iter().enumerate() {
+            let ty = self.value_type(&param.ty)?;
+            let zero = self.zero();
+            let vars = self.declare_vars(zero);
+            if named {
+                self.vars.insert((self.layer, func.symtable.returns[i]), vars);
+            }
+            self.returns.push((ty, vars));
+        }
+
+        if let Some(initializers) = self.initializers {
+            self.builder.ins().call(initializers, &[]);
+        }
+
+        self.layer = 0;
+        self.exits.push(None);
+        self.enter()?;
+
+        // The results are the return variables when the body ends without a
+        // return statement
+        let words = self.return_words();
+        self.exit(&words);
+
+        self.revert_block();
+        Ok(())
+    }
+
+    /// Lower the block reverting the call, if used, and seal the blocks left
+    /// unsealed, as inline assembly leaves them
+    fn revert_block(&mut self) {
+        if let Some(revert) = self.revert {
+            self.builder.switch_to_block(revert);
+            self.builder.seal_block(revert);
+            let data = self.builder.ins().iconst(self.config.pointer_type(), 0);
+            let data_len = self.builder.ins().iconst(types::I32, 0);
+            self.builder.ins().call(self.host["revert"], &[data, data_len]);
+            self.builder.ins().return_(&[]);
+        }
+
+        self.builder.seal_all_blocks();
+    }
+
+    /// The function a modifier invocation runs: the last override of a
+    /// virtual modifier in the contract of the layout
+    fn resolve_virtual(&self, function_no: usize, signature: Option<&str>) -> usize {
+        let overrides = signature.zip(self.contract_no).and_then(|(signature, contract_no)| {
+            self.ctx.contracts[contract_no].virtual_functions.get(signature)
+        });
+        overrides.and_then(|overrides| overrides.last().copied()).unwrap_or(function_no)
+    }
+
+    /// Lower the current layer: bind the arguments of a modifier, evaluated
+    /// in the function, to its parameters, then lower its body
+    fn enter(&mut self) -> Result<(), EmitterError> {
+        let ctx = self.ctx;
+        let layer = self.layers[self.layer];
+        let func = &ctx.functions[layer.function_no];
+
+        if !layer.args.is_empty() {
+            let depth = mem::replace(&mut self.layer, self.layers.len() - 1);
+            let words = layer.args.iter().map(|arg| self.expression(arg)).collect::<Result<_, _>>();
+            self.layer = depth;
+            let words: Vec<Word> = words?;
+            for (word, var_no) in words.into_iter().zip(&func.symtable.arguments) {
+                if let Some(var_no) = var_no {
+                    self.declare(*var_no, word);
+                }
+            }
+        }
+
+        let body = match self.unroll_budget {
+            0 => Cow::Borrowed(&func.body),
+            budget => {
+                let mut body = func.body.clone();
+                unroll::unroll_loops(&mut body, budget);
+                Cow::Owned(body)
+            }
+        };
+        self.statements(&body)
+    }
+
+    /// The placeholder `_` of a modifier, where the next layer is lowered. The
+    /// loops of the modifier cannot be left from there.
+    fn placeholder(&mut self) -> Result<(), EmitterError> {
+        let after = self.builder.create_block();
+        let loops = self.loops.suspend();
+        let unchecked = mem::take(&mut self.unchecked);
+        self.layer += 1;
+        self.exits.push(Some(after));
+
+        let result = self.enter();
+
+        self.exits.pop();
+        self.layer -= 1;
+        self.unchecked = unchecked;
+        self.loops.resume(loops);
+        result?;
+
+        self.builder.ins().jump(after, &[]);
+        self.builder.switch_to_block(after);
+        self.builder.seal_block(after);
+        Ok(())
+    }
+
+    /// Load a parameter the dispatcher passes, reverting unless it is in the
+    /// range of its type
+    fn param(&mut self, ty: &ast::Type, pointer: Value) -> Word {
+        let word = word::load_word(self.builder, self.swap, pointer);
+        match ty {
+            ast::Type::Bytes(n) if *n < 32 => {
+                // The bytes are left aligned, the bits after them must be clear
+                let shift = 256 - 8 * u16::from(*n);
+                let rest = word::truncate(self.builder, word, shift);
+                let dirty = word::any(self.builder, &rest.0);
+                self.revert_if(dirty);
+                word::shr_imm(self.builder, word, shift)
+            }
+            ty if bits(ty) < 256 => {
+                let out_of_range = self.out_of_range(ty, word);
+                self.revert_if(out_of_range);
+                word
+            }
+            _ => word,
+        }
+    }
+
+    /// Jump to the block reverting the call when the flag is set
+    fn revert_if(&mut self, flag: Value) {
+        let revert = match self.revert {
+            Some(revert) => revert,
+            None => *self.revert.insert(self.builder.create_block()),
+        };
+        let next = self.builder.create_block();
+        self.builder.ins().brif(flag, revert, &[], next, &[]);
+        self.builder.switch_to_block(next);
+        self.builder.seal_block(next);
+    }
+
+    fn statements(&mut self, stmts: &[Statement]) -> Result<(), EmitterError> {
+        stmts
+            .iter()
+            .try_for_each(|stmt| self.statement(stmt).map_err(|err| err.located(stmt.loc())))
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> Result<(), EmitterError> {
+        match stmt {
+            Statement::Block { unchecked, statements, .. } => {
+                let outer = self.unchecked;
+                self.unchecked |= *unchecked;
+                let result = self.statements(statements);
+                self.unchecked = outer;
+                result
+            }
+            Statement::VariableDecl(_, var_no, param, init) => {
+                self.value_type(&param.ty)?;
+                let word = match init {
+                    Some(init) => self.expression(init)?,
+                    None => self.zero(),
+                };
+                self.declare(*var_no, word);
+                Ok(())
+            }
+            Statement::Expression(_, _, expr) => self.expression(expr).map(|_| ()),
+            Statement::If(_, _, cond, then, otherwise) => {
+                if let Some(switch) = Switch::detect(stmt) {
+                    return self.switch(switch);
+                }
+
+                let cond = self.condition(cond)?;
+                let then_block = self.builder.create_block();
+                let merge = self.builder.create_block();
+                let else_block = match otherwise.is_empty() {
+                    true => merge,
+                    false => self.builder.create_block(),
+                };
+                self.builder.ins().brif(cond, then_block, &[], else_block, &[]);
+
+                self.builder.switch_to_block(then_block);
+                self.builder.seal_block(then_block);
+                self.statements(then)?;
+                self.builder.ins().jump(merge, &[]);
+
+                if !otherwise.is_empty() {
+                    self.builder.switch_to_block(else_block);
+                    self.builder.seal_block(else_block);
+                    self.statements(otherwise)?;
+                    self.builder.ins().jump(merge, &[]);
+                }
+
+                self.builder.switch_to_block(merge);
+                self.builder.seal_block(merge);
+                Ok(())
+            }
+            Statement::While(_, _, cond, body) => {
+                let blocks = self.loop_blocks(stmt);
+                let body_block = self.builder.create_block();
+                self.builder.ins().jump(blocks.header, &[]);
+
+                self.builder.switch_to_block(blocks.header);
+                let cond = self.condition(cond)?;
+                self.builder.ins().brif(cond, body_block, &[], blocks.exit, &[]);
+
+                self.builder.switch_to_block(body_block);
+                self.builder.seal_block(body_block);
+                self.loop_body(blocks, body)?;
+                self.builder.ins().jump(blocks.header, &[]);
+
+                // The back edge and the `continue`s jump to the header
+                self.builder.seal_block(blocks.header);
+                self.builder.switch_to_block(blocks.exit);
+                self.builder.seal_block(blocks.exit);
+                Ok(())
+            }
+            Statement::DoWhile(_, _, body, cond) => {
+                let blocks = self.loop_blocks(stmt);
+                self.builder.ins().jump(blocks.header, &[]);
+
+                self.builder.switch_to_block(blocks.header);
+                self.loop_body(blocks, body)?;
+                self.builder.ins().jump(blocks.next, &[]);
+
+                self.builder.switch_to_block(blocks.next);
+                self.builder.seal_block(blocks.next);
+                let cond = self.condition(cond)?;
+                self.builder.ins().brif(cond, blocks.header, &[], blocks.exit, &[]);
+
+                self.builder.seal_block(blocks.header);
+                self.builder.switch_to_block(blocks.exit);
+                self.builder.seal_block(blocks.exit);
+                Ok(())
+            }
+            Statement::For { init, cond, next, body, .. } => {
+                self.statements(init)?;
+
+                let blocks = self.loop_blocks(stmt);
+                let body_block = self.builder.create_block();
+                self.builder.ins().jump(blocks.header, &[]);
+
+                self.builder.switch_to_block(blocks.header);
+                match cond {
+                    Some(cond) => {
+                        let cond = self.condition(cond)?;
+                        self.builder.ins().brif(cond, body_block, &[], blocks.exit, &[]);
+                    }
+                    None => {
+                        self.builder.ins().jump(body_block, &[]);
+                    }
+                }
+
+                self.builder.switch_to_block(body_block);
+                self.builder.seal_block(body_block);
+                self.loop_body(blocks, body)?;
+                self.builder.ins().jump(blocks.next, &[]);
+
+                if let Some(next) = next {
+                    self.builder.switch_to_block(blocks.next);
+                    self.builder.seal_block(blocks.next);
+                    self.expression(next)?;
+                    self.builder.ins().jump(blocks.header, &[]);
+                }
+
+                self.builder.seal_block(blocks.header);
+                self.builder.switch_to_block(blocks.exit);
+                self.builder.seal_block(blocks.exit);
+                Ok(())
+            }
+            Statement::Break(_) | Statement::Continue(_) => {
+                let target = match stmt {
+                    Statement::Break(_) => self.loops.break_target(),
+                    _ => self.loops.continue_target(),
+                };
+                let target = target.expect("internal error: a jump outside of a loop");
+                self.builder.ins().jump(target, &[]);
+                self.unreachable();
+                Ok(())
+            }
+            Statement::Return(_, value) => self.return_statement(value.as_ref()),
+            Statement::Underscore(_) => self.placeholder(),
+            Statement::Revert { error_no, args, .. } => {
+                let (data, len) = match error_no {
+                    Some(error_no) => {
+                        let selector = self.ctx.errors[*error_no].selector(self.ctx);
+                        self.error_data(selector, args)?
+                    }
+                    None if args.is_empty() => self.no_data(),
+                    None => self.error_data(ERROR_SELECTOR, args)?,
+                };
+                require::emit_revert(self.builder, self.host["revert"], data, len);
+                self.unreachable();
+                Ok(())
+            }
+            Statement::TryCatch(_, _, try_catch) => self.try_catch(try_catch),
+            Statement::Assembly(assembly, _) => self.assembly(assembly),
+            Statement::Delete(..) | Statement::Destructure(..) | Statement::Emit { .. } => {
+                let name = match stmt {
+                    Statement::Delete(..) => "delete",
+                    Statement::Destructure(..) => "destructuring",
+                    _ => "emit",
+                };
+                Err(EmitterError::Unsupported(stmt.loc(), format!("the `{name}` statement")))
+            }
+        }
+    }
+
+    fn loop_blocks(&mut self, stmt: &Statement) -> LoopBlocks {
+        LoopBlocks::new(self.builder, stmt).expect("internal error: not a loop")
+    }
+
+    /// A chain of `if`/`else if` on one value, jumping to the branch of the
+    /// value at once. A value wider than 64 bits matches no case.
+    fn switch(&mut self, switch: Switch) -> Result<(), EmitterError> {
+        let word = self.expression(switch.scrutinee)?;
+        let merge = self.builder.create_block();
+        let otherwise = match switch.default.is_empty() {
+            true => merge,
+            false => self.builder.create_block(),
+        };
+        let low = self.builder.create_block();
+        let high = word::any(self.builder, &word.0[1..]);
+        self.builder.ins().brif(high, otherwise, &[], low, &[]);
+
+        self.builder.switch_to_block(low);
+        self.builder.seal_block(low);
+        let cases = switch
+            .cases
+            .iter()
+            .map(|(case, _)| (*case as u128, self.builder.create_block()))
+            .collect::<Vec<_>>();
+        switch::emit(self.builder, word.0[0], &cases, otherwise);
+
+        for ((_, body), (_, block)) in switch.cases.iter().zip(&cases) {
+            self.builder.switch_to_block(*block);
+            self.builder.seal_block(*block);
+            self.statements(body)?;
+            self.builder.ins().jump(merge, &[]);
+        }
+
+        if !switch.default.is_empty() {
+            self.builder.switch_to_block(otherwise);
+            self.builder.seal_block(otherwise);
+            self.statements(switch.default)?;
+            self.builder.ins().jump(merge, &[]);
+        }
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(())
+    }
+
+    fn loop_body(&mut self, blocks: LoopBlocks, body: &[Statement]) -> Result<(), EmitterError> {
+        self.loops.enter(blocks);
+        let result = self.statements(body);
+        self.loops.leave();
+        result
+    }
+
+    fn return_statement(&mut self, value: Option<&Expression>) -> Result<(), EmitterError> {
+        let words = match value {
+            None => self.return_words(),
+            Some(Expression::List { list, .. }) => {
+                list.iter().map(|expr| self.expression(expr)).collect::<Result<_, _>>()?
+            }
+            Some(call @ Expression::InternalFunctionCall { returns, .. }) if returns.len() > 1 => {
+                self.internal_call(call)?
+            }
+            Some(expr) => vec![self.expression(expr)?],
+        };
+
+        match self.exits[self.layer] {
+            None => self.exit(&words),
+            Some(after) => {
+                let vars = self.returns.iter().map(|(_, vars)| *vars).collect::<Vec<_>>();
+                for (vars, word) in vars.into_iter().zip(words) {
+                    self.store(vars, word);
+                }
+                self.builder.ins().jump(after, &[]);
+                self.unreachable();
+            }
+        }
+        Ok(())
+    }
+
+    /// The values of the return variables
+    fn return_words(&mut self) -> Vec<Word> {
+        let vars = self.returns.iter().map(|(_, vars)| *vars).collect::<Vec<_>>();
+        vars.into_iter().map(|vars| self.load(vars)).collect()
+    }
+
+    /// End the function with its results, stored where the caller asked for
+    /// an internal function and given to the host otherwise
+    fn exit(&mut self, words: &[Word]) {
+        let size = WORD_SIZE as u32 * words.len() as u32;
+        let data = match (self.results, words.is_empty()) {
+            (Some(results), _) => results,
+            (None, true) => self.builder.ins().iconst(self.config.pointer_type(), 0),
+            (None, false) => self.buffer(size),
+        };
+        for (i, word) in words.iter().enumerate() {
+            let word = match self.external {
+                true => {
+                    let ty = self.returns[i].0.clone();
+                    self.encode(&ty, *word)
+                }
+                false => *word,
+            };
+            let address = self.builder.ins().iadd_imm_u(data, WORD_SIZE * i as i64);
+            word::store_word(self.builder, self.swap, address, word);
+        }
+
+        if !self.external {
+            self.builder.ins().return_(&[]);
+            self.unreachable();
+            return;
+        }
+
+        let data_len = self.builder.ins().iconst(types::I32, i64::from(size));
+        self.builder.ins().call(self.host["return"], &[data, data_len]);
+        self.builder.ins().return_(&[]);
+        self.unreachable();
+    }
+
+    /// The word of a value as the ABI encodes it, `bytesN` left aligned
+    fn encode(&mut self, ty: &ast::Type, word: Word) -> Word {
+        match ty {
+            ast::Type::Bytes(n) if *n < 32 => {
+                word::shl_imm(self.builder, word, 256 - 8 * u16::from(*n))
+            }
+            _ => word,
+        }
+    }
+
+    /// Continue in a block which nothing jumps to, after a statement which
+    /// ends the block
+    fn unreachable(&mut self) {
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+        self.builder.seal_block(block);
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Result<Word, EmitterError> {
+        let ctx = self.ctx;
+
+        match expr {
+            Expression::BoolLiteral { value, .. } => {
+                Ok(self.literal_word(&BigInt::from(u8::from(*value))))
+            }
+            Expression::NumberLiteral { value, .. } => Ok(self.literal_word(value)),
+            Expression::BytesLiteral { ty: ast::Type::Bytes(_), value, .. } => {
+                let mut bytes = [0; 32];
+                bytes[32 - value.len()..].copy_from_slice(value);
+                Ok(word::constant(self.builder, &bytes))
+            }
+            Expression::Add { ty, unchecked, left, right, .. } => {
+                self.binary(Op::Add, ty, *unchecked, left, right)
+            }
+            Expression::Subtract { ty, unchecked, left, right, .. } => {
+                self.binary(Op::Sub, ty, *unchecked, left, right)
+            }
+            Expression::Multiply { ty, unchecked, left, right, .. } => {
+                self.binary(Op::Mul, ty, *unchecked, left, right)
+            }
+            Expression::Power { ty, unchecked, base, exp, .. } => {
+                let ty = self.value_type(ty)?;
+                let base = self.expression(base)?;
+                let exp = self.expression(exp)?;
+                let (mut power, result, base) = Power::emit(self.builder, base, exp);
+                let product = self.arithmetic(Op::Mul, &ty, *unchecked, result, base);
+                let base = power.multiplied(self.builder, product);
+                let square = self.arithmetic(Op::Mul, &ty, *unchecked, base, base);
+                Ok(power.squared(self.builder, square))
+            }
+            Expression::Divide { ty, left, right, .. } => {
+                self.binary(Op::Div, ty, self.unchecked, left, right)
+            }
+            Expression::Modulo { ty, left, right, .. } => {
+                self.binary(Op::Mod, ty, self.unchecked, left, right)
+            }
+            // The bits of values in range are, for every type, in range
+            Expression::BitwiseAnd { left, right, .. } |
+            Expression::BitwiseOr { left, right, .. } |
+            Expression::BitwiseXor { left, right, .. } => {
+                let a = self.expression(left)?;
+                let b = self.expression(right)?;
+                Ok(match expr {
+                    Expression::BitwiseAnd { .. } => {
+                        word::bitwise(self.builder, a, b, |ins, a, b| ins.band(a, b))
+                    }
+                    Expression::BitwiseOr { .. } => {
+                        word::bitwise(self.builder, a, b, |ins, a, b| ins.bor(a, b))
+                    }
+                    _ => word::bitwise(self.builder, a, b, |ins, a, b| ins.bxor(a, b)),
+                })
+            }
+            Expression::ShiftLeft { ty, left, right, .. } => {
+                self.shift(WordOp::Shl, ty, left, right)
+            }
+            Expression::ShiftRight { ty, left, right, sign, .. } => {
+                let op = if *sign { WordOp::Sar } else { WordOp::Shr };
+                self.shift(op, ty, left, right)
+            }
+            Expression::Variable { var_no, .. } => {
+                let vars = self.local(*var_no)?;
+                Ok(self.load(vars))
+            }
+            Expression::StorageLoad { expr: var, .. } => {
+                let place = self.place(var)?;
+                Ok(self.read(&place))
+            }
+            Expression::ConstantVariable { contract_no, var_no, .. } => {
+                let var = match contract_no {
+                    Some(contract_no) => &ctx.contracts[*contract_no].variables[*var_no],
+                    None => &ctx.constants[*var_no],
+                };
+                match &var.initializer {
+                    Some(value) => self.expression(value),
+                    None => Err(self.unsupported(expr)),
+                }
+            }
+            // Unsigned values are zero extended already
+            Expression::ZeroExt { expr: value, .. } => {
+                let from = self.value_type(&value.ty())?;
+                let word = self.expression(value)?;
+                match from {
+                    ast::Type::Int(bits) => Ok(word::truncate(self.builder, word, bits)),
+                    _ => Ok(word),
+                }
+            }
+            // Signed values are sign extended already
+            Expression::SignExt { expr: value, .. } => {
+                let from = self.value_type(&value.ty())?;
+                let word = self.expression(value)?;
+                match from {
+                    ast::Type::Int(_) => Ok(word),
+                    from => Ok(word::sign_extend(self.builder, word, bits(&from))),
+                }
+            }
+            Expression::Trunc { to, expr: value, .. } |
+            Expression::Cast { to, expr: value, .. } => {
+                let to = self.value_type(to)?;
+                let word = self.expression(value)?;
+                Ok(self.wrap(&to, word))
+            }
+            Expression::CheckingTrunc { to, expr: value, .. } => {
+                let to = self.value_type(to)?;
+                let word = self.expression(value)?;
+                let out_of_range = self.out_of_range(&to, word);
+                self.trap_if(out_of_range, TrapCode::INTEGER_OVERFLOW);
+                Ok(word)
+            }
+            Expression::PreIncrement { ty, unchecked, expr: target, .. } => {
+                self.increment(Op::Add, ty, *unchecked, target, false)
+            }
+            Expression::PreDecrement { ty, unchecked, expr: target, .. } => {
+                self.increment(Op::Sub, ty, *unchecked, target, false)
+            }
+            Expression::PostIncrement { ty, unchecked, expr: target, .. } => {
+                self.increment(Op::Add, ty, *unchecked, target, true)
+            }
+            Expression::PostDecrement { ty, unchecked, expr: target, .. } => {
+                self.increment(Op::Sub, ty, *unchecked, target, true)
+            }
+            Expression::Assign { left, right, .. } => {
+                let place = self.place(left)?;
+                let word = self.expression(right)?;
+                self.write(&place, word);
+                Ok(word)
+            }
+            Expression::Less { left, right, .. } => self.comparison(Cmp::Less, left, right),
+            Expression::More { left, right, .. } => self.comparison(Cmp::More, left, right),
+            Expression::LessEqual { left, right, .. } => {
+                self.comparison(Cmp::LessEqual, left, right)
+            }
+            Expression::MoreEqual { left, right, .. } => {
+                self.comparison(Cmp::MoreEqual, left, right)
+            }
+            Expression::Equal { left, right, .. } => self.comparison(Cmp::Equal, left, right),
+            Expression::NotEqual { left, right, .. } => self.comparison(Cmp::NotEqual, left, right),
+            Expression::Not { expr: value, .. } => {
+                let cond = self.condition(value)?;
+                let flag = self.builder.ins().icmp_imm_u(IntCC::Equal, cond, 0);
+                Ok(word::flag_word(self.builder, flag))
+            }
+            Expression::BitwiseNot { ty, expr: value, .. } => {
+                let ty = self.value_type(ty)?;
+                let word = self.expression(value)?;
+                let not = Word(word.0.map(|limb| self.builder.ins().bnot(limb)));
+                Ok(self.wrap(&ty, not))
+            }
+            Expression::Negate { ty, unchecked, expr: value, .. } => {
+                let ty = self.value_type(ty)?;
+                let word = self.expression(value)?;
+                let zero = self.zero();
+                Ok(self.arithmetic(Op::Sub, &ty, *unchecked, zero, word))
+            }
+            Expression::ConditionalOperator { cond, true_option, false_option, .. } => {
+                self.conditional(cond, true_option, false_option)
+            }
+            Expression::And { left, right, .. } => self.logical(true, left, right),
+            Expression::Or { left, right, .. } => self.logical(false, left, right),
+            Expression::Subscript { array, index, .. } if is_calldata(array) => {
+                let [low, high @ ..] = self.expression(index)?.0;
+                let high = word::any(self.builder, &high);
+                let high = self.builder.ins().icmp_imm_u(IntCC::NotEqual, high, 0);
+                let size = self.calldata_size();
+                let past = self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, low, size);
+                let outside = self.builder.ins().bor(high, past);
+                self.trap_if(outside, INDEX_OUT_OF_BOUNDS);
+
+                let offset = self.builder.ins().ireduce(types::I32, low);
+                Ok(self.calldata(offset, 1))
+            }
+            Expression::Builtin { kind: Builtin::Signature, .. } => {
+                let offset = self.builder.ins().iconst(types::I32, 0);
+                Ok(self.calldata(offset, 4))
+            }
+            Expression::Builtin { kind: Builtin::ArrayLength, args, .. }
+                if args.first().is_some_and(is_calldata) =>
+            {
+                let size = self.calldata_size();
+                Ok(word::limb_word(self.builder, size))
+            }
+            Expression::Constructor { contract_no, args, call_args, .. } => {
+                self.create(*contract_no, args, call_args)
+            }
+            Expression::InternalFunctionCall { .. } => {
+                let words = self.internal_call(expr)?;
+                Ok(words.first().copied().unwrap_or_else(|| self.zero()))
+            }
+            Expression::ExternalFunctionCall { returns, function, args, call_args, .. } => {
+                let status = self.emit_call(function, args, call_args)?;
+                // Without a clause, the call reverts with the data of the callee
+                let next = self.builder.create_block();
+                self.branch(
+                    status,
+                    &Targets { success: next, error: None, panic: None, other: None },
+                );
+                self.builder.switch_to_block(next);
+                self.builder.seal_block(next);
+                let words = self.return_values(returns)?;
+                Ok(words.first().copied().unwrap_or_else(|| self.zero()))
+            }
+            Expression::Builtin { kind: Builtin::Require, args, .. } => {
+                let cond = self.condition(&args[0])?;
+                let (data, len) = match args.get(1) {
+                    Some(Expression::Builtin {
+                        kind: Builtin::AbiEncodeWithSelector,
+                        args,
+                        ..
+                    }) => match args.as_slice() {
+                        [Expression::BytesLiteral { value, .. }, args @ ..] => {
+                            self.error_data(value.as_slice().try_into().unwrap(), args)?
+                        }
+                        _ => return Err(self.unsupported(expr)),
+                    },
+                    Some(_) => return Err(self.unsupported(expr)),
+                    None => self.no_data(),
+                };
+                require::emit(self.builder, cond, self.host["revert"], data, len);
+                Ok(self.zero())
+            }
+            expr => Err(self.unsupported(expr)),
+        }
+    }
+
+    /// The results of a call of an internal function, given the limbs of its
+    /// arguments and a slot to store its results in, see [`signature`]. The
+    /// overrides of a virtual function are called as the contract resolves
+    /// them; public functions take their arguments from the dispatcher, and
+    /// calling them internally is not supported.
+    fn internal_call(&mut self, call: &Expression) -> Result<Vec<Word>, EmitterError> {
+        let Expression::InternalFunctionCall { function, args, .. } = call else {
+            return Err(self.unsupported(call));
+        };
+        let Expression::InternalFunction { function_no, signature, .. } = function.as_ref() else {
+            return Err(self.unsupported(call));
+        };
+        let function_no = self.resolve_virtual(*function_no, signature.as_deref());
+        let Some(&callee) = self.functions.get(&function_no) else {
+            return Err(self.unsupported(call));
+        };
+
+        let mut params = Vec::new();
+        for arg in args {
+            params.extend(self.expression(arg)?.0);
+        }
+        let returns = self.ctx.functions[function_no].returns.len();
+        let results = (returns > 0).then(|| self.buffer(WORD_SIZE as u32 * returns as u32));
+        params.extend(results);
+        self.builder.ins().call(callee, &params);
+
+        let words = (0..returns).map(|i| {
+            let address = self.builder.ins().iadd_imm_u(results.unwrap(), WORD_SIZE * i as i64);
+            word::load_word(self.builder, self.swap, address)
+        });
+        Ok(words.collect())
+    }
+
+    /// The data a call reverts with for an error, its selector followed by
+    /// its arguments encoded as by the ABI, as a pointer and an `i32` length.
+    /// The data of literal arguments is encoded at compile time as constant
+    /// data, other arguments must be of value types.
+    fn error_data(
+        &mut self,
+        selector: [u8; 4],
+        args: &[Expression],
+    ) -> Result<(Value, Value), EmitterError> {
+        if let Some(tokens) = args.iter().map(token).collect::<Option<Vec<_>>>() {
+            let data = require::error_data(selector, &tokens);
+            let len = self.builder.ins().iconst(types::I32, data.len() as i64);
+            return Ok((self.constant(&data)?, len));
+        }
+
+        let mut words = Vec::new();
+        for arg in args {
+            let ty = self.value_type(&arg.ty())?;
+            let word = self.expression(arg)?;
+            words.push(self.encode(&ty, word));
+        }
+
+        // The selector ends the first word, so that the arguments are aligned
+        let mut first = [0; WORD_SIZE as usize];
+        first[WORD_SIZE as usize - 4..].copy_from_slice(&selector);
+        let first = word::constant(self.builder, &first);
+        let base = self.buffer(WORD_SIZE as u32 * (1 + words.len() as u32));
+        for (i, word) in [first].into_iter().chain(words).enumerate() {
+            let address = self.builder.ins().iadd_imm_u(base, WORD_SIZE * i as i64);
+            word::store_word(self.builder, self.swap, address, word);
+        }
+
+        let data = self.builder.ins().iadd_imm_u(base, WORD_SIZE - 4);
+        let len = self.builder.ins().iconst(types::I32, 4 + WORD_SIZE * args.len() as i64);
+        Ok((data, len))
+    }
+
+    /// The address of a buffer of the size, dead once the next is requested,
+    /// see [`scratch`]
+    fn buffer(&mut self, size: u32) -> Value {
+        self.scratch.emit_next(self.builder, self.config.pointer_type(), size)
+    }
+
+    /// The address of constant data, defined once in the module
+    fn constant(&mut self, bytes: &[u8]) -> Result<Value, EmitterError> {
+        let Some(define) = &mut self.constants else {
+            return Err(EmitterError::Unsupported(pt::Loc::Implicit, "constant data".into()));
+        };
+        let data = define(bytes, self.builder.func)?;
+        Ok(self.builder.ins().symbol_value(self.config.pointer_type(), data))
+    }
+
+    /// No data to revert with, as a pointer and an `i32` length
+    fn no_data(&mut self) -> (Value, Value) {
+        let data = self.builder.ins().iconst(self.config.pointer_type(), 0);
+        let len = self.builder.ins().iconst(types::I32, 0);
+        (data, len)
+    }
+
+    /// `new C{value: v}(args)`, where the arguments are encoded as the words
+    /// holding them
+    fn create(
+        &mut self,
+        contract_no: usize,
+        args: &[Expression],
+        call_args: &CallArgs,
+    ) -> Result<Word, EmitterError> {
+        let (status, address) = self.emit_create(contract_no, args, call_args)?;
+        let failed = self.builder.ins().icmp_imm_u(IntCC::NotEqual, status, 0);
+        self.revert_if(failed);
+
+        Ok(word::load_word(self.builder, self.swap, address))
+    }
+
+    /// The creation of a contract, giving the status returned by the host and
+    /// the address of the word holding the address of the new contract
+    fn emit_create(
+        &mut self,
+        contract_no: usize,
+        args: &[Expression],
+        call_args: &CallArgs,
+    ) -> Result<(Value, Value), EmitterError> {
+        let name = &self.ctx.contracts[contract_no].id.name;
+        for (option, arg) in [("salt", &call_args.salt), ("gas", &call_args.gas)] {
+            if let Some(arg) = arg {
+                return Err(EmitterError::Unsupported(
+                    arg.loc(),
+                    format!("the `{option}` of `new`"),
+                ));
+            }
+        }
+        let &(code, code_len) = self.codes.get(name).ok_or_else(|| {
+            EmitterError::Unsupported(
+                pt::Loc::Implicit,
+                format!("creating `{name}` without its code"),
+            )
+        })?;
+
+        let value = match &call_args.value {
+            Some(value) => self.expression(value)?,
+            None => self.zero(),
+        };
+        let value = word::spill(self.builder, self.config, self.swap, value);
+
+        let mut words = Vec::new();
+        for arg in args {
+            let ty = self.value_type(&arg.ty())?;
+            let word = self.expression(arg)?;
+            words.push(self.encode(&ty, word));
+        }
+        let size = WORD_SIZE as u32 * words.len() as u32;
+        let input = match words.is_empty() {
+            true => self.builder.ins().iconst(self.config.pointer_type(), 0),
+            false => self.buffer(size),
+        };
+        for (i, word) in words.into_iter().enumerate() {
+            let address = self.builder.ins().iadd_imm_u(input, WORD_SIZE * i as i64);
+            word::store_word(self.builder, self.swap, address, word);
+        }
+        let input_len = self.builder.ins().iconst(types::I32, i64::from(size));
+
+        Ok(create::emit_create(
+            self.builder,
+            self.config.pointer_type(),
+            self.host["create"],
+            code,
+            code_len,
+            value,
+            input,
+            input_len,
+        ))
+    }
+
+    /// The call of an external function of another contract through the
+    /// `call` host function, giving the status it returns. The input is the
+    /// selector of the function followed by the words of its arguments, as
+    /// for the data of an error, and the call is given all the gas left.
+    fn emit_call(
+        &mut self,
+        function: &Expression,
+        args: &[Expression],
+        call_args: &CallArgs,
+    ) -> Result<Value, EmitterError> {
+        let Expression::ExternalFunction { address, function_no, .. } = function else {
+            return Err(self.unsupported(function));
+        };
+        if let Some(gas) = &call_args.gas {
+            return Err(EmitterError::Unsupported(gas.loc(), "the `gas` of a call".into()));
+        }
+
+        let address = self.expression(address)?;
+        let address = word::spill(self.builder, self.config, self.swap, address);
+        let value = match &call_args.value {
+            Some(value) => self.expression(value)?,
+            None => self.zero(),
+        };
+        let value = word::spill(self.builder, self.config, self.swap, value);
+
+        let mut words = Vec::new();
+        for arg in args {
+            let ty = self.value_type(&arg.ty())?;
+            let word = self.expression(arg)?;
+            words.push(self.encode(&ty, word));
+        }
+
+        // The selector ends the first word, so that the arguments are aligned
+        let mut first = [0; WORD_SIZE as usize];
+        first[WORD_SIZE as usize - 4..]
+            .copy_from_slice(&self.ctx.functions[*function_no].selector());
+        let first = word::constant(self.builder, &first);
+        let base = self.buffer(WORD_SIZE as u32 * (1 + words.len() as u32));
+        for (i, word) in [first].into_iter().chain(words).enumerate() {
+            let address = self.builder.ins().iadd_imm_u(base, WORD_SIZE * i as i64);
+            word::store_word(self.builder, self.swap, address, word);
+        }
+
+        let input = self.builder.ins().iadd_imm_u(base, WORD_SIZE - 4);
+        let input_len = self.builder.ins().iconst(types::I32, 4 + WORD_SIZE * args.len() as i64);
+        let gas = self.builder.ins().iconst(types::I64, -1);
+        let call =
+            self.builder.ins().call(self.host["call"], &[address, value, input, input_len, gas]);
+        Ok(self.builder.inst_results(call)[0])
+    }
+
+    /// The values an external call returned, decoded from its return data as
+    /// the parameters of the dispatcher are, reverting when there are fewer
+    /// words than values
+    fn return_values(&mut self, returns: &[ast::Type]) -> Result<Vec<Word>, EmitterError> {
+        let types = returns.iter().map(|ty| self.value_type(ty)).collect::<Result<Vec<_>, _>>()?;
+        if types.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let size = WORD_SIZE as u32 * types.len() as u32;
+        let call = self.builder.ins().call(self.host["return_data_size"], &[]);
+        let len = self.builder.inst_results(call)[0];
+        let short = self.builder.ins().icmp_imm_u(IntCC::UnsignedLessThan, len, i64::from(size));
+        self.revert_if(short);
+
+        let data = self.buffer(size);
+        let offset = self.builder.ins().iconst(types::I32, 0);
+        let len = self.builder.ins().iconst(types::I32, i64::from(size));
+        self.builder.ins().call(self.host["return_data_copy"], &[data, offset, len]);
+        let words = types.iter().enumerate().map(|(i, ty)| {
+            let address = self.builder.ins().iadd_imm_u(data, WORD_SIZE * i as i64);
+            self.param(ty, address)
+        });
+        Ok(words.collect())
+    }
+
+    /// Jump to the target for the status of a call, the data the callee
+    /// returned when it reverted being copied to memory, see [`trycatch`]
+    fn branch(&mut self, status: Value, targets: &Targets) {
+        let (config, big_endian) = (self.config, !self.swap);
+        let [memory, size, copy] =
+            ["memory", "return_data_size", "return_data_copy"].map(|name| self.host[name]);
+        let return_data = |builder: &mut FunctionBuilder| {
+            let call = builder.ins().call(size, &[]);
+            let len = builder.inst_results(call)[0];
+            let base = memory::emit_base(builder, big_endian, memory);
+            let size = builder.ins().uextend(types::I64, len);
+            let data = memory::emit_alloc(builder, config, big_endian, base, size);
+            let offset = builder.ins().iconst(types::I32, 0);
+            builder.ins().call(copy, &[data, offset, len]);
+            ReturnData { data, len }
+        };
+        trycatch::emit(
+            self.builder,
+            status,
+            return_data,
+            targets,
+            self.host["revert"],
+            config.pointer_type(),
+            big_endian,
+        );
+    }
+
+    /// `try new C(args) returns (C c) { ... } catch ...` and
+    /// `try c.f(args) returns (uint v) { ... } catch ...`, the data the
+    /// creation or the call reverted with copied to memory to select the
+    /// catch clause, see [`trycatch`]. The parameters of the clauses for
+    /// `Error` and other data are strings and `bytes`, which are not held in
+    /// words: they are left undeclared.
+    fn try_catch(&mut self, try_catch: &TryCatch) -> Result<(), EmitterError> {
+        let (status, address) = match &try_catch.expr {
+            Expression::Constructor { contract_no, args, call_args, .. } => {
+                let (status, address) = self.emit_create(*contract_no, args, call_args)?;
+                (status, Some(address))
+            }
+            Expression::ExternalFunctionCall { function, args, call_args, .. } => {
+                (self.emit_call(function, args, call_args)?, None)
+            }
+            expr => return Err(self.unsupported(expr)),
+        };
+
+        let catches = Catches::new(try_catch);
+        let pointer_type = self.config.pointer_type();
+        let merge = self.builder.create_block();
+        let mut block = |params: &[Type]| {
+            let block = self.builder.create_block();
+            for ty in params {
+                self.builder.append_block_param(block, *ty);
+            }
+            block
+        };
+        let targets = Targets {
+            success: block(&[]),
+            error: catches.error.map(|_| block(&[pointer_type])),
+            panic: catches.panic.map(|_| block(&[pointer_type])),
+            other: catches.other.map(|_| block(&[pointer_type, types::I32])),
+        };
+
+        self.branch(status, &targets);
+
+        self.builder.switch_to_block(targets.success);
+        self.builder.seal_block(targets.success);
+        let words = match address {
+            Some(address) => vec![word::load_word(self.builder, self.swap, address)],
+            None => {
+                let returns = try_catch.returns.iter().map(|(_, param)| param.ty.clone());
+                self.return_values(&returns.collect::<Vec<_>>())?
+            }
+        };
+        for ((var_no, param), word) in try_catch.returns.iter().zip(words) {
+            self.value_type(&param.ty)?;
+            if let Some(var_no) = var_no {
+                self.declare(*var_no, word);
+            }
+        }
+        self.statements(&try_catch.ok_stmt)?;
+        self.builder.ins().jump(merge, &[]);
+
+        let clauses = [
+            (targets.error, catches.error, false),
+            (targets.panic, catches.panic, true),
+            (targets.other, catches.other, false),
+        ];
+        for (block, clause, panic) in clauses {
+            let (Some(block), Some(clause)) = (block, clause) else {
+                continue;
+            };
+            self.builder.switch_to_block(block);
+            self.builder.seal_block(block);
+            // The code of a panic is the word after the selector
+            if let (true, Some(var_no)) = (panic, clause.param_pos) {
+                let code = self.builder.block_params(block)[0];
+                let word = word::load_word(self.builder, self.swap, code);
+                self.declare(var_no, word);
+            }
+            self.statements(&clause.stmt)?;
+            self.builder.ins().jump(merge, &[]);
+        }
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(())
+    }
+
+    /// An inline assembly block, in which the local variables held in words
+    /// are Yul variables: `bytesN` are left out, being left aligned in Yul
+    fn assembly(&mut self, assembly: &InlineAssembly) -> Result<(), EmitterError> {
+        let ctx = self.ctx;
+        let func = &ctx.functions[self.layers[self.layer].function_no];
+        let variables = assembly
+            .variables
+            .iter()
+            .filter_map(|(name, var_no)| {
+                let vars = self.vars.get(&(self.layer, *var_no))?;
+                let ty = value_type(&func.symtable.vars.get(var_no)?.ty, ctx)?;
+                (!matches!(ty, ast::Type::Bytes(_))).then(|| (name.clone(), *vars))
+            })
+            .collect();
+        let storage = self
+            .contract_no
+            .map(|no| &ctx.contracts[no].layout)
+            .into_iter()
+            .flatten()
+            .map(|layout| {
+                let var = &ctx.contracts[layout.contract_no].variables[layout.var_no];
+                (var.name.clone(), layout.clone())
+            })
+            .collect();
+
+        let memory = memory::emit_base(self.builder, !self.swap, self.host["memory"]);
+        let memory_size = self.builder.ins().iconst(types::I64, MEMORY_SIZE as i64);
+        YulLowering::new(self.builder, self.config, self.host, !self.swap, memory, memory_size)
+            .with_variables(variables)
+            .with_storage(storage)
+            .with_objects(self.codes.clone())
+            .lower(&assembly.block)?;
+        Ok(())
+    }
+
+    /// The `i64` to branch on of a `bool` expression
+    fn condition(&mut self, expr: &Expression) -> Result<Value, EmitterError> {
+        Ok(self.expression(expr)?.0[0])
+    }
+
+    /// `&&` and `||`, which only evaluate the right operand when the left
+    /// does not decide the result
+    fn logical(
+        &mut self,
+        and: bool,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Word, EmitterError> {
+        let left = self.condition(left)?;
+        let right_block = self.builder.create_block();
+        let merge = self.builder.create_block();
+        self.builder.append_block_param(merge, types::I64);
+
+        match and {
+            true => self.builder.ins().brif(left, right_block, &[], merge, &[left.into()]),
+            false => self.builder.ins().brif(left, merge, &[left.into()], right_block, &[]),
+        };
+
+        self.builder.switch_to_block(right_block);
+        self.builder.seal_block(right_block);
+        let right = self.condition(right)?;
+        self.builder.ins().jump(merge, &[right.into()]);
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        let result = self.builder.block_params(merge)[0];
+        Ok(word::limb_word(self.builder, result))
+    }
+
+    /// `cond ? a : b`, which only evaluates the option chosen
+    fn conditional(
+        &mut self,
+        cond: &Expression,
+        then: &Expression,
+        otherwise: &Expression,
+    ) -> Result<Word, EmitterError> {
+        let cond = self.condition(cond)?;
+        let then_block = self.builder.create_block();
+        let else_block = self.builder.create_block();
+        let merge = self.builder.create_block();
+        for _ in 0..4 {
+            self.builder.append_block_param(merge, types::I64);
+        }
+        self.builder.ins().brif(cond, then_block, &[], else_block, &[]);
+
+        for (block, option) in [(then_block, then), (else_block, otherwise)] {
+            self.builder.switch_to_block(block);
+            self.builder.seal_block(block);
+            let word = self.expression(option)?;
+            self.builder.ins().jump(merge, &word.0.map(BlockArg::from));
+        }
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(Word(self.builder.block_params(merge).try_into().unwrap()))
+    }
+
+    fn binary(
+        &mut self,
+        op: Op,
+        ty: &ast::Type,
+        unchecked: bool,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Word, EmitterError> {
+        let ty = self.value_type(ty)?;
+        let a = self.expression(left)?;
+        let b = self.expression(right)?;
+        Ok(self.arithmetic(op, &ty, unchecked, a, b))
+    }
+
+    /// The result of the operation on two words of the type, checked unless
+    /// `unchecked` is set
+    fn arithmetic(&mut self, op: Op, ty: &ast::Type, unchecked: bool, a: Word, b: Word) -> Word {
+        let signed = matches!(ty, ast::Type::Int(_));
+        let wide = bits(ty) == 256;
+
+        let result = match op {
+            Op::Add => {
+                let sum = word::add(self.builder, a, b);
+                if !unchecked && wide {
+                    let overflow = match signed {
+                        true => self.signed_overflow(sum, a, b),
+                        false => word::less(self.builder, sum, a),
+                    };
+                    self.trap_if(overflow, TrapCode::INTEGER_OVERFLOW);
+                }
+                sum
+            }
+            Op::Sub => {
+                let (difference, borrow) = word::sub_borrow(self.builder, a, b);
+                if !unchecked {
+                    match signed {
+                        // a - b overflows when a and b have different signs
+                        // and the difference the sign of b
+                        true if wide => {
+                            let overflow = self.signed_overflow(a, b, difference);
+                            self.trap_if(overflow, TrapCode::INTEGER_OVERFLOW);
+                        }
+                        true => (),
+                        false => self.trap_if(borrow, TrapCode::INTEGER_OVERFLOW),
+                    }
+                }
+                difference
+            }
+            Op::Mul => {
+                let product = word::mul(self.builder, a, b);
+                if !unchecked && bits(ty) > 128 {
+                    // The product of two values of at most 128 bits fits in
+                    // a word, check larger ones by dividing it back
+                    let div = if signed { WordOp::SDiv } else { WordOp::Div };
+                    let quotient = self.word_op(div, a, product);
+                    let nonzero = word::any(self.builder, &a.0);
+                    let nonzero = self.builder.ins().icmp_imm_u(IntCC::NotEqual, nonzero, 0);
+                    let same = word::equal(self.builder, quotient, b);
+                    let mut overflow = self.builder.ins().band_not(nonzero, same);
+                    if signed && wide {
+                        // -1 * MIN wraps to MIN, which divides back to MIN
+                        let minus_one = self.is_minus_one(a);
+                        let min = self.is_min(b);
+                        let special = self.builder.ins().band(minus_one, min);
+                        overflow = self.builder.ins().bor(overflow, special);
+                    }
+                    self.trap_if(overflow, TrapCode::INTEGER_OVERFLOW);
+                }
+                product
+            }
+            Op::Div | Op::Mod => {
+                let divisor = word::any(self.builder, &b.0);
+                self.builder.ins().trapz(divisor, TrapCode::INTEGER_DIVISION_BY_ZERO);
+                let word_op = match (op, signed) {
+                    (Op::Div, false) => WordOp::Div,
+                    (Op::Div, true) => WordOp::SDiv,
+                    (_, false) => WordOp::Mod,
+                    (_, true) => WordOp::SMod,
+                };
+                let result = self.word_op(word_op, b, a);
+                if op == Op::Div && signed && wide && !unchecked {
+                    // MIN / -1 is the only quotient out of range
+                    let min = self.is_min(a);
+                    let minus_one = self.is_minus_one(b);
+                    let overflow = self.builder.ins().band(min, minus_one);
+                    self.trap_if(overflow, TrapCode::INTEGER_OVERFLOW);
+                }
+                result
+            }
+        };
+
+        self.in_range(ty, unchecked, result)
+    }
+
+    /// The result of an operation on values of the type, which may be out of
+    /// its range when the type has less than 256 bits: trap if it is, or wrap
+    /// it around when unchecked
+    fn in_range(&mut self, ty: &ast::Type, unchecked: bool, word: Word) -> Word {
+        if bits(ty) == 256 {
+            return word;
+        }
+
+        match unchecked {
+            true => self.wrap(ty, word),
+            false => {
+                let out_of_range = self.out_of_range(ty, word);
+                self.trap_if(out_of_range, TrapCode::INTEGER_OVERFLOW);
+                word
+            }
+        }
+    }
+
+    /// Whether the sum `a + b = sum` of signed words overflowed, which is when
+    /// `a` and `b` have the same sign and the sum the other
+    fn signed_overflow(&mut self, sum: Word, a: Word, b: Word) -> Value {
+        let a = self.builder.ins().bxor(sum.0[3], a.0[3]);
+        let b = self.builder.ins().bxor(sum.0[3], b.0[3]);
+        let both = self.builder.ins().band(a, b);
+        self.builder.ins().icmp_imm_s(IntCC::SignedLessThan, both, 0)
+    }
+
+    fn shift(
+        &mut self,
+        op: WordOp,
+        ty: &ast::Type,
+        value: &Expression,
+        amount: &Expression,
+    ) -> Result<Word, EmitterError> {
+        let ty = self.value_type(ty)?;
+        let value = self.expression(value)?;
+        let amount = self.expression(amount)?;
+        let shifted =
+            word::word_op(self.builder, self.config, self.swap, self.host, op, &[amount, value]);
+
+        // Shifts never overflow, the bits shifted out are dropped
+        Ok(self.wrap(&ty, shifted))
+    }
+
+    fn comparison(
+        &mut self,
+        cmp: Cmp,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Word, EmitterError> {
+        let signed = matches!(self.value_type(&left.ty())?, ast::Type::Int(_));
+        let mut a = self.expression(left)?;
+        let mut b = self.expression(right)?;
+
+        let flag = match cmp {
+            Cmp::Equal | Cmp::NotEqual => {
+                let equal = word::equal(self.builder, a, b);
+                match cmp {
+                    Cmp::Equal => equal,
+                    _ => self.builder.ins().bxor_imm_u(equal, 1),
+                }
+            }
+            _ => {
+                // Flipping the sign bits orders signed words as unsigned ones
+                if signed {
+                    for word in [&mut a, &mut b] {
+                        word.0[3] = self.builder.ins().bxor_imm_u(word.0[3], i64::MIN);
+                    }
+                }
+                match cmp {
+                    Cmp::Less => word::less(self.builder, a, b),
+                    Cmp::More => word::less(self.builder, b, a),
+                    Cmp::LessEqual => {
+                        let more = word::less(self.builder, b, a);
+                        self.builder.ins().bxor_imm_u(more, 1)
+                    }
+                    _ => {
+                        let less = word::less(self.builder, a, b);
+                        self.builder.ins().bxor_imm_u(less, 1)
+                    }
+                }
+            }
+        };
+
+        Ok(word::flag_word(self.builder, flag))
+    }
+
+    /// `++` and `--`, giving the value before the update when `post` is set
+    fn increment(
+        &mut self,
+        op: Op,
+        ty: &ast::Type,
+        unchecked: bool,
+        target: &Expression,
+        post: bool,
+    ) -> Result<Word, EmitterError> {
+        let ty = self.value_type(ty)?;
+        let place = self.place(target)?;
+        let current = self.read(&place);
+        let one = self.literal_word(&BigInt::one());
+        let result = self.arithmetic(op, &ty, unchecked, current, one);
+        self.write(&place, result);
+        Ok(if post { current } else { result })
+    }
+
+    /// The word wrapped around to the range of the type
+    fn wrap(&mut self, ty: &ast::Type, word: Word) -> Word {
+        match ty {
+            ast::Type::Int(bits) => word::sign_extend(self.builder, word, *bits),
+            ty => word::truncate(self.builder, word, bits(ty)),
+        }
+    }
+
+    /// Whether the word is out of the range of the type, an `i8` flag
+    fn out_of_range(&mut self, ty: &ast::Type, word: Word) -> Value {
+        let wrapped = self.wrap(ty, word);
+        let equal = word::equal(self.builder, word, wrapped);
+        self.builder.ins().bxor_imm_u(equal, 1)
+    }
+
+    fn trap_if(&mut self, flag: Value, code: TrapCode) {
+        self.builder.ins().trapnz(flag, code);
+    }
+
+    /// Compute `a op b` with the `word_op` host function, whose operands are
+    /// in the order of the EVM, the divisor of a division first
+    fn word_op(&mut self, op: WordOp, divisor: Word, dividend: Word) -> Word {
+        word::word_op(self.builder, self.config, self.swap, self.host, op, &[dividend, divisor])
+    }
+
+    /// The size of the call data, as an `i64`
+    fn calldata_size(&mut self) -> Value {
+        let call = self.builder.ins().call(self.host["calldata_size"], &[]);
+        let size = self.builder.inst_results(call)[0];
+        self.builder.ins().uextend(types::I64, size)
+    }
+
+    /// The `len` bytes of the call data at the `i32` offset, aligned to the
+    /// right of a word as `bytesN`
+    fn calldata(&mut self, offset: Value, len: i64) -> Word {
+        let zero = self.zero();
+        let word = word::spill(self.builder, self.config, self.swap, zero);
+        let dest = self.builder.ins().iadd_imm_u(word, WORD_SIZE - len);
+        let len = self.builder.ins().iconst(types::I32, len);
+        self.builder.ins().call(self.host["calldata_copy"], &[dest, offset, len]);
+        word::load_word(self.builder, self.swap, word)
+    }
+
+    fn is_minus_one(&mut self, word: Word) -> Value {
+        let minus_one = self.literal_word(&-BigInt::one());
+        word::equal(self.builder, word, minus_one)
+    }
+
+    fn is_min(&mut self, word: Word) -> Value {
+        let min = self.literal_word(&-(BigInt::one() << 255u32));
+        word::equal(self.builder, word, min)
+    }
+
+    fn zero(&mut self) -> Word {
+        self.literal_word(&BigInt::zero())
+    }
+
+    /// The word of an integer, in two's complement
+    fn literal_word(&mut self, v: &BigInt) -> Word {
+        let bytes = v.to_signed_bytes_be();
+        let fill = if v.sign() == Sign::Minus { 0xff } else { 0 };
+        let mut word = [fill; 32];
+        word[32 - bytes.len()..].copy_from_slice(&bytes);
+        word::constant(self.builder, &word)
+    }
+
+    /// The elementary type of the words of the type, see [`value_type`]
+    fn value_type(&self, ty: &ast::Type) -> Result<ast::Type, EmitterError> {
+        value_type(ty, self.ctx).ok_or_else(|| {
+            EmitterError::Unsupported(
+                pt::Loc::Implicit,
+                format!("the type `{}`", ty.to_string(self.ctx)),
+            )
+        })
+    }
+
+    fn unsupported(&self, expr: &Expression) -> EmitterError {
+        EmitterError::Unsupported(
+            expr.loc(),
+            format!("the expression `{}`", expr.summary(self.ctx)),
+        )
+    }
+
+    /// Declare a local variable of the current layer
+    fn declare(&mut self, var_no: usize, word: Word) {
+        let vars = self.declare_vars(word);
+        self.vars.insert((self.layer, var_no), vars);
+    }
+
+    fn declare_vars(&mut self, word: Word) -> [Variable; 4] {
+        let vars = [(); 4].map(|()| self.builder.declare_var(types::I64));
+        self.store(vars, word);
+        vars
+    }
+
+    /// The variables of a local variable of the current layer
+    fn local(&self, var_no: usize) -> Result<[Variable; 4], EmitterError> {
+        self.vars.get(&(self.layer, var_no)).copied().ok_or_else(|| {
+            let func = &self.ctx.functions[self.layers[self.layer].function_no];
+            let name = func.symtable.vars.get(&var_no).map(|var| var.name.clone());
+            EmitterError::Undeclared(name.unwrap_or_else(|| var_no.to_string()))
+        })
+    }
+
+    /// The local or state variable an expression refers to, or the element
+    /// of a mapping
+    fn place(&mut self, expr: &Expression) -> Result<Place, EmitterError> {
+        match expr {
+            Expression::Variable { var_no, .. } => self.local(*var_no).map(Place::Local),
+            Expression::StorageVariable { contract_no, var_no, .. } => self
+                .layout(*contract_no, *var_no)
+                .and_then(|layout| StateVariable::new(layout, self.ctx))
+                .map(Place::State)
+                .ok_or_else(|| self.unsupported_variable(*contract_no, *var_no)),
+            Expression::Subscript { ty: ast::Type::StorageRef(_, value), array, .. }
+                if matches!(array.ty().deref_any(), ast::Type::Mapping(_)) =>
+            {
+                let ty = self.value_type(value)?;
+                let (storage_class, key) = self.mapping_key(expr)?;
+                Ok(Place::State(StateVariable {
+                    ty,
+                    storage_class,
+                    slot: storage::Slot::Element(key),
+                    offset: 0,
+                }))
+            }
+            expr => Err(self.unsupported(expr)),
+        }
+    }
+
+    /// The storage class and the address of the key of a mapping or of one of
+    /// its elements, see [`storage::emit_element_key`]
+    fn mapping_key(&mut self, expr: &Expression) -> Result<(StorageClass, Value), EmitterError> {
+        match expr {
+            Expression::StorageVariable { contract_no, var_no, .. } => {
+                let layout = self
+                    .layout(*contract_no, *var_no)
+                    .ok_or_else(|| self.unsupported_variable(*contract_no, *var_no))?;
+                let key = word::constant(self.builder, &storage::key(layout));
+                let key = word::spill(self.builder, self.config, self.swap, key);
+                Ok((layout.storage_class, key))
+            }
+            Expression::Subscript { array, index, .. } => {
+                let ast::Type::Mapping(mapping) = array.ty().deref_any().clone() else {
+                    return Err(self.unsupported(expr));
+                };
+                // Keys of strings and `bytes` are not held in words
+                let ty = self.value_type(&mapping.key)?;
+                let (storage_class, parent) = self.mapping_key(array)?;
+                let word = self.expression(index)?;
+                let word = self.encode(&ty, word);
+                let word = word::spill(self.builder, self.config, self.swap, word);
+                let key = storage::emit_element_key(
+                    self.builder,
+                    self.config,
+                    !self.swap,
+                    self.host,
+                    parent,
+                    &[MappingKey::new(&mapping.key, word)],
+                );
+                Ok((storage_class, key))
+            }
+            expr => Err(self.unsupported(expr)),
+        }
+    }
+
+    /// The layout of a state variable in the contract being generated
+    fn layout(&self, contract_no: usize, var_no: usize) -> Option<&'a Layout> {
+        self.contract_no.and_then(|no| self.ctx.contracts[no].storage_slot(contract_no, var_no))
+    }
+
+    fn unsupported_variable(&self, contract_no: usize, var_no: usize) -> EmitterError {
+        let name = &self.ctx.contracts[contract_no].variables[var_no].name;
+        EmitterError::Unsupported(pt::Loc::Implicit, format!("the state variable `{name}`"))
+    }
+
+    fn read(&mut self, place: &Place) -> Word {
+        match place {
+            Place::Local(vars) => self.load(*vars),
+            Place::State(var) => {
+                storage::emit_read(self.builder, self.config, self.swap, self.host, var)
+            }
+        }
+    }
+
+    fn write(&mut self, place: &Place, word: Word) {
+        match place {
+            Place::Local(vars) => self.store(*vars, word),
+            Place::State(var) => {
+                storage::emit_write(self.builder, self.config, self.swap, self.host, var, word)
+            }
+        }
+    }
+
+    fn load(&mut self, vars: [Variable; 4]) -> Word {
+        Word(vars.map(|var| self.builder.use_var(var)))
+    }
+
+    fn store(&mut self, vars: [Variable; 4], word: Word) {
+        for (var, limb) in vars.into_iter().zip(word.0) {
+            self.builder.def_var(var, limb);
+        }
+    }
+}
+
+/// The token of a literal argument of an error, see [`require::error_data`]
+fn token(expr: &Expression) -> Option<Token> {
+    match expr {
+        Expression::BoolLiteral { value, .. } => Some(Token::Bool(*value)),
+        Expression::NumberLiteral { ty: ast::Type::Int(_), value, .. } => {
+            Some(Token::Int(value.clone()))
+        }
+        Expression::NumberLiteral { ty: ast::Type::Uint(_), value, .. } => {
+            Some(Token::Uint(value.clone()))
+        }
+        Expression::BytesLiteral { ty: ast::Type::Bytes(_), value, .. } => {
+            Some(Token::FixedBytes(value.clone()))
+        }
+        Expression::BytesLiteral {
+            ty: ast::Type::String | ast::Type::DynamicBytes, value, ..
+        } => Some(Token::Bytes(value.clone())),
+        _ => None,
+    }
+}
+
+/// Whether the expression is `msg.data`
+fn is_calldata(expr: &Expression) -> bool {
+    matches!(expr, Expression::Builtin { kind: Builtin::Calldata, .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift::{
+        codegen::ir::{ExternalName, GlobalValueData},
+        module::{Linkage, Module},
+    };
+
+    use super::*;
+    use crate::{
+        abi::ethereum,
+        codegen::{
+            mangle::Symbol,
+            require::error_message,
+            tests::{analyze, calls, import_host, try_build, CONFIG},
+            Codegen, CodegenError, CodegenOptions,
+        },
+    };
+
+    /// Lower the function of the source with the name, with the layout of
+    /// its contract and the code of the contracts it creates, given by name
+    /// and size, giving its verified IR
+    fn lower(source: &str, name: &str, codes: &[(&str, usize)]) -> Result<String, EmitterError> {
+        Ok(lower_with_constants(source, name, codes)?.0)
+    }
+
+    /// The IR of the function, with the constant data it defines, the symbol
+    /// `constantN` standing for the `N`th
+    fn lower_with_constants(
+        source: &str,
+        name: &str,
+        codes: &[(&str, usize)],
+    ) -> Result<(String, Vec<Vec<u8>>), EmitterError> {
+        let ctx = analyze(source);
+        let mut constants = Vec::new();
+        let function_no = ctx
+            .functions
+            .iter()
+            .position(|func| func.id.name == name && func.has_body)
+            .expect("a function");
+        let func = &ctx.functions[function_no];
+
+        let signature = signature(func, CONFIG.default_call_conv, types::I64);
+        let ir = try_build(signature, |builder| {
+            let host = import_host(builder);
+            let codes = codes
+                .iter()
+                .map(|&(name, size)| {
+                    let code = builder.create_global_value(GlobalValueData::Symbol {
+                        name: ExternalName::testcase(format!("{name}::code")),
+                        offset: 0.into(),
+                        colocated: true,
+                        tls: false,
+                    });
+                    (name.to_string(), (code, size))
+                })
+                .collect();
+
+            let mut lowering = FunctionLowering::new(builder, CONFIG, &host, &ctx, false)
+                .with_codes(codes)
+                .with_constants(|bytes, func| {
+                    let index =
+                        constants.iter().position(|data| data == bytes).unwrap_or_else(|| {
+                            constants.push(bytes.to_vec());
+                            constants.len() - 1
+                        });
+                    Ok(func.create_global_value(GlobalValueData::Symbol {
+                        name: ExternalName::testcase(format!("constant{index}")),
+                        offset: 0.into(),
+                        colocated: true,
+                        tls: false,
+                    }))
+                });
+            if let Some(contract_no) = func.contract_no {
+                lowering = lowering.with_contract(contract_no);
+            }
+            lowering.lower(function_no)
+        })?;
+        Ok((ir.display().to_string(), constants))
+    }
+
+    #[test]
+    fn test_lower_arithmetic() {
+        let ir = lower(
+            r#"function f(uint8 a, int256 b) pure returns (uint16, int256) {
+                uint16 c = a * 2 + 1;
+                int d = -b / 3 % b;
+                return (c << 1, d >> 2);
+            }"#,
+            "f",
+            &[],
+        )
+        .unwrap();
+
+        // The limbs of two words in, and a pointer to the results
+        assert!(ir.contains("(i64, i64, i64, i64, i64, i64, i64, i64, i64) system_v"), "{ir}");
+        assert!(ir.contains("int_ovf"));
+        assert!(ir.contains("int_divz"));
+        assert!(ir.contains("%word_op"), "the division calls the host:\n{ir}");
+
+        // Wrapping arithmetic does not trap
+        let ir = lower(
+            "function f(uint8 a) pure returns (uint8) { unchecked { return a * 3 - 7; } }",
+            "f",
+            &[],
+        )
+        .unwrap();
+        assert!(!ir.contains("int_ovf"));
+
+        // The square of the base is checked as the product of the result
+        let ir = lower(
+            "function f(uint a, uint8 b, int16 c) pure returns (uint, int16) { return (a ** 2, c ** b); }",
+            "f",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(ir.matches("int_ovf").count(), 4, "{ir}");
+        assert_eq!(calls(&ir, "word_op"), 2, "{ir}");
+    }
+
+    #[test]
+    fn test_lower_control_flow() {
+        let ir = lower(
+            r#"function f(uint n) pure returns (uint total) {
+                for (uint i = 0; i < n; i++) {
+                    if (i % 2 == 0 && i != 4) {
+                        continue;
+                    }
+                    total += i;
+                    if (total > 100) break;
+                }
+                uint j = n;
+                while (j > 0) { j--; }
+                do { j = j + 1; } while (!(j >= 3) || j == 5);
+            }"#,
+            "f",
+            &[],
+        )
+        .unwrap();
+        assert!(ir.contains("brif"));
+
+        // Jumps out of nested loops, and returns in the middle of them
+        let ir = lower(
+            r#"function g(uint n) pure returns (uint) {
+                uint found;
+                for (;;) {
+                    uint i = 0;
+                    do {
+                        i++;
+                        if (i == 2) continue;
+                        else if (i > n) break;
+                        while (true) {
+                            if (found > 10) return found;
+                            found += i;
+                            break;
+                        }
+                    } while (i < 5);
+                    if (found == 0) { break; } else { n--; }
+                }
+                return found;
+            }"#,
+            "g",
+            &[],
+        )
+        .unwrap();
+        // Both return statements, and the end of the body
+        assert_eq!(ir.lines().filter(|line| line.trim() == "return").count(), 3, "{ir}");
+
+        // A router jumps to its branch
+        let ir = lower(
+            r#"function route(uint op) pure returns (uint) {
+                if (op == 0) return 10;
+                else if (op == 1) return 11;
+                else if (op == 2) { op += 12; }
+                else if (op == 3) return 13;
+                else return 0;
+                return op;
+            }"#,
+            "route",
+            &[],
+        )
+        .unwrap();
+        assert!(ir.contains("br_table"), "{ir}");
+        assert!(!ir.contains("icmp eq"), "{ir}");
+    }
+
+    #[test]
+    fn test_lower_external_function() {
+        let ir = lower(
+            r#"contract C {
+                function g(int8 x, bool flag) public pure returns (int16 y) {
+                    y = flag ? x : -x;
+                }
+            }"#,
+            "g",
+            &[],
+        )
+        .unwrap();
+        // The parameters are checked, the conditional merges the options
+        assert_eq!(calls(&ir, "revert"), 1, "{ir}");
+        assert!(ir.contains("brif"), "{ir}");
+
+        let ir = lower(
+            r#"contract C {
+                function g(int8 x, address to) public pure returns (int16 y, address) {
+                    y = x;
+                    y -= 1;
+                    return (y, to);
+                }
+            }"#,
+            "g",
+            &[],
+        )
+        .unwrap();
+
+        // A pointer to each parameter, the results go through the host
+        assert!(ir.contains("function u0:0(i64, i64) system_v {"), "{ir}");
+        assert!(ir.contains("%return"));
+        assert!(ir.contains("stack_addr.i64"));
+    }
+
+    #[test]
+    fn test_lower_calldata() {
+        let ir = lower(
+            r#"contract C {
+                function f(uint i) public pure returns (bytes4, bytes1, uint) {
+                    bytes4 mask = bytes4(uint32(0xffff0000));
+                    return (msg.sig & mask, msg.data[i], msg.data.length);
+                }
+            }"#,
+            "f",
+            &[],
+        )
+        .unwrap();
+        assert!(ir.contains("%calldata_copy"), "{ir}");
+        assert!(ir.contains("%calldata_size"), "{ir}");
+        assert!(ir.contains("heap_oob"), "the index is checked:\n{ir}");
+    }
+
+    #[test]
+    fn test_lower_state_variables() {
+        let ir = lower(
+            r#"contract Counter {
+                uint count;
+                function increment(uint step) public returns (uint) {
+                    count += step;
+                    return count;
+                }
+            }"#,
+            "increment",
+            &[],
+        )
+        .unwrap();
+        // The variable is read for `+=` and for `return`
+        assert_eq!(calls(&ir, "storage_load"), 2, "{ir}");
+        assert_eq!(calls(&ir, "storage_store"), 1, "{ir}");
+
+        // A packed value keeps the others of its slot, and a local variable
+        // hides a state variable
+        let ir = lower(
+            r#"contract Lock {
+                address owner;
+                bool temporary locked;
+                int8 temporary depth;
+                address count;
+                function enter(address count) public returns (address) {
+                    locked = !locked;
+                    depth--;
+                    return count;
+                }
+            }"#,
+            "enter",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(calls(&ir, "storage_load") + calls(&ir, "storage_store"), 0, "{ir}");
+        assert_eq!(calls(&ir, "transient_load"), 4, "{ir}");
+        assert_eq!(calls(&ir, "transient_store"), 2, "{ir}");
+        assert!(ir.contains("int_ovf"), "{ir}");
+    }
+
+    #[test]
+    fn test_lower_modifiers() {
+        let source = r#"contract Counter {
+            uint count;
+            modifier counted(uint by) { count += by; _; count += 1; }
+            modifier twice() { _; _; }
+            function bump() public counted(2) twice returns (uint) {
+                return count;
+            }
+        }"#;
+        let ir = lower(source, "bump", &[]).unwrap();
+        // The body is lowered at each placeholder, between the updates
+        assert_eq!(calls(&ir, "storage_load"), 4, "{ir}");
+        assert_eq!(calls(&ir, "storage_store"), 2, "{ir}");
+        assert_eq!(calls(&ir, "return"), 1, "{ir}");
+    }
+
+    #[test]
+    fn test_lower_create() {
+        let source = r#"
+            contract Vault {
+                constructor(uint a, uint b) payable {}
+            }
+            contract Factory {
+                function make(uint128 amount) public payable returns (address) {
+                    return address(new Vault{value: amount}(amount, 7));
+                }
+            }
+        "#;
+        let ir = lower(source, "make", &[("Vault", 4)]).unwrap();
+        assert_eq!(calls(&ir, "create"), 1, "{ir}");
+        assert!(ir.contains("iconst.i32 64"), "{ir}");
+        // The call reverts when the contract is not created, as when the
+        // parameter is out of range
+        assert_eq!(calls(&ir, "revert"), 1, "{ir}");
+
+        let error = lower(source, "make", &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "creating `Vault` without its code is not supported by the code generator yet"
+        );
+        let error = lower(
+            r#"
+                contract Vault {}
+                contract Factory {
+                    function make() public { new Vault{salt: bytes32(0)}(); }
+                }
+            "#,
+            "make",
+            &[("Vault", 4)],
+        )
+        .unwrap_err();
+        assert!(matches!(error, EmitterError::Unsupported(..)), "{error}");
+    }
+
+    #[test]
+    fn test_lower_try_catch() {
+        let source = r#"
+            contract Child {
+                constructor(uint v) { require(v != 0, "zero"); }
+            }
+            contract Parent {
+                Child child;
+                uint code;
+                uint failures;
+                function make(uint v) public {
+                    try new Child(v) returns (Child c) {
+                        child = c;
+                    } catch Error(string memory reason) {
+                        failures += 1;
+                    } catch Panic(uint c) {
+                        code = c;
+                    } catch {
+                        failures += 2;
+                    }
+                }
+                function quiet(uint v) public {
+                    try new Child(v) returns (Child) {} catch Panic(uint c) { code = c; }
+                }
+            }
+        "#;
+        let ir = lower(source, "make", &[("Child", 4)]).unwrap();
+        assert_eq!(calls(&ir, "create"), 1, "{ir}");
+        // The data is copied to memory when the creation reverts
+        for name in ["return_data_size", "return_data_copy", "memory"] {
+            assert_eq!(calls(&ir, name), 1, "{name}: {ir}");
+        }
+        // `Error(string)` and `Panic(uint256)`
+        assert!(ir.contains("0x08c3_79a0"), "{ir}");
+        assert!(ir.contains("0x4e48_7b71"), "{ir}");
+        // Every clause catches, and the state variables are written in each
+        assert_eq!(calls(&ir, "revert"), 0, "{ir}");
+        assert_eq!(calls(&ir, "storage_store"), 4, "{ir}");
+
+        // Without a clause for other data, the call reverts with it
+        let ir = lower(source, "quiet", &[("Child", 4)]).unwrap();
+        assert_eq!(calls(&ir, "revert"), 1, "{ir}");
+        assert!(!ir.contains("0x08c3_79a0"), "{ir}");
+
+        // The values a call returns are decoded from its return data
+        let source = r#"
+            contract Child { function f() external returns (uint8, uint) { return (1, 2); } }
+            contract Parent {
+                uint total;
+                function ask(Child child) public {
+                    try child.f() returns (uint8 a, uint b) { total = a + b; } catch {}
+                }
+                function call(Child child) public returns (uint) {
+                    (, uint b) = child.f();
+                    return b;
+                }
+            }
+        "#;
+        let ir = lower(source, "ask", &[]).unwrap();
+        assert_eq!(calls(&ir, "call"), 1, "{ir}");
+        assert_eq!(calls(&ir, "return_data_copy"), 2, "{ir}");
+        assert_eq!(calls(&ir, "storage_store"), 1, "{ir}");
+        // The selector of `f()` ends the first word of the input
+        assert!(ir.contains("0x2612_1ff0"), "{ir}");
+    }
+
+    #[test]
+    fn test_lower_assembly() {
+        let source = r#"
+            contract Counter {
+                uint count;
+                function bump(uint by) public returns (uint total) {
+                    uint before = count;
+                    assembly {
+                        total := add(before, by)
+                        sstore(count.slot, total)
+                        mstore(0x80, total)
+                    }
+                }
+            }
+        "#;
+        let ir = lower(source, "bump", &[]).unwrap();
+        // The Solidity variables are read and assigned by the block
+        assert!(ir.contains("uadd_overflow"), "{ir}");
+        assert_eq!(calls(&ir, "storage_load"), 1, "{ir}");
+        assert_eq!(calls(&ir, "storage_store"), 1, "{ir}");
+        assert_eq!(calls(&ir, "memory"), 1, "{ir}");
+        assert!(ir.contains("heap_oob"), "{ir}");
+
+        let error = lower("contract C { function f() public { assembly { x := 1 } } }", "f", &[])
+            .unwrap_err();
+        assert_eq!(error.to_string(), "'x' is not declared");
+    }
+
+    #[test]
+    fn test_lower_revert() {
+        let source = r#"
+            error Insufficient(uint available, uint needed);
+            contract Vault {
+                uint balance;
+                function withdraw(uint amount) public {
+                    require(amount != 0, "zero");
+                    if (amount > balance) revert Insufficient(balance, amount);
+                    require(amount != 7);
+                    if (amount == 9) revert("nine");
+                    balance -= amount;
+                }
+            }
+        "#;
+        let (ir, constants) = lower_with_constants(source, "withdraw", &[]).unwrap();
+
+        // Each reverts with its own data, and the host does not return
+        assert_eq!(calls(&ir, "revert"), 4, "{ir}");
+        assert_eq!(ir.matches("trap user1").count(), 4, "{ir}");
+        // `Error(string)` and the reasons, encoded at compile time as constants
+        assert_eq!(constants, [error_message("zero"), error_message("nine")]);
+        assert_eq!(ir.matches("symbol_value").count(), 2, "{ir}");
+        // The selector of the custom error, then the values of its arguments
+        let selector = u32::from_be_bytes(ethereum::selector("Insufficient(uint256,uint256)"));
+        let selector = format!("iconst.i64 0x{:04x}_{:04x}", selector >> 16, selector & 0xffff);
+        assert!(ir.contains(&selector), "{ir}");
+        // Both lengths of the reasons, then of the custom error
+        assert!(ir.contains("iconst.i32 100"), "{ir}");
+        assert!(ir.contains("iconst.i32 68"), "{ir}");
+    }
+
+    #[test]
+    fn test_lower_scratch_buffers() {
+        let source = r#"
+            error Small(uint a);
+            error Large(uint a, uint b, uint c);
+            contract Vault {
+                function withdraw(uint a, uint b) public pure returns (uint) {
+                    if (a < 3) revert Small(a);
+                    if (a > b) revert Large(a, b, a + b);
+                    if (b == 9) revert Small(b);
+                    return a;
+                }
+            }
+        "#;
+        let ir = lower(source, "withdraw", &[]).unwrap();
+
+        // The data of the errors and the results share a slot, grown for the
+        // selector and the arguments of `Large`
+        assert_eq!(calls(&ir, "revert"), 3, "{ir}");
+        assert_eq!(ir.matches("explicit_slot").count(), 1, "{ir}");
+        assert!(ir.contains("explicit_slot 128"), "{ir}");
+    }
+
+    #[test]
+    fn test_lower_errors() {
+        let unsupported =
+            |source, name| matches!(lower(source, name, &[]), Err(EmitterError::Unsupported(..)));
+        assert!(unsupported("function f(string memory s) pure {}", "f"));
+    }
+
+    #[test]
+    fn test_define_functions() {
+        let source = r#"
+            function helper(uint a, uint b) pure returns (uint) { return a * b + 1; }
+            contract Token {
+                function total(uint a) public pure returns (uint) { return a - 1; }
+                function name() public pure returns (string memory n) {}
+                function check(bool b) internal pure returns (bool) { return !b; }
+            }
+        "#;
+
+        // Nothing calls the functions, keep them
+        let options = CodegenOptions { dce: false, ..Default::default() };
+        let ctx = analyze(source);
+        let mut generator = Codegen::with_options(&options).unwrap();
+
+        // The function returning a string cannot be lowered, which fails the
+        // generation once the others are lowered
+        let Err(CodegenError::Unsupported(diagnostics)) = generator.gen(&ctx) else {
+            panic!("the generation does not fail");
+        };
+        let [error] = diagnostics.errors()[..] else { panic!("{diagnostics:?}") };
+        assert_eq!(error.message, "the type `string` is not supported by the code generator yet");
+        assert_eq!(error.notes[0].message, "function 'name' cannot be generated");
+
+        let mut exports = generator
+            .module
+            .declarations()
+            .get_functions()
+            .filter(|(_, decl)| decl.linkage == Linkage::Export)
+            .filter_map(|(_, decl)| decl.name.clone())
+            .collect::<Vec<_>>();
+        exports.sort();
+
+        let symbol = |contract, name, signature| {
+            Symbol::new(contract, pt::FunctionTy::Function, name, signature).mangle()
+        };
+        let mut expected = vec![
+            symbol(None, "helper", "helper(uint256,uint256)"),
+            symbol(Some("Token"), "total", "total(uint256)"),
+            symbol(Some("Token"), "check", "check(bool)"),
+            // The default constructor, which is the init function, see `init`
+            Symbol::new(Some("Token"), pt::FunctionTy::Constructor, "", "").mangle(),
+        ];
+        expected.sort();
+        assert_eq!(exports, expected);
+
+        assert_eq!(generator.ir.matches("function ").count(), 4);
+        assert!(!generator.finish().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Initialization code of the contracts.
+//!
+//! A contract is initialized when deployed by its init function, its
+//! constructor, which first calls the function assigning the initializers of
+//! the state variables, so `uint count = 1; constructor() { count++; }` runs
+//! `count = 1; count++;`. The initializers of the bases come first, in the
+//! order of the linearization of the contract, see
+//! [`Context::contract_bases`]. They are lowered in a function of their own,
+//! see [`Symbol::initializers`](super::mangle::Symbol::initializers), so that
+//! they are generated, or fail to be, apart from the body of the constructor.
+//! A contract without a constructor has the default constructor the semantic
+//! analysis adds. The constructor is recorded in
+//! [`Contract::initializer`](crate::semantic::ast::Contract) by
+//! [`gen_contracts`](super::gen_contracts).
+//!
+//! Constants are not stored, and immutables are not lowered yet, so their
+//! initializers are left out, as are the bodies of the constructors of the
+//! bases.
+
+use crate::semantic::{
+    ast::{Expression, Type},
+    context::Context,
+};
+
+/// The assignments of the initializers of the state variables of the
+/// contract and its bases, in the order they run
+pub fn initializers(contract_no: usize, ctx: &Context) -> Vec<Expression> {
+    let mut initializers = Vec::new();
+
+    for base_no in ctx.contract_bases(contract_no) {
+        for (var_no, var) in ctx.contracts[base_no].variables.iter().enumerate() {
+            let Some(initializer) = &var.initializer else {
+                continue;
+            };
+            if var.constant || var.immutable {
+                continue;
+            }
+
+            let left = Expression::StorageVariable {
+                loc: var.loc,
+                ty: Type::StorageRef(false, Box::new(var.ty.clone())),
+                contract_no: base_no,
+                var_no,
+            };
+            initializers.push(Expression::Assign {
+                loc: var.loc,
+                ty: var.ty.clone(),
+                left: Box::new(left),
+                right: Box::new(initializer.clone()),
+            });
+        }
+    }
+
+    initializers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initializers() {
+        let source = r#"
+            contract Base { uint base = 1; }
+            contract Other { bool other = true; }
+            contract Token is Base, Other {
+                uint public supply = 100;
+                uint constant CAP = 5;
+                address immutable owner = address(0);
+                uint count;
+                constructor(uint extra) { supply += extra; }
+            }
+            interface IToken { function supply() external view returns (uint); }
+        "#;
+        let ctx = super::super::tests::analyze(source);
+        let assigned = |name: &str| {
+            let contract_no = ctx.contracts.iter().position(|c| c.id.name == name).unwrap();
+            initializers(contract_no, &ctx)
+                .iter()
+                .map(|assign| {
+                    let Expression::Assign { left, .. } = assign else { unreachable!() };
+                    let Expression::StorageVariable { contract_no, var_no, .. } = left.as_ref()
+                    else {
+                        unreachable!()
+                    };
+                    ctx.contracts[*contract_no].variables[*var_no].name.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(assigned("Base"), ["base"]);
+        assert_eq!(assigned("Token"), ["base", "other", "supply"]);
+        assert!(assigned("IToken").is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The disassembly of the generated functions, for `--emit asm`.
+//!
+//! Cranelift keeps the text of the machine code of a function when asked to,
+//! as the final instructions of its backend after register allocation. Each
+//! function of the listing is headed by the Solidity it comes from, its
+//! demangled name and the file and line of its definition.
+
+use std::fmt;
+
+use crate::{parser::ast as pt, semantic::context::Context};
+
+use super::mangle::demangle;
+
+/// Where a generated function is defined in the sources
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub file: String,
+    /// One-based line
+    pub line: usize,
+}
+
+impl Origin {
+    /// The origin of a definition, `None` for builtins and generated code
+    pub fn new(loc: &pt::Loc, ctx: &Context) -> Option<Self> {
+        let file = ctx.files.get(loc.no()?)?;
+        let (line, _) = file.line_column(loc.start());
+        Some(Self { file: file.path.display().to_string(), line: line + 1 })
+    }
+}
+
+/// The disassembly of a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedFunction {
+    /// The mangled symbol of the function
+    pub symbol: String,
+    pub origin: Option<Origin>,
+    pub asm: String,
+}
+
+/// The disassembly of the functions of a module, in the order they were
+/// defined
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Listing {
+    pub functions: Vec<ListedFunction>,
+}
+
+impl Listing {
+    pub fn push(&mut self, symbol: String, origin: Option<Origin>, asm: String) {
+        self.functions.push(ListedFunction { symbol, origin, asm });
+    }
+
+    pub fn extend(&mut self, other: Listing) {
+        self.functions.extend(other.functions);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            match demangle(&function.symbol) {
+                Some(symbol) => write!(f, "; {symbol}")?,
+                None => write!(f, "; {}", function.symbol)?,
+            }
+            match &function.origin {
+                Some(Origin { file, line }) => writeln!(f, " at {file}:{line}")?,
+                None => writeln!(f)?,
+            }
+
+            writeln!(f, "{}:", function.symbol)?;
+            // Unwind directives describe the frame, they are not instructions
+            let lines = function
+                .asm
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with("unwind "));
+            for line in lines {
+                writeln!(f, "  {line}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{dispatch::Dispatcher, Codegen, CodegenOptions};
+
+    #[test]
+    fn test_list_dispatcher() {
+        let dispatcher = Dispatcher {
+            contract: "Token".into(),
+            origin: Some(Origin { file: "token.sol".into(), line: 3 }),
+            functions: Vec::new(),
+            fallback: None,
+            receive: None,
+        };
+
+        let mut generator = Codegen::new();
+        generator.gen_dispatcher(&dispatcher).unwrap();
+        assert!(generator.listing().is_empty());
+
+        let options = CodegenOptions { disasm: true, ..Default::default() };
+        let mut generator = Codegen::with_options(&options).unwrap();
+        generator.gen_dispatcher(&dispatcher).unwrap();
+
+        let listing = generator.listing().to_string();
+        let mut lines = listing.lines();
+        assert_eq!(lines.next(), Some("; Token.dispatch at token.sol:3"));
+        assert_eq!(lines.next(), Some("Token::dispatch:"));
+        let asm = lines.collect::<Vec<_>>();
+        assert!(asm.len() > 10, "{listing}");
+        assert!(asm.iter().all(|line| line.starts_with("  ") && !line.contains("unwind")));
+    }
+}
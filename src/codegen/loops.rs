@@ -0,0 +1,456 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Analysis of the number of iterations of loops.
+//!
+//! A `for` loop runs a constant number of times when it declares an integer
+//! counter, compares it against a constant, steps it by a constant which keeps
+//! it in the range of its type, and its body neither writes the counter nor
+//! leaves the loop early; such loops can be unrolled.
+//! The bound of other loops may depend on the length of an array or on a value
+//! given by the caller, who may then make the loop run out of gas.
+
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::{
+    parser::ast::{self as pt, CatchClause, Expression, Loc, Statement},
+    semantic::ast::Type,
+};
+
+/// What the number of iterations of a loop depends on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bound {
+    /// The loop runs this many times
+    Constant(u64),
+    /// The length of an array, at the location of the `.length` access
+    ArrayLength(Loc),
+    /// A parameter given by the caller, at the location of its use
+    Input(Loc, String),
+    /// Anything else, e.g. a state variable or a condition in the body
+    Unknown,
+}
+
+/// The comparison of a loop counter with its limit, the counter on the left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Comparison {
+    Less,
+    LessEqual,
+    More,
+    MoreEqual,
+    NotEqual,
+}
+
+/// The bound of a loop statement, where `inputs` are the names of the
+/// parameters a caller can choose
+pub fn bound(stmt: &Statement, inputs: &[&str]) -> Bound {
+    if let Some(count) = trip_count(stmt) {
+        return Bound::Constant(count);
+    }
+
+    let cond = match stmt {
+        Statement::For(_, _, Some(cond), ..) => cond.as_ref(),
+        Statement::While(_, cond, _) | Statement::DoWhile(_, _, cond) => cond,
+        _ => return Bound::Unknown,
+    };
+
+    let mut bound = Bound::Unknown;
+    visit(cond, &mut |expr| match expr {
+        Expression::MemberAccess(loc, _, member) if member.name == "length" => {
+            bound = Bound::ArrayLength(*loc);
+        }
+        Expression::Variable(id)
+            if bound == Bound::Unknown && inputs.contains(&id.name.as_str()) =>
+        {
+            bound = Bound::Input(id.loc, id.name.clone());
+        }
+        _ => (),
+    });
+
+    bound
+}
+
+/// The number of iterations of a `for` loop, if it is constant
+pub fn trip_count(stmt: &Statement) -> Option<u64> {
+    let Statement::For(_, Some(init), Some(cond), Some(next), body) = stmt else {
+        return None;
+    };
+    let Statement::VariableDefinition(_, decl, Some(start)) = init.as_ref() else {
+        return None;
+    };
+    let counter = decl.name.as_ref()?.name.as_str();
+    let (min, max) = range(&decl.ty)?;
+
+    let start = constant(start)?;
+    let (comparison, limit) = comparison(cond, counter)?;
+    let step = step(next, counter)?;
+
+    if let Some(body) = body {
+        if !keeps_counter(body, counter) {
+            return None;
+        }
+    }
+
+    let count = count(&start, comparison, &limit, &step)?;
+
+    // The step after the last iteration still runs, and reverts if it takes
+    // the counter out of the range of its type, e.g. `i++` on a `uint8` 255
+    let last = &start + &count * &step;
+    if count.is_positive() && (last < min || last > max) {
+        return None;
+    }
+
+    count.to_u64()
+}
+
+/// The loops in a statement, outer loops before the loops they contain
+pub fn loops(stmt: &Statement) -> Vec<&Statement> {
+    let mut loops = Vec::new();
+    let mut worklist = vec![stmt];
+
+    while let Some(stmt) = worklist.pop() {
+        if matches!(stmt, Statement::For(..) | Statement::While(..) | Statement::DoWhile(..)) {
+            loops.push(stmt);
+        }
+        worklist.extend(statements(stmt).into_iter().rev());
+    }
+
+    loops
+}
+
+/// The number of iterations of a loop counting from `start` by `step` while
+/// the comparison with `limit` holds, if it ends before the counter overflows
+pub(super) fn count(
+    start: &BigInt,
+    comparison: Comparison,
+    limit: &BigInt,
+    step: &BigInt,
+) -> Option<BigInt> {
+    let runs = match comparison {
+        Comparison::Less => start < limit,
+        Comparison::LessEqual => start <= limit,
+        Comparison::More => start > limit,
+        Comparison::MoreEqual => start >= limit,
+        Comparison::NotEqual => start != limit,
+    };
+    if !runs {
+        return Some(BigInt::zero());
+    }
+
+    // A counter stepping away from the limit runs until it overflows
+    let distance = match comparison {
+        Comparison::Less | Comparison::LessEqual if step.is_positive() => limit - start,
+        Comparison::More | Comparison::MoreEqual if step.is_negative() => start - limit,
+        Comparison::NotEqual => (limit - start) * step.signum(),
+        _ => return None,
+    };
+    let step = step.abs();
+
+    match comparison {
+        Comparison::Less | Comparison::More => Some((&distance + &step - 1) / &step),
+        Comparison::LessEqual | Comparison::MoreEqual => Some(&distance / &step + 1),
+        Comparison::NotEqual if distance.is_positive() && (&distance % &step).is_zero() => {
+            Some(&distance / &step)
+        }
+        Comparison::NotEqual => None,
+    }
+}
+
+/// The smallest and largest values of the type of a counter, if it is an
+/// integer type
+fn range(ty: &Expression) -> Option<(BigInt, BigInt)> {
+    let ty = match ty {
+        Expression::Type(_, pt::Type::Uint(bits)) => Type::Uint(*bits),
+        Expression::Type(_, pt::Type::Int(bits)) => Type::Int(*bits),
+        Expression::Variable(id) => Type::elementary(&id.name)?,
+        _ => return None,
+    };
+
+    type_range(&ty)
+}
+
+/// The smallest and largest values of an integer type
+pub(super) fn type_range(ty: &Type) -> Option<(BigInt, BigInt)> {
+    match *ty {
+        Type::Uint(bits) => Some((BigInt::zero(), (BigInt::from(1) << bits) - 1)),
+        Type::Int(bits) => {
+            let half = BigInt::from(1) << (bits - 1);
+            Some((-&half, half - 1))
+        }
+        _ => None,
+    }
+}
+
+/// The comparison of the counter with a constant in the condition
+fn comparison(cond: &Expression, counter: &str) -> Option<(Comparison, BigInt)> {
+    let (comparison, left, right) = match cond {
+        Expression::Less(_, left, right) => (Comparison::Less, left, right),
+        Expression::LessEqual(_, left, right) => (Comparison::LessEqual, left, right),
+        Expression::More(_, left, right) => (Comparison::More, left, right),
+        Expression::MoreEqual(_, left, right) => (Comparison::MoreEqual, left, right),
+        Expression::NotEqual(_, left, right) => (Comparison::NotEqual, left, right),
+        Expression::Parenthesis(_, cond) => return self::comparison(cond, counter),
+        _ => return None,
+    };
+
+    if is_variable(left, counter) {
+        Some((comparison, constant(right)?))
+    } else if is_variable(right, counter) {
+        let flipped = match comparison {
+            Comparison::Less => Comparison::More,
+            Comparison::LessEqual => Comparison::MoreEqual,
+            Comparison::More => Comparison::Less,
+            Comparison::MoreEqual => Comparison::LessEqual,
+            Comparison::NotEqual => Comparison::NotEqual,
+        };
+        Some((flipped, constant(left)?))
+    } else {
+        None
+    }
+}
+
+/// The constant added to the counter after each iteration
+fn step(next: &Expression, counter: &str) -> Option<BigInt> {
+    let step = match next {
+        Expression::PostIncrement(_, expr) | Expression::PreIncrement(_, expr)
+            if is_variable(expr, counter) =>
+        {
+            BigInt::from(1)
+        }
+        Expression::PostDecrement(_, expr) | Expression::PreDecrement(_, expr)
+            if is_variable(expr, counter) =>
+        {
+            BigInt::from(-1)
+        }
+        Expression::AssignAdd(_, expr, step) if is_variable(expr, counter) => constant(step)?,
+        Expression::AssignSubtract(_, expr, step) if is_variable(expr, counter) => -constant(step)?,
+        _ => return None,
+    };
+
+    (!step.is_zero()).then_some(step)
+}
+
+fn constant(expr: &Expression) -> Option<BigInt> {
+    match expr {
+        Expression::NumberLiteral(_, integer, exp, None) if exp.is_empty() => {
+            integer.replace('_', "").parse().ok()
+        }
+        Expression::HexNumberLiteral(_, hex, None) => {
+            BigInt::parse_bytes(hex.trim_start_matches("0x").replace('_', "").as_bytes(), 16)
+        }
+        Expression::Parenthesis(_, expr) => constant(expr),
+        _ => None,
+    }
+}
+
+fn is_variable(expr: &Expression, name: &str) -> bool {
+    matches!(expr, Expression::Variable(id) if id.name == name)
+}
+
+/// Whether the body of a loop runs to its end without writing the counter.
+/// Nested loops may break out of themselves, and Yul could write anything.
+fn keeps_counter(body: &Statement, counter: &str) -> bool {
+    let mut keeps = true;
+    let mut worklist = vec![(body, false)];
+
+    while let Some((stmt, nested)) = worklist.pop() {
+        match stmt {
+            Statement::Break(_) if !nested => keeps = false,
+            Statement::Return(..) | Statement::Assembly { .. } => keeps = false,
+            Statement::VariableDefinition(_, decl, _) => {
+                keeps &= decl.name.as_ref().is_none_or(|id| id.name != counter)
+            }
+            _ => (),
+        }
+
+        for expr in expressions(stmt) {
+            visit(expr, &mut |expr| match expr {
+                Expression::Assign(_, target, _) |
+                Expression::AssignOr(_, target, _) |
+                Expression::AssignAnd(_, target, _) |
+                Expression::AssignXor(_, target, _) |
+                Expression::AssignShiftLeft(_, target, _) |
+                Expression::AssignShiftRight(_, target, _) |
+                Expression::AssignAdd(_, target, _) |
+                Expression::AssignSubtract(_, target, _) |
+                Expression::AssignMultiply(_, target, _) |
+                Expression::AssignDivide(_, target, _) |
+                Expression::AssignModulo(_, target, _) |
+                Expression::PreIncrement(_, target) |
+                Expression::PreDecrement(_, target) |
+                Expression::PostIncrement(_, target) |
+                Expression::PostDecrement(_, target) |
+                Expression::Delete(_, target) => {
+                    let mut writes = false;
+                    visit(target, &mut |expr| writes |= is_variable(expr, counter));
+                    keeps &= !writes;
+                }
+                _ => (),
+            });
+        }
+
+        let nested = nested ||
+            matches!(stmt, Statement::For(..) | Statement::While(..) | Statement::DoWhile(..));
+        worklist.extend(statements(stmt).into_iter().map(|stmt| (stmt, nested)));
+    }
+
+    keeps
+}
+
+/// The statements directly inside a statement
+pub(crate) fn statements(stmt: &Statement) -> Vec<&Statement> {
+    match stmt {
+        Statement::Block { statements, .. } => statements.iter().collect(),
+        Statement::If(_, _, then, otherwise) => {
+            [Some(then.as_ref()), otherwise.as_deref()].into_iter().flatten().collect()
+        }
+        Statement::While(_, _, body) | Statement::DoWhile(_, body, _) => vec![body],
+        Statement::For(_, init, _, _, body) => {
+            [init.as_deref(), body.as_deref()].into_iter().flatten().collect()
+        }
+        Statement::Try(_, _, returns, catches) => returns
+            .iter()
+            .map(|(_, body)| body.as_ref())
+            .chain(catches.iter().map(|catch| match catch {
+                CatchClause::Simple(.., body) | CatchClause::Named(.., body) => body,
+            }))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The expressions directly inside a statement
+pub(crate) fn expressions(stmt: &Statement) -> Vec<&Expression> {
+    match stmt {
+        Statement::Args(_, args) | Statement::RevertNamedArgs(_, _, args) => {
+            args.iter().map(|arg| &arg.expr).collect()
+        }
+        Statement::If(_, cond, ..) |
+        Statement::While(_, cond, _) |
+        Statement::DoWhile(_, _, cond) |
+        Statement::Expression(_, cond) |
+        Statement::Emit(_, cond) => vec![cond],
+        Statement::VariableDefinition(_, _, expr) | Statement::Return(_, expr) => {
+            expr.iter().collect()
+        }
+        Statement::For(_, _, cond, next, _) => {
+            [cond.as_deref(), next.as_deref()].into_iter().flatten().collect()
+        }
+        Statement::Revert(_, _, args) => args.iter().collect(),
+        Statement::Try(_, expr, ..) => vec![expr],
+        _ => Vec::new(),
+    }
+}
+
+/// Call `f` on an expression and all the expressions inside it
+pub(crate) fn visit<'a>(expr: &'a Expression, f: &mut impl FnMut(&'a Expression)) {
+    f(expr);
+
+    match expr {
+        Expression::MemberAccess(_, expr, _) => visit(expr, f),
+        Expression::FunctionCall(_, func, args) => {
+            visit(func, f);
+            for arg in args {
+                visit(arg, f);
+            }
+        }
+        Expression::NamedFunctionCall(_, func, args) => {
+            visit(func, f);
+            for arg in args {
+                visit(&arg.expr, f);
+            }
+        }
+        Expression::ArraySubscript(_, array, index) => {
+            visit(array, f);
+            if let Some(index) = index {
+                visit(index, f);
+            }
+        }
+        Expression::ArraySlice(_, array, from, to) => {
+            visit(array, f);
+            for expr in [from, to].into_iter().flatten() {
+                visit(expr, f);
+            }
+        }
+        Expression::ConditionalOperator(_, cond, left, right) => {
+            for expr in [cond, left, right] {
+                visit(expr, f);
+            }
+        }
+        Expression::ArrayLiteral(_, exprs) => {
+            for expr in exprs {
+                visit(expr, f);
+            }
+        }
+        Expression::List(_, params) => {
+            for (_, param) in params {
+                if let Some(param) = param {
+                    visit(&param.ty, f);
+                }
+            }
+        }
+        expr => {
+            let (left, right) = expr.components();
+            for expr in left.into_iter().chain(right) {
+                visit(expr, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_statement;
+
+    #[test]
+    fn test_loop_bounds() {
+        let bound = |source: &str| bound(&parse_statement(source, 0).unwrap(), &["n"]);
+
+        assert_eq!(bound("for (uint i = 0; i < 10; i++) {}"), Bound::Constant(10));
+        assert_eq!(bound("for (uint i = 1; i <= 10; i += 3) {}"), Bound::Constant(4));
+        assert_eq!(bound("for (uint i = 10; 0 < i; --i) { x += i; }"), Bound::Constant(10));
+        assert_eq!(bound("for (uint i = 0; i != 8; i += 2) {}"), Bound::Constant(4));
+        assert_eq!(bound("for (uint i = 5; i < 2; i++) {}"), Bound::Constant(0));
+
+        // The counter would go below zero, or never reach the limit
+        assert_eq!(bound("for (uint i = 4; i >= 0; i--) {}"), Bound::Unknown);
+        assert_eq!(bound("for (uint i = 0; i != 7; i += 2) {}"), Bound::Unknown);
+        assert_eq!(bound("for (uint i = 0; i < 10; i--) {}"), Bound::Unknown);
+
+        // The step after the last iteration overflows the type of the counter
+        assert_eq!(bound("for (uint8 i = 250; i <= 255; i++) {}"), Bound::Unknown);
+        assert_eq!(bound("for (uint8 i = 250; i < 255; i++) {}"), Bound::Constant(5));
+        assert_eq!(bound("for (int8 i = 120; i <= 127; i += 4) {}"), Bound::Unknown);
+        assert_eq!(bound("for (int8 i = 124; i >= 0; i -= 4) {}"), Bound::Constant(32));
+        assert_eq!(bound("for (uint8 i = 0; i < 300; i++) {}"), Bound::Unknown);
+        assert_eq!(bound("for (bool i = 0; i < 3; i++) {}"), Bound::Unknown);
+
+        // The body writes the counter or leaves the loop
+        assert_eq!(bound("for (uint i = 0; i < 10; i++) { i += 1; }"), Bound::Unknown);
+        assert_eq!(bound("for (uint i = 0; i < 10; i++) { if (x) break; }"), Bound::Unknown);
+        assert_eq!(
+            bound("for (uint i = 0; i < 10; i++) { while (x) { break; } }"),
+            Bound::Constant(10)
+        );
+
+        let Bound::ArrayLength(_) = bound("for (uint i = 0; i < items.length; i++) {}") else {
+            panic!("expected an array length bound");
+        };
+        let Bound::Input(_, name) = bound("while (i < n) { i++; }") else {
+            panic!("expected an input bound");
+        };
+        assert_eq!(name, "n");
+    }
+}
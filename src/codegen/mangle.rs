@@ -0,0 +1,240 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Symbol names of the emitted functions.
+//!
+//! A function is named `<contract>::<function>__<selector>`, the selector being
+//! the hash of its canonical signature, so that overloads and functions of the
+//! same name in different contracts get distinct symbols. Free functions have
+//! no contract part. Constructors, fallback and receive functions are unique
+//! in a contract and are named after their kind, as in `Token::constructor`.
+//! So is the dispatcher of a contract, its external entry point, as in
+//! `Token::dispatch`, and the function assigning the initializers of its state
+//! variables, which its constructor calls, as in `Token::initializers`, see
+//! [`init`](super::init). The code of a contract, embedded in the contracts which
+//! create it, is the data symbol `Token::code`, its hash the data symbol
+//! `Token::code_hash`, see [`metadata`](super::metadata), and constant data
+//! the data symbol `constant__<hash>`, see [`constants`](super::constants).
+//!
+//! Identifiers cannot contain `:`, and the selector is always the last 8 hex
+//! digits, so a mangled name can be split back into its parts.
+
+use std::fmt;
+
+use crate::{
+    abi::ethereum,
+    parser::ast as pt,
+    semantic::{ast::Function, context::Context},
+};
+
+/// Separates the contract from the function
+const CONTRACT_SEPARATOR: &str = "::";
+
+/// Separates the function from the selector
+const SELECTOR_SEPARATOR: &str = "__";
+
+/// The name of the dispatcher of a contract
+const DISPATCHER: &str = "dispatch";
+
+/// The name of the initializers of the state variables of a contract
+const INITIALIZERS: &str = "initializers";
+
+/// The name of the code of a contract
+const CODE: &str = "code";
+
+/// The name of the hash of the code of a contract
+const CODE_HASH: &str = "code_hash";
+
+/// The names of the symbols of a contract which have no selector
+const SPECIAL_NAMES: &[&str] =
+    &["constructor", "fallback", "receive", DISPATCHER, INITIALIZERS, CODE, CODE_HASH];
+
+/// The parts of the symbol of an emitted function
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    pub contract: Option<String>,
+    /// The name of the function, or its kind when it has no name
+    pub name: String,
+    /// The selector of the signature, not set for constructors, fallback and
+    /// receive functions
+    pub selector: Option<[u8; 4]>,
+}
+
+impl Symbol {
+    /// The symbol of a function with the given canonical signature
+    pub fn new(contract: Option<&str>, ty: pt::FunctionTy, name: &str, signature: &str) -> Self {
+        let (name, selector) = match ty {
+            pt::FunctionTy::Constructor | pt::FunctionTy::Fallback | pt::FunctionTy::Receive => {
+                (ty.to_string(), None)
+            }
+            _ => (name.to_string(), Some(ethereum::selector(signature))),
+        };
+
+        Self { contract: contract.map(str::to_string), name, selector }
+    }
+
+    /// The symbol of the dispatcher of a contract
+    pub fn dispatcher(contract: &str) -> Self {
+        Self { contract: Some(contract.to_string()), name: DISPATCHER.to_string(), selector: None }
+    }
+
+    /// The symbol of the initializers of the state variables of a contract
+    pub fn initializers(contract: &str) -> Self {
+        Self {
+            contract: Some(contract.to_string()),
+            name: INITIALIZERS.to_string(),
+            selector: None,
+        }
+    }
+
+    /// The symbol of the code of a contract
+    pub fn code(contract: &str) -> Self {
+        Self { contract: Some(contract.to_string()), name: CODE.to_string(), selector: None }
+    }
+
+    /// The symbol of the hash of the code of a contract
+    pub fn code_hash(contract: &str) -> Self {
+        Self { contract: Some(contract.to_string()), name: CODE_HASH.to_string(), selector: None }
+    }
+
+    /// The symbol of a resolved function
+    pub fn function(func: &Function, ctx: &Context) -> Self {
+        let contract = func.contract_no.map(|no| ctx.contracts[no].id.name.as_str());
+        let signature = ethereum::signature(&func.id.name, &func.params, ctx);
+
+        Self::new(contract, func.ty, &func.id.name, &signature)
+    }
+
+    /// The name of the symbol in the object file
+    pub fn mangle(&self) -> String {
+        let mut symbol = String::new();
+
+        if let Some(contract) = &self.contract {
+            symbol.push_str(contract);
+            symbol.push_str(CONTRACT_SEPARATOR);
+        }
+
+        symbol.push_str(&self.name);
+
+        if let Some(selector) = &self.selector {
+            symbol.push_str(SELECTOR_SEPARATOR);
+            symbol.push_str(&hex(selector));
+        }
+
+        symbol
+    }
+}
+
+/// The function as it would be written in Solidity, with the selector
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(contract) = &self.contract {
+            write!(f, "{contract}.")?;
+        }
+
+        f.write_str(&self.name)?;
+
+        match &self.selector {
+            Some(selector) => write!(f, " (0x{})", hex(selector)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Split a mangled symbol into its parts, `None` when it was not produced by
+/// [`Symbol::mangle`]
+pub fn demangle(symbol: &str) -> Option<Symbol> {
+    let (contract, rest) = match symbol.split_once(CONTRACT_SEPARATOR) {
+        Some((contract, rest)) => (Some(contract), rest),
+        None => (None, symbol),
+    };
+
+    let selector = rest.rsplit_once(SELECTOR_SEPARATOR).and_then(|(name, selector)| {
+        let selector = parse_selector(selector)?;
+        Some((name, selector))
+    });
+
+    let (name, selector) = match selector {
+        Some((name, selector)) => (name, Some(selector)),
+        // Only the special functions of a contract go without a selector
+        None if contract.is_some() && SPECIAL_NAMES.contains(&rest) => (rest, None),
+        None => return None,
+    };
+
+    if !contract.is_none_or(is_identifier) || !is_identifier(name) {
+        return None;
+    }
+
+    Some(Symbol { contract: contract.map(str::to_string), name: name.to_string(), selector })
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$') &&
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+fn parse_selector(digits: &str) -> Option<[u8; 4]> {
+    if digits.len() != 8 {
+        return None;
+    }
+
+    let value = u32::from_str_radix(digits, 16).ok()?;
+    Some(value.to_be_bytes())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mangle() {
+        let transfer = Symbol::new(
+            Some("Token"),
+            pt::FunctionTy::Function,
+            "transfer",
+            "transfer(address,uint256)",
+        );
+        assert_eq!(transfer.mangle(), "Token::transfer__a9059cbb");
+        assert_eq!(transfer.to_string(), "Token.transfer (0xa9059cbb)");
+        assert_eq!(demangle(&transfer.mangle()), Some(transfer));
+
+        // Overloads get distinct names
+        let a = Symbol::new(None, pt::FunctionTy::Function, "f", "f(uint256)");
+        let b = Symbol::new(None, pt::FunctionTy::Function, "f", "f(bool)");
+        assert_ne!(a.mangle(), b.mangle());
+        assert_eq!(demangle(&a.mangle()), Some(a));
+
+        let constructor = Symbol::new(Some("Token"), pt::FunctionTy::Constructor, "", "");
+        assert_eq!(constructor.mangle(), "Token::constructor");
+        assert_eq!(demangle("Token::constructor"), Some(constructor));
+        assert_eq!(demangle("Token::dispatch"), Some(Symbol::dispatcher("Token")));
+        assert_eq!(demangle("Token::initializers"), Some(Symbol::initializers("Token")));
+        assert_eq!(demangle("Token::code"), Some(Symbol::code("Token")));
+        assert_eq!(demangle("Token::code_hash"), Some(Symbol::code_hash("Token")));
+
+        // A name with a double underscore is split at the selector
+        let symbol = demangle("a__b__00000001").unwrap();
+        assert_eq!((symbol.name.as_str(), symbol.selector), ("a__b", Some([0, 0, 0, 1])));
+
+        assert_eq!(demangle("f"), None);
+        assert_eq!(demangle("f__xyz"), None);
+        assert_eq!(demangle("C::f::g__00000000"), None);
+        assert_eq!(demangle("__hmt_storage_load"), None);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The memory of a call and its allocator.
+//!
+//! The `memory` host function gives each call a linear memory of
+//! [`MEMORY_SIZE`] bytes, zeroed when the call starts. It is laid out as by
+//! solc, so that inline assembly finds things where it expects them:
+//!
+//! - `0x00..0x40`: scratch space, e.g. for hashing
+//! - `0x40..0x60`: the free memory pointer, the offset of the first byte not allocated, as a big
+//!   endian word
+//! - `0x60..0x80`: the zero slot, which is never written
+//! - `0x80..`: the allocations
+//!
+//! Memory is never freed: an allocation bumps the free memory pointer by its
+//! size rounded up to a word, and traps with [`OUT_OF_MEMORY`] when it does
+//! not fit. Assembly may write past the free memory pointer, so allocations
+//! are zeroed. Strings and `bytes` are a length word followed by the data,
+//! arrays a length word followed by a word per element, and structs a word
+//! per field.
+
+use cranelift::{
+    codegen::{
+        ir::{FuncRef, GlobalValue},
+        isa::TargetFrontendConfig,
+    },
+    prelude::{types, FunctionBuilder, InstBuilder, IntCC, TrapCode, Value},
+};
+
+use crate::runtime::MEMORY_SIZE;
+
+use super::dispatch::{flags, WORD_SIZE};
+
+/// Offset of the free memory pointer
+pub const FREE_POINTER: i64 = 0x40;
+
+/// Offset of the zero slot
+pub const ZERO_SLOT: i64 = 0x60;
+
+/// Offset of the first allocation
+pub const HEAP_START: i64 = 0x80;
+
+/// Trap of an allocation beyond the memory
+pub const OUT_OF_MEMORY: TrapCode = TrapCode::unwrap_user(3);
+
+/// Emit the initialization of the memory, at the start of a call, giving its
+/// address
+pub fn emit_init(builder: &mut FunctionBuilder, big_endian: bool, memory: FuncRef) -> Value {
+    let call = builder.ins().call(memory, &[]);
+    let base = builder.inst_results(call)[0];
+
+    // The memory is zeroed, only the low limb of the word is set
+    let start = builder.ins().iconst(types::I64, HEAP_START);
+    store_i64(builder, big_endian, start, base, FREE_POINTER as i32 + 24);
+    base
+}
+
+/// Emit the address of the memory in a function which may run before it is
+/// initialized, setting the free memory pointer unless it already is
+pub fn emit_base(builder: &mut FunctionBuilder, big_endian: bool, memory: FuncRef) -> Value {
+    let call = builder.ins().call(memory, &[]);
+    let base = builder.inst_results(call)[0];
+
+    // The pointer is zero until set, as the memory is zeroed
+    let offset = load_i64(builder, big_endian, base, FREE_POINTER as i32 + 24);
+    let start = builder.ins().iconst(types::I64, HEAP_START);
+    let offset = builder.ins().select(offset, offset, start);
+    store_i64(builder, big_endian, offset, base, FREE_POINTER as i32 + 24);
+    base
+}
+
+/// Emit the allocation of `size` bytes, an `i64`, giving their address
+pub fn emit_alloc(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    big_endian: bool,
+    base: Value,
+    size: Value,
+) -> Value {
+    // A free memory pointer beyond 64 bits is beyond the memory too
+    let mut high = builder.ins().load(types::I64, flags(), base, FREE_POINTER as i32);
+    for offset in [8, 16] {
+        let limb = builder.ins().load(types::I64, flags(), base, FREE_POINTER as i32 + offset);
+        high = builder.ins().bor(high, limb);
+    }
+    builder.ins().trapnz(high, OUT_OF_MEMORY);
+    let offset = load_i64(builder, big_endian, base, FREE_POINTER as i32 + 24);
+
+    let padding = builder.ins().iconst(types::I64, WORD_SIZE - 1);
+    let size = builder.ins().uadd_overflow_trap(size, padding, OUT_OF_MEMORY);
+    let size = builder.ins().band_imm_s(size, !(WORD_SIZE - 1));
+    let end = builder.ins().uadd_overflow_trap(offset, size, OUT_OF_MEMORY);
+    let beyond = builder.ins().icmp_imm_u(IntCC::UnsignedGreaterThan, end, MEMORY_SIZE as i64);
+    builder.ins().trapnz(beyond, OUT_OF_MEMORY);
+    store_i64(builder, big_endian, end, base, FREE_POINTER as i32 + 24);
+
+    let offset = pointer(builder, config, offset);
+    let address = builder.ins().iadd(base, offset);
+    let zero = builder.ins().iconst(types::I8, 0);
+    let size = pointer(builder, config, size);
+    builder.call_memset(config, address, zero, size);
+    address
+}
+
+/// Emit the allocation of a string or `bytes` of `len` bytes, an `i64`,
+/// setting its length
+pub fn emit_alloc_bytes(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    big_endian: bool,
+    base: Value,
+    len: Value,
+) -> Value {
+    let word = builder.ins().iconst(types::I64, WORD_SIZE);
+    let size = builder.ins().uadd_overflow_trap(len, word, OUT_OF_MEMORY);
+    let address = emit_alloc(builder, config, big_endian, base, size);
+    store_i64(builder, big_endian, len, address, 24);
+    address
+}
+
+/// Emit the allocation of an array of `len` elements, an `i64`, setting its
+/// length
+pub fn emit_alloc_array(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    big_endian: bool,
+    base: Value,
+    len: Value,
+) -> Value {
+    let word = builder.ins().iconst(types::I64, WORD_SIZE);
+    let (size, overflow) = builder.ins().umul_overflow(len, word);
+    builder.ins().trapnz(overflow, OUT_OF_MEMORY);
+    let size = builder.ins().uadd_overflow_trap(size, word, OUT_OF_MEMORY);
+    let address = emit_alloc(builder, config, big_endian, base, size);
+    store_i64(builder, big_endian, len, address, 24);
+    address
+}
+
+/// Emit the allocation of a struct of `fields` fields
+pub fn emit_alloc_struct(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    big_endian: bool,
+    base: Value,
+    fields: usize,
+) -> Value {
+    let size = builder.ins().iconst(types::I64, WORD_SIZE * fields as i64);
+    emit_alloc(builder, config, big_endian, base, size)
+}
+
+/// Emit a string literal in memory, copied from its data in the module
+pub fn emit_string(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    big_endian: bool,
+    base: Value,
+    data: GlobalValue,
+    len: usize,
+) -> Value {
+    let len_value = builder.ins().iconst(types::I64, len as i64);
+    let address = emit_alloc_bytes(builder, config, big_endian, base, len_value);
+
+    let dest = builder.ins().iadd_imm_u(address, WORD_SIZE);
+    let src = builder.ins().symbol_value(config.pointer_type(), data);
+    let size = builder.ins().iconst(config.pointer_type(), len as i64);
+    builder.call_memcpy(config, dest, src, size);
+    address
+}
+
+/// Load the low `i64` of a word, stored in big endian order as in the EVM
+pub(super) fn load_i64(
+    builder: &mut FunctionBuilder,
+    big_endian: bool,
+    address: Value,
+    offset: i32,
+) -> Value {
+    let value = builder.ins().load(types::I64, flags(), address, offset);
+    match big_endian {
+        true => value,
+        false => builder.ins().bswap(value),
+    }
+}
+
+/// Store the low `i64` of a word in big endian order
+fn store_i64(
+    builder: &mut FunctionBuilder,
+    big_endian: bool,
+    value: Value,
+    address: Value,
+    offset: i32,
+) {
+    let value = match big_endian {
+        true => value,
+        false => builder.ins().bswap(value),
+    };
+    builder.ins().store(flags(), value, address, offset);
+}
+
+/// The `i64` value as a pointer sized one
+pub(super) fn pointer(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    value: Value,
+) -> Value {
+    match config.pointer_type() {
+        types::I64 => value,
+        ty => builder.ins().ireduce(ty, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::tests::{build, entry, import, signature, CONFIG};
+
+    #[test]
+    fn test_emit_alloc() {
+        let func = build(signature(&[types::I64], &[types::I64, types::I64]), |builder| {
+            let memory = import(builder, "memory", &[], &[types::I64]);
+            let len = entry(builder)[0];
+
+            // `new uint[](len)` and `new bytes(len)`
+            let base = emit_init(builder, false, memory);
+            let array = emit_alloc_array(builder, CONFIG, false, base, len);
+            let bytes = emit_alloc_bytes(builder, CONFIG, false, base, len);
+            builder.ins().return_(&[array, bytes]);
+        });
+        let ir = func.display().to_string();
+        assert_eq!(ir.matches("%Memset").count(), 2, "{ir}");
+        assert!(ir.contains("iconst.i64 128"), "{ir}");
+        assert!(ir.contains("iconst.i64 0x0010_0000"), "{ir}");
+        assert!(ir.contains("user3"), "{ir}");
+        // The free memory pointer is big endian
+        assert!(ir.contains("bswap"), "{ir}");
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The metadata of the objects of the contracts, as written by `compile
+//! --emit metadata` for deployment tooling.
+//!
+//! The code hash identifies the object of a contract: it is the keccak256 hash
+//! of its machine code and read-only data, which hold the code of the
+//! contracts it creates, followed by the compact JSON of the other members of
+//! its metadata. The same source compiled by the same compiler with the same
+//! options thus always gets the same hash, and tooling can check that a
+//! deployed artifact is the one built. With
+//! [`CodegenOptions::embed_code_hash`](super::CodegenOptions) set, the hash is
+//! also appended to the read-only data of the object as the data symbol
+//! `<contract>::code_hash`, which the hash does not cover.
+
+use sha3::{Digest, Keccak256};
+
+use crate::{abi::ethereum::hex, helpers::json::Json, runtime::RUNTIME_VERSION};
+
+/// The metadata of the object of a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub contract: String,
+    /// The version of the compiler
+    pub compiler: String,
+    /// The target triple the code was generated for
+    pub target: String,
+    pub opt_level: u8,
+    /// The version of the runtime the code imports its host functions from
+    pub runtime_version: u32,
+    /// The size of the machine code, without the read-only data
+    pub code_size: usize,
+    /// The keccak256 hash of the code and of the other members
+    pub code_hash: [u8; 32],
+}
+
+impl Metadata {
+    /// The metadata of the object of a contract, from the contents of its
+    /// text and read-only data sections
+    pub fn new(contract: &str, target: &str, opt_level: u8, text: &[u8], data: &[u8]) -> Self {
+        let mut metadata = Self {
+            contract: contract.to_string(),
+            compiler: env!("CARGO_PKG_VERSION").to_string(),
+            target: target.to_string(),
+            opt_level,
+            runtime_version: RUNTIME_VERSION,
+            code_size: text.len(),
+            code_hash: [0; 32],
+        };
+
+        let mut hasher = Keccak256::new();
+        hasher.update(text);
+        hasher.update(data);
+        hasher.update(metadata.members().to_string());
+        metadata.code_hash = hasher.finalize().into();
+
+        metadata
+    }
+
+    /// The metadata as a JSON object, with the code hash in hex digits
+    pub fn to_json(&self) -> Json {
+        let mut json = self.members();
+        json.insert("code_hash", format!("0x{}", hex(&self.code_hash)).into());
+        json
+    }
+
+    /// The members the code hash covers
+    fn members(&self) -> Json {
+        Json::object([
+            ("contract", self.contract.clone().into()),
+            ("compiler", self.compiler.clone().into()),
+            ("target", self.target.clone().into()),
+            ("opt_level", Json::Number(self.opt_level.to_string())),
+            ("runtime_version", Json::Number(self.runtime_version.to_string())),
+            ("code_size", Json::Number(self.code_size.to_string())),
+        ])
+    }
+}
+
+/// The JSON manifest of the metadata of the contracts
+pub fn to_json<'a>(metadata: impl IntoIterator<Item = &'a Metadata>) -> Json {
+    let contracts = metadata.into_iter().map(Metadata::to_json);
+    Json::object([("contracts", Json::Array(contracts.collect()))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_hash() {
+        let metadata = Metadata::new("Token", "x86_64-unknown-linux-gnu", 0, &[0xc3], &[1, 2]);
+        assert_eq!(
+            metadata,
+            Metadata::new("Token", "x86_64-unknown-linux-gnu", 0, &[0xc3], &[1, 2])
+        );
+        assert_eq!(metadata.code_size, 1);
+
+        // The hash covers the code, the data and the other members
+        for other in [
+            Metadata::new("Token", "x86_64-unknown-linux-gnu", 0, &[0xc3], &[1, 3]),
+            Metadata::new("Token", "x86_64-unknown-linux-gnu", 0, &[0xc3, 0x90], &[1, 2]),
+            Metadata::new("Token", "x86_64-unknown-linux-gnu", 2, &[0xc3], &[1, 2]),
+            Metadata::new("Token", "aarch64-unknown-linux-gnu", 0, &[0xc3], &[1, 2]),
+            Metadata::new("Vault", "x86_64-unknown-linux-gnu", 0, &[0xc3], &[1, 2]),
+        ] {
+            assert_ne!(metadata.code_hash, other.code_hash, "{}", other.to_json());
+        }
+
+        let json = to_json([&metadata]);
+        let contract = &json.get("contracts").unwrap().as_array().unwrap()[0];
+        assert_eq!(contract.get("contract").and_then(Json::as_str), Some("Token"));
+        let hash = contract.get("code_hash").and_then(Json::as_str).unwrap();
+        assert_eq!(hash, format!("0x{}", hex(&metadata.code_hash)));
+    }
+}
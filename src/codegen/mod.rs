@@ -0,0 +1,756 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod archive;
+pub mod cfg;
+pub mod constants;
+pub mod create;
+pub mod dce;
+pub mod dispatch;
+pub mod function;
+pub mod init;
+pub mod listing;
+pub mod loops;
+pub mod mangle;
+pub mod memory;
+pub mod metadata;
+pub mod power;
+pub mod require;
+pub mod scratch;
+pub mod storage;
+pub mod switch;
+pub mod trycatch;
+pub mod unroll;
+pub mod word;
+pub mod yul;
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+use cranelift::{
+    codegen::settings::{self, Configurable},
+    module::{default_libcall_names, DataId, FuncOrDataId, Module, ModuleError},
+    object::{
+        object::{
+            write::{self, StandardSection, SymbolSection},
+            SymbolFlags, SymbolKind, SymbolScope,
+        },
+        ObjectBuilder, ObjectModule,
+    },
+    prelude::isa,
+};
+use indexmap::IndexMap;
+use target_lexicon::Triple;
+use thiserror::Error;
+
+use crate::{
+    diagnostics::{Diagnostic, Diagnostics},
+    emit::{CraneliftEmitter, EmitContext, EmitterError},
+    helpers::trace::span,
+    parser::ast::{ContractTy, FunctionTy},
+    runtime,
+    semantic::{ast::Contract, context::Context},
+};
+
+/// Options controlling the generated code
+#[derive(Debug, Clone)]
+pub struct CodegenOptions {
+    /// Target to generate code for
+    pub target: Triple,
+    /// Optimization level, 0 (none), 1 (speed) or 2 (speed and size)
+    pub opt_level: u8,
+    /// Skip the functions unreachable from the entry points of the contracts
+    pub dce: bool,
+    /// Most statements an unrolled loop may grow to, at optimization level 2
+    pub unroll_budget: usize,
+    /// Keep the disassembly of the functions, see [`listing`]
+    pub disasm: bool,
+    /// Warn about the contracts with more bytes of code, the limit of the
+    /// platform of the contracts when not set, see [`gen_contracts`]
+    pub code_size_limit: Option<usize>,
+    /// Append the code hash of each contract to its object, see [`metadata`]
+    pub embed_code_hash: bool,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        Self {
+            target: Triple::host(),
+            opt_level: 0,
+            dce: true,
+            unroll_budget: unroll::DEFAULT_UNROLL_BUDGET,
+            disasm: false,
+            code_size_limit: None,
+            embed_code_hash: false,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error("unsupported target '{0}': {1}")]
+    UnsupportedTarget(Triple, String),
+
+    #[error("invalid optimization level {0}")]
+    InvalidOptLevel(u8),
+
+    #[error(transparent)]
+    Module(#[from] Box<ModuleError>),
+
+    #[error(transparent)]
+    Emit(#[from] EmitterError),
+
+    #[error("failed to write the object file: {0}")]
+    Object(String),
+
+    #[error("circular creation of contracts: {0}")]
+    CircularCreation(String),
+
+    #[error("code of contract '{0}' is not generated")]
+    MissingCode(String),
+
+    /// The errors of the functions which cannot be generated
+    #[error("code generation failed with {} errors", .0.errors().len())]
+    Unsupported(Diagnostics),
+}
+
+pub struct Codegen {
+    module: ObjectModule,
+    ir: String,
+    /// The constant data of all the contracts of the module
+    constants: constants::ConstantPool,
+    dce: bool,
+    /// Zero when loops are not unrolled
+    unroll_budget: usize,
+    disasm: bool,
+    listing: listing::Listing,
+    /// The target and optimization level, recorded in the metadata
+    target: Triple,
+    opt_level: u8,
+    embed_code_hash: bool,
+    /// The code of the contracts created with `new`, with its size, by name
+    codes: IndexMap<String, (DataId, usize)>,
+}
+
+impl Codegen {
+    pub fn new() -> Self {
+        // Target ISA is same as host machine.
+        Self::with_options(&CodegenOptions::default()).unwrap()
+    }
+
+    pub fn with_options(options: &CodegenOptions) -> Result<Self, CodegenError> {
+        let opt_level = match options.opt_level {
+            0 => "none",
+            1 => "speed",
+            2 => "speed_and_size",
+            level => return Err(CodegenError::InvalidOptLevel(level)),
+        };
+
+        let mut flag_builder = settings::builder();
+        flag_builder.set("opt_level", opt_level).unwrap();
+        let flag = settings::Flags::new(flag_builder);
+
+        let unsupported = |e: &dyn std::fmt::Display| {
+            CodegenError::UnsupportedTarget(options.target.clone(), e.to_string())
+        };
+
+        let isa = isa::lookup(options.target.clone())
+            .map_err(|e| unsupported(&e))?
+            .finish(flag)
+            .map_err(|e| unsupported(&e))?;
+
+        let builder = ObjectBuilder::new(isa, "", default_libcall_names()).unwrap();
+        let module = ObjectModule::new(builder);
+
+        // Unrolling trades code size for speed
+        let unroll_budget = match options.opt_level {
+            2 => options.unroll_budget,
+            _ => 0,
+        };
+
+        Ok(Self {
+            module,
+            ir: String::new(),
+            constants: constants::ConstantPool::new(),
+            dce: options.dce,
+            unroll_budget,
+            disasm: options.disasm,
+            listing: listing::Listing::default(),
+            target: options.target.clone(),
+            opt_level: options.opt_level,
+            embed_code_hash: options.embed_code_hash,
+            codes: IndexMap::new(),
+        })
+    }
+
+    /// Generate the functions declared in the main file of the analysis, the
+    /// functions of each contract reading the state variables of its layout.
+    /// Unless dead code elimination is off, only the functions the contracts
+    /// of the file can reach are generated, see [`dce`].
+    pub fn gen(&mut self, ctx: &Context) -> Result<(), CodegenError> {
+        let _span = span!("codegen", functions = ctx.functions.len());
+
+        let contracts = (0..ctx.contracts.len())
+            .filter(|no| ctx.contracts[*no].loc.no() == Some(0))
+            .collect::<Vec<_>>();
+        let reachable = self.dce.then(|| {
+            contracts
+                .iter()
+                .flat_map(|no| dce::reachable_functions(*no, ctx))
+                .collect::<HashSet<_>>()
+        });
+
+        let functions = (0..ctx.functions.len())
+            .filter(|no| ctx.functions[*no].loc.no() == Some(0))
+            .filter(|no| reachable.as_ref().is_none_or(|reachable| reachable.contains(no)))
+            .map(|no| (no, ctx.functions[no].contract_no));
+        self.emit_functions(ctx, functions)
+    }
+
+    /// Generate the functions of a contract: its own and those of its bases,
+    /// all reading the state variables of its layout, but for the
+    /// constructors of the bases, and the free functions and the functions of
+    /// libraries it may call. Unless dead code elimination is off, only the
+    /// functions it can reach are generated, see [`dce`].
+    pub fn gen_contract(&mut self, ctx: &Context, contract_no: usize) -> Result<(), CodegenError> {
+        let _span = span!("codegen", contract = ctx.contracts[contract_no].id.name.as_str());
+
+        let bases = ctx.contract_bases(contract_no);
+        let reachable = self.dce.then(|| dce::reachable_functions(contract_no, ctx));
+
+        let functions = (0..ctx.functions.len())
+            .filter(|no| {
+                let func = &ctx.functions[*no];
+                match func.contract_no {
+                    None => true,
+                    Some(no) if func.is_constructor() => no == contract_no,
+                    Some(no) => {
+                        bases.contains(&no) ||
+                            matches!(ctx.contracts[no].ty, ContractTy::Library(_))
+                    }
+                }
+            })
+            .filter(|no| reachable.as_ref().is_none_or(|reachable| reachable.contains(no)))
+            .map(|no| (no, Some(contract_no)));
+        self.emit_functions(ctx, functions)
+    }
+
+    /// Lower the functions with a body which are not modifiers, each with the
+    /// layout of the contract given with it, failing with the errors of all
+    /// those which cannot be lowered
+    fn emit_functions(
+        &mut self,
+        ctx: &Context,
+        functions: impl Iterator<Item = (usize, Option<usize>)>,
+    ) -> Result<(), CodegenError> {
+        let mut emit_ctx = EmitContext::new(&mut self.module, &mut self.constants, self.disasm);
+        emit_ctx.codes = self.codes.clone();
+        let imports = runtime::declare_imports(emit_ctx.module)?;
+        emit_ctx.functions.extend(imports);
+
+        let mut emitter = CraneliftEmitter::new(&mut emit_ctx, ctx, self.unroll_budget);
+        for (function_no, contract_no) in functions {
+            if dce::is_lowered(function_no, ctx) {
+                emitter.emit_function(function_no, contract_no)?;
+            }
+        }
+
+        self.ir.push_str(&emit_ctx.ir);
+        for (symbol, asm) in emit_ctx.listing {
+            self.listing.push(symbol, None, asm);
+        }
+
+        match emit_ctx.diagnostics.any_errors() {
+            true => Err(CodegenError::Unsupported(emit_ctx.diagnostics)),
+            false => Ok(()),
+        }
+    }
+
+    /// Generate the dispatcher of a contract, see [`dispatch`]
+    pub fn gen_dispatcher(
+        &mut self,
+        dispatcher: &dispatch::Dispatcher,
+    ) -> Result<(), CodegenError> {
+        let _span = span!("dispatcher", contract = dispatcher.symbol());
+        let (func, asm) = dispatcher.emit(&mut self.module, self.disasm)?;
+        self.ir.push_str(&format!("{func}\n"));
+        if let Some(asm) = asm {
+            self.listing.push(dispatcher.symbol().mangle(), dispatcher.origin.clone(), asm);
+        }
+
+        Ok(())
+    }
+
+    /// Embed the code of a contract the generated code creates, see [`create`]
+    pub fn embed_code(&mut self, contract: &str, code: &[u8]) -> Result<(), CodegenError> {
+        let id = create::define_code(&mut self.module, contract, code)?;
+        self.codes.insert(contract.to_string(), (id, code.len()));
+
+        Ok(())
+    }
+
+    /// The Cranelift IR of the functions generated so far
+    pub fn ir(&self) -> &str {
+        &self.ir
+    }
+
+    /// Whether the function of the symbol was generated
+    pub fn defines(&self, symbol: &mangle::Symbol) -> bool {
+        matches!(self.module.get_name(&symbol.mangle()), Some(FuncOrDataId::Func(_)))
+    }
+
+    /// The disassembly of the functions defined so far, empty unless
+    /// [`CodegenOptions::disasm`] is set
+    pub fn listing(&self) -> &listing::Listing {
+        &self.listing
+    }
+
+    /// Finish the module, giving the contents of its object file
+    pub fn finish(self) -> Result<Vec<u8>, CodegenError> {
+        self.module.finish().emit().map_err(|e| CodegenError::Object(e.to_string()))
+    }
+
+    /// Finish the module of a contract, giving the contents of its object
+    /// file and its metadata, with the code hash appended to the object when
+    /// [`CodegenOptions::embed_code_hash`] is set, see [`metadata`]
+    pub fn finish_contract(
+        self,
+        contract: &str,
+    ) -> Result<(Vec<u8>, metadata::Metadata), CodegenError> {
+        let mut object = self.module.finish().object;
+        let text = object.section_id(StandardSection::Text);
+        let data = object.section_id(StandardSection::ReadOnlyData);
+        let metadata = metadata::Metadata::new(
+            contract,
+            &self.target.to_string(),
+            self.opt_level,
+            object.section(text).data(),
+            object.section(data).data(),
+        );
+
+        if self.embed_code_hash {
+            let symbol = object.add_symbol(write::Symbol {
+                name: mangle::Symbol::code_hash(contract).mangle().into_bytes(),
+                value: 0,
+                size: 0,
+                kind: SymbolKind::Data,
+                scope: SymbolScope::Linkage,
+                weak: false,
+                section: SymbolSection::Undefined,
+                flags: SymbolFlags::None,
+            });
+            object.add_symbol_data(symbol, data, &metadata.code_hash, 1);
+        }
+
+        let object = object.write().map_err(|e| CodegenError::Object(e.to_string()))?;
+        Ok((object, metadata))
+    }
+
+    pub fn write(&self, path: &Path) {
+        let file = fs::File::create(path).unwrap();
+        let mut buf_writer = BufWriter::new(file);
+        buf_writer.write_all(self.ir.as_bytes()).unwrap();
+    }
+}
+
+impl Default for Codegen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Generate the object of each contract after the contracts it creates, whose
+/// code it embeds, keeping it in [`Contract::code`](crate::semantic::ast::Contract)
+/// and its metadata in `Contract::metadata`, and the symbol of its init
+/// function, see [`init`], in `Contract::initializer` when it was generated.
+/// An object holds the functions of its contract, see
+/// [`Codegen::gen_contract`], and the dispatcher of the contract. A contract generated
+/// already is not generated again. Gives the contracts in the order generated,
+/// adding the disassembly of their functions to the listing if asked to, and
+/// a warning to the diagnostics for each contract whose code is larger than
+/// the limit, see [`CodegenOptions::code_size_limit`].
+pub fn gen_contracts(
+    contracts: &[usize],
+    ctx: &Context,
+    options: &CodegenOptions,
+    listing: &mut listing::Listing,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<usize>, CodegenError> {
+    let order = creation_order(contracts, ctx)?;
+    let code_size_limit = options.code_size_limit.or(ctx.target.code_size_limit());
+
+    for &contract_no in &order {
+        let contract = &ctx.contracts[contract_no];
+        if contract.code.get().is_some() {
+            continue;
+        }
+
+        let mut generator = Codegen::with_options(options)?;
+        for &created_no in &contract.creates {
+            let created = &ctx.contracts[created_no];
+            let code = created
+                .code
+                .get()
+                .ok_or_else(|| CodegenError::MissingCode(created.id.name.clone()))?;
+            generator.embed_code(&created.id.name, code)?;
+        }
+        generator.gen_contract(ctx, contract_no)?;
+        generator.gen_dispatcher(&dispatch::Dispatcher::new(contract_no, ctx))?;
+        listing.extend(generator.listing.clone());
+
+        let initializer =
+            mangle::Symbol::new(Some(&contract.id.name), FunctionTy::Constructor, "", "");
+        if generator.defines(&initializer) {
+            let _ = contract.initializer.set(initializer);
+        }
+
+        let (code, metadata) = generator.finish_contract(&contract.id.name)?;
+        if let Some(warning) =
+            code_size_limit.and_then(|l| code_size_warning(contract, metadata.code_size, l))
+        {
+            diagnostics.push(warning);
+        }
+        let _ = contract.code.set(code);
+        let _ = contract.metadata.set(metadata);
+    }
+
+    Ok(order)
+}
+
+/// A warning when the code of the contract is larger than the limit, which
+/// may keep it from being deployed
+fn code_size_warning(contract: &Contract, code_size: usize, limit: usize) -> Option<Diagnostic> {
+    (code_size > limit).then(|| {
+        Diagnostic::warning(
+            contract.loc,
+            format!(
+                "contract '{}' has {code_size} bytes of code, more than the limit of {limit} bytes",
+                contract.id.name
+            ),
+        )
+    })
+}
+
+/// The object of a contract by name, once generated by [`gen_contracts`]
+pub fn contract_code<'a>(ctx: &'a Context, name: &str) -> Option<&'a [u8]> {
+    ctx.contracts.iter().find(|contract| contract.id.name == name)?.code.get().map(Vec::as_slice)
+}
+
+/// Order contracts so that each comes after the contracts it creates, whose
+/// code it embeds. The contracts created by those given are included.
+pub fn creation_order(contracts: &[usize], ctx: &Context) -> Result<Vec<usize>, CodegenError> {
+    dependency_order(contracts, |no| &ctx.contracts[no].creates).map_err(|cycle| {
+        let names = cycle.iter().map(|no| ctx.contracts[*no].id.name.as_str());
+        CodegenError::CircularCreation(names.collect::<Vec<_>>().join(" -> "))
+    })
+}
+
+/// Order the nodes after their dependencies, depth first from the roots in
+/// their order. A cycle is returned as the path closing it.
+fn dependency_order<'a>(
+    roots: &[usize],
+    dependencies: impl Fn(usize) -> &'a [usize],
+) -> Result<Vec<usize>, Vec<usize>> {
+    fn visit<'a>(
+        node: usize,
+        dependencies: &impl Fn(usize) -> &'a [usize],
+        path: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> Result<(), Vec<usize>> {
+        if order.contains(&node) {
+            return Ok(());
+        }
+
+        if let Some(start) = path.iter().position(|n| *n == node) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(node);
+            return Err(cycle);
+        }
+
+        path.push(node);
+        for dependency in dependencies(node) {
+            visit(*dependency, dependencies, path, order)?;
+        }
+        path.pop();
+
+        order.push(node);
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    for root in roots {
+        visit(*root, &dependencies, &mut Vec::new(), &mut order)?;
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::convert::Infallible;
+
+    use cranelift::{
+        codegen::{
+            ir::{ExtFuncData, ExternalName, FuncRef, Function, UserFuncName},
+            isa::{CallConv, TargetFrontendConfig},
+            verify_function,
+        },
+        prelude::{
+            types, AbiParam, FunctionBuilder, FunctionBuilderContext, Signature, Type, Value,
+        },
+    };
+    use target_lexicon::PointerWidth;
+
+    use super::*;
+    use crate::{config::Config, driver, Input};
+
+    /// The target the functions of the tests are built for
+    pub(crate) const CONFIG: TargetFrontendConfig = TargetFrontendConfig {
+        default_call_conv: CallConv::SystemV,
+        pointer_width: PointerWidth::U64,
+        page_size_align_log2: 12,
+    };
+
+    /// A signature of the calling convention of [`CONFIG`]
+    pub(crate) fn signature(params: &[Type], returns: &[Type]) -> Signature {
+        let mut signature = Signature::new(CONFIG.default_call_conv);
+        signature.params.extend(params.iter().copied().map(AbiParam::new));
+        signature.returns.extend(returns.iter().copied().map(AbiParam::new));
+        signature
+    }
+
+    /// The function of the signature `build` emits, verified
+    pub(crate) fn build(
+        signature: Signature,
+        build: impl FnOnce(&mut FunctionBuilder),
+    ) -> Function {
+        let built = try_build(signature, |builder| {
+            build(builder);
+            Ok::<_, Infallible>(())
+        });
+        built.unwrap_or_else(|err| match err {})
+    }
+
+    /// The function of the signature `build` emits, verified, unless it
+    /// fails
+    pub(crate) fn try_build<E>(
+        signature: Signature,
+        build: impl FnOnce(&mut FunctionBuilder) -> Result<(), E>,
+    ) -> Result<Function, E> {
+        let mut func = Function::with_name_signature(UserFuncName::default(), signature);
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut builder_ctx);
+
+        build(&mut builder)?;
+        builder.seal_all_blocks();
+        builder.finalize(CONFIG);
+
+        verify_function(&func, &settings::Flags::new(settings::builder())).unwrap();
+        Ok(func)
+    }
+
+    /// Switch to a new entry block, giving the parameters of the function
+    pub(crate) fn entry(builder: &mut FunctionBuilder) -> Vec<Value> {
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.block_params(entry).to_vec()
+    }
+
+    /// Import a function of the signature in the function built
+    pub(crate) fn import(
+        builder: &mut FunctionBuilder,
+        name: &str,
+        params: &[Type],
+        returns: &[Type],
+    ) -> FuncRef {
+        let signature = builder.import_signature(signature(params, returns));
+        builder.import_function(ExtFuncData {
+            name: ExternalName::testcase(name),
+            signature,
+            colocated: false,
+            patchable: false,
+        })
+    }
+
+    /// Import the host functions of the runtime in the function built, in the
+    /// order of [`runtime::HOST_FUNCTIONS`]
+    pub(crate) fn import_host(builder: &mut FunctionBuilder) -> IndexMap<&'static str, FuncRef> {
+        runtime::HOST_FUNCTIONS
+            .iter()
+            .map(|function| {
+                let signature = function.signature(CONFIG.default_call_conv, types::I64);
+                let signature = builder.import_signature(signature);
+                let callee = builder.import_function(ExtFuncData {
+                    name: ExternalName::testcase(function.name),
+                    signature,
+                    colocated: false,
+                    patchable: false,
+                });
+                (function.name, callee)
+            })
+            .collect()
+    }
+
+    /// The number of calls to the host function in the IR of a function
+    /// importing them with [`import_host`]
+    pub(crate) fn calls(ir: &str, name: &str) -> usize {
+        let index = runtime::HOST_FUNCTIONS.iter().position(|function| function.name == name);
+        ir.matches(&format!("call fn{}(", index.unwrap())).count()
+    }
+
+    /// The semantic analysis of a source without errors
+    pub(crate) fn analyze(source: &str) -> Context {
+        let input = Input::Source { name: "test.sol".into(), contents: source.into() };
+        let mut resolver = driver::resolver(&Config::default());
+        let file = driver::load(&input, &mut resolver).unwrap();
+        let ctx = driver::analyze(&file, &mut resolver, &[]);
+        assert!(!ctx.diagnostics.any_errors(), "{:?}", ctx.diagnostics.errors());
+        ctx
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let creates: Vec<Vec<usize>> = vec![vec![], vec![0, 2], vec![0], vec![]];
+        let order = dependency_order(&[1, 3], |no| &creates[no]);
+        assert_eq!(order, Ok(vec![0, 2, 1, 3]));
+
+        let creates: Vec<Vec<usize>> = vec![vec![1], vec![2], vec![0]];
+        assert_eq!(dependency_order(&[0], |no| &creates[no]), Err(vec![0, 1, 2, 0]));
+    }
+
+    #[test]
+    fn test_code_size_limit() {
+        let source = r#"
+            contract Token {
+                function total(uint a) public pure returns (uint) { return a * 3 + 1; }
+            }
+        "#;
+
+        // A contract is generated once, in a fresh context each time
+        let gen = |code_size_limit| {
+            let ctx = analyze(source);
+            let options = CodegenOptions { code_size_limit, ..Default::default() };
+            let mut diagnostics = Diagnostics::default();
+            let mut listing = listing::Listing::default();
+            gen_contracts(&[0], &ctx, &options, &mut listing, &mut diagnostics).unwrap();
+            diagnostics
+        };
+
+        let diagnostics = gen(Some(16));
+        let warning = &diagnostics.warnings()[0].message;
+        assert!(warning.starts_with("contract 'Token' has "), "{warning}");
+        assert!(warning.ends_with(" bytes of code, more than the limit of 16 bytes"), "{warning}");
+
+        // Native code has no limit by default
+        assert!(gen(None).is_empty());
+        assert!(gen(Some(1 << 20)).is_empty());
+    }
+
+    #[test]
+    fn test_code_hash() {
+        let source = r#"
+            contract Token {
+                function total(uint a) public pure returns (uint) { return a * 3 + 1; }
+            }
+        "#;
+
+        let gen = |options: &CodegenOptions| {
+            let mut ctx = analyze(source);
+            let mut diagnostics = Diagnostics::default();
+            let mut listing = listing::Listing::default();
+            gen_contracts(&[0], &ctx, options, &mut listing, &mut diagnostics).unwrap();
+            let contract = ctx.contracts.pop().unwrap();
+            (contract.code.into_inner().unwrap(), contract.metadata.into_inner().unwrap())
+        };
+
+        let options = CodegenOptions::default();
+        let (code, metadata) = gen(&options);
+        assert_eq!(gen(&options).1, metadata);
+        assert_eq!(metadata.opt_level, 0);
+        assert_eq!(metadata.target, options.target.to_string());
+        assert!(metadata.code_size > 0);
+
+        let (_, optimized) = gen(&CodegenOptions { opt_level: 2, ..Default::default() });
+        assert_ne!(optimized.code_hash, metadata.code_hash);
+
+        // Embedding the hash leaves it unchanged
+        let contains = |haystack: &[u8], needle: &[u8]| {
+            haystack.windows(needle.len()).any(|window| window == needle)
+        };
+        assert!(!contains(&code, b"Token::code_hash"));
+        let (embedded, same) = gen(&CodegenOptions { embed_code_hash: true, ..Default::default() });
+        assert_eq!(same, metadata);
+        assert!(contains(&embedded, b"Token::code_hash"));
+        assert!(contains(&embedded, &metadata.code_hash));
+    }
+
+    #[test]
+    fn test_initializer() {
+        let source = r#"
+            contract Counter {
+                uint count = 1;
+                uint8 small = 2;
+                constructor() { count += small; }
+            }
+            contract Named { string name = "counter"; }
+            contract Owned {
+                uint8 a = 1;
+                address owner;
+                constructor() { owner = msg.sender; }
+            }
+        "#;
+        let ctx = analyze(source);
+        let mut diagnostics = Diagnostics::default();
+        let mut listing = listing::Listing::default();
+        let options = CodegenOptions::default();
+        gen_contracts(&[0], &ctx, &options, &mut listing, &mut diagnostics).unwrap();
+
+        let initializer = ctx.contracts[0].initializer.get().map(mangle::Symbol::mangle);
+        assert_eq!(initializer.as_deref(), Some("Counter::constructor"));
+        let code = ctx.contracts[0].code.get().unwrap();
+        assert!(code.windows(21).any(|window| window == b"Counter::initializers"));
+
+        // Strings are not lowered yet, which fails the generation
+        let error = gen_contracts(&[1], &ctx, &options, &mut listing, &mut diagnostics);
+        let Err(CodegenError::Unsupported(errors)) = error else { panic!("{error:?}") };
+        assert_eq!(
+            errors.errors()[0].notes[0].message,
+            "the initializers of the state variables of 'Named' cannot be generated"
+        );
+        assert_eq!(ctx.contracts[1].code.get(), None);
+
+        // The initializers do not depend on the body of the constructor
+        let mut generator = Codegen::new();
+        let error = generator.gen_contract(&ctx, 2);
+        let Err(CodegenError::Unsupported(errors)) = error else { panic!("{error:?}") };
+        let messages = errors
+            .errors()
+            .iter()
+            .map(|error| (error.message.as_str(), error.notes[0].message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [(
+                "the expression `msg.sender` is not supported by the code generator yet",
+                "the constructor of 'Owned' cannot be generated"
+            )]
+        );
+        assert!(generator.defines(&mangle::Symbol::initializers("Owned")));
+    }
+}
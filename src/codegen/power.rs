@@ -0,0 +1,158 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lowering of `**`, by squaring the base for each bit of the exponent:
+//!
+//! ```text
+//! result = 1
+//! while exp != 0:
+//!     if exp & 1: result *= base
+//!     exp >>= 1
+//!     if exp != 0: base *= base
+//! ```
+//!
+//! The base is only squared while bits of the exponent remain, so the
+//! squares overflow only when the result would. The loop is emitted on
+//! [words](super::word) around the two multiplications, which the caller
+//! emits as the products of the type of the base, checked outside of
+//! `unchecked` blocks as the panic of Solidity: `a ** 2` is emitted with
+//! [`Power::emit`], then the product of the result and the base given to
+//! [`Power::multiplied`], then the square of the base to [`Power::squared`].
+
+use cranelift::{
+    codegen::ir::BlockArg,
+    prelude::{types, Block, FunctionBuilder, InstBuilder},
+};
+
+use super::word::{self, Word};
+
+/// The loop of `base ** exp`, between the multiplications
+#[derive(Debug)]
+pub struct Power {
+    header: Block,
+    shift: Block,
+    exit: Block,
+    /// The exponent left to the next iteration
+    exp: Word,
+}
+
+impl Power {
+    /// Emit the loop up to the multiplication of the result by the base,
+    /// giving both. The exponent is unsigned.
+    pub fn emit(builder: &mut FunctionBuilder, base: Word, exp: Word) -> (Self, Word, Word) {
+        let header = builder.create_block();
+        let body = builder.create_block();
+        let multiply = builder.create_block();
+        let shift = builder.create_block();
+        let exit = builder.create_block();
+        for block in [header, shift] {
+            for _ in 0..3 * 4 {
+                builder.append_block_param(block, types::I64);
+            }
+        }
+        for _ in 0..4 {
+            builder.append_block_param(exit, types::I64);
+        }
+
+        let one = builder.ins().iconst(types::I64, 1);
+        let one = word::limb_word(builder, one);
+        builder.ins().jump(header, &args(&[one, base, exp]));
+
+        // header(result, base, exp): done when no bits of the exponent remain
+        builder.switch_to_block(header);
+        let [result, base, exp] = params(builder, header);
+        let any = word::any(builder, &exp.0);
+        builder.ins().brif(any, body, &[], exit, &args(&[result]));
+
+        builder.switch_to_block(body);
+        builder.seal_block(body);
+        let odd = builder.ins().band_imm_u(exp.0[0], 1);
+        builder.ins().brif(odd, multiply, &[], shift, &args(&[result, base, exp]));
+
+        builder.switch_to_block(multiply);
+        builder.seal_block(multiply);
+        (Power { header, shift, exit, exp }, result, base)
+    }
+
+    /// Continue with the product of the result and the base, up to the
+    /// squaring of the base, giving it
+    pub fn multiplied(&mut self, builder: &mut FunctionBuilder, product: Word) -> Word {
+        let [_, base, exp] = params(builder, self.header);
+        builder.ins().jump(self.shift, &args(&[product, base, exp]));
+
+        // shift(result, base, exp): drop the bit, squaring for the next if any
+        builder.switch_to_block(self.shift);
+        builder.seal_block(self.shift);
+        let [result, base, exp] = params(builder, self.shift);
+        self.exp = word::shr_imm(builder, exp, 1);
+        let any = word::any(builder, &self.exp.0);
+        let square = builder.create_block();
+        builder.ins().brif(any, square, &[], self.exit, &args(&[result]));
+
+        builder.switch_to_block(square);
+        builder.seal_block(square);
+        base
+    }
+
+    /// Continue with the square of the base, giving the result after the loop
+    pub fn squared(self, builder: &mut FunctionBuilder, square: Word) -> Word {
+        let [result, ..] = params(builder, self.shift);
+        builder.ins().jump(self.header, &args(&[result, square, self.exp]));
+        builder.seal_block(self.header);
+
+        builder.switch_to_block(self.exit);
+        builder.seal_block(self.exit);
+        Word(builder.block_params(self.exit).try_into().unwrap())
+    }
+}
+
+/// The words of the parameters of a block of the loop
+fn params(builder: &FunctionBuilder, block: Block) -> [Word; 3] {
+    let params = builder.block_params(block);
+    [0, 1, 2].map(|i| Word(params[4 * i..4 * i + 4].try_into().unwrap()))
+}
+
+/// The limbs of the words, as the arguments of a jump
+fn args(words: &[Word]) -> Vec<BlockArg> {
+    words.iter().flat_map(|word| word.0).map(BlockArg::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::tests::{build, entry, signature};
+
+    #[test]
+    fn test_emit_power() {
+        let func = build(signature(&[types::I64; 8], &[types::I64; 4]), |builder| {
+            let params = entry(builder);
+            let base = Word(params[..4].try_into().unwrap());
+            let exp = Word(params[4..].try_into().unwrap());
+
+            let (mut power, result, base) = Power::emit(builder, base, exp);
+            let product = word::mul(builder, result, base);
+            let base = power.multiplied(builder, product);
+            let square = word::mul(builder, base, base);
+            let result = power.squared(builder, square);
+            builder.ins().return_(&result.0);
+        });
+
+        // The exponent is tested when entering the loop and after its shift,
+        // and its low bit in the loop
+        let ir = func.display().to_string();
+        assert_eq!(ir.matches("brif").count(), 3, "{ir}");
+        assert!(ir.contains("band.i64 v16, v41  ; v41 = 1"), "{ir}");
+        assert_eq!(func.layout.blocks().count(), 7, "{ir}");
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lowering of `require`. When the condition does not hold the call reverts
+//! with the data of the reason: `Error(string)` for a string, as `revert`
+//! gives, or the selector and arguments of a custom error. The data of a
+//! reason whose arguments are all constant is encoded at compile time.
+
+use cranelift::{
+    codegen::ir::FuncRef,
+    prelude::{FunctionBuilder, InstBuilder, Value},
+};
+
+use crate::abi::codec::{encode, Token};
+
+use super::trycatch::{ERROR_SELECTOR, REVERT_RETURNED};
+
+/// The revert data of `Error(string)` for the message
+pub fn error_message(message: &str) -> Vec<u8> {
+    error_data(ERROR_SELECTOR, &[Token::String(message.to_string())])
+}
+
+/// The revert data of an error: the selector, then the encoded arguments
+pub fn error_data(selector: [u8; 4], args: &[Token]) -> Vec<u8> {
+    let mut data = selector.to_vec();
+    data.extend(encode(args));
+    data
+}
+
+/// Emit the check of the condition, reverting with the data, of the 32 bit
+/// length, when it is false. Execution goes on in a new block otherwise.
+pub fn emit(builder: &mut FunctionBuilder, cond: Value, revert: FuncRef, data: Value, len: Value) {
+    let success = builder.create_block();
+    let failure = builder.create_block();
+    builder.ins().brif(cond, success, &[], failure, &[]);
+
+    builder.switch_to_block(failure);
+    builder.seal_block(failure);
+    emit_revert(builder, revert, data, len);
+
+    builder.switch_to_block(success);
+    builder.seal_block(success);
+}
+
+/// Emit the revert with the data, of the 32 bit length, which ends the block
+pub fn emit_revert(builder: &mut FunctionBuilder, revert: FuncRef, data: Value, len: Value) {
+    builder.ins().call(revert, &[data, len]);
+    builder.ins().trap(REVERT_RETURNED);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abi::ethereum,
+        codegen::tests::{build, entry, import, signature},
+    };
+    use cranelift::prelude::types;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn test_error_data() {
+        let data = error_message("denied");
+        assert_eq!(data[..4], ethereum::selector("Error(string)"));
+        // The offset, the length and the padded string
+        assert_eq!(data.len(), 4 + 3 * 32);
+        assert_eq!(data[4 + 31], 32);
+        assert_eq!(data[4 + 63], 6);
+        assert_eq!(&data[4 + 64..4 + 70], b"denied");
+
+        let selector = ethereum::selector("Insufficient(uint256)");
+        let data = error_data(selector, &[Token::Uint(BigInt::from(5))]);
+        assert_eq!(data.len(), 4 + 32);
+        assert_eq!(data[..4], selector);
+        assert_eq!(data[35], 5);
+    }
+
+    #[test]
+    fn test_emit_require() {
+        let func = build(signature(&[types::I8, types::I64, types::I32], &[]), |builder| {
+            let revert = import(builder, "revert", &[types::I64, types::I32], &[]);
+            let [cond, data, len] = entry(builder).try_into().unwrap();
+            emit(builder, cond, revert, data, len);
+            builder.ins().return_(&[]);
+        });
+        let ir = func.display().to_string();
+        assert!(ir.contains("brif v0"), "{ir}");
+        assert!(ir.contains("call fn0(v1, v2)"), "{ir}");
+        assert!(ir.contains("trap user1"), "{ir}");
+    }
+}
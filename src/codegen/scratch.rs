@@ -0,0 +1,246 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reuse of the buffers of encoded arguments.
+//!
+//! The data of an error, the arguments of a constructor, the results of an
+//! internal function and the data a function returns are encoded in a buffer
+//! which is dead once the host or the function called returns. Allocating it
+//! from [`memory`](super::memory) each time costs the checks of the
+//! allocator, and keeps the memory until the end of the call, and a stack
+//! slot for each grows the frame with each of them. The buffers of statically
+//! sized encodings are instead planned for the whole function: those never
+//! live at the same time share a stack slot, sized for the largest of them.
+//! Buffers are not cleared between uses, the encoder writes every byte of its
+//! buffer.
+
+use cranelift::{
+    codegen::ir::StackSlot,
+    prelude::{FunctionBuilder, InstBuilder, StackSlotData, StackSlotKind, Type, Value},
+};
+
+/// An encoding of a function, with the size of its buffer and the positions
+/// in the function, in the order of emission, of its first and last use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding {
+    pub size: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The stack slots of the encodings of a function
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScratchPlan {
+    /// The size of each slot
+    sizes: Vec<u32>,
+    /// The last position each slot is used at
+    ends: Vec<usize>,
+    /// The slot of each encoding
+    slots: Vec<usize>,
+}
+
+impl ScratchPlan {
+    /// Assign the encodings to slots, an encoding reusing a slot whose
+    /// encodings all ended before it starts
+    pub fn new(encodings: &[Encoding]) -> Self {
+        let mut order = (0..encodings.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| encodings[*i].start);
+
+        let mut plan = Self::default();
+        let mut slots = vec![0; encodings.len()];
+        for i in order {
+            slots[i] = plan.assign(encodings[i]);
+        }
+        plan.slots = slots;
+
+        plan
+    }
+
+    /// Assign the next encoding to a slot, giving the slot. The encodings
+    /// must be assigned in the order they start.
+    pub fn assign(&mut self, encoding: Encoding) -> usize {
+        // The largest free slot grows the least
+        let free = (0..self.ends.len())
+            .filter(|slot| self.ends[*slot] < encoding.start)
+            .max_by_key(|slot| self.sizes[*slot]);
+
+        let slot = match free {
+            Some(slot) => {
+                self.sizes[slot] = self.sizes[slot].max(encoding.size);
+                self.ends[slot] = encoding.end;
+                slot
+            }
+            None => {
+                self.sizes.push(encoding.size);
+                self.ends.push(encoding.end);
+                self.sizes.len() - 1
+            }
+        };
+        self.slots.push(slot);
+        slot
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// The bytes of stack the buffers take
+    pub fn stack_size(&self) -> u32 {
+        self.sizes.iter().sum()
+    }
+}
+
+/// The buffers of a function being emitted, following its plan
+#[derive(Debug, Default)]
+pub struct ScratchBuffers {
+    plan: ScratchPlan,
+    slots: Vec<Option<StackSlot>>,
+}
+
+impl ScratchBuffers {
+    pub fn new(plan: ScratchPlan) -> Self {
+        let slots = vec![None; plan.slot_count()];
+        Self { plan, slots }
+    }
+
+    /// Plan the buffer of the next encoding emitted, dead before the next
+    /// one starts, and emit its address. A slot reused by a larger encoding
+    /// grows.
+    pub fn emit_next(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        pointer_type: Type,
+        size: u32,
+    ) -> Value {
+        let position = self.plan.slots.len();
+        let index = self.plan.assign(Encoding { size, start: position, end: position });
+        self.slots.resize(self.plan.slot_count(), None);
+        if let Some(slot) = self.slots[index] {
+            let data = &mut builder.func.sized_stack_slots[slot];
+            data.size = data.size.max(size);
+        }
+        self.emit_buffer(builder, pointer_type, position)
+    }
+
+    /// Emit the address of the buffer of the encoding, by its index in the
+    /// planned encodings. The slot is created on its first use.
+    pub fn emit_buffer(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        pointer_type: Type,
+        encoding: usize,
+    ) -> Value {
+        let index = self.plan.slots[encoding];
+        let size = self.plan.sizes[index];
+        let slot = *self.slots[index].get_or_insert_with(|| {
+            builder.create_sized_stack_slot(StackSlotData::new(
+                StackSlotKind::ExplicitSlot,
+                size,
+                3,
+            ))
+        });
+        builder.ins().stack_addr(pointer_type, slot, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::memory;
+    use cranelift::{
+        codegen::{
+            ir::{ExtFuncData, ExternalName, Function, UserFuncName},
+            isa::{CallConv, TargetFrontendConfig},
+            settings, verify_function,
+        },
+        prelude::{types, AbiParam, FunctionBuilderContext, Signature},
+    };
+    use target_lexicon::PointerWidth;
+
+    const CONFIG: TargetFrontendConfig = TargetFrontendConfig {
+        default_call_conv: CallConv::SystemV,
+        pointer_width: PointerWidth::U64,
+        page_size_align_log2: 12,
+    };
+
+    fn encoding(size: u32, start: usize, end: usize) -> Encoding {
+        Encoding { size, start, end }
+    }
+
+    #[test]
+    fn test_plan_slots() {
+        // `a.f(x); b.g(x, y, z); c.h();` one after the other
+        let plan = ScratchPlan::new(&[encoding(68, 0, 0), encoding(100, 1, 1), encoding(4, 2, 2)]);
+        assert_eq!(plan.slot_count(), 1);
+        assert_eq!(plan.stack_size(), 100);
+
+        // A buffer still used when the next one is encoded
+        let plan = ScratchPlan::new(&[encoding(36, 0, 2), encoding(68, 1, 1), encoding(36, 3, 3)]);
+        assert_eq!(plan.slots, [0, 1, 1]);
+        assert_eq!(plan.stack_size(), 36 + 68);
+    }
+
+    /// The instructions of a function calling `sink` with `calls` buffers of
+    /// 68 bytes, from the planned slots or allocated from memory
+    fn instructions(calls: usize, reuse: bool) -> usize {
+        let mut sig = Signature::new(CallConv::SystemV);
+        sig.params.push(AbiParam::new(types::I64));
+        let mut func = Function::with_name_signature(UserFuncName::default(), sig);
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut builder_ctx);
+
+        let mut sink_sig = Signature::new(CallConv::SystemV);
+        sink_sig.params.push(AbiParam::new(types::I64));
+        let signature = builder.import_signature(sink_sig);
+        let sink = builder.import_function(ExtFuncData {
+            name: ExternalName::testcase("sink"),
+            signature,
+            colocated: false,
+            patchable: false,
+        });
+
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let base = builder.block_params(entry)[0];
+
+        let encodings = (0..calls).map(|i| encoding(68, i, i)).collect::<Vec<_>>();
+        let mut buffers = ScratchBuffers::new(ScratchPlan::new(&encodings));
+        for i in 0..calls {
+            let buffer = match reuse {
+                true => buffers.emit_buffer(&mut builder, types::I64, i),
+                false => {
+                    let size = builder.ins().iconst(types::I64, 68);
+                    memory::emit_alloc(&mut builder, CONFIG, false, base, size)
+                }
+            };
+            builder.ins().call(sink, &[buffer]);
+        }
+        builder.ins().return_(&[]);
+        builder.seal_all_blocks();
+        builder.finalize(CONFIG);
+
+        verify_function(&func, &settings::Flags::new(settings::builder())).unwrap();
+        assert_eq!(func.sized_stack_slots.len(), if reuse { 1 } else { 0 });
+        func.layout.blocks().map(|block| func.layout.block_insts(block).count()).sum()
+    }
+
+    #[test]
+    fn test_reuse_buffers() {
+        let reused = instructions(8, true);
+        let allocated = instructions(8, false);
+        assert_eq!(reused, 8 * 2 + 1);
+        assert!(reused * 5 < allocated, "{reused} instructions, {allocated} when allocating");
+    }
+}
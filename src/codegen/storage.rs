@@ -0,0 +1,513 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage keys of state variables. The host stores words of 32 bytes by key,
+//! the key of a slot is its number in big endian order, with the first byte
+//! set to the storage class, so that the layouts of the classes, which all
+//! start from slot 0, do not overlap.
+//!
+//! The `temporary` storage class is the transient storage of EIP-1153: it is
+//! read and written with host functions of its own, and the runtime clears it
+//! at the end of each transaction.
+//!
+//! The elements of a mapping have no slot of their own. As in solc, the key of
+//! an element is the Keccak-256 hash of its key followed by the key of the
+//! mapping, so that the elements of a nested mapping hash the key of the
+//! element of the outer one. The key of a mapping keeps its storage class, so
+//! the elements of mappings of different classes do not overlap either. An
+//! element whose value is held in a word is read and written like a state
+//! variable at the key computed, taking its whole slot.
+//!
+//! The state variables the lowering of functions reads and writes are at
+//! their slots in the layout of the contract being generated, see
+//! [`layout`](crate::semantic::layout). A value packed with others is read by
+//! shifting it out of its slot, and written by loading the slot and replacing
+//! its bytes. Values are stored as in solc, right aligned in their bytes:
+//! signed integers are cut to their size, and `bytesN`, which the lowering
+//! holds right aligned as well, are stored as they are.
+
+use cranelift::{
+    codegen::{
+        ir::{Endianness, FuncRef},
+        isa::TargetFrontendConfig,
+    },
+    prelude::{types, FunctionBuilder, InstBuilder, StackSlotData, StackSlotKind, Type, Value},
+};
+use indexmap::IndexMap;
+use num_bigint::{BigInt, Sign};
+
+use crate::semantic::{
+    ast::{self, Layout, StorageClass},
+    context::Context,
+};
+
+use super::{
+    dispatch::{flags, WORD_SIZE},
+    function::{self, value_type},
+    memory::{self, OUT_OF_MEMORY},
+    word::{self, Word},
+};
+
+/// A state variable the lowering of functions reads and writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateVariable {
+    /// The elementary type of its value, see [`value_type`]
+    pub ty: ast::Type,
+    pub storage_class: StorageClass,
+    pub slot: Slot,
+    /// See [`Layout::offset`]
+    pub offset: u8,
+}
+
+/// Where the value of a state variable is stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Slot {
+    /// The slot of the layout of the contract
+    Fixed(BigInt),
+    /// The address of the key of an element of a mapping, see
+    /// [`emit_element_key`]
+    Element(Value),
+}
+
+impl StateVariable {
+    /// The state variable at the slot, `None` when its value is not held in
+    /// a word
+    pub fn new(layout: &Layout, ctx: &Context) -> Option<Self> {
+        Some(Self {
+            ty: value_type(&layout.ty, ctx)?,
+            storage_class: layout.storage_class,
+            slot: Slot::Fixed(layout.slot.clone()),
+            offset: layout.offset,
+        })
+    }
+
+    /// The number of bytes of its slot the value takes
+    fn bytes(&self) -> u8 {
+        match self.ty {
+            ast::Type::Bool => 1,
+            ref ty => (function::bits(ty) / 8) as u8,
+        }
+    }
+}
+
+/// The key of the slot of a state variable
+pub fn key(layout: &Layout) -> [u8; 32] {
+    slot_key(layout.storage_class, &layout.slot)
+}
+
+/// The key of a slot of the storage class
+pub fn slot_key(storage_class: StorageClass, slot: &BigInt) -> [u8; 32] {
+    let mut key = [0; 32];
+    key[0] = match storage_class {
+        StorageClass::Persistent => 0,
+        StorageClass::Temporary => 1,
+        StorageClass::Instance => 2,
+    };
+
+    // A layout never has as many as 2^248 slots
+    let (sign, bytes) = slot.to_bytes_be();
+    assert!(sign != Sign::Minus && bytes.len() < 32, "slot {slot} out of range");
+    key[32 - bytes.len()..].copy_from_slice(&bytes);
+    key
+}
+
+/// Emit the key in a stack slot, returning its address
+pub fn emit_key(builder: &mut FunctionBuilder, pointer_type: Type, key: &[u8; 32]) -> Value {
+    let slot = builder.create_sized_stack_slot(StackSlotData::new(
+        StackSlotKind::ExplicitSlot,
+        WORD_SIZE as u32,
+        3,
+    ));
+    let addr = builder.ins().stack_addr(pointer_type, slot, 0);
+
+    let flags = flags().with_endianness(Endianness::Big);
+    for (offset, word) in key.chunks_exact(8).enumerate() {
+        let word = builder.ins().iconst(types::I64, i64::from_be_bytes(word.try_into().unwrap()));
+        builder.ins().store(flags, word, addr, offset as i32 * 8);
+    }
+    addr
+}
+
+/// The host functions which load and store a word of the storage class
+pub fn host_functions(storage_class: StorageClass) -> (&'static str, &'static str) {
+    match storage_class {
+        StorageClass::Temporary => ("transient_load", "transient_store"),
+        StorageClass::Persistent | StorageClass::Instance => ("storage_load", "storage_store"),
+    }
+}
+
+/// Emit the load of the slot into the word at `value`
+pub fn emit_load(
+    builder: &mut FunctionBuilder,
+    pointer_type: Type,
+    host: &IndexMap<&'static str, FuncRef>,
+    layout: &Layout,
+    value: Value,
+) {
+    let (load, _) = host_functions(layout.storage_class);
+    let key = emit_key(builder, pointer_type, &key(layout));
+    builder.ins().call(host[load], &[key, value]);
+}
+
+/// Emit the store of the word at `value` into the slot
+pub fn emit_store(
+    builder: &mut FunctionBuilder,
+    pointer_type: Type,
+    host: &IndexMap<&'static str, FuncRef>,
+    layout: &Layout,
+    value: Value,
+) {
+    let (_, store) = host_functions(layout.storage_class);
+    let key = emit_key(builder, pointer_type, &key(layout));
+    builder.ins().call(host[store], &[key, value]);
+}
+
+/// Emit the read of a state variable, giving its value as the lowering holds
+/// it, see [`load_word`](word::load_word) for `swap`
+pub fn emit_read(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    swap: bool,
+    host: &IndexMap<&'static str, FuncRef>,
+    var: &StateVariable,
+) -> Word {
+    let key = emit_slot_key(builder, config, swap, var);
+    let mut word = load_slot(builder, config, swap, host, var.storage_class, key);
+
+    let bytes = var.bytes();
+    if bytes < 32 {
+        word = word::shr_imm(builder, word, 8 * u16::from(var.offset));
+        word = word::truncate(builder, word, 8 * u16::from(bytes));
+    }
+
+    match var.ty {
+        ast::Type::Int(bits) => word::sign_extend(builder, word, bits),
+        _ => word,
+    }
+}
+
+/// Emit the write of a state variable, from its value as the lowering holds
+/// it. The other values packed in the slot are kept.
+pub fn emit_write(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    swap: bool,
+    host: &IndexMap<&'static str, FuncRef>,
+    var: &StateVariable,
+    value: Word,
+) {
+    let mut word = match var.ty {
+        ast::Type::Int(bits) => word::truncate(builder, value, bits),
+        _ => value,
+    };
+
+    let key = emit_slot_key(builder, config, swap, var);
+    let bytes = var.bytes();
+    if bytes < 32 {
+        let slot = load_slot(builder, config, swap, host, var.storage_class, key);
+        let mut mask = [0xff; 32];
+        let end = 32 - usize::from(var.offset);
+        mask[end - usize::from(bytes)..end].fill(0);
+        let mask = word::constant(builder, &mask);
+        let others = word::bitwise(builder, slot, mask, |ins, a, b| ins.band(a, b));
+        let value = word::shl_imm(builder, word, 8 * u16::from(var.offset));
+        word = word::bitwise(builder, others, value, |ins, a, b| ins.bor(a, b));
+    }
+
+    let value = word::spill(builder, config, swap, word);
+    let (_, store) = host_functions(var.storage_class);
+    builder.ins().call(host[store], &[key, value]);
+}
+
+/// Emit the key of the slot of a state variable on the stack, returning its
+/// address
+fn emit_slot_key(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    swap: bool,
+    var: &StateVariable,
+) -> Value {
+    match &var.slot {
+        Slot::Fixed(slot) => {
+            let key = word::constant(builder, &slot_key(var.storage_class, slot));
+            word::spill(builder, config, swap, key)
+        }
+        Slot::Element(key) => *key,
+    }
+}
+
+/// Load the word of the slot at `key`
+fn load_slot(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    swap: bool,
+    host: &IndexMap<&'static str, FuncRef>,
+    storage_class: StorageClass,
+    key: Value,
+) -> Word {
+    let (load, _) = host_functions(storage_class);
+    let value = word::stack_word(builder, config);
+    builder.ins().call(host[load], &[key, value]);
+    word::load_word(builder, swap, value)
+}
+
+/// A key of a mapping, which is hashed as its word if it is a value, and as its
+/// data, unpadded, if it is a string or `bytes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingKey {
+    /// The address of the word of the key
+    Word(Value),
+    /// The address of the string or `bytes` in memory
+    Bytes(Value),
+}
+
+impl MappingKey {
+    /// The key at `value` of a mapping whose keys are of the type
+    pub fn new(ty: &ast::Type, value: Value) -> Self {
+        match ty {
+            ast::Type::String | ast::Type::DynamicBytes => MappingKey::Bytes(value),
+            _ => MappingKey::Word(value),
+        }
+    }
+}
+
+/// Emit the key of the element of the mapping whose key is at `mapping`,
+/// taking a key for each nested mapping, outermost first, and giving the
+/// address of the key of the element
+pub fn emit_element_key(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    big_endian: bool,
+    host: &IndexMap<&'static str, FuncRef>,
+    mapping: Value,
+    keys: &[MappingKey],
+) -> Value {
+    let pointer_type = config.pointer_type();
+
+    keys.iter().fold(mapping, |parent, key| {
+        let (data, len) = match *key {
+            MappingKey::Word(word) => {
+                let slot = builder.create_sized_stack_slot(StackSlotData::new(
+                    StackSlotKind::ExplicitSlot,
+                    2 * WORD_SIZE as u32,
+                    3,
+                ));
+                let data = builder.ins().stack_addr(pointer_type, slot, 0);
+                copy_word(builder, word, data, 0);
+                copy_word(builder, parent, data, WORD_SIZE as i32);
+                (data, builder.ins().iconst(types::I32, 2 * WORD_SIZE))
+            }
+            MappingKey::Bytes(bytes) => {
+                // The allocation traps unless the data fits in the memory, so
+                // its length fits in an `i32`
+                let len = memory::load_i64(builder, big_endian, bytes, 24);
+                let word = builder.ins().iconst(types::I64, WORD_SIZE);
+                let size = builder.ins().uadd_overflow_trap(len, word, OUT_OF_MEMORY);
+                let memory = memory::emit_base(builder, big_endian, host["memory"]);
+                let data = memory::emit_alloc(builder, config, big_endian, memory, size);
+
+                let src = builder.ins().iadd_imm_u(bytes, WORD_SIZE);
+                let len = memory::pointer(builder, config, len);
+                builder.call_memcpy(config, data, src, len);
+                let end = builder.ins().iadd(data, len);
+                copy_word(builder, parent, end, 0);
+                (data, builder.ins().ireduce(types::I32, size))
+            }
+        };
+
+        let slot = builder.create_sized_stack_slot(StackSlotData::new(
+            StackSlotKind::ExplicitSlot,
+            WORD_SIZE as u32,
+            3,
+        ));
+        let hash = builder.ins().stack_addr(pointer_type, slot, 0);
+        builder.ins().call(host["keccak256"], &[data, len, hash]);
+        hash
+    })
+}
+
+/// Emit the copy of the word at `src` to `offset` bytes into `dest`
+fn copy_word(builder: &mut FunctionBuilder, src: Value, dest: Value, offset: i32) {
+    for limb in 0..4 {
+        let value = builder.ins().load(types::I64, flags(), src, limb * 8);
+        builder.ins().store(flags(), value, dest, offset + limb * 8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codegen::tests::{build, calls, entry, import_host, signature, CONFIG},
+        semantic::ast::Type as SolType,
+    };
+
+    fn layout(storage_class: StorageClass, slot: u64) -> Layout {
+        Layout {
+            slot: slot.into(),
+            offset: 0,
+            contract_no: 0,
+            var_no: 0,
+            ty: SolType::Uint(256),
+            storage_class,
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let key = key(&layout(StorageClass::Persistent, 0x1234));
+        assert_eq!(key[..30], [0; 30]);
+        assert_eq!(key[30..], [0x12, 0x34]);
+
+        // The classes have a storage each
+        let keys = [StorageClass::Persistent, StorageClass::Temporary, StorageClass::Instance]
+            .map(|class| super::key(&layout(class, 7)));
+        assert_eq!(keys.map(|key| key[0]), [0, 1, 2]);
+        assert!(keys.iter().all(|key| key[31] == 7));
+    }
+
+    #[test]
+    fn test_state_variables() {
+        let source = r#"
+            enum Kind { A, B }
+            contract Base {
+                uint8 decimals;
+            }
+            contract Token is Base {
+                uint constant MAX = 10;
+                address owner;
+                bool temporary locked;
+                bytes12 tag;
+                mapping(address => uint) balances;
+                int64 supply;
+                Kind kind;
+                string name;
+            }
+        "#;
+        let ctx = super::super::tests::analyze(source);
+        let contract = ctx.contracts.iter().find(|c| c.id.name == "Token").unwrap();
+
+        let variables = contract
+            .layout
+            .iter()
+            .map(|layout| {
+                let name = &ctx.contracts[layout.contract_no].variables[layout.var_no].name;
+                let var = StateVariable::new(layout, &ctx);
+                (name.as_str(), var.map(|var| (var.ty, var.storage_class, var.slot, var.offset)))
+            })
+            .collect::<Vec<_>>();
+        let persistent = |ty, slot: u32, offset| {
+            Some((ty, StorageClass::Persistent, Slot::Fixed(slot.into()), offset))
+        };
+        assert_eq!(
+            variables,
+            [
+                // The variables of the bases come first
+                ("decimals", persistent(ast::Type::Uint(8), 0, 0)),
+                ("owner", persistent(ast::Type::Address(false), 0, 1)),
+                (
+                    "locked",
+                    Some((ast::Type::Bool, StorageClass::Temporary, Slot::Fixed(0.into()), 0))
+                ),
+                ("tag", persistent(ast::Type::Bytes(12), 1, 0)),
+                // Mappings and strings are not held in words
+                ("balances", None),
+                ("supply", persistent(ast::Type::Int(64), 3, 0)),
+                // An enum is its underlying type
+                ("kind", persistent(ast::Type::Uint(8), 3, 8)),
+                ("name", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emit_packed_write() {
+        let func = build(signature(&[], &[]), |builder| {
+            let host = import_host(builder);
+            entry(builder);
+
+            // `flag = flag` on a `bytes2 flag` at byte 3 of its slot
+            let var = StateVariable {
+                ty: ast::Type::Bytes(2),
+                storage_class: StorageClass::Persistent,
+                slot: Slot::Fixed(1.into()),
+                offset: 3,
+            };
+            let word = emit_read(builder, CONFIG, true, &host, &var);
+            emit_write(builder, CONFIG, true, &host, &var, word);
+            builder.ins().return_(&[]);
+        });
+        let ir = func.display().to_string();
+        // The slot is read again to keep the other values
+        assert_eq!((calls(&ir, "storage_load"), calls(&ir, "storage_store")), (2, 1), "{ir}");
+        // The mask clears bytes 3 and 4 of the low limb
+        let mask = format!("iconst.i64 {}", 0xffff_ff00_00ff_ffff_u64 as i64);
+        assert!(ir.contains(&mask), "{ir}");
+    }
+
+    #[test]
+    fn test_emit_key() {
+        let func = build(signature(&[], &[types::I64]), |builder| {
+            entry(builder);
+            let key = key(&layout(StorageClass::Instance, 1));
+            let addr = emit_key(builder, types::I64, &key);
+            builder.ins().return_(&[addr]);
+        });
+        let ir = func.display().to_string();
+        assert_eq!(ir.matches("store notrap big").count(), 4, "{ir}");
+        assert!(ir.contains("iconst.i64 0x0200_0000_0000_0000"), "{ir}");
+        assert!(ir.contains("iconst.i64 1"), "{ir}");
+    }
+
+    #[test]
+    fn test_emit_transient() {
+        let func = build(signature(&[types::I64], &[]), |builder| {
+            let host = import_host(builder);
+            let value = entry(builder)[0];
+
+            // `lock = !lock` on a `bool temporary lock`
+            let lock = layout(StorageClass::Temporary, 0);
+            emit_load(builder, types::I64, &host, &lock, value);
+            emit_store(builder, types::I64, &host, &lock, value);
+            builder.ins().return_(&[]);
+        });
+        let ir = func.display().to_string();
+        assert_eq!((calls(&ir, "transient_load"), calls(&ir, "transient_store")), (1, 1), "{ir}");
+        assert_eq!((calls(&ir, "storage_load"), calls(&ir, "storage_store")), (0, 0), "{ir}");
+        assert_eq!(host_functions(StorageClass::Instance), ("storage_load", "storage_store"));
+    }
+
+    #[test]
+    fn test_emit_element_key() {
+        let func = build(signature(&[types::I64; 3], &[types::I64]), |builder| {
+            let host = import_host(builder);
+            let [mapping, owner, name] = entry(builder).try_into().unwrap();
+
+            // `m[owner][name]` of `mapping(address => mapping(string => uint)) m`
+            let keys = [
+                MappingKey::new(&SolType::Address(false), owner),
+                MappingKey::new(&SolType::String, name),
+            ];
+            assert_eq!(keys, [MappingKey::Word(owner), MappingKey::Bytes(name)]);
+            let key = emit_element_key(builder, CONFIG, false, &host, mapping, &keys);
+            builder.ins().return_(&[key]);
+        });
+        let ir = func.display().to_string();
+        assert_eq!(calls(&ir, "keccak256"), 2, "{ir}");
+        // Only the string is copied to memory
+        assert_eq!(calls(&ir, "memory"), 1, "{ir}");
+        // The string is copied before the key of the outer element
+        assert!(ir.contains("%Memcpy"), "{ir}");
+    }
+}
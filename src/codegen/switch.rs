@@ -0,0 +1,211 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lowering of `if`/`else if` chains on one value to jump tables.
+//!
+//! Hand-rolled routers often compare the same value against a list of
+//! constants:
+//!
+//! ```solidity
+//! if (op == 0) { ... } else if (op == 1) { ... } else if (op == 2) { ... } else { ... }
+//! ```
+//!
+//! Evaluated in order, the last branch takes a comparison per case before it.
+//! Such a chain is lowered as a switch instead, which Cranelift turns into a
+//! `br_table` jump for dense values and a search tree for sparse ones. The
+//! constants may be integer literals or the values of enums, which the
+//! semantic analysis resolves to numbers.
+
+use cranelift::{
+    frontend::Switch as CraneliftSwitch,
+    prelude::{Block, FunctionBuilder, Value},
+};
+use num_traits::ToPrimitive;
+
+use crate::semantic::ast::{Expression, Statement};
+
+/// The fewest cases for which a switch is worth it
+pub const MIN_CASES: usize = 3;
+
+/// An `if`/`else if` chain comparing the same value against constants
+#[derive(Debug)]
+pub struct Switch<'a> {
+    /// The value compared, a local or state variable
+    pub scrutinee: &'a Expression,
+    /// The constants in the order of the chain, with their branches
+    pub cases: Vec<(u64, &'a [Statement])>,
+    /// The final `else` branch, empty without one
+    pub default: &'a [Statement],
+}
+
+impl<'a> Switch<'a> {
+    /// Detect a chain of at least [`MIN_CASES`] distinct constants of at most
+    /// 64 bits
+    pub fn detect(stmt: &'a Statement) -> Option<Self> {
+        let mut scrutinee: Option<(&'a Expression, Place)> = None;
+        let mut cases: Vec<(u64, &'a [Statement])> = Vec::new();
+        let mut next: &'a [Statement] = std::slice::from_ref(stmt);
+
+        while let [Statement::If(_, _, cond, then, otherwise)] = next {
+            let Expression::Equal { left, right, .. } = cond else {
+                break;
+            };
+            let (expr, value) = match (constant(right), constant(left)) {
+                (Some(value), _) => (left.as_ref(), value),
+                (None, Some(value)) => (right.as_ref(), value),
+                (None, None) => break,
+            };
+
+            // The value must be the same, and evaluating it again must not
+            // have side effects
+            let Some(place) = place(expr) else {
+                break;
+            };
+            match scrutinee {
+                Some((_, scrutinee)) if scrutinee != place => break,
+                _ => scrutinee = Some((expr, place)),
+            }
+            if cases.iter().any(|(case, _)| *case == value) {
+                return None;
+            }
+
+            cases.push((value, then));
+            next = otherwise;
+        }
+
+        if cases.len() < MIN_CASES {
+            return None;
+        }
+
+        Some(Self { scrutinee: scrutinee?.0, cases, default: next })
+    }
+}
+
+/// Emit the jump to the block of the case matching the value, or `otherwise`
+pub fn emit(
+    builder: &mut FunctionBuilder,
+    value: Value,
+    cases: &[(u128, Block)],
+    otherwise: Block,
+) {
+    let mut switch = CraneliftSwitch::new();
+    for (case, block) in cases {
+        switch.set_entry(*case, *block);
+    }
+    switch.emit(builder, value, otherwise);
+}
+
+/// A constant of at most 64 bits, an enum value being a number too
+fn constant(expr: &Expression) -> Option<u64> {
+    match expr {
+        Expression::NumberLiteral { value, .. } => value.to_u64(),
+        _ => None,
+    }
+}
+
+/// The variable a scrutinee reads, by number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Place {
+    Local(usize),
+    State(usize, usize),
+}
+
+/// The variable of a local variable, or of a state variable read
+fn place(expr: &Expression) -> Option<Place> {
+    match expr {
+        Expression::Variable { var_no, .. } => Some(Place::Local(*var_no)),
+        Expression::StorageLoad { expr, .. } => match expr.as_ref() {
+            Expression::StorageVariable { contract_no, var_no, .. } => {
+                Some(Place::State(*contract_no, *var_no))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::tests::{analyze, build, entry, signature};
+    use cranelift::prelude::{types, InstBuilder};
+
+    /// The cases of the switch detected in the first statement of `f`
+    fn cases(body: &str) -> Option<(Vec<u64>, bool)> {
+        let source = format!(
+            "enum State {{ Idle, Active, Done }}
+            contract C {{
+                State state;
+                function g() internal returns (uint) {{ return 1; }}
+                function f(uint op, uint other) public {{ {body} }}
+            }}"
+        );
+        let ctx = analyze(&source);
+        let func = ctx.functions.iter().find(|func| func.id.name == "f").unwrap();
+        let switch = Switch::detect(&func.body[0])?;
+        Some((switch.cases.iter().map(|(case, _)| *case).collect(), !switch.default.is_empty()))
+    }
+
+    #[test]
+    fn test_detect_switch() {
+        assert_eq!(
+            cases("if (op == 0) g(); else if (1 == op) g(); else if (op == 0x02) g(); else g();"),
+            Some((vec![0, 1, 2], true))
+        );
+        assert_eq!(
+            cases(
+                "if (state == State.Done) g(); else if (state == State.Idle) g(); \
+                 else if (state == State.Active) g();"
+            ),
+            Some((vec![2, 0, 1], false))
+        );
+
+        // Too short, different values, calls evaluated each time, or the same
+        // value twice
+        for body in [
+            "if (op == 0) g(); else if (op == 1) g();",
+            "if (op == 0) g(); else if (other == 1) g(); else if (op == 2) g();",
+            "if (g() == 0) g(); else if (g() == 1) g(); else if (g() == 2) g();",
+            "if (op == 0) g(); else if (op == 0) g(); else if (op == 2) g();",
+        ] {
+            assert_eq!(cases(body), None, "{body}");
+        }
+    }
+
+    #[test]
+    fn test_emit_switch() {
+        let func = build(signature(&[types::I64], &[types::I64]), |builder| {
+            let value = entry(builder)[0];
+
+            let mut cases = Vec::new();
+            for case in 0..4 {
+                let block = builder.create_block();
+                cases.push((case, block));
+            }
+            let otherwise = builder.create_block();
+            emit(builder, value, &cases, otherwise);
+
+            for (case, block) in cases.iter().copied().chain([(99, otherwise)]) {
+                builder.switch_to_block(block);
+                let result = builder.ins().iconst(types::I64, case as i64);
+                builder.ins().return_(&[result]);
+            }
+        });
+
+        // One jump replaces the chain of comparisons
+        let ir = func.display().to_string();
+        assert!(ir.contains("br_table"), "{ir}");
+        assert!(!ir.contains("icmp_imm eq"), "{ir}");
+    }
+}
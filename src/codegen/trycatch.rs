@@ -0,0 +1,319 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lowering of `try`/`catch` on the result of an external call.
+//!
+//! When the call succeeds, execution goes on in the block of the `returns`
+//! clause. When it reverts, its return data is the reason, which selects the
+//! catch clause:
+//!
+//! - `catch Error(string memory reason)` for data encoded as `Error(string)`, as `revert("...")`
+//!   and `require(cond, "...")` give, getting a pointer to the string, which starts with its length
+//! - `catch Panic(uint code)` for data encoded as `Panic(uint256)`, as failed assertions and
+//!   arithmetic give, getting a pointer to the code
+//! - `catch (bytes memory data)` or `catch { ... }` for any other data, and for the above without
+//!   their clause or which fail to decode, getting the data and its length
+//!
+//! Without a matching clause the call reverts with the same data.
+
+use cranelift::{
+    codegen::ir::FuncRef,
+    prelude::{types, Block, FunctionBuilder, InstBuilder, IntCC, TrapCode, Type, Value},
+};
+
+use crate::semantic::ast::{self, CatchClause, TryCatch};
+
+use super::dispatch::{high_bits, load_u32, SELECTOR_SIZE, WORD_SIZE};
+
+/// The selector of `Error(string)`
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// The selector of `Panic(uint256)`
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Trap when the host function reverting the call returns
+pub(super) const REVERT_RETURNED: TrapCode = TrapCode::unwrap_user(1);
+
+/// The catch clauses of a `try` statement, by the data they handle
+#[derive(Debug, Default)]
+pub struct Catches<'a> {
+    pub error: Option<&'a CatchClause>,
+    pub panic: Option<&'a CatchClause>,
+    /// The clause catching any data
+    pub other: Option<&'a CatchClause>,
+}
+
+impl<'a> Catches<'a> {
+    pub fn new(try_catch: &'a TryCatch) -> Self {
+        let mut catches = Self { other: try_catch.catch_all.as_ref(), ..Self::default() };
+
+        // The semantic analysis tells the clauses apart by their parameter
+        for clause in &try_catch.errors {
+            match clause.param.as_ref().map(|param| &param.ty) {
+                Some(ast::Type::String) => catches.error = Some(clause),
+                Some(ast::Type::Uint(256)) => catches.panic = Some(clause),
+                _ => (),
+            }
+        }
+
+        catches
+    }
+}
+
+/// The blocks execution goes on in after the call, matching the [`Catches`].
+/// The `error` and `panic` blocks take a pointer parameter, the `other` block
+/// a pointer and a 32 bit length.
+#[derive(Debug, Clone, Copy)]
+pub struct Targets {
+    pub success: Block,
+    pub error: Option<Block>,
+    pub panic: Option<Block>,
+    pub other: Option<Block>,
+}
+
+/// The data returned by a call
+#[derive(Debug, Clone, Copy)]
+pub struct ReturnData {
+    pub data: Value,
+    /// A 32 bit length
+    pub len: Value,
+}
+
+/// Emit the jump to the target for the status of a call, non-zero when it
+/// reverted, calling `revert` when no clause catches the data. The data
+/// returned is only emitted when the call reverted. The blocks created are
+/// sealed, the targets are left to the caller.
+pub fn emit(
+    builder: &mut FunctionBuilder,
+    status: Value,
+    return_data: impl FnOnce(&mut FunctionBuilder) -> ReturnData,
+    targets: &Targets,
+    revert: FuncRef,
+    pointer_type: Type,
+    big_endian: bool,
+) {
+    let failure = builder.create_block();
+    let other = builder.create_block();
+    builder.ins().brif(status, failure, &[], targets.success, &[]);
+    builder.switch_to_block(failure);
+    builder.seal_block(failure);
+    let ReturnData { data, len } = return_data(builder);
+
+    // Continue in a new block, unless the condition holds
+    let other_if = |builder: &mut FunctionBuilder, condition: Value| {
+        let next = builder.create_block();
+        builder.ins().brif(condition, other, &[], next, &[]);
+        builder.switch_to_block(next);
+        builder.seal_block(next);
+    };
+
+    if targets.error.is_some() || targets.panic.is_some() {
+        let short = builder.ins().icmp_imm_u(IntCC::UnsignedLessThan, len, SELECTOR_SIZE);
+        other_if(builder, short);
+        let selector = load_u32(builder, big_endian, data, 0);
+
+        if let Some(error) = targets.error {
+            let decode = builder.create_block();
+            let next = builder.create_block();
+            let is_error =
+                builder.ins().icmp_imm_u(IntCC::Equal, selector, selector_value(ERROR_SELECTOR));
+            builder.ins().brif(is_error, decode, &[], next, &[]);
+
+            builder.switch_to_block(decode);
+            builder.seal_block(decode);
+            let short =
+                builder.ins().icmp_imm_u(IntCC::UnsignedLessThan, len, SELECTOR_SIZE + WORD_SIZE);
+            other_if(builder, short);
+
+            // The word after the selector is the offset of the string, which
+            // starts with its length
+            let word = builder.ins().iadd_imm_u(data, SELECTOR_SIZE);
+            let high = high_bits(builder, word);
+            other_if(builder, high);
+            let offset = load_u32(builder, big_endian, word, 28);
+            let offset = builder.ins().uextend(types::I64, offset);
+            let len = builder.ins().uextend(types::I64, len);
+            let end = builder.ins().iadd_imm_u(offset, SELECTOR_SIZE + WORD_SIZE);
+            let outside = builder.ins().icmp(IntCC::UnsignedGreaterThan, end, len);
+            other_if(builder, outside);
+
+            let offset = match pointer_type {
+                types::I64 => offset,
+                ty => builder.ins().ireduce(ty, offset),
+            };
+            let string = builder.ins().iadd(word, offset);
+            let high = high_bits(builder, string);
+            other_if(builder, high);
+            let string_len = load_u32(builder, big_endian, string, 28);
+            let string_len = builder.ins().uextend(types::I64, string_len);
+            let end = builder.ins().iadd(end, string_len);
+            let outside = builder.ins().icmp(IntCC::UnsignedGreaterThan, end, len);
+            other_if(builder, outside);
+            builder.ins().jump(error, &[string.into()]);
+
+            builder.switch_to_block(next);
+            builder.seal_block(next);
+        }
+
+        if let Some(panic) = targets.panic {
+            let decode = builder.create_block();
+            let is_panic =
+                builder.ins().icmp_imm_u(IntCC::Equal, selector, selector_value(PANIC_SELECTOR));
+            builder.ins().brif(is_panic, decode, &[], other, &[]);
+
+            builder.switch_to_block(decode);
+            builder.seal_block(decode);
+            let short =
+                builder.ins().icmp_imm_u(IntCC::UnsignedLessThan, len, SELECTOR_SIZE + WORD_SIZE);
+            other_if(builder, short);
+            let code = builder.ins().iadd_imm_u(data, SELECTOR_SIZE);
+            builder.ins().jump(panic, &[code.into()]);
+        } else {
+            builder.ins().jump(other, &[]);
+        }
+    } else {
+        builder.ins().jump(other, &[]);
+    }
+
+    builder.switch_to_block(other);
+    builder.seal_block(other);
+    match targets.other {
+        Some(block) => {
+            builder.ins().jump(block, &[data.into(), len.into()]);
+        }
+        None => {
+            builder.ins().call(revert, &[data, len]);
+            builder.ins().trap(REVERT_RETURNED);
+        }
+    }
+}
+
+fn selector_value(selector: [u8; 4]) -> i64 {
+    u32::from_be_bytes(selector).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abi::ethereum,
+        codegen::{
+            contract_code, gen_contracts,
+            listing::Listing,
+            mangle::Symbol,
+            tests::{analyze, build, entry, import, signature},
+            CodegenOptions,
+        },
+        diagnostics::Diagnostics,
+        semantic::ast::Statement,
+    };
+
+    #[test]
+    fn test_catch_clauses() {
+        assert_eq!(ethereum::selector("Error(string)"), ERROR_SELECTOR);
+        assert_eq!(ethereum::selector("Panic(uint256)"), PANIC_SELECTOR);
+
+        let source = r#"
+            contract Child {}
+            contract Parent {
+                function make() public {
+                    try new Child() returns (Child c) {}
+                    catch Error(string memory reason) {}
+                    catch (bytes memory data) {}
+                }
+            }
+        "#;
+        let ctx = analyze(source);
+        let func = ctx.functions.iter().find(|func| func.id.name == "make").unwrap();
+        let Statement::TryCatch(_, _, try_catch) = &func.body[0] else {
+            panic!("expected a try statement");
+        };
+
+        let catches = Catches::new(try_catch);
+        let name = |clause: Option<&CatchClause>| {
+            clause.and_then(|clause| clause.param.as_ref()?.id.as_ref()).map(|id| id.name.clone())
+        };
+        assert_eq!(name(catches.error), Some("reason".into()));
+        assert!(catches.panic.is_none());
+        assert_eq!(name(catches.other), Some("data".into()));
+    }
+
+    #[test]
+    fn test_emit_catch() {
+        let params = [types::I32, types::I64, types::I32];
+        let func = build(signature(&params, &[types::I32]), |builder| {
+            let revert = import(builder, "revert", &[types::I64, types::I32], &[]);
+            let [status, data, len] = entry(builder).try_into().unwrap();
+
+            // Each target returns a different value
+            let targets = Targets {
+                success: builder.create_block(),
+                error: Some(builder.create_block()),
+                panic: Some(builder.create_block()),
+                other: None,
+            };
+            for block in [targets.error, targets.panic].into_iter().flatten() {
+                builder.append_block_param(block, types::I64);
+            }
+            let data = |_: &mut FunctionBuilder| ReturnData { data, len };
+            emit(builder, status, data, &targets, revert, types::I64, false);
+
+            for (result, block) in
+                [(0, targets.success), (1, targets.error.unwrap()), (2, targets.panic.unwrap())]
+            {
+                builder.switch_to_block(block);
+                let result = builder.ins().iconst(types::I32, result);
+                builder.ins().return_(&[result]);
+            }
+        });
+
+        // Without a clause for other data, the call reverts with it
+        let ir = func.display().to_string();
+        assert!(ir.contains("call fn0(v1, v2)"), "{ir}");
+        assert!(ir.contains("trap user1"), "{ir}");
+    }
+
+    #[test]
+    fn test_try_new() {
+        let source = r#"
+            contract Child {
+                constructor(uint v) { require(v != 0, "zero"); }
+            }
+            contract Parent {
+                Child public child;
+                uint public failures;
+                function make(uint v) public {
+                    try new Child(v) returns (Child c) {
+                        child = c;
+                    } catch Error(string memory) {
+                        failures += 1;
+                    }
+                }
+            }
+        "#;
+        let ctx = analyze(source);
+        let mut listing = Listing::default();
+        let mut diagnostics = Diagnostics::default();
+        let options = CodegenOptions::default();
+        gen_contracts(&[1], &ctx, &options, &mut listing, &mut diagnostics).unwrap();
+
+        // The child is generated first, and the function trying to create it
+        // is exported by the parent
+        let func = ctx.functions.iter().find(|func| func.id.name == "make").unwrap();
+        let symbol = Symbol::function(func, &ctx).mangle();
+        let code = contract_code(&ctx, "Parent").unwrap();
+        assert!(contract_code(&ctx, "Child").is_some());
+        assert!(code.windows(symbol.len()).any(|name| name == symbol.as_bytes()));
+    }
+}
@@ -0,0 +1,354 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Unrolling of loops with a small constant number of iterations.
+//!
+//! A loop `for (uint i = 0; i < 2; i++) { f(i); }` becomes the block
+//! `{ uint i = 0; { f(i); } i++; { f(i); } i++; }`, which needs no condition
+//! or branch back at all. The step after the last iteration is kept, as its
+//! overflow check may revert. Only loops whose body has no `continue` are unrolled,
+//! and only while the copies of the body stay within a budget of statements.
+//!
+//! The loops are those of the bodies resolved by the semantic analysis, see
+//! [`loops`](super::loops) for the loops whose number of iterations is
+//! constant.
+
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::semantic::ast::{Expression, Recurse, Statement};
+
+use super::{
+    dce::expressions,
+    loops::{count, type_range, Comparison},
+};
+
+/// The default budget, large enough for a few iterations of a typical body
+pub const DEFAULT_UNROLL_BUDGET: usize = 64;
+
+/// Unroll the loops of the statements which fit the budget, returning the
+/// number of loops unrolled. Inner loops are unrolled first, so their outer
+/// loops may be unrolled as well.
+pub fn unroll_loops(stmts: &mut [Statement], budget: usize) -> usize {
+    if budget == 0 {
+        return 0;
+    }
+
+    let mut unrolled = 0;
+    for stmt in stmts {
+        statement(stmt, budget, &mut unrolled);
+    }
+    unrolled
+}
+
+fn statement(stmt: &mut Statement, budget: usize, unrolled: &mut usize) {
+    match stmt {
+        Statement::Block { statements: body, .. } |
+        Statement::While(_, _, _, body) |
+        Statement::DoWhile(_, _, body, _) => *unrolled += unroll_loops(body, budget),
+        Statement::If(_, _, _, then, otherwise) => {
+            *unrolled += unroll_loops(then, budget);
+            *unrolled += unroll_loops(otherwise, budget);
+        }
+        Statement::For { body, .. } => *unrolled += unroll_loops(body, budget),
+        Statement::TryCatch(_, _, try_catch) => {
+            *unrolled += unroll_loops(&mut try_catch.ok_stmt, budget);
+            for clause in try_catch.errors.iter_mut().chain(&mut try_catch.catch_all) {
+                *unrolled += unroll_loops(&mut clause.stmt, budget);
+            }
+        }
+        _ => (),
+    }
+
+    if let Some(block) = unroll(stmt, budget) {
+        *stmt = block;
+        *unrolled += 1;
+    }
+}
+
+/// The block replacing a `for` loop, if it can be unrolled within the budget
+fn unroll(stmt: &Statement, budget: usize) -> Option<Statement> {
+    let count = trip_count(stmt)?;
+    let Statement::For { loc, init, next: Some(next), body, .. } = stmt else {
+        return None;
+    };
+
+    let size = body.iter().map(size).sum::<usize>();
+    if body.iter().any(continues) || count.saturating_mul(size as u64) > budget as u64 {
+        return None;
+    }
+
+    let mut statements = init.clone();
+    for _ in 0..count {
+        statements.push(Statement::Block { loc: *loc, unchecked: false, statements: body.clone() });
+        statements.push(Statement::Expression(*loc, true, next.clone()));
+    }
+
+    Some(Statement::Block { loc: *loc, unchecked: false, statements })
+}
+
+/// The number of iterations of a `for` loop, if it is constant: its counter
+/// is an integer declared with a constant, compared against a constant and
+/// stepped by a constant which keeps it in the range of its type, and the
+/// body neither writes the counter nor leaves the loop early
+fn trip_count(stmt: &Statement) -> Option<u64> {
+    let Statement::For { init, cond: Some(cond), next: Some(next), body, .. } = stmt else {
+        return None;
+    };
+    let [Statement::VariableDecl(_, counter, param, Some(start))] = init.as_slice() else {
+        return None;
+    };
+    let (min, max) = type_range(&param.ty)?;
+
+    let start = constant(start)?;
+    let (comparison, limit) = comparison(cond, *counter)?;
+    let step = step(next, *counter)?;
+
+    if !keeps_counter(body, *counter) {
+        return None;
+    }
+
+    let count = count(&start, comparison, &limit, &step)?;
+
+    // The step after the last iteration still runs, and reverts if it takes
+    // the counter out of the range of its type, e.g. `i++` on a `uint8` 255
+    let last = &start + &count * &step;
+    if count.is_positive() && (last < min || last > max) {
+        return None;
+    }
+
+    count.to_u64()
+}
+
+/// The comparison of the counter with a constant in the condition
+fn comparison(cond: &Expression, counter: usize) -> Option<(Comparison, BigInt)> {
+    let (comparison, left, right) = match cond {
+        Expression::Less { left, right, .. } => (Comparison::Less, left, right),
+        Expression::LessEqual { left, right, .. } => (Comparison::LessEqual, left, right),
+        Expression::More { left, right, .. } => (Comparison::More, left, right),
+        Expression::MoreEqual { left, right, .. } => (Comparison::MoreEqual, left, right),
+        Expression::NotEqual { left, right, .. } => (Comparison::NotEqual, left, right),
+        _ => return None,
+    };
+
+    if is_counter(left, counter) {
+        Some((comparison, constant(right)?))
+    } else if is_counter(right, counter) {
+        let flipped = match comparison {
+            Comparison::Less => Comparison::More,
+            Comparison::LessEqual => Comparison::MoreEqual,
+            Comparison::More => Comparison::Less,
+            Comparison::MoreEqual => Comparison::LessEqual,
+            Comparison::NotEqual => Comparison::NotEqual,
+        };
+        Some((flipped, constant(left)?))
+    } else {
+        None
+    }
+}
+
+/// The constant added to the counter after each iteration
+fn step(next: &Expression, counter: usize) -> Option<BigInt> {
+    let step = match next {
+        Expression::PostIncrement { expr, .. } | Expression::PreIncrement { expr, .. }
+            if is_counter(expr, counter) =>
+        {
+            BigInt::from(1)
+        }
+        Expression::PostDecrement { expr, .. } | Expression::PreDecrement { expr, .. }
+            if is_counter(expr, counter) =>
+        {
+            BigInt::from(-1)
+        }
+        // `i += n` is resolved to `i = i + n`
+        Expression::Assign { left, right, .. } if is_counter(left, counter) => match right.as_ref()
+        {
+            Expression::Add { left, right, .. } if is_counter(left, counter) => constant(right)?,
+            Expression::Subtract { left, right, .. } if is_counter(left, counter) => {
+                -constant(right)?
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    (!step.is_zero()).then_some(step)
+}
+
+fn constant(expr: &Expression) -> Option<BigInt> {
+    match expr {
+        Expression::NumberLiteral { value, .. } => Some(value.clone()),
+        Expression::ZeroExt { expr, .. } | Expression::SignExt { expr, .. } => constant(expr),
+        _ => None,
+    }
+}
+
+/// Whether the expression is the counter, maybe extended to the type of the
+/// value it is compared with
+fn is_counter(expr: &Expression, counter: usize) -> bool {
+    match expr {
+        Expression::Variable { var_no, .. } => *var_no == counter,
+        Expression::ZeroExt { expr, .. } | Expression::SignExt { expr, .. } => {
+            is_counter(expr, counter)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the body of a loop runs to its end without writing the counter.
+/// Nested loops may break out of themselves.
+fn keeps_counter(body: &[Statement], counter: usize) -> bool {
+    let mut cx = Writes { counter, writes: false };
+    let mut worklist = body.iter().map(|stmt| (stmt, false)).collect::<Vec<_>>();
+
+    while let Some((stmt, nested)) = worklist.pop() {
+        match stmt {
+            Statement::Break(_) if !nested => return false,
+            Statement::Return(..) => return false,
+            Statement::Delete(_, _, expr) if is_counter(expr, counter) => return false,
+            _ => (),
+        }
+
+        for expr in expressions(stmt) {
+            expr.recurse(&mut cx, writes);
+        }
+
+        let nested = nested || is_loop(stmt);
+        worklist.extend(statements(stmt).into_iter().map(|stmt| (stmt, nested)));
+    }
+
+    !cx.writes
+}
+
+/// Whether an expression writes the counter
+struct Writes {
+    counter: usize,
+    writes: bool,
+}
+
+fn writes(expr: &Expression, cx: &mut Writes) -> bool {
+    match expr {
+        Expression::Assign { left: target, .. } |
+        Expression::PreIncrement { expr: target, .. } |
+        Expression::PreDecrement { expr: target, .. } |
+        Expression::PostIncrement { expr: target, .. } |
+        Expression::PostDecrement { expr: target, .. } => {
+            cx.writes |= is_counter(target, cx.counter);
+        }
+        _ => (),
+    }
+    true
+}
+
+fn is_loop(stmt: &Statement) -> bool {
+    matches!(stmt, Statement::For { .. } | Statement::While(..) | Statement::DoWhile(..))
+}
+
+/// The statements directly inside a statement
+fn statements(stmt: &Statement) -> Vec<&Statement> {
+    match stmt {
+        Statement::Block { statements, .. } |
+        Statement::While(_, _, _, statements) |
+        Statement::DoWhile(_, _, statements, _) => statements.iter().collect(),
+        Statement::If(_, _, _, then, otherwise) => then.iter().chain(otherwise).collect(),
+        Statement::For { init, body, .. } => init.iter().chain(body).collect(),
+        Statement::TryCatch(_, _, try_catch) => try_catch
+            .errors
+            .iter()
+            .chain(&try_catch.catch_all)
+            .flat_map(|clause| &clause.stmt)
+            .chain(&try_catch.ok_stmt)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The number of statements in a statement, itself included
+fn size(stmt: &Statement) -> usize {
+    1 + statements(stmt).into_iter().map(size).sum::<usize>()
+}
+
+/// Whether a `continue` in the statement belongs to the enclosing loop
+fn continues(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Continue(_) => true,
+        stmt if is_loop(stmt) => false,
+        stmt => statements(stmt).into_iter().any(continues),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The body of the function `f` of the source
+    fn body(source: &str) -> Vec<Statement> {
+        let ctx = super::super::tests::analyze(source);
+        ctx.functions.iter().find(|func| func.id.name == "f").unwrap().body.clone()
+    }
+
+    #[test]
+    fn test_unroll_loops() {
+        let source = r#"
+function f() pure returns (uint s) {
+    for (uint i = 0; i < 3; i++) { s += i; }
+    for (uint i = 0; i < 100; i++) { s += i; }
+    for (uint i = 0; i < 2; i++) { if (s > 1) continue; s += i; }
+}
+"#;
+        let mut statements = body(source);
+        assert_eq!(unroll_loops(&mut statements, 0), 0);
+        assert_eq!(unroll_loops(&mut statements, DEFAULT_UNROLL_BUDGET), 1);
+
+        // The first loop is now straight line code, the others are left alone
+        let Statement::Block { statements: unrolled, .. } = &statements[0] else {
+            panic!("expected the unrolled loop");
+        };
+        assert_eq!(unrolled.len(), 7);
+        assert!(matches!(unrolled[0], Statement::VariableDecl(..)));
+        assert!(matches!(
+            &unrolled[2],
+            Statement::Expression(_, _, Expression::PostIncrement { .. })
+        ));
+        assert!(matches!(
+            &unrolled[6],
+            Statement::Expression(_, _, Expression::PostIncrement { .. })
+        ));
+        assert!(matches!(statements[1], Statement::For { .. }));
+        assert!(matches!(statements[2], Statement::For { .. }));
+    }
+
+    #[test]
+    fn test_unroll_overflowing_step() {
+        // The last `i++` reverts, which the unrolled loop would skip
+        let source = r#"
+contract C {
+    function f() public pure returns (uint8 r) {
+        for (uint8 i = 250; i <= 255; i++) { r = i; }
+    }
+}
+"#;
+        assert_eq!(unroll_loops(&mut body(source), DEFAULT_UNROLL_BUDGET), 0);
+
+        // A step of 2 stops at 254, which is in range
+        let source = r#"
+contract C {
+    function f() public pure returns (uint8 r) {
+        for (uint8 i = 250; i < 254; i += 2) { r = i; }
+    }
+}
+"#;
+        assert_eq!(unroll_loops(&mut body(source), DEFAULT_UNROLL_BUDGET), 1);
+    }
+}
@@ -0,0 +1,332 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arithmetic on words of 256 bits, shared by the lowering of functions and
+//! of inline assembly.
+//!
+//! A word is four `i64` limbs, the least significant first. Additions,
+//! subtractions, products and comparisons are computed on the limbs, the
+//! divisions and shifts by the `word_op` host function of the
+//! [`runtime`](crate::runtime), which takes its operands in memory. Words are
+//! stored in memory and passed to the host in big endian order, as in the EVM.
+
+use cranelift::{
+    codegen::{
+        ir::{Endianness, FuncRef, MemFlagsData},
+        isa::TargetFrontendConfig,
+    },
+    frontend::FuncInstBuilder,
+    prelude::{types, FunctionBuilder, InstBuilder, IntCC, StackSlotData, StackSlotKind, Value},
+};
+use indexmap::IndexMap;
+
+use crate::runtime::WordOp;
+
+use super::dispatch::{flags, WORD_SIZE};
+
+/// A word of 256 bits, as its `i64` limbs from the least significant
+#[derive(Debug, Clone, Copy)]
+pub struct Word(pub [Value; 4]);
+
+/// The sum modulo 2^256. The carries are added separately, the backends do
+/// not all support the additions with a carry in.
+pub fn add(builder: &mut FunctionBuilder, a: Word, b: Word) -> Word {
+    let mut limbs = a.0;
+    let mut carry = None;
+    for (limb, other) in limbs.iter_mut().zip(b.0) {
+        let (sum, out) = builder.ins().uadd_overflow(*limb, other);
+        *limb = sum;
+        carry = Some(match carry {
+            Some(carry) => {
+                let carry = builder.ins().uextend(types::I64, carry);
+                let (sum, again) = builder.ins().uadd_overflow(sum, carry);
+                *limb = sum;
+                builder.ins().bor(out, again)
+            }
+            None => out,
+        });
+    }
+    Word(limbs)
+}
+
+/// The difference of the words, with the `i8` borrow out of it
+pub fn sub_borrow(builder: &mut FunctionBuilder, a: Word, b: Word) -> (Word, Value) {
+    let mut limbs = a.0;
+    let mut borrow = None;
+    for (limb, other) in limbs.iter_mut().zip(b.0) {
+        let (difference, out) = builder.ins().usub_overflow(*limb, other);
+        *limb = difference;
+        borrow = Some(match borrow {
+            Some(borrow) => {
+                let borrow = builder.ins().uextend(types::I64, borrow);
+                let (difference, again) = builder.ins().usub_overflow(difference, borrow);
+                *limb = difference;
+                builder.ins().bor(out, again)
+            }
+            None => out,
+        });
+    }
+    (Word(limbs), borrow.expect("a word has limbs"))
+}
+
+/// The product modulo 2^256, summing the products of the limbs by column
+pub fn mul(builder: &mut FunctionBuilder, a: Word, b: Word) -> Word {
+    let zero = builder.ins().iconst(types::I64, 0);
+    let mut result = [zero; 4];
+
+    for i in 0..4 {
+        let mut carry = zero;
+        for j in 0..4 - i {
+            let k = i + j;
+            let low = builder.ins().imul(a.0[i], b.0[j]);
+            let (sum, c1) = builder.ins().uadd_overflow(result[k], low);
+            let (sum, c2) = builder.ins().uadd_overflow(sum, carry);
+            result[k] = sum;
+
+            // The high half is at most 2^64 - 2, adding the carries cannot
+            // overflow
+            let high = builder.ins().umulhi(a.0[i], b.0[j]);
+            let c1 = builder.ins().uextend(types::I64, c1);
+            let c2 = builder.ins().uextend(types::I64, c2);
+            let high = builder.ins().iadd(high, c1);
+            carry = builder.ins().iadd(high, c2);
+        }
+    }
+
+    Word(result)
+}
+
+pub fn bitwise(
+    builder: &mut FunctionBuilder,
+    a: Word,
+    b: Word,
+    op: impl Fn(FuncInstBuilder, Value, Value) -> Value,
+) -> Word {
+    let mut limbs = a.0;
+    for (limb, other) in limbs.iter_mut().zip(b.0) {
+        *limb = op(builder.ins(), *limb, other);
+    }
+    Word(limbs)
+}
+
+/// Whether `a < b` as unsigned words, an `i8` flag
+pub fn less(builder: &mut FunctionBuilder, a: Word, b: Word) -> Value {
+    let (_, borrow) = sub_borrow(builder, a, b);
+    borrow
+}
+
+/// Whether the words are equal, an `i8` flag
+pub fn equal(builder: &mut FunctionBuilder, a: Word, b: Word) -> Value {
+    let diff = bitwise(builder, a, b, |ins, a, b| ins.bxor(a, b));
+    let any = any(builder, &diff.0);
+    builder.ins().icmp_imm_u(IntCC::Equal, any, 0)
+}
+
+/// The `or` of the limbs, non-zero if any is
+pub fn any(builder: &mut FunctionBuilder, limbs: &[Value]) -> Value {
+    let mut any = limbs[0];
+    for limb in &limbs[1..] {
+        any = builder.ins().bor(any, *limb);
+    }
+    any
+}
+
+/// The word of an `i8` flag, 1 or 0
+pub fn flag_word(builder: &mut FunctionBuilder, flag: Value) -> Word {
+    let limb = builder.ins().uextend(types::I64, flag);
+    limb_word(builder, limb)
+}
+
+/// The word of an `i64` value
+pub fn limb_word(builder: &mut FunctionBuilder, limb: Value) -> Word {
+    let zero = builder.ins().iconst(types::I64, 0);
+    Word([limb, zero, zero, zero])
+}
+
+/// The word of its bytes in big endian order
+pub fn constant(builder: &mut FunctionBuilder, bytes: &[u8; 32]) -> Word {
+    let mut limbs = bytes.rchunks_exact(8).map(|limb| {
+        let limb = i64::from_be_bytes(limb.try_into().unwrap());
+        builder.ins().iconst(types::I64, limb)
+    });
+    Word([(); 4].map(|()| limbs.next().unwrap()))
+}
+
+/// The low `bits` of the word, the others cleared
+pub fn truncate(builder: &mut FunctionBuilder, word: Word, bits: u16) -> Word {
+    let mut limbs = word.0;
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let low = 64 * i as u16;
+        if bits <= low {
+            *limb = builder.ins().iconst(types::I64, 0);
+        } else if bits < low + 64 {
+            let mask = (1u64 << (bits - low)) - 1;
+            *limb = builder.ins().band_imm_u(*limb, mask as i64);
+        }
+    }
+    Word(limbs)
+}
+
+/// The word shifted left by a constant number of bits, moving the limbs
+/// rather than calling the host
+pub fn shl_imm(builder: &mut FunctionBuilder, word: Word, amount: u16) -> Word {
+    let (limbs, bits) = (usize::from(amount / 64), i64::from(amount % 64));
+    let zero = builder.ins().iconst(types::I64, 0);
+    let limb = |i: usize| if i >= limbs { word.0[i - limbs] } else { zero };
+
+    let mut result = [zero; 4];
+    for (i, out) in result.iter_mut().enumerate().skip(limbs) {
+        *out = match bits {
+            0 => limb(i),
+            _ => {
+                let high = builder.ins().ishl_imm_u(limb(i), bits);
+                match i > limbs {
+                    true => {
+                        let low = builder.ins().ushr_imm_u(limb(i - 1), 64 - bits);
+                        builder.ins().bor(high, low)
+                    }
+                    false => high,
+                }
+            }
+        };
+    }
+    Word(result)
+}
+
+/// The word shifted right by a constant number of bits, zeros shifted in, see
+/// [`shl_imm`]
+pub fn shr_imm(builder: &mut FunctionBuilder, word: Word, amount: u16) -> Word {
+    let (limbs, bits) = (usize::from(amount / 64), i64::from(amount % 64));
+    let zero = builder.ins().iconst(types::I64, 0);
+    let limb = |i: usize| word.0.get(i + limbs).copied().unwrap_or(zero);
+
+    let mut result = [zero; 4];
+    for (i, out) in result.iter_mut().enumerate().take(4 - limbs.min(4)) {
+        *out = match bits {
+            0 => limb(i),
+            _ => {
+                let low = builder.ins().ushr_imm_u(limb(i), bits);
+                match i + limbs + 1 < 4 {
+                    true => {
+                        let high = builder.ins().ishl_imm_u(limb(i + 1), 64 - bits);
+                        builder.ins().bor(low, high)
+                    }
+                    false => low,
+                }
+            }
+        };
+    }
+    Word(result)
+}
+
+/// The low `bits` of the word as a two's complement integer, their top bit
+/// copied to all the bits above them
+pub fn sign_extend(builder: &mut FunctionBuilder, word: Word, bits: u16) -> Word {
+    let top = usize::from((bits - 1) / 64);
+    let mut limbs = word.0;
+
+    let unused = 64 * (top as i64 + 1) - i64::from(bits);
+    if unused > 0 {
+        let shifted = builder.ins().ishl_imm_u(limbs[top], unused);
+        limbs[top] = builder.ins().sshr_imm_u(shifted, unused);
+    }
+
+    let fill = builder.ins().sshr_imm_u(limbs[top], 63);
+    for limb in &mut limbs[top + 1..] {
+        *limb = fill;
+    }
+    Word(limbs)
+}
+
+/// Whether the word is negative as a two's complement integer, an `i8` flag
+pub fn negative(builder: &mut FunctionBuilder, word: Word) -> Value {
+    builder.ins().icmp_imm_s(IntCC::SignedLessThan, word.0[3], 0)
+}
+
+/// Compute an operation with the `word_op` host function, see [`load_word`]
+/// for `swap`
+pub fn word_op(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    swap: bool,
+    host: &IndexMap<&'static str, FuncRef>,
+    op: WordOp,
+    args: &[Word],
+) -> Word {
+    let mut params = vec![builder.ins().iconst(types::I32, op as i64)];
+    for i in 0..3 {
+        params.push(match args.get(i) {
+            Some(arg) if i < op.arity() => spill(builder, config, swap, *arg),
+            _ => builder.ins().iconst(config.pointer_type(), 0),
+        });
+    }
+    let result = stack_word(builder, config);
+    params.push(result);
+    builder.ins().call(host["word_op"], &params);
+    load_word(builder, swap, result)
+}
+
+/// A word on the stack, to pass to the host
+pub fn stack_word(builder: &mut FunctionBuilder, config: TargetFrontendConfig) -> Value {
+    let slot = builder.create_sized_stack_slot(StackSlotData::new(
+        StackSlotKind::ExplicitSlot,
+        WORD_SIZE as u32,
+        3,
+    ));
+    builder.ins().stack_addr(config.pointer_type(), slot, 0)
+}
+
+/// The word copied on the stack
+pub fn spill(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    swap: bool,
+    word: Word,
+) -> Value {
+    let address = stack_word(builder, config);
+    store_word(builder, swap, address, word);
+    address
+}
+
+/// Load a word stored in big endian order. The limbs are loaded with big
+/// endian accesses, or with native ones whose bytes are swapped when `swap`
+/// is set, for little endian targets which do not support the former.
+pub fn load_word(builder: &mut FunctionBuilder, swap: bool, address: Value) -> Word {
+    Word([24, 16, 8, 0].map(|offset| match swap {
+        true => {
+            let limb = builder.ins().load(types::I64, flags(), address, offset);
+            builder.ins().bswap(limb)
+        }
+        false => builder.ins().load(types::I64, big_endian(), address, offset),
+    }))
+}
+
+/// Store a word in big endian order, see [`load_word`] for `swap`
+pub fn store_word(builder: &mut FunctionBuilder, swap: bool, address: Value, word: Word) {
+    for (limb, offset) in word.0.into_iter().zip([24, 16, 8, 0]) {
+        match swap {
+            true => {
+                let limb = builder.ins().bswap(limb);
+                builder.ins().store(flags(), limb, address, offset);
+            }
+            false => {
+                builder.ins().store(big_endian(), limb, address, offset);
+            }
+        }
+    }
+}
+
+fn big_endian() -> MemFlagsData {
+    flags().with_endianness(Endianness::Big)
+}
@@ -0,0 +1,1207 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lowering of inline assembly blocks.
+//!
+//! A Yul word of 256 bits is four `i64` limbs, the least significant first,
+//! and each Yul variable four Cranelift variables, so that the locals of a
+//! block end up in registers. The builtins available to native code are
+//! either lowered to instructions on the limbs, like `add` or `lt`, or call
+//! the host functions of the [`runtime`](crate::runtime), like `sload` or
+//! the `div` of the `word_op` hook.
+//!
+//! Memory is the memory of the call, laid out as described in
+//! [`memory`](super::memory): words are stored in big endian order as in the
+//! EVM, and every access is checked against its size. Yul functions are inlined where they are
+//! called, which the semantic analysis allows as they cannot be recursive.
+//!
+//! The blocks are lowered in the functions they are in, see
+//! [`function`](super::function), where the local variables of the function
+//! in scope are Yul variables too, see [`with_variables`](YulLowering::with_variables).
+
+use cranelift::{
+    codegen::{
+        ir::{FuncRef, GlobalValue},
+        isa::TargetFrontendConfig,
+    },
+    prelude::{
+        types, Block, FunctionBuilder, InstBuilder, IntCC, StackSlotData, StackSlotKind, TrapCode,
+        Value, Variable,
+    },
+};
+use indexmap::IndexMap;
+use num_bigint::BigUint;
+use num_traits::Num;
+use thiserror::Error;
+
+use crate::{
+    parser::ast as pt,
+    runtime::WordOp,
+    semantic::{
+        ast::{Layout, StorageClass},
+        yul::builtin::YulBuiltin,
+    },
+    target::Target,
+};
+
+use super::{
+    cfg::{LoopBlocks, LoopTargets},
+    dispatch::{flags, WORD_SIZE},
+    storage, switch,
+    trycatch::REVERT_RETURNED,
+    word::{self, Word},
+};
+
+/// Trap of the `invalid` builtin
+pub const INVALID: TrapCode = TrapCode::unwrap_user(2);
+
+/// Trap of a memory access outside the buffer of the contract
+pub const OUT_OF_BOUNDS: TrapCode = TrapCode::HEAP_OUT_OF_BOUNDS;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum YulError {
+    #[error("'{0}' is not declared")]
+    Undeclared(String),
+
+    #[error("builtin '{0}' is not available in native code")]
+    Unavailable(String),
+
+    #[error("function '{0}' is recursive and cannot be inlined")]
+    Recursive(String),
+
+    #[error("unknown data object '{0}'")]
+    UnknownObject(String),
+
+    #[error("'{0}' has no storage slot")]
+    NoStorage(String),
+
+    #[error("expected {expected} values, found {found}")]
+    ValueCount { expected: usize, found: usize },
+
+    #[error("invalid literal '{0}'")]
+    Literal(String),
+
+    #[error("'{0}' outside of a {1}")]
+    Misplaced(&'static str, &'static str),
+}
+
+/// The state of the block being lowered. The host functions must contain all
+/// those of the [`runtime`](crate::runtime) the builtins call. The blocks
+/// are left unsealed, for the caller to seal once the function is complete.
+pub struct YulLowering<'a, 'b> {
+    builder: &'a mut FunctionBuilder<'b>,
+    config: TargetFrontendConfig,
+    host: &'a IndexMap<&'static str, FuncRef>,
+    /// Swap the bytes of the words in memory, on little endian targets
+    swap: bool,
+    /// The address of the memory buffer
+    memory: Value,
+    /// The `i64` size of the memory buffer
+    memory_size: Value,
+    /// The layouts of the state variables, by name, for `.slot`
+    storage: IndexMap<String, Layout>,
+    /// The data objects with their size, by name, for `dataoffset`
+    objects: IndexMap<String, (GlobalValue, usize)>,
+    /// The variables in scope, by block
+    scopes: Vec<IndexMap<String, [Variable; 4]>>,
+    /// The functions in scope, by block
+    functions: Vec<IndexMap<String, pt::YulFunctionDefinition>>,
+    /// The blocks `continue` and `break` jump to
+    loops: LoopTargets,
+    /// The block a `leave` jumps to in the function being inlined
+    leave: Option<Block>,
+    /// The functions being inlined
+    calls: Vec<String>,
+}
+
+impl<'a, 'b> YulLowering<'a, 'b> {
+    pub fn new(
+        builder: &'a mut FunctionBuilder<'b>,
+        config: TargetFrontendConfig,
+        host: &'a IndexMap<&'static str, FuncRef>,
+        big_endian: bool,
+        memory: Value,
+        memory_size: Value,
+    ) -> Self {
+        Self {
+            builder,
+            config,
+            host,
+            swap: !big_endian,
+            memory,
+            memory_size,
+            storage: IndexMap::new(),
+            objects: IndexMap::new(),
+            scopes: Vec::new(),
+            functions: Vec::new(),
+            loops: LoopTargets::default(),
+            leave: None,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Give the storage layout the `.slot` and `.offset` of state variables
+    /// read
+    pub fn with_storage(mut self, storage: IndexMap<String, Layout>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Give the data objects of `datasize`, `dataoffset` and `datacopy`,
+    /// like the code of the contracts created
+    pub fn with_objects(mut self, objects: IndexMap<String, (GlobalValue, usize)>) -> Self {
+        self.objects = objects;
+        self
+    }
+
+    /// Give the variables of the enclosing function in scope, which the block
+    /// reads and assigns like its own
+    pub fn with_variables(mut self, variables: IndexMap<String, [Variable; 4]>) -> Self {
+        self.scopes = vec![variables];
+        self
+    }
+
+    /// Lower an assembly block in the current block of the builder, where
+    /// execution goes on after it
+    pub fn lower(&mut self, block: &pt::YulBlock) -> Result<(), YulError> {
+        self.block(block)
+    }
+
+    fn block(&mut self, block: &pt::YulBlock) -> Result<(), YulError> {
+        // Functions may be called before their definition in the block
+        let functions = block
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                pt::YulStatement::FunctionDefinition(def) => {
+                    Some((def.id.name.clone(), def.as_ref().clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        self.functions.push(functions);
+        self.scopes.push(IndexMap::new());
+
+        let result = block.statements.iter().try_for_each(|stmt| self.statement(stmt));
+
+        self.scopes.pop();
+        self.functions.pop();
+        result
+    }
+
+    fn statement(&mut self, stmt: &pt::YulStatement) -> Result<(), YulError> {
+        match stmt {
+            pt::YulStatement::VariableDeclaration(_, ids, value) => {
+                let values = match value {
+                    Some(value) => self.values(value, ids.len())?,
+                    None => vec![self.constant(&[0; 32]); ids.len()],
+                };
+                for (id, value) in ids.iter().zip(values) {
+                    self.declare(&id.id.name, value);
+                }
+            }
+            pt::YulStatement::Assign(_, places, value) => {
+                let values = self.values(value, places.len())?;
+                for (place, value) in places.iter().zip(values) {
+                    let vars = match place {
+                        pt::YulExpression::Variable(id) => self.variable(&id.name)?,
+                        place => return Err(YulError::Undeclared(place.to_string())),
+                    };
+                    for (var, limb) in vars.into_iter().zip(value.0) {
+                        self.builder.def_var(var, limb);
+                    }
+                }
+            }
+            pt::YulStatement::If(_, cond, body) => {
+                let cond = self.value(cond)?;
+                let cond = self.nonzero(cond);
+                let then = self.builder.create_block();
+                let next = self.builder.create_block();
+                self.builder.ins().brif(cond, then, &[], next, &[]);
+
+                self.builder.switch_to_block(then);
+                self.block(body)?;
+                self.builder.ins().jump(next, &[]);
+                self.builder.switch_to_block(next);
+            }
+            pt::YulStatement::For(for_loop) => self.for_loop(for_loop)?,
+            pt::YulStatement::Switch(switch) => self.switch(switch)?,
+            pt::YulStatement::Leave(_) => {
+                let leave = self.leave.ok_or(YulError::Misplaced("leave", "function"))?;
+                self.jump(leave);
+            }
+            pt::YulStatement::Break(_) => {
+                let exit = self.loops.break_target().ok_or(YulError::Misplaced("break", "loop"))?;
+                self.jump(exit);
+            }
+            pt::YulStatement::Continue(_) => {
+                let next =
+                    self.loops.continue_target().ok_or(YulError::Misplaced("continue", "loop"))?;
+                self.jump(next);
+            }
+            pt::YulStatement::Block(block) => self.block(block)?,
+            // Hoisted by the block defining them
+            pt::YulStatement::FunctionDefinition(_) => (),
+            pt::YulStatement::FunctionCall(call) => {
+                let values = self.call(call)?;
+                if !values.is_empty() {
+                    return Err(YulError::ValueCount { expected: 0, found: values.len() });
+                }
+            }
+            // Rejected by the parser
+            pt::YulStatement::Error(_) => (),
+        }
+
+        Ok(())
+    }
+
+    /// A `for` loop, whose initialization block is the scope of the loop
+    fn for_loop(&mut self, for_loop: &pt::YulFor) -> Result<(), YulError> {
+        self.scopes.push(IndexMap::new());
+        for stmt in &for_loop.init_block.statements {
+            self.statement(stmt)?;
+        }
+
+        let header = self.builder.create_block();
+        let body = self.builder.create_block();
+        let post = self.builder.create_block();
+        let exit = self.builder.create_block();
+        self.builder.ins().jump(header, &[]);
+
+        self.builder.switch_to_block(header);
+        let cond = self.value(&for_loop.condition)?;
+        let cond = self.nonzero(cond);
+        self.builder.ins().brif(cond, body, &[], exit, &[]);
+
+        self.builder.switch_to_block(body);
+        self.loops.enter(LoopBlocks { header, next: post, exit });
+        let result = self.block(&for_loop.execution_block);
+        self.loops.leave();
+        result?;
+        self.builder.ins().jump(post, &[]);
+
+        self.builder.switch_to_block(post);
+        self.block(&for_loop.post_block)?;
+        self.builder.ins().jump(header, &[]);
+
+        self.builder.switch_to_block(exit);
+        self.scopes.pop();
+        Ok(())
+    }
+
+    /// A `switch`, lowered to a jump table when the cases fit in a limb
+    fn switch(&mut self, switch: &pt::YulSwitch) -> Result<(), YulError> {
+        let value = self.value(&switch.condition)?;
+        let exit = self.builder.create_block();
+        let default = match &switch.default {
+            Some(_) => self.builder.create_block(),
+            None => exit,
+        };
+
+        let mut cases = Vec::new();
+        for case in &switch.cases {
+            if let pt::YulSwitchOptions::Case(_, literal, body) = case {
+                let bytes = literal_bytes(literal)?;
+                // Duplicates are rejected by the semantic analysis
+                if cases.iter().all(|(other, _, _)| *other != bytes) {
+                    cases.push((bytes, self.builder.create_block(), body));
+                }
+            }
+        }
+
+        let [low, high @ ..] = value.0;
+        if cases.iter().all(|(bytes, _, _)| bytes[..24] == [0; 24]) {
+            let table = self.builder.create_block();
+            let high = self.any(&high);
+            self.builder.ins().brif(high, default, &[], table, &[]);
+
+            self.builder.switch_to_block(table);
+            let cases = cases
+                .iter()
+                .map(|(bytes, block, _)| {
+                    (u64::from_be_bytes(bytes[24..].try_into().unwrap()).into(), *block)
+                })
+                .collect::<Vec<_>>();
+            switch::emit(self.builder, low, &cases, default);
+        } else {
+            for (bytes, block, _) in &cases {
+                let case = self.constant(bytes);
+                let equal = self.equal(value, case);
+                let next = self.builder.create_block();
+                self.builder.ins().brif(equal, *block, &[], next, &[]);
+                self.builder.switch_to_block(next);
+            }
+            self.builder.ins().jump(default, &[]);
+        }
+
+        for (_, block, body) in cases {
+            self.builder.switch_to_block(block);
+            self.block(body)?;
+            self.builder.ins().jump(exit, &[]);
+        }
+        if let Some(pt::YulSwitchOptions::Default(_, body)) = &switch.default {
+            self.builder.switch_to_block(default);
+            self.block(body)?;
+            self.builder.ins().jump(exit, &[]);
+        }
+
+        self.builder.switch_to_block(exit);
+        Ok(())
+    }
+
+    /// The value of an expression giving a single word
+    fn value(&mut self, expr: &pt::YulExpression) -> Result<Word, YulError> {
+        let values = self.values(expr, 1)?;
+        Ok(values[0])
+    }
+
+    /// The values of an expression, which must give as many as expected
+    fn values(&mut self, expr: &pt::YulExpression, expected: usize) -> Result<Vec<Word>, YulError> {
+        let values = match expr {
+            pt::YulExpression::FunctionCall(call) => self.call(call)?,
+            pt::YulExpression::Variable(id) => {
+                let vars = self.variable(&id.name)?;
+                vec![Word(vars.map(|var| self.builder.use_var(var)))]
+            }
+            pt::YulExpression::SuffixAccess(_, base, member) => {
+                let pt::YulExpression::Variable(id) = base.as_ref() else {
+                    return Err(YulError::NoStorage(base.to_string()));
+                };
+                let layout = self
+                    .storage
+                    .get(&id.name)
+                    .ok_or_else(|| YulError::NoStorage(id.name.clone()))?;
+                // The slot is the key of the host, so that it is read and
+                // written with `sload` and `sstore`
+                let bytes = match member.name.as_str() {
+                    "slot" => storage::key(layout),
+                    "offset" => {
+                        let mut offset = [0; 32];
+                        offset[31] = layout.offset;
+                        offset
+                    }
+                    _ => return Err(YulError::Undeclared(expr.to_string())),
+                };
+                vec![self.constant(&bytes)]
+            }
+            literal => {
+                let bytes = literal_bytes(literal)?;
+                vec![self.constant(&bytes)]
+            }
+        };
+
+        if values.len() != expected {
+            return Err(YulError::ValueCount { expected, found: values.len() });
+        }
+        Ok(values)
+    }
+
+    /// Call a function defined in scope or a builtin
+    fn call(&mut self, call: &pt::YulFunctionCall) -> Result<Vec<Word>, YulError> {
+        let name = &call.id.name;
+        let function = self.functions.iter().rev().find_map(|scope| scope.get(name)).cloned();
+        if let Some(def) = function {
+            let args = self.args(&call.arguments)?;
+            return self.inline(&def, args);
+        }
+
+        let builtin =
+            YulBuiltin::from_name(name).ok_or_else(|| YulError::Undeclared(name.clone()))?;
+        let prototype = builtin.prototype();
+        if !prototype.is_available(Target::Native) {
+            return Err(YulError::Unavailable(name.clone()));
+        }
+        if call.arguments.len() != prototype.params {
+            return Err(YulError::ValueCount {
+                expected: prototype.params,
+                found: call.arguments.len(),
+            });
+        }
+
+        // Names of data objects are not evaluated
+        if let YulBuiltin::DataSize | YulBuiltin::DataOffset = builtin {
+            let [pt::YulExpression::StringLiteral(name, _)] = call.arguments.as_slice() else {
+                return Err(YulError::Literal(call.arguments[0].to_string()));
+            };
+            let (data, size) = *self
+                .objects
+                .get(&name.string)
+                .ok_or_else(|| YulError::UnknownObject(name.string.clone()))?;
+            let value = match builtin {
+                YulBuiltin::DataSize => self.builder.ins().iconst(types::I64, size as i64),
+                _ => {
+                    let address = self.builder.ins().symbol_value(self.config.pointer_type(), data);
+                    self.extend(address)
+                }
+            };
+            return Ok(vec![self.limb_word(value)]);
+        }
+
+        let args = self.args(&call.arguments)?;
+        Ok(self.builtin(builtin, &args).into_iter().collect())
+    }
+
+    /// Evaluate the arguments of a call, from the last as in the EVM
+    fn args(&mut self, args: &[pt::YulExpression]) -> Result<Vec<Word>, YulError> {
+        let mut values =
+            args.iter().rev().map(|arg| self.value(arg)).collect::<Result<Vec<_>, _>>()?;
+        values.reverse();
+        Ok(values)
+    }
+
+    /// Inline a call of the function, whose body only sees its parameters
+    /// and results
+    fn inline(
+        &mut self,
+        def: &pt::YulFunctionDefinition,
+        args: Vec<Word>,
+    ) -> Result<Vec<Word>, YulError> {
+        if self.calls.contains(&def.id.name) {
+            return Err(YulError::Recursive(def.id.name.clone()));
+        }
+
+        let exit = self.builder.create_block();
+        let scopes = std::mem::take(&mut self.scopes);
+        let loops = self.loops.suspend();
+        let leave = self.leave.replace(exit);
+        self.calls.push(def.id.name.clone());
+
+        self.scopes.push(IndexMap::new());
+        for (param, arg) in def.params.iter().zip(args) {
+            self.declare(&param.id.name, arg);
+        }
+        let zero = self.constant(&[0; 32]);
+        for ret in &def.returns {
+            self.declare(&ret.id.name, zero);
+        }
+
+        let result = self.block(&def.body).map(|()| {
+            self.builder.ins().jump(exit, &[]);
+            self.builder.switch_to_block(exit);
+            let results = def.returns.iter().map(|ret| self.scopes[0][&ret.id.name]);
+            results.collect::<Vec<_>>()
+        });
+
+        self.calls.pop();
+        self.leave = leave;
+        self.loops.resume(loops);
+        self.scopes = scopes;
+
+        let results = result?;
+        Ok(results
+            .into_iter()
+            .map(|vars| Word(vars.map(|var| self.builder.use_var(var))))
+            .collect())
+    }
+
+    /// Lower a builtin whose arguments are evaluated and available
+    fn builtin(&mut self, builtin: YulBuiltin, args: &[Word]) -> Option<Word> {
+        let word = match builtin {
+            YulBuiltin::Add => self.add(args[0], args[1]),
+            YulBuiltin::Sub => self.sub(args[0], args[1]),
+            YulBuiltin::Mul => self.mul(args[0], args[1]),
+            YulBuiltin::Not => Word(args[0].0.map(|limb| self.builder.ins().bnot(limb))),
+            YulBuiltin::And => self.bitwise(args[0], args[1], |ins, a, b| ins.band(a, b)),
+            YulBuiltin::Or => self.bitwise(args[0], args[1], |ins, a, b| ins.bor(a, b)),
+            YulBuiltin::Xor => self.bitwise(args[0], args[1], |ins, a, b| ins.bxor(a, b)),
+            YulBuiltin::Lt => {
+                let lt = self.less(args[0], args[1]);
+                self.flag_word(lt)
+            }
+            YulBuiltin::Gt => {
+                let gt = self.less(args[1], args[0]);
+                self.flag_word(gt)
+            }
+            YulBuiltin::Slt | YulBuiltin::Sgt => {
+                // Flipping the sign bits orders signed words as unsigned
+                let [a, b] = [args[0], args[1]].map(|Word([l0, l1, l2, l3])| {
+                    let sign = self.builder.ins().iconst(types::I64, i64::MIN);
+                    Word([l0, l1, l2, self.builder.ins().bxor(l3, sign)])
+                });
+                let (a, b) = if builtin == YulBuiltin::Slt { (a, b) } else { (b, a) };
+                let lt = self.less(a, b);
+                self.flag_word(lt)
+            }
+            YulBuiltin::Eq => {
+                let equal = self.equal(args[0], args[1]);
+                self.flag_word(equal)
+            }
+            YulBuiltin::IsZero => {
+                let any = self.any(&args[0].0);
+                let zero = self.builder.ins().icmp_imm_u(IntCC::Equal, any, 0);
+                self.flag_word(zero)
+            }
+            YulBuiltin::Div => self.word_op(WordOp::Div, args),
+            YulBuiltin::SDiv => self.word_op(WordOp::SDiv, args),
+            YulBuiltin::Mod => self.word_op(WordOp::Mod, args),
+            YulBuiltin::SMod => self.word_op(WordOp::SMod, args),
+            YulBuiltin::Exp => self.word_op(WordOp::Exp, args),
+            YulBuiltin::AddMod => self.word_op(WordOp::AddMod, args),
+            YulBuiltin::MulMod => self.word_op(WordOp::MulMod, args),
+            YulBuiltin::SignExtend => self.word_op(WordOp::SignExtend, args),
+            YulBuiltin::Byte => self.word_op(WordOp::Byte, args),
+            YulBuiltin::Shl => self.word_op(WordOp::Shl, args),
+            YulBuiltin::Shr => self.word_op(WordOp::Shr, args),
+            YulBuiltin::Sar => self.word_op(WordOp::Sar, args),
+            YulBuiltin::Pop => return None,
+            YulBuiltin::MemoryGuard => args[0],
+            YulBuiltin::MLoad => {
+                let address = self.address(args[0], WORD_SIZE);
+                self.load_word(address)
+            }
+            YulBuiltin::MStore => {
+                let address = self.address(args[0], WORD_SIZE);
+                self.store_word(address, args[1]);
+                return None;
+            }
+            YulBuiltin::MStore8 => {
+                let address = self.address(args[0], 1);
+                self.builder.ins().istore8(flags(), args[1].0[0], address, 0);
+                return None;
+            }
+            YulBuiltin::MSize => self.limb_word(self.memory_size),
+            YulBuiltin::MCopy => {
+                let (dest, size) = self.range(args[0], args[2]);
+                let (src, _) = self.range(args[1], args[2]);
+                let size = self.pointer(size);
+                self.builder.call_memmove(self.config, dest, src, size);
+                return None;
+            }
+            YulBuiltin::DataCopy => {
+                self.data_copy(args);
+                return None;
+            }
+            YulBuiltin::SLoad | YulBuiltin::TLoad => {
+                let class = match builtin {
+                    YulBuiltin::SLoad => StorageClass::Persistent,
+                    _ => StorageClass::Temporary,
+                };
+                let (load, _) = storage::host_functions(class);
+                let key = self.spill(args[0]);
+                let value = self.stack_word();
+                self.builder.ins().call(self.host[load], &[key, value]);
+                self.load_word(value)
+            }
+            YulBuiltin::SStore | YulBuiltin::TStore => {
+                let class = match builtin {
+                    YulBuiltin::SStore => StorageClass::Persistent,
+                    _ => StorageClass::Temporary,
+                };
+                let (_, store) = storage::host_functions(class);
+                let key = self.spill(args[0]);
+                let value = self.spill(args[1]);
+                self.builder.ins().call(self.host[store], &[key, value]);
+                return None;
+            }
+            YulBuiltin::Caller | YulBuiltin::CallValue => {
+                let name = match builtin {
+                    YulBuiltin::Caller => "caller",
+                    _ => "value_transferred",
+                };
+                let dest = self.stack_word();
+                self.builder.ins().call(self.host[name], &[dest]);
+                self.load_word(dest)
+            }
+            YulBuiltin::CallDataLoad => {
+                let offset = self.calldata_offset(args[0]);
+                let dest = self.stack_word();
+                let len = self.builder.ins().iconst(types::I32, WORD_SIZE);
+                self.builder.ins().call(self.host["calldata_copy"], &[dest, offset, len]);
+                self.load_word(dest)
+            }
+            YulBuiltin::CallDataSize => {
+                let call = self.builder.ins().call(self.host["calldata_size"], &[]);
+                let size = self.builder.inst_results(call)[0];
+                let size = self.builder.ins().uextend(types::I64, size);
+                self.limb_word(size)
+            }
+            YulBuiltin::CallDataCopy => {
+                let (dest, len) = self.range(args[0], args[2]);
+                let offset = self.calldata_offset(args[1]);
+                let len = self.builder.ins().ireduce(types::I32, len);
+                self.builder.ins().call(self.host["calldata_copy"], &[dest, offset, len]);
+                return None;
+            }
+            YulBuiltin::ReturnDataSize => {
+                let size = self.return_data_size();
+                let size = self.builder.ins().uextend(types::I64, size);
+                self.limb_word(size)
+            }
+            YulBuiltin::ReturnDataCopy => {
+                let (dest, len) = self.range(args[0], args[2]);
+                let offset = self.limb(args[1]);
+                let end = self.builder.ins().iadd(offset, len);
+                let size = self.return_data_size();
+                let size = self.builder.ins().uextend(types::I64, size);
+                let outside = self.builder.ins().icmp(IntCC::UnsignedGreaterThan, end, size);
+                self.builder.ins().trapnz(outside, OUT_OF_BOUNDS);
+
+                let offset = self.builder.ins().ireduce(types::I32, offset);
+                let len = self.builder.ins().ireduce(types::I32, len);
+                self.builder.ins().call(self.host["return_data_copy"], &[dest, offset, len]);
+                return None;
+            }
+            YulBuiltin::Call => return Some(self.external_call(args)),
+            YulBuiltin::Create => return Some(self.create(args)),
+            YulBuiltin::Return | YulBuiltin::Revert => {
+                let name = match builtin {
+                    YulBuiltin::Return => "return",
+                    _ => "revert",
+                };
+                let (data, len) = self.range(args[0], args[1]);
+                let len = self.builder.ins().ireduce(types::I32, len);
+                self.end(name, data, len);
+                return None;
+            }
+            YulBuiltin::Stop => {
+                let data = self.builder.ins().iconst(self.config.pointer_type(), 0);
+                let len = self.builder.ins().iconst(types::I32, 0);
+                self.end("return", data, len);
+                return None;
+            }
+            YulBuiltin::Invalid => {
+                self.builder.ins().trap(INVALID);
+                self.unreachable();
+                return None;
+            }
+            YulBuiltin::Log0 |
+            YulBuiltin::Log1 |
+            YulBuiltin::Log2 |
+            YulBuiltin::Log3 |
+            YulBuiltin::Log4 => {
+                self.log(args);
+                return None;
+            }
+            // Handled by the caller, or not available in native code
+            _ => unreachable!("builtin {} is not lowered", builtin.prototype().name),
+        };
+
+        Some(word)
+    }
+
+    /// `call(g, a, v, in, insize, out, outsize)`, copying as much of the
+    /// return data as fits in the output and giving 1 on success
+    fn external_call(&mut self, args: &[Word]) -> Word {
+        let [gas, high @ ..] = args[0].0;
+        let high = self.any(&high);
+        let max = self.builder.ins().iconst(types::I64, -1);
+        let gas = self.builder.ins().select(high, max, gas);
+
+        let address = self.spill(args[1]);
+        let value = self.spill(args[2]);
+        let (input, input_len) = self.range(args[3], args[4]);
+        let input_len = self.builder.ins().ireduce(types::I32, input_len);
+        let call =
+            self.builder.ins().call(self.host["call"], &[address, value, input, input_len, gas]);
+        let status = self.builder.inst_results(call)[0];
+
+        let (output, output_len) = self.range(args[5], args[6]);
+        let output_len = self.builder.ins().ireduce(types::I32, output_len);
+        let size = self.return_data_size();
+        let len = self.builder.ins().umin(size, output_len);
+        let offset = self.builder.ins().iconst(types::I32, 0);
+        self.builder.ins().call(self.host["return_data_copy"], &[output, offset, len]);
+
+        let success = self.builder.ins().icmp_imm_u(IntCC::Equal, status, 0);
+        self.flag_word(success)
+    }
+
+    /// `create(v, p, n)`, giving the address of the contract or 0 when its
+    /// constructor reverted
+    fn create(&mut self, args: &[Word]) -> Word {
+        let value = self.spill(args[0]);
+        let (code, code_len) = self.range(args[1], args[2]);
+        let code_len = self.builder.ins().ireduce(types::I32, code_len);
+        let input = self.builder.ins().iconst(self.config.pointer_type(), 0);
+        let input_len = self.builder.ins().iconst(types::I32, 0);
+        let address = self.stack_word();
+        let call = self
+            .builder
+            .ins()
+            .call(self.host["create"], &[code, code_len, value, input, input_len, address]);
+        let status = self.builder.inst_results(call)[0];
+
+        let address = self.load_word(address);
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        Word(address.0.map(|limb| self.builder.ins().select(status, zero, limb)))
+    }
+
+    /// `datacopy(t, f, s)`, where the data from `f` to `f + s` must be in a
+    /// single object
+    fn data_copy(&mut self, args: &[Word]) {
+        let (dest, len) = self.range(args[0], args[2]);
+        let src = self.limb(args[1]);
+        let (end, overflow) = self.builder.ins().uadd_overflow(src, len);
+        self.builder.ins().trapnz(overflow, OUT_OF_BOUNDS);
+
+        let mut inside = self.builder.ins().iconst(types::I8, 0);
+        for (data, size) in self.objects.values().copied().collect::<Vec<_>>() {
+            let start = self.builder.ins().symbol_value(self.config.pointer_type(), data);
+            let start = self.extend(start);
+            let limit = self.builder.ins().iadd_imm_u(start, size as i64);
+            let after = self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, src, start);
+            let before = self.builder.ins().icmp(IntCC::UnsignedLessThanOrEqual, end, limit);
+            let within = self.builder.ins().band(after, before);
+            inside = self.builder.ins().bor(inside, within);
+        }
+        self.builder.ins().trapz(inside, OUT_OF_BOUNDS);
+
+        let src = self.pointer(src);
+        let len = self.pointer(len);
+        self.builder.call_memmove(self.config, dest, src, len);
+    }
+
+    /// `logN(p, s, t1, ..., tN)`
+    fn log(&mut self, args: &[Word]) {
+        let (data, len) = self.range(args[0], args[1]);
+        let len = self.builder.ins().ireduce(types::I32, len);
+
+        let topics = &args[2..];
+        let address = match topics.len() {
+            0 => self.builder.ins().iconst(self.config.pointer_type(), 0),
+            count => {
+                let slot = self.builder.create_sized_stack_slot(StackSlotData::new(
+                    StackSlotKind::ExplicitSlot,
+                    WORD_SIZE as u32 * count as u32,
+                    3,
+                ));
+                let address = self.builder.ins().stack_addr(self.config.pointer_type(), slot, 0);
+                for (i, topic) in topics.iter().enumerate() {
+                    let topic_address =
+                        self.builder.ins().iadd_imm_u(address, WORD_SIZE * i as i64);
+                    self.store_word(topic_address, *topic);
+                }
+                address
+            }
+        };
+        let count = self.builder.ins().iconst(types::I32, topics.len() as i64);
+        self.builder.ins().call(self.host["emit_log"], &[data, len, address, count]);
+    }
+
+    /// End the call with the host function, which does not return
+    fn end(&mut self, name: &str, data: Value, len: Value) {
+        self.builder.ins().call(self.host[name], &[data, len]);
+        self.builder.ins().trap(REVERT_RETURNED);
+        self.unreachable();
+    }
+
+    /// Jump to the block, the statements after the jump are unreachable
+    fn jump(&mut self, block: Block) {
+        self.builder.ins().jump(block, &[]);
+        self.unreachable();
+    }
+
+    /// Continue in a block which nothing jumps to, after a statement which
+    /// ends the block
+    fn unreachable(&mut self) {
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+    }
+
+    /// Compute an operation with the `word_op` host function
+    fn word_op(&mut self, op: WordOp, args: &[Word]) -> Word {
+        word::word_op(self.builder, self.config, self.swap, self.host, op, args)
+    }
+
+    fn add(&mut self, a: Word, b: Word) -> Word {
+        word::add(self.builder, a, b)
+    }
+
+    fn sub(&mut self, a: Word, b: Word) -> Word {
+        let (difference, _) = self.sub_borrow(a, b);
+        difference
+    }
+
+    /// The difference of the words, with the `i8` borrow out of it
+    fn sub_borrow(&mut self, a: Word, b: Word) -> (Word, Value) {
+        word::sub_borrow(self.builder, a, b)
+    }
+
+    /// The product modulo 2^256
+    fn mul(&mut self, a: Word, b: Word) -> Word {
+        word::mul(self.builder, a, b)
+    }
+
+    fn bitwise(
+        &mut self,
+        a: Word,
+        b: Word,
+        op: impl Fn(cranelift::frontend::FuncInstBuilder, Value, Value) -> Value,
+    ) -> Word {
+        word::bitwise(self.builder, a, b, op)
+    }
+
+    /// Whether `a < b` as unsigned words, an `i8` flag
+    fn less(&mut self, a: Word, b: Word) -> Value {
+        word::less(self.builder, a, b)
+    }
+
+    /// Whether the words are equal, an `i8` flag
+    fn equal(&mut self, a: Word, b: Word) -> Value {
+        word::equal(self.builder, a, b)
+    }
+
+    /// The `or` of the limbs, non-zero if any is
+    fn any(&mut self, limbs: &[Value]) -> Value {
+        word::any(self.builder, limbs)
+    }
+
+    /// Whether the word is not zero, to branch on
+    fn nonzero(&mut self, word: Word) -> Value {
+        self.any(&word.0)
+    }
+
+    /// The word of an `i8` flag, 1 or 0
+    fn flag_word(&mut self, flag: Value) -> Word {
+        word::flag_word(self.builder, flag)
+    }
+
+    /// The word of an `i64` value
+    fn limb_word(&mut self, limb: Value) -> Word {
+        word::limb_word(self.builder, limb)
+    }
+
+    /// The word of its bytes in big endian order
+    fn constant(&mut self, bytes: &[u8; 32]) -> Word {
+        word::constant(self.builder, bytes)
+    }
+
+    /// The low limb of a word, trapping if it does not fit, for sizes and
+    /// offsets
+    fn limb(&mut self, word: Word) -> Value {
+        let [low, high @ ..] = word.0;
+        let high = self.any(&high);
+        self.builder.ins().trapnz(high, OUT_OF_BOUNDS);
+        low
+    }
+
+    /// The address of `len` bytes of memory at the offset, trapping if they
+    /// are not all in the buffer
+    fn address(&mut self, offset: Word, len: i64) -> Value {
+        let len = self.builder.ins().iconst(types::I64, len);
+        self.checked(offset, len)
+    }
+
+    /// The address and `i64` length of the memory at the offset
+    fn range(&mut self, offset: Word, len: Word) -> (Value, Value) {
+        let len = self.limb(len);
+        (self.checked(offset, len), len)
+    }
+
+    fn checked(&mut self, offset: Word, len: Value) -> Value {
+        let offset = self.limb(offset);
+        let (end, overflow) = self.builder.ins().uadd_overflow(offset, len);
+        self.builder.ins().trapnz(overflow, OUT_OF_BOUNDS);
+        let outside = self.builder.ins().icmp(IntCC::UnsignedGreaterThan, end, self.memory_size);
+        self.builder.ins().trapnz(outside, OUT_OF_BOUNDS);
+
+        let offset = self.pointer(offset);
+        self.builder.ins().iadd(self.memory, offset)
+    }
+
+    /// The `i64` value as a pointer sized one
+    fn pointer(&mut self, value: Value) -> Value {
+        match self.config.pointer_type() {
+            types::I64 => value,
+            ty => self.builder.ins().ireduce(ty, value),
+        }
+    }
+
+    /// The pointer sized value as an `i64` one
+    fn extend(&mut self, value: Value) -> Value {
+        match self.config.pointer_type() {
+            types::I64 => value,
+            _ => self.builder.ins().uextend(types::I64, value),
+        }
+    }
+
+    /// The `i32` offset into the call data, as in the EVM the bytes past its
+    /// end read as zero: an offset which does not fit is clamped to the
+    /// largest one, past the end of any input
+    fn calldata_offset(&mut self, offset: Word) -> Value {
+        let [low, high @ ..] = offset.0;
+        let high = self.any(&high);
+        let max = self.builder.ins().iconst(types::I64, i64::from(i32::MAX));
+        let clamped = self.builder.ins().umin(low, max);
+        let clamped = self.builder.ins().select(high, max, clamped);
+        self.builder.ins().ireduce(types::I32, clamped)
+    }
+
+    fn return_data_size(&mut self) -> Value {
+        let call = self.builder.ins().call(self.host["return_data_size"], &[]);
+        self.builder.inst_results(call)[0]
+    }
+
+    /// A word on the stack, to pass to the host
+    fn stack_word(&mut self) -> Value {
+        word::stack_word(self.builder, self.config)
+    }
+
+    /// The word copied on the stack
+    fn spill(&mut self, word: Word) -> Value {
+        word::spill(self.builder, self.config, self.swap, word)
+    }
+
+    fn load_word(&mut self, address: Value) -> Word {
+        word::load_word(self.builder, self.swap, address)
+    }
+
+    fn store_word(&mut self, address: Value, word: Word) {
+        word::store_word(self.builder, self.swap, address, word)
+    }
+
+    fn declare(&mut self, name: &str, value: Word) {
+        let vars = [(); 4].map(|()| self.builder.declare_var(types::I64));
+        for (var, limb) in vars.into_iter().zip(value.0) {
+            self.builder.def_var(var, limb);
+        }
+        self.scopes.last_mut().expect("declared in a block").insert(name.to_string(), vars);
+    }
+
+    fn variable(&self, name: &str) -> Result<[Variable; 4], YulError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .ok_or_else(|| YulError::Undeclared(name.to_string()))
+    }
+}
+
+/// The bytes of a literal in big endian order. Numbers are right aligned and
+/// strings left aligned, as in the EVM.
+fn literal_bytes(literal: &pt::YulExpression) -> Result<[u8; 32], YulError> {
+    let invalid = || YulError::Literal(literal.to_string());
+    let number = |n: Option<BigUint>| {
+        let bytes = n.ok_or_else(invalid)?.to_bytes_be();
+        let mut word = [0; 32];
+        match word.len().checked_sub(bytes.len()) {
+            Some(start) => word[start..].copy_from_slice(&bytes),
+            None => return Err(invalid()),
+        }
+        Ok(word)
+    };
+    let string = |bytes: &[u8]| {
+        let mut word = [0; 32];
+        word.get_mut(..bytes.len()).ok_or_else(invalid)?.copy_from_slice(bytes);
+        Ok(word)
+    };
+
+    match literal {
+        pt::YulExpression::BoolLiteral(_, value, _) => number(Some(BigUint::from(*value as u8))),
+        pt::YulExpression::NumberLiteral(_, digits, exp, _) => {
+            let mut n = BigUint::from_str_radix(&digits.replace('_', ""), 10).ok();
+            if !exp.is_empty() {
+                let exp = exp.parse::<u32>().map_err(|_| invalid())?;
+                n = n.map(|n| n * BigUint::from(10u8).pow(exp));
+            }
+            number(n)
+        }
+        pt::YulExpression::HexNumberLiteral(_, hex, _) => {
+            let digits = hex.trim_start_matches("0x").replace('_', "");
+            number(BigUint::from_str_radix(&digits, 16).ok())
+        }
+        pt::YulExpression::StringLiteral(literal, _) => string(literal.string.as_bytes()),
+        pt::YulExpression::HexStringLiteral(literal, _) => {
+            let digits = literal.hex.trim_start_matches("hex");
+            let digits = digits.trim_matches(|c| c == '"' || c == '\'').replace('_', "");
+            let bytes = (0..digits.len())
+                .step_by(2)
+                .map(|i| digits.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid)?;
+            string(&bytes)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codegen::{
+            contract_code, gen_contracts,
+            listing::Listing,
+            mangle::Symbol,
+            tests::{analyze, entry, import_host, signature, try_build, CONFIG},
+            CodegenOptions,
+        },
+        diagnostics::Diagnostics,
+        parser::{ast::Statement, parse_statement},
+        semantic::ast::Type as SolType,
+    };
+
+    /// Lower the assembly block in a function taking the memory buffer,
+    /// giving its verified IR
+    fn lower(source: &str, storage: IndexMap<String, Layout>) -> Result<String, YulError> {
+        let Ok(Statement::Assembly { block, .. }) = parse_statement(source, 0) else {
+            panic!("not an assembly block: {source}");
+        };
+
+        let func = try_build(signature(&[types::I64, types::I64], &[]), |builder| {
+            let host = import_host(builder);
+            let [memory, memory_size] = entry(builder).try_into().unwrap();
+
+            YulLowering::new(builder, CONFIG, &host, true, memory, memory_size)
+                .with_storage(storage)
+                .lower(&block)?;
+            builder.ins().return_(&[]);
+            Ok(())
+        })?;
+        Ok(func.display().to_string())
+    }
+
+    #[test]
+    fn test_lower_arithmetic() {
+        let ir = lower(
+            r#"assembly {
+                let x := add(1, 0x02)
+                let y := mul(x, sub(x, 1))
+                if lt(x, y) { mstore(0, div(y, x)) }
+                sstore(0, not(y))
+                log1(0, 32, "Transfer")
+            }"#,
+            IndexMap::new(),
+        )
+        .unwrap();
+
+        assert!(ir.contains("uadd_overflow"), "{ir}");
+        assert!(ir.contains("umulhi"), "{ir}");
+        assert!(ir.contains("usub_overflow"), "{ir}");
+        for host in ["word_op", "storage_store", "emit_log"] {
+            assert!(ir.contains(&format!("%{host}")), "{host} is not called: {ir}");
+        }
+        assert!(ir.contains("store notrap big"), "{ir}");
+        assert!(ir.contains("heap_oob"), "{ir}");
+    }
+
+    #[test]
+    fn test_lower_control_flow() {
+        let ir = lower(
+            r#"assembly {
+                function twice(v) -> r {
+                    if iszero(v) { leave }
+                    r := add(v, v)
+                }
+                let sum := 0
+                for { let i := 0 } lt(i, 10) { i := add(i, 1) } {
+                    if eq(i, 3) { continue }
+                    if gt(i, 8) { break }
+                    sum := add(sum, twice(i))
+                }
+                switch sum
+                case 0 { revert(0, 0) }
+                case 1 { sum := 2 }
+                case 2 { sum := 3 }
+                case 3 { sum := 4 }
+                default { return(0, 32) }
+                mstore(0, sum)
+            }"#,
+            IndexMap::new(),
+        )
+        .unwrap();
+
+        assert!(ir.contains("br_table"), "{ir}");
+        assert!(ir.contains("%revert"), "{ir}");
+        assert!(ir.contains("%return"), "{ir}");
+        assert!(ir.contains("user1"), "{ir}");
+    }
+
+    #[test]
+    fn test_lower_storage_slot() {
+        let layout = Layout {
+            slot: 3.into(),
+            offset: 7,
+            contract_no: 0,
+            var_no: 0,
+            ty: SolType::Uint(256),
+            storage_class: StorageClass::Persistent,
+        };
+        let storage = IndexMap::from([("counter".to_string(), layout)]);
+        let ir = lower(
+            "assembly { sstore(counter.slot, add(sload(counter.slot), counter.offset)) }",
+            storage,
+        )
+        .unwrap();
+
+        assert!(ir.contains("%storage_load"), "{ir}");
+        assert!(ir.contains("%storage_store"), "{ir}");
+        assert!(ir.contains("iconst.i64 3"), "{ir}");
+        assert!(ir.contains("iconst.i64 7"), "{ir}");
+
+        let err = lower("assembly { sstore(other.slot, 1) }", IndexMap::new());
+        assert_eq!(err, Err(YulError::NoStorage("other".into())));
+    }
+
+    #[test]
+    fn test_lower_calldata() {
+        let ir = lower(
+            "assembly { calldatacopy(0, 4, calldatasize()) mstore(0, calldataload(4)) }",
+            IndexMap::new(),
+        )
+        .unwrap();
+
+        assert!(ir.contains("%calldata_size"), "{ir}");
+        assert!(ir.contains("%calldata_copy"), "{ir}");
+        assert!(ir.contains("umin"), "the offsets are clamped: {ir}");
+    }
+
+    #[test]
+    fn test_lower_errors() {
+        let cases = [
+            ("assembly { x := 1 }", YulError::Undeclared("x".into())),
+            ("assembly { pop(gas()) }", YulError::Unavailable("gas".into())),
+            (
+                "assembly { function f(a) -> b { b := f(a) } pop(f(1)) }",
+                YulError::Recursive("f".into()),
+            ),
+            ("assembly { break }", YulError::Misplaced("break", "loop")),
+            ("assembly { let a, b := 1 }", YulError::ValueCount { expected: 2, found: 1 }),
+            ("assembly { pop(datasize(\"C\")) }", YulError::UnknownObject("C".into())),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(lower(source, IndexMap::new()), Err(expected), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_inline_assembly() {
+        let source = r#"
+            contract Vault {
+                uint balance;
+                function deposit(uint amount) public returns (uint total) {
+                    assembly {
+                        total := add(sload(balance.slot), amount)
+                        sstore(balance.slot, total)
+                        mstore(0, total)
+                        log0(0, 32)
+                    }
+                }
+            }
+        "#;
+        let ctx = analyze(source);
+        let mut listing = Listing::default();
+        let mut diagnostics = Diagnostics::default();
+        let options = CodegenOptions::default();
+        gen_contracts(&[0], &ctx, &options, &mut listing, &mut diagnostics).unwrap();
+        assert!(!diagnostics.any_errors(), "{:?}", diagnostics.errors());
+
+        // The function is compiled for the host and exported
+        let func = ctx.functions.iter().find(|func| func.id.name == "deposit").unwrap();
+        let symbol = Symbol::function(func, &ctx).mangle();
+        let code = contract_code(&ctx, "Vault").unwrap();
+        assert!(code.windows(symbol.len()).any(|name| name == symbol.as_bytes()));
+    }
+}
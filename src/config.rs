@@ -0,0 +1,617 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Project level compiler options loaded from `hmt-solidity.toml`.
+//!
+//! Only the subset of TOML needed by the config schema is understood: tables,
+//! comments, and keys holding strings, integers, booleans or arrays of those.
+//!
+//! ```toml
+//! import-paths = ["lib", "node_modules"]
+//! target = "x86_64-unknown-linux-gnu"
+//! opt-level = 2
+//! unroll-budget = 64
+//! code-size-limit = 24576
+//! artifacts = ["ir", "ast"]
+//! features = ["debug"]
+//!
+//! [remappings]
+//! "@openzeppelin/" = "lib/openzeppelin-contracts/"
+//!
+//! [lints]
+//! enable = ["storage-in-loop"]
+//! disable = ["unused-variable"]
+//!
+//! [fmt]
+//! indent-width = 4
+//! line-length = 120
+//! brace-style = "same-line"
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use strum::{Display, EnumString};
+use thiserror::Error;
+
+use crate::formatter::BraceStyle;
+
+/// Name of the config file searched for in the working directory and its ancestors
+pub const CONFIG_FILENAME: &str = "hmt-solidity.toml";
+
+/// The highest supported optimization level
+pub const MAX_OPT_LEVEL: u8 = 2;
+
+/// Compiler options of a project
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Directories searched for imports
+    pub import_paths: Vec<PathBuf>,
+    /// Import prefix remappings, e.g. `@openzeppelin/` to `lib/openzeppelin-contracts/`
+    pub remappings: Vec<(String, PathBuf)>,
+    /// Target triple, the host when not set
+    pub target: Option<String>,
+    /// Optimization level between 0 and [`MAX_OPT_LEVEL`]
+    pub opt_level: Option<u8>,
+    /// Most statements a loop may grow to when unrolled at optimization level 2
+    pub unroll_budget: Option<usize>,
+    /// Size in bytes above which the code of a contract is warned about, the
+    /// limit of the platform when not set
+    pub code_size_limit: Option<usize>,
+    /// Lints explicitly switched on or off
+    pub lints: Lints,
+    /// Artifacts to produce, the IR only when empty
+    pub artifacts: Vec<Artifact>,
+    /// Features switched on, which select the items marked with `@if(feature)`
+    pub features: Vec<String>,
+    /// Layout of the `fmt` command
+    pub fmt: FmtOptions,
+}
+
+/// Lints explicitly switched on or off
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lints {
+    pub enable: Vec<String>,
+    pub disable: Vec<String>,
+}
+
+/// Layout options of the formatter, the defaults apply to those not set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FmtOptions {
+    pub indent_width: Option<usize>,
+    pub line_length: Option<usize>,
+    pub brace_style: Option<BraceStyle>,
+}
+
+/// An output artifact of the compiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Artifact {
+    /// The generated Cranelift IR
+    Ir,
+    /// The debug print of the parse tree
+    Ast,
+    /// The manifest of the host functions imported from the runtime
+    Imports,
+    /// The disassembly of the machine code of each function
+    Asm,
+    /// The manifest of the files read, with the hashes of their contents
+    Deps,
+    /// The rules making the outputs depend on the files read, for `make`
+    MakeDeps,
+    /// The metadata of the object of each contract, with its code hash
+    Metadata,
+    /// The object file of the input, written to the output file instead of
+    /// the IR
+    Object,
+    /// The generated Cranelift IR, printed rather than written
+    Clif,
+    /// The parse tree as JSON, with the `serde` feature
+    AstJson,
+    /// The declarations of the semantic analysis as JSON
+    SemaJson,
+}
+
+/// Errors found while loading a config file
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read '{path}': {err}", path = .0.display(), err = .1)]
+    Io(PathBuf, std::io::Error),
+
+    #[error("line {0}: {1}")]
+    Syntax(usize, String),
+
+    #[error("line {0}: unknown key '{1}'")]
+    UnknownKey(usize, String),
+
+    #[error("line {0}: expected {2} for '{1}'")]
+    InvalidValue(usize, String, &'static str),
+}
+
+impl Config {
+    /// Load the config from the given file
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let mut config = contents.parse::<Config>()?;
+
+        // Relative paths in a config file are relative to the file itself
+        if let Some(base) = path.parent() {
+            for path in config.import_paths.iter_mut() {
+                *path = base.join(&*path);
+            }
+            for (_, path) in config.remappings.iter_mut() {
+                *path = base.join(&*path);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Find the closest config file, starting at `dir` and walking up its ancestors
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors().map(|dir| dir.join(CONFIG_FILENAME)).find(|path| path.is_file())
+    }
+
+    /// Merge `other` into this config. Options set in `other` take precedence,
+    /// lists are combined.
+    pub fn merge(&mut self, other: Config) {
+        for path in other.import_paths {
+            if !self.import_paths.contains(&path) {
+                self.import_paths.push(path);
+            }
+        }
+
+        for (map, path) in other.remappings {
+            if let Some((_, existing)) = self.remappings.iter_mut().find(|(m, _)| *m == map) {
+                *existing = path;
+            } else {
+                self.remappings.push((map, path));
+            }
+        }
+
+        if other.target.is_some() {
+            self.target = other.target;
+        }
+
+        if other.opt_level.is_some() {
+            self.opt_level = other.opt_level;
+        }
+
+        if other.unroll_budget.is_some() {
+            self.unroll_budget = other.unroll_budget;
+        }
+
+        if other.code_size_limit.is_some() {
+            self.code_size_limit = other.code_size_limit;
+        }
+
+        for lint in other.lints.enable {
+            self.lints.disable.retain(|l| *l != lint);
+            if !self.lints.enable.contains(&lint) {
+                self.lints.enable.push(lint);
+            }
+        }
+
+        for lint in other.lints.disable {
+            self.lints.enable.retain(|l| *l != lint);
+            if !self.lints.disable.contains(&lint) {
+                self.lints.disable.push(lint);
+            }
+        }
+
+        for artifact in other.artifacts {
+            if !self.artifacts.contains(&artifact) {
+                self.artifacts.push(artifact);
+            }
+        }
+
+        for feature in other.features {
+            if !self.features.contains(&feature) {
+                self.features.push(feature);
+            }
+        }
+
+        if other.fmt.indent_width.is_some() {
+            self.fmt.indent_width = other.fmt.indent_width;
+        }
+
+        if other.fmt.line_length.is_some() {
+            self.fmt.line_length = other.fmt.line_length;
+        }
+
+        if other.fmt.brace_style.is_some() {
+            self.fmt.brace_style = other.fmt.brace_style;
+        }
+    }
+
+    /// Returns `Some(true)` or `Some(false)` if the lint was explicitly switched
+    /// on or off, `None` if the default applies.
+    pub fn lint(&self, name: &str) -> Option<bool> {
+        if self.lints.disable.iter().any(|l| l == name) {
+            Some(false)
+        } else if self.lints.enable.iter().any(|l| l == name) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the artifact should be produced
+    pub fn emits(&self, artifact: Artifact) -> bool {
+        if self.artifacts.is_empty() {
+            artifact == Artifact::Ir
+        } else {
+            self.artifacts.contains(&artifact)
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+
+        for (line, table, key, value) in parse_toml(s)? {
+            match (table.as_str(), key.as_str()) {
+                ("", "import-paths") => {
+                    config.import_paths =
+                        value.into_strings(line, &key)?.into_iter().map(PathBuf::from).collect();
+                }
+                ("", "target") => config.target = Some(value.into_string(line, &key)?),
+                ("", "opt-level") => {
+                    config.opt_level = match value {
+                        Value::Integer(level) if (0..=MAX_OPT_LEVEL as i64).contains(&level) => {
+                            Some(level as u8)
+                        }
+                        _ => return Err(ConfigError::InvalidValue(line, key, "0, 1 or 2")),
+                    };
+                }
+                ("", "unroll-budget") => config.unroll_budget = Some(value.into_usize(line, &key)?),
+                ("", "code-size-limit") => {
+                    config.code_size_limit = Some(value.into_usize(line, &key)?)
+                }
+                ("", "artifacts") => {
+                    config.artifacts = value
+                        .into_strings(line, &key)?
+                        .iter()
+                        .map(|s| {
+                            s.parse().map_err(|_| {
+                                ConfigError::InvalidValue(line, key.clone(), "artifact")
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                ("", "features") => config.features = value.into_strings(line, &key)?,
+                ("remappings", _) => {
+                    let path = value.into_string(line, &key)?;
+                    config.remappings.push((key, PathBuf::from(path)));
+                }
+                ("lints", "enable") => config.lints.enable = value.into_strings(line, &key)?,
+                ("lints", "disable") => config.lints.disable = value.into_strings(line, &key)?,
+                ("fmt", "indent-width") => {
+                    config.fmt.indent_width = Some(value.into_usize(line, &key)?)
+                }
+                ("fmt", "line-length") => {
+                    config.fmt.line_length = Some(value.into_usize(line, &key)?)
+                }
+                ("fmt", "brace-style") => {
+                    config.fmt.brace_style =
+                        Some(value.into_string(line, &key)?.parse().map_err(|_| {
+                            ConfigError::InvalidValue(
+                                line,
+                                key.clone(),
+                                "'same-line' or 'next-line'",
+                            )
+                        })?);
+                }
+                ("", _) => return Err(ConfigError::UnknownKey(line, key)),
+                (table, _) => return Err(ConfigError::UnknownKey(line, format!("{table}.{key}"))),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// A TOML value
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn into_string(self, line: usize, key: &str) -> Result<String, ConfigError> {
+        match self {
+            Value::String(s) => Ok(s),
+            _ => Err(ConfigError::InvalidValue(line, key.to_string(), "a string")),
+        }
+    }
+
+    fn into_usize(self, line: usize, key: &str) -> Result<usize, ConfigError> {
+        match self {
+            Value::Integer(n) if n > 0 => Ok(n as usize),
+            _ => Err(ConfigError::InvalidValue(line, key.to_string(), "a positive integer")),
+        }
+    }
+
+    fn into_strings(self, line: usize, key: &str) -> Result<Vec<String>, ConfigError> {
+        match self {
+            Value::Array(values) => values.into_iter().map(|v| v.into_string(line, key)).collect(),
+            _ => Err(ConfigError::InvalidValue(line, key.to_string(), "an array of strings")),
+        }
+    }
+}
+
+/// Parse the document into a list of `(line, table, key, value)` entries
+fn parse_toml(s: &str) -> Result<Vec<(usize, String, String, Value)>, ConfigError> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    let mut lines = s.lines().enumerate().map(|(no, line)| (no + 1, line));
+
+    while let Some((line, text)) = lines.next() {
+        let mut text = strip_comment(text).trim().to_string();
+
+        if text.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = text.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| ConfigError::Syntax(line, "unterminated table header".into()))?;
+            table = name.trim().to_string();
+            continue;
+        }
+
+        // Arrays may span multiple lines
+        while !brackets_balanced(&text) {
+            let Some((_, next)) = lines.next() else {
+                return Err(ConfigError::Syntax(line, "unterminated array".into()));
+            };
+            text.push(' ');
+            text.push_str(strip_comment(next).trim());
+        }
+
+        let (key, value) =
+            if text.starts_with('"') { split_quoted_key(&text) } else { text.split_once('=') }
+                .ok_or_else(|| ConfigError::Syntax(line, "expected 'key = value'".into()))?;
+
+        let key =
+            parse_key(key.trim()).ok_or_else(|| ConfigError::Syntax(line, "invalid key".into()))?;
+        let (value, rest) = parse_value(value.trim())
+            .ok_or_else(|| ConfigError::Syntax(line, format!("invalid value for '{key}'")))?;
+
+        if !rest.trim().is_empty() {
+            return Err(ConfigError::Syntax(line, format!("unexpected '{}'", rest.trim())));
+        }
+
+        entries.push((line, table.clone(), key, value));
+    }
+
+    Ok(entries)
+}
+
+/// Split `"quoted = key" = value` on the first `=` after the closing quote
+fn split_quoted_key(text: &str) -> Option<(&str, &str)> {
+    let end = text[1..].find('"')? + 2;
+    let (key, rest) = text.split_at(end);
+    Some((key, rest.trim_start().strip_prefix('=')?))
+}
+
+fn parse_key(key: &str) -> Option<String> {
+    if let Some(quoted) = key.strip_prefix('"') {
+        return quoted.strip_suffix('"').map(str::to_string);
+    }
+
+    let valid =
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| key.to_string())
+}
+
+/// Parse a value, returning the value and the remaining input
+fn parse_value(s: &str) -> Option<(Value, &str)> {
+    if let Some(rest) = s.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Some((Value::String(value), &rest[i + 1..])),
+                '\\' => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    c @ ('"' | '\\') => value.push(c),
+                    _ => return None,
+                },
+                c => value.push(c),
+            }
+        }
+
+        return None;
+    }
+
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut values = Vec::new();
+
+        loop {
+            rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Some((Value::Array(values), rest));
+            }
+
+            let (value, next) = parse_value(rest)?;
+            values.push(value);
+
+            rest = next.trim_start();
+            if let Some(next) = rest.strip_prefix(',') {
+                rest = next;
+            } else if !rest.starts_with(']') {
+                return None;
+            }
+        }
+    }
+
+    let end = s.find([',', ']', ' ']).unwrap_or(s.len());
+    let (word, rest) = s.split_at(end);
+
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => Value::Integer(word.replace('_', "").parse().ok()?),
+    };
+
+    Some((value, rest))
+}
+
+/// Remove a trailing `#` comment which is not part of a string
+fn strip_comment(line: &str) -> &str {
+    match unquoted(line).find(|(_, c)| *c == '#') {
+        Some((i, _)) => &line[..i],
+        None => line,
+    }
+}
+
+fn brackets_balanced(text: &str) -> bool {
+    unquoted(text).fold(0i32, |depth, (_, c)| match c {
+        '[' => depth + 1,
+        ']' => depth - 1,
+        _ => depth,
+    }) <= 0
+}
+
+/// Iterate over the characters of `text` which are outside of a string
+fn unquoted(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut in_string = false;
+    let mut escaped = false;
+
+    text.char_indices().filter(move |&(_, c)| {
+        let quoted = in_string;
+        match c {
+            '\\' if in_string && !escaped => {
+                escaped = true;
+                return false;
+            }
+            '"' if !escaped => in_string = !in_string,
+            _ => (),
+        }
+        escaped = false;
+        !quoted && c != '"'
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = r#"
+            # project options
+            import-paths = [
+                "lib",
+                "node_modules", # npm packages
+            ]
+            target = "x86_64-unknown-linux-gnu"
+            opt-level = 2
+            unroll-budget = 32
+            code-size-limit = 24576
+            artifacts = ["ir", "ast"]
+            features = ["debug"]
+
+            [remappings]
+            "@openzeppelin/" = "lib/openzeppelin-contracts/"
+
+            [lints]
+            enable = ["storage-in-loop"]
+            disable = []
+
+            [fmt]
+            indent-width = 2
+            brace-style = "next-line"
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(config.import_paths, vec![PathBuf::from("lib"), PathBuf::from("node_modules")]);
+        assert_eq!(
+            config.remappings,
+            vec![("@openzeppelin/".to_string(), PathBuf::from("lib/openzeppelin-contracts/"))]
+        );
+        assert_eq!(config.target.as_deref(), Some("x86_64-unknown-linux-gnu"));
+        assert_eq!(config.opt_level, Some(2));
+        assert_eq!(config.unroll_budget, Some(32));
+        assert_eq!(config.code_size_limit, Some(24576));
+        assert_eq!(config.artifacts, vec![Artifact::Ir, Artifact::Ast]);
+        assert_eq!(config.features, vec!["debug".to_string()]);
+        assert_eq!(config.lint("storage-in-loop"), Some(true));
+        assert_eq!(config.lint("unused-variable"), None);
+        assert_eq!(
+            config.fmt,
+            FmtOptions {
+                indent_width: Some(2),
+                line_length: None,
+                brace_style: Some(BraceStyle::NextLine)
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_errors() {
+        assert!(matches!("optimize = 1".parse::<Config>(), Err(ConfigError::UnknownKey(1, _))));
+        assert!(matches!(
+            "\nopt-level = 3".parse::<Config>(),
+            Err(ConfigError::InvalidValue(2, _, _))
+        ));
+        assert!(matches!("target = ".parse::<Config>(), Err(ConfigError::Syntax(1, _))));
+        assert!(matches!(
+            "import-paths = [\"lib\"".parse::<Config>(),
+            Err(ConfigError::Syntax(1, _))
+        ));
+    }
+
+    #[test]
+    fn test_merge_config() {
+        let mut config = Config {
+            import_paths: vec!["lib".into()],
+            remappings: vec![("@oz/".into(), "lib/oz".into())],
+            opt_level: Some(1),
+            lints: Lints { enable: vec!["a".into()], disable: vec!["b".into()] },
+            ..Default::default()
+        };
+
+        config.merge(Config {
+            import_paths: vec!["lib".into(), "src".into()],
+            remappings: vec![("@oz/".into(), "vendor/oz".into())],
+            opt_level: Some(2),
+            lints: Lints { enable: vec!["b".into()], disable: vec![] },
+            ..Default::default()
+        });
+
+        assert_eq!(config.import_paths, vec![PathBuf::from("lib"), PathBuf::from("src")]);
+        assert_eq!(config.remappings, vec![("@oz/".to_string(), PathBuf::from("vendor/oz"))]);
+        assert_eq!(config.opt_level, Some(2));
+        assert_eq!(config.lint("a"), Some(true));
+        assert_eq!(config.lint("b"), Some(true));
+        assert!(config.emits(Artifact::Ir));
+        assert!(!config.emits(Artifact::Ast));
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The files which took part in a compilation, as written by `compile --emit
+//! deps` and `--emit make-deps` for build systems. The JSON manifest gives the
+//! keccak256 hash of the contents of each file, so that a cache can tell
+//! whether a build is still up to date, and the Makefile fragment gives the
+//! rules rebuilding the outputs when any of the files changes.
+
+use std::path::{Path, PathBuf};
+
+use sha3::{Digest, Keccak256};
+
+use crate::{abi::ethereum::hex, helpers::json::Json, resolver::FileResolver};
+
+/// A file read by the compiler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub path: PathBuf,
+    /// The keccak256 hash of the contents, in hex digits
+    pub hash: String,
+}
+
+impl Dependency {
+    pub fn new(path: PathBuf, contents: &[u8]) -> Self {
+        Self { path, hash: hex(&Keccak256::digest(contents)) }
+    }
+}
+
+/// The files the resolver loaded from the filesystem, the input and its
+/// imports, in the order they were loaded. Sources which are not on the
+/// filesystem, like stdin or the builtin modules, are left out.
+pub fn dependencies(resolver: &FileResolver) -> Vec<Dependency> {
+    let mut deps: Vec<Dependency> = Vec::new();
+
+    // The resolved files have absolute paths, see `FileResolver::resolve`
+    for file in resolver.files().iter().filter(|file| file.full_path.is_absolute()) {
+        if !deps.iter().any(|dep| dep.path == file.full_path) {
+            deps.push(Dependency::new(file.full_path.clone(), file.contents.as_bytes()));
+        }
+    }
+
+    deps
+}
+
+/// The JSON manifest of the dependencies
+pub fn to_json(deps: &[Dependency]) -> Json {
+    let files = deps.iter().map(|dep| {
+        Json::object([
+            ("path", dep.path.display().to_string().into()),
+            ("keccak256", dep.hash.clone().into()),
+        ])
+    });
+
+    Json::object([("files", Json::Array(files.collect()))])
+}
+
+/// A Makefile fragment making the targets depend on the files. Every file also
+/// gets a rule without prerequisites, so that deleting one of the imports does
+/// not stop `make` before the build is run again.
+pub fn to_makefile(targets: &[&Path], deps: &[Dependency]) -> String {
+    let targets = targets.iter().map(|target| escape(target)).collect::<Vec<_>>();
+    let prerequisites = deps.iter().map(|dep| escape(&dep.path)).collect::<Vec<_>>();
+
+    let mut makefile = format!("{}:", targets.join(" "));
+    for prerequisite in &prerequisites {
+        makefile.push_str(" \\\n  ");
+        makefile.push_str(prerequisite);
+    }
+    makefile.push('\n');
+
+    for prerequisite in &prerequisites {
+        makefile.push_str(&format!("\n{prerequisite}:\n"));
+    }
+
+    makefile
+}
+
+/// Escape the characters `make` would otherwise read as separators or
+/// variable references
+fn escape(path: &Path) -> String {
+    let mut escaped = String::new();
+    for c in path.display().to_string().chars() {
+        match c {
+            ' ' | '#' | ':' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '$' => escaped.push_str("$$"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let mut resolver = FileResolver::default();
+        resolver.set_file_contents("/src/main.sol", "import \"lib.sol\";".into());
+        resolver.set_file_contents("/src/lib.sol", "contract C {}".into());
+        resolver.set_file_contents("/src/main.sol", "import \"lib.sol\";".into());
+        resolver.set_file_contents("<stdin>", "contract D {}".into());
+
+        let deps = dependencies(&resolver);
+        let paths = deps.iter().map(|dep| dep.path.to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(paths, ["/src/main.sol", "/src/lib.sol"]);
+
+        // keccak256 of the empty input
+        assert_eq!(
+            Dependency::new("empty.sol".into(), b"").hash,
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+
+        let json = to_json(&deps);
+        let files = json.get("files").and_then(Json::as_array).unwrap();
+        assert_eq!(files[1].get("path").and_then(Json::as_str), Some("/src/lib.sol"));
+        assert_eq!(files[1].get("keccak256").and_then(Json::as_str), Some(deps[1].hash.as_str()));
+
+        let deps = [
+            Dependency::new("/src/main.sol".into(), b""),
+            Dependency::new("/my lib/$a.sol".into(), b""),
+        ];
+        assert_eq!(
+            to_makefile(&[Path::new("out/a.o"), Path::new("out/b.o")], &deps),
+            "out/a.o out/b.o: \\\n  /src/main.sol \\\n  /my\\ lib/$$a.sol\n\n/src/main.sol:\n\n/my\\ lib/$$a.sol:\n"
+        );
+    }
+}
@@ -15,16 +15,15 @@
 //! Solidity parser diagnostics.
 
 use std::{
-    ops::Range,
+    ops::{Range, RangeBounds},
     slice::{Iter, IterMut},
 };
 
-use ariadne::{Cache, Label, Report, ReportKind, Span};
+use ariadne::{Cache, Config, IndexType, Label, Report, ReportKind, Source, Span};
 use itertools::Itertools;
-use lalrpop_util::ParseError;
 use strum::{AsRefStr, Display, EnumString};
 
-use crate::{error::LexicalError, helpers::CodeLocation, lexer::token::Token, parser::ast::Loc};
+use crate::parser::ast::Loc;
 
 /// The level of a diagnostic.
 #[derive(Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq, EnumString, AsRefStr, Display)]
@@ -116,6 +115,23 @@ impl Diagnostic {
     pub fn error(loc: Loc, msg: impl Into<String>) -> Self {
         DiagnosticBuilder::new(loc, Level::Error).ty(ErrorType::SyntaxError).message(msg).build()
     }
+
+    #[inline]
+    /// Instantiate a new cast error Diagnostic.
+    pub fn cast_error(loc: Loc, msg: impl Into<String>) -> Self {
+        DiagnosticBuilder::new(loc, Level::Error).ty(ErrorType::CastError).message(msg).build()
+    }
+
+    #[inline]
+    /// Instantiate a new type error Diagnostic.
+    pub fn type_error(loc: Loc, msg: impl Into<String>) -> Self {
+        DiagnosticBuilder::new(loc, Level::Error).ty(ErrorType::TypeError).message(msg).build()
+    }
+
+    /// Render the diagnostic as an ariadne report on the source of its file.
+    pub fn render(&self, source: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Report::from(self).write_to_string(Source::from(source))
+    }
 }
 
 /// A builder for `Diagnostic`.
@@ -169,46 +185,6 @@ impl DiagnosticBuilder {
     }
 }
 
-/// Convert lalrop parser error to a Diagnostic
-impl<'input> From<(&ParseError<usize, Token<'input>, LexicalError>, usize)> for Diagnostic {
-    fn from((error, no): (&ParseError<usize, Token<'input>, LexicalError>, usize)) -> Self {
-        match error {
-            ParseError::InvalidToken { location } => {
-                Diagnostic::builder(Loc::File(no, *location, *location), Level::Error)
-                    .ty(ErrorType::ParserError)
-                    .message("invalid token")
-                    .build()
-            }
-            ParseError::UnrecognizedToken { token: (l, token, r), expected } => {
-                Diagnostic::builder(Loc::File(no, *l, *r), Level::Error)
-                    .ty(ErrorType::ParserError)
-                    .message(format!(
-                        "unrecognised token '{}', expected {}",
-                        token,
-                        expected.join(", ")
-                    ))
-                    .build()
-            }
-            ParseError::User { error } => Diagnostic::builder(error.loc(), Level::Error)
-                .ty(ErrorType::ParserError)
-                .message(error.to_string())
-                .build(),
-            ParseError::ExtraToken { token } => {
-                Diagnostic::builder(Loc::File(no, token.0, token.2), Level::Error)
-                    .ty(ErrorType::ParserError)
-                    .message(format!("extra token '{}' encountered", token.0))
-                    .build()
-            }
-            ParseError::UnrecognizedEof { expected, location } => {
-                Diagnostic::builder(Loc::File(no, *location, *location), Level::Error)
-                    .ty(ErrorType::ParserError)
-                    .message(format!("unexpected end of file, expecting {}", expected.join(", ")))
-                    .build()
-            }
-        }
-    }
-}
-
 /// Convert Diagnostic to ariadne::Report
 impl<'a> From<&Diagnostic> for Report<'a, Range<usize>> {
     fn from(val: &Diagnostic) -> Self {
@@ -220,14 +196,23 @@ impl<'a> From<&Diagnostic> for Report<'a, Range<usize>> {
                 Level::Warning => ReportKind::Warning,
                 Level::Error => ReportKind::Error,
             },
-            val.loc.range(),
+            // A diagnostic without a location, like one of generated code,
+            // is reported at the start of the source
+            val.loc.range().unwrap_or_default(),
         )
+        // Locations are byte offsets, which differ from characters in sources
+        // which are not ASCII
+        .with_config(Config::default().with_index_type(IndexType::Byte))
         .with_message(&val.message);
 
-        // Initialize labels vector
+        // Initialize labels vector, the notes without a location are kept as
+        // plain notes
         let mut labels = Vec::new();
         for note in &val.notes {
-            labels.push(Label::new(note.loc.range()).with_message(&note.message));
+            match note.loc.range() {
+                Some(range) => labels.push(Label::new(range).with_message(&note.message)),
+                None => report = report.with_note(&note.message),
+            }
         }
         report = report.with_labels(labels);
 
@@ -256,14 +241,44 @@ impl<'a, S: Span> ReportToStringExt<'a, S> for Report<'a, S> {
     }
 }
 
+/// Where the resolvers report diagnostics.
+///
+/// A resolver only pushes diagnostics and asks how many errors were reported,
+/// so each worker of a parallel pipeline, or each request of a language
+/// server, can report into a sink of its own without locking. The sinks are
+/// then merged in the order of the work, see [`Diagnostics::merge`], so the
+/// output does not depend on the scheduling.
+pub trait DiagnosticSink {
+    /// Reports a diagnostic.
+    fn push(&mut self, diagnostic: Diagnostic);
+
+    /// Returns the number of error-level diagnostics reported so far.
+    fn error_count(&self) -> usize;
+
+    /// Checks if any error-level diagnostic was reported.
+    fn any_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+}
+
 /// A collection of diagnostics with error tracking.
 ///
-/// Maintains a list of diagnostics and tracks whether any errors are present.
+/// Maintains a list of diagnostics and counts the errors among them.
 /// Provides methods for adding diagnostics and checking error status.
 #[derive(Default, Debug, Clone)]
 pub struct Diagnostics {
     contents: Vec<Diagnostic>,
-    has_error: bool,
+    errors: usize,
+}
+
+impl DiagnosticSink for Diagnostics {
+    fn push(&mut self, diagnostic: Diagnostic) {
+        Diagnostics::push(self, diagnostic)
+    }
+
+    fn error_count(&self) -> usize {
+        self.errors
+    }
 }
 
 impl Diagnostics {
@@ -295,28 +310,39 @@ impl Diagnostics {
     /// Adds a diagnostic to the collection.
     pub fn push(&mut self, diagnostic: Diagnostic) {
         if matches!(diagnostic.level, Level::Error) {
-            self.has_error = true;
+            self.errors += 1;
         }
         self.contents.push(diagnostic)
     }
 
     /// Extends the collection with another Diagnostics.
     pub fn extend(&mut self, diagnostics: Diagnostics) {
-        self.has_error |= diagnostics.has_error;
+        self.errors += diagnostics.errors;
         self.contents.extend(diagnostics.contents);
     }
 
     /// Appends diagnostics from a vector into this collection.
     pub fn append(&mut self, diagnostics: &mut Vec<Diagnostic>) {
-        if !self.has_error {
-            self.has_error = diagnostics.iter().any(|m| m.level == Level::Error);
-        }
+        self.errors += diagnostics.iter().filter(|m| m.level == Level::Error).count();
         self.contents.append(diagnostics);
     }
 
+    /// Merges the sinks of the workers, each keyed by the position of its work,
+    /// in the order of the work whatever the order they finished in.
+    pub fn merge(sinks: impl IntoIterator<Item = (usize, Diagnostics)>) -> Diagnostics {
+        let mut sinks = sinks.into_iter().collect::<Vec<_>>();
+        sinks.sort_by_key(|(position, _)| *position);
+
+        let mut merged = Diagnostics::default();
+        for (_, sink) in sinks {
+            merged.extend(sink);
+        }
+        merged
+    }
+
     /// Checks if there are any error-level diagnostics in the collection.
     pub fn any_errors(&self) -> bool {
-        self.has_error
+        self.errors > 0
     }
 
     /// Returns all error-level diagnostics in the collection.
@@ -354,9 +380,125 @@ impl Diagnostics {
         self.contents.iter().any(|x| x.message == message)
     }
 
+    /// Returns the diagnostics located in the given file.
+    pub fn in_file(&self, file_no: usize) -> Vec<&Diagnostic> {
+        self.contents.iter().filter(|x| x.loc.no() == Some(file_no)).collect()
+    }
+
+    /// Returns the diagnostics whose level is in the range, e.g. `Level::Warning..`
+    /// for the warnings and errors.
+    pub fn with_levels(&self, levels: impl RangeBounds<Level>) -> Vec<&Diagnostic> {
+        self.contents.iter().filter(|x| levels.contains(&x.level)).collect()
+    }
+
+    /// Returns the diagnostics of the given type.
+    pub fn of_type(&self, ty: ErrorType) -> Vec<&Diagnostic> {
+        self.contents.iter().filter(|x| x.ty == ty).collect()
+    }
+
+    /// Returns the diagnostics located within the byte range of the file.
+    pub fn in_range(&self, file_no: usize, span: Range<usize>) -> Vec<&Diagnostic> {
+        self.contents
+            .iter()
+            .filter(|x| match x.loc {
+                Loc::File(no, start, end) => {
+                    no == file_no && span.start <= start && end <= span.end
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
     /// Sorts and deduplicates diagnostics, ensuring they're in order by location.
     pub fn normalize(&mut self) {
         self.contents.sort();
         self.contents.dedup();
+        self.errors = self.contents.iter().filter(|x| x.level == Level::Error).count();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::Loc;
+
+    fn resolve(sink: &mut dyn DiagnosticSink, message: &str) {
+        sink.push(Diagnostic::warning(Loc::Builtin, format!("{message} is shadowed")));
+        sink.push(Diagnostic::error(Loc::Builtin, format!("{message} is not found")));
+    }
+
+    #[test]
+    fn test_merge_sinks() {
+        let mut first = Diagnostics::default();
+        let mut second = Diagnostics::default();
+        resolve(&mut second, "b");
+        resolve(&mut first, "a");
+        assert_eq!(first.error_count(), 1);
+
+        let merged = Diagnostics::merge([(1, second), (0, first)]);
+        let messages = merged.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            ["a is shadowed", "a is not found", "b is shadowed", "b is not found"]
+        );
+        assert_eq!(merged.error_count(), 2);
+
+        let mut twice = Diagnostics::merge([(0, merged.clone()), (1, merged)]);
+        twice.normalize();
+        assert_eq!(twice.error_count(), 2);
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.push(Diagnostic::warning(Loc::File(0, 10, 12), "unused variable"));
+        diagnostics.push(Diagnostic::type_error(Loc::File(0, 20, 30), "cannot convert"));
+        diagnostics.push(Diagnostic::error(Loc::File(1, 10, 12), "not found"));
+        diagnostics.push(Diagnostic::info(Loc::File(1, 0, 40), "found 2 contracts"));
+        diagnostics.push(Diagnostic::error(Loc::CommandLine, "no such file"));
+
+        let messages =
+            |subset: Vec<&Diagnostic>| subset.iter().map(|d| d.message.clone()).collect::<Vec<_>>();
+        assert_eq!(messages(diagnostics.in_file(0)), ["unused variable", "cannot convert"]);
+        assert_eq!(
+            messages(diagnostics.with_levels(Level::Warning..)),
+            ["unused variable", "cannot convert", "not found", "no such file"]
+        );
+        assert_eq!(messages(diagnostics.with_levels(..=Level::Info)), ["found 2 contracts"]);
+        assert_eq!(messages(diagnostics.of_type(ErrorType::TypeError)), ["cannot convert"]);
+        assert_eq!(messages(diagnostics.in_range(0, 0..25)), ["unused variable"]);
+        assert_eq!(messages(diagnostics.in_range(1, 10..12)), ["not found"]);
+        assert!(diagnostics.in_range(2, 0..100).is_empty());
+    }
+
+    #[test]
+    fn test_render_multibyte_source() {
+        let source = "// 🦀 crab\n/* 中文注释 */ uint x;\n";
+        let x = source.find("x;").unwrap();
+        let loc = Loc::File(0, x, x + 1);
+        let diagnostic =
+            Diagnostic::builder(loc, Level::Error).message("m").note(loc, "declared here").build();
+        let report = diagnostic.render(source).unwrap();
+        assert!(report.contains(":2:17 ]"), "{report}");
+
+        // The label is under the `x`, past the four CJK characters which are
+        // two columns wide each
+        let lines = report.lines().collect::<Vec<_>>();
+        let line = lines.iter().position(|line| line.contains("uint x;")).unwrap();
+        let column = lines[line].chars().position(|c| c == 'x').unwrap() + 4;
+        assert_eq!(lines[line + 1].chars().nth(column), Some('┬'), "{report}");
+    }
+
+    #[test]
+    fn test_render_without_location() {
+        let diagnostic = Diagnostic::builder(Loc::Implicit, Level::Error)
+            .message("accessor cannot be generated")
+            .note(Loc::Builtin, "builtin definition")
+            .note(Loc::File(0, 5, 6), "declared here")
+            .build();
+        let report = diagnostic.render("uint x;").unwrap();
+        assert!(report.contains("accessor cannot be generated"), "{report}");
+        assert!(report.contains("builtin definition"), "{report}");
+        assert!(report.contains("declared here"), "{report}");
     }
 }
@@ -0,0 +1,199 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Entry points running the compiler pipeline on a source file.
+
+use std::{ffi::OsStr, path::PathBuf};
+
+use crate::{
+    config::Config,
+    diagnostics::{Diagnostic, Diagnostics},
+    lint,
+    parser::ast::Loc,
+    resolver::{FileResolver, ResolvedFile},
+    semantic::{self, context::Context, pass::PassManager},
+};
+
+/// A source file given to the compiler
+#[derive(Debug, Clone)]
+pub enum Input {
+    /// A file on the filesystem, resolved like an import
+    Path(PathBuf),
+    /// Source code which is not on the filesystem, e.g. read from stdin or an
+    /// editor buffer. The name is used for the file in diagnostics.
+    Source { name: String, contents: String },
+}
+
+impl Input {
+    /// The name of the input as given by the user
+    pub fn name(&self) -> String {
+        match self {
+            Input::Path(path) => path.display().to_string(),
+            Input::Source { name, .. } => name.clone(),
+        }
+    }
+}
+
+/// Create a file resolver with the import paths and remappings of the config
+pub fn resolver(config: &Config) -> FileResolver {
+    let mut resolver = FileResolver::default();
+
+    for path in &config.import_paths {
+        resolver.add_import_path(path);
+    }
+    for (map, path) in &config.remappings {
+        resolver.add_import_map(map.into(), path.clone());
+    }
+
+    resolver
+}
+
+/// Load the input into the resolver
+pub fn load(input: &Input, resolver: &mut FileResolver) -> Result<ResolvedFile, Diagnostic> {
+    let result = match input {
+        Input::Path(path) => resolver.resolve(None, path.as_os_str()),
+        Input::Source { name, contents } => {
+            resolver.set_file_contents(name, contents.clone());
+            resolver.resolve(None, OsStr::new(name))
+        }
+    };
+
+    result.map_err(|message| Diagnostic::error(Loc::CommandLine, message))
+}
+
+/// Run the semantic analysis on a loaded file, with the features switched on.
+/// The returned context holds the diagnostics, parse errors included.
+pub fn analyze(file: &ResolvedFile, resolver: &mut FileResolver, features: &[String]) -> Context {
+    analyze_passes(file, resolver, PassManager::all(), features)
+}
+
+/// Run the given passes of the semantic analysis, see [`analyze`]. The
+/// returned context holds their timings.
+pub fn analyze_passes(
+    file: &ResolvedFile,
+    resolver: &mut FileResolver,
+    passes: PassManager,
+    features: &[String],
+) -> Context {
+    let mut ctx = Context::new();
+    ctx.passes = passes;
+    ctx.features = features.iter().cloned().collect();
+
+    // A failed analysis has normally pushed its errors to the diagnostics
+    if let Err(err) = semantic::analyze(file, resolver, &mut ctx) {
+        if !ctx.diagnostics.any_errors() {
+            ctx.diagnostics.push(Diagnostic::error(Loc::CommandLine, err.to_string()));
+        }
+    }
+
+    ctx
+}
+
+/// Lex, parse and analyze the input without generating any code, for editors
+/// and other tools which are only interested in the diagnostics. The lints
+/// switched on in the config are run as well.
+pub fn check(input: &Input, config: &Config) -> Diagnostics {
+    let mut resolver = resolver(config);
+
+    match load(input, &mut resolver) {
+        Ok(file) => {
+            let mut ctx = analyze(&file, &mut resolver, &config.features);
+            // The tree is kept by the analysis unless the file does not parse
+            let tree =
+                ctx.files.iter_mut().find(|f| f.path == file.full_path).and_then(|f| f.tree.take());
+            if let Some(tree) = tree {
+                ctx.diagnostics.append(&mut lint::run(&tree, config));
+            }
+            ctx.diagnostics
+        }
+        Err(diagnostic) => {
+            let mut diagnostics = Diagnostics::default();
+            diagnostics.push(diagnostic);
+            diagnostics
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_errors() {
+        let input = Input::Source { name: "test.sol".into(), contents: "contract {".into() };
+        let diagnostics = check(&input, &Config::default());
+        assert!(diagnostics.any_errors());
+
+        let input = Input::Path("does/not/exist.sol".into());
+        let diagnostics = check(&input, &Config::default());
+        assert_eq!(diagnostics.first_error(), "file not found 'does/not/exist.sol'");
+
+        let input =
+            Input::Source { name: "test.sol".into(), contents: "pragma solidity ^0.8.0;".into() };
+        let diagnostics = check(&input, &Config::default());
+        assert!(!diagnostics.any_errors());
+    }
+
+    #[test]
+    fn test_check_contract() {
+        let contents = r#"
+            pragma solidity ^0.8.0;
+
+            function double(uint a) pure returns (uint) {
+                return a * 2;
+            }
+
+            abstract contract Ownable {
+                address owner;
+                error NotOwner(address caller);
+
+                constructor(address initial) {
+                    owner = initial;
+                }
+
+                modifier onlyOwner() {
+                    if (msg.sender != owner) revert NotOwner(msg.sender);
+                    _;
+                }
+            }
+
+            contract Vault is Ownable(address(0)) {
+                mapping(address => uint) balances;
+                uint total = 3;
+
+                event Deposit(address indexed from, uint amount);
+
+                function deposit() public payable {
+                    balances[msg.sender] += msg.value;
+                    total = double(total);
+                    emit Deposit(msg.sender, msg.value);
+                }
+
+                function withdraw(address payable to) public onlyOwner {
+                    to.transfer(address(this).balance);
+                }
+            }
+        "#;
+        let input = Input::Source { name: "vault.sol".into(), contents: contents.into() };
+        let diagnostics = check(&input, &Config::default());
+        assert_eq!(diagnostics.errors(), Vec::<&Diagnostic>::new());
+
+        let input = Input::Source {
+            name: "vault.sol".into(),
+            contents: contents.replace("double(total)", "double(missing)"),
+        };
+        let diagnostics = check(&input, &Config::default());
+        assert_eq!(diagnostics.first_error(), "'missing' not found");
+    }
+}
@@ -12,56 +12,320 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 use cranelift::{
-    module::FuncId,
+    codegen::ir::{Endianness, Signature},
+    module::{DataId, FuncId, Linkage, Module, ModuleError},
     object::ObjectModule,
-    prelude::{EntityRef, FunctionBuilder, Variable},
+    prelude::{FunctionBuilder, FunctionBuilderContext},
 };
 use thiserror::Error;
 
-use crate::parser::visitor::Visitor;
+use crate::{
+    codegen::{
+        constants::ConstantPool,
+        dce,
+        function::{self, FunctionLowering},
+        init,
+        mangle::Symbol,
+        yul::YulError,
+    },
+    diagnostics::{Diagnostic, Diagnostics, Level},
+    parser::ast::{self as pt, Loc},
+    runtime,
+    semantic::context::Context,
+};
 
 pub struct EmitContext<'a> {
     pub module: &'a mut ObjectModule,
-    pub builder: FunctionBuilder<'a>,
-    pub functions: HashMap<String, FuncId>,
-    pub variables: HashMap<String, Variable>,
-    pub index: usize,
+    /// The functions declared in the module, the imports of the runtime by
+    /// their name and the others by their mangled name
+    pub functions: IndexMap<String, FuncId>,
+    /// The IR of the functions defined
+    pub ir: String,
+    /// Keep the disassembly of the functions defined
+    pub disasm: bool,
+    /// The disassembly of the functions defined, by mangled name
+    pub listing: Vec<(String, String)>,
+    /// The code of the contracts created with `new`, with its size, by name
+    pub codes: IndexMap<String, (DataId, usize)>,
+    /// The constant data of the module, see [`ConstantPool`]
+    pub constants: &'a mut ConstantPool,
+    /// The errors of the functions which cannot be lowered
+    pub diagnostics: Diagnostics,
 }
 
 impl<'a> EmitContext<'a> {
-    pub fn new(module: &'a mut ObjectModule, builder: FunctionBuilder<'a>) -> Self {
-        Self { module, builder, functions: HashMap::new(), variables: HashMap::new(), index: 0 }
+    pub fn new(
+        module: &'a mut ObjectModule,
+        constants: &'a mut ConstantPool,
+        disasm: bool,
+    ) -> Self {
+        Self {
+            module,
+            functions: IndexMap::new(),
+            ir: String::new(),
+            disasm,
+            listing: Vec::new(),
+            codes: IndexMap::new(),
+            constants,
+            diagnostics: Diagnostics::default(),
+        }
     }
 
-    pub fn declare_var(&mut self, name: &str) -> Variable {
-        let var = Variable::new(self.index);
-        self.index += 1;
-        self.variables.insert(name.to_string(), var);
-        var
-    }
+    /// Declare a function of the module under its mangled name
+    pub fn declare_function(
+        &mut self,
+        symbol: &Symbol,
+        linkage: Linkage,
+        signature: &Signature,
+    ) -> Result<FuncId, Box<ModuleError>> {
+        let name = symbol.mangle();
+        let id = self.module.declare_function(&name, linkage, signature).map_err(Box::new)?;
+        self.functions.insert(name, id);
 
-    pub fn get_variable(&self, name: &str) -> Option<Variable> {
-        self.variables.get(name).cloned()
+        Ok(id)
     }
 }
 
-#[allow(dead_code)]
-pub struct CraneliftEmitter<'a> {
-    ctx: &'a mut EmitContext<'a>,
+/// Defines the functions resolved by the semantic analysis in the module. A
+/// function whose body cannot be lowered yet is reported as an error in
+/// [`EmitContext::diagnostics`], and the other functions are still lowered to
+/// find their errors too; only the errors of the module stop the generation.
+pub struct CraneliftEmitter<'a, 'b> {
+    ctx: &'a mut EmitContext<'b>,
+    sema: &'a Context,
+    /// Zero when loops are not unrolled
+    unroll_budget: usize,
 }
 
-impl<'a> CraneliftEmitter<'a> {
-    pub fn new(ctx: &'a mut EmitContext<'a>) -> Self {
-        Self { ctx }
+impl<'a, 'b> CraneliftEmitter<'a, 'b> {
+    pub fn new(ctx: &'a mut EmitContext<'b>, sema: &'a Context, unroll_budget: usize) -> Self {
+        Self { ctx, sema, unroll_budget }
+    }
+
+    /// Define the function, reading the state variables from the layout of
+    /// the contract when it has a body which uses them. The constructor of
+    /// the contract calls the initializers of its state variables, defined
+    /// first in a function of their own, see [`Symbol::initializers`].
+    pub fn emit_function(
+        &mut self,
+        function_no: usize,
+        contract_no: Option<usize>,
+    ) -> Result<(), EmitterError> {
+        let func = &self.sema.functions[function_no];
+
+        // Only the constructor of the contract being generated initializes it
+        let initializers = match contract_no {
+            Some(no) if func.is_constructor() && func.contract_no == Some(no) => {
+                self.emit_initializers(no)?
+            }
+            _ => None,
+        };
+
+        let symbol = Symbol::function(func, self.sema);
+        let signature = function::signature(
+            func,
+            self.ctx.module.isa().default_call_conv(),
+            self.ctx.module.isa().pointer_type(),
+        );
+        let callees = dce::callees(function_no, contract_no, self.sema);
+        let result = self.define(
+            &symbol,
+            Linkage::Export,
+            signature,
+            contract_no,
+            initializers,
+            &callees,
+            |lowering| lowering.lower(function_no),
+        );
+
+        let name = match (func.ty, func.contract_no) {
+            (pt::FunctionTy::Constructor, Some(no)) => {
+                format!("the constructor of '{}'", self.sema.contracts[no].id.name)
+            }
+            (pt::FunctionTy::Fallback | pt::FunctionTy::Receive, Some(no)) => {
+                format!("the {} function of '{}'", func.ty, self.sema.contracts[no].id.name)
+            }
+            _ => format!("function '{}'", func.id.name),
+        };
+        self.report(result.map(|_| ()), func.loc_prototype, &name)
+    }
+
+    /// Define the function assigning the initializers of the state variables
+    /// of the contract and its bases, see [`init`](crate::codegen::init),
+    /// unless it has none or they cannot be lowered
+    fn emit_initializers(&mut self, contract_no: usize) -> Result<Option<FuncId>, EmitterError> {
+        if init::initializers(contract_no, self.sema).is_empty() {
+            return Ok(None);
+        }
+
+        let contract = &self.sema.contracts[contract_no];
+        let symbol = Symbol::initializers(&contract.id.name);
+        let signature = Signature::new(self.ctx.module.isa().default_call_conv());
+        let callees = dce::initializer_callees(contract_no, self.sema);
+        let result = self.define(
+            &symbol,
+            Linkage::Local,
+            signature,
+            Some(contract_no),
+            None,
+            &callees,
+            |lowering| lowering.lower_initializers(),
+        );
+
+        let id = result.as_ref().ok().copied();
+        let name = format!("the initializers of the state variables of '{}'", contract.id.name);
+        self.report(result.map(|_| ()), contract.loc, &name)?;
+        Ok(id)
+    }
+
+    /// Report an error of the function or initializers named, located at the
+    /// location given when it has none of its own. Only the errors of the
+    /// module stop the generation.
+    fn report(
+        &mut self,
+        result: Result<(), EmitterError>,
+        loc: Loc,
+        name: &str,
+    ) -> Result<(), EmitterError> {
+        let err = match result {
+            Err(EmitterError::Module(err)) => return Err(EmitterError::Module(err)),
+            Err(err) => err,
+            Ok(()) => return Ok(()),
+        };
+        let at = match &err {
+            EmitterError::Unsupported(at, _) if *at != Loc::Implicit => *at,
+            _ => loc,
+        };
+        self.ctx.diagnostics.push(
+            Diagnostic::builder(at, Level::Error)
+                .message(err.to_string())
+                .note(loc, format!("{name} cannot be generated"))
+                .build(),
+        );
+        Ok(())
+    }
+
+    /// Lower a function and define it in the module under the symbol,
+    /// declaring it with the linkage only once lowered. The function calls the initializers
+    /// given and the internal functions of the callees, declared in the function.
+    #[allow(clippy::too_many_arguments)]
+    fn define(
+        &mut self,
+        symbol: &Symbol,
+        linkage: Linkage,
+        signature: Signature,
+        contract_no: Option<usize>,
+        initializers: Option<FuncId>,
+        callees: &[usize],
+        lower: impl FnOnce(FunctionLowering) -> Result<(), EmitterError>,
+    ) -> Result<FuncId, EmitterError> {
+        // Public functions are only called by the dispatcher
+        let mut functions = Vec::new();
+        for &function_no in callees {
+            let func = &self.sema.functions[function_no];
+            if function::is_external(func) {
+                continue;
+            }
+            let signature = function::signature(
+                func,
+                self.ctx.module.isa().default_call_conv(),
+                self.ctx.module.isa().pointer_type(),
+            );
+            let symbol = Symbol::function(func, self.sema);
+            functions.push((
+                function_no,
+                self.ctx.declare_function(&symbol, Linkage::Export, &signature)?,
+            ));
+        }
+
+        let module = &mut *self.ctx.module;
+        let config = module.isa().frontend_config();
+        let big_endian = module.isa().endianness() == Endianness::Big;
+
+        let mut ctx = module.make_context();
+        ctx.func.signature = signature.clone();
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+
+        // The builtins of inline assembly may call any host function
+        let host = runtime::HOST_FUNCTIONS
+            .iter()
+            .map(|function| {
+                let id = self.ctx.functions[function.name];
+                (function.name, module.declare_func_in_func(id, builder.func))
+            })
+            .collect();
+        let codes = self
+            .ctx
+            .codes
+            .iter()
+            .map(|(name, &(id, size))| {
+                (name.clone(), (module.declare_data_in_func(id, builder.func), size))
+            })
+            .collect();
+        let initializers = initializers.map(|id| module.declare_func_in_func(id, builder.func));
+        let functions = functions
+            .into_iter()
+            .map(|(function_no, id)| (function_no, module.declare_func_in_func(id, builder.func)))
+            .collect();
+        let constants = &mut *self.ctx.constants;
+        let mut lowering =
+            FunctionLowering::new(&mut builder, config, &host, self.sema, big_endian)
+                .with_codes(codes)
+                .with_constants(move |bytes, func| {
+                    let id = constants.define(module, bytes)?;
+                    Ok(module.declare_data_in_func(id, func))
+                })
+                .with_unroll_budget(self.unroll_budget)
+                .with_functions(functions);
+        if let Some(contract_no) = contract_no {
+            lowering = lowering.with_contract(contract_no);
+        }
+        if let Some(initializers) = initializers {
+            lowering = lowering.with_initializers(initializers);
+        }
+        lower(lowering)?;
+        builder.finalize(config);
+
+        let id = self.ctx.declare_function(symbol, linkage, &signature)?;
+        ctx.set_disasm(self.ctx.disasm);
+        self.ctx.module.define_function(id, &mut ctx).map_err(Box::new)?;
+
+        if let Some(asm) = ctx.compiled_code().and_then(|code| code.vcode.clone()) {
+            self.ctx.listing.push((symbol.mangle(), asm));
+        }
+        self.ctx.ir.push_str(&format!("{}\n", ctx.func));
+
+        Ok(id)
     }
 }
 
 #[derive(Debug, Error)]
-pub enum EmitterError {}
+pub enum EmitterError {
+    #[error(transparent)]
+    Module(#[from] Box<ModuleError>),
+
+    /// What cannot be lowered, located at the innermost statement around it
+    /// when it has no location of its own
+    #[error("{1} is not supported by the code generator yet")]
+    Unsupported(Loc, String),
+
+    #[error("'{0}' is not declared")]
+    Undeclared(String),
 
-impl<'a> Visitor for CraneliftEmitter<'a> {
-    type Error = EmitterError;
+    #[error(transparent)]
+    Yul(#[from] YulError),
+}
+
+impl EmitterError {
+    /// Locate an error without a location of its own at the location
+    pub(crate) fn located(self, loc: Loc) -> Self {
+        match self {
+            EmitterError::Unsupported(Loc::Implicit, what) => EmitterError::Unsupported(loc, what),
+            err => err,
+        }
+    }
 }
@@ -12,9 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::ops::Range;
+
 use thiserror::Error;
 
-use crate::parser::ast::Loc;
+use crate::{
+    diagnostics::{Diagnostic, ErrorType, Level},
+    helpers::CodeLocation,
+    lexer::token::Token,
+    parser::ast::Loc,
+};
 
 /// An error thrown by [Lexer].
 #[derive(Debug, Clone, PartialEq, Eq, Error, Default)]
@@ -47,3 +54,136 @@ pub enum LexicalError {
     #[default]
     InvalidToken,
 }
+
+/// A syntax error, found by the lexer or the parser. The tokens are kept as
+/// text, so the error does not borrow the source.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Lexical(LexicalError),
+
+    #[error("invalid token")]
+    InvalidToken(Loc),
+
+    #[error("unrecognised token '{token}', expected {}", .expected.join(", "))]
+    UnrecognizedToken { loc: Loc, token: String, expected: Vec<String> },
+
+    #[error("extra token '{token}' encountered")]
+    ExtraToken { loc: Loc, token: String },
+
+    #[error("unexpected end of file, expecting {}", .expected.join(", "))]
+    UnrecognizedEof { loc: Loc, expected: Vec<String> },
+}
+
+impl ParseError {
+    /// Convert an error of the generated parser of the file `no`
+    pub(crate) fn from_lalrpop(
+        error: &lalrpop_util::ParseError<usize, Token<'_>, LexicalError>,
+        no: usize,
+    ) -> Self {
+        use lalrpop_util::ParseError as Error;
+
+        match error {
+            Error::InvalidToken { location } => {
+                ParseError::InvalidToken(Loc::File(no, *location, *location))
+            }
+            Error::UnrecognizedToken { token: (start, token, end), expected } => {
+                ParseError::UnrecognizedToken {
+                    loc: Loc::File(no, *start, *end),
+                    token: token.to_string(),
+                    expected: expected.clone(),
+                }
+            }
+            Error::ExtraToken { token: (start, token, end) } => ParseError::ExtraToken {
+                loc: Loc::File(no, *start, *end),
+                token: token.to_string(),
+            },
+            Error::UnrecognizedEof { location, expected } => ParseError::UnrecognizedEof {
+                loc: Loc::File(no, *location, *location),
+                expected: expected.clone(),
+            },
+            Error::User { error } => ParseError::Lexical(error.clone()),
+        }
+    }
+
+    /// The byte range of the error in the source, empty at the start when the
+    /// error has no location
+    pub fn span(&self) -> Range<usize> {
+        self.loc().range().unwrap_or_default()
+    }
+
+    /// Render the error with the snippet of the source it is in
+    pub fn render(&self, source: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Diagnostic::from(self).render(source)
+    }
+}
+
+impl CodeLocation for ParseError {
+    fn loc(&self) -> Loc {
+        match self {
+            ParseError::Lexical(error) => error.loc(),
+            ParseError::InvalidToken(loc) |
+            ParseError::UnrecognizedToken { loc, .. } |
+            ParseError::ExtraToken { loc, .. } |
+            ParseError::UnrecognizedEof { loc, .. } => *loc,
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::builder(error.loc(), Level::Error)
+            .ty(ErrorType::ParserError)
+            .message(error.to_string())
+            .build()
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic::from(&error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::SourceUnitParser};
+
+    fn parse_error(source: &str) -> ParseError {
+        let mut errors = Vec::new();
+        let error = SourceUnitParser::new().parse(source, 0, &mut errors, Lexer::new(source));
+        ParseError::from_lalrpop(&error.unwrap_err(), 0)
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_error("contract C { uint a }");
+        let ParseError::UnrecognizedToken { token, .. } = &error else { panic!("{error:?}") };
+        assert_eq!(token, "CloseCurlyBrace");
+        assert!(error.to_string().starts_with("unrecognised token 'CloseCurlyBrace', expected "));
+        assert_eq!(error.span(), 20..21);
+
+        let error = parse_error("contract C {");
+        assert!(matches!(error, ParseError::UnrecognizedEof { .. }));
+        assert_eq!(error.loc(), Loc::File(0, 12, 12));
+
+        let error = ParseError::Lexical(LexicalError::EndOfFileInString(Loc::File(0, 11, 15)));
+        assert_eq!(error.to_string(), "end of file found in string literal");
+
+        let diagnostic = Diagnostic::from(&error);
+        assert_eq!(diagnostic.loc, error.loc());
+        assert_eq!(diagnostic.ty, ErrorType::ParserError);
+        assert_eq!(diagnostic.message, error.to_string());
+        assert!(error
+            .render("string s = \"abc")
+            .unwrap()
+            .contains("end of file found in string literal"));
+
+        // An invalid token of the lexer has no location
+        let error = ParseError::Lexical(LexicalError::InvalidToken);
+        assert_eq!(error.loc(), Loc::Implicit);
+        assert_eq!(error.span(), 0..0);
+        assert!(error.render("uint x;").unwrap().contains("invalid token"));
+    }
+}
@@ -0,0 +1,790 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reprinting of a parse tree with a configurable layout.
+//!
+//! Declarations and statements are laid out over lines by the formatter, while
+//! the expressions within a line are written by their `Display` implementation.
+//! Comments are written around the node they are attached to, see
+//! [`trivia`](crate::parser::trivia).
+
+use std::{fmt::Display, mem};
+
+use strum::{Display, EnumString};
+
+use crate::{
+    config::FmtOptions,
+    diagnostics::Diagnostic,
+    helpers::{CodeLocation, CodeLocationExt},
+    parser::{
+        ast as pt, parse_with_comments,
+        trivia::{Comments, Trivia},
+    },
+};
+
+/// Where the opening brace of a block goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum BraceStyle {
+    /// At the end of the line of the declaration or statement
+    #[default]
+    SameLine,
+    /// On a line of its own
+    NextLine,
+}
+
+/// The layout of the formatted code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    /// Number of spaces per indentation level
+    pub indent_width: usize,
+    /// Lines longer than this have their lists broken up, where possible
+    pub line_length: usize,
+    pub brace_style: BraceStyle,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self { indent_width: 4, line_length: 120, brace_style: BraceStyle::SameLine }
+    }
+}
+
+impl From<&FmtOptions> for Style {
+    fn from(options: &FmtOptions) -> Self {
+        let default = Style::default();
+
+        Self {
+            indent_width: options.indent_width.unwrap_or(default.indent_width),
+            line_length: options.line_length.unwrap_or(default.line_length),
+            brace_style: options.brace_style.unwrap_or(default.brace_style),
+        }
+    }
+}
+
+/// Parse and reprint the source code, comments included
+pub fn format(source: &str, style: &Style) -> Result<String, Vec<Diagnostic>> {
+    let (tree, comments) = parse_with_comments(source, 0)?;
+
+    Ok(format_tree(&tree, &comments, style))
+}
+
+/// Print a parse tree with the comments attached to it
+pub fn format_tree(tree: &pt::SourceUnit, comments: &Comments, style: &Style) -> String {
+    let mut formatter = Formatter { style, comments, out: String::new(), indent: 0 };
+
+    formatter.source_unit(tree);
+
+    formatter.out
+}
+
+struct Formatter<'a> {
+    style: &'a Style,
+    comments: &'a Comments,
+    out: String,
+    indent: usize,
+}
+
+impl<'a> Formatter<'a> {
+    /// Write a line at the current indentation
+    fn line(&mut self, text: impl Display) {
+        let text = text.to_string();
+
+        if !text.is_empty() {
+            self.out.extend(std::iter::repeat_n(' ', self.indent * self.style.indent_width));
+            self.out.push_str(&text);
+        }
+        self.out.push('\n');
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Does the text fit on a line at the current indentation
+    fn fits(&self, text: &str) -> bool {
+        self.indent * self.style.indent_width + text.len() <= self.style.line_length
+    }
+
+    fn trivia(&self, loc: pt::Loc) -> Option<&'a Trivia> {
+        self.comments.get(&loc)
+    }
+
+    /// Write the comments on the lines before a node
+    fn leading(&mut self, loc: pt::Loc) {
+        for comment in self.trivia(loc).map_or(&[][..], |t| &t.leading) {
+            self.comment(comment);
+        }
+    }
+
+    /// Append the comments after a node to the last line written
+    fn trailing(&mut self, loc: pt::Loc) {
+        for comment in self.trivia(loc).map_or(&[][..], |t| &t.trailing) {
+            self.out.pop();
+            self.out.push(' ');
+            self.out.push_str(&self.reindent(comment));
+            self.out.push('\n');
+        }
+    }
+
+    /// Write the comments at the end of a node, before its closing brace
+    fn inner(&mut self, loc: pt::Loc) {
+        for comment in self.trivia(loc).map_or(&[][..], |t| &t.inner) {
+            self.comment(comment);
+        }
+    }
+
+    fn has_inner(&self, loc: pt::Loc) -> bool {
+        self.trivia(loc).is_some_and(|t| !t.inner.is_empty())
+    }
+
+    fn comment(&mut self, comment: &pt::Comment) {
+        let text = self.reindent(comment);
+        self.line(text);
+    }
+
+    /// Indent the continuation lines of a block comment, aligning the `*` of
+    /// doc comments with the one opening them
+    fn reindent(&self, comment: &pt::Comment) -> String {
+        let indent = " ".repeat(self.indent * self.style.indent_width);
+        let mut lines = comment.value().lines();
+        let mut text = lines.next().unwrap_or_default().to_string();
+
+        for line in lines {
+            let line = line.trim();
+            text.push('\n');
+            if !line.is_empty() {
+                text.push_str(&indent);
+                if line.starts_with('*') {
+                    text.push(' ');
+                }
+                text.push_str(line);
+            }
+        }
+
+        text
+    }
+
+    /// Write the header of a block and its opening brace
+    fn open(&mut self, header: &str) {
+        match (self.style.brace_style, header.is_empty()) {
+            (_, true) => self.line("{"),
+            (BraceStyle::SameLine, false) => self.line(format!("{header} {{")),
+            (BraceStyle::NextLine, false) => {
+                self.line(header);
+                self.line("{");
+            }
+        }
+        self.indent += 1;
+    }
+
+    /// Write the closing brace of a block, followed by the text
+    fn close(&mut self, text: &str) {
+        self.indent -= 1;
+        self.line(format!("}}{text}"));
+    }
+
+    /// Close a block which is continued by another clause, like `else`. The
+    /// header of the clause is returned, to be passed to [`Formatter::open`].
+    fn close_into(&mut self, header: &str) -> String {
+        self.indent -= 1;
+
+        match self.style.brace_style {
+            BraceStyle::SameLine => format!("}} {header}"),
+            BraceStyle::NextLine => {
+                self.line("}");
+                header.to_string()
+            }
+        }
+    }
+
+    /// Write a list on the line if it fits, otherwise one item per line
+    fn list(&mut self, prefix: &str, items: &[String], suffix: &str) {
+        let inline = format!("{prefix}{}{suffix}", items.join(", "));
+
+        if items.is_empty() || self.fits(&inline) {
+            self.line(inline);
+        } else {
+            self.list_lines(prefix, items);
+            self.line(suffix);
+        }
+    }
+
+    /// Write the prefix of a list and its items on lines of their own
+    fn list_lines(&mut self, prefix: &str, items: &[String]) {
+        self.line(prefix);
+        self.indent += 1;
+        for (i, item) in items.iter().enumerate() {
+            let comma = if i + 1 < items.len() { "," } else { "" };
+            self.line(format!("{item}{comma}"));
+        }
+        self.indent -= 1;
+    }
+
+    /// Write the items of a source unit or contract, with a blank line around
+    /// items spanning several lines and between groups of different items.
+    /// Annotations stick to the item following them.
+    fn items<T: CodeLocationExt>(
+        &mut self,
+        items: &[T],
+        write: impl Fn(&mut Self, &T),
+        layout: impl Fn(&T) -> Option<Layout<T>>,
+    ) {
+        let mut prev: Option<Layout<T>> = None;
+
+        for item in items {
+            let Some(layout) = layout(item) else {
+                continue;
+            };
+
+            if let Some(prev) = prev {
+                if !prev.annotation &&
+                    (layout.multiline || prev.multiline || prev.kind != layout.kind)
+                {
+                    self.blank_line();
+                }
+            }
+
+            let loc = CodeLocationExt::loc(item);
+            self.leading(loc);
+            write(self, item);
+            self.trailing(loc);
+            prev = Some(layout);
+        }
+    }
+
+    fn source_unit(&mut self, tree: &pt::SourceUnit) {
+        self.items(&tree.0, Self::source_unit_part, |part| match part {
+            pt::SourceUnitPart::StraySemicolon(_) => None,
+            part => Some(Layout {
+                kind: mem::discriminant(part),
+                multiline: is_multiline_part(part),
+                annotation: matches!(part, pt::SourceUnitPart::Annotation(_)),
+            }),
+        });
+
+        for comment in &self.comments.rest {
+            self.comment(comment);
+        }
+    }
+
+    fn source_unit_part(&mut self, part: &pt::SourceUnitPart) {
+        match part {
+            pt::SourceUnitPart::ContractDefinition(def) => self.contract(def),
+            pt::SourceUnitPart::EnumDefinition(def) => self.enum_def(def),
+            pt::SourceUnitPart::StructDefinition(def) => self.struct_def(def),
+            pt::SourceUnitPart::EventDefinition(def) => self.event(def),
+            pt::SourceUnitPart::ErrorDefinition(def) => self.error(def),
+            pt::SourceUnitPart::FunctionDefinition(def) => self.function(def),
+            part => self.line(part),
+        }
+    }
+
+    fn contract(&mut self, def: &pt::ContractDefinition) {
+        let mut header = def.ty.to_string();
+        if let Some(name) = &def.name {
+            header = format!("{header} {name}");
+        }
+        if !def.base.is_empty() {
+            let bases = def.base.iter().map(ToString::to_string).collect::<Vec<_>>();
+            header = format!("{header} is {}", bases.join(", "));
+        }
+
+        if def.parts.iter().all(|part| matches!(part, pt::ContractPart::StraySemicolon(_))) &&
+            !self.has_inner(def.loc)
+        {
+            self.line(format!("{header} {{}}"));
+            return;
+        }
+
+        self.open(&header);
+        self.items(&def.parts, Self::contract_part, |part| match part {
+            pt::ContractPart::StraySemicolon(_) => None,
+            part => Some(Layout {
+                kind: mem::discriminant(part),
+                multiline: is_multiline_contract_part(part),
+                annotation: matches!(part, pt::ContractPart::Annotation(_)),
+            }),
+        });
+        self.inner(def.loc);
+        self.close("");
+    }
+
+    fn contract_part(&mut self, part: &pt::ContractPart) {
+        match part {
+            pt::ContractPart::StructDefinition(def) => self.struct_def(def),
+            pt::ContractPart::EventDefinition(def) => self.event(def),
+            pt::ContractPart::EnumDefinition(def) => self.enum_def(def),
+            pt::ContractPart::ErrorDefinition(def) => self.error(def),
+            pt::ContractPart::FunctionDefinition(def) => self.function(def),
+            part => self.line(part),
+        }
+    }
+
+    fn struct_def(&mut self, def: &pt::StructDefinition) {
+        let header = format!("struct {}", name(&def.name));
+
+        if def.fields.is_empty() && !self.has_inner(def.loc) {
+            self.line(format!("{header} {{}}"));
+            return;
+        }
+
+        self.open(&header);
+        for field in &def.fields {
+            self.leading(field.loc);
+            self.line(format!("{field};"));
+            self.trailing(field.loc);
+        }
+        self.inner(def.loc);
+        self.close("");
+    }
+
+    fn enum_def(&mut self, def: &pt::EnumDefinition) {
+        let header = format!("enum {}", name(&def.name));
+        let values = def.values.iter().flatten().collect::<Vec<_>>();
+
+        if values.is_empty() && !self.has_inner(def.loc) {
+            self.line(format!("{header} {{}}"));
+            return;
+        }
+
+        self.open(&header);
+        for (i, value) in values.iter().enumerate() {
+            let comma = if i + 1 < values.len() { "," } else { "" };
+            self.leading(value.loc);
+            self.line(format!("{value}{comma}"));
+            self.trailing(value.loc);
+        }
+        self.inner(def.loc);
+        self.close("");
+    }
+
+    fn event(&mut self, def: &pt::EventDefinition) {
+        let fields = def.fields.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let anonymous = if def.anonymous { " anonymous" } else { "" };
+
+        self.list(&format!("event {}(", name(&def.name)), &fields, &format!("){anonymous};"));
+    }
+
+    fn error(&mut self, def: &pt::ErrorDefinition) {
+        let fields = def.fields.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        self.list(&format!("{} {}(", def.keyword, name(&def.name)), &fields, ");");
+    }
+
+    fn function(&mut self, def: &pt::FunctionDefinition) {
+        let loc = CodeLocationExt::loc(def);
+        let mut prefix = def.ty.to_string();
+        if let Some(name) = &def.name {
+            prefix = format!("{prefix} {name}");
+        }
+
+        let mut suffix = String::from(")");
+        for attr in &def.attributes {
+            suffix = format!("{suffix} {attr}");
+        }
+        if !def.returns.is_empty() {
+            suffix = format!("{suffix} returns ({})", parameters(&def.returns).join(", "));
+        }
+
+        let statements = match &def.body {
+            None => {
+                suffix.push(';');
+                None
+            }
+            Some(pt::Statement::Block { statements, .. })
+                if statements.is_empty() && !self.has_inner(loc) =>
+            {
+                suffix.push_str(" {}");
+                None
+            }
+            Some(pt::Statement::Block { statements, .. }) => Some(statements),
+            Some(body) => {
+                suffix = format!("{suffix} {body}");
+                None
+            }
+        };
+
+        let params = parameters(&def.params);
+
+        let Some(statements) = statements else {
+            self.list(&format!("{prefix}("), &params, &suffix);
+            return;
+        };
+
+        // The brace goes after the signature, so check with it included
+        let inline = format!("{prefix}({}{suffix} {{", params.join(", "));
+        if params.is_empty() || self.fits(&inline) {
+            self.open(&format!("{prefix}({}{suffix}", params.join(", ")));
+        } else {
+            self.list_lines(&format!("{prefix}("), &params);
+            self.open(&suffix);
+        }
+
+        self.statements(statements);
+        self.inner(loc);
+        self.close("");
+    }
+
+    fn statements(&mut self, statements: &[pt::Statement]) {
+        for stmt in statements {
+            let loc = CodeLocation::loc(stmt);
+            self.leading(loc);
+            self.statement(stmt);
+            self.trailing(loc);
+        }
+    }
+
+    /// Write a block with a header, or a single line if the block is empty.
+    /// The comments at its end are those of the statement at the location.
+    fn block(&mut self, loc: pt::Loc, header: &str, statements: &[pt::Statement]) {
+        if statements.is_empty() && !self.has_inner(loc) {
+            match header.is_empty() {
+                true => self.line("{}"),
+                false => self.line(format!("{header} {{}}")),
+            }
+            return;
+        }
+
+        self.open(header);
+        self.statements(statements);
+        self.inner(loc);
+        self.close("");
+    }
+
+    /// Write a statement with a header, like a loop and its body
+    fn compound(&mut self, loc: pt::Loc, header: &str, body: &pt::Statement) {
+        match body {
+            pt::Statement::Block { unchecked: false, statements, .. } => {
+                self.block(loc, header, statements)
+            }
+            body => self.line(format!("{header} {body}")),
+        }
+    }
+
+    fn statement(&mut self, stmt: &pt::Statement) {
+        let loc = CodeLocation::loc(stmt);
+
+        match stmt {
+            pt::Statement::Block { unchecked, statements, .. } => {
+                self.block(loc, if *unchecked { "unchecked" } else { "" }, statements)
+            }
+            pt::Statement::If(_, cond, then, otherwise) => {
+                self.if_statement(loc, format!("if ({cond})"), then, otherwise.as_deref())
+            }
+            pt::Statement::While(_, cond, body) => {
+                self.compound(loc, &format!("while ({cond})"), body)
+            }
+            pt::Statement::For(_, init, cond, next, body) => {
+                // The initializer statement is written with its semicolon
+                let mut header = format!(
+                    "for ({}",
+                    init.as_ref().map_or_else(|| String::from(";"), ToString::to_string)
+                );
+                if let Some(cond) = cond {
+                    header = format!("{header} {cond}");
+                }
+                header.push(';');
+                if let Some(next) = next {
+                    header = format!("{header} {next}");
+                }
+                header.push(')');
+
+                match body {
+                    Some(body) => self.compound(loc, &header, body),
+                    None => self.line(format!("{header};")),
+                }
+            }
+            pt::Statement::DoWhile(_, body, cond) => match body.as_ref() {
+                pt::Statement::Block { unchecked: false, statements, .. } => {
+                    self.open("do");
+                    self.statements(statements);
+                    self.inner(loc);
+                    let header = self.close_into(&format!("while ({cond});"));
+                    self.line(header);
+                }
+                _ => self.line(stmt),
+            },
+            pt::Statement::Try(_, expr, Some((returns, body)), catches) => {
+                let returns = parameters(returns).join(", ");
+                let mut header = format!("try {expr} returns ({returns})");
+                let mut body = body.as_ref();
+
+                for catch in catches {
+                    self.open(&header);
+                    self.statements(block_statements(body));
+
+                    let (clause, block) = match catch {
+                        pt::CatchClause::Simple(_, None, block) => ("catch".to_string(), block),
+                        pt::CatchClause::Simple(_, Some(param), block) => {
+                            (format!("catch ({param})"), block)
+                        }
+                        pt::CatchClause::Named(_, id, param, block) => {
+                            (format!("catch {id}({param})"), block)
+                        }
+                    };
+                    header = self.close_into(&clause);
+                    body = block;
+                }
+
+                self.open(&header);
+                self.statements(block_statements(body));
+                self.inner(loc);
+                self.close("");
+            }
+            stmt => self.line(stmt),
+        }
+    }
+
+    fn if_statement(
+        &mut self,
+        loc: pt::Loc,
+        header: String,
+        then: &pt::Statement,
+        otherwise: Option<&pt::Statement>,
+    ) {
+        let pt::Statement::Block { unchecked: false, statements, .. } = then else {
+            self.line(format!("{header} {then}"));
+            if let Some(otherwise) = otherwise {
+                self.line(format!("else {otherwise}"));
+            }
+            return;
+        };
+
+        let Some(otherwise) = otherwise else {
+            return self.block(loc, &header, statements);
+        };
+
+        self.open(&header);
+        self.statements(statements);
+
+        let else_loc = CodeLocation::loc(otherwise);
+        match otherwise {
+            pt::Statement::If(_, cond, then, otherwise) => {
+                self.leading(else_loc);
+                let header = self.close_into(&format!("else if ({cond})"));
+                self.if_statement(else_loc, header, then, otherwise.as_deref());
+                self.trailing(else_loc);
+            }
+            pt::Statement::Block { unchecked: false, statements, .. } => {
+                let header = self.close_into("else");
+                self.open(&header);
+                self.statements(statements);
+                self.inner(loc);
+                self.close("");
+            }
+            otherwise => {
+                self.close("");
+                self.leading(else_loc);
+                self.line(format!("else {otherwise}"));
+                self.trailing(else_loc);
+            }
+        }
+    }
+}
+
+/// How an item of a source unit or contract is laid out
+struct Layout<T> {
+    kind: mem::Discriminant<T>,
+    multiline: bool,
+    annotation: bool,
+}
+
+impl<T> Clone for Layout<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Layout<T> {}
+
+fn is_multiline_part(part: &pt::SourceUnitPart) -> bool {
+    match part {
+        pt::SourceUnitPart::ContractDefinition(_) |
+        pt::SourceUnitPart::EnumDefinition(_) |
+        pt::SourceUnitPart::StructDefinition(_) => true,
+        pt::SourceUnitPart::FunctionDefinition(def) => def.body.is_some(),
+        _ => false,
+    }
+}
+
+fn is_multiline_contract_part(part: &pt::ContractPart) -> bool {
+    match part {
+        pt::ContractPart::EnumDefinition(_) | pt::ContractPart::StructDefinition(_) => true,
+        pt::ContractPart::FunctionDefinition(def) => def.body.is_some(),
+        _ => false,
+    }
+}
+
+fn block_statements(stmt: &pt::Statement) -> &[pt::Statement] {
+    match stmt {
+        pt::Statement::Block { statements, .. } => statements,
+        stmt => std::slice::from_ref(stmt),
+    }
+}
+
+fn parameters(params: &pt::ParameterList) -> Vec<String> {
+    params.iter().flat_map(|(_, param)| param).map(ToString::to_string).collect()
+}
+
+fn name(name: &Option<pt::Identifier>) -> String {
+    name.as_ref().map(ToString::to_string).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    const SOURCE: &str = r#"pragma solidity ^0.8.0;
+import "lib";
+contract C is A, B(1) { uint x; uint y;
+    event E(uint indexed a);
+    struct S { uint a; bool b; }
+    function f(uint a) public view returns (uint) { if (a > 1) { return 1; } else if (a == 0) { return 2; } else { return 3; } }
+    function g() external;
+    function h() public { for (uint i = 0; i < 10; i++) { x += i; } do { y--; } while (y > 0); }
+}
+"#;
+
+    #[test]
+    fn test_format_same_line() {
+        let formatted = format(SOURCE, &Style::default()).unwrap();
+
+        assert_eq!(
+            formatted,
+            r#"pragma solidity ^0.8.0;
+
+import "lib";
+
+contract C is A, B(1) {
+    uint x;
+    uint y;
+
+    event E(uint indexed a);
+
+    struct S {
+        uint a;
+        bool b;
+    }
+
+    function f(uint a) public view returns (uint) {
+        if (a > 1) {
+            return 1;
+        } else if (a == 0) {
+            return 2;
+        } else {
+            return 3;
+        }
+    }
+
+    function g() external;
+
+    function h() public {
+        for (uint i = 0; i < 10; i++) {
+            x += i;
+        }
+        do {
+            y--;
+        } while (y > 0);
+    }
+}
+"#
+        );
+
+        // Formatting is idempotent and keeps the parse tree
+        assert_eq!(format(&formatted, &Style::default()).unwrap(), formatted);
+        assert_eq!(
+            parse(&formatted, 0).unwrap().to_string(),
+            parse(SOURCE, 0).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_format_style() {
+        let style = Style { indent_width: 2, line_length: 30, brace_style: BraceStyle::NextLine };
+        let source = "contract C { function f(uint first, uint second) public { if (first > 1) { g(); } else { h(); } } }";
+
+        assert_eq!(
+            format(source, &style).unwrap(),
+            r#"contract C
+{
+  function f(
+    uint first,
+    uint second
+  ) public
+  {
+    if (first > 1)
+    {
+      g();
+    }
+    else
+    {
+      h();
+    }
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let source = r#"// SPDX-License-Identifier: MIT
+contract C {
+    /// The total
+    uint total; // in wei
+  /**
+     * Add to the total
+     */
+    function add(uint a) public {
+        total += a; /* no overflow check */
+        if (a > 1) { g(); } // done
+        // nothing else
+    }
+    function g() public { /* empty */ }
+}
+// end
+"#;
+
+        let formatted = format(source, &Style::default()).unwrap();
+        assert_eq!(
+            formatted,
+            r#"// SPDX-License-Identifier: MIT
+contract C {
+    /// The total
+    uint total; // in wei
+
+    /**
+     * Add to the total
+     */
+    function add(uint a) public {
+        total += a; /* no overflow check */
+        if (a > 1) {
+            g();
+        } // done
+        // nothing else
+    }
+
+    function g() public {
+        /* empty */
+    }
+}
+// end
+"#
+        );
+        assert_eq!(format(&formatted, &Style::default()).unwrap(), formatted);
+    }
+}
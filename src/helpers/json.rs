@@ -0,0 +1,446 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal JSON document model used for the machine readable outputs, and
+//! the inputs produced by other tools.
+//!
+//! `Display` writes compact JSON, the alternate form (`{:#}`) indents it. With
+//! the `serde` feature, the types deriving `Serialize` can be converted to it,
+//! see [`ser`].
+
+use std::fmt::{self, Display, Formatter, Write};
+
+#[cfg(feature = "serde")]
+pub mod ser;
+
+/// A JSON value. Object members keep their insertion order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// The number as written, so that large integers keep their precision
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Create an object from its members
+    pub fn object<K: Into<String>>(members: impl IntoIterator<Item = (K, Json)>) -> Self {
+        Json::Object(members.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Add a member to an object, this is a no-op for other values
+    pub fn insert(&mut self, key: impl Into<String>, value: Json) {
+        if let Json::Object(members) = self {
+            members.push((key.into(), value));
+        }
+    }
+
+    /// Look up a member of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    /// Parse a JSON document. The error gives the byte offset at which the
+    /// text stops being valid JSON.
+    pub fn parse(text: &str) -> Result<Json, (usize, String)> {
+        let mut reader = Reader { text, pos: 0 };
+        let value = reader.value()?;
+        reader.whitespace();
+
+        if reader.pos < text.len() {
+            return Err(reader.error("trailing characters after the document"));
+        }
+        Ok(value)
+    }
+
+    fn write(&self, f: &mut Formatter<'_>, indent: Option<usize>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) => f.write_str(n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                write_list(f, '[', ']', values.iter(), indent, |f, value, indent| {
+                    value.write(f, indent)
+                })
+            }
+            Json::Object(members) => {
+                write_list(f, '{', '}', members.iter(), indent, |f, (key, value), indent| {
+                    write_string(f, key)?;
+                    f.write_str(if indent.is_some() { ": " } else { ":" })?;
+                    value.write(f, indent)
+                })
+            }
+        }
+    }
+}
+
+fn write_list<T>(
+    f: &mut Formatter<'_>,
+    open: char,
+    close: char,
+    items: impl ExactSizeIterator<Item = T>,
+    indent: Option<usize>,
+    write_item: impl Fn(&mut Formatter<'_>, T, Option<usize>) -> fmt::Result,
+) -> fmt::Result {
+    f.write_char(open)?;
+
+    if items.len() == 0 {
+        return f.write_char(close);
+    }
+
+    let inner = indent.map(|n| n + 2);
+
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        if let Some(n) = inner {
+            write!(f, "\n{:n$}", "")?;
+        }
+        write_item(f, item, inner)?;
+    }
+
+    if let Some(n) = indent {
+        write!(f, "\n{:n$}", "")?;
+    }
+    f.write_char(close)
+}
+
+fn write_string(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// A recursive descent reader over the text of a document
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, msg: impl Into<String>) -> (usize, String) {
+        (self.pos, msg.into())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), (usize, String)> {
+        self.whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(format!("expected '{}'", c as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, (usize, String)> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, (usize, String)> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'{') => self.members(),
+            Some(b'[') => self.values(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.keyword("true", Json::Bool(true)),
+            Some(b'f') => self.keyword("false", Json::Bool(false)),
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => Ok(self.number()),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn members(&mut self) -> Result<Json, (usize, String)> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+
+        self.whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a member name"));
+            }
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn values(&mut self) -> Result<Json, (usize, String)> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+
+        self.whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Json {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        Json::Number(self.text[start..self.pos].to_string())
+    }
+
+    fn string(&mut self) -> Result<String, (usize, String)> {
+        self.pos += 1;
+        let mut s = String::new();
+
+        loop {
+            let Some(c) = self.text[self.pos..].chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += c.len_utf8();
+
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match escape {
+                        b'"' => s.push('"'),
+                        b'\\' => s.push('\\'),
+                        b'/' => s.push('/'),
+                        b'b' => s.push('\u{8}'),
+                        b'f' => s.push('\u{c}'),
+                        b'n' => s.push('\n'),
+                        b'r' => s.push('\r'),
+                        b't' => s.push('\t'),
+                        b'u' => s.push(self.unicode_escape()?),
+                        _ => return Err(self.error("invalid escape in string")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\uXXXX` escape, which may be the first half of a
+    /// surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, (usize, String)> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("invalid unicode escape"));
+        }
+
+        if !self.text[self.pos..].starts_with("\\u") {
+            return Err(self.error("unpaired surrogate in unicode escape"));
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate in unicode escape"));
+        }
+
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, (usize, String)> {
+        let digits = self.text.get(self.pos..self.pos + 4);
+        let value = digits
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(value)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write(f, f.alternate().then_some(0))
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Self {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+macro_rules! impl_from_number {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Json {
+                fn from(n: $ty) -> Self {
+                    Json::Number(n.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_number!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_json() {
+        let json = Json::object([
+            ("name", "a \"b\"\n".into()),
+            ("inputs", Json::Array(vec![])),
+            ("values", vec![1u8, 2].into()),
+            ("payable", false.into()),
+            ("none", Json::Null),
+        ]);
+
+        assert_eq!(
+            json.to_string(),
+            r#"{"name":"a \"b\"\n","inputs":[],"values":[1,2],"payable":false,"none":null}"#
+        );
+        assert_eq!(
+            format!("{json:#}"),
+            "{\n  \"name\": \"a \\\"b\\\"\\n\",\n  \"inputs\": [],\n  \"values\": [\n    1,\n    2\n  ],\n  \"payable\": false,\n  \"none\": null\n}"
+        );
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = Json::object([
+            ("name", "a \"b\"\n\u{1}é".into()),
+            ("inputs", Json::Array(vec![])),
+            ("values", Json::Array(vec![Json::Number("-1.5e3".into()), 2u8.into()])),
+            ("payable", false.into()),
+            ("none", Json::Null),
+        ]);
+
+        assert_eq!(Json::parse(&json.to_string()), Ok(json.clone()));
+        assert_eq!(Json::parse(&format!("{json:#}")), Ok(json));
+        assert_eq!(Json::parse(r#" "\ud83e\udd80 \u00e9\/" "#), Ok("🦀 é/".into()));
+
+        assert_eq!(Json::parse("[1, 2"), Err((5, "expected ',' or ']'".into())));
+        assert_eq!(Json::parse("{\"a\" 1}"), Err((5, "expected ':'".into())));
+        assert_eq!(Json::parse("[1] x"), Err((4, "trailing characters after the document".into())));
+        assert_eq!(
+            Json::parse("\"\\ud83e\""),
+            Err((7, "unpaired surrogate in unicode escape".into()))
+        );
+        assert_eq!(Json::parse("nul"), Err((0, "expected a value".into())));
+    }
+}
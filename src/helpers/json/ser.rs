@@ -0,0 +1,395 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serialization of the types deriving `Serialize`, like the parse tree, to
+//! [`Json`] values, in the representation of serde_json: a struct is an
+//! object, a unit variant its name, and any other variant an object with its
+//! name as the only member, as in `{"File": [0, 7, 12]}`.
+
+use std::fmt::{self, Display};
+
+use serde::ser::{self, Serialize};
+
+use super::Json;
+
+/// Serialize the value to JSON
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<Json, Error> {
+    value.serialize(Serializer)
+}
+
+/// The error of a value which cannot be serialized to JSON, like a map whose
+/// keys are not strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+struct Serializer;
+
+/// A variant of an enum, with its name
+fn variant(name: &str, value: Json) -> Json {
+    Json::object([(name, value)])
+}
+
+fn float(n: f64) -> Json {
+    match n.is_finite() {
+        true => Json::Number(n.to_string()),
+        false => Json::Null,
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Json;
+    type Error = Error;
+    type SerializeSeq = Seq;
+    type SerializeTuple = Seq;
+    type SerializeTupleStruct = Seq;
+    type SerializeTupleVariant = Seq;
+    type SerializeMap = Map;
+    type SerializeStruct = Map;
+    type SerializeStructVariant = Map;
+
+    fn serialize_bool(self, v: bool) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Json, Error> {
+        Ok(Json::Number(v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Json, Error> {
+        Ok(Json::Number(v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Json, Error> {
+        Ok(float(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Json, Error> {
+        Ok(float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Json, Error> {
+        Ok(Json::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Json, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Json, Error> {
+        Ok(Json::Array(v.iter().map(|&b| b.into()).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Json, Error> {
+        Ok(Json::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Json, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Json, Error> {
+        Ok(Json::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Json, Error> {
+        Ok(Json::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Json, Error> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Json, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Json, Error> {
+        Ok(variant(name, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Seq, Error> {
+        Ok(Seq { variant: None, values: Vec::with_capacity(len.unwrap_or_default()) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Seq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Seq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Seq, Error> {
+        Ok(Seq { variant: Some(name), values: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Map, Error> {
+        Ok(Map { variant: None, members: Vec::new(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Map, Error> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Map, Error> {
+        Ok(Map { variant: Some(name), members: Vec::new(), key: None })
+    }
+}
+
+/// The elements of an array, of a tuple variant when it has a name
+struct Seq {
+    variant: Option<&'static str>,
+    values: Vec<Json>,
+}
+
+impl Seq {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.values.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Json, Error> {
+        let array = Json::Array(self.values);
+        Ok(match self.variant {
+            Some(name) => variant(name, array),
+            None => array,
+        })
+    }
+}
+
+impl ser::SerializeSeq for Seq {
+    type Ok = Json;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Json, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Seq {
+    type Ok = Json;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Json, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Seq {
+    type Ok = Json;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Json, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Seq {
+    type Ok = Json;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Json, Error> {
+        self.finish()
+    }
+}
+
+/// The members of an object, of a struct variant when it has a name
+struct Map {
+    variant: Option<&'static str>,
+    members: Vec<(String, Json)>,
+    /// The key of the member whose value is serialized next
+    key: Option<String>,
+}
+
+impl Map {
+    fn finish(self) -> Result<Json, Error> {
+        let object = Json::Object(self.members);
+        Ok(match self.variant {
+            Some(name) => variant(name, object),
+            None => object,
+        })
+    }
+}
+
+impl ser::SerializeMap for Map {
+    type Ok = Json;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(Serializer)? {
+            Json::String(key) | Json::Number(key) => self.key = Some(key),
+            key => return Err(Error(format!("the key {key} of a map is not a string"))),
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| Error("a map value has no key".into()))?;
+        self.members.push((key, value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Json, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Map {
+    type Ok = Json;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.members.push((key.to_string(), value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Json, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Map {
+    type Ok = Json;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Json, Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_parse_tree_to_json() {
+        let tree = parse("contract Token { uint public supply; }", 0).unwrap();
+        let json = to_json(&tree).unwrap();
+
+        let contract = &json.as_array().unwrap()[0];
+        let def = contract.get("ContractDefinition").unwrap();
+        assert_eq!(def.get("name").and_then(|id| id.get("name")), Some(&"Token".into()));
+        assert_eq!(def.get("ty").unwrap().to_string(), r#"{"Contract":{"File":[0,0,8]}}"#);
+
+        let var = def.get("parts").unwrap().as_array().unwrap()[0].get("VariableDefinition");
+        let var = var.unwrap();
+        assert_eq!(var.get("initializer"), Some(&Json::Null));
+        assert_eq!(
+            var.get("attrs").unwrap().to_string(),
+            r#"[{"Visibility":{"Public":{"File":[0,22,28]}}}]"#
+        );
+    }
+}
@@ -302,9 +302,9 @@ impl_for_enums! {
     }
 
     Expression: match self {
-        // literals have at least one item
-        Self::StringLiteral(ref l, ..) => l.loc_opt().unwrap(),
-        Self::HexLiteral(ref l, ..) => l.loc_opt().unwrap(),
+        // parsed literals have at least one item
+        Self::StringLiteral(ref l, ..) => l.loc_opt().unwrap_or(Loc::Implicit),
+        Self::HexLiteral(ref l, ..) => l.loc_opt().unwrap_or(Loc::Implicit),
         Self::Variable(ref l, ..) => l.loc(),
         Self::PostIncrement(l, ..)
         | Self::PostDecrement(l, ..)
@@ -432,7 +432,8 @@ impl_for_enums! {
     UsingList: match self {
         Self::Library(ref l, ..) => l.loc(),
         Self::Functions(ref l, ..) => l.loc_opt().unwrap_or_default(),
-        Self::Error => panic!("an error occurred"),
+        // The parser reported the error, the list has no location of its own
+        Self::Error => Loc::Implicit,
     }
 
     VariableAttribute: match self {
@@ -490,7 +491,7 @@ impl_for_enums! {
         | Self::UnrecognisedToken(l, _)
         | Self::ExpectedFrom(l, _)
         | Self::MissingExponent(l) => l,
-        | Self::InvalidToken => panic!("an error occurred"),
+        | Self::InvalidToken => Loc::Implicit,
     }
 }
 
@@ -9,4 +9,6 @@ pub use loc::*;
 
 mod ord;
 
+pub mod json;
 pub mod num;
+pub mod trace;
@@ -24,6 +24,21 @@ fn split_exponent(s: &str) -> (&str, String) {
     if let Some(e_pos) = s.find(['e', 'E']) {
         (&s[..e_pos], s[e_pos + 1..].to_string())
     } else {
-        (s, "0".to_string())
+        (s, String::new())
     }
 }
+
+/// Split a version into its parts, keeping the pre-release and build metadata
+/// on the last one: `0.8.0-rc.1` is `["0", "8", "0-rc.1"]`
+pub fn split_version(s: &str) -> Vec<String> {
+    let (core, suffix) = match s.find(['-', '+']) {
+        Some(pos) => s.split_at(pos),
+        None => (s, ""),
+    };
+
+    let mut parts = core.split('.').map(str::to_string).collect::<Vec<_>>();
+    if let Some(last) = parts.last_mut() {
+        last.push_str(suffix);
+    }
+    parts
+}
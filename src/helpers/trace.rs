@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Timed spans around the stages of the compiler pipeline, logged at the debug
+//! level with the [`log`] crate: a line when a stage starts and one with its
+//! duration when it ends. The target is the module of the stage, so a logger
+//! can filter them like any other record.
+
+use std::time::Instant;
+
+use log::Level;
+
+/// Enter a span, which ends when the returned guard is dropped. Fields are
+/// given as `key = value` and only formatted when the span is logged.
+macro_rules! span {
+    ($name:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::helpers::trace::Span::enter(module_path!(), $name, || {
+            let mut fields = String::new();
+            $(fields.push_str(&format!(" {}={}", stringify!($key), $value));)*
+            fields
+        })
+    };
+}
+
+pub(crate) use span;
+
+/// A running stage, see [`span!`]
+#[must_use = "the span ends when dropped"]
+pub struct Span {
+    /// The target, name and fields when logged
+    entered: Option<(&'static str, &'static str, String)>,
+    start: Instant,
+}
+
+impl Span {
+    pub fn enter(
+        target: &'static str,
+        name: &'static str,
+        fields: impl FnOnce() -> String,
+    ) -> Self {
+        let entered = log::log_enabled!(target: target, Level::Debug).then(|| {
+            let fields = fields();
+            log::debug!(target: target, "{name}{fields}");
+            (target, name, fields)
+        });
+
+        Self { entered, start: Instant::now() }
+    }
+
+    /// Run the function in the span, ending it afterwards
+    pub fn in_scope<T>(self, f: impl FnOnce() -> T) -> T {
+        f()
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some((target, name, fields)) = &self.entered {
+            log::debug!(target: target, "{name}{fields} done in {:.2?}", self.start.elapsed());
+        }
+    }
+}
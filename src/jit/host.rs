@@ -0,0 +1,500 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The host functions of the [`runtime`](crate::runtime), for the code the
+//! [`Machine`](super::Machine) runs.
+//!
+//! A call runs alone in a child process, whose state the host functions
+//! read and change. The `return` and `revert` host functions, and the end of
+//! the function called, report how the call ended to the parent process and
+//! exit. A call to another contract deployed in the machine runs in a child
+//! of its own in turn, the storage it leaves when it returns kept for the
+//! calls after it; a contract called cannot call back the contract calling
+//! it. Calls to other addresses and contract creations fail, without return
+//! data.
+
+use std::{cell::Cell, collections::HashMap, mem, slice};
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{One, Zero};
+use sha3::{Digest, Keccak256};
+
+use crate::runtime::{WordOp, MEMORY_SIZE};
+
+/// A word of 32 bytes, in big endian order
+pub type Word = [u8; 32];
+
+/// How a call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(super) enum Ending {
+    Returned,
+    Reverted,
+}
+
+/// A contract the call can call
+#[derive(Debug, Clone)]
+pub(super) struct Callee {
+    /// The address of the dispatcher of the contract
+    pub dispatcher: usize,
+    pub storage: HashMap<Word, Word>,
+}
+
+/// The state of the call running in the process
+pub(super) struct Call {
+    /// The address of the contract running
+    address: Word,
+    storage: HashMap<Word, Word>,
+    /// The other contracts, by address
+    callees: HashMap<Word, Callee>,
+    transient: HashMap<Word, Word>,
+    input: Vec<u8>,
+    caller: Word,
+    value: Word,
+    memory: Vec<u8>,
+    /// The data returned by the last call to another contract
+    return_data: Vec<u8>,
+    /// Where the ending of the call is written
+    report: libc::c_int,
+}
+
+/// How a call ended, as reported by its process
+#[derive(Debug)]
+pub(super) struct Report {
+    pub ending: Ending,
+    pub data: Vec<u8>,
+    /// The storage the call leaves, when it returned
+    pub storage: HashMap<Word, Word>,
+    /// The storage the other contracts leave, by address, when it returned
+    pub callees: HashMap<Word, HashMap<Word, Word>>,
+}
+
+thread_local! {
+    static CALL: Cell<*mut Call> = const { Cell::new(std::ptr::null_mut()) };
+}
+
+impl Call {
+    pub fn new(
+        address: Word,
+        storage: HashMap<Word, Word>,
+        callees: HashMap<Word, Callee>,
+        input: Vec<u8>,
+        caller: Word,
+        value: Word,
+    ) -> Self {
+        Self {
+            address,
+            storage,
+            callees,
+            transient: HashMap::new(),
+            input,
+            caller,
+            value,
+            memory: vec![0; MEMORY_SIZE as usize],
+            return_data: Vec::new(),
+            report: -1,
+        }
+    }
+
+    /// Make the call the one the host functions use, for the rest of the
+    /// process, reporting its ending to the descriptor
+    pub fn enter(mut self, report: libc::c_int) {
+        self.report = report;
+        CALL.with(|call| call.set(Box::into_raw(Box::new(self))));
+    }
+
+    /// End the call, reporting its ending with the data and, when it
+    /// returned, its storage, then exit the process
+    pub fn end(ending: Ending, data: &[u8]) -> ! {
+        let call = current();
+        let mut report = vec![ending as u8];
+        report.extend((data.len() as u32).to_le_bytes());
+        report.extend(data);
+        if ending == Ending::Returned {
+            write_storage(&mut report, &call.storage);
+            report.extend((call.callees.len() as u32).to_le_bytes());
+            for (address, callee) in &call.callees {
+                report.extend(address);
+                write_storage(&mut report, &callee.storage);
+            }
+        }
+
+        let mut written = 0;
+        while written < report.len() {
+            let rest = &report[written..];
+            // SAFETY: the pipe is open until the process exits
+            let n = unsafe { libc::write(call.report, rest.as_ptr().cast(), rest.len()) };
+            if n <= 0 {
+                break;
+            }
+            written += n as usize;
+        }
+
+        // SAFETY: exiting the child process without running the destructors
+        // and exit handlers of the parent it copies
+        unsafe { libc::_exit(0) }
+    }
+}
+
+impl Report {
+    /// Read the report of [`Call::end`]
+    pub fn parse(report: &[u8]) -> Option<Self> {
+        let (&ending, rest) = report.split_first()?;
+        let (len, rest) = rest.split_first_chunk::<4>()?;
+        let (data, rest) = rest.split_at_checked(u32::from_le_bytes(*len) as usize)?;
+        let ending = match ending {
+            0 => Ending::Returned,
+            1 => Ending::Reverted,
+            _ => return None,
+        };
+        let mut report =
+            Self { ending, data: data.to_vec(), storage: HashMap::new(), callees: HashMap::new() };
+        if ending == Ending::Reverted {
+            return Some(report);
+        }
+
+        let rest = read_storage(rest, &mut report.storage)?;
+        let (count, mut rest) = rest.split_first_chunk::<4>()?;
+        for _ in 0..u32::from_le_bytes(*count) {
+            let (address, entries) = rest.split_first_chunk::<32>()?;
+            rest = read_storage(entries, report.callees.entry(*address).or_default())?;
+        }
+        Some(report)
+    }
+}
+
+/// Write the entries of the storage, after their count
+fn write_storage(report: &mut Vec<u8>, storage: &HashMap<Word, Word>) {
+    report.extend((storage.len() as u32).to_le_bytes());
+    for (key, value) in storage {
+        report.extend(key);
+        report.extend(value);
+    }
+}
+
+/// Read the entries of [`write_storage`], giving the rest of the report
+fn read_storage<'a>(report: &'a [u8], storage: &mut HashMap<Word, Word>) -> Option<&'a [u8]> {
+    let (count, mut rest) = report.split_first_chunk::<4>()?;
+    for _ in 0..u32::from_le_bytes(*count) {
+        let (key, entries) = rest.split_first_chunk::<32>()?;
+        let (value, entries) = entries.split_first_chunk::<32>()?;
+        storage.insert(*key, *value);
+        rest = entries;
+    }
+    Some(rest)
+}
+
+fn current() -> &'static mut Call {
+    let call = CALL.with(Cell::get);
+    assert!(!call.is_null(), "internal error: a host function was called outside a call");
+    // SAFETY: the call is leaked by `enter` and only used by this thread
+    unsafe { &mut *call }
+}
+
+/// The address of a host function by its symbol, see
+/// [`HostFunction::symbol`](crate::runtime::HostFunction::symbol), or of a
+/// function of the C library Cranelift calls, as to fill memory
+pub(super) fn address(symbol: &str) -> Option<usize> {
+    let libcall = match symbol {
+        "memset" => Some(libc::memset as *const () as usize),
+        "memcpy" => Some(libc::memcpy as *const () as usize),
+        "memmove" => Some(libc::memmove as *const () as usize),
+        "memcmp" => Some(libc::memcmp as *const () as usize),
+        _ => None,
+    };
+    if libcall.is_some() {
+        return libcall;
+    }
+
+    let address = match symbol.strip_prefix(crate::runtime::SYMBOL_PREFIX)? {
+        "storage_load" => storage_load as *const () as usize,
+        "storage_store" => storage_store as *const () as usize,
+        "transient_load" => transient_load as *const () as usize,
+        "transient_store" => transient_store as *const () as usize,
+        "emit_log" => emit_log as *const () as usize,
+        "call" => call as *const () as usize,
+        "create" => create as *const () as usize,
+        "return_data_size" => return_data_size as *const () as usize,
+        "return_data_copy" => return_data_copy as *const () as usize,
+        "calldata_size" => calldata_size as *const () as usize,
+        "calldata_copy" => calldata_copy as *const () as usize,
+        "value_transferred" => value_transferred as *const () as usize,
+        "caller" => caller as *const () as usize,
+        "return" => return_ as *const () as usize,
+        "revert" => revert as *const () as usize,
+        "memory" => memory as *const () as usize,
+        "word_op" => word_op as *const () as usize,
+        "keccak256" => keccak256 as *const () as usize,
+        _ => return None,
+    };
+    Some(address)
+}
+
+/// The bytes at the address
+///
+/// # Safety
+///
+/// The generated code passes valid pointers with their lengths.
+unsafe fn bytes<'a>(data: *const u8, len: i32) -> &'a [u8] {
+    match len {
+        0 => &[],
+        len => unsafe { slice::from_raw_parts(data, len as usize) },
+    }
+}
+
+/// # Safety
+///
+/// As [`bytes`].
+unsafe fn word(data: *const u8) -> Word {
+    unsafe { bytes(data, 32) }.try_into().unwrap()
+}
+
+/// # Safety
+///
+/// As [`bytes`].
+unsafe fn write(dest: *mut u8, data: &[u8]) {
+    unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), dest, data.len()) }
+}
+
+/// The bytes of the data in the range, zero past its end
+fn range(data: &[u8], offset: i32, len: i32) -> Vec<u8> {
+    let mut range = vec![0; len.max(0) as usize];
+    let start = (offset.max(0) as usize).min(data.len());
+    let end = (start + range.len()).min(data.len());
+    range[..end - start].copy_from_slice(&data[start..end]);
+    range
+}
+
+unsafe extern "C" fn storage_load(key: *const u8, value: *mut u8) {
+    let word = current().storage.get(&unsafe { word(key) }).copied().unwrap_or_default();
+    unsafe { write(value, &word) }
+}
+
+unsafe extern "C" fn storage_store(key: *const u8, value: *const u8) {
+    current().storage.insert(unsafe { word(key) }, unsafe { word(value) });
+}
+
+unsafe extern "C" fn transient_load(key: *const u8, value: *mut u8) {
+    let word = current().transient.get(&unsafe { word(key) }).copied().unwrap_or_default();
+    unsafe { write(value, &word) }
+}
+
+unsafe extern "C" fn transient_store(key: *const u8, value: *const u8) {
+    current().transient.insert(unsafe { word(key) }, unsafe { word(value) });
+}
+
+extern "C" fn emit_log(_data: *const u8, _data_len: i32, _topics: *const u8, _topic_count: i32) {}
+
+unsafe extern "C" fn call(
+    address: *const u8,
+    value: *const u8,
+    input: *const u8,
+    input_len: i32,
+    _gas: i64,
+) -> i32 {
+    let call = current();
+    call.return_data.clear();
+    let address = unsafe { word(address) };
+    let mut callees = call.callees.clone();
+    let Some(callee) = callees.remove(&address) else {
+        return 1;
+    };
+
+    let input = unsafe { bytes(input, input_len) }.to_vec();
+    let data = input.clone();
+    let value = unsafe { word(value) };
+    let dispatcher = callee.dispatcher;
+    let callee = Call::new(address, callee.storage, callees, input, call.address, value);
+    // SAFETY: the dispatcher takes the input and its length
+    let ended = super::spawn(callee, move || unsafe {
+        let dispatch: extern "C" fn(*const u8, i32) = mem::transmute(dispatcher);
+        dispatch(data.as_ptr(), input_len);
+        Vec::new()
+    });
+
+    // A trap of the contract called reverts it without data
+    let Ok(super::Ended::Reported(report)) = ended else {
+        return 1;
+    };
+    call.return_data = report.data;
+    if report.ending == Ending::Reverted {
+        return 1;
+    }
+    for (address, storage) in report.callees {
+        if let Some(callee) = call.callees.get_mut(&address) {
+            callee.storage = storage;
+        }
+    }
+    if let Some(callee) = call.callees.get_mut(&address) {
+        callee.storage = report.storage;
+    }
+    0
+}
+
+unsafe extern "C" fn create(
+    _code: *const u8,
+    _code_len: i32,
+    _value: *const u8,
+    _input: *const u8,
+    _input_len: i32,
+    address: *mut u8,
+) -> i32 {
+    unsafe { write(address, &[0; 32]) };
+    1
+}
+
+extern "C" fn return_data_size() -> i32 {
+    current().return_data.len() as i32
+}
+
+unsafe extern "C" fn return_data_copy(dest: *mut u8, offset: i32, len: i32) {
+    unsafe { write(dest, &range(&current().return_data, offset, len)) }
+}
+
+extern "C" fn calldata_size() -> i32 {
+    current().input.len() as i32
+}
+
+unsafe extern "C" fn calldata_copy(dest: *mut u8, offset: i32, len: i32) {
+    unsafe { write(dest, &range(&current().input, offset, len)) }
+}
+
+unsafe extern "C" fn value_transferred(dest: *mut u8) {
+    unsafe { write(dest, &current().value) }
+}
+
+unsafe extern "C" fn caller(dest: *mut u8) {
+    unsafe { write(dest, &current().caller) }
+}
+
+unsafe extern "C" fn return_(data: *const u8, data_len: i32) {
+    Call::end(Ending::Returned, unsafe { bytes(data, data_len) })
+}
+
+unsafe extern "C" fn revert(data: *const u8, data_len: i32) {
+    Call::end(Ending::Reverted, unsafe { bytes(data, data_len) })
+}
+
+extern "C" fn memory() -> *mut u8 {
+    current().memory.as_mut_ptr()
+}
+
+unsafe extern "C" fn word_op(op: i32, a: *const u8, b: *const u8, c: *const u8, result: *mut u8) {
+    let Some(&op) = usize::try_from(op).ok().and_then(|op| WordOp::ALL.get(op)) else {
+        panic!("internal error: unknown word operation {op}");
+    };
+    let args = [a, b, c].map(|arg| match arg.is_null() {
+        true => BigUint::zero(),
+        false => BigUint::from_bytes_be(&unsafe { word(arg) }),
+    });
+
+    let value = compute(op, &args);
+    let bytes = value.to_bytes_be();
+    let mut word = [0; 32];
+    word[32 - bytes.len()..].copy_from_slice(&bytes);
+    unsafe { write(result, &word) }
+}
+
+unsafe extern "C" fn keccak256(data: *const u8, data_len: i32, hash: *mut u8) {
+    let digest = Keccak256::digest(unsafe { bytes(data, data_len) });
+    unsafe { write(hash, &digest) }
+}
+
+/// The result of the word operation, with the semantics of the EVM
+pub fn compute(op: WordOp, args: &[BigUint; 3]) -> BigUint {
+    let [a, b, c] = args;
+    let modulus = BigUint::one() << 256;
+
+    match op {
+        WordOp::Div if b.is_zero() => BigUint::zero(),
+        WordOp::Div => a / b,
+        WordOp::Mod if b.is_zero() => BigUint::zero(),
+        WordOp::Mod => a % b,
+        WordOp::SDiv if b.is_zero() => BigUint::zero(),
+        WordOp::SDiv => unsigned(signed(a) / signed(b)),
+        WordOp::SMod if b.is_zero() => BigUint::zero(),
+        WordOp::SMod => unsigned(signed(a) % signed(b)),
+        WordOp::Exp => a.modpow(b, &modulus),
+        WordOp::AddMod | WordOp::MulMod if c.is_zero() => BigUint::zero(),
+        WordOp::AddMod => (a + b) % c,
+        WordOp::MulMod => (a * b) % c,
+        WordOp::SignExtend if *a >= BigUint::from(31u8) => b.clone(),
+        WordOp::SignExtend => {
+            let bits = 8 * usize::try_from(a).unwrap() + 8;
+            let low = b % (BigUint::one() << bits);
+            match low.bit(bits as u64 - 1) {
+                true => unsigned(BigInt::from(low) - (BigInt::one() << bits)),
+                false => low,
+            }
+        }
+        WordOp::Byte if *a >= BigUint::from(32u8) => BigUint::zero(),
+        WordOp::Byte => (b >> (8 * (31 - usize::try_from(a).unwrap()))) % 256u32,
+        WordOp::Shl | WordOp::Shr | WordOp::Sar => {
+            let shift = usize::try_from(a).unwrap_or(256).min(256);
+            match op {
+                WordOp::Shl => (b << shift) % modulus,
+                WordOp::Shr => b >> shift,
+                _ => unsigned(signed(b) >> shift),
+            }
+        }
+    }
+}
+
+/// The word as a signed integer, in two's complement
+fn signed(word: &BigUint) -> BigInt {
+    match word.bit(255) {
+        true => BigInt::from(word.clone()) - (BigInt::one() << 256),
+        false => BigInt::from(word.clone()),
+    }
+}
+
+/// The word of a signed integer, in two's complement
+fn unsigned(value: BigInt) -> BigUint {
+    let modulus = BigInt::one() << 256;
+    let value: BigInt = ((value % &modulus) + &modulus) % modulus;
+    let (sign, bytes) = value.to_bytes_be();
+    debug_assert!(sign != Sign::Minus);
+    BigUint::from_bytes_be(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: i64) -> BigUint {
+        unsigned(BigInt::from(value))
+    }
+
+    fn op(op: WordOp, a: i64, b: i64) -> BigUint {
+        compute(op, &[word(a), word(b), BigUint::zero()])
+    }
+
+    #[test]
+    fn test_word_ops() {
+        assert_eq!(op(WordOp::Div, 7, 2), word(3));
+        assert_eq!(op(WordOp::Div, 7, 0), word(0));
+        assert_eq!(op(WordOp::SDiv, -7, 2), word(-3));
+        assert_eq!(op(WordOp::SMod, -7, 2), word(-1));
+        assert_eq!(op(WordOp::Exp, 2, 256), word(0));
+        assert_eq!(op(WordOp::Exp, 3, 4), word(81));
+        assert_eq!(compute(WordOp::AddMod, &[word(-1), word(2), word(10)]), word(7));
+        assert_eq!(op(WordOp::SignExtend, 0, 0xff), word(-1));
+        assert_eq!(op(WordOp::SignExtend, 0, 0x17f), word(0x7f));
+        assert_eq!(op(WordOp::Byte, 31, 0x1234), word(0x34));
+        assert_eq!(op(WordOp::Shl, 4, 1), word(16));
+        assert_eq!(op(WordOp::Shr, 300, -1), word(0));
+        assert_eq!(op(WordOp::Sar, 300, -1), word(-1));
+        assert_eq!(op(WordOp::Sar, 1, -4), word(-2));
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loading of object files in memory.
+//!
+//! The sections of the object holding code and data are copied into a single
+//! mapping, so that the relative relocations between them always fit, then
+//! relocated: the imports, the host functions, are resolved by name, and the
+//! generated code refers to them with absolute relocations, as it is not
+//! position independent. The mapping is made executable and read-only once
+//! relocated.
+
+use std::{collections::HashMap, io, ptr};
+
+use cranelift::object::object::{
+    self, Architecture, BinaryFormat, Object, ObjectSection, ObjectSymbol, RelocationKind,
+    RelocationTarget, SectionIndex, SectionKind, SymbolSection,
+};
+
+use super::JitError;
+
+/// An object file loaded in memory, unmapped when dropped
+#[derive(Debug)]
+pub struct LoadedObject {
+    base: *mut u8,
+    size: usize,
+    /// The addresses of the symbols the object defines, by name
+    symbols: HashMap<String, usize>,
+}
+
+impl LoadedObject {
+    /// Load an ELF object for x86-64, resolving its imports with `resolve`
+    pub fn load(data: &[u8], resolve: impl Fn(&str) -> Option<usize>) -> Result<Self, JitError> {
+        let file = object::File::parse(data).map_err(|err| JitError::Object(err.to_string()))?;
+        if file.format() != BinaryFormat::Elf || file.architecture() != Architecture::X86_64 {
+            return Err(JitError::UnsupportedObject(format!(
+                "{:?} {:?}",
+                file.format(),
+                file.architecture()
+            )));
+        }
+
+        let mut offsets = HashMap::new();
+        let mut size = 0usize;
+        for section in file.sections().filter(|section| is_loaded(section.kind())) {
+            size = size.next_multiple_of(section.align().max(1) as usize);
+            offsets.insert(section.index(), size);
+            size += section.size() as usize;
+        }
+
+        let mut loaded = Self::map(size)?;
+        let base = loaded.base as usize;
+        let address = |index: SectionIndex| {
+            offsets
+                .get(&index)
+                .map(|offset| base + offset)
+                .ok_or_else(|| JitError::Object(format!("section {} is not loaded", index.0)))
+        };
+
+        for section in file.sections().filter(|section| offsets.contains_key(&section.index())) {
+            let start = address(section.index())?;
+            let data = section.data().map_err(|err| JitError::Object(err.to_string()))?;
+            // SAFETY: the section was laid out in the mapping with its size
+            unsafe { ptr::copy_nonoverlapping(data.as_ptr(), start as *mut u8, data.len()) };
+        }
+
+        for section in file.sections().filter(|section| offsets.contains_key(&section.index())) {
+            let start = address(section.index())?;
+            for (offset, relocation) in section.relocations() {
+                let target = match relocation.target() {
+                    RelocationTarget::Section(index) => address(index)?,
+                    RelocationTarget::Symbol(index) => {
+                        let symbol = file
+                            .symbol_by_index(index)
+                            .map_err(|err| JitError::Object(err.to_string()))?;
+                        let name = symbol.name().unwrap_or_default();
+                        match symbol.section() {
+                            SymbolSection::Section(index) => {
+                                address(index)? + symbol.address() as usize
+                            }
+                            SymbolSection::Undefined => resolve(name)
+                                .ok_or_else(|| JitError::Undefined(name.to_string()))?,
+                            _ => return Err(JitError::Undefined(name.to_string())),
+                        }
+                    }
+                    target => {
+                        return Err(JitError::Object(format!("relocation to {target:?}")));
+                    }
+                };
+
+                let place = start + offset as usize;
+                let value = (target as i64).wrapping_add(relocation.addend());
+                match (relocation.kind(), relocation.size()) {
+                    // SAFETY: the place is in the section, which was copied
+                    (RelocationKind::Absolute, 64) => unsafe {
+                        ptr::write_unaligned(place as *mut i64, value)
+                    },
+                    (RelocationKind::Relative | RelocationKind::PltRelative, 32) => {
+                        let value = i32::try_from(value.wrapping_sub(place as i64))
+                            .map_err(|_| JitError::Object("relocation out of range".into()))?;
+                        // SAFETY: as above
+                        unsafe { ptr::write_unaligned(place as *mut i32, value) }
+                    }
+                    (kind, size) => {
+                        return Err(JitError::Object(format!(
+                            "unsupported relocation {kind:?} of {size} bits"
+                        )));
+                    }
+                }
+            }
+        }
+
+        for symbol in file.symbols().filter(|symbol| symbol.is_definition()) {
+            if let (Ok(name), Some(index)) = (symbol.name(), symbol.section_index()) {
+                loaded
+                    .symbols
+                    .insert(name.to_string(), address(index)? + symbol.address() as usize);
+            }
+        }
+
+        loaded.protect()?;
+        Ok(loaded)
+    }
+
+    /// The address of a symbol the object defines
+    pub fn symbol(&self, name: &str) -> Option<usize> {
+        self.symbols.get(name).copied()
+    }
+
+    /// Map writable memory of at least `size` bytes
+    fn map(size: usize) -> Result<Self, JitError> {
+        let size = size.max(1).next_multiple_of(page_size());
+        // SAFETY: an anonymous private mapping, which nothing else refers to
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(JitError::Io(io::Error::last_os_error()));
+        }
+
+        Ok(Self { base: base as *mut u8, size, symbols: HashMap::new() })
+    }
+
+    /// Make the mapping executable and read-only
+    fn protect(&self) -> Result<(), JitError> {
+        // SAFETY: the mapping is ours, and its size a multiple of the page size
+        let result = unsafe {
+            libc::mprotect(self.base.cast(), self.size, libc::PROT_READ | libc::PROT_EXEC)
+        };
+        match result {
+            0 => Ok(()),
+            _ => Err(JitError::Io(io::Error::last_os_error())),
+        }
+    }
+}
+
+impl Drop for LoadedObject {
+    fn drop(&mut self) {
+        // SAFETY: the mapping is ours, and no function of it runs anymore
+        unsafe { libc::munmap(self.base.cast(), self.size) };
+    }
+}
+
+/// Whether the section is loaded, holding code or data
+fn is_loaded(kind: SectionKind) -> bool {
+    matches!(
+        kind,
+        SectionKind::Text |
+            SectionKind::Data |
+            SectionKind::ReadOnlyData |
+            SectionKind::ReadOnlyDataWithRel |
+            SectionKind::ReadOnlyString |
+            SectionKind::UninitializedData
+    )
+}
+
+fn page_size() -> usize {
+    // SAFETY: reading a configuration value has no side effect
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(size).unwrap_or(4096)
+}
@@ -0,0 +1,696 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Execution of the generated code, for `compile --interpret` and the tests
+//! of the code generator.
+//!
+//! The object file of a module generated for the host is loaded in memory,
+//! see [`loader`], its imports resolved to the host functions of [`host`].
+//! Each call runs in a child process, so that a trap of the generated code
+//! ends the child rather than the compiler, and is reported as such. The
+//! storage a call leaves when it returns is kept by the [`Machine`] for the
+//! next calls, as a deployed contract keeps it between transactions. Other
+//! contracts can be deployed at addresses, for the generated code to call.
+//! Only ELF objects for x86-64 can be loaded.
+
+pub mod host;
+pub mod loader;
+
+use std::{collections::HashMap, io, mem, panic};
+
+use cranelift::{
+    codegen::{ir::MemFlagsData, settings},
+    module::{default_libcall_names, Linkage, Module, ModuleError},
+    object::{ObjectBuilder, ObjectModule},
+    prelude::{
+        isa, types, AbiParam, FunctionBuilder, FunctionBuilderContext, InstBuilder, Signature,
+    },
+};
+use num_bigint::{BigInt, Sign};
+use num_traits::Zero;
+use target_lexicon::Triple;
+use thiserror::Error;
+
+use crate::{
+    codegen::mangle::Symbol,
+    diagnostics::{Diagnostic, Diagnostics},
+    parser::{ast as pt, parse_expression},
+    semantic::{
+        ast::{Expression, Type},
+        context::Context,
+        eval::eval_constants_in_expression,
+        expression::{resolve_expression::expression, ExprContext, ResolveTo},
+        symtable::Symtable,
+    },
+};
+use host::{Call, Callee, Ending, Report, Word};
+use loader::LoadedObject;
+
+/// The name of the function calling a generated function with its arguments
+const ENTRY: &str = "entry";
+
+#[derive(Debug, Error)]
+pub enum JitError {
+    #[error("invalid object file: {0}")]
+    Object(String),
+
+    #[error("unsupported object file: {0}")]
+    UnsupportedObject(String),
+
+    #[error("undefined symbol '{0}'")]
+    Undefined(String),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Module(#[from] Box<ModuleError>),
+
+    /// The process running the call ended without reporting it
+    #[error("the call ended without a result")]
+    Lost,
+
+    /// The call to run cannot be resolved
+    #[error("{0}")]
+    Call(String),
+}
+
+/// How a call ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// With the data given to the `return` host function, or the results of
+    /// an internal function
+    Returned(Vec<u8>),
+    /// With the data given to the `revert` host function
+    Reverted(Vec<u8>),
+    /// By the signal ending the process, a trap of the generated code
+    Trapped(i32),
+}
+
+/// A contract deployed at an address, see [`Machine::deploy_at`]
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub contract: String,
+    pub storage: HashMap<Word, Word>,
+}
+
+/// How the process running a call ended
+#[derive(Debug)]
+enum Ended {
+    Reported(Report),
+    Signaled(i32),
+}
+
+/// The generated code of a module, loaded to be called
+pub struct Machine {
+    /// The module, then the objects linked
+    objects: Vec<LoadedObject>,
+    /// The storage of the contract, by key
+    pub storage: HashMap<Word, Word>,
+    /// The other contracts the contract can call, by address
+    pub accounts: HashMap<Word, Account>,
+    /// The caller of the next calls
+    pub caller: Word,
+}
+
+impl Machine {
+    /// Load the object file of a module generated for the host
+    pub fn load(object: &[u8]) -> Result<Self, JitError> {
+        let object = LoadedObject::load(object, host::address)?;
+        Ok(Self {
+            objects: vec![object],
+            storage: HashMap::new(),
+            accounts: HashMap::new(),
+            caller: [0; 32],
+        })
+    }
+
+    /// Load the object file of another contract, to deploy it at an address
+    pub fn link(&mut self, object: &[u8]) -> Result<(), JitError> {
+        self.objects.push(LoadedObject::load(object, host::address)?);
+        Ok(())
+    }
+
+    /// Deploy the contract: run its constructor, which takes no arguments,
+    /// or the initializers of its state variables when it has none
+    pub fn deploy(&mut self, contract: &str) -> Result<Outcome, JitError> {
+        self.construct(contract, None)
+    }
+
+    /// Deploy another contract at the address, see [`deploy`](Self::deploy),
+    /// which the contract can call once deployed
+    pub fn deploy_at(&mut self, contract: &str, address: Word) -> Result<Outcome, JitError> {
+        self.address(&Symbol::dispatcher(contract))?;
+        let account = Account { contract: contract.to_string(), storage: HashMap::new() };
+        self.accounts.insert(address, account);
+        let outcome = self.construct(contract, Some(address));
+        if !matches!(outcome, Ok(Outcome::Returned(_))) {
+            self.accounts.remove(&address);
+        }
+        outcome
+    }
+
+    /// Run the constructor of the contract with the storage of the contract
+    /// at the address, or of the contract when none
+    fn construct(&mut self, contract: &str, at: Option<Word>) -> Result<Outcome, JitError> {
+        let constructor = Symbol::new(Some(contract), pt::FunctionTy::Constructor, "", "");
+        let initializers = Symbol::initializers(contract);
+        match [constructor, initializers].iter().find_map(|symbol| self.address(symbol).ok()) {
+            // SAFETY: both take no parameters
+            Some(address) => self.run(at, Vec::new(), move || unsafe {
+                let function: extern "C" fn() = mem::transmute(address);
+                function();
+                Vec::new()
+            }),
+            None => Ok(Outcome::Returned(Vec::new())),
+        }
+    }
+
+    /// Call the contract with the input of a transaction, through its
+    /// dispatcher
+    pub fn call(&mut self, contract: &str, input: &[u8]) -> Result<Outcome, JitError> {
+        let address = self.address(&Symbol::dispatcher(contract))?;
+        let input = input.to_vec();
+        let len = input.len() as i32;
+        let data = input.clone();
+        // SAFETY: the dispatcher takes the input and its length
+        self.run(None, input, move || unsafe {
+            let dispatch: extern "C" fn(*const u8, i32) = mem::transmute(address);
+            dispatch(data.as_ptr(), len);
+            Vec::new()
+        })
+    }
+
+    /// Call an internal or free function with the words of its arguments,
+    /// giving the words of its results when it returns
+    pub fn call_function(
+        &mut self,
+        symbol: &Symbol,
+        args: &[Word],
+        returns: usize,
+    ) -> Result<Outcome, JitError> {
+        let entry = self.entry(symbol, args.len(), returns)?;
+        let address = entry.symbol(ENTRY).ok_or_else(|| JitError::Undefined(ENTRY.into()))?;
+        let limbs = args.iter().flat_map(limbs).collect::<Vec<_>>();
+        // SAFETY: the entry takes the limbs of the arguments and a pointer
+        // to the words of the results
+        self.run(None, Vec::new(), move || unsafe {
+            let entry: extern "C" fn(*const u64, *mut u8) = mem::transmute(address);
+            let mut results = vec![0; 32 * returns];
+            entry(limbs.as_ptr(), results.as_mut_ptr());
+            results
+        })
+    }
+
+    fn address(&self, symbol: &Symbol) -> Result<usize, JitError> {
+        let name = symbol.mangle();
+        self.symbol(&name).ok_or(JitError::Undefined(name))
+    }
+
+    /// The address of a symbol of the module, or of the objects linked
+    fn symbol(&self, name: &str) -> Option<usize> {
+        self.objects.iter().find_map(|object| object.symbol(name))
+    }
+
+    /// A function calling the internal function with the limbs it reads from
+    /// its first parameter, see
+    /// [`function::signature`](crate::codegen::function::signature)
+    fn entry(
+        &self,
+        symbol: &Symbol,
+        params: usize,
+        returns: usize,
+    ) -> Result<LoadedObject, JitError> {
+        let unsupported = |e: &dyn std::fmt::Display| JitError::UnsupportedObject(e.to_string());
+        let isa = isa::lookup(Triple::host())
+            .map_err(|e| unsupported(&e))?
+            .finish(settings::Flags::new(settings::builder()))
+            .map_err(|e| unsupported(&e))?;
+        let pointer_type = isa.pointer_type();
+        let call_conv = isa.default_call_conv();
+        let builder = ObjectBuilder::new(isa, "", default_libcall_names()).map_err(Box::new)?;
+        let mut module = ObjectModule::new(builder);
+
+        let mut signature = Signature::new(call_conv);
+        signature.params.extend((0..4 * params).map(|_| AbiParam::new(types::I64)));
+        if returns > 0 {
+            signature.params.push(AbiParam::new(pointer_type));
+        }
+        let callee = module
+            .declare_function(&symbol.mangle(), Linkage::Import, &signature)
+            .map_err(Box::new)?;
+
+        let config = module.isa().frontend_config();
+        let mut ctx = module.make_context();
+        ctx.func.signature.params.extend([AbiParam::new(pointer_type); 2]);
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+        let block = builder.create_block();
+        builder.append_block_params_for_function_params(block);
+        builder.switch_to_block(block);
+        builder.seal_block(block);
+
+        let [limbs, results] = builder.block_params(block).try_into().unwrap();
+        let mut args = (0..4 * params)
+            .map(|i| builder.ins().load(types::I64, MemFlagsData::trusted(), limbs, 8 * i as i32))
+            .collect::<Vec<_>>();
+        if returns > 0 {
+            args.push(results);
+        }
+        let callee = module.declare_func_in_func(callee, builder.func);
+        builder.ins().call(callee, &args);
+        builder.ins().return_(&[]);
+        builder.finalize(config);
+
+        let id = module
+            .declare_function(ENTRY, Linkage::Export, &ctx.func.signature)
+            .map_err(Box::new)?;
+        module.define_function(id, &mut ctx).map_err(Box::new)?;
+        let object = module.finish().emit().map_err(|e| JitError::Object(e.to_string()))?;
+
+        LoadedObject::load(&object, |name| self.symbol(name).or_else(|| host::address(name)))
+    }
+
+    /// Run the function in a child process with the storage of the contract
+    /// at the address, or of the contract when none, giving how it ended, and
+    /// keeping the storage of the contracts when it returned. A function
+    /// returning normally returns the data it gives.
+    fn run(
+        &mut self,
+        at: Option<Word>,
+        input: Vec<u8>,
+        function: impl FnOnce() -> Vec<u8>,
+    ) -> Result<Outcome, JitError> {
+        let storage = match at {
+            Some(address) => self.accounts[&address].storage.clone(),
+            None => self.storage.clone(),
+        };
+        let mut callees = HashMap::new();
+        for (address, account) in self.accounts.iter().filter(|(address, _)| Some(**address) != at)
+        {
+            let dispatcher = self.address(&Symbol::dispatcher(&account.contract))?;
+            callees.insert(*address, Callee { dispatcher, storage: account.storage.clone() });
+        }
+
+        let call = Call::new(at.unwrap_or_default(), storage, callees, input, self.caller, [0; 32]);
+        let report = match spawn(call, function)? {
+            Ended::Signaled(signal) => return Ok(Outcome::Trapped(signal)),
+            Ended::Reported(report) if report.ending == Ending::Reverted => {
+                return Ok(Outcome::Reverted(report.data));
+            }
+            Ended::Reported(report) => report,
+        };
+
+        for (address, storage) in report.callees {
+            if let Some(account) = self.accounts.get_mut(&address) {
+                account.storage = storage;
+            }
+        }
+        match at {
+            Some(address) => self.accounts.get_mut(&address).unwrap().storage = report.storage,
+            None => self.storage = report.storage,
+        }
+        Ok(Outcome::Returned(report.data))
+    }
+}
+
+/// Run the function in a child process making the call, giving how the
+/// process ended
+fn spawn(call: Call, function: impl FnOnce() -> Vec<u8>) -> Result<Ended, JitError> {
+    let mut fds = [0; 2];
+    // SAFETY: the array holds the two descriptors
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let [read, write] = fds;
+
+    // SAFETY: the child only runs the call, then exits
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    if pid == 0 {
+        // SAFETY: the child does not read the report
+        unsafe { libc::close(read) };
+        call.enter(write);
+        match panic::catch_unwind(panic::AssertUnwindSafe(function)) {
+            Ok(data) => Call::end(Ending::Returned, &data),
+            // SAFETY: exiting the child, as in `Call::end`
+            Err(_) => unsafe { libc::_exit(101) },
+        }
+    }
+
+    // SAFETY: the parent does not write the report
+    unsafe { libc::close(write) };
+    let mut report = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        // SAFETY: the buffer holds as many bytes as read
+        let n = unsafe { libc::read(read, buffer.as_mut_ptr().cast(), buffer.len()) };
+        match n {
+            0 => break,
+            n if n > 0 => report.extend(&buffer[..n as usize]),
+            _ if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => (),
+            _ => break,
+        }
+    }
+    // SAFETY: closing the descriptor we own, and waiting for our child
+    let mut status = 0;
+    unsafe {
+        libc::close(read);
+        libc::waitpid(pid, &mut status, 0);
+    }
+
+    if libc::WIFSIGNALED(status) {
+        return Ok(Ended::Signaled(libc::WTERMSIG(status)));
+    }
+    Report::parse(&report).map(Ended::Reported).ok_or(JitError::Lost)
+}
+
+/// The limbs of the word, the least significant first
+fn limbs(word: &Word) -> [u64; 4] {
+    std::array::from_fn(|i| u64::from_be_bytes(word[24 - 8 * i..32 - 8 * i].try_into().unwrap()))
+}
+
+/// The word of an integer, in two's complement
+pub fn word(value: &BigInt) -> Word {
+    let mut word = [if value.sign() == Sign::Minus { 0xff } else { 0 }; 32];
+    let bytes = value.to_signed_bytes_be();
+    let bytes = &bytes[bytes.len().saturating_sub(32)..];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    word
+}
+
+/// A call of a free function written as `name(args...)` in the main file of
+/// the analysis, resolved as an expression of the file: the number of the
+/// function and the words of its arguments, which must be constant
+pub fn resolve_call(call: &str, ctx: &mut Context) -> Result<(usize, Vec<Word>), JitError> {
+    let first = |diagnostic: Option<&Diagnostic>| match diagnostic {
+        Some(diagnostic) => JitError::Call(diagnostic.message.clone()),
+        None => JitError::Call(format!("`{call}` cannot be resolved")),
+    };
+    let expr = parse_expression(call.trim(), 0).map_err(|errors| first(errors.first()))?;
+
+    let mut context = ExprContext { no: 0, ..Default::default() };
+    let mut symtable = Symtable::default();
+    let mut diagnostics = Diagnostics::default();
+    let resolved =
+        expression(&expr, &mut context, ctx, &mut symtable, &mut diagnostics, ResolveTo::Discard);
+    let Ok(resolved) = resolved else {
+        return Err(first(diagnostics.errors().first().copied()));
+    };
+
+    let Expression::InternalFunctionCall { function, args, .. } = &resolved else {
+        return Err(JitError::Call(format!("`{call}` is not a function call")));
+    };
+    let function_no = match function.as_ref() {
+        Expression::InternalFunction { function_no, .. }
+            if ctx.functions[*function_no].contract_no.is_none() =>
+        {
+            *function_no
+        }
+        _ => return Err(JitError::Call(format!("`{expr}` does not call a free function"))),
+    };
+
+    let words = args
+        .iter()
+        .map(|arg| match eval_constants_in_expression(arg, &mut diagnostics) {
+            (Some(Expression::NumberLiteral { value, .. }), _) => Ok(word(&value)),
+            _ => match arg {
+                Expression::BoolLiteral { value, .. } => Ok(word(&BigInt::from(*value))),
+                Expression::BytesLiteral { value, .. } => {
+                    Ok(word(&BigInt::from_bytes_be(Sign::Plus, value)))
+                }
+                _ => Err(JitError::Call(format!("the arguments of `{expr}` must be constant"))),
+            },
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((function_no, words))
+}
+
+/// The value of the word held in the type, as Solidity writes it
+pub fn format_value(word: &Word, ty: &Type) -> String {
+    let value = BigInt::from_bytes_be(Sign::Plus, word);
+    let low = |bits: u16| value.clone() % (BigInt::from(1) << bits);
+    let hex = |bytes: usize| {
+        let bytes = &word[32 - bytes..];
+        format!("0x{}", bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+    };
+
+    match ty {
+        Type::Bool => (!value.is_zero()).to_string(),
+        Type::Uint(bits) => low(*bits).to_string(),
+        Type::Int(bits) => {
+            let low = low(*bits);
+            match low.bit(u64::from(*bits) - 1) {
+                true => (low - (BigInt::from(1) << bits)).to_string(),
+                false => low.to_string(),
+            }
+        }
+        Type::Address(_) => hex(20),
+        Type::Bytes(n) => hex(usize::from(*n)),
+        _ => hex(32),
+    }
+}
+
+#[cfg(all(test, target_os = "linux", target_arch = "x86_64"))]
+mod tests {
+    use sha3::{Digest, Keccak256};
+
+    use super::*;
+    use crate::{
+        abi::ethereum::selector,
+        codegen::{
+            gen_contracts, listing::Listing, require, tests::analyze, Codegen, CodegenOptions,
+        },
+    };
+
+    /// Run a call of a free function of the source, formatting its results
+    fn run(source: &str, call: &str) -> String {
+        let mut ctx = analyze(source);
+        let options = CodegenOptions { dce: false, ..Default::default() };
+        let mut codegen = Codegen::with_options(&options).unwrap();
+        codegen.gen(&ctx).unwrap();
+        let mut machine = Machine::load(&codegen.finish().unwrap()).unwrap();
+
+        let (function_no, args) = resolve_call(call, &mut ctx).unwrap();
+        let func = &ctx.functions[function_no];
+        let symbol = Symbol::function(func, &ctx);
+        match machine.call_function(&symbol, &args, func.returns.len()).unwrap() {
+            Outcome::Returned(data) => data
+                .chunks(32)
+                .zip(func.returns.iter())
+                .map(|(word, param)| format_value(word.try_into().unwrap(), &param.ty))
+                .collect::<Vec<_>>()
+                .join(", "),
+            outcome => format!("{outcome:?}"),
+        }
+    }
+
+    #[test]
+    fn test_call_function() {
+        let source = r#"
+            function divmod(uint a, uint b) pure returns (uint, uint) { return (a / b, a % b); }
+            function half(int a) pure returns (int) { return a / 2; }
+            function fib(uint n) pure returns (uint a) { uint b = 1; for (uint i = 0; i < n; i++) { b += a; a = b - a; } }
+            function even(uint8 n) pure returns (bool) { return n % 2 == 0; }
+            function positive(int8 n) pure returns (int8) { require(n > 0, "negative"); return n; }
+            function sum(uint8 n) pure returns (uint8 s) { for (uint8 i = 1; i <= n; i++) { s += i; } }
+            function rfib(uint n) pure returns (uint) { return n < 2 ? n : rfib(n - 1) + rfib(n - 2); }
+            function flip(uint a, uint b) pure returns (uint, uint) { return divmod(b, a); }
+        "#;
+
+        assert_eq!(run(source, "divmod(7, 2)"), "3, 1");
+        assert_eq!(run(source, "half(-7)"), "-3");
+        assert_eq!(run(source, "fib(10)"), "55");
+        assert_eq!(run(source, "even(4)"), "true");
+        assert!(run(source, "positive(-1)").starts_with("Reverted("));
+        assert_eq!(run(source, "sum(10)"), "55");
+        assert_eq!(run(source, "sum(30)"), format!("{:?}", Outcome::Trapped(libc::SIGILL)));
+        assert_eq!(run(source, "rfib(10)"), "55");
+        assert_eq!(run(source, "flip(2, 7)"), "3, 1");
+    }
+
+    #[test]
+    fn test_resolve_call() {
+        let mut ctx = analyze("function f(uint8 a) pure returns (uint8) { return a; }");
+        let error = |ctx: &mut Context, call| resolve_call(call, ctx).unwrap_err().to_string();
+
+        assert_eq!(resolve_call("f(uint8(255))", &mut ctx).unwrap().1, vec![word(&255.into())]);
+        assert_eq!(error(&mut ctx, "1 + 2"), "`1 + 2` is not a function call");
+        assert_eq!(error(&mut ctx, "g(1)"), "'g' not found");
+    }
+
+    /// The input of a call of the function with the words of its arguments
+    fn input(signature: &str, args: &[u64]) -> Vec<u8> {
+        let args = args.iter().flat_map(|arg| word(&BigInt::from(*arg)));
+        selector(signature).into_iter().chain(args).collect()
+    }
+
+    #[test]
+    fn test_nested_mapping() {
+        let source = r#"
+            contract Allowances {
+                uint8 decimals = 18;
+                mapping(address => mapping(uint64 => uint)) public allowance;
+
+                function approve(address spender, uint64 id, uint amount) public {
+                    allowance[spender][id] += amount;
+                }
+            }
+        "#;
+        let ctx = analyze(source);
+        let options = CodegenOptions::default();
+        let mut diagnostics = Diagnostics::default();
+        gen_contracts(&[0], &ctx, &options, &mut Listing::default(), &mut diagnostics).unwrap();
+        let mut machine = Machine::load(ctx.contracts[0].code.get().unwrap()).unwrap();
+
+        assert_eq!(machine.deploy("Allowances").unwrap(), Outcome::Returned(Vec::new()));
+        let approve = input("approve(address,uint64,uint256)", &[0xbeef, 7, 100]);
+        assert_eq!(machine.call("Allowances", &approve).unwrap(), Outcome::Returned(Vec::new()));
+        assert_eq!(machine.call("Allowances", &approve).unwrap(), Outcome::Returned(Vec::new()));
+
+        // The getter reads the element the function wrote, and no other
+        let allowance = |machine: &mut Machine, spender, id| match machine
+            .call("Allowances", &input("allowance(address,uint64)", &[spender, id]))
+        {
+            Ok(Outcome::Returned(data)) => BigInt::from_bytes_be(Sign::Plus, &data),
+            outcome => panic!("{outcome:?}"),
+        };
+        assert_eq!(allowance(&mut machine, 0xbeef, 7), BigInt::from(200));
+        assert_eq!(allowance(&mut machine, 0xbeef, 8), BigInt::zero());
+        assert_eq!(allowance(&mut machine, 7, 0xbeef), BigInt::zero());
+
+        // As in solc, the key of the element hashes its key with the key of
+        // the element of the outer mapping, and the initializer is kept
+        let outer = Keccak256::digest([word(&0xbeef.into()), word(&1.into())].concat());
+        let key = Keccak256::digest([word(&7.into()).as_slice(), &outer].concat());
+        assert_eq!(machine.storage.get(key.as_slice()), Some(&word(&200.into())));
+        assert_eq!(machine.storage.get(&word(&0.into())), Some(&word(&18.into())));
+    }
+
+    #[test]
+    fn test_internal_calls() {
+        let source = r#"
+            contract Base {
+                function scale(uint a) internal pure virtual returns (uint) { return a; }
+            }
+
+            contract Scaled is Base {
+                uint total = scale(21);
+
+                function scale(uint a) internal pure override returns (uint) { return 2 * a; }
+
+                function get() public view returns (uint) { return scale(total); }
+            }
+        "#;
+        let ctx = analyze(source);
+        let options = CodegenOptions::default();
+        let mut diagnostics = Diagnostics::default();
+        gen_contracts(&[1], &ctx, &options, &mut Listing::default(), &mut diagnostics).unwrap();
+        let mut machine = Machine::load(ctx.contracts[1].code.get().unwrap()).unwrap();
+
+        // The initializer and the function both call the override
+        assert_eq!(machine.deploy("Scaled").unwrap(), Outcome::Returned(Vec::new()));
+        let outcome = machine.call("Scaled", &input("get()", &[])).unwrap();
+        assert_eq!(outcome, Outcome::Returned(word(&84.into()).to_vec()));
+    }
+
+    #[test]
+    fn test_external_calls() {
+        let source = r#"
+            contract Counter {
+                uint count;
+
+                function add(uint n) external returns (uint) { count += n; return count; }
+
+                function fail(uint8 code) external pure returns (uint) {
+                    if (code == 1) { revert("one"); }
+                    if (code == 2) {
+                        assembly { mstore(0, shl(224, 0x4e487b71)) mstore(4, 0x11) revert(0, 0x24) }
+                    }
+                    if (code == 3) { uint8 max = 255; max++; }
+                    if (code == 4) { revert(); }
+                    return 7;
+                }
+            }
+
+            contract Client {
+                Counter counter;
+
+                function setCounter(Counter c) public { counter = c; }
+
+                function bump(uint n) public returns (uint) { return counter.add(n); }
+
+                function fail(uint8 code) public view returns (uint) { return counter.fail(code); }
+
+                function probe(uint8 code) public view returns (uint) {
+                    try counter.fail(code) returns (uint v) {
+                        return v;
+                    } catch Error(string memory) {
+                        return 1;
+                    } catch Panic(uint c) {
+                        return 100 + c;
+                    } catch {
+                        return 2;
+                    }
+                }
+            }
+        "#;
+        let ctx = analyze(source);
+        let options = CodegenOptions::default();
+        let mut diagnostics = Diagnostics::default();
+        gen_contracts(&[0, 1], &ctx, &options, &mut Listing::default(), &mut diagnostics).unwrap();
+        let mut machine = Machine::load(ctx.contracts[1].code.get().unwrap()).unwrap();
+        machine.link(ctx.contracts[0].code.get().unwrap()).unwrap();
+
+        let address = word(&0xc0ffee.into());
+        assert_eq!(machine.deploy_at("Counter", address).unwrap(), Outcome::Returned(Vec::new()));
+        assert_eq!(machine.deploy("Client").unwrap(), Outcome::Returned(Vec::new()));
+        let set = input("setCounter(address)", &[0xc0ffee]);
+        assert_eq!(machine.call("Client", &set).unwrap(), Outcome::Returned(Vec::new()));
+
+        // The callee keeps its storage between the calls
+        let returned = |n: u64| Outcome::Returned(word(&n.into()).to_vec());
+        assert_eq!(machine.call("Client", &input("bump(uint256)", &[5])).unwrap(), returned(5));
+        assert_eq!(machine.call("Client", &input("bump(uint256)", &[3])).unwrap(), returned(8));
+        assert_eq!(
+            machine.accounts[&address].storage.get(&word(&0.into())),
+            Some(&word(&8.into()))
+        );
+
+        // The data the callee reverts with selects the clause
+        let probe = |machine: &mut Machine, code| {
+            machine.call("Client", &input("probe(uint8)", &[code])).unwrap()
+        };
+        assert_eq!(probe(&mut machine, 0), returned(7));
+        assert_eq!(probe(&mut machine, 1), returned(1));
+        assert_eq!(probe(&mut machine, 2), returned(117));
+        assert_eq!(probe(&mut machine, 3), returned(2));
+        assert_eq!(probe(&mut machine, 4), returned(2));
+
+        // Without `try`, the caller reverts with the data of the callee
+        let fail = input("fail(uint8)", &[1]);
+        let outcome = machine.call("Client", &fail).unwrap();
+        assert_eq!(outcome, Outcome::Reverted(require::error_message("one")));
+        assert_eq!(machine.call("Client", &input("fail(uint8)", &[0])).unwrap(), returned(7));
+
+        // Calling an address without a contract reverts
+        let set = input("setCounter(address)", &[0xdead]);
+        assert_eq!(machine.call("Client", &set).unwrap(), Outcome::Returned(Vec::new()));
+        let outcome = machine.call("Client", &input("bump(uint256)", &[1])).unwrap();
+        assert_eq!(outcome, Outcome::Reverted(Vec::new()));
+    }
+}
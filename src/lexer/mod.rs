@@ -17,17 +17,62 @@ pub mod token;
 use self::token::Token;
 use logos::{Logos, SpannedIter};
 
-use crate::error::LexicalError;
+use crate::{
+    error::LexicalError,
+    parser::ast::{Comment, Loc},
+};
 
 pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 
 pub struct Lexer<'input> {
     tokens: SpannedIter<'input, Token<'input>>,
+    pragma: PragmaState,
+}
+
+/// Where the lexer is in a pragma directive. In its value, versions are
+/// single tokens, so that `0.8.0-rc.1` is a pre-release and `0.7 - 0.8` a
+/// range, while a minus anywhere else is an operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PragmaState {
+    None,
+    Name,
+    Value,
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(source: &'input str) -> Self {
-        Self { tokens: Token::lexer(source).spanned() }
+        Self { tokens: Token::lexer(source).spanned(), pragma: PragmaState::None }
+    }
+
+    /// Lex a version at the start of the remaining source: numbers separated
+    /// by dots, then optionally a pre-release after `-` and build metadata
+    /// after `+`
+    fn version(&mut self) -> Option<(usize, Token<'input>, usize)> {
+        let remainder = self.tokens.remainder();
+        let skipped = remainder.len() - remainder.trim_start().len();
+        let rest = &remainder[skipped..];
+
+        if !rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        for separator in ['-', '+'] {
+            if rest[len..].starts_with(separator) {
+                let suffix = &rest[len + 1..];
+                let end = suffix
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-')
+                    .unwrap_or(suffix.len());
+                // A minus followed by a space is a range
+                if end > 0 {
+                    len += 1 + end;
+                }
+            }
+        }
+
+        let start = self.tokens.source().len() - rest.len();
+        self.tokens.bump(skipped + len);
+        Some((start, Token::Version(&rest[..len]), start + len))
     }
 }
 
@@ -35,17 +80,60 @@ impl<'input> Iterator for Lexer<'input> {
     type Item = Spanned<Token<'input>, usize, LexicalError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.tokens.next().map(|(token, span)| match token {
-            Ok(token) => Ok((span.start, token, span.end)),
-            Err(_) => Ok((span.start, Token::Error, span.end)),
-        })
+        if self.pragma == PragmaState::Value {
+            if let Some(version) = self.version() {
+                return Some(Ok(version));
+            }
+        }
+
+        loop {
+            let (token, span) = self.tokens.next()?;
+
+            self.pragma = match (&token, self.pragma) {
+                (Ok(Token::Pragma), _) => PragmaState::Name,
+                (Ok(Token::Semicolon), _) => PragmaState::None,
+                (Ok(Token::LineComment(_) | Token::BlockComment(_)), state) => state,
+                (_, PragmaState::Name | PragmaState::Value) => PragmaState::Value,
+                (_, PragmaState::None) => PragmaState::None,
+            };
+
+            return Some(match token {
+                Ok(Token::LineComment(_) | Token::BlockComment(_)) => continue,
+                Ok(token) => Ok((span.start, token, span.end)),
+                Err(_) => Ok((span.start, Token::Error, span.end)),
+            });
+        }
     }
 }
 
+/// Collect the comments of the source, which the parser does not see
+pub fn comments(source: &str, no: usize) -> Vec<Comment> {
+    Token::lexer(source)
+        .spanned()
+        .filter_map(|(token, span)| {
+            let loc = Loc::File(no, span.start, span.end);
+
+            match token {
+                Ok(Token::LineComment(text)) => Some(match text.strip_prefix("///") {
+                    Some(rest) if !rest.starts_with('/') => Comment::DocLine(loc, text.into()),
+                    _ => Comment::Line(loc, text.into()),
+                }),
+                Ok(Token::BlockComment(text)) => Some(match text.strip_prefix("/**") {
+                    Some(rest) if !rest.starts_with(['*', '/']) => {
+                        Comment::DocBlock(loc, text.into())
+                    }
+                    _ => Comment::Block(loc, text.into()),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
 
-    use crate::lexer::{token::Token, Lexer};
+    use crate::lexer::{comments, token::Token, Lexer};
 
     #[test]
     fn test_lex_pragma() {
@@ -54,7 +142,88 @@ mod test {
         assert_eq!(lexer.next(), Some(Ok((0, Token::Pragma, 6))));
         assert_eq!(lexer.next(), Some(Ok((7, Token::Identifier("solidity"), 15))));
         assert_eq!(lexer.next(), Some(Ok((16, Token::BitwiseXor, 17))));
-        assert_eq!(lexer.next(), Some(Ok((17, Token::Number("0.8"), 20))));
+        assert_eq!(lexer.next(), Some(Ok((17, Token::Version("0.8"), 20))));
         assert_eq!(lexer.next(), Some(Ok((20, Token::Semicolon, 21))));
     }
+
+    #[test]
+    fn test_lex_versions() {
+        let source =
+            "pragma solidity >=0.8.0-rc.1+commit.7dd6d404 <0.9 || 0.7 - 0.7.6; int a = b -1;";
+
+        let tokens = Lexer::new(source).map(|t| t.unwrap().1).collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                Token::Pragma,
+                Token::Identifier("solidity"),
+                Token::MoreEqual,
+                Token::Version("0.8.0-rc.1+commit.7dd6d404"),
+                Token::Less,
+                Token::Version("0.9"),
+                Token::Or,
+                Token::Version("0.7"),
+                Token::Subtract,
+                Token::Version("0.7.6"),
+                Token::Semicolon,
+                Token::Identifier("int"),
+                Token::Identifier("a"),
+                Token::Assign,
+                Token::Identifier("b"),
+                Token::Subtract,
+                Token::Number("1"),
+                Token::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_comments() {
+        let source = "/// doc\nuint /* a */ x; // b\n/**\n * c\n */\n/**/";
+
+        let tokens = Lexer::new(source).map(|t| t.unwrap().1).collect::<Vec<_>>();
+        assert_eq!(tokens, [Token::Identifier("uint"), Token::Identifier("x"), Token::Semicolon]);
+
+        let comments = comments(source, 0);
+        assert_eq!(
+            comments.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["/// doc", "/* a */", "// b", "/**\n * c\n */", "/**/"]
+        );
+        assert!(comments[0].is_doc() && comments[3].is_doc());
+        assert!(!comments[4].is_doc());
+    }
+
+    #[test]
+    fn test_lex_strings() {
+        let source = r#"import "../a.sol"; 'it\'s' unicode"é \"q\"" "a"#;
+
+        let tokens = Lexer::new(source).map(|t| t.unwrap().1).collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                Token::Import,
+                Token::StringLiteral(r#""../a.sol""#),
+                Token::Semicolon,
+                Token::StringLiteral(r"'it\'s'"),
+                Token::StringLiteral(r#"unicode"é \"q\"""#),
+                Token::Error,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_hex_numbers() {
+        let source = "0x1 0x1_000 0xdead_BEEF 0x5B38Da6a701c568545dCfcB03FcB875f56beddC4";
+
+        let tokens = Lexer::new(source).map(|t| t.unwrap().1).collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                Token::HexNumber("0x1"),
+                Token::HexNumber("0x1_000"),
+                Token::HexNumber("0xdead_BEEF"),
+                Token::AddressLiteral("0x5B38Da6a701c568545dCfcB03FcB875f56beddC4"),
+            ]
+        );
+    }
 }
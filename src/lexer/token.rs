@@ -19,7 +19,7 @@ use logos::Logos;
 use crate::error::LexicalError;
 
 #[derive(Logos, Clone, Debug, PartialEq)]
-#[logos(error = LexicalError, skip r"[ \t\n\f]+", skip(r"//[^\n]*", allow_greedy = true))]
+#[logos(error = LexicalError, skip r"[ \t\n\f]+")]
 pub enum Token<'input> {
     #[regex("[_a-zA-Z][_0-9a-zA-Z]*", |lex| lex.slice())]
     Identifier(&'input str),
@@ -28,7 +28,7 @@ pub enum Token<'input> {
     Annotation(&'input str),
 
     /// `(unicode, literal)`
-    #[regex(r#"(unicode)?"[_a-zA-Z][_0-9a-zA-Z]*""#, |lex| lex.slice())]
+    #[regex(r#"(unicode)?("([^"\r\n\\]|\\.)*"|'([^'\r\n\\]|\\.)*')"#, |lex| lex.slice())]
     StringLiteral(&'input str),
 
     #[regex(r#"hex["']([0-9a-fA-F]{2}(_?[0-9a-fA-F]{2})*)*["']"#, |lex| lex.slice())]
@@ -38,20 +38,34 @@ pub enum Token<'input> {
     AddressLiteral(&'input str),
 
     #[regex(
-        r"-?(?:0|[1-9]\d*)(?:_\d+)*(?:\.(?:\d(?:_\d+)*))?(?:[eE][+-]?(?:\d(?:_\d+)*))?",
+        r"(?:0|[1-9]\d*)(?:_\d+)*(?:\.(?:\d(?:_\d+)*))?(?:[eE][+-]?(?:\d(?:_\d+)*))?",
         |lex| lex.slice()
     )]
     Number(&'input str),
 
     #[regex(
-        r"-?(?:0|[1-9]\d*)(?:_\d+)*/(?:0|[1-9]\d*)(?:_\d+)*(?:[eE][+-]?(?:\d(?:_\d+)*))?",
+        r"(?:0|[1-9]\d*)(?:_\d+)*/(?:0|[1-9]\d*)(?:_\d+)*(?:[eE][+-]?(?:\d(?:_\d+)*))?",
         |lex| lex.slice()
     )]
     RationalNumber(&'input str),
 
-    #[regex(r"0x([0-9a-fA-F]{2}(_?[0-9a-fA-F]{2})*)*", |lex| lex.slice())]
+    #[regex(r"0x[0-9a-fA-F]+(?:_[0-9a-fA-F]+)*", |lex| lex.slice())]
     HexNumber(&'input str),
 
+    /// A version in a pragma, like `0.8.0-rc.1`, only produced by [`Lexer`]
+    /// while lexing the value of a pragma
+    ///
+    /// [`Lexer`]: super::Lexer
+    Version(&'input str),
+
+    /// `// comment`, the lexer iterator skips comments
+    #[regex(r"//[^\n]*", |lex| lex.slice(), allow_greedy = true)]
+    LineComment(&'input str),
+
+    /// `/* comment */`
+    #[token("/*", block_comment)]
+    BlockComment(&'input str),
+
     #[token(";")]
     Semicolon,
 
@@ -407,6 +421,14 @@ pub enum Token<'input> {
     Error,
 }
 
+/// Read a block comment up to its closing `*/`
+fn block_comment<'input>(lex: &mut logos::Lexer<'input, Token<'input>>) -> Option<&'input str> {
+    let end = lex.remainder().find("*/")?;
+    lex.bump(end + 2);
+
+    Some(lex.slice())
+}
+
 impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{self:?}")
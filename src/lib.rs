@@ -12,12 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod abi;
 pub mod codegen;
+pub mod config;
+pub mod deps;
 pub mod diagnostics;
+pub mod driver;
 pub mod emit;
 pub mod error;
+pub mod formatter;
 pub mod helpers;
+#[cfg(unix)]
+pub mod jit;
 pub mod lexer;
+pub mod lint;
+pub mod outline;
 pub mod parser;
+pub mod query;
+pub mod rename;
+pub mod repl;
 pub mod resolver;
+pub mod runtime;
 pub mod semantic;
+pub mod stats;
+pub mod target;
+
+pub use codegen::mangle::demangle;
+pub use driver::{check, Input};
@@ -0,0 +1,81 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Opt-in checks for valid code which is likely wasteful or a mistake.
+//!
+//! Lints are switched on and off by name, in the `[lints]` table of the config
+//! file or with `--enable-lint` and `--disable-lint`, and never report errors.
+
+pub mod storage;
+pub mod unbounded;
+
+use crate::{
+    config::Config,
+    diagnostics::Diagnostic,
+    parser::ast::{
+        ContractDefinition, FunctionAttribute, FunctionDefinition, FunctionTy, SourceUnit,
+        Visibility,
+    },
+};
+
+/// Run the lints switched on in the config on a parse tree
+pub fn run(tree: &SourceUnit, config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = storage::check(tree, |lint| config.lint(lint.as_ref()) == Some(true));
+
+    if config.lint(unbounded::LINT) != Some(false) {
+        diagnostics.extend(unbounded::check(tree));
+    }
+
+    diagnostics
+}
+
+/// The contract and its bases declared in the same source unit
+pub(crate) fn inheritance_chain(no: usize, contracts: &[&ContractDefinition]) -> Vec<usize> {
+    let mut chain = vec![no];
+    let mut next = 0;
+
+    while let Some(contract_no) = chain.get(next).copied() {
+        for base in &contracts[contract_no].base {
+            let Some(name) = base.name.identifiers.last() else {
+                continue;
+            };
+            let base_no = contracts
+                .iter()
+                .position(|def| def.name.as_ref().is_some_and(|id| id.name == name.name));
+            if let Some(base_no) = base_no.filter(|no| !chain.contains(no)) {
+                chain.push(base_no);
+            }
+        }
+        next += 1;
+    }
+
+    chain
+}
+
+/// Whether the function can be called from outside of its contract
+pub(crate) fn is_entry_point(def: &FunctionDefinition) -> bool {
+    match def.ty {
+        FunctionTy::Constructor | FunctionTy::Fallback | FunctionTy::Receive => true,
+        FunctionTy::Modifier => false,
+        FunctionTy::Function => {
+            // Functions without a visibility are public, as before Solidity 0.5
+            !def.attributes.iter().any(|attr| {
+                matches!(
+                    attr,
+                    FunctionAttribute::Visibility(Visibility::Internal(_) | Visibility::Private(_))
+                )
+            })
+        }
+    }
+}
@@ -0,0 +1,594 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Warnings about storage accesses which cost more gas than needed.
+//!
+//! Storage is by far the most expensive memory of a contract, and a few common
+//! patterns access it more often than the code needs:
+//!
+//! - `storage-read-in-loop`: a state variable read on every iteration of a loop which never writes
+//!   it, like `items.length` in the loop condition
+//! - `repeated-storage-access`: the same mapping or array element, or the same `keccak256` hash,
+//!   computed more than once in a function
+//! - `storage-push-in-loop`: a storage array grown with `push` in a loop
+//!
+//! The checks run on the parse tree, so they recognize state variables by name
+//! when no local variable shadows them.
+
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+
+use crate::{
+    diagnostics::{Diagnostic, ErrorType, Level},
+    helpers::CodeLocation,
+    lint::inheritance_chain,
+    parser::ast::{
+        CatchClause, ContractPart, Expression, FunctionDefinition, Loc, ParameterList, SourceUnit,
+        SourceUnitPart, Statement, VariableAttribute,
+    },
+};
+
+/// The storage lints, all off by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumIter)]
+pub enum StorageLint {
+    #[strum(serialize = "storage-read-in-loop")]
+    ReadInLoop,
+    #[strum(serialize = "repeated-storage-access")]
+    RepeatedAccess,
+    #[strum(serialize = "storage-push-in-loop")]
+    PushInLoop,
+}
+
+/// Check the functions of the contracts for the enabled lints
+pub fn check(tree: &SourceUnit, enabled: impl Fn(StorageLint) -> bool) -> Vec<Diagnostic> {
+    let contracts = tree
+        .0
+        .iter()
+        .filter_map(|part| match part {
+            SourceUnitPart::ContractDefinition(def) => Some(def.as_ref()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut checker = Checker {
+        lints: StorageLint::iter().filter(|lint| enabled(*lint)).collect(),
+        state: HashSet::new(),
+        scopes: Vec::new(),
+        loops: Vec::new(),
+        accesses: IndexMap::new(),
+        diagnostics: Vec::new(),
+    };
+
+    if checker.lints.is_empty() {
+        return Vec::new();
+    }
+
+    for (no, contract) in contracts.iter().enumerate() {
+        // The state variables of the bases are in scope too
+        checker.state = inheritance_chain(no, &contracts)
+            .into_iter()
+            .flat_map(|no| &contracts[no].parts)
+            .filter_map(|part| match part {
+                ContractPart::VariableDefinition(def) => Some(def),
+                _ => None,
+            })
+            .filter(|def| {
+                !def.attrs.iter().any(|attr| {
+                    matches!(attr, VariableAttribute::Constant(_) | VariableAttribute::Immutable(_))
+                })
+            })
+            .filter_map(|def| def.name.as_ref().map(|id| id.name.as_str()))
+            .collect();
+
+        for part in &contract.parts {
+            if let ContractPart::FunctionDefinition(def) = part {
+                checker.function(def);
+            }
+        }
+    }
+
+    checker.diagnostics
+}
+
+struct Checker<'a> {
+    lints: Vec<StorageLint>,
+    /// The state variables of the current contract
+    state: HashSet<&'a str>,
+    /// The local variables of the current function, by block
+    scopes: Vec<Vec<&'a str>>,
+    loops: Vec<Loop<'a>>,
+    /// The storage elements and hashes computed so far in the function
+    accesses: IndexMap<String, Access>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A loop being checked
+struct Loop<'a> {
+    loc: Loc,
+    /// The state variables read in the loop, with their first read
+    reads: IndexMap<&'a str, Loc>,
+    /// The variables written in the loop, including the nested loops
+    writes: HashSet<&'a str>,
+    /// The storage arrays already reported as growing in the loop
+    pushes: HashSet<&'a str>,
+}
+
+/// The first computation of a storage element or hash
+#[derive(Clone)]
+struct Access {
+    loc: Loc,
+    /// The variables the key depends on; writing one of them makes it another
+    /// element
+    deps: Vec<String>,
+    reported: bool,
+}
+
+impl<'a> Checker<'a> {
+    fn enabled(&self, lint: StorageLint) -> bool {
+        self.lints.contains(&lint)
+    }
+
+    fn warn(&mut self, loc: Loc, message: String, note: (Loc, String)) {
+        let diagnostic = Diagnostic::builder(loc, Level::Warning)
+            .ty(ErrorType::Warning)
+            .message(message)
+            .note(note.0, note.1)
+            .build();
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn is_state(&self, name: &str) -> bool {
+        self.state.contains(name) && !self.scopes.iter().flatten().any(|local| *local == name)
+    }
+
+    fn declare(&mut self, params: &'a ParameterList) {
+        for (_, param) in params {
+            if let Some(name) = param.as_ref().and_then(|param| param.name.as_ref()) {
+                self.write(&name.name);
+                self.scopes.last_mut().unwrap().push(&name.name);
+            }
+        }
+    }
+
+    fn function(&mut self, def: &'a FunctionDefinition) {
+        let Some(body) = &def.body else {
+            return;
+        };
+
+        self.scopes = vec![Vec::new()];
+        self.accesses.clear();
+        self.declare(&def.params);
+        self.declare(&def.returns);
+        self.statement(body);
+    }
+
+    fn read(&mut self, name: &'a str, loc: Loc) {
+        if let Some(current) = self.loops.last_mut() {
+            current.reads.entry(name).or_insert(loc);
+        }
+    }
+
+    fn write(&mut self, name: &'a str) {
+        for current in &mut self.loops {
+            current.writes.insert(name);
+        }
+        self.accesses.retain(|_, access| !access.deps.iter().any(|dep| dep == name));
+    }
+
+    fn access(&mut self, expr: &Expression) {
+        let mut deps = Vec::new();
+        let Some(key) = key(expr, &mut deps) else {
+            return;
+        };
+
+        match self.accesses.get_mut(&key) {
+            Some(first) if !first.reported => {
+                first.reported = true;
+                let first = first.loc;
+                if self.enabled(StorageLint::RepeatedAccess) {
+                    let message = match expr {
+                        Expression::FunctionCall(..) => {
+                            format!("'{key}' is computed more than once")
+                        }
+                        _ => format!("'{key}' is read from storage more than once"),
+                    };
+                    let note = "first computed here, store it in a local variable and reuse it";
+                    self.warn(expr.loc(), message, (first, note.into()));
+                }
+            }
+            Some(_) => (),
+            None => {
+                let access = Access { loc: expr.loc(), deps, reported: false };
+                self.accesses.insert(key, access);
+            }
+        }
+    }
+
+    fn block<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(Vec::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn in_loop(&mut self, loc: Loc, f: impl FnOnce(&mut Self)) {
+        self.loops.push(Loop {
+            loc,
+            reads: IndexMap::new(),
+            writes: HashSet::new(),
+            pushes: HashSet::new(),
+        });
+        f(self);
+        let current = self.loops.pop().unwrap();
+
+        if !self.enabled(StorageLint::ReadInLoop) {
+            return;
+        }
+        for (name, loc) in current.reads {
+            if !current.writes.contains(name) {
+                self.warn(
+                    loc,
+                    format!("state variable '{name}' is read from storage on every iteration of the loop"),
+                    (current.loc, format!("read '{name}' into a local variable before the loop")),
+                );
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &'a Statement) {
+        match stmt {
+            Statement::Block { statements, .. } => self.block(|this| {
+                for stmt in statements {
+                    this.statement(stmt);
+                }
+            }),
+            Statement::Args(_, args) => {
+                for arg in args {
+                    self.expression(&arg.expr);
+                }
+            }
+            Statement::If(_, cond, then, otherwise) => {
+                self.expression(cond);
+
+                // Only what both branches compute has been computed after them
+                let before = self.accesses.clone();
+                self.block(|this| this.statement(then));
+                let then = std::mem::replace(&mut self.accesses, before);
+                if let Some(otherwise) = otherwise {
+                    self.block(|this| this.statement(otherwise));
+                }
+                self.accesses.retain(|key, _| then.contains_key(key));
+            }
+            Statement::While(loc, cond, body) | Statement::DoWhile(loc, body, cond) => self
+                .in_loop(*loc, |this| {
+                    this.expression(cond);
+                    this.block(|this| this.statement(body));
+                }),
+            Statement::Expression(_, expr) | Statement::Emit(_, expr) => self.expression(expr),
+            Statement::VariableDefinition(_, decl, initializer) => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                if let Some(name) = &decl.name {
+                    self.write(&name.name);
+                    self.scopes.last_mut().unwrap().push(&name.name);
+                }
+            }
+            Statement::For(loc, init, cond, next, body) => self.block(|this| {
+                if let Some(init) = init {
+                    this.statement(init);
+                }
+                this.in_loop(*loc, |this| {
+                    if let Some(cond) = cond {
+                        this.expression(cond);
+                    }
+                    if let Some(body) = body {
+                        this.block(|this| this.statement(body));
+                    }
+                    if let Some(next) = next {
+                        this.expression(next);
+                    }
+                });
+            }),
+            Statement::Return(_, expr) => {
+                if let Some(expr) = expr {
+                    self.expression(expr);
+                }
+            }
+            Statement::Revert(_, _, args) => {
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            Statement::RevertNamedArgs(_, _, args) => {
+                for arg in args {
+                    self.expression(&arg.expr);
+                }
+            }
+            Statement::Try(_, expr, returns, catches) => {
+                self.expression(expr);
+                if let Some((params, body)) = returns {
+                    self.block(|this| {
+                        this.declare(params);
+                        this.statement(body);
+                    });
+                }
+                for catch in catches {
+                    let (CatchClause::Simple(.., body) | CatchClause::Named(.., body)) = catch;
+                    self.block(|this| this.statement(body));
+                }
+            }
+            // Yul accesses storage by slot, not by name
+            Statement::Assembly { .. } |
+            Statement::Continue(_) |
+            Statement::Break(_) |
+            Statement::Error(_) => (),
+        }
+    }
+
+    /// An assignment target, which is written and, for elements, also accessed
+    fn target(&mut self, expr: &'a Expression) {
+        if let Expression::List(_, params) = expr {
+            for (_, param) in params {
+                if let Some(param) = param.as_ref().filter(|param| param.name.is_none()) {
+                    self.target(&param.ty);
+                }
+            }
+            return;
+        }
+
+        if let Some(name) = root(expr) {
+            if self.is_state(name) && !matches!(expr, Expression::Variable(_)) {
+                self.access(expr);
+            }
+            self.indices(expr);
+            self.write(name);
+        } else {
+            self.expression(expr);
+        }
+    }
+
+    /// The indices of a chain of subscripts and member accesses, whose root is
+    /// not a read on its own
+    fn indices(&mut self, expr: &'a Expression) {
+        match expr {
+            Expression::ArraySubscript(_, base, index) => {
+                self.indices(base);
+                if let Some(index) = index {
+                    self.expression(index);
+                }
+            }
+            Expression::MemberAccess(_, base, _) | Expression::Parenthesis(_, base) => {
+                self.indices(base)
+            }
+            Expression::Variable(_) => (),
+            expr => self.expression(expr),
+        }
+    }
+
+    fn expression(&mut self, expr: &'a Expression) {
+        match expr {
+            Expression::Variable(id) if self.is_state(&id.name) => self.read(&id.name, id.loc),
+            Expression::ArraySubscript(..)
+                if root(expr).is_some_and(|name| self.is_state(name)) =>
+            {
+                self.access(expr);
+                self.indices(expr);
+            }
+            Expression::FunctionCall(loc, func, args) => {
+                match func.as_ref() {
+                    Expression::MemberAccess(_, array, member)
+                        if matches!(member.name.as_str(), "push" | "pop") =>
+                    {
+                        match root(array).filter(|name| self.is_state(name)) {
+                            Some(name) => {
+                                self.indices(array);
+                                self.write(name);
+                                if member.name == "push" {
+                                    self.push(name, *loc);
+                                }
+                            }
+                            None => self.expression(func),
+                        }
+                    }
+                    Expression::Variable(id) if id.name == "keccak256" => self.access(expr),
+                    func => self.expression(func),
+                }
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            Expression::MemberAccess(_, base, _) => self.expression(base),
+            Expression::FunctionCallBlock(_, func, block) => {
+                self.expression(func);
+                self.statement(block);
+            }
+            Expression::NamedFunctionCall(_, func, args) => {
+                self.expression(func);
+                for arg in args {
+                    self.expression(&arg.expr);
+                }
+            }
+            Expression::ArraySlice(_, array, from, to) => {
+                self.expression(array);
+                for expr in [from, to].into_iter().flatten() {
+                    self.expression(expr);
+                }
+            }
+            Expression::ConditionalOperator(_, cond, left, right) => {
+                for expr in [cond, left, right] {
+                    self.expression(expr);
+                }
+            }
+            Expression::ArrayLiteral(_, exprs) => {
+                for expr in exprs {
+                    self.expression(expr);
+                }
+            }
+            Expression::Assign(_, left, right) => {
+                self.expression(right);
+                self.target(left);
+            }
+            Expression::AssignOr(_, left, right) |
+            Expression::AssignAnd(_, left, right) |
+            Expression::AssignXor(_, left, right) |
+            Expression::AssignShiftLeft(_, left, right) |
+            Expression::AssignShiftRight(_, left, right) |
+            Expression::AssignAdd(_, left, right) |
+            Expression::AssignSubtract(_, left, right) |
+            Expression::AssignMultiply(_, left, right) |
+            Expression::AssignDivide(_, left, right) |
+            Expression::AssignModulo(_, left, right) => {
+                self.target(left);
+                self.expression(right);
+            }
+            Expression::PreIncrement(_, expr) |
+            Expression::PreDecrement(_, expr) |
+            Expression::PostIncrement(_, expr) |
+            Expression::PostDecrement(_, expr) |
+            Expression::Delete(_, expr) => self.target(expr),
+            // Types and parameter lists hold no storage reads
+            Expression::List(..) | Expression::Type(..) => (),
+            expr => {
+                let (left, right) = expr.components();
+                for expr in left.into_iter().chain(right) {
+                    self.expression(expr);
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, name: &'a str, loc: Loc) {
+        let enabled = self.enabled(StorageLint::PushInLoop);
+        let Some(current) = self.loops.last_mut() else {
+            return;
+        };
+        if current.pushes.insert(name) && enabled {
+            let loop_loc = current.loc;
+            self.warn(
+                loc,
+                format!("storage array '{name}' grows on every iteration of the loop"),
+                (
+                    loop_loc,
+                    "every push writes the new length as well as the element, collect the \
+                     elements in a memory array first where possible"
+                        .into(),
+                ),
+            );
+        }
+    }
+}
+
+/// The variable at the root of a chain of subscripts and member accesses
+fn root(expr: &Expression) -> Option<&str> {
+    match expr {
+        Expression::Variable(id) => Some(&id.name),
+        Expression::ArraySubscript(_, base, _) |
+        Expression::MemberAccess(_, base, _) |
+        Expression::Parenthesis(_, base) => root(base),
+        _ => None,
+    }
+}
+
+/// The source text of an element or hash which always computes the same
+/// value, as long as none of the variables it depends on are written
+fn key(expr: &Expression, deps: &mut Vec<String>) -> Option<String> {
+    let key = match expr {
+        Expression::Variable(id) => {
+            deps.push(id.name.clone());
+            id.name.clone()
+        }
+        Expression::MemberAccess(_, base, member) => {
+            format!("{}.{}", key(base, deps)?, member.name)
+        }
+        Expression::ArraySubscript(_, base, Some(index)) => {
+            format!("{}[{}]", key(base, deps)?, key(index, deps)?)
+        }
+        Expression::Parenthesis(_, expr) => key(expr, deps)?,
+        Expression::FunctionCall(_, func, args) => {
+            let func = key(func, deps)?;
+            if !matches!(func.as_str(), "keccak256" | "abi.encode" | "abi.encodePacked") {
+                return None;
+            }
+            let args = args.iter().map(|arg| key(arg, deps)).collect::<Option<Vec<_>>>()?;
+            format!("{func}({})", args.join(", "))
+        }
+        Expression::NumberLiteral(_, integer, exp, None) if exp.is_empty() => integer.clone(),
+        Expression::HexNumberLiteral(_, hex, None) | Expression::AddressLiteral(_, hex) => {
+            hex.clone()
+        }
+        Expression::BoolLiteral(_, value) => value.to_string(),
+        Expression::StringLiteral(parts) => {
+            format!("\"{}\"", parts.iter().map(|part| part.string.as_str()).collect::<String>())
+        }
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_storage_lints() {
+        let source = r#"
+contract Base { uint[] items; }
+contract C is Base {
+    mapping(address => uint) balances;
+    uint constant LIMIT = 10;
+    uint total;
+
+    function sum() public view returns (uint s) {
+        for (uint i = 0; i < items.length; i++) { s += items[i] + LIMIT; }
+    }
+    function count() public {
+        for (uint i = 0; i < 10; i++) { total += 1; items.push(i); }
+    }
+    function shadowed(uint total) public pure returns (uint t) {
+        while (t < total) { t++; }
+    }
+    function withdraw(uint amount) public {
+        require(balances[msg.sender] >= amount);
+        balances[msg.sender] -= amount;
+        bytes32 h = keccak256(abi.encode(amount));
+        amount = 0;
+        h = keccak256(abi.encode(amount));
+    }
+}
+"#;
+        let tree = parse(source, 0).unwrap();
+        let messages = |diagnostics: Vec<Diagnostic>| {
+            diagnostics.into_iter().map(|d| d.message).collect::<Vec<_>>()
+        };
+
+        assert!(check(&tree, |_| false).is_empty());
+        assert_eq!(
+            messages(check(&tree, |_| true)),
+            [
+                "state variable 'items' is read from storage on every iteration of the loop",
+                "storage array 'items' grows on every iteration of the loop",
+                "'balances[msg.sender]' is read from storage more than once",
+            ]
+        );
+        assert_eq!(
+            messages(check(&tree, |lint| lint == StorageLint::PushInLoop)),
+            ["storage array 'items' grows on every iteration of the loop"]
+        );
+    }
+}
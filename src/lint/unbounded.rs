@@ -0,0 +1,119 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Information about loops whose number of iterations is not under the control
+//! of the contract, `unbounded-loop`.
+//!
+//! A loop over an array which anyone can grow, or up to a number given by the
+//! caller, may need more gas than a block allows. Leaving it stuck that way can
+//! lock funds or block a contract, so such loops are worth a closer look.
+
+use crate::{
+    codegen::loops::{bound, loops, Bound},
+    diagnostics::{Diagnostic, Level},
+    helpers::CodeLocation,
+    lint::is_entry_point,
+    parser::ast::{ContractPart, FunctionDefinition, SourceUnit, SourceUnitPart},
+};
+
+/// Name of the lint, which is on by default
+pub const LINT: &str = "unbounded-loop";
+
+/// Report the loops of the functions bounded by an array length or an input
+pub fn check(tree: &SourceUnit) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for part in &tree.0 {
+        match part {
+            SourceUnitPart::FunctionDefinition(def) => function(def, false, &mut diagnostics),
+            SourceUnitPart::ContractDefinition(contract) => {
+                for part in &contract.parts {
+                    if let ContractPart::FunctionDefinition(def) = part {
+                        function(def, is_entry_point(def), &mut diagnostics);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    diagnostics
+}
+
+fn function(def: &FunctionDefinition, external: bool, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(body) = &def.body else {
+        return;
+    };
+
+    // Only the callers of entry points are untrusted
+    let inputs = match external {
+        true => def
+            .params
+            .iter()
+            .filter_map(|(_, param)| param.as_ref()?.name.as_ref())
+            .map(|id| id.name.as_str())
+            .collect(),
+        false => Vec::new(),
+    };
+
+    for stmt in loops(body) {
+        let diagnostic = match bound(stmt, &inputs) {
+            Bound::ArrayLength(loc) => Diagnostic::builder(loc, Level::Info)
+                .message("the number of iterations of the loop grows with the length of an array")
+                .note(
+                    stmt.loc(),
+                    "if the array can grow without limit, the loop may run out of gas",
+                ),
+            Bound::Input(loc, name) => Diagnostic::builder(loc, Level::Info)
+                .message(format!(
+                    "the number of iterations of the loop is chosen by the caller with '{name}'"
+                ))
+                .note(
+                    stmt.loc(),
+                    "limit the input, or process the work in batches, to not run out of gas",
+                ),
+            Bound::Constant(_) | Bound::Unknown => continue,
+        };
+        diagnostics.push(diagnostic.build());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_unbounded_loops() {
+        let source = r#"
+contract C {
+    address[] holders;
+    function pay() public { for (uint i = 0; i < holders.length; i++) {} }
+    function repeat(uint n) external { for (uint i = 0; i < n; i++) {} }
+    function fixed() public { for (uint i = 0; i < 10; i++) {} }
+    function helper(uint n) internal { while (n > 0) { n--; } }
+}
+"#;
+        let diagnostics = check(&parse(source, 0).unwrap());
+        let messages = diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "the number of iterations of the loop grows with the length of an array",
+                "the number of iterations of the loop is chosen by the caller with 'n'",
+            ]
+        );
+    }
+}
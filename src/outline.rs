@@ -0,0 +1,263 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The outline of a file: its contracts and the declarations inside them, as
+//! shown in editor sidebars and returned for the LSP `documentSymbol` request.
+
+use strum::Display;
+
+use crate::{
+    helpers::{json::Json, CodeLocationExt},
+    parser::ast as pt,
+    semantic::{
+        ast::{ContractDefinition, SourceUnit},
+        file::File,
+    },
+};
+
+/// The kind of a declaration in the outline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum SymbolKind {
+    Contract,
+    Interface,
+    Library,
+    Function,
+    Constructor,
+    Modifier,
+    Variable,
+    Constant,
+    Event,
+    Error,
+    Struct,
+    Field,
+    Enum,
+    EnumMember,
+    Type,
+}
+
+/// A declaration with the declarations nested inside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The location of the whole declaration
+    pub loc: pt::Loc,
+    /// The location of the name
+    pub name_loc: pt::Loc,
+    pub children: Vec<DocumentSymbol>,
+}
+
+impl DocumentSymbol {
+    fn new(name: Option<&pt::Identifier>, kind: SymbolKind, loc: pt::Loc) -> Self {
+        Self {
+            name: name.map_or_else(String::new, |id| id.name.clone()),
+            kind,
+            loc,
+            name_loc: name.map_or(loc, |id| id.loc),
+            children: Vec::new(),
+        }
+    }
+
+    /// Serialize the symbol in the LSP format, with line and column ranges
+    pub fn to_json(&self, file: &File) -> Json {
+        Json::object([
+            ("name", self.name.as_str().into()),
+            ("kind", self.kind.to_string().into()),
+            ("range", range(self.loc, file)),
+            ("selectionRange", range(self.name_loc, file)),
+            ("children", Json::Array(self.children.iter().map(|s| s.to_json(file)).collect())),
+        ])
+    }
+}
+
+/// Build the outline of a collected source unit, in source order
+pub fn outline(tree: &SourceUnit) -> Vec<DocumentSymbol> {
+    let mut symbols = tree
+        .parts
+        .iter()
+        .filter_map(|part| match &part.part {
+            pt::SourceUnitPart::EnumDefinition(def) => Some(enum_symbol(def)),
+            pt::SourceUnitPart::StructDefinition(def) => Some(struct_symbol(def)),
+            pt::SourceUnitPart::EventDefinition(def) => Some(event_symbol(def)),
+            pt::SourceUnitPart::ErrorDefinition(def) => Some(error_symbol(def)),
+            pt::SourceUnitPart::FunctionDefinition(def) => Some(function_symbol(def)),
+            pt::SourceUnitPart::VariableDefinition(def) => Some(variable_symbol(def)),
+            pt::SourceUnitPart::TypeDefinition(def) => Some(type_symbol(def)),
+            _ => None,
+        })
+        .chain(tree.contracts.iter().map(contract_symbol))
+        .collect::<Vec<_>>();
+
+    // The collected tree keeps the contracts apart from the other parts
+    symbols.sort_by_key(|symbol| start(symbol.loc));
+
+    symbols
+}
+
+/// Serialize an outline in the LSP format
+pub fn to_json(symbols: &[DocumentSymbol], file: &File) -> Json {
+    Json::Array(symbols.iter().map(|symbol| symbol.to_json(file)).collect())
+}
+
+fn contract_symbol(def: &ContractDefinition) -> DocumentSymbol {
+    let kind = match def.ty {
+        pt::ContractTy::Interface(_) => SymbolKind::Interface,
+        pt::ContractTy::Library(_) => SymbolKind::Library,
+        _ => SymbolKind::Contract,
+    };
+
+    let mut symbol = DocumentSymbol::new(def.name.as_ref(), kind, def.loc);
+
+    symbol.children = def
+        .parts
+        .iter()
+        .filter_map(|part| match &part.part {
+            pt::ContractPart::StructDefinition(def) => Some(struct_symbol(def)),
+            pt::ContractPart::EventDefinition(def) => Some(event_symbol(def)),
+            pt::ContractPart::EnumDefinition(def) => Some(enum_symbol(def)),
+            pt::ContractPart::ErrorDefinition(def) => Some(error_symbol(def)),
+            pt::ContractPart::VariableDefinition(def) => Some(variable_symbol(def)),
+            pt::ContractPart::FunctionDefinition(def) => Some(function_symbol(def)),
+            pt::ContractPart::TypeDefinition(def) => Some(type_symbol(def)),
+            _ => None,
+        })
+        .collect();
+
+    symbol
+}
+
+fn function_symbol(def: &pt::FunctionDefinition) -> DocumentSymbol {
+    let kind = match def.ty {
+        pt::FunctionTy::Constructor => SymbolKind::Constructor,
+        pt::FunctionTy::Modifier => SymbolKind::Modifier,
+        _ => SymbolKind::Function,
+    };
+
+    let mut symbol = DocumentSymbol::new(def.name.as_ref(), kind, def.loc());
+
+    // constructor, fallback and receive have no name
+    if def.name.is_none() {
+        symbol.name = def.ty.to_string();
+        symbol.name_loc = def.name_loc;
+    }
+
+    symbol
+}
+
+fn variable_symbol(def: &pt::VariableDefinition) -> DocumentSymbol {
+    let kind = if def.attrs.iter().any(|attr| matches!(attr, pt::VariableAttribute::Constant(_))) {
+        SymbolKind::Constant
+    } else {
+        SymbolKind::Variable
+    };
+
+    DocumentSymbol::new(def.name.as_ref(), kind, def.loc)
+}
+
+fn struct_symbol(def: &pt::StructDefinition) -> DocumentSymbol {
+    let mut symbol = DocumentSymbol::new(def.name.as_ref(), SymbolKind::Struct, def.loc);
+
+    symbol.children = def
+        .fields
+        .iter()
+        .map(|field| DocumentSymbol::new(field.name.as_ref(), SymbolKind::Field, field.loc))
+        .collect();
+
+    symbol
+}
+
+fn enum_symbol(def: &pt::EnumDefinition) -> DocumentSymbol {
+    let mut symbol = DocumentSymbol::new(def.name.as_ref(), SymbolKind::Enum, def.loc);
+
+    symbol.children = def
+        .values
+        .iter()
+        .flatten()
+        .map(|value| DocumentSymbol::new(Some(value), SymbolKind::EnumMember, value.loc))
+        .collect();
+
+    symbol
+}
+
+fn event_symbol(def: &pt::EventDefinition) -> DocumentSymbol {
+    DocumentSymbol::new(def.name.as_ref(), SymbolKind::Event, def.loc)
+}
+
+fn error_symbol(def: &pt::ErrorDefinition) -> DocumentSymbol {
+    DocumentSymbol::new(def.name.as_ref(), SymbolKind::Error, def.loc)
+}
+
+fn type_symbol(def: &pt::TypeDefinition) -> DocumentSymbol {
+    DocumentSymbol::new(Some(&def.name), SymbolKind::Type, def.loc)
+}
+
+fn start(loc: pt::Loc) -> usize {
+    match loc {
+        pt::Loc::File(_, start, _) => start,
+        _ => 0,
+    }
+}
+
+fn range(loc: pt::Loc, file: &File) -> Json {
+    let position = |offset| {
+        let (line, character) = file.line_column(offset);
+        Json::object([("line", line.into()), ("character", character.into())])
+    };
+
+    match loc {
+        pt::Loc::File(_, start, end) => {
+            Json::object([("start", position(start)), ("end", position(end))])
+        }
+        _ => Json::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{parse, visitor::Visitable},
+        semantic::{collector::AnnotationCollector, context::Context},
+    };
+
+    #[test]
+    fn test_outline() {
+        let src = "struct S { uint a; }\ncontract C {\n    uint constant N = 1;\n    constructor() {}\n    function f() public {}\n}\nenum E { A, B }\n";
+
+        let mut ctx = Context::new();
+        let mut tree = parse(src, 0).unwrap();
+        let mut collector = AnnotationCollector::new(&mut ctx);
+        tree.visit(&mut collector).unwrap();
+
+        let symbols = outline(&collector.collect());
+        let names = |symbols: &[DocumentSymbol]| {
+            symbols.iter().map(|s| format!("{} {}", s.kind, s.name)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&symbols), ["struct S", "contract C", "enum E"]);
+        assert_eq!(names(&symbols[0].children), ["field a"]);
+        assert_eq!(
+            names(&symbols[1].children),
+            ["constant N", "constructor constructor", "function f"]
+        );
+        assert_eq!(names(&symbols[2].children), ["enum-member A", "enum-member B"]);
+
+        let file = File::new("test.sol".into(), src, 0, None);
+        let json = to_json(&symbols, &file).to_string();
+        assert!(json.starts_with(
+            r#"[{"name":"S","kind":"struct","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":20}},"selectionRange":{"start":{"line":0,"character":7}"#
+        ));
+    }
+}
@@ -72,23 +72,9 @@ impl Loc {
         }
     }
 
-    /// Returns this location's file number.
-    ///
-    /// # Panics
-    ///
-    /// If this location is not a file.
-    #[track_caller]
-    #[inline]
-    pub fn no(&self) -> usize {
-        match self {
-            Loc::File(no, _, _) => *no,
-            _ => not_a_file(),
-        }
-    }
-
     /// Returns this location's file number if it is a file, otherwise `None`.
     #[inline]
-    pub fn try_no(&self) -> Option<usize> {
+    pub fn no(&self) -> Option<usize> {
         match self {
             Loc::File(no, _, _) => Some(*no),
             _ => None,
@@ -220,30 +206,25 @@ impl Loc {
         }
     }
 
-    /// Returns this location's range.
-    ///
-    /// # Panics
-    ///
-    /// If this location is not a file.
-    #[track_caller]
+    /// Returns this location's range if it is a file, otherwise `None`.
     #[inline]
-    pub fn range(self) -> std::ops::Range<usize> {
+    pub fn range(self) -> Option<std::ops::Range<usize>> {
         match self {
-            Self::File(_, start, end) => start..end,
-            _ => not_a_file(),
+            Self::File(_, start, end) => Some(start..end),
+            _ => None,
         }
     }
 
-    /// Performs the union of two locations
-    pub fn union(&mut self, other: &Self) {
+    /// Returns the union of two locations if they are in the same file,
+    /// otherwise `None`.
+    pub fn union(&self, other: &Self) -> Option<Self> {
         match (self, other) {
-            (Self::File(r_file, r_start, r_end), Self::File(l_file, l_start, l_end)) => {
-                assert_eq!(r_file, l_file, "cannot perform union in different files");
-                *r_start = std::cmp::min(*r_start, *l_start);
-                *r_end = std::cmp::max(*r_end, *l_end);
+            (Self::File(file, start, end), Self::File(other_file, other_start, other_end))
+                if file == other_file =>
+            {
+                Some(Self::File(*file, *start.min(other_start), *end.max(other_end)))
             }
-
-            _ => unimplemented!("cannot perform union in non File Loc"),
+            _ => None,
         }
     }
 }
@@ -609,7 +590,8 @@ pub enum VersionComparator {
     Plain {
         /// The code location.
         loc: Loc,
-        /// List of versions: major, minor, patch. minor and patch are optional
+        /// List of versions: major, minor, patch. minor and patch are optional,
+        /// a pre-release or build metadata is kept on the last one
         version: Vec<String>,
     },
     /// =0.5.16
@@ -0,0 +1,1455 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion of the compact AST JSON of solc (`solc --ast-compact-json`) into
+//! a parse tree, for sources which were preprocessed by other tools.
+//!
+//! The locations of the nodes are taken from their `src` members, so they
+//! point into the source the AST was produced from. The type annotations of
+//! solc are dropped, the tree is resolved like any other. Inline assembly is
+//! not converted: its Yul AST has a shape of its own.
+
+use crate::{
+    diagnostics::Diagnostic,
+    helpers::{
+        json::Json,
+        num::{parse_number, split_version},
+        CodeLocation,
+    },
+    parser::ast::*,
+};
+
+/// Convert the compact AST JSON of a source unit into a parse tree with the
+/// file number `no`. The text may start with the header solc prints before
+/// the JSON, or hold the unit under an `ast` member as in the standard JSON
+/// output.
+pub fn import(json: &str, no: usize) -> Result<SourceUnit, Vec<Diagnostic>> {
+    let start = json.find('{').unwrap_or(0);
+    let root = Json::parse(&json[start..]).map_err(|(offset, message)| {
+        let offset = start + offset;
+        vec![Diagnostic::error(Loc::File(no, offset, offset), format!("invalid JSON: {message}"))]
+    })?;
+
+    let unit = root.get("ast").unwrap_or(&root);
+    let mut importer = Importer { no, diagnostics: Vec::new() };
+
+    if node_type(unit) != "SourceUnit" {
+        importer.error(unit, "expected a solc AST of a 'SourceUnit'");
+        return Err(importer.diagnostics);
+    }
+
+    match importer.list(nodes(unit, "nodes"), Importer::source_unit_part) {
+        Ok(parts) => Ok(SourceUnit(parts)),
+        Err(()) => Err(importer.diagnostics),
+    }
+}
+
+fn node_type(node: &Json) -> &str {
+    node.get("nodeType").and_then(Json::as_str).unwrap_or_default()
+}
+
+fn string<'a>(node: &'a Json, key: &str) -> &'a str {
+    node.get(key).and_then(Json::as_str).unwrap_or_default()
+}
+
+fn flag(node: &Json, key: &str) -> bool {
+    node.get(key).and_then(Json::as_bool).unwrap_or_default()
+}
+
+fn nodes<'a>(node: &'a Json, key: &str) -> &'a [Json] {
+    node.get(key).and_then(Json::as_array).unwrap_or_default()
+}
+
+/// A member which may be missing or `null`
+fn optional<'a>(node: &'a Json, key: &str) -> Option<&'a Json> {
+    node.get(key).filter(|value| !value.is_null())
+}
+
+struct Importer {
+    no: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Importer {
+    fn error(&mut self, node: &Json, message: impl Into<String>) {
+        let loc = self.loc(node);
+        self.diagnostics.push(Diagnostic::error(loc, message));
+    }
+
+    fn unsupported<T>(&mut self, node: &Json) -> Result<T, ()> {
+        let message = format!("unsupported solc AST node '{}'", node_type(node));
+        self.error(node, message);
+        Err(())
+    }
+
+    /// A member which the node must have
+    fn member<'a>(&mut self, node: &'a Json, key: &str) -> Result<&'a Json, ()> {
+        match optional(node, key) {
+            Some(value) => Ok(value),
+            None => {
+                let message = format!("solc AST node '{}' has no '{key}'", node_type(node));
+                self.error(node, message);
+                Err(())
+            }
+        }
+    }
+
+    /// Convert a member which the node must have
+    fn child<T>(
+        &mut self,
+        node: &Json,
+        key: &str,
+        convert: impl FnOnce(&mut Self, &Json) -> Result<T, ()>,
+    ) -> Result<T, ()> {
+        let child = self.member(node, key)?;
+        convert(self, child)
+    }
+
+    /// Convert all the nodes, so that the errors of all of them are reported
+    fn list<T>(
+        &mut self,
+        nodes: &[Json],
+        mut convert: impl FnMut(&mut Self, &Json) -> Result<T, ()>,
+    ) -> Result<Vec<T>, ()> {
+        let converted = nodes.iter().map(|node| convert(self, node)).collect::<Vec<_>>();
+        converted.into_iter().collect()
+    }
+
+    /// The location of a `start:length:file` source range
+    fn src(&self, src: &str) -> Option<Loc> {
+        let mut parts = src.split(':');
+        let start = parts.next()?.parse::<usize>().ok()?;
+        let length = parts.next()?.parse::<usize>().ok()?;
+        Some(Loc::File(self.no, start, start + length))
+    }
+
+    fn loc(&self, node: &Json) -> Loc {
+        self.src(string(node, "src")).unwrap_or(Loc::File(self.no, 0, 0))
+    }
+
+    /// The name of a declaration, located at its `nameLocation` if solc gives
+    /// one
+    fn name(&self, node: &Json) -> Option<Identifier> {
+        let name = string(node, "name");
+        if name.is_empty() {
+            return None;
+        }
+
+        let loc = self.src(string(node, "nameLocation")).unwrap_or_else(|| self.loc(node));
+        Some(Identifier { loc, name: name.to_string() })
+    }
+
+    /// A dotted name, like that of an `IdentifierPath` or a
+    /// `UserDefinedTypeName`
+    fn identifier_path(&mut self, node: &Json) -> Result<IdentifierPath, ()> {
+        let node = optional(node, "pathNode").unwrap_or(node);
+        let name = string(node, "name");
+        if name.is_empty() {
+            return self.unsupported(node);
+        }
+
+        let loc = self.loc(node);
+        let mut offset = loc.start();
+        let identifiers = name
+            .split('.')
+            .map(|name| {
+                let identifier = Identifier {
+                    loc: Loc::File(self.no, offset, offset + name.len()),
+                    name: name.to_string(),
+                };
+                offset += name.len() + 1;
+                identifier
+            })
+            .collect();
+
+        Ok(IdentifierPath { loc, identifiers })
+    }
+
+    fn source_unit_part(&mut self, node: &Json) -> Result<SourceUnitPart, ()> {
+        Ok(match node_type(node) {
+            "PragmaDirective" => SourceUnitPart::PragmaDirective(Box::new(self.pragma(node)?)),
+            "ImportDirective" => SourceUnitPart::ImportDirective(self.import(node)?),
+            "ContractDefinition" => {
+                SourceUnitPart::ContractDefinition(Box::new(self.contract(node)?))
+            }
+            "StructDefinition" => SourceUnitPart::StructDefinition(Box::new(self.structure(node)?)),
+            "EnumDefinition" => SourceUnitPart::EnumDefinition(Box::new(self.enumeration(node))),
+            "EventDefinition" => SourceUnitPart::EventDefinition(Box::new(self.event(node)?)),
+            "ErrorDefinition" => SourceUnitPart::ErrorDefinition(Box::new(self.error_def(node)?)),
+            "FunctionDefinition" => {
+                SourceUnitPart::FunctionDefinition(Box::new(self.function(node)?))
+            }
+            "VariableDeclaration" => {
+                SourceUnitPart::VariableDefinition(Box::new(self.variable_definition(node)?))
+            }
+            "UserDefinedValueTypeDefinition" => {
+                SourceUnitPart::TypeDefinition(Box::new(self.type_definition(node)?))
+            }
+            "UsingForDirective" => SourceUnitPart::Using(Box::new(self.using(node)?)),
+            _ => return self.unsupported(node),
+        })
+    }
+
+    fn contract_part(&mut self, node: &Json) -> Result<ContractPart, ()> {
+        Ok(match node_type(node) {
+            "StructDefinition" => ContractPart::StructDefinition(Box::new(self.structure(node)?)),
+            "EnumDefinition" => ContractPart::EnumDefinition(Box::new(self.enumeration(node))),
+            "EventDefinition" => ContractPart::EventDefinition(Box::new(self.event(node)?)),
+            "ErrorDefinition" => ContractPart::ErrorDefinition(Box::new(self.error_def(node)?)),
+            "FunctionDefinition" | "ModifierDefinition" => {
+                ContractPart::FunctionDefinition(Box::new(self.function(node)?))
+            }
+            "VariableDeclaration" => {
+                ContractPart::VariableDefinition(Box::new(self.variable_definition(node)?))
+            }
+            "UserDefinedValueTypeDefinition" => {
+                ContractPart::TypeDefinition(Box::new(self.type_definition(node)?))
+            }
+            "UsingForDirective" => ContractPart::Using(Box::new(self.using(node)?)),
+            _ => return self.unsupported(node),
+        })
+    }
+
+    /// solc splits the version of a pragma into tokens, `^0.8.0` being `^`,
+    /// `0.8` and `.0`
+    fn pragma(&mut self, node: &Json) -> Result<PragmaDirective, ()> {
+        let loc = self.loc(node);
+        let literals = nodes(node, "literals").iter().filter_map(Json::as_str).collect::<Vec<_>>();
+        let Some((name, rest)) = literals.split_first() else {
+            return self.unsupported(node);
+        };
+        let name = Identifier { loc, name: name.to_string() };
+
+        if name.name != "solidity" {
+            let value = rest.first().map(|value| Identifier { loc, name: value.to_string() });
+            return Ok(PragmaDirective::Identifier(loc, Some(name), value));
+        }
+
+        let mut words: Vec<String> = Vec::new();
+        for literal in rest {
+            match words.last_mut() {
+                Some(word) if literal.starts_with('.') || word.ends_with('.') => {
+                    word.push_str(literal)
+                }
+                _ => words.push(literal.to_string()),
+            }
+        }
+
+        match version_comparators(loc, &words) {
+            Some(comparators) => Ok(PragmaDirective::Version(loc, name, comparators)),
+            None => {
+                self.error(node, format!("invalid version '{}' in pragma", rest.join(" ")));
+                Err(())
+            }
+        }
+    }
+
+    fn import(&mut self, node: &Json) -> Result<Import, ()> {
+        let loc = self.loc(node);
+        let path = ImportPath::Filename(StringLiteral {
+            loc,
+            unicode: false,
+            string: string(node, "file").to_string(),
+        });
+
+        let alias = string(node, "unitAlias");
+        if !alias.is_empty() {
+            return Ok(Import::GlobalSymbol(path, Identifier { loc, name: alias.to_string() }, loc));
+        }
+
+        let symbols = nodes(node, "symbolAliases");
+        if symbols.is_empty() {
+            return Ok(Import::Plain(path, loc));
+        }
+
+        let symbols = self.list(symbols, |importer, symbol| {
+            let foreign = importer.member(symbol, "foreign")?;
+            let foreign =
+                Identifier { loc: importer.loc(foreign), name: string(foreign, "name").into() };
+            let local = optional(symbol, "local").and_then(Json::as_str).map(|name| Identifier {
+                loc: importer.src(string(symbol, "nameLocation")).unwrap_or(foreign.loc),
+                name: name.to_string(),
+            });
+            Ok((foreign, local))
+        })?;
+
+        Ok(Import::Rename(path, symbols, loc))
+    }
+
+    fn contract(&mut self, node: &Json) -> Result<ContractDefinition, ()> {
+        let loc = self.loc(node);
+        let ty = match string(node, "contractKind") {
+            "interface" => ContractTy::Interface(loc),
+            "library" => ContractTy::Library(loc),
+            _ if flag(node, "abstract") => ContractTy::Abstract(loc),
+            _ => ContractTy::Contract(loc),
+        };
+
+        let base = self.list(nodes(node, "baseContracts"), |importer, base| {
+            let name = importer.member(base, "baseName")?;
+            Ok(Base {
+                loc: importer.loc(base),
+                name: importer.identifier_path(name)?,
+                args: importer.arguments(base)?,
+            })
+        });
+        let parts = self.list(nodes(node, "nodes"), Importer::contract_part);
+
+        Ok(ContractDefinition { loc, ty, name: self.name(node), base: base?, parts: parts? })
+    }
+
+    /// The arguments of an inheritance specifier or a modifier invocation,
+    /// `None` when there are no parentheses
+    fn arguments(&mut self, node: &Json) -> Result<Option<Vec<Expression>>, ()> {
+        match optional(node, "arguments") {
+            Some(args) => {
+                Ok(Some(self.list(args.as_array().unwrap_or_default(), Self::expression)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn structure(&mut self, node: &Json) -> Result<StructDefinition, ()> {
+        Ok(StructDefinition {
+            loc: self.loc(node),
+            name: self.name(node),
+            fields: self.list(nodes(node, "members"), Self::variable_declaration)?,
+        })
+    }
+
+    fn enumeration(&mut self, node: &Json) -> EnumDefinition {
+        EnumDefinition {
+            loc: self.loc(node),
+            name: self.name(node),
+            values: nodes(node, "members").iter().map(|value| self.name(value)).collect(),
+        }
+    }
+
+    fn event(&mut self, node: &Json) -> Result<EventDefinition, ()> {
+        let params = self.member(node, "parameters")?;
+        let fields = self.list(nodes(params, "parameters"), |importer, param| {
+            Ok(EventParameter {
+                loc: importer.loc(param),
+                ty: importer.child(param, "typeName", Self::type_name)?,
+                indexed: flag(param, "indexed"),
+                name: importer.name(param),
+            })
+        })?;
+
+        Ok(EventDefinition {
+            loc: self.loc(node),
+            name: self.name(node),
+            fields,
+            anonymous: flag(node, "anonymous"),
+        })
+    }
+
+    fn error_def(&mut self, node: &Json) -> Result<ErrorDefinition, ()> {
+        let loc = self.loc(node);
+        let params = self.member(node, "parameters")?;
+        let fields = self.list(nodes(params, "parameters"), |importer, param| {
+            Ok(ErrorParameter {
+                loc: importer.loc(param),
+                ty: importer.child(param, "typeName", Self::type_name)?,
+                name: importer.name(param),
+            })
+        })?;
+
+        Ok(ErrorDefinition {
+            loc,
+            keyword: Expression::Variable(Identifier { loc, name: "error".to_string() }),
+            name: self.name(node),
+            fields,
+        })
+    }
+
+    fn type_definition(&mut self, node: &Json) -> Result<TypeDefinition, ()> {
+        let Some(name) = self.name(node) else {
+            return self.unsupported(node);
+        };
+
+        Ok(TypeDefinition {
+            loc: self.loc(node),
+            name,
+            ty: self.child(node, "underlyingType", Self::type_name)?,
+        })
+    }
+
+    fn using(&mut self, node: &Json) -> Result<Using, ()> {
+        let loc = self.loc(node);
+        let list = match optional(node, "libraryName") {
+            Some(library) => UsingList::Library(self.identifier_path(library)?),
+            None => {
+                UsingList::Functions(self.list(nodes(node, "functionList"), |importer, item| {
+                    let function = match optional(item, "function") {
+                        Some(function) => function,
+                        None => importer.member(item, "definition")?,
+                    };
+                    Ok(UsingFunction {
+                        loc: importer.loc(function),
+                        path: importer.identifier_path(function)?,
+                        oper: user_defined_operator(string(item, "operator")),
+                    })
+                })?)
+            }
+        };
+        let ty = match optional(node, "typeName") {
+            Some(ty) => Some(self.type_name(ty)?),
+            None => None,
+        };
+        let global = flag(node, "global").then(|| Identifier { loc, name: "global".to_string() });
+
+        Ok(Using { loc, list, ty, global })
+    }
+
+    fn variable_definition(&mut self, node: &Json) -> Result<VariableDefinition, ()> {
+        let loc = self.loc(node);
+        let mut attrs = Vec::new();
+
+        // Leave out the default visibility, as the source most likely did
+        match visibility(string(node, "visibility"), loc) {
+            Some(Visibility::Internal(_)) | None => (),
+            Some(visibility) => attrs.push(VariableAttribute::Visibility(visibility)),
+        }
+        match string(node, "mutability") {
+            "constant" => attrs.push(VariableAttribute::Constant(loc)),
+            "immutable" => attrs.push(VariableAttribute::Immutable(loc)),
+            _ if flag(node, "constant") => attrs.push(VariableAttribute::Constant(loc)),
+            _ => (),
+        }
+        if let Some(overrides) = optional(node, "overrides") {
+            attrs
+                .push(VariableAttribute::Override(self.loc(overrides), self.overrides(overrides)?));
+        }
+
+        let initializer = match optional(node, "value") {
+            Some(value) => Some(self.expression(value)?),
+            None => None,
+        };
+
+        Ok(VariableDefinition {
+            loc,
+            ty: self.child(node, "typeName", Self::type_name)?,
+            attrs,
+            name: self.name(node),
+            initializer,
+        })
+    }
+
+    fn overrides(&mut self, node: &Json) -> Result<Vec<IdentifierPath>, ()> {
+        self.list(nodes(node, "overrides"), Self::identifier_path)
+    }
+
+    fn variable_declaration(&mut self, node: &Json) -> Result<VariableDeclaration, ()> {
+        Ok(VariableDeclaration {
+            loc: self.loc(node),
+            ty: self.child(node, "typeName", Self::type_name)?,
+            storage: self.storage(node),
+            name: self.name(node),
+        })
+    }
+
+    fn storage(&self, node: &Json) -> Option<StorageLocation> {
+        let loc = self.loc(node);
+        match string(node, "storageLocation") {
+            "memory" => Some(StorageLocation::Memory(loc)),
+            "storage" => Some(StorageLocation::Storage(loc)),
+            "calldata" => Some(StorageLocation::Calldata(loc)),
+            _ => None,
+        }
+    }
+
+    fn parameter_list(&mut self, node: &Json) -> Result<ParameterList, ()> {
+        self.list(nodes(node, "parameters"), |importer, param| {
+            let loc = importer.loc(param);
+            let parameter = Parameter {
+                loc,
+                annotation: None,
+                ty: importer.child(param, "typeName", Self::type_name)?,
+                storage: importer.storage(param),
+                name: importer.name(param),
+            };
+            Ok((loc, Some(parameter)))
+        })
+    }
+
+    /// A function, constructor, fallback, receive or modifier definition
+    fn function(&mut self, node: &Json) -> Result<FunctionDefinition, ()> {
+        let loc = self.loc(node);
+        let kind = string(node, "kind");
+        let ty = match (node_type(node), kind) {
+            ("ModifierDefinition", _) => FunctionTy::Modifier,
+            (_, "constructor") => FunctionTy::Constructor,
+            (_, "fallback") => FunctionTy::Fallback,
+            (_, "receive") => FunctionTy::Receive,
+            _ => FunctionTy::Function,
+        };
+        let name = match ty {
+            FunctionTy::Function | FunctionTy::Modifier => self.name(node),
+            _ => None,
+        };
+
+        let mut attributes = Vec::new();
+
+        // Constructors, free functions and modifiers have no visibility, solc
+        // gives them one anyway
+        if !matches!(ty, FunctionTy::Constructor | FunctionTy::Modifier) && kind != "freeFunction" {
+            if let Some(visibility) = visibility(string(node, "visibility"), loc) {
+                attributes.push(FunctionAttribute::Visibility(visibility));
+            }
+        }
+        if let Some(mutability) = mutability(string(node, "stateMutability"), loc) {
+            attributes.push(FunctionAttribute::Mutability(mutability));
+        }
+        if flag(node, "virtual") {
+            attributes.push(FunctionAttribute::Virtual(loc));
+        }
+        if let Some(overrides) = optional(node, "overrides") {
+            attributes
+                .push(FunctionAttribute::Override(self.loc(overrides), self.overrides(overrides)?));
+        }
+        let modifiers = self.list(nodes(node, "modifiers"), |importer, modifier| {
+            let loc = importer.loc(modifier);
+            let base = Base {
+                loc,
+                name: importer.child(modifier, "modifierName", Self::identifier_path)?,
+                args: importer.arguments(modifier)?,
+            };
+            Ok(FunctionAttribute::BaseOrModifier(loc, base))
+        })?;
+        attributes.extend(modifiers);
+
+        let params = self.child(node, "parameters", Self::parameter_list)?;
+        let returns = match optional(node, "returnParameters") {
+            Some(returns) => self.parameter_list(returns)?,
+            None => Vec::new(),
+        };
+        let body = match optional(node, "body") {
+            Some(body) => Some(self.statement(body)?),
+            None => None,
+        };
+
+        Ok(FunctionDefinition {
+            loc_prototype: loc,
+            loc,
+            ty,
+            name_loc: name.as_ref().map_or(loc, |name| name.loc),
+            name,
+            params,
+            attributes,
+            return_not_returns: None,
+            returns,
+            body,
+        })
+    }
+
+    /// A type name, which the parse tree holds as an expression
+    fn type_name(&mut self, node: &Json) -> Result<Expression, ()> {
+        let loc = self.loc(node);
+
+        match node_type(node) {
+            "ElementaryTypeName" => {
+                let payable = string(node, "stateMutability") == "payable";
+                let ty = self.elementary_type(node, payable)?;
+                Ok(Expression::Type(loc, ty))
+            }
+            "UserDefinedTypeName" => Ok(path_expression(self.identifier_path(node)?)),
+            "ArrayTypeName" => {
+                let elem = self.child(node, "baseType", Self::type_name)?;
+                let length = match optional(node, "length") {
+                    Some(length) => Some(Box::new(self.expression(length)?)),
+                    None => None,
+                };
+                Ok(Expression::ArraySubscript(loc, Box::new(elem), length))
+            }
+            "Mapping" => {
+                let key = self.child(node, "keyType", Self::type_name)?;
+                let value = self.child(node, "valueType", Self::type_name)?;
+                let named = |key: &str, location: &str| {
+                    let name = string(node, key);
+                    (!name.is_empty()).then(|| Identifier {
+                        loc: self.src(string(node, location)).unwrap_or(loc),
+                        name: name.to_string(),
+                    })
+                };
+
+                Ok(Expression::Type(
+                    loc,
+                    Type::Mapping {
+                        loc,
+                        key: Box::new(key),
+                        key_name: named("keyName", "keyNameLocation"),
+                        value: Box::new(value),
+                        value_name: named("valueName", "valueNameLocation"),
+                    },
+                ))
+            }
+            "FunctionTypeName" => {
+                let params = self.child(node, "parameterTypes", Self::parameter_list)?;
+                let returns = match optional(node, "returnParameterTypes") {
+                    Some(returns) => self.parameter_list(returns)?,
+                    None => Vec::new(),
+                };
+
+                let mut attributes = Vec::new();
+                if let Some(Visibility::External(loc)) = visibility(string(node, "visibility"), loc)
+                {
+                    attributes.push(FunctionAttribute::Visibility(Visibility::External(loc)));
+                }
+                if let Some(mutability) = mutability(string(node, "stateMutability"), loc) {
+                    attributes.push(FunctionAttribute::Mutability(mutability));
+                }
+
+                let returns = (!returns.is_empty()).then(|| (returns, Vec::new()));
+                Ok(Expression::Type(loc, Type::Function { params, attributes, returns }))
+            }
+            _ => self.unsupported(node),
+        }
+    }
+
+    fn elementary_type(&mut self, node: &Json, payable: bool) -> Result<Type, ()> {
+        let name = string(node, "name");
+        let ty = match name {
+            "address" if payable => Some(Type::AddressPayable),
+            "address" => Some(Type::Address),
+            "bool" => Some(Type::Bool),
+            "string" => Some(Type::String),
+            "bytes" => Some(Type::DynamicBytes),
+            "byte" => Some(Type::Bytes(1)),
+            _ => None,
+        };
+
+        let sized = |prefix: &str, default: Option<u16>| match name.strip_prefix(prefix)? {
+            "" => default,
+            bits => bits.parse::<u16>().ok(),
+        };
+        let ty = ty
+            .or_else(|| sized("uint", Some(256)).map(Type::Uint))
+            .or_else(|| sized("int", Some(256)).map(Type::Int))
+            .or_else(|| sized("bytes", None).and_then(|n| u8::try_from(n).ok()).map(Type::Bytes));
+
+        match ty {
+            Some(ty) => Ok(ty),
+            None => {
+                self.error(node, format!("unsupported elementary type '{name}'"));
+                Err(())
+            }
+        }
+    }
+
+    fn statement(&mut self, node: &Json) -> Result<Statement, ()> {
+        let loc = self.loc(node);
+
+        Ok(match node_type(node) {
+            "Block" | "UncheckedBlock" => Statement::Block {
+                loc,
+                unchecked: node_type(node) == "UncheckedBlock",
+                statements: self.list(nodes(node, "statements"), Self::statement)?,
+            },
+            "PlaceholderStatement" => Statement::Expression(
+                loc,
+                Expression::Variable(Identifier { loc, name: "_".into() }),
+            ),
+            "ExpressionStatement" => {
+                Statement::Expression(loc, self.child(node, "expression", Self::expression)?)
+            }
+            "VariableDeclarationStatement" => self.variable_declaration_statement(node)?,
+            "IfStatement" => {
+                let condition = self.child(node, "condition", Self::expression)?;
+                let then = self.child(node, "trueBody", Self::statement)?;
+                let otherwise = match optional(node, "falseBody") {
+                    Some(otherwise) => Some(Box::new(self.statement(otherwise)?)),
+                    None => None,
+                };
+                Statement::If(loc, condition, Box::new(then), otherwise)
+            }
+            "ForStatement" => {
+                let init = match optional(node, "initializationExpression") {
+                    Some(init) => Some(Box::new(self.statement(init)?)),
+                    None => None,
+                };
+                let condition = match optional(node, "condition") {
+                    Some(condition) => Some(Box::new(self.expression(condition)?)),
+                    None => None,
+                };
+                // The loop expression is an expression statement
+                let next = match optional(node, "loopExpression") {
+                    Some(next) => {
+                        let next = optional(next, "expression").unwrap_or(next);
+                        Some(Box::new(self.expression(next)?))
+                    }
+                    None => None,
+                };
+                let body = self.child(node, "body", Self::statement)?;
+                Statement::For(loc, init, condition, next, Some(Box::new(body)))
+            }
+            "WhileStatement" => {
+                let condition = self.child(node, "condition", Self::expression)?;
+                Statement::While(
+                    loc,
+                    condition,
+                    Box::new(self.child(node, "body", Self::statement)?),
+                )
+            }
+            "DoWhileStatement" => {
+                let body = self.child(node, "body", Self::statement)?;
+                Statement::DoWhile(
+                    loc,
+                    Box::new(body),
+                    self.child(node, "condition", Self::expression)?,
+                )
+            }
+            "Continue" => Statement::Continue(loc),
+            "Break" => Statement::Break(loc),
+            "Return" => match optional(node, "expression") {
+                Some(expr) => Statement::Return(loc, Some(self.expression(expr)?)),
+                None => Statement::Return(loc, None),
+            },
+            "EmitStatement" => {
+                Statement::Emit(loc, self.child(node, "eventCall", Self::expression)?)
+            }
+            "RevertStatement" => {
+                let call = self.member(node, "errorCall")?;
+                let error = self.child(call, "expression", Self::expression)?;
+                let Some(path) = expression_path(&error) else {
+                    return self.unsupported(call);
+                };
+
+                let names = nodes(call, "names");
+                if names.is_empty() {
+                    Statement::Revert(
+                        loc,
+                        Some(path),
+                        self.list(nodes(call, "arguments"), Self::expression)?,
+                    )
+                } else {
+                    Statement::RevertNamedArgs(
+                        loc,
+                        Some(path),
+                        self.named_arguments(call, "arguments")?,
+                    )
+                }
+            }
+            "TryStatement" => self.try_statement(node)?,
+            _ => return self.unsupported(node),
+        })
+    }
+
+    /// A single declaration is a variable definition, a tuple of them is an
+    /// assignment to a list, as in the grammar
+    fn variable_declaration_statement(&mut self, node: &Json) -> Result<Statement, ()> {
+        let loc = self.loc(node);
+        let declarations = nodes(node, "declarations");
+        let value = match optional(node, "initialValue") {
+            Some(value) => Some(self.expression(value)?),
+            None => None,
+        };
+
+        if let [declaration] = declarations {
+            if !declaration.is_null() {
+                return Ok(Statement::VariableDefinition(
+                    loc,
+                    self.variable_declaration(declaration)?,
+                    value,
+                ));
+            }
+        }
+
+        let Some(value) = value else {
+            return self.unsupported(node);
+        };
+        let list = self.list(declarations, |importer, declaration| {
+            if declaration.is_null() {
+                return Ok((loc, None));
+            }
+            let declaration = importer.variable_declaration(declaration)?;
+            let parameter = Parameter {
+                loc: declaration.loc,
+                annotation: None,
+                ty: declaration.ty,
+                storage: declaration.storage,
+                name: declaration.name,
+            };
+            Ok((parameter.loc, Some(parameter)))
+        })?;
+
+        Ok(Statement::Expression(
+            loc,
+            Expression::Assign(loc, Box::new(Expression::List(loc, list)), Box::new(value)),
+        ))
+    }
+
+    /// The first clause of solc is the success block, which has the returns
+    fn try_statement(&mut self, node: &Json) -> Result<Statement, ()> {
+        let loc = self.loc(node);
+        let call = self.child(node, "externalCall", Self::expression)?;
+        let Some((success, clauses)) = nodes(node, "clauses").split_first() else {
+            return self.unsupported(node);
+        };
+
+        let block = self.child(success, "block", Self::statement)?;
+        let (call, returns) = match optional(success, "parameters") {
+            Some(params) => (call, Some((self.parameter_list(params)?, Box::new(block)))),
+            // Without returns, the grammar takes the block as that of the call
+            None => (Expression::FunctionCallBlock(loc, Box::new(call), Box::new(block)), None),
+        };
+
+        let catches = self.list(clauses, |importer, clause| {
+            let loc = importer.loc(clause);
+            let block = importer.child(clause, "block", Self::statement)?;
+            let param = match optional(clause, "parameters") {
+                Some(params) => {
+                    importer.parameter_list(params)?.into_iter().next().and_then(|(_, p)| p)
+                }
+                None => None,
+            };
+
+            Ok(match (string(clause, "errorName"), param) {
+                ("", param) => CatchClause::Simple(loc, param, block),
+                (name, Some(param)) => {
+                    let id = Identifier { loc, name: name.to_string() };
+                    CatchClause::Named(loc, id, param, block)
+                }
+                (_, None) => return importer.unsupported(clause),
+            })
+        })?;
+
+        Ok(Statement::Try(loc, call, returns, catches))
+    }
+
+    fn named_arguments(&mut self, node: &Json, key: &str) -> Result<Vec<NamedArgument>, ()> {
+        let names = nodes(node, "names");
+        let values = nodes(node, key);
+        let locations = nodes(node, "nameLocations");
+
+        let exprs = self.list(values, Self::expression)?;
+        Ok(names
+            .iter()
+            .zip(exprs)
+            .enumerate()
+            .map(|(i, (name, expr))| {
+                let name_loc = locations
+                    .get(i)
+                    .and_then(Json::as_str)
+                    .and_then(|src| self.src(src))
+                    .unwrap_or_else(|| expr.loc());
+                NamedArgument {
+                    loc: Loc::File(self.no, name_loc.start(), expr.loc().end()),
+                    name: Identifier {
+                        loc: name_loc,
+                        name: name.as_str().unwrap_or_default().into(),
+                    },
+                    expr,
+                }
+            })
+            .collect())
+    }
+
+    fn expression(&mut self, node: &Json) -> Result<Expression, ()> {
+        let loc = self.loc(node);
+
+        Ok(match node_type(node) {
+            "Literal" => self.literal(node)?,
+            "Identifier" => {
+                Expression::Variable(Identifier { loc, name: string(node, "name").to_string() })
+            }
+            "MemberAccess" => {
+                let expr = self.child(node, "expression", Self::expression)?;
+                let member = Identifier {
+                    loc: self.src(string(node, "memberLocation")).unwrap_or(loc),
+                    name: string(node, "memberName").to_string(),
+                };
+                Expression::MemberAccess(loc, Box::new(expr), member)
+            }
+            "IndexAccess" => {
+                let array = self.child(node, "baseExpression", Self::expression)?;
+                let index = match optional(node, "indexExpression") {
+                    Some(index) => Some(Box::new(self.expression(index)?)),
+                    None => None,
+                };
+                Expression::ArraySubscript(loc, Box::new(array), index)
+            }
+            "IndexRangeAccess" => {
+                let array = self.child(node, "baseExpression", Self::expression)?;
+                let mut bound = |key: &str| match optional(node, key) {
+                    Some(bound) => self.expression(bound).map(|bound| Some(Box::new(bound))),
+                    None => Ok(None),
+                };
+                let start = bound("startExpression")?;
+                let end = bound("endExpression")?;
+                Expression::ArraySlice(loc, Box::new(array), start, end)
+            }
+            "FunctionCall" => {
+                let callee = self.child(node, "expression", Self::expression)?;
+                let call = |callee| match nodes(node, "names") {
+                    [] => self
+                        .list(nodes(node, "arguments"), Self::expression)
+                        .map(|args| Expression::FunctionCall(loc, Box::new(callee), args)),
+                    _ => self
+                        .named_arguments(node, "arguments")
+                        .map(|args| Expression::NamedFunctionCall(loc, Box::new(callee), args)),
+                };
+                lift_new(loc, callee, call)?
+            }
+            "FunctionCallOptions" => {
+                let callee = self.child(node, "expression", Self::expression)?;
+                let args = self.named_arguments(node, "options")?;
+                lift_new(loc, callee, |callee| {
+                    Ok(Expression::FunctionCallBlock(
+                        loc,
+                        Box::new(callee),
+                        Box::new(Statement::Args(loc, args)),
+                    ))
+                })?
+            }
+            "BinaryOperation" => {
+                let left = Box::new(self.child(node, "leftExpression", Self::expression)?);
+                let right = Box::new(self.child(node, "rightExpression", Self::expression)?);
+                let Some(operator) = binary_operator(string(node, "operator")) else {
+                    return self.unsupported(node);
+                };
+                operator(loc, left, right)
+            }
+            "Assignment" => {
+                let left = Box::new(self.child(node, "leftHandSide", Self::expression)?);
+                let right = Box::new(self.child(node, "rightHandSide", Self::expression)?);
+                let Some(operator) = assignment_operator(string(node, "operator")) else {
+                    return self.unsupported(node);
+                };
+                operator(loc, left, right)
+            }
+            "UnaryOperation" => {
+                let expr = Box::new(self.child(node, "subExpression", Self::expression)?);
+                match (string(node, "operator"), flag(node, "prefix")) {
+                    ("!", _) => Expression::Not(loc, expr),
+                    ("~", _) => Expression::BitwiseNot(loc, expr),
+                    ("-", _) => Expression::Negate(loc, expr),
+                    ("+", _) => Expression::UnaryPlus(loc, expr),
+                    ("delete", _) => Expression::Delete(loc, expr),
+                    ("++", true) => Expression::PreIncrement(loc, expr),
+                    ("++", false) => Expression::PostIncrement(loc, expr),
+                    ("--", true) => Expression::PreDecrement(loc, expr),
+                    ("--", false) => Expression::PostDecrement(loc, expr),
+                    _ => return self.unsupported(node),
+                }
+            }
+            "Conditional" => Expression::ConditionalOperator(
+                loc,
+                Box::new(self.child(node, "condition", Self::expression)?),
+                Box::new(self.child(node, "trueExpression", Self::expression)?),
+                Box::new(self.child(node, "falseExpression", Self::expression)?),
+            ),
+            "TupleExpression" => {
+                let components = nodes(node, "components");
+                if flag(node, "isInlineArray") {
+                    Expression::ArrayLiteral(loc, self.list(components, Self::expression)?)
+                } else if let [component] = components.iter().as_slice() {
+                    Expression::Parenthesis(loc, Box::new(self.expression(component)?))
+                } else {
+                    let list = self.list(components, |importer, component| {
+                        if component.is_null() {
+                            return Ok((loc, None));
+                        }
+                        let ty = importer.expression(component)?;
+                        let loc = ty.loc();
+                        Ok((
+                            loc,
+                            Some(Parameter {
+                                loc,
+                                annotation: None,
+                                ty,
+                                storage: None,
+                                name: None,
+                            }),
+                        ))
+                    })?;
+                    Expression::List(loc, list)
+                }
+            }
+            "NewExpression" => {
+                Expression::New(loc, Box::new(self.child(node, "typeName", Self::type_name)?))
+            }
+            "ElementaryTypeNameExpression" => {
+                let ty = self.member(node, "typeName")?;
+                // `payable(x)` is a conversion to `address payable`
+                let ty = match self.elementary_type(ty, false)? {
+                    Type::Address if string(ty, "stateMutability") == "payable" => Type::Payable,
+                    ty => ty,
+                };
+                Expression::Type(loc, ty)
+            }
+            _ => return self.unsupported(node),
+        })
+    }
+
+    fn literal(&mut self, node: &Json) -> Result<Expression, ()> {
+        let loc = self.loc(node);
+        let value = optional(node, "value").and_then(Json::as_str);
+        let hex = || vec![HexLiteral { loc, hex: format!("hex\"{}\"", string(node, "hexValue")) }];
+
+        Ok(match (string(node, "kind"), value) {
+            ("bool", Some(value)) => Expression::BoolLiteral(loc, value == "true"),
+            ("number", Some(value)) => {
+                let unit = optional(node, "subdenomination")
+                    .and_then(Json::as_str)
+                    .map(|unit| Identifier { loc, name: unit.to_string() });
+                number_literal(loc, value, unit)
+            }
+            ("string", Some(value)) => Expression::StringLiteral(vec![StringLiteral {
+                loc,
+                unicode: false,
+                string: escape(value, false),
+            }]),
+            ("unicodeString", Some(value)) => Expression::StringLiteral(vec![StringLiteral {
+                loc,
+                unicode: true,
+                string: escape(value, true),
+            }]),
+            // A string which is not valid UTF-8 has no value, only its bytes
+            ("string" | "hexString", _) => Expression::HexLiteral(hex()),
+            _ => return self.unsupported(node),
+        })
+    }
+}
+
+/// The version comparators of a pragma from its words, with the versions
+/// joined up
+fn version_comparators(loc: Loc, words: &[String]) -> Option<Vec<VersionComparator>> {
+    let mut comparators = Vec::new();
+    let mut or = false;
+    let mut words = words.iter().map(String::as_str).peekable();
+
+    while let Some(word) = words.next() {
+        if word == "||" {
+            or = true;
+            continue;
+        }
+
+        let op = match word {
+            "=" => Some(VersionOp::Exact),
+            ">" => Some(VersionOp::Greater),
+            ">=" => Some(VersionOp::GreaterEq),
+            "<" => Some(VersionOp::Less),
+            "<=" => Some(VersionOp::LessEq),
+            "~" => Some(VersionOp::Tilde),
+            "^" => Some(VersionOp::Caret),
+            _ => None,
+        };
+
+        let comparator = match op {
+            Some(op) => {
+                let version = words.next().filter(|version| is_version(version))?;
+                VersionComparator::Operator { loc, op, version: split_version(version) }
+            }
+            None if !is_version(word) => return None,
+            None if words.peek() == Some(&"-") => {
+                words.next();
+                let to = words.next().filter(|version| is_version(version))?;
+                VersionComparator::Range { loc, from: split_version(word), to: split_version(to) }
+            }
+            None => VersionComparator::Plain { loc, version: split_version(word) },
+        };
+
+        if std::mem::take(&mut or) {
+            let left = comparators.pop()?;
+            comparators.push(VersionComparator::Or {
+                loc,
+                left: Box::new(left),
+                right: Box::new(comparator),
+            });
+        } else {
+            comparators.push(comparator);
+        }
+    }
+
+    (!or && !comparators.is_empty()).then_some(comparators)
+}
+
+fn is_version(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn visibility(visibility: &str, loc: Loc) -> Option<Visibility> {
+    match visibility {
+        "external" => Some(Visibility::External(Some(loc))),
+        "public" => Some(Visibility::Public(Some(loc))),
+        "internal" => Some(Visibility::Internal(Some(loc))),
+        "private" => Some(Visibility::Private(Some(loc))),
+        _ => None,
+    }
+}
+
+fn mutability(mutability: &str, loc: Loc) -> Option<Mutability> {
+    match mutability {
+        "pure" => Some(Mutability::Pure(loc)),
+        "view" => Some(Mutability::View(loc)),
+        "payable" => Some(Mutability::Payable(loc)),
+        _ => None,
+    }
+}
+
+/// The operator of a `using {f as -} for T` function. solc does not tell the
+/// unary `-` from the binary one, the arity of the function does.
+fn user_defined_operator(operator: &str) -> Option<UserDefinedOperator> {
+    Some(match operator {
+        "&" => UserDefinedOperator::BitwiseAnd,
+        "~" => UserDefinedOperator::BitwiseNot,
+        "|" => UserDefinedOperator::BitwiseOr,
+        "^" => UserDefinedOperator::BitwiseXor,
+        "+" => UserDefinedOperator::Add,
+        "/" => UserDefinedOperator::Divide,
+        "%" => UserDefinedOperator::Modulo,
+        "*" => UserDefinedOperator::Multiply,
+        "-" => UserDefinedOperator::Subtract,
+        "==" => UserDefinedOperator::Equal,
+        ">" => UserDefinedOperator::More,
+        ">=" => UserDefinedOperator::MoreEqual,
+        "<" => UserDefinedOperator::Less,
+        "<=" => UserDefinedOperator::LessEqual,
+        "!=" => UserDefinedOperator::NotEqual,
+        _ => return None,
+    })
+}
+
+type BinaryExpression = fn(Loc, Box<Expression>, Box<Expression>) -> Expression;
+
+fn binary_operator(operator: &str) -> Option<BinaryExpression> {
+    Some(match operator {
+        "+" => Expression::Add,
+        "-" => Expression::Subtract,
+        "*" => Expression::Multiply,
+        "/" => Expression::Divide,
+        "%" => Expression::Modulo,
+        "**" => Expression::Power,
+        "<<" => Expression::ShiftLeft,
+        ">>" => Expression::ShiftRight,
+        "&" => Expression::BitwiseAnd,
+        "|" => Expression::BitwiseOr,
+        "^" => Expression::BitwiseXor,
+        "<" => Expression::Less,
+        ">" => Expression::More,
+        "<=" => Expression::LessEqual,
+        ">=" => Expression::MoreEqual,
+        "==" => Expression::Equal,
+        "!=" => Expression::NotEqual,
+        "&&" => Expression::And,
+        "||" => Expression::Or,
+        _ => return None,
+    })
+}
+
+fn assignment_operator(operator: &str) -> Option<BinaryExpression> {
+    Some(match operator {
+        "=" => Expression::Assign,
+        "+=" => Expression::AssignAdd,
+        "-=" => Expression::AssignSubtract,
+        "*=" => Expression::AssignMultiply,
+        "/=" => Expression::AssignDivide,
+        "%=" => Expression::AssignModulo,
+        "|=" => Expression::AssignOr,
+        "&=" => Expression::AssignAnd,
+        "^=" => Expression::AssignXor,
+        "<<=" => Expression::AssignShiftLeft,
+        ">>=" => Expression::AssignShiftRight,
+        _ => return None,
+    })
+}
+
+/// The grammar puts `new` around the call, `new C(1)` being `New(C(1))`,
+/// while solc calls the `new C` expression
+fn lift_new(
+    loc: Loc,
+    callee: Expression,
+    call: impl FnOnce(Expression) -> Result<Expression, ()>,
+) -> Result<Expression, ()> {
+    match callee {
+        Expression::New(_, ty) => Ok(Expression::New(loc, Box::new(call(*ty)?))),
+        callee => call(callee),
+    }
+}
+
+/// A type name like `L.S` as the member accesses the grammar gives for it
+fn path_expression(path: IdentifierPath) -> Expression {
+    let mut identifiers = path.identifiers.into_iter();
+    let first = identifiers.next().expect("identifier paths are never empty");
+    let start = first.loc;
+
+    identifiers.fold(Expression::Variable(first), |expr, member| {
+        Expression::MemberAccess(start.union(&member.loc).unwrap_or(start), Box::new(expr), member)
+    })
+}
+
+/// The error of a revert statement as a path, `E` or `L.E`
+fn expression_path(expr: &Expression) -> Option<IdentifierPath> {
+    match expr {
+        Expression::Variable(id) => {
+            Some(IdentifierPath { loc: id.loc, identifiers: vec![id.clone()] })
+        }
+        Expression::MemberAccess(loc, expr, member) => {
+            let mut path = expression_path(expr)?;
+            path.loc = *loc;
+            path.identifiers.push(member.clone());
+            Some(path)
+        }
+        _ => None,
+    }
+}
+
+/// A number literal as solc wrote it, with its underscores and exponent
+fn number_literal(loc: Loc, value: &str, unit: Option<Identifier>) -> Expression {
+    if value.starts_with("0x") || value.starts_with("0X") {
+        if value.len() == 42 {
+            return Expression::AddressLiteral(loc, value.to_string());
+        }
+        return Expression::HexNumberLiteral(loc, value.to_string(), unit);
+    }
+
+    let (mantissa, exp) = parse_number(value);
+    match mantissa.split_once('.') {
+        Some((integer, fraction)) => {
+            Expression::RationalNumberLiteral(loc, integer.into(), fraction.into(), exp, unit)
+        }
+        None => Expression::NumberLiteral(loc, mantissa, exp, unit),
+    }
+}
+
+/// Escape the decoded value of a string literal for printing. A plain string
+/// literal may only hold ASCII, the other bytes are written as escapes.
+fn escape(value: &str, unicode: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c if !c.is_ascii() && !unicode => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    escaped.push_str(&format!("\\x{byte:02x}"));
+                }
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formatter, parser::trivia::Comments};
+
+    /// The source printed from the converted tree
+    fn source(json: &str) -> String {
+        let tree = import(json, 0).unwrap();
+        formatter::format_tree(&tree, &Comments::default(), &Default::default())
+    }
+
+    fn unit(nodes: &str) -> String {
+        format!(r#"{{"nodeType":"SourceUnit","src":"0:0:0","nodes":[{nodes}]}}"#)
+    }
+
+    fn elementary(name: &str) -> String {
+        format!(r#"{{"nodeType":"ElementaryTypeName","src":"0:0:0","name":"{name}"}}"#)
+    }
+
+    fn identifier(name: &str) -> String {
+        format!(r#"{{"nodeType":"Identifier","src":"0:0:0","name":"{name}"}}"#)
+    }
+
+    fn number(value: &str) -> String {
+        format!(r#"{{"nodeType":"Literal","src":"0:0:0","kind":"number","value":"{value}"}}"#)
+    }
+
+    fn parameter(name: &str, ty: &str, storage: &str) -> String {
+        format!(
+            r#"{{"nodeType":"VariableDeclaration","src":"0:0:0","name":"{name}",
+                "typeName":{},"storageLocation":"{storage}"}}"#,
+            elementary(ty)
+        )
+    }
+
+    #[test]
+    fn test_import_contract() {
+        let x = identifier("x");
+        let json = unit(&format!(
+            r#"{{"nodeType":"PragmaDirective","src":"0:23:0","literals":["solidity","^","0.8",".0"]}},
+            {{"nodeType":"ImportDirective","src":"24:30:0","file":"./a.sol","unitAlias":"",
+              "symbolAliases":[{{"foreign":{{"nodeType":"Identifier","src":"32:1:0","name":"A"}},"local":"B"}}]}},
+            {{"nodeType":"ContractDefinition","src":"55:200:0","name":"C","nameLocation":"64:1:0",
+              "contractKind":"contract","abstract":false,
+              "baseContracts":[{{"nodeType":"InheritanceSpecifier","src":"69:1:0",
+                "baseName":{{"nodeType":"IdentifierPath","src":"69:1:0","name":"B"}}}}],
+              "nodes":[
+                {{"nodeType":"VariableDeclaration","src":"80:20:0","name":"total","visibility":"public",
+                  "mutability":"mutable","stateVariable":true,"storageLocation":"default",
+                  "typeName":{}}},
+                {{"nodeType":"FunctionDefinition","src":"110:100:0","name":"add","kind":"function",
+                  "visibility":"external","stateMutability":"nonpayable","virtual":false,"modifiers":[],
+                  "parameters":{{"nodeType":"ParameterList","src":"0:0:0","parameters":[{}]}},
+                  "returnParameters":{{"nodeType":"ParameterList","src":"0:0:0","parameters":[{}]}},
+                  "body":{{"nodeType":"Block","src":"150:50:0","statements":[
+                    {{"nodeType":"ExpressionStatement","src":"0:0:0","expression":
+                      {{"nodeType":"Assignment","src":"0:0:0","operator":"+=",
+                        "leftHandSide":{},"rightHandSide":{}}}}},
+                    {{"nodeType":"Return","src":"0:0:0","expression":
+                      {{"nodeType":"BinaryOperation","src":"0:0:0","operator":"*",
+                        "leftExpression":{},"rightExpression":{}}}}}
+                  ]}}}}
+              ]}}"#,
+            elementary("uint256"),
+            parameter("x", "uint256", "default"),
+            parameter("", "uint256", "default"),
+            identifier("total"),
+            x,
+            x,
+            number("2"),
+        ));
+
+        let tree = import(&json, 3).unwrap();
+        let SourceUnitPart::ContractDefinition(contract) = &tree.0[2] else { panic!() };
+        assert_eq!(contract.loc, Loc::File(3, 55, 255));
+        assert_eq!(contract.name.as_ref().unwrap().loc, Loc::File(3, 64, 65));
+
+        assert_eq!(
+            source(&json),
+            r#"pragma solidity ^0.8.0;
+
+import {A as B} from "./a.sol";
+
+contract C is B {
+    uint256 public total;
+
+    function add(uint256 x) external returns (uint256) {
+        total += x;
+        return x * 2;
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_import_expressions() {
+        let expr = |json: &str| {
+            let json = unit(&format!(
+                r#"{{"nodeType":"VariableDeclaration","src":"0:0:0","name":"v","constant":true,
+                    "mutability":"constant","visibility":"internal","typeName":{},"value":{json}}}"#,
+                elementary("uint")
+            ));
+            let tree = import(&json, 0).unwrap();
+            let SourceUnitPart::VariableDefinition(def) = &tree.0[0] else { panic!() };
+            def.initializer.as_ref().unwrap().to_string()
+        };
+
+        assert_eq!(expr(&number("1_000e18")), "1000e18");
+        assert_eq!(expr(&number("2.50")), "2.5");
+        assert_eq!(
+            expr(
+                r#"{"nodeType":"Literal","src":"0:0:0","kind":"number","value":"1","subdenomination":"ether"}"#
+            ),
+            "1 ether"
+        );
+        assert_eq!(
+            expr(r#"{"nodeType":"Literal","src":"0:0:0","kind":"string","value":"a\"\né"}"#),
+            r#""a\"\n\xc3\xa9""#
+        );
+        assert_eq!(
+            expr(
+                r#"{"nodeType":"Literal","src":"0:0:0","kind":"string","value":null,"hexValue":"ff"}"#
+            ),
+            r#"hex"ff""#
+        );
+        assert_eq!(
+            expr(&format!(
+                r#"{{"nodeType":"FunctionCall","src":"0:0:0","names":[],"arguments":[{}],
+                    "expression":{{"nodeType":"FunctionCallOptions","src":"0:0:0","names":["value"],
+                      "options":[{}],"expression":{{"nodeType":"NewExpression","src":"0:0:0",
+                        "typeName":{{"nodeType":"UserDefinedTypeName","src":"0:0:0",
+                          "pathNode":{{"nodeType":"IdentifierPath","src":"0:0:0","name":"L.C"}}}}}}}}}}"#,
+                identifier("a"),
+                number("1"),
+            )),
+            "new L.C{value: 1}(a)"
+        );
+        assert_eq!(
+            expr(&format!(
+                r#"{{"nodeType":"TupleExpression","src":"0:0:0","isInlineArray":false,"components":[
+                    {{"nodeType":"UnaryOperation","src":"0:0:0","operator":"-","prefix":true,"subExpression":{}}}]}}"#,
+                identifier("a"),
+            )),
+            "(-a)"
+        );
+        assert_eq!(
+            expr(&format!(
+                r#"{{"nodeType":"FunctionCall","src":"0:0:0","names":[],"arguments":[{}],
+                    "expression":{{"nodeType":"ElementaryTypeNameExpression","src":"0:0:0",
+                      "typeName":{{"nodeType":"ElementaryTypeName","src":"0:0:0","name":"address",
+                        "stateMutability":"payable"}}}}}}"#,
+                identifier("a"),
+            )),
+            "payable(a)"
+        );
+    }
+
+    #[test]
+    fn test_version_comparators() {
+        let words = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        let loc = Loc::File(0, 0, 0);
+
+        let comparators = version_comparators(loc, &words(&[">=0.7.0", "<", "0.9.0"]));
+        assert!(comparators.is_none());
+
+        let comparators = version_comparators(
+            loc,
+            &words(&[">=", "0.7.0", "<", "0.9.0", "||", "0.6.0", "-", "0.6.9"]),
+        )
+        .unwrap();
+        assert_eq!(comparators.len(), 2);
+        assert!(matches!(&comparators[1], VersionComparator::Or { right, .. }
+            if matches!(right.as_ref(), VersionComparator::Range { .. })));
+
+        assert!(version_comparators(loc, &words(&["^", "||"])).is_none());
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let errors = import("{\"nodeType\":", 0).unwrap_err();
+        assert_eq!(errors[0].message, "invalid JSON: unexpected end of input");
+        assert_eq!(errors[0].loc, Loc::File(0, 12, 12));
+
+        let json = unit(
+            r#"{"nodeType":"ContractDefinition","src":"0:100:0","name":"C","contractKind":"contract",
+                "nodes":[{"nodeType":"FunctionDefinition","src":"10:50:0","name":"f","kind":"function",
+                  "visibility":"public","stateMutability":"pure",
+                  "parameters":{"nodeType":"ParameterList","src":"0:0:0","parameters":[]},
+                  "body":{"nodeType":"Block","src":"30:30:0","statements":[
+                    {"nodeType":"InlineAssembly","src":"32:10:0"},
+                    {"nodeType":"Bogus","src":"44:3:0"}]}}]}"#,
+        );
+        let errors = import(&json, 0).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "unsupported solc AST node 'InlineAssembly'");
+        assert_eq!(errors[0].loc, Loc::File(0, 32, 42));
+        assert_eq!(errors[1].message, "unsupported solc AST node 'Bogus'");
+
+        let errors = import(r#"{"ast":{"nodeType":"Block","src":"1:2:0"}}"#, 0).unwrap_err();
+        assert_eq!(errors[0].message, "expected a solc AST of a 'SourceUnit'");
+    }
+}
@@ -13,11 +13,22 @@
 // limitations under the License.
 
 pub mod ast;
+pub mod from_solc_ast;
+pub mod trivia;
 pub mod visitor;
 
 use std::iter::once;
 
-use crate::{diagnostics::Diagnostic, lexer::Lexer, parser::ast::SourceUnit};
+use crate::{
+    diagnostics::Diagnostic,
+    error::{self, LexicalError},
+    helpers::trace::span,
+    lexer::{self, token::Token, Lexer},
+    parser::{
+        ast::{Expression, SourceUnit, Statement},
+        trivia::Comments,
+    },
+};
 
 #[allow(clippy::ptr_arg)]
 #[allow(clippy::type_complexity)]
@@ -26,19 +37,144 @@ mod grammar {
     include!(concat!(env!("OUT_DIR"), "/parser/grammar.rs"));
 }
 
-pub use grammar::*;
+pub use grammar::SourceUnitParser;
+#[cfg(feature = "test-parsers")]
+pub use grammar::{
+    ExpressionParser, StatementParser, TestContractDefinitionParser as ContractDefinitionParser,
+    TestContractPartParser as ContractPartParser,
+    TestFunctionDefinitionParser as FunctionDefinitionParser, TestTypeParser as TypeParser,
+    TestVariableDefinitionParser as VariableDefinitionParser,
+};
 
-/// Parses source into SourceUnit or returns syntax errors
+type ParseError<'input> = lalrpop_util::ParseError<usize, Token<'input>, LexicalError>;
+type ErrorRecovery<'input> = lalrpop_util::ErrorRecovery<usize, Token<'input>, LexicalError>;
+
+/// Parses source into SourceUnit or returns syntax errors, those the parser
+/// recovered from included
 pub fn parse(source: &str, no: usize) -> Result<SourceUnit, Vec<Diagnostic>> {
+    let _span = span!("parse", file = no, bytes = source.len());
     let lexer = Lexer::new(source);
     let parser = grammar::SourceUnitParser::new();
     let mut errors = Vec::new(); // Collected during parse
 
-    parser.parse(source, no, &mut errors, lexer).map_err(|err| {
-        errors
-            .into_iter()
-            .map(|err| Diagnostic::from((&err.error, no)))
-            .chain(once(Diagnostic::from((&err, no))))
-            .collect()
-    })
+    let result = parser.parse(source, no, &mut errors, lexer);
+    recovered(result, errors, no)
+}
+
+/// Parses a single expression, like `a + 1`, without wrapping it in a contract.
+/// Like [`parse`], any syntax error fails the parse.
+pub fn parse_expression(source: &str, no: usize) -> Result<Expression, Vec<Diagnostic>> {
+    let lexer = Lexer::new(source);
+    let parser = grammar::ExpressionParser::new();
+    let mut errors = Vec::new();
+
+    let result = parser.parse(source, no, &mut errors, lexer);
+    recovered(result, errors, no)
+}
+
+/// Parses a single statement, like `uint a = 1;` or a block, failing on any
+/// syntax error like [`parse_expression`]
+pub fn parse_statement(source: &str, no: usize) -> Result<Statement, Vec<Diagnostic>> {
+    let lexer = Lexer::new(source);
+    let parser = grammar::StatementParser::new();
+    let mut errors = Vec::new();
+
+    let result = parser.parse(source, no, &mut errors, lexer);
+    recovered(result, errors, no)
+}
+
+/// The node parsed, unless errors were recovered from while parsing it
+fn recovered<T>(
+    result: Result<T, ParseError>,
+    errors: Vec<ErrorRecovery>,
+    no: usize,
+) -> Result<T, Vec<Diagnostic>> {
+    match result {
+        Ok(node) if errors.is_empty() => Ok(node),
+        Ok(_) => Err(errors.into_iter().map(|err| syntax_error(&err.error, no)).collect()),
+        Err(err) => Err(diagnostics(errors, err, no)),
+    }
+}
+
+/// The errors recovered from during a parse, followed by the one ending it
+fn diagnostics(errors: Vec<ErrorRecovery>, err: ParseError, no: usize) -> Vec<Diagnostic> {
+    errors
+        .into_iter()
+        .map(|err| syntax_error(&err.error, no))
+        .chain(once(syntax_error(&err, no)))
+        .collect()
+}
+
+fn syntax_error(err: &ParseError, no: usize) -> Diagnostic {
+    error::ParseError::from_lalrpop(err, no).into()
+}
+
+/// Parses source like [`parse`], also returning its comments attached to the
+/// nodes of the tree
+pub fn parse_with_comments(
+    source: &str,
+    no: usize,
+) -> Result<(SourceUnit, Comments), Vec<Diagnostic>> {
+    let tree = parse(source, no)?;
+    let comments = Comments::attach(&tree, lexer::comments(source, no), source);
+
+    Ok((tree, comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snippets() {
+        let expr = parse_expression("a + b * 2", 0).unwrap();
+        assert!(matches!(expr, Expression::Add(..)));
+        assert_eq!(expr.to_string(), "a + b * 2");
+
+        let stmt = parse_statement("uint a = 1;", 0).unwrap();
+        assert!(matches!(stmt, Statement::VariableDefinition(..)));
+
+        assert!(parse_expression("a +", 0).is_err());
+        assert!(parse_expression("a; b", 0).is_err());
+
+        // The grammar recovers from the error, the snippet parse fails anyway
+        let errors = parse_statement("{ uint a = ; }", 0).unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_locations() {
+        use crate::{helpers::CodeLocation, parser::ast::Loc};
+
+        let loc = Loc::File(1, 4, 9);
+        assert_eq!(loc.no(), Some(1));
+        assert_eq!(loc.range(), Some(4..9));
+        assert_eq!(loc.union(&Loc::File(1, 2, 6)), Some(Loc::File(1, 2, 9)));
+
+        // Locations outside of the files have no number, range or union
+        assert_eq!(Loc::Builtin.no(), None);
+        assert_eq!(Loc::Implicit.range(), None);
+        assert_eq!(loc.union(&Loc::File(2, 2, 6)), None);
+        assert_eq!(loc.union(&Loc::Codegen), None);
+
+        // A literal built without any part has no location of its own
+        assert_eq!(Expression::StringLiteral(Vec::new()).loc(), Loc::Implicit);
+    }
+
+    #[test]
+    #[cfg(feature = "test-parsers")]
+    fn test_sub_parsers() {
+        let source = "function f(uint a) public returns (uint) { return a; }";
+        let mut errors = Vec::new();
+        let def = FunctionDefinitionParser::new()
+            .parse(source, 0, &mut errors, Lexer::new(source))
+            .unwrap();
+
+        assert_eq!(def.name.unwrap().name, "f");
+        assert!(errors.is_empty());
+
+        let source = "mapping(address => uint)";
+        let ty = TypeParser::new().parse(source, 0, &mut errors, Lexer::new(source)).unwrap();
+        assert_eq!(ty.to_string(), source);
+    }
 }
@@ -0,0 +1,276 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Attachment of comments to the nodes of a parse tree.
+//!
+//! The grammar never sees comments, so they are lexed separately and attached
+//! afterwards to the declaration, field or statement nearest to them. Only the
+//! nodes laid out on lines of their own take comments: a comment within an
+//! expression goes to the end of its statement.
+
+use std::collections::HashMap;
+
+use crate::{
+    helpers::{CodeLocation, CodeLocationExt},
+    parser::ast::{
+        CatchClause, Comment, ContractDefinition, ContractPart, EnumDefinition, FunctionDefinition,
+        Loc, SourceUnit, SourceUnitPart, Statement, StructDefinition,
+    },
+};
+
+/// The comments attached to a node
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trivia {
+    /// Comments on the lines before the node
+    pub leading: Vec<Comment>,
+    /// Comments after the node on its last line, or anywhere within a node
+    /// which has no children
+    pub trailing: Vec<Comment>,
+    /// Comments within a node which are after all its children, like those at
+    /// the end of a block
+    pub inner: Vec<Comment>,
+}
+
+/// The comments of a source unit, by the location of the node they are
+/// attached to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    trivia: HashMap<Loc, Trivia>,
+    /// Comments after the last part of the source unit
+    pub rest: Vec<Comment>,
+}
+
+impl Comments {
+    /// Attach the comments to the nodes of the tree parsed from the source
+    pub fn attach(tree: &SourceUnit, comments: Vec<Comment>, source: &str) -> Self {
+        let nodes = tree.0.iter().filter_map(source_unit_part).collect::<Vec<_>>();
+        let mut attached = Self::default();
+
+        for comment in comments {
+            attached.attach_to(&nodes, None, comment, source);
+        }
+
+        attached
+    }
+
+    /// The comments attached to the node at the location
+    pub fn get(&self, loc: &Loc) -> Option<&Trivia> {
+        self.trivia.get(loc)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trivia.is_empty() && self.rest.is_empty()
+    }
+
+    fn attach_to(&mut self, nodes: &[Node], parent: Option<Loc>, comment: Comment, source: &str) {
+        let loc = CodeLocation::loc(&comment);
+        let (start, end) = (loc.start(), loc.end());
+
+        if let Some(node) =
+            nodes.iter().find(|node| node.loc.start() <= start && end <= node.loc.end())
+        {
+            match &node.children {
+                Some(children) => self.attach_to(children, Some(node.loc), comment, source),
+                None => self.entry(node.loc).trailing.push(comment),
+            }
+            return;
+        }
+
+        let preceding = nodes.iter().rev().find(|node| node.loc.end() <= start);
+        let following = nodes.iter().find(|node| node.loc.start() >= end);
+
+        match (preceding, following, parent) {
+            (Some(node), ..) if !source[node.loc.end()..start].contains('\n') => {
+                self.entry(node.loc).trailing.push(comment)
+            }
+            (_, Some(node), _) => self.entry(node.loc).leading.push(comment),
+            (.., Some(parent)) => self.entry(parent).inner.push(comment),
+            _ => self.rest.push(comment),
+        }
+    }
+
+    fn entry(&mut self, loc: Loc) -> &mut Trivia {
+        self.trivia.entry(loc).or_default()
+    }
+}
+
+/// A node taking comments, with the nodes within it when it is laid out over
+/// several lines
+struct Node {
+    loc: Loc,
+    children: Option<Vec<Node>>,
+}
+
+impl Node {
+    fn leaf(loc: Loc) -> Self {
+        Self { loc, children: None }
+    }
+
+    fn parent(loc: Loc, children: Vec<Node>) -> Self {
+        Self { loc, children: Some(children) }
+    }
+}
+
+fn source_unit_part(part: &SourceUnitPart) -> Option<Node> {
+    Some(match part {
+        SourceUnitPart::StraySemicolon(_) => return None,
+        SourceUnitPart::ContractDefinition(def) => contract(def),
+        SourceUnitPart::EnumDefinition(def) => enum_def(def),
+        SourceUnitPart::StructDefinition(def) => struct_def(def),
+        SourceUnitPart::FunctionDefinition(def) => function(def),
+        part => Node::leaf(CodeLocationExt::loc(part)),
+    })
+}
+
+fn contract(def: &ContractDefinition) -> Node {
+    let parts = def
+        .parts
+        .iter()
+        .filter_map(|part| {
+            Some(match part {
+                ContractPart::StraySemicolon(_) => return None,
+                ContractPart::EnumDefinition(def) => enum_def(def),
+                ContractPart::StructDefinition(def) => struct_def(def),
+                ContractPart::FunctionDefinition(def) => function(def),
+                part => Node::leaf(CodeLocationExt::loc(part)),
+            })
+        })
+        .collect();
+
+    Node::parent(def.loc, parts)
+}
+
+fn struct_def(def: &StructDefinition) -> Node {
+    Node::parent(def.loc, def.fields.iter().map(|field| Node::leaf(field.loc)).collect())
+}
+
+fn enum_def(def: &EnumDefinition) -> Node {
+    Node::parent(def.loc, def.values.iter().flatten().map(|value| Node::leaf(value.loc)).collect())
+}
+
+fn function(def: &FunctionDefinition) -> Node {
+    match &def.body {
+        Some(Statement::Block { statements, .. }) => {
+            Node::parent(CodeLocationExt::loc(def), statements_of(statements))
+        }
+        _ => Node::leaf(CodeLocationExt::loc(def)),
+    }
+}
+
+fn statements_of(statements: &[Statement]) -> Vec<Node> {
+    statements.iter().map(statement).collect()
+}
+
+fn statement(stmt: &Statement) -> Node {
+    let loc = CodeLocation::loc(stmt);
+
+    match stmt {
+        Statement::Block { statements, .. } => Node::parent(loc, statements_of(statements)),
+        Statement::If(_, _, then, otherwise) => {
+            let Statement::Block { unchecked: false, statements, .. } = then.as_ref() else {
+                return Node::leaf(loc);
+            };
+
+            let mut children = statements_of(statements);
+            match otherwise.as_deref() {
+                Some(Statement::Block { unchecked: false, statements, .. }) => {
+                    children.extend(statements_of(statements))
+                }
+                Some(otherwise @ Statement::If(..)) => children.push(statement(otherwise)),
+                Some(otherwise) => children.push(Node::leaf(CodeLocation::loc(otherwise))),
+                None => (),
+            }
+
+            Node::parent(loc, children)
+        }
+        Statement::While(_, _, body) |
+        Statement::For(_, _, _, _, Some(body)) |
+        Statement::DoWhile(_, body, _) => match body.as_ref() {
+            Statement::Block { unchecked: false, statements, .. } => {
+                Node::parent(loc, statements_of(statements))
+            }
+            _ => Node::leaf(loc),
+        },
+        Statement::Try(_, _, Some((_, body)), catches) => {
+            let mut children = statements_of(block_statements(body));
+            for catch in catches {
+                let (CatchClause::Simple(.., block) | CatchClause::Named(.., block)) = catch;
+                children.extend(statements_of(block_statements(block)));
+            }
+
+            Node::parent(loc, children)
+        }
+        _ => Node::leaf(loc),
+    }
+}
+
+fn block_statements(stmt: &Statement) -> &[Statement] {
+    match stmt {
+        Statement::Block { statements, .. } => statements,
+        stmt => std::slice::from_ref(stmt),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_with_comments;
+
+    #[test]
+    fn test_attach_comments() {
+        let source = r#"// license
+contract C {
+    uint a; // after a
+    // before f
+    function f() public {
+        a = 1; /* inline */
+        // end of f
+    }
+    function g(/* params */) external;
+}
+// end of file
+"#;
+
+        let (tree, comments) = parse_with_comments(source, 0).unwrap();
+        let SourceUnitPart::ContractDefinition(contract) = &tree.0[0] else {
+            panic!("expected a contract");
+        };
+        let trivia = |loc: Loc| {
+            let trivia = comments.get(&loc).unwrap();
+            [&trivia.leading, &trivia.trailing, &trivia.inner]
+                .map(|comments| comments.iter().map(ToString::to_string).collect::<Vec<_>>())
+        };
+
+        assert_eq!(trivia(contract.loc)[0], ["// license"]);
+        assert_eq!(trivia(CodeLocationExt::loc(&contract.parts[0]))[1], ["// after a"]);
+
+        let f = trivia(CodeLocationExt::loc(&contract.parts[1]));
+        assert_eq!(f[0], ["// before f"]);
+        assert_eq!(f[2], ["// end of f"]);
+
+        let ContractPart::FunctionDefinition(def) = &contract.parts[1] else {
+            panic!("expected a function");
+        };
+        let Some(Statement::Block { statements, .. }) = &def.body else {
+            panic!("expected a body");
+        };
+        assert_eq!(trivia(CodeLocation::loc(&statements[0]))[1], ["/* inline */"]);
+
+        assert_eq!(trivia(CodeLocationExt::loc(&contract.parts[2]))[1], ["/* params */"]);
+        assert_eq!(
+            comments.rest.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["// end of file"]
+        );
+    }
+}
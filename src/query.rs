@@ -0,0 +1,697 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lookup of the parse tree node at a position in a source file, and of the
+//! resolved symbol it declares or refers to. This is what hover, go to
+//! definition and rename are built on.
+
+use crate::{
+    helpers::{CodeLocation, CodeLocationExt},
+    parser::ast as pt,
+    semantic::{
+        ast::{StructType, Symbol},
+        context::Context,
+        file::File,
+    },
+};
+
+/// A position in a source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Byte offset from the start of the file
+    Offset(usize),
+    /// Zero-based line and column, the column counted in characters
+    LineColumn(usize, usize),
+}
+
+impl Position {
+    /// The byte offset of the position in the file
+    pub fn offset(&self, file: &File) -> Option<usize> {
+        match *self {
+            Position::Offset(offset) => Some(offset),
+            Position::LineColumn(line, column) => file.offset(line, column),
+        }
+    }
+}
+
+/// A node of the parse tree
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Pragma(&'a pt::PragmaDirective),
+    Import(&'a pt::Import),
+    Contract(&'a pt::ContractDefinition),
+    Base(&'a pt::Base),
+    Struct(&'a pt::StructDefinition),
+    Enum(&'a pt::EnumDefinition),
+    Event(&'a pt::EventDefinition),
+    Error(&'a pt::ErrorDefinition),
+    Function(&'a pt::FunctionDefinition),
+    Variable(&'a pt::VariableDefinition),
+    TypeDefinition(&'a pt::TypeDefinition),
+    Using(&'a pt::Using),
+    Annotation(&'a pt::Annotation),
+    Parameter(&'a pt::Parameter),
+    /// A struct field, event or error field, or a local variable
+    Declaration(&'a pt::VariableDeclaration),
+    Statement(&'a pt::Statement),
+    Expression(&'a pt::Expression),
+    Identifier(&'a pt::Identifier),
+}
+
+impl CodeLocation for Node<'_> {
+    fn loc(&self) -> pt::Loc {
+        match self {
+            Node::Pragma(pragma) => match pragma {
+                pt::PragmaDirective::Identifier(loc, ..) |
+                pt::PragmaDirective::StringLiteral(loc, ..) |
+                pt::PragmaDirective::Version(loc, ..) => *loc,
+            },
+            Node::Import(import) => CodeLocation::loc(*import),
+            Node::Contract(contract) => contract.loc,
+            Node::Base(base) => base.loc,
+            Node::Struct(def) => def.loc,
+            Node::Enum(def) => def.loc,
+            Node::Event(def) => def.loc,
+            Node::Error(def) => def.loc,
+            // The plain location of a function does not include its body
+            Node::Function(func) => CodeLocationExt::loc(*func),
+            Node::Variable(def) => def.loc,
+            Node::TypeDefinition(def) => def.loc,
+            Node::Using(using) => using.loc,
+            Node::Annotation(annotation) => annotation.loc,
+            Node::Parameter(param) => param.loc,
+            Node::Declaration(decl) => decl.loc,
+            Node::Statement(stmt) => CodeLocation::loc(*stmt),
+            Node::Expression(expr) => CodeLocation::loc(*expr),
+            Node::Identifier(id) => id.loc,
+        }
+    }
+}
+
+/// The result of a lookup: the nodes enclosing the position
+#[derive(Debug, Clone, Default)]
+pub struct Query<'a> {
+    /// The enclosing nodes, outermost first
+    pub path: Vec<Node<'a>>,
+}
+
+impl<'a> Query<'a> {
+    /// Find the nodes of the parse tree enclosing a byte offset
+    pub fn new(tree: &'a pt::SourceUnit, offset: usize) -> Self {
+        let mut finder = Finder { offset, path: Vec::new() };
+
+        for part in &tree.0 {
+            finder.source_unit_part(part);
+        }
+
+        Self { path: finder.path }
+    }
+
+    /// The innermost node at the position
+    pub fn node(&self) -> Option<&Node<'a>> {
+        self.path.last()
+    }
+
+    /// The contract the position is in
+    pub fn contract(&self) -> Option<&'a pt::ContractDefinition> {
+        self.path.iter().find_map(|node| match node {
+            Node::Contract(contract) => Some(*contract),
+            _ => None,
+        })
+    }
+
+    /// The resolved symbol declared or referenced at the position. Local
+    /// variables are not in the symbol tables, so none is found for them.
+    pub fn symbol(&self, ctx: &Context) -> Option<Symbol> {
+        let (i, node) = self.path.iter().enumerate().next_back()?;
+        let contract_no = self.contract_no(ctx);
+
+        match node {
+            Node::Identifier(id) => match i.checked_sub(1).map(|i| &self.path[i]) {
+                // The name of a declaration gives the declared symbol
+                Some(parent) if declared_name(parent) == Some(id.loc) => {
+                    declaration(parent, contract_no, ctx)
+                }
+                // A member depends on the type of the expression it is accessed on
+                Some(Node::Expression(pt::Expression::MemberAccess(_, _, member)))
+                    if member.loc == id.loc =>
+                {
+                    None
+                }
+                _ => self.resolve(&id.name, ctx),
+            },
+            Node::Expression(pt::Expression::Variable(id)) => self.resolve(&id.name, ctx),
+            node => declaration(node, contract_no, ctx),
+        }
+    }
+
+    /// Look up a name in the scope of the position, from the enclosing contract
+    /// through its bases to the file scope
+    pub fn resolve(&self, name: &str, ctx: &Context) -> Option<Symbol> {
+        let no = self.node()?.loc().no()?;
+
+        lookup(no, self.contract_no(ctx), name, ctx)
+    }
+
+    fn contract_no(&self, ctx: &Context) -> Option<usize> {
+        self.contract()
+            .and_then(|def| ctx.contracts.iter().position(|contract| contract.loc == def.loc))
+    }
+}
+
+/// Is the location a file location containing the offset
+fn contains(loc: pt::Loc, offset: usize) -> bool {
+    matches!(loc, pt::Loc::File(_, start, end) if start <= offset && offset < end)
+}
+
+/// Walks the parse tree down to the position, recording the enclosing nodes
+struct Finder<'a> {
+    offset: usize,
+    path: Vec<Node<'a>>,
+}
+
+impl<'a> Finder<'a> {
+    /// Record the node if it encloses the position
+    fn enter(&mut self, node: Node<'a>) -> bool {
+        let found = contains(node.loc(), self.offset);
+        if found {
+            self.path.push(node);
+        }
+        found
+    }
+
+    fn ident(&mut self, id: Option<&'a pt::Identifier>) {
+        if let Some(id) = id {
+            self.enter(Node::Identifier(id));
+        }
+    }
+
+    fn ident_path(&mut self, path: &'a pt::IdentifierPath) {
+        for id in &path.identifiers {
+            self.ident(Some(id));
+        }
+    }
+
+    fn source_unit_part(&mut self, part: &'a pt::SourceUnitPart) {
+        match part {
+            pt::SourceUnitPart::PragmaDirective(pragma) => {
+                self.enter(Node::Pragma(pragma));
+            }
+            pt::SourceUnitPart::ImportDirective(import) => {
+                if self.enter(Node::Import(import)) {
+                    match import {
+                        pt::Import::Plain(..) => (),
+                        pt::Import::GlobalSymbol(_, id, _) => self.ident(Some(id)),
+                        pt::Import::Rename(_, renames, _) => {
+                            for (id, alias) in renames {
+                                self.ident(Some(id));
+                                self.ident(alias.as_ref());
+                            }
+                        }
+                    }
+                }
+            }
+            pt::SourceUnitPart::ContractDefinition(def) => self.contract(def),
+            pt::SourceUnitPart::EnumDefinition(def) => self.enum_def(def),
+            pt::SourceUnitPart::StructDefinition(def) => self.struct_def(def),
+            pt::SourceUnitPart::EventDefinition(def) => self.event(def),
+            pt::SourceUnitPart::ErrorDefinition(def) => self.error(def),
+            pt::SourceUnitPart::FunctionDefinition(def) => self.function(def),
+            pt::SourceUnitPart::VariableDefinition(def) => self.variable(def),
+            pt::SourceUnitPart::TypeDefinition(def) => self.type_def(def),
+            pt::SourceUnitPart::Annotation(annotation) => self.annotation(annotation),
+            pt::SourceUnitPart::Using(using) => self.using(using),
+            pt::SourceUnitPart::StraySemicolon(_) => (),
+        }
+    }
+
+    fn contract(&mut self, def: &'a pt::ContractDefinition) {
+        if !self.enter(Node::Contract(def)) {
+            return;
+        }
+
+        self.ident(def.name.as_ref());
+
+        for base in &def.base {
+            self.base(base);
+        }
+
+        for part in &def.parts {
+            match part {
+                pt::ContractPart::StructDefinition(def) => self.struct_def(def),
+                pt::ContractPart::EventDefinition(def) => self.event(def),
+                pt::ContractPart::EnumDefinition(def) => self.enum_def(def),
+                pt::ContractPart::ErrorDefinition(def) => self.error(def),
+                pt::ContractPart::VariableDefinition(def) => self.variable(def),
+                pt::ContractPart::FunctionDefinition(def) => self.function(def),
+                pt::ContractPart::TypeDefinition(def) => self.type_def(def),
+                pt::ContractPart::Annotation(annotation) => self.annotation(annotation),
+                pt::ContractPart::Using(using) => self.using(using),
+                pt::ContractPart::StraySemicolon(_) => (),
+            }
+        }
+    }
+
+    fn base(&mut self, base: &'a pt::Base) {
+        if self.enter(Node::Base(base)) {
+            self.ident_path(&base.name);
+            for arg in base.args.iter().flatten() {
+                self.expression(arg);
+            }
+        }
+    }
+
+    fn enum_def(&mut self, def: &'a pt::EnumDefinition) {
+        if self.enter(Node::Enum(def)) {
+            self.ident(def.name.as_ref());
+            for value in &def.values {
+                self.ident(value.as_ref());
+            }
+        }
+    }
+
+    fn struct_def(&mut self, def: &'a pt::StructDefinition) {
+        if self.enter(Node::Struct(def)) {
+            self.ident(def.name.as_ref());
+            for field in &def.fields {
+                self.declaration(field);
+            }
+        }
+    }
+
+    fn event(&mut self, def: &'a pt::EventDefinition) {
+        if self.enter(Node::Event(def)) {
+            self.ident(def.name.as_ref());
+            for field in &def.fields {
+                self.expression(&field.ty);
+                self.ident(field.name.as_ref());
+            }
+        }
+    }
+
+    fn error(&mut self, def: &'a pt::ErrorDefinition) {
+        if self.enter(Node::Error(def)) {
+            self.ident(def.name.as_ref());
+            for field in &def.fields {
+                self.expression(&field.ty);
+                self.ident(field.name.as_ref());
+            }
+        }
+    }
+
+    fn function(&mut self, def: &'a pt::FunctionDefinition) {
+        if !self.enter(Node::Function(def)) {
+            return;
+        }
+
+        self.ident(def.name.as_ref());
+        self.parameters(&def.params);
+
+        for attr in &def.attributes {
+            match attr {
+                pt::FunctionAttribute::BaseOrModifier(_, base) => self.base(base),
+                pt::FunctionAttribute::Override(_, paths) => {
+                    for path in paths {
+                        self.ident_path(path);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        self.parameters(&def.returns);
+
+        if let Some(body) = &def.body {
+            self.statement(body);
+        }
+    }
+
+    fn variable(&mut self, def: &'a pt::VariableDefinition) {
+        if self.enter(Node::Variable(def)) {
+            self.expression(&def.ty);
+            self.ident(def.name.as_ref());
+            if let Some(initializer) = &def.initializer {
+                self.expression(initializer);
+            }
+        }
+    }
+
+    fn type_def(&mut self, def: &'a pt::TypeDefinition) {
+        if self.enter(Node::TypeDefinition(def)) {
+            self.ident(Some(&def.name));
+            self.expression(&def.ty);
+        }
+    }
+
+    fn annotation(&mut self, annotation: &'a pt::Annotation) {
+        if self.enter(Node::Annotation(annotation)) {
+            self.ident(Some(&annotation.id));
+            if let Some(value) = &annotation.value {
+                self.expression(value);
+            }
+        }
+    }
+
+    fn using(&mut self, using: &'a pt::Using) {
+        if !self.enter(Node::Using(using)) {
+            return;
+        }
+
+        match &using.list {
+            pt::UsingList::Library(path) => self.ident_path(path),
+            pt::UsingList::Functions(functions) => {
+                for function in functions {
+                    self.ident_path(&function.path);
+                }
+            }
+            pt::UsingList::Error => (),
+        }
+
+        if let Some(ty) = &using.ty {
+            self.expression(ty);
+        }
+    }
+
+    fn parameters(&mut self, params: &'a pt::ParameterList) {
+        for param in params.iter().filter_map(|(_, param)| param.as_ref()) {
+            if self.enter(Node::Parameter(param)) {
+                self.expression(&param.ty);
+                self.ident(param.name.as_ref());
+            }
+        }
+    }
+
+    fn declaration(&mut self, decl: &'a pt::VariableDeclaration) {
+        if self.enter(Node::Declaration(decl)) {
+            self.expression(&decl.ty);
+            self.ident(decl.name.as_ref());
+        }
+    }
+
+    fn arguments(&mut self, args: &'a [pt::NamedArgument]) {
+        for arg in args {
+            self.ident(Some(&arg.name));
+            self.expression(&arg.expr);
+        }
+    }
+
+    fn statement(&mut self, stmt: &'a pt::Statement) {
+        if !self.enter(Node::Statement(stmt)) {
+            return;
+        }
+
+        match stmt {
+            pt::Statement::Block { statements, .. } => {
+                for stmt in statements {
+                    self.statement(stmt);
+                }
+            }
+            pt::Statement::Args(_, args) => self.arguments(args),
+            pt::Statement::If(_, cond, then, otherwise) => {
+                self.expression(cond);
+                self.statement(then);
+                if let Some(otherwise) = otherwise {
+                    self.statement(otherwise);
+                }
+            }
+            pt::Statement::While(_, cond, body) => {
+                self.expression(cond);
+                self.statement(body);
+            }
+            pt::Statement::Expression(_, expr) | pt::Statement::Emit(_, expr) => {
+                self.expression(expr)
+            }
+            pt::Statement::VariableDefinition(_, decl, initializer) => {
+                self.declaration(decl);
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+            }
+            pt::Statement::For(_, init, cond, next, body) => {
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                if let Some(cond) = cond {
+                    self.expression(cond);
+                }
+                if let Some(next) = next {
+                    self.expression(next);
+                }
+                if let Some(body) = body {
+                    self.statement(body);
+                }
+            }
+            pt::Statement::DoWhile(_, body, cond) => {
+                self.statement(body);
+                self.expression(cond);
+            }
+            pt::Statement::Return(_, expr) => {
+                if let Some(expr) = expr {
+                    self.expression(expr);
+                }
+            }
+            pt::Statement::Revert(_, path, args) => {
+                if let Some(path) = path {
+                    self.ident_path(path);
+                }
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            pt::Statement::RevertNamedArgs(_, path, args) => {
+                if let Some(path) = path {
+                    self.ident_path(path);
+                }
+                self.arguments(args);
+            }
+            pt::Statement::Try(_, expr, returns, catches) => {
+                self.expression(expr);
+                if let Some((params, body)) = returns {
+                    self.parameters(params);
+                    self.statement(body);
+                }
+                for catch in catches {
+                    let (param, body) = match catch {
+                        pt::CatchClause::Simple(_, param, body) => (param.as_ref(), body),
+                        pt::CatchClause::Named(_, id, param, body) => {
+                            self.ident(Some(id));
+                            (Some(param), body)
+                        }
+                    };
+                    if let Some(param) = param {
+                        if self.enter(Node::Parameter(param)) {
+                            self.expression(&param.ty);
+                            self.ident(param.name.as_ref());
+                        }
+                    }
+                    self.statement(body);
+                }
+            }
+            pt::Statement::Assembly { .. } |
+            pt::Statement::Continue(_) |
+            pt::Statement::Break(_) |
+            pt::Statement::Error(_) => (),
+        }
+    }
+
+    fn expression(&mut self, expr: &'a pt::Expression) {
+        if !self.enter(Node::Expression(expr)) {
+            return;
+        }
+
+        match expr {
+            pt::Expression::Variable(id) => self.ident(Some(id)),
+            pt::Expression::MemberAccess(_, expr, id) => {
+                self.expression(expr);
+                self.ident(Some(id));
+            }
+            pt::Expression::FunctionCall(_, func, args) => {
+                self.expression(func);
+                for arg in args {
+                    self.expression(arg);
+                }
+            }
+            pt::Expression::FunctionCallBlock(_, func, block) => {
+                self.expression(func);
+                self.statement(block);
+            }
+            pt::Expression::NamedFunctionCall(_, func, args) => {
+                self.expression(func);
+                self.arguments(args);
+            }
+            pt::Expression::ArraySubscript(_, array, index) => {
+                self.expression(array);
+                if let Some(index) = index {
+                    self.expression(index);
+                }
+            }
+            pt::Expression::ArraySlice(_, array, from, to) => {
+                self.expression(array);
+                for expr in [from, to].into_iter().flatten() {
+                    self.expression(expr);
+                }
+            }
+            pt::Expression::ConditionalOperator(_, cond, left, right) => {
+                self.expression(cond);
+                self.expression(left);
+                self.expression(right);
+            }
+            pt::Expression::ArrayLiteral(_, exprs) => {
+                for expr in exprs {
+                    self.expression(expr);
+                }
+            }
+            pt::Expression::List(_, params) => self.parameters(params),
+            pt::Expression::Type(_, pt::Type::Mapping { key, key_name, value, value_name, .. }) => {
+                self.expression(key);
+                self.ident(key_name.as_ref());
+                self.expression(value);
+                self.ident(value_name.as_ref());
+            }
+            expr => {
+                let (left, right) = expr.components();
+                for expr in left.into_iter().chain(right) {
+                    self.expression(expr);
+                }
+            }
+        }
+    }
+}
+
+/// The location of the name a node declares
+fn declared_name(node: &Node<'_>) -> Option<pt::Loc> {
+    let name = match node {
+        Node::Contract(def) => def.name.as_ref(),
+        Node::Struct(def) => def.name.as_ref(),
+        Node::Enum(def) => def.name.as_ref(),
+        Node::Event(def) => def.name.as_ref(),
+        Node::Error(def) => def.name.as_ref(),
+        Node::Function(def) => def.name.as_ref(),
+        Node::Variable(def) => def.name.as_ref(),
+        Node::TypeDefinition(def) => Some(&def.name),
+        _ => None,
+    };
+
+    name.map(|id| id.loc)
+}
+
+/// Find the symbol of a declaration. The semantic declarations keep either the
+/// location of the definition or of its name, so both are matched.
+fn declaration(node: &Node<'_>, contract_no: Option<usize>, ctx: &Context) -> Option<Symbol> {
+    let def_loc = node.loc();
+    let name_loc = declared_name(node);
+    let is_decl = |loc: &pt::Loc| *loc == def_loc || Some(*loc) == name_loc;
+
+    match node {
+        Node::Contract(_) => ctx
+            .contracts
+            .iter()
+            .position(|contract| is_decl(&contract.loc))
+            .map(|no| Symbol::Contract(def_loc, no)),
+        Node::Struct(_) => ctx
+            .structs
+            .iter()
+            .position(|decl| is_decl(&decl.loc))
+            .map(|no| Symbol::Struct(def_loc, StructType::UserDefined(no))),
+        Node::Enum(_) => {
+            ctx.enums.iter().position(|decl| is_decl(&decl.loc)).map(|no| Symbol::Enum(def_loc, no))
+        }
+        Node::Event(_) => ctx
+            .events
+            .iter()
+            .position(|decl| is_decl(&decl.loc))
+            .map(|no| Symbol::Event(vec![(def_loc, no)])),
+        Node::Error(_) => ctx
+            .errors
+            .iter()
+            .position(|decl| is_decl(&decl.loc))
+            .map(|no| Symbol::Error(def_loc, no)),
+        Node::Function(_) => ctx
+            .functions
+            .iter()
+            .position(|func| is_decl(&func.loc) || is_decl(&func.loc_prototype))
+            .map(|no| Symbol::Function(vec![(def_loc, no)])),
+        Node::TypeDefinition(_) => ctx
+            .user_types
+            .iter()
+            .position(|decl| is_decl(&decl.loc))
+            .map(|no| Symbol::UserType(def_loc, no)),
+        Node::Variable(_) => {
+            let variables = match contract_no {
+                Some(contract_no) => &ctx.contracts[contract_no].variables,
+                None => &ctx.constants,
+            };
+
+            variables
+                .iter()
+                .position(|var| is_decl(&var.loc))
+                .map(|no| Symbol::Variable(def_loc, contract_no, no))
+        }
+        Node::Base(base) => {
+            let id = base.name.identifiers.last()?;
+            lookup(def_loc.no()?, contract_no, &id.name, ctx)
+        }
+        _ => None,
+    }
+}
+
+/// Look up a name in the symbol tables of a contract and the file
+fn lookup(no: usize, contract_no: Option<usize>, name: &str, ctx: &Context) -> Option<Symbol> {
+    let get = |contract_no: Option<usize>| {
+        let key = (no, contract_no, name.to_string());
+        ctx.variable_symbols.get(&key).or_else(|| ctx.function_symbols.get(&key))
+    };
+
+    let mut scopes = Vec::new();
+    if let Some(contract_no) = contract_no {
+        scopes.push(contract_no);
+    }
+
+    while let Some(contract_no) = scopes.pop() {
+        if let Some(symbol) = get(Some(contract_no)) {
+            return Some(symbol.clone());
+        }
+        scopes.extend(ctx.contracts[contract_no].bases.iter().rev().map(|base| base.contract_no));
+    }
+
+    get(None).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_node_at_offset() {
+        let src = "contract C {\n    function f(uint a) public { a + 1; }\n}\n";
+        let tree = parse(src, 0).unwrap();
+
+        let query = Query::new(&tree, src.find("a +").unwrap());
+        assert!(matches!(query.path[0], Node::Contract(_)));
+        assert!(matches!(query.path[1], Node::Function(_)));
+        assert!(matches!(query.node(), Some(Node::Identifier(id)) if id.name == "a"));
+
+        let query = Query::new(&tree, src.find("f(").unwrap());
+        assert!(matches!(query.node(), Some(Node::Identifier(id)) if id.name == "f"));
+
+        let query = Query::new(&tree, src.len());
+        assert!(query.node().is_none());
+
+        let file = File::new("test.sol".into(), src, 0, None);
+        assert_eq!(Position::LineColumn(1, 13).offset(&file), src.find("f(uint"));
+        assert_eq!(file.line_column(src.find("f(uint").unwrap()), (1, 13));
+        assert_eq!(file.line_column(0), (0, 0));
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Renaming of variables, functions, contracts and other declarations across
+//! all the files of a compilation.
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::{
+    helpers::CodeLocation,
+    lexer::{token::Token, Lexer},
+    parser::{ast as pt, parse},
+    query::{Node, Position, Query},
+    resolver::FileResolver,
+    semantic::{ast::Symbol, context::Context},
+};
+
+/// Words reserved by Solidity for future use, which the lexer accepts as
+/// identifiers
+const RESERVED: &[&str] = &[
+    "after",
+    "alias",
+    "apply",
+    "auto",
+    "byte",
+    "case",
+    "copyof",
+    "default",
+    "define",
+    "final",
+    "implements",
+    "in",
+    "inline",
+    "let",
+    "macro",
+    "match",
+    "mutable",
+    "null",
+    "of",
+    "partial",
+    "promise",
+    "reference",
+    "relocatable",
+    "sealed",
+    "sizeof",
+    "static",
+    "supports",
+    "switch",
+    "typedef",
+    "typeof",
+    "var",
+];
+
+/// A replacement of the text at a location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub loc: pt::Loc,
+    pub text: String,
+}
+
+/// Reasons a rename cannot be done
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RenameError {
+    #[error("no symbol found at the position")]
+    NoSymbol,
+
+    #[error("'{0}' is not a valid identifier")]
+    InvalidName(String),
+
+    #[error("'{0}' is a reserved word")]
+    Reserved(String),
+
+    #[error("'{0}' is already declared")]
+    Collision(String, pt::Loc),
+}
+
+/// Compute the edits renaming the symbol at a position in a file, covering its
+/// declaration and all the references to it in the compilation.
+pub fn rename(
+    file_no: usize,
+    position: Position,
+    new_name: &str,
+    ctx: &Context,
+    resolver: &FileResolver,
+) -> Result<Vec<TextEdit>, RenameError> {
+    validate_name(new_name)?;
+
+    let file = ctx.files.get(file_no).ok_or(RenameError::NoSymbol)?;
+    let offset = position.offset(file).ok_or(RenameError::NoSymbol)?;
+    let source = contents(file_no, ctx, resolver).ok_or(RenameError::NoSymbol)?;
+    let tree = parse(&source, file_no).map_err(|_| RenameError::NoSymbol)?;
+
+    let query = Query::new(&tree, offset);
+    let (Some(Node::Identifier(id)), Some(symbol)) = (query.node(), query.symbol(ctx)) else {
+        return Err(RenameError::NoSymbol);
+    };
+
+    // Rename all the overloads of a function or event together, as the
+    // references cannot tell them apart
+    let symbol = match symbol {
+        Symbol::Function(_) | Symbol::Event(_) => {
+            query.resolve(&id.name, ctx).filter(|s| same_symbol(s, &symbol)).unwrap_or(symbol)
+        }
+        symbol => symbol,
+    };
+
+    let mut edits = Vec::new();
+
+    for no in 0..ctx.files.len() {
+        let Some(source) = contents(no, ctx, resolver) else {
+            continue;
+        };
+        let Ok(tree) = parse(&source, no) else {
+            continue;
+        };
+
+        for (start, token, _) in Lexer::new(&source).flatten() {
+            if token != Token::Identifier(&id.name) {
+                continue;
+            }
+
+            let query = Query::new(&tree, start);
+            let Some(Node::Identifier(reference)) = query.node() else {
+                continue;
+            };
+
+            if !query.symbol(ctx).is_some_and(|s| same_symbol(&s, &symbol)) {
+                continue;
+            }
+
+            if let Some(other) = query.resolve(new_name, ctx) {
+                if !same_symbol(&other, &symbol) {
+                    return Err(RenameError::Collision(new_name.to_string(), other.loc()));
+                }
+            }
+
+            edits.push(TextEdit { loc: reference.loc, text: new_name.to_string() });
+        }
+    }
+
+    Ok(edits)
+}
+
+/// Check that the name can be used as an identifier
+fn validate_name(name: &str) -> Result<(), RenameError> {
+    let mut tokens = Lexer::new(name);
+
+    match (tokens.next(), tokens.next()) {
+        (Some(Ok((0, Token::Identifier(_), end))), None) if end == name.len() => (),
+        (Some(Ok((0, _, end))), None) if end == name.len() => {
+            return Err(RenameError::Reserved(name.to_string()))
+        }
+        _ => return Err(RenameError::InvalidName(name.to_string())),
+    }
+
+    if RESERVED.contains(&name) {
+        return Err(RenameError::Reserved(name.to_string()));
+    }
+
+    Ok(())
+}
+
+fn contents(no: usize, ctx: &Context, resolver: &FileResolver) -> Option<Arc<str>> {
+    ctx.files.get(no)?.cache_no.and_then(|no| resolver.get_contents_of_no(no))
+}
+
+/// Do two symbols refer to the same declaration. Overloaded functions and
+/// events are the same when they share a declaration.
+fn same_symbol(a: &Symbol, b: &Symbol) -> bool {
+    match (a, b) {
+        (Symbol::Enum(_, a), Symbol::Enum(_, b)) |
+        (Symbol::Error(_, a), Symbol::Error(_, b)) |
+        (Symbol::Contract(_, a), Symbol::Contract(_, b)) |
+        (Symbol::Import(_, a), Symbol::Import(_, b)) |
+        (Symbol::UserType(_, a), Symbol::UserType(_, b)) => a == b,
+        (Symbol::Struct(_, a), Symbol::Struct(_, b)) => a == b,
+        (Symbol::Variable(_, a_contract, a), Symbol::Variable(_, b_contract, b)) => {
+            a_contract == b_contract && a == b
+        }
+        (Symbol::Function(a), Symbol::Function(b)) | (Symbol::Event(a), Symbol::Event(b)) => {
+            a.iter().any(|(_, a)| b.iter().any(|(_, b)| a == b))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert_eq!(validate_name("balance_2"), Ok(()));
+        assert_eq!(validate_name("contract"), Err(RenameError::Reserved("contract".into())));
+        assert_eq!(validate_name("typeof"), Err(RenameError::Reserved("typeof".into())));
+        assert_eq!(validate_name("2x"), Err(RenameError::InvalidName("2x".into())));
+        assert_eq!(validate_name("a b"), Err(RenameError::InvalidName("a b".into())));
+        assert_eq!(validate_name(""), Err(RenameError::InvalidName("".into())));
+    }
+}
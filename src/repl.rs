@@ -0,0 +1,483 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Evaluation of expressions and statements, for the `repl` command.
+//!
+//! The input is resolved by the semantic analysis in the body of a scratch
+//! free function, after the free functions defined at the prompt, so that it
+//! follows the rules of the compiler for literal math, conversions and
+//! checked arithmetic. An expression the analysis folds to a constant, see
+//! [`eval`](crate::semantic::eval), is printed as such. Otherwise the scratch
+//! function is generated for the host and run, see [`jit`](crate::jit).
+//!
+//! The variables declared at the prompt live in the session: the scratch
+//! function is named with them as its results, assigned their values first,
+//! and returns their values after the input ran.
+
+use std::fmt;
+
+use indexmap::IndexMap;
+use thiserror::Error;
+
+use crate::{
+    codegen::CodegenError,
+    config::Config,
+    diagnostics::{Diagnostic, Diagnostics},
+    driver::{self, Input},
+    helpers::CodeLocation,
+    parser::{ast as pt, parse_expression, parse_statement},
+    semantic::{
+        ast::{Expression, RetrieveType, Statement, Type},
+        context::Context,
+        eval::eval_constants_in_expression,
+    },
+};
+
+/// The name of the scratch function the input is evaluated in
+const SCRATCH: &str = "__repl";
+
+/// The result of the scratch function holding the value of an expression
+const VALUE: &str = "__value";
+
+/// A value computed at the prompt, as Solidity writes it, with its type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value {
+    value: String,
+    ty: String,
+}
+
+impl Value {
+    /// The name of the type of the value, as the semantic analysis gives it
+    pub fn type_name(&self) -> &str {
+        &self.ty
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+/// Reasons the input cannot be evaluated
+#[derive(Debug, Error)]
+pub enum ReplError {
+    /// The errors of the parser, the semantic analysis or the code generator
+    #[error("{}", .0.iter().map(|d| d.message.as_str()).collect::<Vec<_>>().join("\n"))]
+    Diagnostics(Vec<Diagnostic>),
+
+    #[error("{0} is not supported in the REPL")]
+    Unsupported(String),
+
+    #[error(transparent)]
+    Codegen(CodegenError),
+
+    #[cfg(unix)]
+    #[error(transparent)]
+    Jit(#[from] crate::jit::JitError),
+
+    #[error("the evaluation reverted with 0x{}", .0.iter().map(|b| format!("{b:02x}")).collect::<String>())]
+    Reverted(Vec<u8>),
+
+    #[error(
+        "the evaluation trapped, as on an arithmetic overflow, a division by zero or an index out \
+         of bounds"
+    )]
+    Trapped,
+}
+
+impl From<CodegenError> for ReplError {
+    fn from(err: CodegenError) -> Self {
+        match err {
+            CodegenError::Unsupported(diagnostics) => ReplError::errors(&diagnostics),
+            err => ReplError::Codegen(err),
+        }
+    }
+}
+
+impl ReplError {
+    fn errors(diagnostics: &Diagnostics) -> Self {
+        ReplError::Diagnostics(diagnostics.errors().into_iter().cloned().collect())
+    }
+}
+
+/// A variable declared at the prompt: the name of its type, and its value as
+/// an expression assigning it in the scratch function
+#[derive(Debug, Clone)]
+struct Variable {
+    ty: String,
+    value: String,
+}
+
+/// The scratch function resolved, in the analysis of the free functions
+struct Scratch {
+    ctx: Context,
+    function_no: usize,
+}
+
+/// The free functions and variables defined at the prompt
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// The source of the free functions, in order of definition
+    functions: Vec<String>,
+    variables: IndexMap<String, Variable>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate an expression, or a statement when the input ends with `;` or
+    /// `}`. The value of the expression or of the declared variable is
+    /// returned. Input starting with `function` defines free functions.
+    pub fn eval(&mut self, input: &str) -> Result<Option<Value>, ReplError> {
+        let input = input.trim();
+
+        if input.starts_with("function") {
+            return self.define(input).map(|()| None);
+        }
+
+        if !input.ends_with(';') && !input.ends_with('}') {
+            parse_expression(input, 0).map_err(ReplError::Diagnostics)?;
+            return self.expression(input);
+        }
+
+        match parse_statement(input, 0).map_err(ReplError::Diagnostics)? {
+            pt::Statement::Expression(_, expr) => self.expression(text(input, &expr.loc())),
+            pt::Statement::VariableDefinition(_, decl, value) => {
+                self.declare(input, &decl, value.as_ref())
+            }
+            _ => self.run(input, &[]).map(|_| None),
+        }
+    }
+
+    /// Define the free functions of the source, which must be generated
+    fn define(&mut self, source: &str) -> Result<(), ReplError> {
+        self.functions.push(source.to_string());
+        let result = self.scratch("", &[]).and_then(|scratch| generate(&scratch).map(drop));
+        if result.is_err() {
+            self.functions.pop();
+        }
+        result
+    }
+
+    /// Evaluate an expression, folded when the analysis finds it constant
+    fn expression(&mut self, expr: &str) -> Result<Option<Value>, ReplError> {
+        let scratch = self.scratch(&format!("{expr};"), &[])?;
+        let func = &scratch.ctx.functions[scratch.function_no];
+        let Some(Statement::Expression(_, _, resolved)) =
+            func.body.iter().rev().find(|stmt| matches!(stmt, Statement::Expression(..)))
+        else {
+            return Err(ReplError::Unsupported(format!("`{expr}`")));
+        };
+
+        if let Some(value) = constant(resolved, &scratch.ctx) {
+            return Ok(Some(value));
+        }
+        if let Expression::InternalFunctionCall { returns, .. } = resolved {
+            if returns.len() > 1 {
+                return Err(ReplError::Unsupported("an expression of several values".into()));
+            }
+        }
+
+        let ty = resolved.ty();
+        if ty == Type::Void {
+            return self.run(&format!("{expr};"), &[]).map(|_| None);
+        }
+        if !is_supported(&ty) {
+            let ty = ty.to_string(&scratch.ctx);
+            return Err(ReplError::Unsupported(format!("a value of type '{ty}'")));
+        }
+        let ty = ty.to_string(&scratch.ctx);
+        self.run(&format!("{VALUE} = {expr};"), &[(ty, VALUE.to_string())])
+    }
+
+    /// Declare a variable of the session, replacing one of the same name
+    fn declare(
+        &mut self,
+        input: &str,
+        decl: &pt::VariableDeclaration,
+        value: Option<&pt::Expression>,
+    ) -> Result<Option<Value>, ReplError> {
+        let (Some(name), None) = (&decl.name, &decl.storage) else {
+            return Err(ReplError::Unsupported("a variable with a data location".into()));
+        };
+        let body = match value {
+            Some(value) => format!("{} = {};", name.name, text(input, &value.loc())),
+            None => String::new(),
+        };
+        let ty = text(input, &decl.ty.loc()).to_string();
+        self.run(&body, &[(ty, name.name.clone())])
+    }
+
+    /// Run the body in the scratch function with the results given, before
+    /// the variables of the session, giving the value of the last result
+    /// given, and keeping the values of the variables
+    fn run(
+        &mut self,
+        body: &str,
+        results: &[(String, String)],
+    ) -> Result<Option<Value>, ReplError> {
+        let scratch = self.scratch(body, results)?;
+        let words = execute(&scratch)?;
+
+        let ctx = &scratch.ctx;
+        let mut value = None;
+        for (param, word) in ctx.functions[scratch.function_no].returns.iter().zip(words) {
+            let name = param.id.as_ref().map(|id| id.name.clone()).unwrap_or_default();
+            let ty = param.ty.to_string(ctx);
+            if results.iter().any(|(_, result)| *result == name) {
+                value = Some(Value { value: format_value(&word, &param.ty), ty: ty.clone() });
+            }
+            if name != VALUE {
+                self.variables.insert(name, Variable { ty, value: literal(&word, &param.ty) });
+            }
+        }
+        Ok(value)
+    }
+
+    /// Resolve the body in the scratch function, named with the results given
+    /// and with the variables of the session, which are assigned their values
+    /// first. The results must be of value types held in words.
+    fn scratch(&self, body: &str, results: &[(String, String)]) -> Result<Scratch, ReplError> {
+        let variables = self
+            .variables
+            .iter()
+            .filter(|(name, _)| results.iter().all(|(_, result)| result != *name))
+            .collect::<Vec<_>>();
+        let returns = results
+            .iter()
+            .map(|(ty, name)| format!("{ty} {name}"))
+            .chain(variables.iter().map(|(name, var)| format!("{} {name}", var.ty)))
+            .collect::<Vec<_>>();
+        let returns = match returns.is_empty() {
+            true => String::new(),
+            false => format!(" returns ({})", returns.join(", ")),
+        };
+        let assignments = variables
+            .iter()
+            .map(|(name, var)| format!("{name} = {};", var.value))
+            .collect::<String>();
+        let contents = format!(
+            "{}\nfunction {SCRATCH}(){returns} {{ {assignments} {body}\n}}\n",
+            self.functions.join("\n")
+        );
+
+        let config = Config::default();
+        let mut resolver = driver::resolver(&config);
+        let input = Input::Source { name: "repl.sol".into(), contents };
+        let file = driver::load(&input, &mut resolver)
+            .map_err(|diagnostic| ReplError::Diagnostics(vec![diagnostic]))?;
+        let ctx = driver::analyze(&file, &mut resolver, &[]);
+        if ctx.diagnostics.any_errors() {
+            return Err(ReplError::errors(&ctx.diagnostics));
+        }
+
+        let function_no = ctx
+            .functions
+            .iter()
+            .position(|func| func.contract_no.is_none() && func.id.name == SCRATCH)
+            .ok_or_else(|| ReplError::Unsupported(format!("`{body}`")))?;
+        if let Some(param) =
+            ctx.functions[function_no].returns.iter().find(|param| !is_supported(&param.ty))
+        {
+            let ty = param.ty.to_string(&ctx);
+            return Err(ReplError::Unsupported(format!("a value of type '{ty}'")));
+        }
+
+        Ok(Scratch { ctx, function_no })
+    }
+}
+
+/// The text of the input at the location
+fn text<'a>(input: &'a str, loc: &pt::Loc) -> &'a str {
+    &input[loc.start()..loc.end()]
+}
+
+/// Whether values of the type are held in words, as the scratch function
+/// returns them
+fn is_supported(ty: &Type) -> bool {
+    matches!(ty, Type::Bool | Type::Int(_) | Type::Uint(_) | Type::Address(_) | Type::Bytes(_))
+}
+
+/// The value of an expression the analysis folds to a literal
+fn constant(expr: &Expression, ctx: &Context) -> Option<Value> {
+    if let Expression::BoolLiteral { value, .. } = expr {
+        return Some(Value { value: value.to_string(), ty: Type::Bool.to_string(ctx) });
+    }
+    match eval_constants_in_expression(expr, &mut Diagnostics::default()).0? {
+        Expression::NumberLiteral { ty: ty @ (Type::Int(_) | Type::Uint(_)), value, .. } => {
+            Some(Value { value: value.to_string(), ty: ty.to_string(ctx) })
+        }
+        _ => None,
+    }
+}
+
+/// Generate the functions of the analysis for the host
+fn generate(scratch: &Scratch) -> Result<Vec<u8>, ReplError> {
+    use crate::codegen::{Codegen, CodegenOptions};
+
+    // All the functions, for the host
+    let options = CodegenOptions { dce: false, ..CodegenOptions::default() };
+    let mut generator = Codegen::with_options(&options)?;
+    generator.gen(&scratch.ctx)?;
+    Ok(generator.finish()?)
+}
+
+/// Run the scratch function, giving the words of its results
+#[cfg(unix)]
+fn execute(scratch: &Scratch) -> Result<Vec<[u8; 32]>, ReplError> {
+    use crate::{
+        codegen::mangle::Symbol,
+        jit::{Machine, Outcome},
+    };
+
+    let mut machine = Machine::load(&generate(scratch)?)?;
+    let func = &scratch.ctx.functions[scratch.function_no];
+    let symbol = Symbol::function(func, &scratch.ctx);
+    match machine.call_function(&symbol, &[], func.returns.len())? {
+        Outcome::Returned(data) => {
+            Ok(data.chunks_exact(32).map(|word| word.try_into().unwrap()).collect())
+        }
+        Outcome::Reverted(data) => Err(ReplError::Reverted(data)),
+        Outcome::Trapped(_) => Err(ReplError::Trapped),
+    }
+}
+
+#[cfg(not(unix))]
+fn execute(_scratch: &Scratch) -> Result<Vec<[u8; 32]>, ReplError> {
+    Err(ReplError::Unsupported("running the generated code on this platform".into()))
+}
+
+/// The value of the word held in the type, as Solidity writes it
+#[cfg(unix)]
+fn format_value(word: &[u8; 32], ty: &Type) -> String {
+    crate::jit::format_value(word, ty)
+}
+
+#[cfg(not(unix))]
+fn format_value(_word: &[u8; 32], _ty: &Type) -> String {
+    unreachable!("no code is run on this platform")
+}
+
+/// An expression of the value of the word held in the type, assigned to a
+/// variable of the type
+fn literal(word: &[u8; 32], ty: &Type) -> String {
+    let value = num_bigint::BigInt::from_bytes_be(num_bigint::Sign::Plus, word);
+    match ty {
+        Type::Address(true) => format!("payable(address(uint160({value})))"),
+        Type::Address(false) => format!("address(uint160({value}))"),
+        Type::Bytes(n) => format!("bytes{n}(uint{}({value}))", 8 * u16::from(*n)),
+        ty => format_value(word, ty),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(session: &mut Session, input: &str) -> String {
+        match session.eval(input) {
+            Ok(Some(value)) => format!("{value}: {}", value.type_name()),
+            Ok(None) => String::new(),
+            Err(err) => format!("error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_literal_math() {
+        let mut session = Session::new();
+
+        assert_eq!(eval(&mut session, "1 + 2 * 3"), "7: uint8");
+        assert_eq!(eval(&mut session, "-7"), "-7: int8");
+        assert_eq!(eval(&mut session, "1.5e3"), "1500: uint16");
+        assert_eq!(eval(&mut session, "1 ether / 1 gwei"), "1000000000: uint32");
+        assert_eq!(eval(&mut session, "true"), "true: bool");
+        assert_eq!(
+            eval(&mut session, "1 / 3"),
+            "error: a value of type 'rational' is not supported in the REPL"
+        );
+        assert_eq!(eval(&mut session, "1 / 0"), "error: divide by zero");
+        assert_eq!(
+            eval(&mut session, "uint8(256)"),
+            "error: explicit conversion of 256 to 'uint8' not allowed, as it does not fit"
+        );
+        assert_eq!(eval(&mut session, "y"), "error: 'y' not found");
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn test_typed_values() {
+        let mut session = Session::new();
+
+        assert_eq!(eval(&mut session, "uint8 a = 200;"), "200: uint8");
+        assert_eq!(eval(&mut session, "a + 55"), "255: uint8");
+        assert!(eval(&mut session, "a + 56").starts_with("error: the evaluation trapped"));
+        assert_eq!(
+            eval(&mut session, "a + 300"),
+            "error: implicit conversion would truncate from 'uint16' to 'uint8'"
+        );
+        assert_eq!(eval(&mut session, "uint16(a) + 300"), "500: uint16");
+        assert_eq!(eval(&mut session, "int8(a)"), "-56: int8");
+        assert_eq!(eval(&mut session, "uint8(uint16(a) + 300)"), "244: uint8");
+        assert_eq!(eval(&mut session, "a++"), "200: uint8");
+        assert_eq!(eval(&mut session, "a"), "201: uint8");
+        assert_eq!(eval(&mut session, "a -= 1;"), "200: uint8");
+        assert_eq!(eval(&mut session, "for (uint8 i = 1; i <= 4; i++) { a += i; }"), "");
+        assert_eq!(eval(&mut session, "a"), "210: uint8");
+        assert_eq!(eval(&mut session, "int x = -7;"), "-7: int256");
+        assert_eq!(eval(&mut session, "x / 2"), "-3: int256");
+        assert_eq!(eval(&mut session, "x % 2 == -1 && a > 0"), "true: bool");
+        assert_eq!(eval(&mut session, "bytes2 b = 0x1234;"), "0x1234: bytes2");
+        assert_eq!(eval(&mut session, "b"), "0x1234: bytes2");
+        assert_eq!(
+            eval(&mut session, "address o = address(0xbeef);"),
+            "0x000000000000000000000000000000000000beef: address"
+        );
+        assert_eq!(eval(&mut session, "o == address(0xbeef)"), "true: bool");
+        assert_eq!(eval(&mut session, "uint8 a = 7;"), "7: uint8");
+        assert_eq!(eval(&mut session, "a"), "7: uint8");
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn test_run_functions() {
+        let mut session = Session::new();
+
+        for source in [
+            "function fib(uint n) pure returns (uint) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }",
+            "function sum(uint8 n) pure returns (uint8 s) { for (uint8 i = 1; i <= n; i++) { s += i; } }",
+            "function divmod(uint a, uint b) pure returns (uint, uint) { return (a / b, a % b); }",
+        ] {
+            assert_eq!(eval(&mut session, source), "");
+        }
+
+        assert_eq!(eval(&mut session, "fib(10)"), "55: uint256");
+        assert_eq!(eval(&mut session, "sum(10)"), "55: uint8");
+        assert!(eval(&mut session, "sum(30)").starts_with("error: the evaluation trapped"));
+        assert_eq!(
+            eval(&mut session, "divmod(7, 2)"),
+            "error: an expression of several values is not supported in the REPL"
+        );
+        assert_eq!(eval(&mut session, "fib(12) + 1"), "145: uint256");
+
+        // A function which cannot be resolved is not defined
+        assert_eq!(
+            eval(&mut session, "function g() pure returns (uint) { return y; }"),
+            "error: 'y' not found"
+        );
+        assert_eq!(eval(&mut session, "g()"), "error: 'g' not found");
+    }
+}
@@ -31,6 +31,8 @@ pub struct FileResolver {
     cached_paths: HashMap<PathBuf, usize>,
     /// The actual file contents
     files: Vec<ResolvedFile>,
+    /// Virtual modules by the name they are imported with
+    builtins: HashMap<PathBuf, Arc<str>>,
 }
 
 /// When we resolve a file, we need to know its base compared to the import so
@@ -96,11 +98,28 @@ impl FileResolver {
         self.cached_paths.insert(pathbuf, pos);
     }
 
+    /// Register a builtin module, e.g. a library of a target, which any file
+    /// can import by its name, like `import "hmt/builtins.sol";`. The name is
+    /// matched as written in the import, before searching the import paths.
+    pub fn add_builtin(&mut self, name: &str, contents: impl Into<Arc<str>>) {
+        self.builtins.insert(PathBuf::from(name), contents.into());
+    }
+
+    /// Get the contents of the builtin module imported with the given name
+    pub fn get_builtin(&self, name: &Path) -> Option<Arc<str>> {
+        self.builtins.get(name).cloned()
+    }
+
     /// Get the file contents of `no`th file if it exists
     pub fn get_contents_of_no(&self, no: usize) -> Option<Arc<str>> {
         self.files.get(no).map(|f| f.contents.clone())
     }
 
+    /// Get the files loaded so far, in the order they were loaded
+    pub fn files(&self) -> &[ResolvedFile] {
+        &self.files
+    }
+
     /// Get file with contents and number.
     /// This must be a file which was previously added to the cache.
     pub fn get_file_contents_and_no(&self, file: &Path) -> (Arc<str>, usize) {
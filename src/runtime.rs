@@ -0,0 +1,362 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The interface between the generated code and the runtime hosting it.
+//!
+//! Contracts cannot touch storage, logs or other accounts directly, they call
+//! the host functions listed here, which the runtime provides when linking or
+//! loading the object. The list is versioned: a function is never changed once
+//! released, a new version of the interface adds or replaces functions.
+
+use indexmap::IndexMap;
+
+use cranelift::{
+    codegen::{ir::Signature, isa::CallConv},
+    module::{FuncId, Linkage, Module, ModuleError},
+    prelude::{types, AbiParam, Type},
+};
+use strum::Display;
+
+use crate::helpers::json::Json;
+
+/// Version of the host function interface
+pub const RUNTIME_VERSION: u32 = 7;
+
+/// Size in bytes of the memory of a call, see [`crate::codegen::memory`]
+pub const MEMORY_SIZE: u64 = 1 << 20;
+
+/// Prefix of the symbols of the host functions
+pub const SYMBOL_PREFIX: &str = "__hmt_";
+
+/// The type of a parameter or result of a host function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum HostType {
+    /// A pointer into the memory of the contract, of the target pointer width
+    Ptr,
+    I32,
+    I64,
+}
+
+impl HostType {
+    fn to_clif(self, pointer_type: Type) -> Type {
+        match self {
+            HostType::Ptr => pointer_type,
+            HostType::I32 => types::I32,
+            HostType::I64 => types::I64,
+        }
+    }
+}
+
+/// A function imported from the runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostFunction {
+    pub name: &'static str,
+    pub params: &'static [(&'static str, HostType)],
+    pub returns: &'static [HostType],
+    /// Whether the function never returns to the contract
+    pub noreturn: bool,
+    pub description: &'static str,
+}
+
+impl HostFunction {
+    /// The name of the imported symbol
+    pub fn symbol(&self) -> String {
+        format!("{SYMBOL_PREFIX}{}", self.name)
+    }
+
+    /// The Cranelift signature of the function
+    pub fn signature(&self, call_conv: CallConv, pointer_type: Type) -> Signature {
+        let mut signature = Signature::new(call_conv);
+        signature
+            .params
+            .extend(self.params.iter().map(|(_, ty)| AbiParam::new(ty.to_clif(pointer_type))));
+        signature
+            .returns
+            .extend(self.returns.iter().map(|ty| AbiParam::new(ty.to_clif(pointer_type))));
+        signature
+    }
+
+    fn to_json(self) -> Json {
+        let params = self.params.iter().map(|(name, ty)| {
+            Json::object([("name", (*name).into()), ("type", ty.to_string().into())])
+        });
+
+        Json::object([
+            ("name", self.symbol().into()),
+            ("params", Json::Array(params.collect())),
+            ("returns", Json::Array(self.returns.iter().map(|ty| ty.to_string().into()).collect())),
+            ("noreturn", self.noreturn.into()),
+            ("description", self.description.into()),
+        ])
+    }
+}
+
+/// The host functions of [`RUNTIME_VERSION`]. Words of 32 bytes, like keys,
+/// values and addresses, are passed by pointer, in big endian order.
+pub const HOST_FUNCTIONS: &[HostFunction] = &[
+    HostFunction {
+        name: "storage_load",
+        params: &[("key", HostType::Ptr), ("value", HostType::Ptr)],
+        returns: &[],
+        noreturn: false,
+        description: "Read the storage word at the key into the value buffer",
+    },
+    HostFunction {
+        name: "storage_store",
+        params: &[("key", HostType::Ptr), ("value", HostType::Ptr)],
+        returns: &[],
+        noreturn: false,
+        description: "Write the value to the storage word at the key",
+    },
+    HostFunction {
+        name: "transient_load",
+        params: &[("key", HostType::Ptr), ("value", HostType::Ptr)],
+        returns: &[],
+        noreturn: false,
+        description: "Read the transient storage word at the key into the value buffer",
+    },
+    HostFunction {
+        name: "transient_store",
+        params: &[("key", HostType::Ptr), ("value", HostType::Ptr)],
+        returns: &[],
+        noreturn: false,
+        description: "Write the value to the transient storage word at the key, cleared after the transaction",
+    },
+    HostFunction {
+        name: "emit_log",
+        params: &[
+            ("data", HostType::Ptr),
+            ("data_len", HostType::I32),
+            ("topics", HostType::Ptr),
+            ("topic_count", HostType::I32),
+        ],
+        returns: &[],
+        noreturn: false,
+        description: "Emit a log with up to four topics of 32 bytes each",
+    },
+    HostFunction {
+        name: "call",
+        params: &[
+            ("address", HostType::Ptr),
+            ("value", HostType::Ptr),
+            ("input", HostType::Ptr),
+            ("input_len", HostType::I32),
+            ("gas", HostType::I64),
+        ],
+        returns: &[HostType::I32],
+        noreturn: false,
+        description: "Call another contract with the gas, all the gas left when negative, returns 0 on success and 1 when it reverted",
+    },
+    HostFunction {
+        name: "create",
+        params: &[
+            ("code", HostType::Ptr),
+            ("code_len", HostType::I32),
+            ("value", HostType::Ptr),
+            ("input", HostType::Ptr),
+            ("input_len", HostType::I32),
+            ("address", HostType::Ptr),
+        ],
+        returns: &[HostType::I32],
+        noreturn: false,
+        description: "Deploy a contract from its object code, running its constructor with the input, and write its address, returns 0 on success and 1 when it reverted",
+    },
+    HostFunction {
+        name: "return_data_size",
+        params: &[],
+        returns: &[HostType::I32],
+        noreturn: false,
+        description: "The size of the data returned by the last call",
+    },
+    HostFunction {
+        name: "return_data_copy",
+        params: &[("dest", HostType::Ptr), ("offset", HostType::I32), ("len", HostType::I32)],
+        returns: &[],
+        noreturn: false,
+        description: "Copy the data returned by the last call into memory",
+    },
+    HostFunction {
+        name: "calldata_size",
+        params: &[],
+        returns: &[HostType::I32],
+        noreturn: false,
+        description: "The size of the input of the current call, `msg.data.length`",
+    },
+    HostFunction {
+        name: "calldata_copy",
+        params: &[("dest", HostType::Ptr), ("offset", HostType::I32), ("len", HostType::I32)],
+        returns: &[],
+        noreturn: false,
+        description: "Copy the input of the current call into memory, the bytes past its end are zero",
+    },
+    HostFunction {
+        name: "value_transferred",
+        params: &[("dest", HostType::Ptr)],
+        returns: &[],
+        noreturn: false,
+        description: "Write the value sent with the current call, `msg.value`",
+    },
+    HostFunction {
+        name: "caller",
+        params: &[("dest", HostType::Ptr)],
+        returns: &[],
+        noreturn: false,
+        description: "Write the address of the caller, `msg.sender`",
+    },
+    HostFunction {
+        name: "return",
+        params: &[("data", HostType::Ptr), ("data_len", HostType::I32)],
+        returns: &[],
+        noreturn: true,
+        description: "End the call successfully with the data as the result",
+    },
+    HostFunction {
+        name: "revert",
+        params: &[("data", HostType::Ptr), ("data_len", HostType::I32)],
+        returns: &[],
+        noreturn: true,
+        description: "End the call, undoing its changes, with the data as the reason",
+    },
+    HostFunction {
+        name: "memory",
+        params: &[],
+        returns: &[HostType::Ptr],
+        noreturn: false,
+        description: "The memory of the current call, of `memory_size` bytes zeroed when the call starts",
+    },
+    HostFunction {
+        name: "word_op",
+        params: &[
+            ("op", HostType::I32),
+            ("a", HostType::Ptr),
+            ("b", HostType::Ptr),
+            ("c", HostType::Ptr),
+            ("result", HostType::Ptr),
+        ],
+        returns: &[],
+        noreturn: false,
+        description: "Compute the word operation of the manifest's `word_ops` on the words, the unused ones are null",
+    },
+    HostFunction {
+        name: "keccak256",
+        params: &[("data", HostType::Ptr), ("data_len", HostType::I32), ("hash", HostType::Ptr)],
+        returns: &[],
+        noreturn: false,
+        description: "Write the Keccak-256 hash of the data, which gives the storage keys of the elements of mappings",
+    },
+];
+
+/// The operations on words of [`RUNTIME_VERSION`] done by the `word_op` host
+/// function, with the semantics of the EVM instructions of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+#[repr(i32)]
+pub enum WordOp {
+    Div,
+    SDiv,
+    Mod,
+    SMod,
+    Exp,
+    AddMod,
+    MulMod,
+    SignExtend,
+    Byte,
+    Shl,
+    Shr,
+    Sar,
+}
+
+impl WordOp {
+    pub const ALL: &'static [WordOp] = &[
+        WordOp::Div,
+        WordOp::SDiv,
+        WordOp::Mod,
+        WordOp::SMod,
+        WordOp::Exp,
+        WordOp::AddMod,
+        WordOp::MulMod,
+        WordOp::SignExtend,
+        WordOp::Byte,
+        WordOp::Shl,
+        WordOp::Shr,
+        WordOp::Sar,
+    ];
+
+    /// The number of words the operation reads
+    pub fn arity(self) -> usize {
+        match self {
+            WordOp::AddMod | WordOp::MulMod => 3,
+            _ => 2,
+        }
+    }
+}
+
+/// Declare all the host functions as imports of the module, by their name
+pub fn declare_imports<M: Module>(
+    module: &mut M,
+) -> Result<IndexMap<String, FuncId>, Box<ModuleError>> {
+    let call_conv = module.isa().default_call_conv();
+    let pointer_type = module.isa().pointer_type();
+    let mut imports = IndexMap::new();
+
+    for function in HOST_FUNCTIONS {
+        let signature = function.signature(call_conv, pointer_type);
+        let id = module
+            .declare_function(&function.symbol(), Linkage::Import, &signature)
+            .map_err(Box::new)?;
+        imports.insert(function.name.to_string(), id);
+    }
+
+    Ok(imports)
+}
+
+/// The manifest of the host functions, for runtime implementers
+pub fn manifest() -> Json {
+    Json::object([
+        ("version", RUNTIME_VERSION.into()),
+        ("memory_size", MEMORY_SIZE.into()),
+        ("functions", Json::Array(HOST_FUNCTIONS.iter().map(|f| f.to_json()).collect())),
+        ("word_ops", Json::Array(WordOp::ALL.iter().map(|op| op.to_string().into()).collect())),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_functions() {
+        let mut names = HOST_FUNCTIONS.iter().map(|f| f.name).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), HOST_FUNCTIONS.len());
+
+        let call = HOST_FUNCTIONS.iter().find(|f| f.name == "call").unwrap();
+        let signature = call.signature(CallConv::SystemV, types::I64);
+        assert_eq!(signature.to_string(), "(i64, i64, i64, i32, i64) -> i32 system_v");
+
+        let manifest = manifest();
+        assert_eq!(manifest.get("version"), Some(&Json::Number("7".into())));
+        assert_eq!(
+            manifest.get("functions").and_then(|f| f.as_array()).map(|f| f.len()),
+            Some(HOST_FUNCTIONS.len())
+        );
+
+        // The code of an operation is its index in the manifest
+        for (i, op) in WordOp::ALL.iter().enumerate() {
+            assert_eq!(*op as usize, i);
+        }
+    }
+}
@@ -13,16 +13,19 @@
 // limitations under the License.
 
 use crate::{
+    helpers::trace::span,
     parser::{parse, visitor::Visitable},
     resolver::{FileResolver, ResolvedFile},
     semantic::{
         collector::AnnotationCollector,
         context::Context,
-        contract::{BaseContractResolver, ContractResolver},
+        contract::{self, BaseContractResolver, ContractResolver},
         file::File,
         function::FunctionResolver,
         import::ImportResolver,
+        legacy::LegacySyntaxChecker,
         mutability,
+        pass::{self, Pass},
         pragma::PragmaResolver,
         semicolon::StraySemicolonChecker,
         symtable::Symtable,
@@ -33,6 +36,8 @@ use crate::{
     },
 };
 
+use std::path::Path;
+
 use anyhow::{bail, Result};
 
 /// Parse and resolve a file and its imports in a recursive manner.
@@ -42,11 +47,40 @@ pub(crate) fn analyze(
     ctx: &mut Context,
 ) -> Result<()> {
     let no = ctx.files.len();
+    let _span = span!("analyze", file = no, path = file.full_path.display());
 
     let (source, cache_no) = resolver.get_file_contents_and_no(&file.full_path);
     ctx.files.push(File::new(file.full_path.clone(), &source, cache_no, file.import_no));
 
-    let mut ast = match parse(&source, no) {
+    analyze_source(no, &source, Some(file), resolver, ctx)
+}
+
+/// Parse and resolve a builtin module registered with the resolver, see
+/// [`FileResolver::add_builtin`]. Its file has no cache number.
+pub(crate) fn analyze_builtin(
+    name: &Path,
+    source: &str,
+    resolver: &mut FileResolver,
+    ctx: &mut Context,
+) -> Result<()> {
+    let no = ctx.files.len();
+    let _span = span!("analyze", file = no, path = name.display());
+
+    ctx.files.push(File::builtin(name.to_path_buf(), source));
+
+    analyze_source(no, source, None, resolver, ctx)
+}
+
+/// Run the passes on the source of the file `no`, which has been added to the
+/// context. Relative imports are resolved from the parent, if any.
+fn analyze_source(
+    no: usize,
+    source: &str,
+    parent: Option<&ResolvedFile>,
+    resolver: &mut FileResolver,
+    ctx: &mut Context,
+) -> Result<()> {
+    let mut ast = match parse(source, no) {
         Ok(ast) => ast,
         Err(mut errors) => {
             ctx.diagnostics.append(&mut errors);
@@ -59,31 +93,56 @@ pub(crate) fn analyze(
     let mut collector = AnnotationCollector::new(ctx);
     ast.visit(&mut collector)?;
     let mut tree = collector.collect();
+    ctx.files[no].tree = Some(ast.clone());
 
     // First resolve all the types we can find
-    tree.visit(&mut TypeResolver::new(ctx, no))?;
+    pass::run(ctx, Pass::Types, no, |ctx| tree.visit(&mut TypeResolver::new(ctx, no)))
+        .transpose()?;
 
     // Resolve pragmas and imports
-    tree.visit(&mut PragmaResolver::new(ctx))?;
-    tree.visit(&mut ImportResolver::new(ctx, resolver, Some(file), no))?;
+    pass::run(ctx, Pass::Pragmas, no, |ctx| tree.visit(&mut PragmaResolver::new(ctx)))
+        .transpose()?;
+    pass::run(ctx, Pass::Legacy, no, |ctx| ast.visit(&mut LegacySyntaxChecker::new(ctx, no)))
+        .transpose()?;
+    pass::run(ctx, Pass::Imports, no, |ctx| {
+        tree.visit(&mut ImportResolver::new(ctx, resolver, parent, no))
+    })
+    .transpose()?;
 
     // Resolve the base contracts list and check for cycles.
-    tree.visit(&mut BaseContractResolver::new(ctx, no))?;
+    pass::run(ctx, Pass::Bases, no, |ctx| tree.visit(&mut BaseContractResolver::new(ctx, no)))
+        .transpose()?;
 
-    tree.visit(&mut FunctionResolver::new(ctx, no))?;
-    tree.visit(&mut VariableResolver::new(ctx, no, None, None, &mut Symtable::default()))?;
+    pass::run(ctx, Pass::Functions, no, |ctx| tree.visit(&mut FunctionResolver::new(ctx, no)))
+        .transpose()?;
+    pass::run(ctx, Pass::Variables, no, |ctx| {
+        tree.visit(&mut VariableResolver::new(ctx, no, None, None, &mut Symtable::default()))
+    })
+    .transpose()?;
 
     // Now we can resolve the global using directives
-    tree.visit(&mut UsingResolver::new(ctx, no, None))?;
+    pass::run(ctx, Pass::Using, no, |ctx| tree.visit(&mut UsingResolver::new(ctx, no, None)))
+        .transpose()?;
 
     // Now resolve the contracts
-    tree.visit(&mut ContractResolver::new(ctx, no))?;
+    pass::run(ctx, Pass::Contracts, no, |ctx| tree.visit(&mut ContractResolver::new(ctx, no)))
+        .transpose()?;
+
+    // A contract cannot embed its own code
+    pass::run(ctx, Pass::Creates, no, |ctx| {
+        for contract_no in 0..ctx.contracts.len() {
+            if ctx.contracts[contract_no].loc.no() == Some(no) {
+                contract::check_circular_creation(contract_no, ctx);
+            }
+        }
+    });
 
     // Check for stray semicolons
-    ast.visit(&mut StraySemicolonChecker::new(ctx))?;
+    pass::run(ctx, Pass::Semicolons, no, |ctx| ast.visit(&mut StraySemicolonChecker::new(ctx)))
+        .transpose()?;
 
     // Now check state mutability for all contracts
-    mutability::check(ctx, no);
+    pass::run(ctx, Pass::Mutability, no, |ctx| mutability::check(ctx, no));
 
     Ok(())
 }
@@ -13,6 +13,8 @@
 // limitations under the License.
 
 use crate::{
+    abi::ethereum,
+    codegen::{mangle, metadata::Metadata},
     helpers::{CodeLocation, OptionalCodeLocation},
     parser::ast as pt,
     semantic::symtable::Symtable,
@@ -20,11 +22,13 @@ use crate::{
 
 use indexmap::IndexMap;
 use num_bigint::BigInt;
-use num_rational::BigRational;
+use num_rational::Ratio;
+use num_traits::Zero;
 use once_cell::unsync::OnceCell;
 
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
     fmt,
     fmt::Write,
     hash,
@@ -32,7 +36,7 @@ use std::{
     sync::Arc,
 };
 
-use super::context::Context;
+use super::{context::Context, intern::TypeId};
 pub struct SourceUnit {
     pub parts: Vec<SourceUnitPart>,
     pub contracts: Vec<ContractDefinition>,
@@ -170,6 +174,14 @@ impl Type {
         }
     }
 
+    /// The type referenced by a memory or storage reference, else the type itself
+    pub fn deref_any(&self) -> &Type {
+        match self {
+            Type::Ref(ty) | Type::StorageRef(_, ty) => ty,
+            _ => self,
+        }
+    }
+
     pub fn unwrap_user_type(self, ctx: &Context) -> Type {
         if let Type::UserType(type_no) = self {
             ctx.user_types[type_no].ty.clone()
@@ -226,6 +238,11 @@ pub struct EventDecl {
 }
 
 impl EventDecl {
+    /// The first topic of the logs of the event, the hash of its signature
+    pub fn selector(&self, ctx: &Context) -> [u8; 32] {
+        ethereum::topic(&ethereum::signature(&self.id.name, &self.fields, ctx))
+    }
+
     pub fn symbol_name(&self, ctx: &Context) -> String {
         match &self.contract {
             Some(c) => format!("{}.{}", ctx.contracts[*c].id, self.id),
@@ -245,6 +262,11 @@ pub struct ErrorDecl {
 }
 
 impl ErrorDecl {
+    /// The selector the data of the error starts with
+    pub fn selector(&self, ctx: &Context) -> [u8; 4] {
+        ethereum::selector(&ethereum::signature(&self.name, &self.fields, ctx))
+    }
+
     pub fn symbol_name(&self, ctx: &Context) -> String {
         match &self.contract {
             Some(c) => format!("{}.{}", ctx.contracts[*c].id, self.name),
@@ -291,6 +313,9 @@ pub struct Parameter<Type> {
     /// The name can empty (e.g. in an event field or unnamed parameter/return)
     pub id: Option<pt::Identifier>,
     pub ty: Type,
+    /// The handle of the type in [`Context::type_arena`], interned when the
+    /// parameters of the functions declared are resolved
+    pub ty_id: Option<TypeId>,
     /// Yul function parameters may not have a type identifier
     pub ty_loc: Option<pt::Loc>,
     /// Event fields may indexed, which means they are sent to the log
@@ -319,6 +344,7 @@ impl Parameter<Type> {
             ty,
             loc: Default::default(),
             id: Default::default(),
+            ty_id: Default::default(),
             ty_loc: Default::default(),
             indexed: Default::default(),
             readonly: Default::default(),
@@ -448,13 +474,12 @@ impl Function {
         visibility: pt::Visibility,
         params: Vec<Parameter<Type>>,
         returns: Vec<Parameter<Type>>,
-        _ctx: &Context,
+        ctx: &Context,
     ) -> Self {
         let signature = match ty {
             pt::FunctionTy::Fallback => String::from("@fallback"),
             pt::FunctionTy::Receive => String::from("@receive"),
-            // _ => ctx.signature(&id.name, &params),
-            _ => String::new(), // FIXME
+            _ => ethereum::signature(&id.name, &params, ctx),
         };
 
         let mutability = match mutability {
@@ -502,32 +527,14 @@ impl Function {
         }
     }
 
-    // /// Generate selector for this function
-    // pub fn selector(&self, ctx: &Context, contract_no: &usize) -> Vec<u8> {
-    //     if let Some((_, selector)) = &self.selector {
-    //         selector.clone()
-    //     } else if ctx.target == Target::Solana {
-    //         match self.ty {
-    //             FunctionTy::Constructor => function_discriminator("new"),
-    //             _ => {
-    //                 let discriminator_image = if
-    // self.mangled_name_contracts.contains(contract_no) {
-    // &self.mangled_name                 } else {
-    //                     &self.id.name
-    //                 };
-    //                 function_discriminator(discriminator_image.as_str())
-    //             }
-    //         }
-    //     } else {
-    //         let mut res = [0u8; 32];
-
-    //         let mut hasher = Keccak::v256();
-    //         hasher.update(self.signature.as_bytes());
-    //         hasher.finalize(&mut res);
-
-    //         res[..4].to_vec()
-    //     }
-    // }
+    /// The selector of the function: the one set with an annotation, or the
+    /// first four bytes of the Keccak-256 hash of its signature
+    pub fn selector(&self) -> Vec<u8> {
+        match &self.selector {
+            Some((_, selector)) => selector.clone(),
+            None => ethereum::selector(&self.signature).to_vec(),
+        }
+    }
 
     /// Is this a constructor
     pub fn is_constructor(&self) -> bool {
@@ -554,6 +561,29 @@ impl Function {
         matches!(self.mutability, Mutability::Pure(_))
     }
 
+    /// Does this function have the view state
+    pub fn is_view(&self) -> bool {
+        matches!(self.mutability, Mutability::View(_))
+    }
+
+    /// Is this a modifier
+    pub fn is_modifier(&self) -> bool {
+        self.ty == pt::FunctionTy::Modifier
+    }
+
+    /// Can this function be declared payable. Value is only sent with calls
+    /// from outside the contract and when deploying it, so internal and
+    /// private functions and modifiers cannot be payable.
+    pub fn can_be_payable(&self) -> bool {
+        match self.ty {
+            pt::FunctionTy::Constructor | pt::FunctionTy::Fallback | pt::FunctionTy::Receive => {
+                true
+            }
+            pt::FunctionTy::Function => self.is_public(),
+            pt::FunctionTy::Modifier => false,
+        }
+    }
+
     /// Is this function visible externally, based on it's visibilty modifiers.
     ///
     /// Due to inheritance, this alone does not determine whether a function is
@@ -567,6 +597,19 @@ impl Function {
     pub fn is_private(&self) -> bool {
         matches!(self.visibility, pt::Visibility::Private(_))
     }
+
+    /// The type of a pointer to the function, internal or external
+    pub fn function_type(&self, external: bool) -> Type {
+        let params = self.params.iter().map(|param| param.ty.clone()).collect();
+        let returns = self.returns.iter().map(|param| param.ty.clone()).collect();
+        let mutability = self.mutability.clone();
+
+        if external {
+            Type::ExternalFunction { mutability, params, returns }
+        } else {
+            Type::InternalFunction { mutability, params, returns }
+        }
+    }
 }
 
 impl From<&pt::Type> for Type {
@@ -621,6 +664,8 @@ pub struct Variable {
     pub assigned: bool,
     pub read: bool,
     pub storage_type: Option<pt::StorageType>,
+    /// The data location of a local variable or parameter, `None` for state variables
+    pub storage_location: Option<pt::StorageLocation>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -711,11 +756,116 @@ pub enum VersionReq {
     Or { loc: pt::Loc, left: Box<VersionReq>, right: Box<VersionReq> },
 }
 
+impl VersionReq {
+    /// Whether the requirement admits a version older than the given one
+    pub fn admits_older(&self, than: &Version) -> bool {
+        match self {
+            VersionReq::Plain { version, .. } | VersionReq::Range { from: version, .. } => {
+                version.is_older(than)
+            }
+            VersionReq::Operator { op, version, .. } => match op {
+                pt::VersionOp::Less | pt::VersionOp::LessEq | pt::VersionOp::Wildcard => true,
+                _ => version.is_older(than),
+            },
+            VersionReq::Or { left, right, .. } => {
+                left.admits_older(than) || right.admits_older(than)
+            }
+        }
+    }
+
+    /// Whether the requirement admits the given version or a newer one
+    pub fn admits_since(&self, since: &Version) -> bool {
+        let parts = (since.major, since.minor.unwrap_or(0), since.patch.unwrap_or(0));
+        match self.upper_bound() {
+            Some(upper) => parts < upper,
+            None => true,
+        }
+    }
+
+    /// The first version newer than those admitted, `None` when unbounded
+    fn upper_bound(&self) -> Option<(u32, u32, u32)> {
+        // The versions starting with the given parts, `0.8` is any `0.8.x`
+        let exact = |v: &Version| match (v.minor, v.patch) {
+            (Some(minor), Some(patch)) => (v.major, minor, patch + 1),
+            (Some(minor), None) => (v.major, minor + 1, 0),
+            _ => (v.major + 1, 0, 0),
+        };
+
+        match self {
+            VersionReq::Plain { version, .. } | VersionReq::Range { to: version, .. } => {
+                Some(exact(version))
+            }
+            VersionReq::Operator { op, version, .. } => match op {
+                pt::VersionOp::Exact | pt::VersionOp::LessEq => Some(exact(version)),
+                pt::VersionOp::Less => {
+                    Some((version.major, version.minor.unwrap_or(0), version.patch.unwrap_or(0)))
+                }
+                pt::VersionOp::Tilde => Some(match version.minor {
+                    Some(minor) => (version.major, minor + 1, 0),
+                    None => (version.major + 1, 0, 0),
+                }),
+                // `^0.8.1` admits `0.8.x`, `^1.2` admits `1.x`
+                pt::VersionOp::Caret => Some(match version.minor {
+                    Some(minor) if version.major == 0 => (0, minor + 1, 0),
+                    _ => (version.major + 1, 0, 0),
+                }),
+                pt::VersionOp::Greater | pt::VersionOp::GreaterEq | pt::VersionOp::Wildcard => None,
+            },
+            VersionReq::Or { left, right, .. } => {
+                Some(left.upper_bound()?.max(right.upper_bound()?))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Version {
     pub major: u32,
     pub minor: Option<u32>,
     pub patch: Option<u32>,
+    /// The pre-release, like `rc.1`. Build metadata is not kept, it does not
+    /// take part in comparisons.
+    pub pre: Option<String>,
+}
+
+impl Version {
+    /// Whether the version is older, missing parts count as zero. As in
+    /// semver, a pre-release is older than its release.
+    pub fn is_older(&self, than: &Version) -> bool {
+        let parts = |v: &Version| (v.major, v.minor.unwrap_or(0), v.patch.unwrap_or(0));
+        match parts(self).cmp(&parts(than)) {
+            Ordering::Equal => match (&self.pre, &than.pre) {
+                (Some(pre), Some(than)) => compare_pre_release(pre, than) == Ordering::Less,
+                (Some(_), None) => true,
+                _ => false,
+            },
+            ordering => ordering == Ordering::Less,
+        }
+    }
+}
+
+/// Compare pre-releases by their dot separated identifiers, numeric ones
+/// numerically and before alphanumeric ones, a prefix being older
+fn compare_pre_release(pre: &str, than: &str) -> Ordering {
+    let mut left = pre.split('.');
+    let mut right = than.split('.');
+
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) => match (l.parse::<u64>(), r.parse::<u64>()) {
+                (Ok(l), Ok(r)) => l.cmp(&r),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => l.cmp(r),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
 }
 
 impl fmt::Display for Version {
@@ -729,16 +879,53 @@ impl fmt::Display for Version {
             f.write_char('.')?;
             patch.fmt(f)?;
         }
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Layout {
     pub slot: BigInt,
+    /// The byte of the slot the value starts at, from its low order end, for
+    /// the values packed with others in a slot
+    pub offset: u8,
     pub contract_no: usize,
     pub var_no: usize,
     pub ty: Type,
+    /// The storage the slot is in, each class is laid out from slot 0
+    pub storage_class: StorageClass,
+}
+
+/// The storage class of a state variable, set with the Soroban `persistent`,
+/// `temporary` and `instance` attributes. Variables without one are persistent.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum StorageClass {
+    Persistent,
+    Temporary,
+    Instance,
+}
+
+impl From<&pt::StorageType> for StorageClass {
+    fn from(ty: &pt::StorageType) -> Self {
+        match ty {
+            pt::StorageType::Persistent(_) => StorageClass::Persistent,
+            pt::StorageType::Temporary(_) => StorageClass::Temporary,
+            pt::StorageType::Instance(_) => StorageClass::Instance,
+        }
+    }
+}
+
+impl fmt::Display for StorageClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageClass::Persistent => f.write_str("persistent"),
+            StorageClass::Temporary => f.write_str("temporary"),
+            StorageClass::Instance => f.write_str("instance"),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -777,31 +964,68 @@ pub struct Contract {
     pub id: pt::Identifier,
     pub bases: Vec<Base>,
     pub using: Vec<Using>,
+    /// The slots of the state variables of the contract and its bases
     pub layout: Vec<Layout>,
+    /// The number of slots of the persistent storage
     pub fixed_layout_size: BigInt,
     pub functions: Vec<usize>,
+    /// The functions of the contract and its bases, each mapped to the
+    /// function implementing it in the contract, the one overriding it last
     pub all_functions: BTreeMap<usize, usize>,
     /// maps the name of virtual functions to a vector of overriden functions.
     /// Each time a virtual function is overriden, there will be an entry pushed to the vector. The
     /// last element represents the current overriding function - there will be at least one
     /// entry in this vector.
-    pub virtual_functions: HashMap<String, Vec<usize>>,
+    pub virtual_functions: IndexMap<String, Vec<usize>>,
     pub yul_functions: Vec<usize>,
     pub variables: Vec<Variable>,
     /// List of contracts this contract instantiates
     pub creates: Vec<usize>,
     /// List of events this contract may emit
     pub emits_events: Vec<usize>,
-    pub initializer: Option<usize>,
-    pub default_constructor: Option<(Function, usize)>,
+    /// The symbol of the function initializing the contract when it is
+    /// deployed, its constructor with the initializers of the state variables.
+    /// Only available after emit.
+    pub initializer: OnceCell<mangle::Symbol>,
+    /// The constructor added to a contract which declares none, an index into
+    /// the functions of the Context
+    pub default_constructor: Option<usize>,
     // pub cfg: Vec<ControlFlowGraph>,
     /// Compiled program. Only available after emit.
     pub code: OnceCell<Vec<u8>>,
+    /// The metadata of the compiled program, with its code hash
+    pub metadata: OnceCell<Metadata>,
     /// Can the contract be instantiated, i.e. not abstract, no errors, etc.
     pub instantiable: bool,
 }
 
 impl Contract {
+    /// A contract as declared, before its bases and members are resolved
+    pub fn new(id: pt::Identifier, ty: pt::ContractTy, tags: Vec<Tag>, loc: pt::Loc) -> Self {
+        Contract {
+            tags,
+            loc,
+            ty,
+            id,
+            bases: Vec::new(),
+            using: Vec::new(),
+            layout: Vec::new(),
+            fixed_layout_size: BigInt::zero(),
+            functions: Vec::new(),
+            all_functions: BTreeMap::new(),
+            virtual_functions: IndexMap::new(),
+            yul_functions: Vec::new(),
+            variables: Vec::new(),
+            creates: Vec::new(),
+            emits_events: Vec::new(),
+            initializer: OnceCell::new(),
+            default_constructor: None,
+            code: OnceCell::new(),
+            metadata: OnceCell::new(),
+            instantiable: false,
+        }
+    }
+
     // Is this a concrete contract, which can be instantiated
     pub fn is_concrete(&self) -> bool {
         matches!(self.ty, pt::ContractTy::Contract(_))
@@ -864,7 +1088,7 @@ pub enum Expression {
     RationalNumberLiteral {
         loc: pt::Loc,
         ty: Type,
-        value: BigRational,
+        value: Ratio<BigInt>,
     },
     StructLiteral {
         loc: pt::Loc,
@@ -1226,6 +1450,11 @@ pub enum Expression {
         ty: Type,
         event_no: usize,
     },
+    InterfaceId {
+        loc: pt::Loc,
+        ty: Type,
+        contract_no: usize,
+    },
     TypeOperator {
         loc: pt::Loc,
         ty: Type,
@@ -1462,6 +1691,7 @@ impl Recurse for Expression {
                 Expression::BytesLiteral { .. } |
                 Expression::BoolLiteral { .. } |
                 Expression::EventSelector { .. } |
+                Expression::InterfaceId { .. } |
                 Expression::TypeOperator { .. } => (),
             }
         }
@@ -1535,6 +1765,7 @@ impl CodeLocation for Expression {
             Expression::NamedMember { loc, .. } |
             Expression::UserDefinedOperator { loc, .. } |
             Expression::EventSelector { loc, .. } |
+            Expression::InterfaceId { loc, .. } |
             Expression::TypeOperator { loc, .. } => *loc,
         }
     }
@@ -1559,7 +1790,7 @@ impl CodeLocation for Statement {
             Statement::Emit { loc, .. } |
             Statement::TryCatch(loc, ..) |
             Statement::Underscore(loc, ..) => *loc,
-            // Statement::Assembly(ia, _) => ia.loc,
+            Statement::Assembly(assembly, _) => assembly.loc,
         }
     }
 }
@@ -1785,7 +2016,16 @@ pub enum Statement {
     },
     TryCatch(pt::Loc, bool, TryCatch),
     Underscore(pt::Loc),
-    // Assembly(InlineAssembly, bool),
+    Assembly(InlineAssembly, bool),
+}
+
+/// An inline assembly block, whose Yul is kept as parsed
+#[derive(Clone, Debug)]
+pub struct InlineAssembly {
+    pub loc: pt::Loc,
+    pub block: pt::YulBlock,
+    /// The local variables in scope, by name
+    pub variables: BTreeMap<String, usize>,
 }
 
 #[derive(Clone, Debug)]
@@ -1908,8 +2148,8 @@ impl Statement {
             Statement::DoWhile(_, reachable, ..) |
             Statement::Expression(_, reachable, _) |
             Statement::For { reachable, .. } |
-            Statement::TryCatch(_, reachable, _) => *reachable,
-            // Statement::Assembly(_, reachable) => *reachable, // FIXME
+            Statement::TryCatch(_, reachable, _) |
+            Statement::Assembly(_, reachable) => *reachable,
         }
     }
 }
@@ -1921,3 +2161,130 @@ pub struct Tag {
     pub no: usize,
     pub value: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(
+        name: &str,
+        ty: pt::FunctionTy,
+        mutability: Option<pt::Mutability>,
+        visibility: pt::Visibility,
+        params: Vec<Type>,
+    ) -> Function {
+        Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+            Some(0),
+            Vec::new(),
+            ty,
+            mutability,
+            visibility,
+            params.into_iter().map(Parameter::new_default).collect(),
+            Vec::new(),
+            &Context::new(),
+        )
+    }
+
+    #[test]
+    fn test_function_signature() {
+        let mut transfer = function(
+            "transfer",
+            pt::FunctionTy::Function,
+            None,
+            pt::Visibility::External(None),
+            vec![Type::Address(false), Type::Uint(256)],
+        );
+        assert_eq!(transfer.signature, "transfer(address,uint256)");
+        assert_eq!(transfer.mangled_name, "transfer_address_uint256");
+        assert_eq!(transfer.selector(), [0xa9, 0x05, 0x9c, 0xbb]);
+
+        // A selector set with an annotation wins
+        transfer.selector = Some((pt::Loc::Builtin, vec![1, 2, 3, 4]));
+        assert_eq!(transfer.selector(), [1, 2, 3, 4]);
+
+        let fallback = function(
+            "",
+            pt::FunctionTy::Fallback,
+            None,
+            pt::Visibility::External(None),
+            Vec::new(),
+        );
+        assert_eq!(fallback.signature, "@fallback");
+    }
+
+    #[test]
+    fn test_function_mutability() {
+        let loc = pt::Loc::Builtin;
+
+        // Without a mutability a function is nonpayable, at its prototype
+        let func =
+            function("f", pt::FunctionTy::Function, None, pt::Visibility::Public(None), Vec::new());
+        assert_eq!(func.mutability, Mutability::Nonpayable(loc));
+        assert!(!func.is_payable() && !func.is_view() && !func.is_pure());
+        assert!(func.is_public() && func.can_be_payable());
+
+        // `constant` is the old spelling of `view`
+        let func = function(
+            "f",
+            pt::FunctionTy::Function,
+            Some(pt::Mutability::Constant(loc)),
+            pt::Visibility::Internal(None),
+            Vec::new(),
+        );
+        assert!(func.is_view());
+        assert!(!func.is_public() && !func.can_be_payable());
+
+        let func = function(
+            "",
+            pt::FunctionTy::Receive,
+            Some(pt::Mutability::Payable(loc)),
+            pt::Visibility::External(None),
+            Vec::new(),
+        );
+        assert!(func.is_payable() && func.can_be_payable());
+
+        let func = function(
+            "onlyOwner",
+            pt::FunctionTy::Modifier,
+            None,
+            pt::Visibility::Internal(None),
+            Vec::new(),
+        );
+        assert!(func.is_modifier() && !func.can_be_payable());
+    }
+
+    fn version(major: u32, minor: u32, patch: u32, pre: Option<&str>) -> Version {
+        Version { major, minor: Some(minor), patch: Some(patch), pre: pre.map(Into::into) }
+    }
+
+    #[test]
+    fn test_version_order() {
+        // The precedence of the semver specification
+        let versions = [
+            version(0, 8, 0, Some("alpha")),
+            version(0, 8, 0, Some("alpha.1")),
+            version(0, 8, 0, Some("alpha.beta")),
+            version(0, 8, 0, Some("beta.2")),
+            version(0, 8, 0, Some("beta.11")),
+            version(0, 8, 0, Some("rc.1")),
+            version(0, 8, 0, None),
+            version(0, 8, 1, Some("rc.1")),
+        ];
+        for (i, older) in versions.iter().enumerate() {
+            for (j, newer) in versions.iter().enumerate() {
+                assert_eq!(older.is_older(newer), i < j, "{older} older than {newer}");
+            }
+        }
+
+        let pre_release = VersionReq::Operator {
+            loc: pt::Loc::Builtin,
+            op: pt::VersionOp::Less,
+            version: version(0, 8, 18, Some("rc.1")),
+        };
+        assert!(pre_release.admits_older(&version(0, 8, 18, None)));
+        assert!(!pre_release.admits_since(&version(0, 8, 18, None)));
+    }
+}
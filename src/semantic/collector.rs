@@ -12,12 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::mem;
+use std::{collections::HashSet, mem};
 
 use thiserror::Error;
 
 use crate::{
-    diagnostics::Diagnostic,
+    diagnostics::{Diagnostic, Diagnostics},
+    helpers::CodeLocation,
     parser::{
         ast as pt,
         visitor::{Visitable, Visitor},
@@ -82,8 +83,11 @@ pub enum CollectorError {}
 impl<'a> Visitor for AnnotationCollector<'a> {
     type Error = CollectorError;
 
-    /// Visits and processes all parts of a source unit, handling annotations
+    /// Visits and processes all parts of a source unit, handling annotations.
+    /// The items excluded by the features are removed first.
     fn visit_source_unit(&mut self, source_unit: &mut pt::SourceUnit) -> Result<(), Self::Error> {
+        select_features(source_unit, &self.ctx.features, &mut self.ctx.diagnostics);
+
         for part in source_unit.0.iter_mut() {
             if let pt::SourceUnitPart::Annotation(note) = part {
                 self.annotations.push(note.as_ref().clone());
@@ -145,3 +149,199 @@ impl<'a> Visitor for AnnotationCollector<'a> {
         Ok(())
     }
 }
+
+/// Remove the items whose `@if` conditions do not hold for the features from
+/// the tree, with their annotations, and the `@if` annotations of the others.
+/// A condition is a feature name, or features combined with `!`, `&&` and
+/// `||`. An invalid condition is reported and keeps its item.
+pub fn select_features(
+    tree: &mut pt::SourceUnit,
+    features: &HashSet<String>,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut selector = FeatureSelector { features, diagnostics, conditions: Vec::new() };
+
+    let mut parts = Vec::new();
+    for mut part in mem::take(&mut tree.0) {
+        match &mut part {
+            pt::SourceUnitPart::Annotation(note) if note.id.name == "@if" => {
+                selector.conditions.push(note.as_ref().clone());
+                continue;
+            }
+            pt::SourceUnitPart::Annotation(_) => (),
+            _ if !selector.select() => {
+                // Drop the other annotations of the item too
+                while matches!(parts.last(), Some(pt::SourceUnitPart::Annotation(_))) {
+                    parts.pop();
+                }
+                continue;
+            }
+            pt::SourceUnitPart::ContractDefinition(contract) => {
+                selector.contract(contract);
+            }
+            _ => (),
+        }
+        parts.push(part);
+    }
+
+    selector.dangling("'contract' or other item");
+    tree.0 = parts;
+}
+
+/// Evaluates the `@if` conditions collected for the next item
+struct FeatureSelector<'a> {
+    features: &'a HashSet<String>,
+    diagnostics: &'a mut Diagnostics,
+    conditions: Vec<pt::Annotation>,
+}
+
+impl FeatureSelector<'_> {
+    /// Select the parts of the contract
+    fn contract(&mut self, contract: &mut pt::ContractDefinition) {
+        let mut parts = Vec::new();
+        for part in mem::take(&mut contract.parts) {
+            match &part {
+                pt::ContractPart::Annotation(note) if note.id.name == "@if" => {
+                    self.conditions.push(note.as_ref().clone());
+                    continue;
+                }
+                pt::ContractPart::Annotation(_) => (),
+                _ if !self.select() => {
+                    while matches!(parts.last(), Some(pt::ContractPart::Annotation(_))) {
+                        parts.pop();
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+            parts.push(part);
+        }
+
+        self.dangling("'constructor' or other item");
+        contract.parts = parts;
+    }
+
+    /// Whether the conditions collected for the item hold, taking them
+    fn select(&mut self) -> bool {
+        let mut selected = true;
+        for note in mem::take(&mut self.conditions) {
+            match &note.value {
+                Some(condition) => selected &= self.enabled(condition),
+                None => self
+                    .diagnostics
+                    .push(Diagnostic::error(note.loc, "'@if' requires a condition on features")),
+            }
+        }
+        selected
+    }
+
+    fn enabled(&mut self, condition: &pt::Expression) -> bool {
+        match condition {
+            pt::Expression::Variable(id) => self.features.contains(&id.name),
+            pt::Expression::Not(_, expr) => !self.enabled(expr),
+            pt::Expression::Parenthesis(_, expr) => self.enabled(expr),
+            pt::Expression::And(_, left, right) => {
+                let left = self.enabled(left);
+                self.enabled(right) && left
+            }
+            pt::Expression::Or(_, left, right) => {
+                let left = self.enabled(left);
+                self.enabled(right) || left
+            }
+            _ => {
+                self.diagnostics.push(Diagnostic::error(
+                    condition.loc(),
+                    "expected a feature name, or features combined with '!', '&&' and '||'",
+                ));
+                true
+            }
+        }
+    }
+
+    /// Report the conditions which are not followed by an item
+    fn dangling(&mut self, item: &str) {
+        for note in mem::take(&mut self.conditions) {
+            self.diagnostics
+                .push(Diagnostic::error(note.loc, format!("annotations should precede {item}")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    /// The names of the items left in the tree, those of contracts followed
+    /// by the names of their parts
+    fn select(src: &str, features: &[&str]) -> (Vec<String>, Diagnostics) {
+        let mut tree = parse(src, 0).unwrap();
+        let features = features.iter().map(|feature| feature.to_string()).collect();
+        let mut diagnostics = Diagnostics::default();
+        select_features(&mut tree, &features, &mut diagnostics);
+
+        let mut names = Vec::new();
+        for part in &tree.0 {
+            match part {
+                pt::SourceUnitPart::ContractDefinition(contract) => {
+                    names.push(contract.name.as_ref().unwrap().name.clone());
+                    for part in &contract.parts {
+                        match part {
+                            pt::ContractPart::FunctionDefinition(f) => {
+                                names.push(f.name.as_ref().unwrap().name.clone())
+                            }
+                            pt::ContractPart::VariableDefinition(v) => {
+                                names.push(v.name.as_ref().unwrap().name.clone())
+                            }
+                            pt::ContractPart::Annotation(note) => names.push(note.id.name.clone()),
+                            _ => (),
+                        }
+                    }
+                }
+                pt::SourceUnitPart::FunctionDefinition(f) => {
+                    names.push(f.name.as_ref().unwrap().name.clone())
+                }
+                pt::SourceUnitPart::Annotation(note) => names.push(note.id.name.clone()),
+                _ => (),
+            }
+        }
+        (names, diagnostics)
+    }
+
+    #[test]
+    fn test_select_features() {
+        let src = r#"
+            @if(debug)
+            function log() pure {}
+
+            @if(!debug)
+            contract Release {}
+
+            contract C {
+                @if(debug || test)
+                @seed("x")
+                uint a;
+                @if(debug)
+                @if(!(test && debug))
+                function f() public {}
+                @seed("y")
+                function g() public {}
+            }
+        "#;
+
+        assert_eq!(select(src, &[]).0, ["Release", "C", "@seed", "g"]);
+        assert_eq!(select(src, &["debug"]).0, ["log", "C", "@seed", "a", "f", "@seed", "g"]);
+        assert_eq!(select(src, &["debug", "test"]).0, ["log", "C", "@seed", "a", "@seed", "g"]);
+
+        let (names, diagnostics) = select("contract C { @if(1) uint a; @if(x) }", &[]);
+        assert_eq!(names, ["C", "a"]);
+        let messages = diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "expected a feature name, or features combined with '!', '&&' and '||'",
+                "annotations should precede 'constructor' or other item"
+            ]
+        );
+    }
+}
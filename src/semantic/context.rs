@@ -12,16 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
+use indexmap::IndexMap;
+
+use num_bigint::BigInt;
+use num_traits::Zero;
 
 use crate::{
-    diagnostics::{Diagnostic, Diagnostics, ErrorType, Level},
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics, ErrorType, Level, Note},
+    helpers::CodeLocation,
     parser::ast as pt,
+    semantic::expression::selector::identifier_path,
+    target::Target,
 };
 
-use super::{ast::*, file::File};
+use super::{ast::*, file::File, intern::TypeArena, pass::PassManager};
 
 /// Provides context information for the `resolve_type` function.
 #[derive(PartialEq, Eq)]
@@ -58,9 +65,9 @@ pub struct Context {
     pub value_length: usize,
     pub diagnostics: Diagnostics,
     /// There is a separate namespace for functions and non-functions
-    pub function_symbols: HashMap<(usize, Option<usize>, String), Symbol>,
+    pub function_symbols: IndexMap<(usize, Option<usize>, String), Symbol>,
     /// Symbol key is file_no, contract, identifier
-    pub variable_symbols: HashMap<(usize, Option<usize>, String), Symbol>,
+    pub variable_symbols: IndexMap<(usize, Option<usize>, String), Symbol>,
     // each variable in the symbol table should have a unique number
     pub next_id: usize,
     /// For a variable reference at a location, give the constant value
@@ -68,19 +75,98 @@ pub struct Context {
     // pub var_constants: HashMap<pt::Loc, codegen::Expression>,
     /// Overrides for hover in the language server
     pub hover_overrides: HashMap<pt::Loc, String>,
+    /// The passes to run on each file, and how long they took
+    pub passes: PassManager,
+    /// The platform the contracts are written for
+    pub target: Target,
+    /// The features switched on, which select the items marked with `@if`
+    pub features: HashSet<String>,
+    /// The interned types, compared by their handles
+    pub type_arena: TypeArena,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Context {
-    /// Add symbol to symbol table.
-    /// either returns true for success, or adds an appropriate error
+    /// Create an empty context, with the address and value lengths of Ethereum
+    pub fn new() -> Self {
+        Self {
+            pragmas: Vec::new(),
+            files: Vec::new(),
+            enums: Vec::new(),
+            structs: Vec::new(),
+            events: Vec::new(),
+            errors: Vec::new(),
+            contracts: Vec::new(),
+            using: Vec::new(),
+            user_types: Vec::new(),
+            functions: Vec::new(),
+            constants: Vec::new(),
+            address_length: 20,
+            value_length: 16,
+            diagnostics: Diagnostics::default(),
+            function_symbols: IndexMap::new(),
+            variable_symbols: IndexMap::new(),
+            next_id: 0,
+            hover_overrides: HashMap::new(),
+            passes: PassManager::default(),
+            target: Target::default(),
+            features: HashSet::new(),
+            type_arena: TypeArena::default(),
+        }
+    }
+
+    /// Add a symbol to the symbol table of the file, or of the contract when
+    /// `contract_no` is given. Functions have a namespace of their own, but a
+    /// name cannot be both a function and another symbol of the same scope.
+    /// Returns false after reporting an error when the name is already
+    /// defined; a symbol of a contract shadowing one of the file is a warning.
+    /// The overloads of functions and events are merged by the callers.
     pub fn add_symbol(
         &mut self,
-        _no: usize,
-        _contract_no: Option<usize>,
-        _id: &pt::Identifier,
-        _symbol: Symbol,
+        no: usize,
+        contract_no: Option<usize>,
+        id: &pt::Identifier,
+        symbol: Symbol,
     ) -> bool {
-        todo!()
+        let key = (no, contract_no, id.name.to_owned());
+
+        if let Some(previous) = self.symbol(&key) {
+            let diagnostic = Diagnostic::builder(id.loc, Level::Error)
+                .ty(ErrorType::DeclarationError)
+                .message(format!("'{}' is already defined as {}", id.name, describe(previous)))
+                .notes(previous_definitions(previous, &id.name))
+                .build();
+            self.diagnostics.push(diagnostic);
+            return false;
+        }
+
+        if contract_no.is_some() {
+            if let Some(previous) = self.symbol(&(no, None, id.name.to_owned())) {
+                let diagnostic = Diagnostic::builder(id.loc, Level::Warning)
+                    .ty(ErrorType::Warning)
+                    .message(format!("'{}' shadows {} of the file", id.name, describe(previous)))
+                    .notes(previous_definitions(previous, &id.name))
+                    .build();
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        match symbol {
+            Symbol::Function(_) => self.function_symbols.insert(key, symbol),
+            _ => self.variable_symbols.insert(key, symbol),
+        };
+
+        true
+    }
+
+    /// The symbol of either namespace with the given file, contract and name
+    fn symbol(&self, key: &(usize, Option<usize>, String)) -> Option<&Symbol> {
+        self.variable_symbols.get(key).or_else(|| self.function_symbols.get(key))
     }
 
     pub fn wrong_symbol(symbol: Option<&Symbol>, id: &pt::Identifier) -> Diagnostic {
@@ -128,6 +214,40 @@ impl Context {
         }
     }
 
+    /// The error for a name found to be another kind of symbol than the one
+    /// expected, as in "'S' is a struct, not a contract", with a note on its
+    /// definition
+    pub fn expected_symbol(
+        symbol: Option<&Symbol>,
+        id: &pt::Identifier,
+        expected: &str,
+    ) -> Diagnostic {
+        let mut diagnostic = Context::wrong_symbol(symbol, id);
+        if let Some(symbol) = symbol {
+            diagnostic.message = format!("{}, not {expected}", diagnostic.message);
+            diagnostic
+                .notes
+                .push(Note { loc: symbol.loc(), message: format!("definition of '{}'", id.name) });
+        }
+        diagnostic
+    }
+
+    /// The name of a definition. The parser only leaves it out when recovering
+    /// from a syntax error, which stops the analysis, so a definition without
+    /// a name is an internal error and the resolvers skip it.
+    pub(crate) fn definition_name(
+        &mut self,
+        name: &Option<pt::Identifier>,
+        loc: pt::Loc,
+        kind: &str,
+    ) -> Option<pt::Identifier> {
+        if name.is_none() {
+            self.diagnostics
+                .push(Diagnostic::error(loc, format!("internal error: {kind} without a name")));
+        }
+        name.clone()
+    }
+
     /// If an item does not allow annotations, then generate diagnostic errors.
     pub(crate) fn reject(&mut self, annotations: &[pt::Annotation], item: &str) {
         for note in annotations {
@@ -141,7 +261,7 @@ impl Context {
         &mut self,
         no: usize,
         name: &pt::IdentifierPath,
-        diagnostics: &mut Diagnostics,
+        diagnostics: &mut dyn DiagnosticSink,
     ) -> Result<usize, ()> {
         let (id, namespace) = name
             .identifiers
@@ -154,7 +274,7 @@ impl Context {
         if let Some(Symbol::Contract(_, contract_no)) = s {
             Ok(*contract_no)
         } else {
-            diagnostics.push(Context::wrong_symbol(s, id));
+            diagnostics.push(Context::expected_symbol(s, id, "a contract"));
             Err(())
         }
     }
@@ -165,7 +285,7 @@ impl Context {
         file_no: usize,
         contract_no: Option<usize>,
         name: &pt::IdentifierPath,
-        diagnostics: &mut Diagnostics,
+        diagnostics: &mut dyn DiagnosticSink,
     ) -> Result<Vec<(pt::Loc, usize)>, ()> {
         let (id, namespace) = name
             .identifiers
@@ -178,7 +298,55 @@ impl Context {
         if let Some(Symbol::Function(list)) = symbol {
             Ok(list.clone())
         } else {
-            diagnostics.push(Context::wrong_symbol(symbol, id));
+            diagnostics.push(Context::expected_symbol(symbol, id, "a function"));
+            Err(())
+        }
+    }
+
+    /// Resolve an event name with namespace, giving its overloads
+    pub(super) fn resolve_event_with_namespace(
+        &self,
+        file_no: usize,
+        contract_no: Option<usize>,
+        name: &pt::IdentifierPath,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<Vec<(pt::Loc, usize)>, ()> {
+        let (id, namespace) = name
+            .identifiers
+            .split_last()
+            .map(|(id, namespace)| (id, namespace.iter().collect()))
+            .unwrap();
+
+        let symbol = self.resolve_namespace(namespace, file_no, contract_no, id, diagnostics)?;
+
+        if let Some(Symbol::Event(list)) = symbol {
+            Ok(list.clone())
+        } else {
+            diagnostics.push(Context::expected_symbol(symbol, id, "an event"));
+            Err(())
+        }
+    }
+
+    /// Resolve a custom error name with namespace
+    pub(super) fn resolve_error_with_namespace(
+        &self,
+        file_no: usize,
+        contract_no: Option<usize>,
+        name: &pt::IdentifierPath,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<usize, ()> {
+        let (id, namespace) = name
+            .identifiers
+            .split_last()
+            .map(|(id, namespace)| (id, namespace.iter().collect()))
+            .unwrap();
+
+        let symbol = self.resolve_namespace(namespace, file_no, contract_no, id, diagnostics)?;
+
+        if let Some(Symbol::Error(_, error_no)) = symbol {
+            Ok(*error_no)
+        } else {
+            diagnostics.push(Context::expected_symbol(symbol, id, "an error"));
             Err(())
         }
     }
@@ -186,13 +354,67 @@ impl Context {
     /// Resolve the type name with the namespace to a symbol
     fn resolve_namespace(
         &self,
-        mut _namespace: Vec<&pt::Identifier>,
-        _no: usize,
-        mut _contract_no: Option<usize>,
-        _id: &pt::Identifier,
-        _diagnostics: &mut Diagnostics,
+        namespace: Vec<&pt::Identifier>,
+        mut no: usize,
+        mut contract_no: Option<usize>,
+        id: &pt::Identifier,
+        diagnostics: &mut dyn DiagnosticSink,
     ) -> Result<Option<&Symbol>, ()> {
-        todo!()
+        // Each name of the namespace is an import or a contract, whose
+        // symbols the next name is looked up in
+        let mut in_file = true;
+        for name in namespace {
+            match self.find_symbol(no, contract_no, &name.name, in_file) {
+                Some(Symbol::Import(_, import_no)) => {
+                    no = *import_no;
+                    contract_no = None;
+                    in_file = true;
+                }
+                Some(Symbol::Contract(_, base_no)) => {
+                    no = self.contracts[*base_no].loc.no().unwrap_or(no);
+                    contract_no = Some(*base_no);
+                    in_file = false;
+                }
+                symbol => {
+                    diagnostics.push(Context::expected_symbol(
+                        symbol,
+                        name,
+                        "an import or a contract",
+                    ));
+                    return Err(());
+                }
+            }
+        }
+
+        Ok(self.find_symbol(no, contract_no, &id.name, in_file))
+    }
+
+    /// Find the symbol of the name in the contract and its bases, the most
+    /// derived first, and then in the file if `in_file` is set. Variables and
+    /// functions have separate symbol tables, variables are looked up first.
+    pub(crate) fn find_symbol(
+        &self,
+        no: usize,
+        contract_no: Option<usize>,
+        name: &str,
+        in_file: bool,
+    ) -> Option<&Symbol> {
+        let get = |key: (usize, Option<usize>, String)| self.symbol(&key);
+
+        if let Some(contract_no) = contract_no {
+            for base_no in self.contract_bases(contract_no).into_iter().rev() {
+                let file_no = self.contracts[base_no].loc.no().unwrap_or(no);
+                if let Some(symbol) = get((file_no, Some(base_no), name.to_owned())) {
+                    return Some(symbol);
+                }
+            }
+        }
+
+        if in_file {
+            get((no, None, name.to_owned()))
+        } else {
+            None
+        }
     }
 
     /// Resolve the parsed data type. The type can be a primitive, enum and also an arrays.
@@ -200,17 +422,187 @@ impl Context {
     /// casting. So, we need to know what we are resolving for.
     pub(super) fn resolve_type(
         &mut self,
-        _file_no: usize,
-        _contract_no: Option<usize>,
-        _resolve_context: ResolveTypeContext,
-        _id: &pt::Expression,
-        _diagnostics: &mut Diagnostics,
+        file_no: usize,
+        contract_no: Option<usize>,
+        resolve_context: ResolveTypeContext,
+        id: &pt::Expression,
+        diagnostics: &mut dyn DiagnosticSink,
     ) -> Result<Type, ()> {
-        todo!()
+        match id {
+            pt::Expression::Type(loc, pt::Type::Payable)
+                if resolve_context != ResolveTypeContext::Casting =>
+            {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    "'payable' cannot be used as a type, use 'address payable'",
+                ));
+                Err(())
+            }
+            pt::Expression::Type(_, pt::Type::Mapping { key, key_name, value, value_name, .. }) => {
+                let key_ty = self.resolve_type(
+                    file_no,
+                    contract_no,
+                    ResolveTypeContext::None,
+                    key,
+                    diagnostics,
+                )?;
+                if matches!(key_ty, Type::Mapping(_) | Type::Array(..) | Type::Struct(_)) {
+                    diagnostics.push(Diagnostic::type_error(
+                        key.loc(),
+                        format!("'{}' cannot be the key of a mapping", key_ty.to_string(self)),
+                    ));
+                    return Err(());
+                }
+                let value_ty = self.resolve_type(
+                    file_no,
+                    contract_no,
+                    ResolveTypeContext::None,
+                    value,
+                    diagnostics,
+                )?;
+
+                Ok(Type::Mapping(Mapping {
+                    key: Box::new(key_ty),
+                    key_name: key_name.clone(),
+                    value: Box::new(value_ty),
+                    value_name: value_name.clone(),
+                }))
+            }
+            pt::Expression::Type(_, pt::Type::Function { params, attributes, returns }) => {
+                let mut mutability = Mutability::Nonpayable(pt::Loc::Implicit);
+                let mut external = false;
+                for attribute in attributes {
+                    match attribute {
+                        pt::FunctionAttribute::Visibility(pt::Visibility::External(_)) => {
+                            external = true
+                        }
+                        pt::FunctionAttribute::Mutability(m) => {
+                            mutability = match m {
+                                pt::Mutability::Pure(loc) => Mutability::Pure(*loc),
+                                pt::Mutability::View(loc) | pt::Mutability::Constant(loc) => {
+                                    Mutability::View(*loc)
+                                }
+                                pt::Mutability::Payable(loc) => Mutability::Payable(*loc),
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+
+                let params =
+                    self.resolve_parameter_types(file_no, contract_no, params, diagnostics)?;
+                let returns = match returns {
+                    Some((returns, _)) => {
+                        self.resolve_parameter_types(file_no, contract_no, returns, diagnostics)?
+                    }
+                    None => Vec::new(),
+                };
+
+                Ok(if external {
+                    Type::ExternalFunction { mutability, params, returns }
+                } else {
+                    Type::InternalFunction { mutability, params, returns }
+                })
+            }
+            pt::Expression::Type(_, ty) => Ok(Type::from(ty)),
+            pt::Expression::Variable(id) if Type::elementary(&id.name).is_some() => {
+                Ok(Type::elementary(&id.name).unwrap())
+            }
+            pt::Expression::ArraySubscript(loc, ty, length) => {
+                let elem =
+                    self.resolve_type(file_no, contract_no, resolve_context, ty, diagnostics)?;
+                let length = match length.as_deref() {
+                    None => ArrayLength::Dynamic,
+                    Some(pt::Expression::NumberLiteral(loc, integer, exp, None))
+                        if exp.is_empty() =>
+                    {
+                        let length = integer.replace('_', "").parse::<BigInt>().unwrap();
+                        if length.is_zero() {
+                            diagnostics
+                                .push(Diagnostic::error(*loc, "zero size array not permitted"));
+                            return Err(());
+                        }
+                        ArrayLength::Fixed(length)
+                    }
+                    Some(_) => {
+                        diagnostics.push(Diagnostic::error(
+                            *loc,
+                            "array dimension must be a constant number literal",
+                        ));
+                        return Err(());
+                    }
+                };
+
+                Ok(match elem {
+                    Type::Array(elem, mut dims) => {
+                        dims.push(length);
+                        Type::Array(elem, dims)
+                    }
+                    elem => Type::Array(Box::new(elem), vec![length]),
+                })
+            }
+            expr => {
+                let Some(name) = identifier_path(expr) else {
+                    diagnostics.push(Diagnostic::error(expr.loc(), "expected a type"));
+                    return Err(());
+                };
+                let (id, namespace) = name
+                    .identifiers
+                    .split_last()
+                    .map(|(id, namespace)| (id, namespace.iter().collect()))
+                    .unwrap();
+
+                match self.resolve_namespace(namespace, file_no, contract_no, id, diagnostics)? {
+                    Some(Symbol::Contract(_, contract_no)) => Ok(Type::Contract(*contract_no)),
+                    Some(Symbol::Enum(_, enum_no)) => Ok(Type::Enum(*enum_no)),
+                    Some(Symbol::Struct(_, struct_ty)) => Ok(Type::Struct(*struct_ty)),
+                    Some(Symbol::UserType(_, type_no)) => Ok(Type::UserType(*type_no)),
+                    symbol => {
+                        diagnostics.push(Context::expected_symbol(symbol, id, "a type"));
+                        Err(())
+                    }
+                }
+            }
+        }
     }
 
-    /// base contracts in depth-first post-order
+    /// Resolve the types of the parameters of a function type
+    fn resolve_parameter_types(
+        &mut self,
+        file_no: usize,
+        contract_no: Option<usize>,
+        params: &pt::ParameterList,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<Vec<Type>, ()> {
+        let mut tys = Vec::new();
+
+        for (loc, param) in params {
+            let Some(param) = param else {
+                diagnostics.push(Diagnostic::error(*loc, "missing parameter type"));
+                return Err(());
+            };
+            tys.push(self.resolve_type(
+                file_no,
+                contract_no,
+                ResolveTypeContext::FunctionType,
+                &param.ty,
+                diagnostics,
+            )?);
+        }
+
+        Ok(tys)
+    }
+
+    /// The contract and its bases in the order of the C3 linearization, the
+    /// most base-like first and the contract last. When the bases cannot be
+    /// linearized, which [`check_inheritance`](super::contract::check_inheritance)
+    /// reports, they are given in depth-first post-order.
     pub fn contract_bases(&self, contract_no: usize) -> Vec<usize> {
+        if let Some(mut order) = self.linearize(contract_no) {
+            order.reverse();
+            return order;
+        }
+
         let mut order = Vec::new();
 
         fn base(contract_no: usize, order: &mut Vec<usize>, ctx: &Context) {
@@ -227,4 +619,182 @@ impl Context {
 
         order
     }
+
+    /// The C3 linearization of the contract and its bases, the contract first
+    /// and the most base-like last. As in Solidity, the bases are listed from
+    /// the most base-like to the most derived, so `contract C is A, B` gives
+    /// `C, B, A`. Returns `None` if no order is consistent with the lists of
+    /// bases of all the contracts.
+    pub fn linearize(&self, contract_no: usize) -> Option<Vec<usize>> {
+        let bases = &self.contracts[contract_no].bases;
+
+        let mut sequences = bases
+            .iter()
+            .rev()
+            .map(|base| self.linearize(base.contract_no))
+            .collect::<Option<Vec<_>>>()?;
+        sequences.push(bases.iter().rev().map(|base| base.contract_no).collect());
+
+        let mut order = vec![contract_no];
+        loop {
+            sequences.retain(|sequence| !sequence.is_empty());
+            if sequences.is_empty() {
+                return Some(order);
+            }
+
+            // The first head which does not come after another contract in
+            // any of the sequences
+            let head = sequences
+                .iter()
+                .map(|sequence| sequence[0])
+                .find(|head| !sequences.iter().any(|sequence| sequence[1..].contains(head)))?;
+
+            order.push(head);
+            for sequence in &mut sequences {
+                if sequence[0] == head {
+                    sequence.remove(0);
+                }
+            }
+        }
+    }
+}
+
+/// The kind of a symbol with its article, as in "'x' is already defined as a
+/// struct"
+fn describe(symbol: &Symbol) -> &'static str {
+    match symbol {
+        Symbol::Enum(..) => "an enum",
+        Symbol::Function(_) => "a function",
+        Symbol::Variable(_, Some(_), _) => "a state variable",
+        Symbol::Variable(_, None, _) => "a constant",
+        Symbol::Struct(..) => "a struct",
+        Symbol::Event(_) => "an event",
+        Symbol::Error(..) => "an error",
+        Symbol::Contract(..) => "a contract",
+        Symbol::Import(..) => "an import",
+        Symbol::UserType(..) => "a user type",
+    }
+}
+
+/// A note for each definition of the symbol, one per overload of a function or
+/// an event
+fn previous_definitions(symbol: &Symbol, name: &str) -> Vec<Note> {
+    let message = format!("previous definition of '{name}'");
+    match symbol {
+        Symbol::Function(overloads) | Symbol::Event(overloads) => {
+            overloads.iter().map(|(loc, _)| Note { loc: *loc, message: message.clone() }).collect()
+        }
+        _ => vec![Note { loc: symbol.loc(), message }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str, start: usize) -> pt::Identifier {
+        pt::Identifier { loc: pt::Loc::File(0, start, start + name.len()), name: name.into() }
+    }
+
+    #[test]
+    fn test_add_symbol() {
+        let mut ctx = Context::new();
+        let loc = |start| id("", start).loc;
+
+        assert!(ctx.add_symbol(
+            0,
+            None,
+            &id("S", 0),
+            Symbol::Struct(loc(0), StructType::UserDefined(0))
+        ));
+        assert!(ctx.add_symbol(
+            0,
+            None,
+            &id("f", 10),
+            Symbol::Function(vec![(loc(10), 0), (loc(20), 1)])
+        ));
+        assert!(!ctx.diagnostics.any_errors());
+
+        // The same name in the same scope, in either namespace
+        assert!(!ctx.add_symbol(0, None, &id("S", 30), Symbol::Error(loc(30), 0)));
+        assert!(!ctx.add_symbol(0, None, &id("f", 40), Symbol::Variable(loc(40), None, 0)));
+        let errors = ctx.diagnostics.errors();
+        assert_eq!(errors[0].message, "'S' is already defined as a struct");
+        assert_eq!(errors[0].notes.len(), 1);
+        assert_eq!(errors[0].notes[0].loc, loc(0));
+        assert_eq!(errors[1].message, "'f' is already defined as a function");
+        assert_eq!(
+            errors[1].notes.iter().map(|note| note.loc).collect::<Vec<_>>(),
+            [loc(10), loc(20)]
+        );
+        assert_eq!(errors[1].notes[0].message, "previous definition of 'f'");
+        assert_eq!(ctx.variable_symbols.len(), 1);
+
+        // A contract may shadow the symbols of the file, but not its own
+        let mut ctx = Context::new();
+        assert!(ctx.add_symbol(
+            0,
+            None,
+            &id("S", 0),
+            Symbol::Struct(loc(0), StructType::UserDefined(0))
+        ));
+        assert!(ctx.add_symbol(0, Some(0), &id("S", 10), Symbol::Variable(loc(10), Some(0), 0)));
+        assert!(!ctx.diagnostics.any_errors());
+        assert!(ctx.diagnostics.warning_contains("'S' shadows a struct of the file"));
+        assert!(!ctx.add_symbol(0, Some(0), &id("S", 20), Symbol::Enum(loc(20), 0)));
+        assert_eq!(ctx.diagnostics.first_error(), "'S' is already defined as a state variable");
+
+        // Other files and contracts have symbol tables of their own
+        assert!(ctx.add_symbol(1, None, &id("S", 0), Symbol::Enum(loc(0), 1)));
+        assert!(ctx.add_symbol(0, Some(1), &id("S", 30), Symbol::Enum(loc(30), 2)));
+        assert_eq!(ctx.variable_symbols.len(), 4);
+    }
+
+    fn path(names: &[&str]) -> pt::IdentifierPath {
+        pt::IdentifierPath {
+            loc: pt::Loc::Builtin,
+            identifiers: names.iter().enumerate().map(|(i, name)| id(name, i * 10)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_namespace() {
+        let mut ctx = Context::new();
+        let mut diagnostics = Diagnostics::default();
+        let loc = |no, start| pt::Loc::File(no, start, start + 1);
+
+        // `import "a.sol" as A;` where a.sol has the contract `C` and the
+        // struct `S`, and `C` has the event `E`
+        ctx.contracts.push(crate::semantic::layout::tests::contract("C", &[], Vec::new()));
+        ctx.contracts[0].loc = loc(1, 0);
+        ctx.variable_symbols.insert((0, None, "A".into()), Symbol::Import(loc(0, 0), 1));
+        ctx.variable_symbols.insert((1, None, "C".into()), Symbol::Contract(loc(1, 0), 0));
+        let s = Symbol::Struct(loc(1, 5), StructType::UserDefined(0));
+        ctx.variable_symbols.insert((1, None, "S".into()), s);
+        ctx.variable_symbols.insert((1, Some(0), "E".into()), Symbol::Event(vec![(loc(1, 9), 0)]));
+
+        assert_eq!(
+            ctx.resolve_contract_with_namespace(0, &path(&["A", "C"]), &mut diagnostics),
+            Ok(0)
+        );
+        assert_eq!(
+            ctx.resolve_event_with_namespace(0, None, &path(&["A", "C", "E"]), &mut diagnostics),
+            Ok(vec![(loc(1, 9), 0)])
+        );
+        assert!(!diagnostics.any_errors());
+
+        let mut error = |name: &[&str]| {
+            let mut diagnostics = Diagnostics::default();
+            assert!(ctx.resolve_contract_with_namespace(0, &path(name), &mut diagnostics).is_err());
+            let error = diagnostics.errors()[0].clone();
+            (error.message, error.notes.first().map(|note| note.loc))
+        };
+        assert_eq!(error(&["A", "S"]), ("'S' is a struct, not a contract".into(), Some(loc(1, 5))));
+        assert_eq!(error(&["A", "D"]), ("'D' not found".into(), None));
+        assert_eq!(error(&["C"]), ("'C' not found".into(), None));
+        assert_eq!(
+            error(&["A", "S", "C"]),
+            ("'S' is a struct, not an import or a contract".into(), Some(loc(1, 5)))
+        );
+    }
 }
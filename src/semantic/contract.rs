@@ -12,19 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{BTreeMap, HashMap};
+
+use indexmap::IndexMap;
+use itertools::Itertools;
 use thiserror::Error;
 
 use crate::{
-    diagnostics::{Diagnostic, Diagnostics, Level, Note},
+    abi::ethereum,
+    diagnostics::{Diagnostic, Diagnostics, ErrorType, Level, Note},
+    helpers::CodeLocation,
     parser::{
         ast as pt,
         visitor::{Visitable, Visitor},
     },
     semantic::{
-        ast::{Base, ContractDefinition, ContractPart},
+        ast::{
+            Base, ContractDefinition, ContractPart, Function, Mutability, SourceUnit,
+            SourceUnitPart, Statement, Symbol,
+        },
         context::Context,
         expression::{constructor::match_constructor_to_args, ExprContext},
-        function,
+        function, layout, statement,
         symtable::Symtable,
         using::UsingResolver,
         variable,
@@ -176,7 +185,7 @@ impl<'a> ContractResolver<'a> {
     fn resolve_declarations(&mut self, def: &ContractDefinition) {
         self.ctx.diagnostics.push(Diagnostic::debug(
             def.loc,
-            format!("found {} '{}'", def.ty, def.name.as_ref().unwrap().name),
+            format!("found {} '{}'", def.ty, def.name.as_ref().map_or("", |id| id.name.as_str())),
         ));
 
         let mut function_no_bodies = Vec::new();
@@ -230,26 +239,81 @@ impl<'a> ContractResolver<'a> {
 
     /// Check the inheritance of all functions and other symbols
     fn check_inheritance(&mut self) {
-        todo!()
+        check_inheritance(self.contract_no, self.ctx);
     }
 
     /// This function checks which function names must be mangled given a
     /// contract. Mangling happens when there is more than one function with the
     /// same name in the given `contract_no`.
     fn mangle_function_names(&mut self) {
-        todo!()
+        let mut names: HashMap<&str, usize> = HashMap::new();
+        let mut mangled = Vec::new();
+
+        for function_no in self.implementations() {
+            let func = &self.ctx.functions[function_no];
+            if func.ty != pt::FunctionTy::Function {
+                continue;
+            }
+            match names.get(func.id.name.as_str()) {
+                Some(&first) => mangled.extend([first, function_no]),
+                None => {
+                    names.insert(&func.id.name, function_no);
+                }
+            }
+        }
+
+        for function_no in mangled {
+            self.ctx.functions[function_no].mangled_name_contracts.insert(self.contract_no);
+        }
     }
 
     /// This check guarantees that each public Solidity function has a unique selector.
     fn verify_unique_selector(&mut self) {
-        todo!()
+        let mut selectors: HashMap<Vec<u8>, usize> = HashMap::new();
+
+        for function_no in self.public_functions() {
+            let func = &self.ctx.functions[function_no];
+            let Some(&prev_no) = selectors.get(&func.selector()) else {
+                selectors.insert(func.selector(), function_no);
+                continue;
+            };
+            let prev = &self.ctx.functions[prev_no];
+            self.ctx.diagnostics.push(
+                Diagnostic::builder(func.loc_prototype, Level::Error)
+                    .message(format!(
+                        "function '{}' has the same selector as function '{}'",
+                        func.signature, prev.signature
+                    ))
+                    .note(prev.loc_prototype, format!("definition of '{}'", prev.signature))
+                    .build(),
+            );
+        }
     }
 
-    /// Constructors and functions are no different pallet contracts.
-    /// This function checks that all constructors and function names are unique.
-    /// Overloading (mangled function or constructor names) is taken into account.
+    /// A contract has at most one constructor, one fallback and one receive
+    /// function, which are not distinguished by their parameters.
     fn unique_constructor_names(&mut self) {
-        todo!()
+        let mut seen: Vec<(pt::FunctionTy, usize)> = Vec::new();
+
+        for &function_no in &self.ctx.contracts[self.contract_no].functions {
+            let func = &self.ctx.functions[function_no];
+            if func.ty == pt::FunctionTy::Function || func.ty == pt::FunctionTy::Modifier {
+                continue;
+            }
+            let Some(&(_, prev_no)) = seen.iter().find(|(ty, _)| *ty == func.ty) else {
+                seen.push((func.ty, function_no));
+                continue;
+            };
+            self.ctx.diagnostics.push(
+                Diagnostic::builder(func.loc_prototype, Level::Error)
+                    .message(format!("{} already defined", func.ty))
+                    .note(
+                        self.ctx.functions[prev_no].loc_prototype,
+                        format!("location of previous {}", func.ty),
+                    )
+                    .build(),
+            );
+        }
     }
 
     /// Given a contract number, check for function names conflicting with any mangled name.
@@ -258,21 +322,136 @@ impl<'a> ContractResolver<'a> {
     /// Note: In sema we do not care about the function name too much.
     /// The mangled name is consumed later by the ABI generation.
     fn check_mangled_function_names(&mut self) {
-        todo!()
+        let public = self.public_functions();
+
+        for &offender in &public {
+            let func = &self.ctx.functions[offender];
+            if !func.mangled_name_contracts.contains(&self.contract_no) {
+                continue;
+            }
+            for &function_no in public.iter().filter(|no| **no != offender) {
+                let other = &self.ctx.functions[function_no];
+                if other.id.name != func.mangled_name {
+                    continue;
+                }
+                self.ctx.diagnostics.push(
+                    Diagnostic::builder(other.loc_prototype, Level::Error)
+                        .message(format!(
+                            "mangling the symbol of overloaded function '{}' with signature \
+                             '{}' results in a new symbol '{}' but this symbol already exists",
+                            func.id, func.signature, func.mangled_name
+                        ))
+                        .note(
+                            func.loc_prototype,
+                            "this function declaration conflicts with the mangled symbol",
+                        )
+                        .build(),
+                );
+            }
+        }
+    }
+
+    /// The functions of the contract and its bases which are not overridden
+    fn implementations(&self) -> Vec<usize> {
+        let all_functions = &self.ctx.contracts[self.contract_no].all_functions;
+        all_functions
+            .iter()
+            .filter(|(no, implementation)| no == implementation)
+            .map(|(no, _)| *no)
+            .collect()
+    }
+
+    /// The implementations which are public or external functions
+    fn public_functions(&self) -> Vec<usize> {
+        self.implementations()
+            .into_iter()
+            .filter(|no| {
+                let func = &self.ctx.functions[*no];
+                func.ty == pt::FunctionTy::Function && func.is_public()
+            })
+            .collect()
     }
 
     /// Resolve contract functions bodies
     fn resolve_bodies(&mut self) -> bool {
-        todo!()
+        let mut broken = false;
+
+        for body in &self.delayed.function_bodies {
+            if !function::check_modifiers(
+                body.contract_no,
+                body.function_no,
+                &body.function,
+                self.no,
+                self.ctx,
+            ) {
+                broken = true;
+            }
+
+            if statement::resolve_function_body(
+                &body.function,
+                self.no,
+                Some(body.contract_no),
+                body.function_no,
+                self.ctx,
+            )
+            .is_err()
+            {
+                broken = true;
+            }
+        }
+
+        broken
+    }
+
+    /// Resolve the bodies of the free functions of the file, which may refer
+    /// to its contracts
+    fn resolve_free_bodies(&mut self, parts: &[SourceUnitPart]) {
+        for part in parts {
+            let pt::SourceUnitPart::FunctionDefinition(def) = &part.part else {
+                continue;
+            };
+            // A function which failed to be declared has no body to resolve
+            let Some(function_no) = self.ctx.functions.iter().position(|func| {
+                func.contract_no.is_none() && func.loc_prototype == def.loc_prototype
+            }) else {
+                continue;
+            };
+
+            // The errors are in the diagnostics
+            let _ = statement::resolve_function_body(def, self.no, None, function_no, self.ctx);
+        }
     }
 
     /// Check if we have arguments for all the base contracts
     fn check_base_args(&mut self) {
-        todo!()
+        add_default_constructor(self.contract_no, self.ctx);
+        check_base_args(self.contract_no, self.ctx);
     }
 }
 
 impl<'a> SemanticVisitor for ContractResolver<'a> {
+    /// The bodies may refer to any contract of the file, so they are resolved
+    /// once all the contracts are declared. The items outside contracts are
+    /// resolved by the passes before.
+    fn visit_sema_source_unit(&mut self, source_unit: &mut SourceUnit) -> Result<(), Self::Error> {
+        for contract in source_unit.contracts.iter_mut() {
+            self.visit_sema_contract(contract)?;
+        }
+
+        // Now we can resolve the initializers
+        variable::resolve_initializers(&self.delayed.initializers, self.no, self.ctx);
+
+        // Now we can resolve the bodies
+        self.resolve_free_bodies(&source_unit.parts);
+        if !self.resolve_bodies() {
+            for contract in &source_unit.contracts {
+                self.contract_no = contract.contract_no;
+                self.check_base_args();
+            }
+        }
+        Ok(())
+    }
+
     fn visit_sema_contract(
         &mut self,
         contract: &mut ContractDefinition,
@@ -280,21 +459,26 @@ impl<'a> SemanticVisitor for ContractResolver<'a> {
         self.contract_no = contract.contract_no;
 
         self.resolve_declarations(contract);
+        contract.base.visit(self)?;
+        for part in &contract.parts {
+            if let pt::ContractPart::Using(using) = &part.part {
+                self.part = Some(part.clone());
+                self.visit_using(&mut using.as_ref().clone())?;
+            }
+        }
+        self.part = None;
+
+        // The variables of the bases are resolved before those of the contract
+        layout::layout(self.contract_no, self.ctx);
 
         // Now we have all the declarations, we can handle base contracts
         self.check_inheritance();
+        compute_instantiable(self.contract_no, self.ctx);
+        check_interface_ids(self.contract_no, self.ctx);
         self.mangle_function_names();
         self.verify_unique_selector();
         self.unique_constructor_names();
         self.check_mangled_function_names();
-
-        // Now we can resolve the initializers
-        variable::resolve_initializers(&self.delayed.initializers, self.no, self.ctx);
-
-        // Now we can resolve the bodies
-        if !self.resolve_bodies() {
-            self.check_base_args();
-        }
         Ok(())
     }
 
@@ -372,6 +556,615 @@ impl<'a> Visitor for ContractResolver<'a> {
     }
 }
 
+/// Give a contract without a constructor a public one with an empty body,
+/// which calls the constructors without arguments of the direct bases which
+/// are not given arguments in the list of bases.
+pub fn add_default_constructor(contract_no: usize, ctx: &mut Context) {
+    let contract = &ctx.contracts[contract_no];
+    if !contract.constructors(ctx).is_empty() || contract.is_interface() || contract.is_library() {
+        return;
+    }
+
+    let mut func = Function::new(
+        contract.loc,
+        contract.loc,
+        pt::Identifier { loc: contract.loc, name: String::new() },
+        Some(contract_no),
+        Vec::new(),
+        pt::FunctionTy::Constructor,
+        None,
+        pt::Visibility::Public(None),
+        Vec::new(),
+        Vec::new(),
+        ctx,
+    );
+    func.has_body = true;
+    func.body = vec![Statement::Return(pt::Loc::Implicit, None)];
+
+    for base in contract.bases.iter().filter(|base| base.constructor.is_none()) {
+        if let Some(constructor_no) = ctx.contracts[base.contract_no].no_args_constructor(ctx) {
+            func.bases.insert(base.contract_no, (base.loc, constructor_no, Vec::new()));
+        }
+    }
+
+    let function_no = ctx.functions.len();
+    ctx.functions.push(func);
+
+    let contract = &mut ctx.contracts[contract_no];
+    contract.functions.push(function_no);
+    contract.default_constructor = Some(function_no);
+}
+
+/// Check what the contract inherits: its bases can be linearized, the names
+/// declared in them do not conflict, and each function which overrides
+/// another matches it. Fills in [`Contract::all_functions`] and
+/// [`Contract::virtual_functions`].
+///
+/// [`Contract::all_functions`]: super::ast::Contract::all_functions
+/// [`Contract::virtual_functions`]: super::ast::Contract::virtual_functions
+pub fn check_inheritance(contract_no: usize, ctx: &mut Context) {
+    let contract = &ctx.contracts[contract_no];
+
+    // An impossible order of a base is reported with the base
+    if ctx.linearize(contract_no).is_none() &&
+        contract.bases.iter().all(|base| ctx.linearize(base.contract_no).is_some())
+    {
+        ctx.diagnostics.push(Diagnostic::error(
+            contract.loc,
+            format!(
+                "the bases of contract '{}' cannot be linearized, list them from the most \
+                 base-like to the most derived",
+                contract.id
+            ),
+        ));
+    }
+
+    let mut diagnostics = Diagnostics::default();
+    check_inherited_symbols(contract_no, ctx, &mut diagnostics);
+    check_overrides(contract_no, ctx, &mut diagnostics);
+    ctx.diagnostics.extend(diagnostics);
+
+    // In the order of the bases, the last function of each signature
+    // implements the others
+    let mut chains: IndexMap<&str, Vec<usize>> = IndexMap::new();
+    let mut all_functions = BTreeMap::new();
+    for base_no in ctx.contract_bases(contract_no) {
+        for &function_no in &ctx.contracts[base_no].functions {
+            let func = &ctx.functions[function_no];
+            if func.is_constructor() {
+                all_functions.insert(function_no, function_no);
+            } else {
+                chains.entry(&func.signature).or_default().push(function_no);
+            }
+        }
+    }
+
+    let mut virtual_functions = IndexMap::new();
+    for (signature, chain) in chains {
+        let implementation = *chain.last().unwrap();
+        all_functions.extend(chain.iter().map(|function_no| (*function_no, implementation)));
+        if chain.len() > 1 || ctx.functions[implementation].is_virtual {
+            virtual_functions.insert(signature.to_owned(), chain);
+        }
+    }
+
+    let contract = &mut ctx.contracts[contract_no];
+    contract.all_functions = all_functions;
+    contract.virtual_functions = virtual_functions;
+}
+
+/// Whether any direct base of the contract inherits both contracts, so that
+/// a conflict between them was reported with the base
+fn inherited_together(contract_no: usize, a: usize, b: usize, ctx: &Context) -> bool {
+    ctx.contracts[contract_no]
+        .bases
+        .iter()
+        .any(|base| is_base(a, base.contract_no, ctx) && is_base(b, base.contract_no, ctx))
+}
+
+/// Check that no name is declared in the contract and one of its bases, or in
+/// two bases, except for the overloads and overrides of functions, events and
+/// the public state variables overriding functions. Private state variables
+/// are not inherited.
+fn check_inherited_symbols(contract_no: usize, ctx: &Context, diagnostics: &mut Diagnostics) {
+    let contract = &ctx.contracts[contract_no];
+    let mut visible: IndexMap<&str, (&Symbol, usize)> = IndexMap::new();
+
+    for base_no in ctx.contract_bases(contract_no) {
+        let symbols = ctx
+            .variable_symbols
+            .iter()
+            .chain(ctx.function_symbols.iter())
+            .filter(|((_, symbol_contract_no, _), _)| *symbol_contract_no == Some(base_no));
+
+        for ((_, _, name), symbol) in symbols {
+            if let Some((previous, previous_no)) = visible.get(name.as_str()) {
+                let allowed = matches!(
+                    (previous, symbol),
+                    (Symbol::Function(_), Symbol::Function(_)) |
+                        (Symbol::Event(_), Symbol::Event(_))
+                ) || previous.has_accessor(ctx) ||
+                    symbol.has_accessor(ctx);
+
+                let previous_contract = &ctx.contracts[*previous_no].id;
+                if allowed {
+                    // The functions are checked by their signatures
+                } else if base_no == contract_no {
+                    diagnostics.push(
+                        Diagnostic::builder(symbol.loc(), Level::Error)
+                            .ty(ErrorType::DeclarationError)
+                            .message(format!(
+                                "'{name}' is already defined in base contract '{previous_contract}'"
+                            ))
+                            .note(previous.loc(), format!("previous definition of '{name}'"))
+                            .build(),
+                    );
+                } else if !inherited_together(contract_no, *previous_no, base_no, ctx) {
+                    diagnostics.push(
+                        Diagnostic::builder(contract.loc, Level::Error)
+                            .ty(ErrorType::DeclarationError)
+                            .message(format!(
+                                "contract '{}' inherits '{name}' from both '{previous_contract}' \
+                                 and '{}'",
+                                contract.id, ctx.contracts[base_no].id
+                            ))
+                            .note(previous.loc(), format!("definition of '{name}'"))
+                            .note(symbol.loc(), format!("definition of '{name}'"))
+                            .build(),
+                    );
+                }
+            }
+
+            if !symbol.is_private_variable(ctx) {
+                visible.insert(name, (symbol, base_no));
+            }
+        }
+    }
+}
+
+/// Check the functions of the contract against those they override, and that
+/// it overrides the functions it inherits from more than one base
+fn check_overrides(contract_no: usize, ctx: &Context, diagnostics: &mut Diagnostics) {
+    // The implementations inherited for each signature, those which no more
+    // derived base overrides
+    let mut inherited: IndexMap<&str, Vec<usize>> = IndexMap::new();
+    for base_no in ctx.contract_bases(contract_no).into_iter().filter(|no| *no != contract_no) {
+        for &function_no in &ctx.contracts[base_no].functions {
+            let func = &ctx.functions[function_no];
+            if func.is_constructor() {
+                continue;
+            }
+            let implementations = inherited.entry(&func.signature).or_default();
+            implementations.retain(|previous| {
+                !is_base(ctx.functions[*previous].contract_no.unwrap(), base_no, ctx)
+            });
+            implementations.push(function_no);
+        }
+    }
+
+    let contract = &ctx.contracts[contract_no];
+    for &function_no in &contract.functions {
+        let func = &ctx.functions[function_no];
+        if func.is_constructor() {
+            continue;
+        }
+        match inherited.get(func.signature.as_str()) {
+            Some(previous) => check_override(func, previous, ctx, diagnostics),
+            None => {
+                if let Some((loc, _)) = &func.is_override {
+                    diagnostics.push(Diagnostic::error(
+                        *loc,
+                        format!("{} '{}' does not override anything", func.ty, func.id),
+                    ));
+                }
+            }
+        }
+    }
+
+    for (signature, previous) in &inherited {
+        let overridden =
+            contract.functions.iter().any(|no| ctx.functions[*no].signature == *signature);
+        let reported = previous.iter().array_combinations().any(|[a, b]| {
+            let (a, b) = (ctx.functions[*a].contract_no, ctx.functions[*b].contract_no);
+            inherited_together(contract_no, a.unwrap(), b.unwrap(), ctx)
+        });
+        if previous.len() < 2 || overridden || reported {
+            continue;
+        }
+
+        let func = &ctx.functions[previous[0]];
+        diagnostics.push(
+            Diagnostic::builder(contract.loc, Level::Error)
+                .ty(ErrorType::TypeError)
+                .message(format!(
+                    "contract '{}' must override {} '{}', which it inherits from {}",
+                    contract.id,
+                    func.ty,
+                    func.id,
+                    contract_names(previous, ctx, " and ")
+                ))
+                .notes(definitions(previous, ctx))
+                .build(),
+        );
+    }
+}
+
+/// Check a function against the inherited functions it overrides
+fn check_override(
+    func: &Function,
+    previous: &[usize],
+    ctx: &Context,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut error = |message: String, function_no: usize| {
+        let prev = &ctx.functions[function_no];
+        diagnostics.push(
+            Diagnostic::builder(func.loc_prototype, Level::Error)
+                .ty(ErrorType::TypeError)
+                .message(message)
+                .note(prev.loc_prototype, format!("previous definition of '{}'", prev.id))
+                .build(),
+        );
+    };
+
+    for &function_no in previous {
+        let prev = &ctx.functions[function_no];
+        let (ty, id) = (func.ty, &func.id);
+
+        if prev.is_accessor {
+            error(format!("{ty} '{id}' cannot override public state variable '{id}'"), function_no);
+        } else if prev.ty != func.ty {
+            error(format!("{ty} '{id}' overrides {} '{id}'", prev.ty), function_no);
+        } else if !prev.is_virtual {
+            error(format!("{ty} '{id}' overrides {ty} which is not 'virtual'"), function_no);
+        } else if prev.returns.iter().map(|r| &r.ty).ne(func.returns.iter().map(|r| &r.ty)) {
+            error(format!("{ty} '{id}' overrides {ty} with different return types"), function_no);
+        } else if !visibility_overrides(&func.visibility, &prev.visibility) {
+            error(
+                format!(
+                    "{ty} '{id}' changes visibility from '{}' to '{}'",
+                    prev.visibility, func.visibility
+                ),
+                function_no,
+            );
+        } else if !mutability_overrides(&func.mutability, &prev.mutability) {
+            error(
+                format!(
+                    "{ty} '{id}' changes state mutability from '{}' to '{}'",
+                    prev.mutability, func.mutability
+                ),
+                function_no,
+            );
+        }
+    }
+
+    let bases = previous.iter().filter_map(|no| ctx.functions[*no].contract_no).collect::<Vec<_>>();
+    let list = bases.iter().map(|no| &ctx.contracts[*no].id.name).join(", ");
+
+    match &func.is_override {
+        // Implementing a single function of an interface needs no 'override'
+        None if previous.len() == 1 && ctx.contracts[bases[0]].is_interface() => (),
+        None => diagnostics.push(
+            Diagnostic::builder(func.loc_prototype, Level::Error)
+                .ty(ErrorType::TypeError)
+                .message(format!("{} '{}' should specify 'override'", func.ty, func.id))
+                .notes(definitions(previous, ctx))
+                .build(),
+        ),
+        Some((loc, contracts)) if contracts.is_empty() => {
+            if previous.len() > 1 {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    format!(
+                        "{} '{}' should specify override list 'override({list})'",
+                        func.ty, func.id
+                    ),
+                ));
+            }
+        }
+        Some((loc, contracts)) => {
+            let names = |nos: Vec<&usize>| {
+                nos.into_iter().map(|no| format!("'{}'", ctx.contracts[*no].id)).join(", ")
+            };
+            let missing = bases.iter().filter(|no| !contracts.contains(no)).collect::<Vec<_>>();
+            if !missing.is_empty() {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    format!(
+                        "{} '{}' is missing {} in its override list, specify 'override({list})'",
+                        func.ty,
+                        func.id,
+                        names(missing)
+                    ),
+                ));
+            }
+            let extra = contracts.iter().filter(|no| !bases.contains(no)).collect::<Vec<_>>();
+            if !extra.is_empty() {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    format!(
+                        "{} '{}' does not override a function of {}, specify 'override({list})'",
+                        func.ty,
+                        func.id,
+                        names(extra)
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Whether a function with the visibility may override one with the other:
+/// the same, or public for external
+fn visibility_overrides(visibility: &pt::Visibility, overridden: &pt::Visibility) -> bool {
+    matches!(
+        (visibility, overridden),
+        (pt::Visibility::Public(_), pt::Visibility::Public(_) | pt::Visibility::External(_)) |
+            (pt::Visibility::External(_), pt::Visibility::External(_)) |
+            (pt::Visibility::Internal(_), pt::Visibility::Internal(_)) |
+            (pt::Visibility::Private(_), pt::Visibility::Private(_))
+    )
+}
+
+/// Whether a function with the mutability may override one with the other:
+/// the same or stricter, except that payable functions stay payable
+fn mutability_overrides(mutability: &Mutability, overridden: &Mutability) -> bool {
+    matches!(
+        (mutability, overridden),
+        (Mutability::Payable(_), Mutability::Payable(_)) |
+            (Mutability::Nonpayable(_), Mutability::Nonpayable(_)) |
+            (Mutability::View(_), Mutability::Nonpayable(_) | Mutability::View(_)) |
+            (
+                Mutability::Pure(_),
+                Mutability::Nonpayable(_) | Mutability::View(_) | Mutability::Pure(_)
+            )
+    )
+}
+
+/// The quoted names of the contracts of the functions
+fn contract_names(functions: &[usize], ctx: &Context, separator: &str) -> String {
+    functions
+        .iter()
+        .filter_map(|function_no| ctx.functions[*function_no].contract_no)
+        .map(|contract_no| format!("'{}'", ctx.contracts[contract_no].id))
+        .join(separator)
+}
+
+/// A note on the definition of each function
+fn definitions(functions: &[usize], ctx: &Context) -> Vec<Note> {
+    functions
+        .iter()
+        .map(|function_no| {
+            let func = &ctx.functions[*function_no];
+            Note { loc: func.loc_prototype, message: format!("definition of '{}'", func.id) }
+        })
+        .collect()
+}
+
+/// The functions of the contract and its bases declared without a body which
+/// no function of a more derived contract implements
+pub fn unimplemented_functions(contract_no: usize, ctx: &Context) -> Vec<usize> {
+    let mut functions: IndexMap<&str, usize> = IndexMap::new();
+
+    for base_no in ctx.contract_bases(contract_no) {
+        for &function_no in &ctx.contracts[base_no].functions {
+            let func = &ctx.functions[function_no];
+            if !func.is_constructor() {
+                functions.insert(&func.signature, function_no);
+            }
+        }
+    }
+
+    functions.into_values().filter(|function_no| !ctx.functions[*function_no].has_body).collect()
+}
+
+/// Decide whether the contract can be created, with `new` or by deploying it:
+/// it is a contract, not abstract, an interface or a library, it implements
+/// all its functions and neither it nor its bases have errors. A contract
+/// which is not declared abstract must implement the functions of its bases.
+pub fn compute_instantiable(contract_no: usize, ctx: &mut Context) {
+    let contract = &ctx.contracts[contract_no];
+    let unimplemented = unimplemented_functions(contract_no, ctx);
+
+    // The functions of the contract itself without a body are reported when
+    // its declarations are resolved
+    let inherited = unimplemented
+        .iter()
+        .filter(|function_no| ctx.functions[**function_no].contract_no != Some(contract_no))
+        .map(|function_no| {
+            let func = &ctx.functions[*function_no];
+            Note {
+                loc: func.loc_prototype,
+                message: format!("missing implementation of function '{}'", func.id),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if contract.is_concrete() && !inherited.is_empty() {
+        ctx.diagnostics.push(
+            Diagnostic::builder(contract.loc, Level::Error)
+                .message(format!(
+                    "contract '{}' should be marked 'abstract contract' since it does not \
+                     implement {} inherited functions",
+                    contract.id,
+                    inherited.len()
+                ))
+                .notes(inherited)
+                .build(),
+        );
+    }
+
+    let bases = ctx.contract_bases(contract_no);
+    let errors = ctx.diagnostics.iter().any(|diagnostic| {
+        diagnostic.level == Level::Error &&
+            bases.iter().any(|no| within(&diagnostic.loc, &ctx.contracts[*no].loc))
+    });
+
+    ctx.contracts[contract_no].instantiable =
+        ctx.contracts[contract_no].is_concrete() && unimplemented.is_empty() && !errors;
+}
+
+/// The interface id a contract claims, e.g. in its ERC-165 `supportsInterface`,
+/// is computed from the selectors of the functions of the interface. Check
+/// the contract implements each of them with that selector, which an
+/// annotation could change. Missing implementations are reported by
+/// [`compute_instantiable`].
+pub fn check_interface_ids(contract_no: usize, ctx: &mut Context) {
+    if !ctx.contracts[contract_no].is_concrete() {
+        return;
+    }
+
+    let bases = ctx.contract_bases(contract_no);
+
+    for &base_no in bases.iter().filter(|base_no| ctx.contracts[**base_no].is_interface()) {
+        for function_no in ethereum::interface_functions(base_no, ctx) {
+            let func = &ctx.functions[function_no];
+
+            // The most derived implementation
+            let Some(implementation) = bases
+                .iter()
+                .rev()
+                .flat_map(|no| &ctx.contracts[*no].functions)
+                .map(|no| &ctx.functions[*no])
+                .find(|implementation| {
+                    implementation.signature == func.signature && implementation.has_body
+                })
+            else {
+                continue;
+            };
+
+            if implementation.selector() != func.selector() {
+                let interface = &ctx.contracts[base_no].id;
+                ctx.diagnostics.push(
+                    Diagnostic::builder(implementation.loc_prototype, Level::Error)
+                        .message(format!(
+                            "function '{}' has selector '0x{}' but the interface id of '{}' \
+                             is computed with selector '0x{}'",
+                            implementation.id,
+                            ethereum::hex(&implementation.selector()),
+                            interface,
+                            ethereum::hex(&func.selector()),
+                        ))
+                        .note(
+                            func.loc_prototype,
+                            format!("definition of '{interface}.{}'", func.id),
+                        )
+                        .build(),
+                );
+            }
+        }
+    }
+}
+
+/// Is the location inside the other one
+fn within(loc: &pt::Loc, outer: &pt::Loc) -> bool {
+    match (loc, outer) {
+        (pt::Loc::File(no, start, end), pt::Loc::File(outer_no, outer_start, outer_end)) => {
+            no == outer_no && outer_start <= start && end <= outer_end
+        }
+        _ => false,
+    }
+}
+
+/// A contract embeds the code of the contracts it creates, so it cannot create
+/// itself, directly or through the contracts it creates
+pub fn check_circular_creation(contract_no: usize, ctx: &mut Context) {
+    let creates = ctx.contracts[contract_no]
+        .functions
+        .iter()
+        .flat_map(|function_no| ctx.functions[*function_no].creates.iter().copied())
+        .collect::<Vec<_>>();
+
+    for (loc, created_no) in creates {
+        let Some(path) = creation_path(created_no, contract_no, ctx, &mut Vec::new()) else {
+            continue;
+        };
+
+        let notes = std::iter::once(contract_no)
+            .chain(path.iter().copied())
+            .zip(path.iter())
+            .map(|(creator_no, created_no)| Note {
+                loc: ctx.contracts[creator_no].loc,
+                message: format!(
+                    "contract '{}' creates contract '{}'",
+                    ctx.contracts[creator_no].id, ctx.contracts[*created_no].id
+                ),
+            })
+            .collect();
+
+        ctx.diagnostics.push(
+            Diagnostic::builder(loc, Level::Error)
+                .message(format!(
+                    "circular dependency creating contract '{}'",
+                    ctx.contracts[created_no].id
+                ))
+                .notes(notes)
+                .build(),
+        );
+    }
+}
+
+/// The contracts through which `from` creates `to`, ending with `to`
+fn creation_path(
+    from: usize,
+    to: usize,
+    ctx: &Context,
+    visited: &mut Vec<usize>,
+) -> Option<Vec<usize>> {
+    if from == to {
+        return Some(vec![to]);
+    }
+    if visited.contains(&from) {
+        return None;
+    }
+    visited.push(from);
+
+    ctx.contracts[from].creates.iter().find_map(|created_no| {
+        let mut path = creation_path(*created_no, to, ctx, visited)?;
+        path.insert(0, from);
+        Some(path)
+    })
+}
+
+/// Check the constructor of each base which needs arguments gets them, either
+/// in a list of bases or from a constructor, of the contract or of a base
+pub fn check_base_args(contract_no: usize, ctx: &mut Context) {
+    if !ctx.contracts[contract_no].is_concrete() {
+        return;
+    }
+
+    let bases = ctx.contract_bases(contract_no);
+
+    for &base_no in &bases {
+        if base_no == contract_no || !ctx.contracts[base_no].constructor_needs_arguments(ctx) {
+            continue;
+        }
+
+        let given = bases.iter().any(|&no| {
+            let contract = &ctx.contracts[no];
+            contract
+                .bases
+                .iter()
+                .any(|base| base.contract_no == base_no && base.constructor.is_some()) ||
+                contract
+                    .constructors(ctx)
+                    .into_iter()
+                    .any(|function_no| ctx.functions[function_no].bases.contains_key(&base_no))
+        });
+
+        if !given {
+            let contract = &ctx.contracts[contract_no];
+            ctx.diagnostics.push(Diagnostic::error(
+                contract.loc,
+                format!(
+                    "missing arguments to base contract '{}' constructor",
+                    ctx.contracts[base_no].id
+                ),
+            ));
+        }
+    }
+}
+
 // Is a contract a base of another contract
 pub fn is_base(base: usize, derived: usize, ctx: &Context) -> bool {
     let bases = &ctx.contracts[derived].bases;
@@ -382,3 +1175,324 @@ pub fn is_base(base: usize, derived: usize, ctx: &Context) -> bool {
 
     bases.iter().any(|parent| is_base(base, parent.contract_no, ctx))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{
+        ast::{Parameter, Type},
+        layout::tests::contract,
+    };
+
+    fn constructor(ctx: &mut Context, contract_no: usize, params: Vec<Type>) -> usize {
+        let func = Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            pt::Identifier { loc: pt::Loc::Builtin, name: String::new() },
+            Some(contract_no),
+            Vec::new(),
+            pt::FunctionTy::Constructor,
+            None,
+            pt::Visibility::Public(None),
+            params.into_iter().map(Parameter::new_default).collect(),
+            Vec::new(),
+            ctx,
+        );
+        ctx.functions.push(func);
+        ctx.contracts[contract_no].functions.push(ctx.functions.len() - 1);
+        ctx.functions.len() - 1
+    }
+
+    #[test]
+    fn test_default_constructor() {
+        let mut ctx = Context::new();
+        ctx.contracts.push(contract("Ownable", &[], Vec::new()));
+        let ownable = constructor(&mut ctx, 0, Vec::new());
+        ctx.contracts.push(contract("Token", &[0], Vec::new()));
+
+        add_default_constructor(1, &mut ctx);
+
+        let function_no = ctx.contracts[1].default_constructor.unwrap();
+        assert_eq!(ctx.contracts[1].constructors(&ctx), [function_no]);
+        assert!(!ctx.contracts[1].constructor_needs_arguments(&ctx));
+
+        let func = &ctx.functions[function_no];
+        assert!(func.is_constructor() && func.is_public() && func.has_body);
+        assert_eq!(func.bases.get(&0).map(|(_, no, args)| (*no, args.len())), Some((ownable, 0)));
+
+        // A contract with a constructor keeps it
+        add_default_constructor(0, &mut ctx);
+        assert_eq!(ctx.contracts[0].default_constructor, None);
+    }
+
+    #[test]
+    fn test_missing_base_args() {
+        let mut ctx = Context::new();
+        ctx.contracts.push(contract("ERC20", &[], Vec::new()));
+        constructor(&mut ctx, 0, vec![Type::String]);
+        ctx.contracts.push(contract("Token", &[0], Vec::new()));
+
+        add_default_constructor(1, &mut ctx);
+        check_base_args(1, &mut ctx);
+        assert_eq!(
+            ctx.diagnostics.first_error(),
+            "missing arguments to base contract 'ERC20' constructor"
+        );
+
+        // Arguments in the list of bases of an intermediate contract will do
+        let mut ctx = Context::new();
+        ctx.contracts.push(contract("ERC20", &[], Vec::new()));
+        constructor(&mut ctx, 0, vec![Type::String]);
+        ctx.contracts.push(contract("Named", &[0], Vec::new()));
+        ctx.contracts[1].bases[0].constructor = Some((0, Vec::new()));
+        ctx.contracts.push(contract("Token", &[1], Vec::new()));
+
+        check_base_args(2, &mut ctx);
+        assert!(!ctx.diagnostics.any_errors());
+    }
+
+    #[test]
+    fn test_circular_creation() {
+        let mut ctx = Context::new();
+        for name in ["Factory", "Pool", "Token"] {
+            ctx.contracts.push(contract(name, &[], Vec::new()));
+            let contract_no = ctx.contracts.len() - 1;
+            constructor(&mut ctx, contract_no, Vec::new());
+        }
+        // Factory creates Pool, which creates Token and Factory
+        for (creator_no, created_no) in [(0, 1), (1, 2), (1, 0)] {
+            let function_no = ctx.contracts[creator_no].functions[0];
+            ctx.functions[function_no].creates.push((pt::Loc::Builtin, created_no));
+            ctx.contracts[creator_no].creates.push(created_no);
+        }
+
+        check_circular_creation(2, &mut ctx);
+        assert!(!ctx.diagnostics.any_errors());
+
+        check_circular_creation(0, &mut ctx);
+        assert_eq!(ctx.diagnostics.first_error(), "circular dependency creating contract 'Pool'");
+        let notes = &ctx.diagnostics.iter().next().unwrap().notes;
+        let notes = notes.iter().map(|note| note.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            notes,
+            [
+                "contract 'Factory' creates contract 'Pool'",
+                "contract 'Pool' creates contract 'Factory'"
+            ]
+        );
+    }
+
+    fn function(ctx: &mut Context, contract_no: usize, name: &str, has_body: bool) -> usize {
+        let mut func = Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+            Some(contract_no),
+            Vec::new(),
+            pt::FunctionTy::Function,
+            None,
+            pt::Visibility::External(None),
+            vec![Parameter::new_default(Type::Bytes(4))],
+            Vec::new(),
+            ctx,
+        );
+        func.has_body = has_body;
+        ctx.functions.push(func);
+        ctx.contracts[contract_no].functions.push(ctx.functions.len() - 1);
+        ctx.functions.len() - 1
+    }
+
+    #[test]
+    fn test_interface_ids() {
+        let mut ctx = Context::new();
+        let mut erc165 = contract("IERC165", &[], Vec::new());
+        erc165.ty = pt::ContractTy::Interface(pt::Loc::Builtin);
+        ctx.contracts.push(erc165);
+        function(&mut ctx, 0, "supportsInterface", false);
+        ctx.contracts.push(contract("Token", &[0], Vec::new()));
+        let implementation = function(&mut ctx, 1, "supportsInterface", true);
+
+        check_interface_ids(1, &mut ctx);
+        assert!(!ctx.diagnostics.any_errors());
+
+        // An annotation gives the implementation another selector
+        ctx.functions[implementation].selector = Some((pt::Loc::Builtin, vec![1, 2, 3, 4]));
+        check_interface_ids(1, &mut ctx);
+        assert_eq!(
+            ctx.diagnostics.first_error(),
+            "function 'supportsInterface' has selector '0x01020304' but the interface id of \
+             'IERC165' is computed with selector '0x01ffc9a7'"
+        );
+    }
+
+    /// Contracts with the given bases, those marked with a virtual function
+    /// `f` overriding the ones of their bases
+    fn contracts(ctx: &mut Context, bases: &[(&str, &[usize], bool)]) -> Vec<usize> {
+        let mut functions = Vec::new();
+        for (name, bases, has_f) in bases {
+            ctx.contracts.push(contract(name, bases, Vec::new()));
+            if *has_f {
+                let function_no = function(ctx, ctx.contracts.len() - 1, "f", true);
+                ctx.functions[function_no].is_virtual = true;
+                if !bases.is_empty() {
+                    ctx.functions[function_no].is_override = Some((pt::Loc::Builtin, Vec::new()));
+                }
+                functions.push(function_no);
+            }
+        }
+        functions
+    }
+
+    fn errors(ctx: &Context) -> Vec<&str> {
+        ctx.diagnostics.errors().into_iter().map(|error| error.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_linearization() {
+        let mut ctx = Context::new();
+        contracts(
+            &mut ctx,
+            &[("A", &[], false), ("B", &[0], false), ("C", &[0, 1], false), ("D", &[1, 0], false)],
+        );
+
+        assert_eq!(ctx.linearize(2), Some(vec![2, 1, 0]));
+        assert_eq!(ctx.contract_bases(2), [0, 1, 2]);
+        check_inheritance(2, &mut ctx);
+        assert!(!ctx.diagnostics.any_errors());
+
+        // 'A' cannot be more derived than its base 'B'
+        assert_eq!(ctx.linearize(3), None);
+        check_inheritance(3, &mut ctx);
+        assert_eq!(
+            ctx.diagnostics.first_error(),
+            "the bases of contract 'D' cannot be linearized, list them from the most base-like \
+             to the most derived"
+        );
+
+        // Unlike a depth-first order, a base shared by two bases comes
+        // before both
+        let mut ctx = Context::new();
+        contracts(
+            &mut ctx,
+            &[("A", &[], false), ("B", &[0], false), ("C", &[0], false), ("D", &[1, 2], false)],
+        );
+        assert_eq!(ctx.contract_bases(3), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut ctx = Context::new();
+        let f = contracts(&mut ctx, &[("A", &[], true), ("B", &[0], true), ("C", &[1], false)]);
+
+        check_inheritance(2, &mut ctx);
+        assert!(!ctx.diagnostics.any_errors());
+        let contract = &ctx.contracts[2];
+        assert_eq!(contract.virtual_functions["f(bytes4)"], f);
+        assert_eq!(
+            contract.all_functions.iter().collect::<Vec<_>>(),
+            [(&f[0], &f[1]), (&f[1], &f[1])]
+        );
+
+        // The function overridden must be virtual and the override marked
+        ctx.functions[f[0]].is_virtual = false;
+        ctx.functions[f[1]].is_override = None;
+        let g = function(&mut ctx, 2, "g", true);
+        ctx.functions[g].is_override = Some((pt::Loc::Builtin, vec![0]));
+        check_inheritance(1, &mut ctx);
+        check_inheritance(2, &mut ctx);
+        assert_eq!(
+            errors(&ctx),
+            [
+                "function 'f' overrides function which is not 'virtual'",
+                "function 'f' should specify 'override'",
+                "function 'g' does not override anything"
+            ]
+        );
+
+        // Overrides keep the return types and visibility, and may only make the
+        // state mutability stricter
+        let mut ctx = Context::new();
+        let f = contracts(&mut ctx, &[("A", &[], true), ("B", &[0], true)]);
+        ctx.functions[f[0]].mutability = Mutability::View(pt::Loc::Builtin);
+        ctx.functions[f[1]].visibility = pt::Visibility::Public(None);
+        check_inheritance(1, &mut ctx);
+        assert_eq!(
+            errors(&ctx),
+            ["function 'f' changes state mutability from 'view' to 'nonpayable'"]
+        );
+
+        // Implementing a function of an interface needs no 'override'
+        let mut ctx = Context::new();
+        let f = contracts(&mut ctx, &[("I", &[], false), ("B", &[0], true)]);
+        ctx.contracts[0].ty = pt::ContractTy::Interface(pt::Loc::Builtin);
+        let i = function(&mut ctx, 0, "f", false);
+        ctx.functions[i].is_virtual = true;
+        ctx.functions[f[0]].is_override = None;
+        check_inheritance(1, &mut ctx);
+        assert!(!ctx.diagnostics.any_errors());
+    }
+
+    #[test]
+    fn test_diamond() {
+        let mut ctx = Context::new();
+        let f = contracts(
+            &mut ctx,
+            &[("A", &[], true), ("B", &[0], true), ("C", &[0], true), ("D", &[1, 2], false)],
+        );
+
+        check_inheritance(3, &mut ctx);
+        assert_eq!(
+            errors(&ctx),
+            ["contract 'D' must override function 'f', which it inherits from 'B' and 'C'"]
+        );
+
+        // Overriding both needs the list of their contracts
+        let d = function(&mut ctx, 3, "f", true);
+        ctx.functions[d].is_override = Some((pt::Loc::Builtin, Vec::new()));
+        let mut check = |list: Vec<usize>| {
+            ctx.diagnostics = Diagnostics::default();
+            ctx.functions[d].is_override = Some((pt::Loc::Builtin, list));
+            check_inheritance(3, &mut ctx);
+            errors(&ctx).into_iter().map(String::from).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            check(Vec::new()),
+            ["function 'f' should specify override list 'override(B, C)'"]
+        );
+        assert_eq!(
+            check(vec![1, 0]),
+            [
+                "function 'f' is missing 'C' in its override list, specify 'override(B, C)'",
+                "function 'f' does not override a function of 'A', specify 'override(B, C)'"
+            ]
+        );
+        assert!(check(vec![2, 1]).is_empty());
+        assert_eq!(ctx.contracts[3].virtual_functions["f(bytes4)"], [f[0], f[1], f[2], d]);
+    }
+
+    #[test]
+    fn test_inherited_symbols() {
+        let mut ctx = Context::new();
+        let loc = |start| pt::Loc::File(0, start, start + 1);
+        contracts(&mut ctx, &[("A", &[], false), ("B", &[], false), ("C", &[0, 1], false)]);
+        ctx.contracts[0].variables.push(layout::tests::variable("x", Type::Bool, None));
+        ctx.contracts[0].variables[0].visibility = pt::Visibility::Private(None);
+        ctx.contracts[1].variables.push(layout::tests::variable("y", Type::Bool, None));
+        let mut symbol = |contract_no, name: &str, symbol| {
+            ctx.variable_symbols.insert((0, Some(contract_no), name.into()), symbol);
+        };
+        symbol(0, "x", Symbol::Variable(loc(0), Some(0), 0));
+        symbol(0, "y", Symbol::Enum(loc(2), 0));
+        symbol(1, "x", Symbol::Enum(loc(4), 1));
+        symbol(1, "y", Symbol::Variable(loc(6), Some(1), 0));
+        symbol(2, "x", Symbol::Event(vec![(loc(8), 0)]));
+
+        // The private 'x' of 'A' is not inherited
+        check_inheritance(2, &mut ctx);
+        let errors = ctx.diagnostics.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "contract 'C' inherits 'y' from both 'A' and 'B'");
+        assert_eq!(errors[1].message, "'x' is already defined in base contract 'B'");
+        assert_eq!(errors[1].notes[0].loc, loc(4));
+    }
+}
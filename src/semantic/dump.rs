@@ -0,0 +1,194 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dump of the declarations of an analyzed context as JSON, for tools which
+//! need the resolved types of the declarations without resolving them again:
+//! the contracts with their bases, state variables and functions, the free
+//! functions, and the structs, enums, events, errors, user types and
+//! constants. Types are written as in Solidity, and the bodies of the
+//! functions are left out.
+
+use crate::{
+    helpers::json::Json,
+    semantic::{
+        ast::{Contract, Function, Parameter, Type, Variable},
+        context::Context,
+    },
+};
+
+/// The declarations of the context
+pub fn to_json(ctx: &Context) -> Json {
+    let free_functions = ctx.functions.iter().filter(|func| func.contract_no.is_none());
+
+    Json::object([
+        ("files", array(&ctx.files, |file| file.path.display().to_string().into())),
+        ("contracts", array(&ctx.contracts, |contract| contract_json(contract, ctx))),
+        ("functions", array(free_functions, |func| function_json(func, ctx))),
+        (
+            "structs",
+            array(&ctx.structs, |decl| {
+                Json::object([
+                    ("name", decl.to_string().into()),
+                    ("fields", array(&decl.fields, |field| param_json(field, ctx))),
+                ])
+            }),
+        ),
+        (
+            "enums",
+            array(&ctx.enums, |decl| {
+                Json::object([
+                    ("name", decl.to_string().into()),
+                    ("values", array(decl.values.keys(), |value| value.as_str().into())),
+                ])
+            }),
+        ),
+        (
+            "events",
+            array(&ctx.events, |decl| {
+                let fields = array(&decl.fields, |field| {
+                    let mut json = param_json(field, ctx);
+                    json.insert("indexed", field.indexed.into());
+                    json
+                });
+                Json::object([
+                    ("name", decl.symbol_name(ctx).into()),
+                    ("fields", fields),
+                    ("anonymous", decl.anonymous.into()),
+                ])
+            }),
+        ),
+        (
+            "errors",
+            array(&ctx.errors, |decl| {
+                Json::object([
+                    ("name", decl.symbol_name(ctx).into()),
+                    ("fields", array(&decl.fields, |field| param_json(field, ctx))),
+                ])
+            }),
+        ),
+        (
+            "types",
+            array(&ctx.user_types, |decl| {
+                Json::object([
+                    ("name", decl.to_string().into()),
+                    ("type", decl.ty.to_string(ctx).into()),
+                ])
+            }),
+        ),
+        ("constants", array(&ctx.constants, |var| variable_json(var, ctx))),
+    ])
+}
+
+fn array<T>(values: impl IntoIterator<Item = T>, f: impl FnMut(T) -> Json) -> Json {
+    Json::Array(values.into_iter().map(f).collect())
+}
+
+fn contract_json(contract: &Contract, ctx: &Context) -> Json {
+    Json::object([
+        ("name", contract.id.name.as_str().into()),
+        ("kind", contract.ty.to_string().into()),
+        (
+            "bases",
+            array(&contract.bases, |base| ctx.contracts[base.contract_no].id.name.as_str().into()),
+        ),
+        ("variables", array(&contract.variables, |var| variable_json(var, ctx))),
+        ("functions", array(&contract.functions, |&no| function_json(&ctx.functions[no], ctx))),
+    ])
+}
+
+fn function_json(func: &Function, ctx: &Context) -> Json {
+    Json::object([
+        ("name", func.id.name.as_str().into()),
+        ("kind", func.ty.to_string().into()),
+        ("signature", func.signature.as_str().into()),
+        ("visibility", func.visibility.to_string().into()),
+        ("mutability", func.mutability.to_string().into()),
+        ("params", array(func.params.iter(), |param| param_json(param, ctx))),
+        ("returns", array(func.returns.iter(), |param| param_json(param, ctx))),
+    ])
+}
+
+fn variable_json(var: &Variable, ctx: &Context) -> Json {
+    Json::object([
+        ("name", var.name.as_str().into()),
+        ("type", var.ty.to_string(ctx).into()),
+        ("visibility", var.visibility.to_string().into()),
+        ("constant", var.constant.into()),
+        ("immutable", var.immutable.into()),
+    ])
+}
+
+fn param_json(param: &Parameter<Type>, ctx: &Context) -> Json {
+    Json::object([
+        ("name", param.id.as_ref().map(|id| id.name.as_str()).into()),
+        ("type", param.ty.to_string(ctx).into()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::ast as pt,
+        semantic::layout::tests::{contract, variable},
+    };
+
+    #[test]
+    fn test_dump() {
+        let mut ctx = Context::new();
+        ctx.contracts.push(contract("Token", &[], vec![variable("supply", Type::Uint(256), None)]));
+        ctx.contracts.push(contract("Coin", &[0], Vec::new()));
+
+        let amount = Parameter {
+            loc: pt::Loc::Builtin,
+            id: Some(pt::Identifier { loc: pt::Loc::Builtin, name: "amount".into() }),
+            ty: Type::Uint(64),
+            ty_id: None,
+            ty_loc: None,
+            indexed: false,
+            readonly: false,
+            infinite_size: false,
+            recursive: false,
+            annotation: None,
+        };
+        let func = Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            pt::Identifier { loc: pt::Loc::Builtin, name: "burn".into() },
+            Some(1),
+            Vec::new(),
+            pt::FunctionTy::Function,
+            None,
+            pt::Visibility::External(None),
+            vec![amount],
+            Vec::new(),
+            &ctx,
+        );
+        ctx.functions.push(func);
+        ctx.contracts[1].functions.push(0);
+
+        let json = to_json(&ctx);
+        let contracts = json.get("contracts").unwrap().as_array().unwrap();
+        assert_eq!(
+            contracts[0].get("variables").unwrap().to_string(),
+            r#"[{"name":"supply","type":"uint256","visibility":"internal","constant":false,"immutable":false}]"#
+        );
+        assert_eq!(contracts[1].get("bases").unwrap().to_string(), r#"["Token"]"#);
+        assert_eq!(
+            contracts[1].get("functions").unwrap().to_string(),
+            r#"[{"name":"burn","kind":"function","signature":"burn(uint64)","visibility":"external","mutability":"nonpayable","params":[{"name":"amount","type":"uint64"}],"returns":[]}]"#
+        );
+        assert_eq!(json.get("functions").unwrap().to_string(), "[]");
+    }
+}
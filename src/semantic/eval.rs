@@ -12,11 +12,421 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{diagnostics::Diagnostics, semantic::ast::Expression};
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+    abi::ethereum,
+    diagnostics::{Diagnostic, DiagnosticSink, ErrorType, Level},
+    parser::ast::Loc,
+    semantic::{
+        ast::{Expression, Recurse, Type},
+        context::Context,
+    },
+};
+
+/// Largest shift or exponent folded, as larger ones overflow any type
+const MAX_SHIFT: u32 = 1024;
 
 impl Expression {
     /// Check the expression for constant overflows, e.g. `uint8 a = 100 + 200;`.
-    pub fn check_constant_overflow(&self, _diagnostics: &mut Diagnostics) {
-        todo!()
+    pub fn check_constant_overflow(&self, diagnostics: &mut dyn DiagnosticSink) {
+        self.recurse(&mut &mut *diagnostics, check_term_for_constant_overflow);
+    }
+
+    /// Fold an event selector into the `bytes32` literal of its topic, for
+    /// where the value is needed at compile time, like a constant or assembly
+    pub fn eval_event_selector(&self, ctx: &Context) -> Option<Expression> {
+        match self {
+            Expression::EventSelector { loc, ty, event_no } => Some(Expression::BytesLiteral {
+                loc: *loc,
+                ty: ty.clone(),
+                value: ctx.events[*event_no].selector(ctx).to_vec(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Fold `type(I).interfaceId` into the `bytes4` literal of the interface id
+    pub fn eval_interface_id(&self, ctx: &Context) -> Option<Expression> {
+        match self {
+            Expression::InterfaceId { loc, ty, contract_no } => Some(Expression::BytesLiteral {
+                loc: *loc,
+                ty: ty.clone(),
+                value: ethereum::interface_id(*contract_no, ctx).to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Fold the arithmetic on number literals of the term, and check the result
+/// fits its type. Terms which are folded are not recursed into, so a literal
+/// is only reported once.
+fn check_term_for_constant_overflow(
+    expr: &Expression,
+    diagnostics: &mut &mut dyn DiagnosticSink,
+) -> bool {
+    match expr {
+        Expression::Add { .. } |
+        Expression::Subtract { .. } |
+        Expression::Multiply { .. } |
+        Expression::Divide { .. } |
+        Expression::Modulo { .. } |
+        Expression::Power { .. } |
+        Expression::ShiftLeft { .. } |
+        Expression::ShiftRight { .. } |
+        Expression::BitwiseAnd { .. } |
+        Expression::BitwiseOr { .. } |
+        Expression::BitwiseXor { .. } |
+        Expression::Negate { .. } |
+        Expression::ZeroExt { .. } |
+        Expression::SignExt { .. } |
+        Expression::NumberLiteral { .. } => {
+            match eval_constants_in_expression(expr, *diagnostics) {
+                (Some(Expression::NumberLiteral { loc, ty, value }), _) => {
+                    if let Some(diagnostic) = overflow_diagnostic(&value, &ty, loc) {
+                        diagnostics.push(diagnostic);
+                    }
+                    false
+                }
+                (None, false) => false,
+                _ => true,
+            }
+        }
+        Expression::BytesLiteral { loc, ty: Type::Bytes(len), value } => {
+            check_hex_literal_length(*loc, value, *len, *diagnostics);
+            false
+        }
+        Expression::BytesCast { loc, to: Type::Bytes(len), expr, .. } => match expr.as_ref() {
+            Expression::BytesLiteral { value, .. } => {
+                check_hex_literal_length(*loc, value, *len, *diagnostics);
+                false
+            }
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+/// Warn for a hex literal longer than the `bytesN` it is assigned to, whose
+/// trailing bytes are dropped
+fn check_hex_literal_length(loc: Loc, value: &[u8], len: u8, diagnostics: &mut dyn DiagnosticSink) {
+    if value.len() > len as usize {
+        diagnostics.push(Diagnostic::warning(
+            loc,
+            format!("hex literal of {} bytes is truncated to bytes{len}", value.len()),
+        ));
+    }
+}
+
+/// Fold the number literals of an arithmetic expression. The value is the
+/// folded literal if the expression is constant, and the flag is false when
+/// an error was found and reported, e.g. a division by zero.
+pub(crate) fn eval_constants_in_expression(
+    expr: &Expression,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> (Option<Expression>, bool) {
+    let literal = |loc: &Loc, ty: &Type, value: BigInt| {
+        (Some(Expression::NumberLiteral { loc: *loc, ty: ty.clone(), value }), true)
+    };
+
+    match expr {
+        Expression::NumberLiteral { .. } => (Some(expr.clone()), true),
+        Expression::Add { loc, ty, left, right, .. } => {
+            match eval_operands(left, right, diagnostics) {
+                Ok(Some((left, right))) => literal(loc, ty, left + right),
+                Ok(None) => (None, true),
+                Err(()) => (None, false),
+            }
+        }
+        Expression::Subtract { loc, ty, left, right, .. } => {
+            match eval_operands(left, right, diagnostics) {
+                Ok(Some((left, right))) => literal(loc, ty, left - right),
+                Ok(None) => (None, true),
+                Err(()) => (None, false),
+            }
+        }
+        Expression::Multiply { loc, ty, left, right, .. } => {
+            match eval_operands(left, right, diagnostics) {
+                Ok(Some((left, right))) => literal(loc, ty, left * right),
+                Ok(None) => (None, true),
+                Err(()) => (None, false),
+            }
+        }
+        Expression::Divide { loc, ty, left, right } |
+        Expression::Modulo { loc, ty, left, right } => {
+            match eval_operands(left, right, diagnostics) {
+                Ok(Some((_, right))) if right.is_zero() => {
+                    diagnostics.push(error(*loc, "divide by zero"));
+                    (None, false)
+                }
+                // Both truncate toward zero, as in Solidity
+                Ok(Some((left, right))) => match expr {
+                    Expression::Divide { .. } => literal(loc, ty, left / right),
+                    _ => literal(loc, ty, left % right),
+                },
+                Ok(None) => (None, true),
+                Err(()) => (None, false),
+            }
+        }
+        Expression::Power { loc, ty, base, exp, .. } => match eval_operands(base, exp, diagnostics)
+        {
+            Ok(Some((base, exp))) => match exp.to_u32().filter(|exp| *exp <= MAX_SHIFT) {
+                Some(exp) => literal(loc, ty, base.pow(exp)),
+                None => {
+                    diagnostics.push(error(*loc, format!("power by {exp} is not possible")));
+                    (None, false)
+                }
+            },
+            Ok(None) => (None, true),
+            Err(()) => (None, false),
+        },
+        Expression::ShiftLeft { loc, ty, left, right } |
+        Expression::ShiftRight { loc, ty, left, right, .. } => {
+            match eval_operands(left, right, diagnostics) {
+                Ok(Some((left, right))) => match right.to_u32().filter(|n| *n <= MAX_SHIFT) {
+                    Some(n) => match expr {
+                        Expression::ShiftLeft { .. } => literal(loc, ty, left << n),
+                        _ => literal(loc, ty, left >> n),
+                    },
+                    None => {
+                        diagnostics.push(error(*loc, format!("shift by {right} is not possible")));
+                        (None, false)
+                    }
+                },
+                Ok(None) => (None, true),
+                Err(()) => (None, false),
+            }
+        }
+        Expression::BitwiseAnd { loc, ty, left, right } |
+        Expression::BitwiseOr { loc, ty, left, right } |
+        Expression::BitwiseXor { loc, ty, left, right } => {
+            match eval_operands(left, right, diagnostics) {
+                Ok(Some((left, right))) => match expr {
+                    Expression::BitwiseAnd { .. } => literal(loc, ty, left & right),
+                    Expression::BitwiseOr { .. } => literal(loc, ty, left | right),
+                    _ => literal(loc, ty, left ^ right),
+                },
+                Ok(None) => (None, true),
+                Err(()) => (None, false),
+            }
+        }
+        Expression::Negate { loc, ty, expr, .. } => {
+            match eval_constants_in_expression(expr, diagnostics) {
+                (Some(Expression::NumberLiteral { value, .. }), _) => literal(loc, ty, -value),
+                other => (None, other.1),
+            }
+        }
+        // The value is unchanged by the extension, but now has the wider type
+        Expression::ZeroExt { loc, to, expr } | Expression::SignExt { loc, to, expr } => {
+            match eval_constants_in_expression(expr, diagnostics) {
+                (Some(Expression::NumberLiteral { value, .. }), _) => literal(loc, to, value),
+                other => (None, other.1),
+            }
+        }
+        _ => (None, true),
+    }
+}
+
+/// Fold both operands, `None` when either is not constant
+fn eval_operands(
+    left: &Expression,
+    right: &Expression,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Option<(BigInt, BigInt)>, ()> {
+    let left = eval_constants_in_expression(left, diagnostics);
+    let right = eval_constants_in_expression(right, diagnostics);
+
+    match (left, right) {
+        (
+            (Some(Expression::NumberLiteral { value: left, .. }), _),
+            (Some(Expression::NumberLiteral { value: right, .. }), _),
+        ) => Ok(Some((left, right))),
+        ((_, false), _) | (_, (_, false)) => Err(()),
+        _ => Ok(None),
+    }
+}
+
+/// The error for a constant which does not fit in its integer type
+pub(crate) fn overflow_diagnostic(value: &BigInt, ty: &Type, loc: Loc) -> Option<Diagnostic> {
+    let message = match ty {
+        Type::Uint(_) if value.is_negative() => format!(
+            "negative value {value} does not fit into type {}. Cannot implicitly convert signed literal to unsigned type.",
+            type_name(ty)
+        ),
+        Type::Uint(bits) if value.bits() > *bits as u64 => {
+            format!("value {value} does not fit into type uint{bits}.")
+        }
+        Type::Int(bits) => {
+            let (min, max) = int_range(*bits);
+            if *value >= min && *value <= max {
+                return None;
+            }
+            format!("value {value} does not fit into type int{bits}.")
+        }
+        _ => return None,
+    };
+
+    Some(Diagnostic::builder(loc, Level::Error).ty(ErrorType::TypeError).message(message).build())
+}
+
+/// The smallest and largest values of a signed integer of the bits
+fn int_range(bits: u16) -> (BigInt, BigInt) {
+    let half = BigInt::one() << (bits - 1);
+    (-half.clone(), half - 1)
+}
+
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Uint(bits) => format!("uint{bits}"),
+        Type::Int(bits) => format!("int{bits}"),
+        _ => unreachable!(),
+    }
+}
+
+fn error(loc: Loc, message: impl Into<String>) -> Diagnostic {
+    Diagnostic::builder(loc, Level::Error).ty(ErrorType::TypeError).message(message).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+
+    fn number(ty: Type, value: impl Into<BigInt>) -> Expression {
+        Expression::NumberLiteral { loc: Loc::Builtin, ty, value: value.into() }
+    }
+
+    fn errors(expr: &Expression) -> Vec<String> {
+        let mut diagnostics = Diagnostics::default();
+        expr.check_constant_overflow(&mut diagnostics);
+        diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_unsigned_ranges() {
+        for bits in (8..=256).step_by(8) {
+            let ty = Type::Uint(bits);
+            let max: BigInt = (BigInt::one() << bits) - 1;
+
+            assert!(errors(&number(ty.clone(), 0)).is_empty());
+            assert!(errors(&number(ty.clone(), max.clone())).is_empty());
+            assert_eq!(
+                errors(&number(ty.clone(), max.clone() + 1)),
+                [format!("value {} does not fit into type uint{bits}.", max + 1)]
+            );
+            assert_eq!(
+                errors(&number(ty, -1)),
+                [format!(
+                    "negative value -1 does not fit into type uint{bits}. Cannot implicitly convert signed literal to unsigned type."
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn test_signed_ranges() {
+        for bits in (8..=256).step_by(8) {
+            let ty = Type::Int(bits);
+            let (min, max) = int_range(bits);
+
+            assert!(errors(&number(ty.clone(), min.clone())).is_empty());
+            assert!(errors(&number(ty.clone(), max.clone())).is_empty());
+            assert_eq!(
+                errors(&number(ty.clone(), max.clone() + 1)),
+                [format!("value {} does not fit into type int{bits}.", max + 1)]
+            );
+            assert_eq!(
+                errors(&number(ty, min.clone() - 1)),
+                [format!("value {} does not fit into type int{bits}.", min - 1)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_odd_widths() {
+        assert!(errors(&number(Type::Uint(1), 1)).is_empty());
+        assert_eq!(errors(&number(Type::Uint(1), 2)).len(), 1);
+        assert!(errors(&number(Type::Int(1), -1)).is_empty());
+        assert_eq!(errors(&number(Type::Int(1), 1)).len(), 1);
+        assert_eq!(int_range(12), (BigInt::from(-2048), BigInt::from(2047)));
+    }
+
+    #[test]
+    fn test_folded_arithmetic() {
+        let add = |left, right| Expression::Add {
+            loc: Loc::Builtin,
+            ty: Type::Uint(8),
+            unchecked: false,
+            left: Box::new(number(Type::Uint(8), left)),
+            right: Box::new(number(Type::Uint(8), right)),
+        };
+        assert!(errors(&add(100, 155)).is_empty());
+        assert_eq!(errors(&add(100, 200)), ["value 300 does not fit into type uint8."]);
+
+        let subtract = Expression::Subtract {
+            loc: Loc::Builtin,
+            ty: Type::Uint(8),
+            unchecked: false,
+            left: Box::new(number(Type::Uint(8), 1)),
+            right: Box::new(number(Type::Uint(8), 2)),
+        };
+        assert_eq!(errors(&subtract).len(), 1);
+
+        let shift = Expression::ShiftLeft {
+            loc: Loc::Builtin,
+            ty: Type::Int(16),
+            left: Box::new(number(Type::Int(16), 1)),
+            right: Box::new(number(Type::Int(16), 15)),
+        };
+        assert_eq!(errors(&shift), ["value 32768 does not fit into type int16."]);
+
+        let power = Expression::Power {
+            loc: Loc::Builtin,
+            ty: Type::Uint(256),
+            unchecked: false,
+            base: Box::new(number(Type::Uint(256), 2)),
+            exp: Box::new(number(Type::Uint(256), 256)),
+        };
+        assert_eq!(errors(&power).len(), 1);
+
+        let divide = Expression::Divide {
+            loc: Loc::Builtin,
+            ty: Type::Uint(8),
+            left: Box::new(number(Type::Uint(8), 1)),
+            right: Box::new(number(Type::Uint(8), 0)),
+        };
+        assert_eq!(errors(&divide), ["divide by zero"]);
+
+        // Widening keeps the value, which is checked against the wider type
+        let ext = Expression::ZeroExt {
+            loc: Loc::Builtin,
+            to: Type::Uint(16),
+            expr: Box::new(add(100, 200)),
+        };
+        assert!(errors(&ext).is_empty());
+    }
+
+    #[test]
+    fn test_truncated_hex_literals() {
+        let literal = |len, bytes: &[u8]| Expression::BytesLiteral {
+            loc: Loc::Builtin,
+            ty: Type::Bytes(len),
+            value: bytes.to_vec(),
+        };
+        assert!(errors(&literal(4, &[1, 2, 3, 4])).is_empty());
+        assert_eq!(
+            errors(&literal(2, &[1, 2, 3, 4])),
+            ["hex literal of 4 bytes is truncated to bytes2"]
+        );
+
+        let cast = Expression::BytesCast {
+            loc: Loc::Builtin,
+            from: Type::DynamicBytes,
+            to: Type::Bytes(1),
+            expr: Box::new(literal(4, &[1, 2, 3, 4])),
+        };
+        assert_eq!(errors(&cast).len(), 1);
     }
 }
@@ -0,0 +1,824 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The arithmetic operators.
+//!
+//! The binary operators cast both operands to their common type, a literal
+//! taking the type of the other operand, and are folded on literals.
+//! Exponentiation and shifts follow solc: the result has the type of the
+//! left operand, the right operand must be unsigned, and the operations on
+//! two literals are folded exactly. A literal left operand with a right one
+//! which is not a literal is a `uint256`, or an `int256` when negative.
+
+use num_bigint::{BigInt, Sign};
+use num_rational::Ratio;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Expression, RetrieveType, Type},
+        context::Context,
+        expression::{
+            coerce::coerce_types, literals::bigint_to_expression, resolve_expression::expression,
+            ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// Largest exponent of a folded power or shift, as larger ones overflow any
+/// type unless the base is -1, 0 or 1
+const MAX_EXPONENT: u32 = 256;
+
+/// A binary arithmetic or bitwise operator, other than exponentiation and
+/// shifts
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Arithmetic {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    BitwiseOr,
+    BitwiseAnd,
+    BitwiseXor,
+}
+
+impl Arithmetic {
+    /// The operator of the parsed expression, with its operands
+    pub fn from_pt(
+        expr: &pt::Expression,
+    ) -> Option<(pt::Loc, Arithmetic, &pt::Expression, &pt::Expression)> {
+        let (loc, op, left, right) = match expr {
+            pt::Expression::Add(loc, l, r) => (loc, Arithmetic::Add, l, r),
+            pt::Expression::Subtract(loc, l, r) => (loc, Arithmetic::Subtract, l, r),
+            pt::Expression::Multiply(loc, l, r) => (loc, Arithmetic::Multiply, l, r),
+            pt::Expression::Divide(loc, l, r) => (loc, Arithmetic::Divide, l, r),
+            pt::Expression::Modulo(loc, l, r) => (loc, Arithmetic::Modulo, l, r),
+            pt::Expression::BitwiseOr(loc, l, r) => (loc, Arithmetic::BitwiseOr, l, r),
+            pt::Expression::BitwiseAnd(loc, l, r) => (loc, Arithmetic::BitwiseAnd, l, r),
+            pt::Expression::BitwiseXor(loc, l, r) => (loc, Arithmetic::BitwiseXor, l, r),
+            _ => return None,
+        };
+
+        Some((*loc, op, left, right))
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Arithmetic::Add => "+",
+            Arithmetic::Subtract => "-",
+            Arithmetic::Multiply => "*",
+            Arithmetic::Divide => "/",
+            Arithmetic::Modulo => "%",
+            Arithmetic::BitwiseOr => "|",
+            Arithmetic::BitwiseAnd => "&",
+            Arithmetic::BitwiseXor => "^",
+        }
+    }
+
+    /// Whether the operator applies to `bytesN` as well as integers
+    fn is_bitwise(self) -> bool {
+        matches!(self, Arithmetic::BitwiseOr | Arithmetic::BitwiseAnd | Arithmetic::BitwiseXor)
+    }
+}
+
+/// Resolve `left + right` and the other binary arithmetic operators
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn arithmetic(
+    loc: &pt::Loc,
+    op: Arithmetic,
+    l: &pt::Expression,
+    r: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let left = expression(l, context, ctx, symtable, diagnostics, resolve_to)?;
+    let right = expression(r, context, ctx, symtable, diagnostics, resolve_to)?;
+
+    resolve_arithmetic(loc, op, left, right, context.unchecked, ctx, diagnostics, resolve_to)
+}
+
+/// Resolve the operator on two resolved operands, which are cast to their
+/// common type. The operations on literals are folded exactly, so that
+/// `(2**256 - 1) / 3` is a literal of the value, and a division which is not
+/// exact is a rational literal.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn resolve_arithmetic(
+    loc: &pt::Loc,
+    op: Arithmetic,
+    left: Expression,
+    right: Expression,
+    unchecked: bool,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    if let (Some(l), Some(r)) = (rational_value(&left), rational_value(&right)) {
+        return fold_arithmetic(loc, op, l, r, ctx, diagnostics, resolve_to);
+    }
+
+    let Some(ty) = coerce_types(&left, &right, ctx) else {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!(
+                "operator '{}' is not supported between types '{}' and '{}'",
+                op.as_str(),
+                left.ty().deref_any().to_string(ctx),
+                right.ty().deref_any().to_string(ctx)
+            ),
+        ));
+        return Err(());
+    };
+
+    let valid = match ty {
+        Type::Uint(_) | Type::Int(_) => true,
+        Type::Bytes(_) => op.is_bitwise(),
+        _ => false,
+    };
+    if !valid {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!("operator '{}' is not supported on type '{}'", op.as_str(), ty.to_string(ctx)),
+        ));
+        return Err(());
+    }
+
+    let left = Box::new(left.cast(&left.loc(), &ty, true, ctx, diagnostics)?);
+    let right = Box::new(right.cast(&right.loc(), &ty, true, ctx, diagnostics)?);
+    let loc = *loc;
+
+    Ok(match op {
+        Arithmetic::Add => Expression::Add { loc, ty, unchecked, left, right },
+        Arithmetic::Subtract => Expression::Subtract { loc, ty, unchecked, left, right },
+        Arithmetic::Multiply => Expression::Multiply { loc, ty, unchecked, left, right },
+        Arithmetic::Divide => Expression::Divide { loc, ty, left, right },
+        Arithmetic::Modulo => Expression::Modulo { loc, ty, left, right },
+        Arithmetic::BitwiseOr => Expression::BitwiseOr { loc, ty, left, right },
+        Arithmetic::BitwiseAnd => Expression::BitwiseAnd { loc, ty, left, right },
+        Arithmetic::BitwiseXor => Expression::BitwiseXor { loc, ty, left, right },
+    })
+}
+
+/// The value of a number or rational literal
+fn rational_value(expr: &Expression) -> Option<Ratio<BigInt>> {
+    match expr {
+        Expression::NumberLiteral { value, .. } => Some(Ratio::from_integer(value.clone())),
+        Expression::RationalNumberLiteral { value, .. } => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// The exact result of the operator on two literals
+fn fold_arithmetic(
+    loc: &pt::Loc,
+    op: Arithmetic,
+    left: Ratio<BigInt>,
+    right: Ratio<BigInt>,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    if matches!(op, Arithmetic::Divide | Arithmetic::Modulo) && right.is_zero() {
+        diagnostics.push(Diagnostic::type_error(*loc, "divide by zero"));
+        return Err(());
+    }
+
+    let value = match op {
+        Arithmetic::Add => left + right,
+        Arithmetic::Subtract => left - right,
+        Arithmetic::Multiply => left * right,
+        Arithmetic::Divide => left / right,
+        Arithmetic::Modulo |
+        Arithmetic::BitwiseOr |
+        Arithmetic::BitwiseAnd |
+        Arithmetic::BitwiseXor => {
+            if !left.is_integer() || !right.is_integer() {
+                diagnostics.push(Diagnostic::type_error(
+                    *loc,
+                    format!("operator '{}' is not supported on rational numbers", op.as_str()),
+                ));
+                return Err(());
+            }
+            let (left, right) = (left.to_integer(), right.to_integer());
+            Ratio::from_integer(match op {
+                // Truncates toward zero, as in Solidity
+                Arithmetic::Modulo => left % right,
+                Arithmetic::BitwiseOr => left | right,
+                Arithmetic::BitwiseAnd => left & right,
+                _ => left ^ right,
+            })
+        }
+    };
+
+    if value.is_integer() {
+        bigint_to_expression(loc, &value.to_integer(), ctx, diagnostics, resolve_to, None)
+    } else {
+        Ok(Expression::RationalNumberLiteral { loc: *loc, ty: Type::Rational, value })
+    }
+}
+
+/// Resolve `~expr`, on integers and `bytesN`. The complement of a literal is
+/// folded, e.g. `~0` is `-1`.
+pub(crate) fn bitwise_not(
+    loc: &pt::Loc,
+    e: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let expr = expression(e, context, ctx, symtable, diagnostics, resolve_to)?;
+
+    resolve_bitwise_not(loc, expr, ctx, diagnostics, resolve_to)
+}
+
+/// Resolve the complement of a resolved expression
+pub(crate) fn resolve_bitwise_not(
+    loc: &pt::Loc,
+    expr: Expression,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    if let Expression::NumberLiteral { value, .. } = &expr {
+        return bigint_to_expression(loc, &!value, ctx, diagnostics, resolve_to, None);
+    }
+
+    let ty = expr.ty().deref_any().clone();
+    if !matches!(ty, Type::Uint(_) | Type::Int(_) | Type::Bytes(_)) {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!("operator '~' is not supported on type '{}'", ty.to_string(ctx)),
+        ));
+        return Err(());
+    }
+
+    let expr = expr.cast(loc, &ty, true, ctx, diagnostics)?;
+    Ok(Expression::BitwiseNot { loc: *loc, ty, expr: Box::new(expr) })
+}
+
+/// Resolve `base ** exp`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn power(
+    loc: &pt::Loc,
+    b: &pt::Expression,
+    e: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let base = expression(b, context, ctx, symtable, diagnostics, resolve_to)?;
+    let exp = expression(e, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+
+    resolve_power(loc, base, exp, context.unchecked, ctx, diagnostics, resolve_to)
+}
+
+/// Resolve the power of a resolved base and exponent
+pub(crate) fn resolve_power(
+    loc: &pt::Loc,
+    base: Expression,
+    exp: Expression,
+    unchecked: bool,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let base_ty = base.ty().deref_any().clone();
+
+    if !matches!(base_ty, Type::Uint(_) | Type::Int(_)) {
+        diagnostics.push(Diagnostic::type_error(
+            base.loc(),
+            format!("operator '**' is not supported on type '{}'", base_ty.to_string(ctx)),
+        ));
+        return Err(());
+    }
+
+    let exp_ty = unsigned_operand(&exp, "exponent", ctx, diagnostics)?;
+
+    match (&base, &exp) {
+        (
+            Expression::NumberLiteral { value: b, .. },
+            Expression::NumberLiteral { value: e, .. },
+        ) => return fold_power(loc, b, e, ctx, diagnostics, resolve_to),
+        (Expression::NumberLiteral { value, .. }, _) => {
+            let ty = word_type(value);
+            let base = base.cast(loc, &ty, true, ctx, diagnostics)?;
+            let exp = exp.cast(loc, &exp_ty, true, ctx, diagnostics)?;
+            return Ok(Expression::Power {
+                loc: *loc,
+                ty,
+                unchecked,
+                base: Box::new(base),
+                exp: Box::new(exp),
+            });
+        }
+        _ => (),
+    }
+
+    let base = base.cast(loc, &base_ty, true, ctx, diagnostics)?;
+    let exp = exp.cast(loc, &exp_ty, true, ctx, diagnostics)?;
+
+    Ok(Expression::Power {
+        loc: *loc,
+        ty: base_ty,
+        unchecked,
+        base: Box::new(base),
+        exp: Box::new(exp),
+    })
+}
+
+/// Resolve `left << right` or `left >> right`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn shift(
+    loc: &pt::Loc,
+    left_shift: bool,
+    l: &pt::Expression,
+    r: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let left = expression(l, context, ctx, symtable, diagnostics, resolve_to)?;
+    let right = expression(r, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+
+    resolve_shift(loc, left_shift, left, right, ctx, diagnostics, resolve_to)
+}
+
+/// Resolve the shift of a resolved value by a resolved amount. The result has
+/// the type of the value, and integers shifted by their width or more are zero,
+/// or -1 for negative values shifted right.
+pub(crate) fn resolve_shift(
+    loc: &pt::Loc,
+    left_shift: bool,
+    left: Expression,
+    right: Expression,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let left_ty = left.ty().deref_any().clone();
+
+    if !matches!(left_ty, Type::Uint(_) | Type::Int(_) | Type::Bytes(_)) {
+        diagnostics.push(Diagnostic::type_error(
+            left.loc(),
+            format!(
+                "operator '{}' is not supported on type '{}'",
+                if left_shift { "<<" } else { ">>" },
+                left_ty.to_string(ctx)
+            ),
+        ));
+        return Err(());
+    }
+
+    let right_ty = unsigned_operand(&right, "shift amount", ctx, diagnostics)?;
+
+    let ty = match (&left, &right) {
+        (
+            Expression::NumberLiteral { value: l, .. },
+            Expression::NumberLiteral { value: r, .. },
+        ) => return fold_shift(loc, left_shift, l, r, ctx, diagnostics, resolve_to),
+        (Expression::NumberLiteral { value, .. }, _) => word_type(value),
+        _ => left_ty,
+    };
+
+    let left = Box::new(left.cast(loc, &ty, true, ctx, diagnostics)?);
+    let right = Box::new(right.cast(loc, &right_ty, true, ctx, diagnostics)?);
+
+    Ok(if left_shift {
+        Expression::ShiftLeft { loc: *loc, ty, left, right }
+    } else {
+        let sign = matches!(ty, Type::Int(_));
+        Expression::ShiftRight { loc: *loc, ty, left, right, sign }
+    })
+}
+
+/// Resolve `-expr`. The minus is not part of a number literal, so that
+/// `a -1` is a subtraction; the negation of a literal is folded instead.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn negate(
+    loc: &pt::Loc,
+    e: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let expr = expression(e, context, ctx, symtable, diagnostics, resolve_to)?;
+
+    resolve_negate(loc, expr, context.unchecked, ctx, diagnostics, resolve_to)
+}
+
+/// Resolve the negation of a resolved expression. Only signed integers can be
+/// negated, but a literal is folded whatever its type, e.g. `-(2**255)` is the
+/// smallest `int256`.
+pub(crate) fn resolve_negate(
+    loc: &pt::Loc,
+    expr: Expression,
+    unchecked: bool,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    if let Expression::NumberLiteral { value, .. } = &expr {
+        return bigint_to_expression(loc, &-value, ctx, diagnostics, resolve_to, None);
+    }
+
+    let ty = expr.ty().deref_any().clone();
+    match ty {
+        Type::Int(_) => {
+            let expr = expr.cast(loc, &ty, true, ctx, diagnostics)?;
+            Ok(Expression::Negate { loc: *loc, ty, unchecked, expr: Box::new(expr) })
+        }
+        Type::Uint(_) => {
+            diagnostics.push(Diagnostic::type_error(
+                *loc,
+                format!(
+                    "unary negation is only allowed for signed integers, not '{}'",
+                    ty.to_string(ctx)
+                ),
+            ));
+            Err(())
+        }
+        _ => {
+            diagnostics.push(Diagnostic::type_error(
+                *loc,
+                format!("operator '-' is not supported on type '{}'", ty.to_string(ctx)),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// The type of an operand which must be an unsigned integer, like an exponent
+/// or shift amount. A literal operand is unsigned whatever type it was
+/// resolved to, unless it is negative.
+fn unsigned_operand(
+    expr: &Expression,
+    name: &str,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Type, ()> {
+    match (expr, expr.ty().deref_any()) {
+        (Expression::NumberLiteral { value, .. }, _) if value.is_negative() => {
+            diagnostics
+                .push(Diagnostic::type_error(expr.loc(), format!("{name} cannot be negative")));
+            Err(())
+        }
+        (Expression::NumberLiteral { value, .. }, _) => {
+            Ok(Type::Uint(value.bits().max(1).next_multiple_of(8) as u16))
+        }
+        (_, ty @ Type::Uint(_)) => Ok(ty.clone()),
+        (_, ty) => {
+            diagnostics.push(Diagnostic::type_error(
+                expr.loc(),
+                format!("{name} of type '{}' must be an unsigned integer", ty.to_string(ctx)),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// The type of a literal operated on with a value which is not a literal
+fn word_type(value: &BigInt) -> Type {
+    if value.sign() == Sign::Minus {
+        Type::Int(256)
+    } else {
+        Type::Uint(256)
+    }
+}
+
+/// The exact shift of two literals. Shifting right rounds toward negative
+/// infinity, like the arithmetic shift.
+fn fold_shift(
+    loc: &pt::Loc,
+    left_shift: bool,
+    value: &BigInt,
+    amount: &BigInt,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let value = match amount.to_u32() {
+        _ if value.is_zero() => value.clone(),
+        Some(amount) if !left_shift => value >> amount,
+        Some(amount) if amount <= MAX_EXPONENT => value << amount,
+        // Any value shifted right by so much is all its sign
+        None if !left_shift => {
+            if value.is_negative() {
+                BigInt::from(-1)
+            } else {
+                BigInt::zero()
+            }
+        }
+        _ => {
+            diagnostics.push(Diagnostic::type_error(
+                *loc,
+                format!("shift {value} << {amount} is too large to be evaluated"),
+            ));
+            return Err(());
+        }
+    };
+
+    bigint_to_expression(loc, &value, ctx, diagnostics, resolve_to, None)
+}
+
+/// The exact power of two literals
+fn fold_power(
+    loc: &pt::Loc,
+    base: &BigInt,
+    exp: &BigInt,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let trivial = base.abs() <= BigInt::from(1);
+    let exp = match exp.to_u32() {
+        Some(exp) if exp <= MAX_EXPONENT || trivial => exp,
+        // Only the parity of the exponent matters for -1, 0 and 1
+        _ if trivial => 2 + (exp % 2u8).to_u32().unwrap(),
+        _ => {
+            diagnostics.push(Diagnostic::type_error(
+                *loc,
+                format!("power {base} ** {exp} is too large to be evaluated"),
+            ));
+            return Err(());
+        }
+    };
+
+    bigint_to_expression(loc, &base.pow(exp), ctx, diagnostics, resolve_to, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+
+    fn number(value: i64) -> Expression {
+        Expression::NumberLiteral { loc: pt::Loc::Builtin, ty: Type::Uint(8), value: value.into() }
+    }
+
+    fn variable(ty: Type) -> Expression {
+        Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 }
+    }
+
+    fn resolve(base: Expression, exp: Expression) -> Result<Expression, String> {
+        let ctx = Context::new();
+        let mut diagnostics = Diagnostics::default();
+        resolve_power(
+            &pt::Loc::Builtin,
+            base,
+            exp,
+            false,
+            &ctx,
+            &mut diagnostics,
+            ResolveTo::Unknown,
+        )
+        .map_err(|_| diagnostics.first_error())
+    }
+
+    fn literal(expr: Result<Expression, String>) -> (Type, BigInt) {
+        match expr {
+            Ok(Expression::NumberLiteral { ty, value, .. }) => (ty, value),
+            other => panic!("not a number literal: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_fold_powers() {
+        assert_eq!(literal(resolve(number(2), number(10))), (Type::Uint(16), 1024.into()));
+        assert_eq!(literal(resolve(number(-3), number(3))), (Type::Int(8), (-27).into()));
+        assert_eq!(literal(resolve(number(10), number(0))), (Type::Uint(8), 1.into()));
+        assert_eq!(literal(resolve(number(2), number(255))).1, BigInt::from(1) << 255);
+
+        // A power no type holds is an error
+        let too_large = resolve(number(2), number(256)).unwrap_err();
+        assert_eq!(too_large, format!("{} is too large", BigInt::from(1) << 256));
+        assert_eq!(
+            resolve(number(10), number(1000)).unwrap_err(),
+            "power 10 ** 1000 is too large to be evaluated"
+        );
+        let huge = Expression::NumberLiteral {
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(256),
+            value: BigInt::from(u64::MAX) * 2,
+        };
+        assert_eq!(literal(resolve(number(-1), huge.clone())).1, 1.into());
+        assert_eq!(literal(resolve(number(0), huge)).1, 0.into());
+    }
+
+    #[test]
+    fn test_power_types() {
+        // The result has the type of the base
+        let expr = resolve(variable(Type::Int(64)), variable(Type::Uint(256))).unwrap();
+        assert!(matches!(expr, Expression::Power { ty: Type::Int(64), unchecked: false, .. }));
+
+        // A literal base is a full word
+        let expr = resolve(number(2), variable(Type::Uint(8))).unwrap();
+        assert!(matches!(expr, Expression::Power { ty: Type::Uint(256), .. }));
+        let expr = resolve(number(-2), variable(Type::Uint(8))).unwrap();
+        assert!(matches!(expr, Expression::Power { ty: Type::Int(256), .. }));
+
+        assert_eq!(
+            resolve(variable(Type::Uint(8)), variable(Type::Int(8))).unwrap_err(),
+            "exponent of type 'int8' must be an unsigned integer"
+        );
+        assert_eq!(
+            resolve(variable(Type::Uint(8)), number(-1)).unwrap_err(),
+            "exponent cannot be negative"
+        );
+        assert_eq!(
+            resolve(variable(Type::Bool), number(2)).unwrap_err(),
+            "operator '**' is not supported on type 'bool'"
+        );
+    }
+
+    #[test]
+    fn test_shifts() {
+        let ctx = Context::new();
+        let shift = |left_shift, left, right| {
+            let mut diagnostics = Diagnostics::default();
+            let loc = pt::Loc::Builtin;
+            resolve_shift(&loc, left_shift, left, right, &ctx, &mut diagnostics, ResolveTo::Unknown)
+                .map_err(|_| diagnostics.first_error())
+        };
+
+        assert_eq!(literal(shift(true, number(1), number(8))), (Type::Uint(16), 256.into()));
+        assert_eq!(literal(shift(false, number(-7), number(1))), (Type::Int(8), (-4).into()));
+        let huge = Expression::NumberLiteral {
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(256),
+            value: BigInt::from(u64::MAX) * 2,
+        };
+        assert_eq!(literal(shift(false, number(-7), huge.clone())).1, (-1).into());
+        assert_eq!(literal(shift(true, number(0), huge.clone())).1, 0.into());
+        assert_eq!(
+            shift(true, number(1), huge).unwrap_err(),
+            "shift 1 << 36893488147419103230 is too large to be evaluated"
+        );
+
+        // The result has the type of the value, whatever the amount
+        let expr = shift(true, variable(Type::Uint(8)), variable(Type::Uint(256))).unwrap();
+        assert!(matches!(expr, Expression::ShiftLeft { ty: Type::Uint(8), .. }));
+        let expr = shift(false, variable(Type::Int(32)), number(40)).unwrap();
+        assert!(matches!(expr, Expression::ShiftRight { ty: Type::Int(32), sign: true, .. }));
+        let expr = shift(false, variable(Type::Bytes(4)), number(8)).unwrap();
+        assert!(matches!(expr, Expression::ShiftRight { ty: Type::Bytes(4), sign: false, .. }));
+        let expr = shift(true, number(1), variable(Type::Uint(8))).unwrap();
+        assert!(matches!(expr, Expression::ShiftLeft { ty: Type::Uint(256), .. }));
+
+        assert_eq!(
+            shift(true, variable(Type::Uint(8)), number(-1)).unwrap_err(),
+            "shift amount cannot be negative"
+        );
+        assert_eq!(
+            shift(true, variable(Type::Uint(8)), variable(Type::Int(8))).unwrap_err(),
+            "shift amount of type 'int8' must be an unsigned integer"
+        );
+        assert_eq!(
+            shift(true, variable(Type::Bool), number(1)).unwrap_err(),
+            "operator '<<' is not supported on type 'bool'"
+        );
+    }
+
+    #[test]
+    fn test_negate() {
+        let ctx = Context::new();
+        let negate = |expr, resolve_to| {
+            let mut diagnostics = Diagnostics::default();
+            resolve_negate(&pt::Loc::Builtin, expr, false, &ctx, &mut diagnostics, resolve_to)
+                .map_err(|_| diagnostics.first_error())
+        };
+
+        assert_eq!(literal(negate(number(128), ResolveTo::Unknown)), (Type::Int(8), (-128).into()));
+        assert_eq!(literal(negate(number(-5), ResolveTo::Unknown)), (Type::Uint(8), 5.into()));
+
+        // `-(2**255)` is the smallest int256, though 2**255 is not an int256
+        let min = BigInt::from(1) << 255;
+        let power = literal(resolve(number(2), number(255)));
+        assert_eq!(power.1, min);
+        let power =
+            Expression::NumberLiteral { loc: pt::Loc::Builtin, ty: power.0, value: power.1 };
+        let int256 = Type::Int(256);
+        let expr = negate(power.clone(), ResolveTo::Unknown).unwrap();
+        assert_eq!(literal(Ok(expr.clone())), (int256.clone(), -min.clone()));
+        let mut diagnostics = Diagnostics::default();
+        expr.check_constant_overflow(&mut diagnostics);
+        assert!(diagnostics.is_empty());
+        let expr = negate(power, ResolveTo::Type(&int256)).unwrap();
+        assert_eq!(literal(Ok(expr)), (int256.clone(), -min));
+
+        let expr = negate(variable(Type::Int(64)), ResolveTo::Unknown).unwrap();
+        assert!(matches!(expr, Expression::Negate { ty: Type::Int(64), unchecked: false, .. }));
+        assert_eq!(
+            negate(variable(Type::Uint(64)), ResolveTo::Unknown).unwrap_err(),
+            "unary negation is only allowed for signed integers, not 'uint64'"
+        );
+        assert_eq!(
+            negate(variable(Type::Bool), ResolveTo::Unknown).unwrap_err(),
+            "operator '-' is not supported on type 'bool'"
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let ctx = Context::new();
+        let arithmetic = |op, left, right| {
+            let mut diagnostics = Diagnostics::default();
+            let loc = pt::Loc::Builtin;
+            resolve_arithmetic(
+                &loc,
+                op,
+                left,
+                right,
+                false,
+                &ctx,
+                &mut diagnostics,
+                ResolveTo::Unknown,
+            )
+            .map_err(|_| diagnostics.first_error())
+        };
+
+        // Literals are folded, and a fraction is a rational literal
+        assert_eq!(
+            literal(arithmetic(Arithmetic::Add, number(200), number(100))),
+            (Type::Uint(16), 300.into())
+        );
+        assert_eq!(
+            literal(arithmetic(Arithmetic::Subtract, number(1), number(2))),
+            (Type::Int(8), (-1).into())
+        );
+        assert_eq!(literal(arithmetic(Arithmetic::BitwiseXor, number(6), number(3))).1, 5.into());
+        assert!(matches!(
+            arithmetic(Arithmetic::Divide, number(1), number(2)),
+            Ok(Expression::RationalNumberLiteral { .. })
+        ));
+        assert_eq!(
+            arithmetic(Arithmetic::Modulo, number(1), number(0)).unwrap_err(),
+            "divide by zero"
+        );
+
+        // The literal takes the type of the other operand, and the narrower one widens
+        let expr = arithmetic(Arithmetic::Multiply, variable(Type::Uint(64)), number(3)).unwrap();
+        assert!(matches!(expr, Expression::Multiply { ty: Type::Uint(64), unchecked: false, .. }));
+        let expr =
+            arithmetic(Arithmetic::Add, variable(Type::Uint(8)), variable(Type::Int(16))).unwrap();
+        assert!(matches!(expr, Expression::Add { ty: Type::Int(16), .. }));
+        let expr =
+            arithmetic(Arithmetic::BitwiseAnd, variable(Type::Bytes(4)), variable(Type::Bytes(4)))
+                .unwrap();
+        assert!(matches!(expr, Expression::BitwiseAnd { ty: Type::Bytes(4), .. }));
+
+        assert_eq!(
+            arithmetic(Arithmetic::Add, variable(Type::Uint(8)), variable(Type::Int(8)))
+                .unwrap_err(),
+            "operator '+' is not supported between types 'uint8' and 'int8'"
+        );
+        assert_eq!(
+            arithmetic(Arithmetic::Add, variable(Type::Bytes(4)), variable(Type::Bytes(4)))
+                .unwrap_err(),
+            "operator '+' is not supported on type 'bytes4'"
+        );
+    }
+
+    #[test]
+    fn test_bitwise_not() {
+        let ctx = Context::new();
+        let bitwise_not = |expr| {
+            let mut diagnostics = Diagnostics::default();
+            resolve_bitwise_not(&pt::Loc::Builtin, expr, &ctx, &mut diagnostics, ResolveTo::Unknown)
+                .map_err(|_| diagnostics.first_error())
+        };
+
+        assert_eq!(literal(bitwise_not(number(0))), (Type::Int(8), (-1).into()));
+        let expr = bitwise_not(variable(Type::Uint(32))).unwrap();
+        assert!(matches!(expr, Expression::BitwiseNot { ty: Type::Uint(32), .. }));
+        assert_eq!(
+            bitwise_not(variable(Type::Bool)).unwrap_err(),
+            "operator '~' is not supported on type 'bool'"
+        );
+    }
+}
@@ -0,0 +1,416 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Assignments, simple and compound, and increments and decrements. The left
+//! hand side is resolved once, as an [`lvalue`], and a compound assignment like
+//! `balances[to] += amount` loads it, applies the operator and stores the
+//! result back.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Expression, RetrieveType, Type},
+        context::Context,
+        expression::{
+            arithmetic::resolve_shift,
+            lvalue::{lvalue, Mutation},
+            resolve_expression::expression,
+            ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// The operator of a compound assignment
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AssignOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    BitwiseOr,
+    BitwiseAnd,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl AssignOp {
+    /// The operator of the parsed compound assignment, with its operands
+    pub fn from_pt(
+        expr: &pt::Expression,
+    ) -> Option<(pt::Loc, AssignOp, &pt::Expression, &pt::Expression)> {
+        let (loc, op, left, right) = match expr {
+            pt::Expression::AssignAdd(loc, l, r) => (loc, AssignOp::Add, l, r),
+            pt::Expression::AssignSubtract(loc, l, r) => (loc, AssignOp::Subtract, l, r),
+            pt::Expression::AssignMultiply(loc, l, r) => (loc, AssignOp::Multiply, l, r),
+            pt::Expression::AssignDivide(loc, l, r) => (loc, AssignOp::Divide, l, r),
+            pt::Expression::AssignModulo(loc, l, r) => (loc, AssignOp::Modulo, l, r),
+            pt::Expression::AssignOr(loc, l, r) => (loc, AssignOp::BitwiseOr, l, r),
+            pt::Expression::AssignAnd(loc, l, r) => (loc, AssignOp::BitwiseAnd, l, r),
+            pt::Expression::AssignXor(loc, l, r) => (loc, AssignOp::BitwiseXor, l, r),
+            pt::Expression::AssignShiftLeft(loc, l, r) => (loc, AssignOp::ShiftLeft, l, r),
+            pt::Expression::AssignShiftRight(loc, l, r) => (loc, AssignOp::ShiftRight, l, r),
+            _ => return None,
+        };
+
+        Some((*loc, op, left, right))
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            AssignOp::Add => "+=",
+            AssignOp::Subtract => "-=",
+            AssignOp::Multiply => "*=",
+            AssignOp::Divide => "/=",
+            AssignOp::Modulo => "%=",
+            AssignOp::BitwiseOr => "|=",
+            AssignOp::BitwiseAnd => "&=",
+            AssignOp::BitwiseXor => "^=",
+            AssignOp::ShiftLeft => "<<=",
+            AssignOp::ShiftRight => ">>=",
+        }
+    }
+
+    /// Whether the operator applies to `bytesN` as well as integers
+    fn is_bitwise(self) -> bool {
+        matches!(
+            self,
+            AssignOp::BitwiseOr |
+                AssignOp::BitwiseAnd |
+                AssignOp::BitwiseXor |
+                AssignOp::ShiftLeft |
+                AssignOp::ShiftRight
+        )
+    }
+}
+
+/// An increment or decrement, before or after the value is read
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum IncrDecr {
+    PreIncrement,
+    PreDecrement,
+    PostIncrement,
+    PostDecrement,
+}
+
+impl IncrDecr {
+    /// The operator of the parsed increment or decrement, with its operand
+    pub fn from_pt(expr: &pt::Expression) -> Option<(pt::Loc, IncrDecr, &pt::Expression)> {
+        let (loc, op, expr) = match expr {
+            pt::Expression::PreIncrement(loc, e) => (loc, IncrDecr::PreIncrement, e),
+            pt::Expression::PreDecrement(loc, e) => (loc, IncrDecr::PreDecrement, e),
+            pt::Expression::PostIncrement(loc, e) => (loc, IncrDecr::PostIncrement, e),
+            pt::Expression::PostDecrement(loc, e) => (loc, IncrDecr::PostDecrement, e),
+            _ => return None,
+        };
+
+        Some((*loc, op, expr))
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            IncrDecr::PreIncrement | IncrDecr::PostIncrement => "++",
+            IncrDecr::PreDecrement | IncrDecr::PostDecrement => "--",
+        }
+    }
+}
+
+/// Resolve `left = right`
+pub(crate) fn assign_single(
+    loc: &pt::Loc,
+    left: &pt::Expression,
+    right: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let var = lvalue(left, Mutation::Assign, context, ctx, symtable, diagnostics)?;
+    let ty = var.ty().deref_any().clone();
+
+    let right = expression(right, context, ctx, symtable, diagnostics, ResolveTo::Type(&ty))?;
+    let right = right.cast(&right.loc(), &ty, true, ctx, diagnostics)?;
+    right.check_constant_overflow(diagnostics);
+
+    Ok(Expression::Assign { loc: *loc, ty, left: Box::new(var), right: Box::new(right) })
+}
+
+/// Resolve a compound assignment like `left += right`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn assign_expr(
+    loc: &pt::Loc,
+    op: AssignOp,
+    left: &pt::Expression,
+    right: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let var = lvalue(left, Mutation::Assign, context, ctx, symtable, diagnostics)?;
+    let ty = var.ty().deref_any().clone();
+
+    // The amount of a shift has a type of its own
+    let resolve_to = match op {
+        AssignOp::ShiftLeft | AssignOp::ShiftRight => ResolveTo::Unknown,
+        _ => ResolveTo::Type(&ty),
+    };
+    let right = expression(right, context, ctx, symtable, diagnostics, resolve_to)?;
+
+    compound(loc, op, var, right, context.unchecked, ctx, diagnostics)
+}
+
+/// Assign the result of the operator on the lvalue and the right hand side
+/// to the lvalue
+pub(crate) fn compound(
+    loc: &pt::Loc,
+    op: AssignOp,
+    var: Expression,
+    right: Expression,
+    unchecked: bool,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let ty = var.ty().deref_any().clone();
+
+    let valid = match ty {
+        Type::Uint(_) | Type::Int(_) => true,
+        Type::Bytes(_) => op.is_bitwise(),
+        _ => false,
+    };
+    if !valid {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!("operator '{}' is not supported on type '{}'", op.as_str(), ty.to_string(ctx)),
+        ));
+        return Err(());
+    }
+
+    let current = load(&var, &ty);
+    let value = match op {
+        AssignOp::ShiftLeft | AssignOp::ShiftRight => {
+            let left_shift = op == AssignOp::ShiftLeft;
+            resolve_shift(loc, left_shift, current, right, ctx, diagnostics, ResolveTo::Type(&ty))?
+        }
+        _ => {
+            let left = Box::new(current);
+            let right = right.cast(&right.loc(), &ty, true, ctx, diagnostics)?;
+            right.check_constant_overflow(diagnostics);
+            let right = Box::new(right);
+            let (loc, ty) = (*loc, ty.clone());
+
+            match op {
+                AssignOp::Add => Expression::Add { loc, ty, unchecked, left, right },
+                AssignOp::Subtract => Expression::Subtract { loc, ty, unchecked, left, right },
+                AssignOp::Multiply => Expression::Multiply { loc, ty, unchecked, left, right },
+                AssignOp::Divide => Expression::Divide { loc, ty, left, right },
+                AssignOp::Modulo => Expression::Modulo { loc, ty, left, right },
+                AssignOp::BitwiseOr => Expression::BitwiseOr { loc, ty, left, right },
+                AssignOp::BitwiseAnd => Expression::BitwiseAnd { loc, ty, left, right },
+                AssignOp::BitwiseXor => Expression::BitwiseXor { loc, ty, left, right },
+                AssignOp::ShiftLeft | AssignOp::ShiftRight => unreachable!(),
+            }
+        }
+    };
+
+    Ok(Expression::Assign { loc: *loc, ty, left: Box::new(var), right: Box::new(value) })
+}
+
+/// Resolve `++x`, `x++`, `--x` or `x--`
+pub(crate) fn incr_decr(
+    loc: &pt::Loc,
+    op: IncrDecr,
+    expr: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let var = lvalue(expr, Mutation::IncrDecr, context, ctx, symtable, diagnostics)?;
+
+    resolve_incr_decr(loc, op, var, context.unchecked, ctx, diagnostics)
+}
+
+/// Increment or decrement the lvalue by one. The expression is the value
+/// after the change for the prefix operators, and before it for the postfix
+/// ones; either way the lvalue is loaded and stored once.
+pub(crate) fn resolve_incr_decr(
+    loc: &pt::Loc,
+    op: IncrDecr,
+    var: Expression,
+    unchecked: bool,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let ty = var.ty().deref_any().clone();
+
+    if !matches!(ty, Type::Uint(_) | Type::Int(_)) {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!("operator '{}' is not supported on type '{}'", op.as_str(), ty.to_string(ctx)),
+        ));
+        return Err(());
+    }
+
+    let (loc, expr) = (*loc, Box::new(var));
+    Ok(match op {
+        IncrDecr::PreIncrement => Expression::PreIncrement { loc, ty, unchecked, expr },
+        IncrDecr::PreDecrement => Expression::PreDecrement { loc, ty, unchecked, expr },
+        IncrDecr::PostIncrement => Expression::PostIncrement { loc, ty, unchecked, expr },
+        IncrDecr::PostDecrement => Expression::PostDecrement { loc, ty, unchecked, expr },
+    })
+}
+
+/// The current value of the lvalue
+pub(crate) fn load(var: &Expression, ty: &Type) -> Expression {
+    let loc = var.loc();
+    match var.ty() {
+        Type::StorageRef(..) => {
+            Expression::StorageLoad { loc, ty: ty.clone(), expr: Box::new(var.clone()) }
+        }
+        Type::Ref(_) => Expression::Load { loc, ty: ty.clone(), expr: Box::new(var.clone()) },
+        _ => var.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+
+    fn number(value: i64) -> Expression {
+        Expression::NumberLiteral { loc: pt::Loc::Builtin, ty: Type::Uint(8), value: value.into() }
+    }
+
+    #[test]
+    fn test_compound_storage() {
+        let ctx = Context::new();
+        let mut diagnostics = Diagnostics::default();
+
+        // `balances[to] += 1`, on a mapping element in storage
+        let element = Expression::Subscript {
+            loc: pt::Loc::Builtin,
+            ty: Type::StorageRef(false, Box::new(Type::Uint(256))),
+            array_ty: Type::Uint(256),
+            array: Box::new(number(0)),
+            index: Box::new(number(0)),
+        };
+        let expr = compound(
+            &pt::Loc::Builtin,
+            AssignOp::Add,
+            element.clone(),
+            number(1),
+            false,
+            &ctx,
+            &mut diagnostics,
+        )
+        .unwrap();
+
+        let Expression::Assign { ty, left, right, .. } = expr else { panic!("{expr:?}") };
+        assert_eq!(ty, Type::Uint(256));
+        assert!(matches!(*left, Expression::Subscript { .. }));
+        let Expression::Add { unchecked: false, left, right, .. } = *right else { panic!() };
+        assert!(matches!(*left, Expression::StorageLoad { ty: Type::Uint(256), .. }));
+        assert!(matches!(*right, Expression::NumberLiteral { ty: Type::Uint(256), .. }));
+
+        // Shifts keep the type of the lvalue
+        let expr = compound(
+            &pt::Loc::Builtin,
+            AssignOp::ShiftRight,
+            element.clone(),
+            number(3),
+            true,
+            &ctx,
+            &mut diagnostics,
+        )
+        .unwrap();
+        let Expression::Assign { right, .. } = expr else { panic!() };
+        assert!(matches!(*right, Expression::ShiftRight { ty: Type::Uint(256), sign: false, .. }));
+
+        let flag = Expression::Variable { loc: pt::Loc::Builtin, ty: Type::Bool, var_no: 0 };
+        assert!(compound(
+            &pt::Loc::Builtin,
+            AssignOp::BitwiseOr,
+            flag,
+            number(1),
+            false,
+            &ctx,
+            &mut diagnostics,
+        )
+        .is_err());
+        assert_eq!(diagnostics.first_error(), "operator '|=' is not supported on type 'bool'");
+    }
+
+    #[test]
+    fn test_incr_decr() {
+        let ctx = Context::new();
+        let mut diagnostics = Diagnostics::default();
+
+        let counter = Expression::StorageVariable {
+            loc: pt::Loc::Builtin,
+            ty: Type::StorageRef(false, Box::new(Type::Int(64))),
+            contract_no: 0,
+            var_no: 0,
+        };
+        let expr = resolve_incr_decr(
+            &pt::Loc::Builtin,
+            IncrDecr::PostDecrement,
+            counter,
+            false,
+            &ctx,
+            &mut diagnostics,
+        )
+        .unwrap();
+        let Expression::PostDecrement { ty, unchecked: false, expr, .. } = expr else { panic!() };
+        assert_eq!(ty, Type::Int(64));
+        assert!(matches!(*expr, Expression::StorageVariable { .. }));
+
+        let flag = Expression::Variable { loc: pt::Loc::Builtin, ty: Type::Bool, var_no: 0 };
+        let expr = resolve_incr_decr(
+            &pt::Loc::Builtin,
+            IncrDecr::PreIncrement,
+            flag,
+            true,
+            &ctx,
+            &mut diagnostics,
+        );
+        assert!(expr.is_err());
+        assert_eq!(diagnostics.first_error(), "operator '++' is not supported on type 'bool'");
+    }
+
+    /// The errors of checking the contract with the function in the source
+    fn errors(src: &str) -> Vec<String> {
+        let contents = format!("contract C {{ {src} }}");
+        let input = crate::Input::Source { name: "test.sol".into(), contents };
+        let diagnostics = crate::check(&input, &crate::config::Config::default());
+        diagnostics.errors().into_iter().map(|error| error.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_assign_overflow() {
+        let src = "function f() public pure { uint8 x; x = 255; x += 1; }";
+        assert!(errors(src).is_empty());
+
+        let src = "function f() public pure { uint8 x; x = 256; }";
+        assert_eq!(errors(src), ["value 256 does not fit into type uint8."]);
+        let src = "function f() public pure { int8 x; x = -2**7 - 1; }";
+        assert_eq!(errors(src), ["value -129 does not fit into type int8."]);
+        let src = "function f() public pure { uint8 x; x += 256; }";
+        assert_eq!(errors(src), ["value 256 does not fit into type uint8."]);
+    }
+}
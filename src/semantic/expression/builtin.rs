@@ -0,0 +1,500 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The builtin variables and functions of Solidity, like `msg.sender` or
+//! `keccak256(data)`. A declaration of the same name shadows a builtin
+//! function, but the namespaces `msg`, `block`, `tx` and `abi` are reserved.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Builtin, Expression, RetrieveType, Type},
+        context::{Context, ResolveTypeContext},
+        expression::{resolve_expression::expression, ExprContext, ResolveTo},
+        symtable::Symtable,
+    },
+};
+
+/// The signature of a builtin variable or function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prototype {
+    pub builtin: Builtin,
+    /// The namespace of the builtin, like `msg` or `abi`
+    pub namespace: Option<&'static str>,
+    pub name: &'static str,
+    pub params: &'static [Type],
+    pub ret: &'static [Type],
+    /// Whether any number of arguments of any type follow the parameters
+    pub variadic: bool,
+    pub doc: &'static str,
+}
+
+const fn prototype(
+    builtin: Builtin,
+    namespace: Option<&'static str>,
+    name: &'static str,
+    params: &'static [Type],
+    ret: &'static [Type],
+    doc: &'static str,
+) -> Prototype {
+    Prototype { builtin, namespace, name, params, ret, variadic: false, doc }
+}
+
+impl Prototype {
+    const fn variadic(self) -> Self {
+        Self { variadic: true, ..self }
+    }
+}
+
+const MSG: Option<&str> = Some("msg");
+const TX: Option<&str> = Some("tx");
+const BLOCK: Option<&str> = Some("block");
+const ABI: Option<&str> = Some("abi");
+
+/// The builtin variables
+pub const BUILTIN_VARIABLES: &[Prototype] = &[
+    prototype(
+        Builtin::Sender,
+        MSG,
+        "sender",
+        &[],
+        &[Type::Address(false)],
+        "The sender of the message",
+    ),
+    prototype(Builtin::Value, MSG, "value", &[], &[Type::Value], "The value sent with the message"),
+    prototype(
+        Builtin::Signature,
+        MSG,
+        "sig",
+        &[],
+        &[Type::Bytes(4)],
+        "The selector of the function called",
+    ),
+    prototype(
+        Builtin::Calldata,
+        MSG,
+        "data",
+        &[],
+        &[Type::DynamicBytes],
+        "The data of the message",
+    ),
+    prototype(
+        Builtin::Origin,
+        TX,
+        "origin",
+        &[],
+        &[Type::Address(false)],
+        "The sender of the transaction",
+    ),
+    prototype(
+        Builtin::Gasprice,
+        TX,
+        "gasprice",
+        &[],
+        &[Type::Value],
+        "The gas price of the transaction",
+    ),
+    prototype(
+        Builtin::BlockNumber,
+        BLOCK,
+        "number",
+        &[],
+        &[Type::Uint(256)],
+        "The number of the block",
+    ),
+    prototype(
+        Builtin::Timestamp,
+        BLOCK,
+        "timestamp",
+        &[],
+        &[Type::Uint(256)],
+        "The timestamp of the block, in seconds since the epoch",
+    ),
+    prototype(Builtin::BaseFee, BLOCK, "basefee", &[], &[Type::Value], "The base fee of the block"),
+    prototype(
+        Builtin::PrevRandao,
+        BLOCK,
+        "prevrandao",
+        &[],
+        &[Type::Uint(256)],
+        "The random number of the beacon chain",
+    ),
+    prototype(
+        Builtin::BlockDifficulty,
+        BLOCK,
+        "difficulty",
+        &[],
+        &[Type::Uint(256)],
+        "The difficulty of the block, the random number since the merge",
+    ),
+    prototype(
+        Builtin::BlockCoinbase,
+        BLOCK,
+        "coinbase",
+        &[],
+        &[Type::Address(true)],
+        "The address of the miner of the block",
+    ),
+    prototype(
+        Builtin::GasLimit,
+        BLOCK,
+        "gaslimit",
+        &[],
+        &[Type::Uint(256)],
+        "The gas limit of the block",
+    ),
+    prototype(Builtin::ChainId, BLOCK, "chainid", &[], &[Type::Uint(256)], "The id of the chain"),
+];
+
+/// The builtin functions, other than `require` and `abi.decode`
+pub const BUILTIN_FUNCTIONS: &[Prototype] = &[
+    prototype(
+        Builtin::Assert,
+        None,
+        "assert",
+        &[Type::Bool],
+        &[Type::Void],
+        "Panic if the condition is false",
+    ),
+    prototype(
+        Builtin::Keccak256,
+        None,
+        "keccak256",
+        &[Type::DynamicBytes],
+        &[Type::Bytes(32)],
+        "The Keccak-256 hash of the data",
+    ),
+    prototype(
+        Builtin::Sha256,
+        None,
+        "sha256",
+        &[Type::DynamicBytes],
+        &[Type::Bytes(32)],
+        "The SHA-256 hash of the data",
+    ),
+    prototype(
+        Builtin::Ripemd160,
+        None,
+        "ripemd160",
+        &[Type::DynamicBytes],
+        &[Type::Bytes(20)],
+        "The RIPEMD-160 hash of the data",
+    ),
+    prototype(Builtin::Gasleft, None, "gasleft", &[], &[Type::Uint(64)], "The gas left"),
+    prototype(
+        Builtin::BlockHash,
+        None,
+        "blockhash",
+        &[Type::Uint(256)],
+        &[Type::Bytes(32)],
+        "The hash of one of the 256 most recent blocks",
+    ),
+    prototype(
+        Builtin::AddMod,
+        None,
+        "addmod",
+        &[Type::Uint(256), Type::Uint(256), Type::Uint(256)],
+        &[Type::Uint(256)],
+        "(x + y) % k with arbitrary precision",
+    ),
+    prototype(
+        Builtin::MulMod,
+        None,
+        "mulmod",
+        &[Type::Uint(256), Type::Uint(256), Type::Uint(256)],
+        &[Type::Uint(256)],
+        "(x * y) % k with arbitrary precision",
+    ),
+    prototype(
+        Builtin::ECRecover,
+        None,
+        "ecrecover",
+        &[Type::Bytes(32), Type::Uint(8), Type::Bytes(32), Type::Bytes(32)],
+        &[Type::Address(false)],
+        "The address of the signer of the hash",
+    ),
+    prototype(
+        Builtin::SelfDestruct,
+        None,
+        "selfdestruct",
+        &[Type::Address(true)],
+        &[Type::Unreachable],
+        "Destroy the contract, sending its balance to the address",
+    ),
+    prototype(
+        Builtin::AbiEncode,
+        ABI,
+        "encode",
+        &[],
+        &[Type::DynamicBytes],
+        "The ABI encoding of the arguments",
+    )
+    .variadic(),
+    prototype(
+        Builtin::AbiEncodePacked,
+        ABI,
+        "encodePacked",
+        &[],
+        &[Type::DynamicBytes],
+        "The packed ABI encoding of the arguments",
+    )
+    .variadic(),
+    prototype(
+        Builtin::AbiEncodeWithSelector,
+        ABI,
+        "encodeWithSelector",
+        &[Type::Bytes(4)],
+        &[Type::DynamicBytes],
+        "The selector followed by the ABI encoding of the arguments",
+    )
+    .variadic(),
+    prototype(
+        Builtin::AbiEncodeWithSignature,
+        ABI,
+        "encodeWithSignature",
+        &[Type::String],
+        &[Type::DynamicBytes],
+        "The selector of the signature followed by the ABI encoding of the arguments",
+    )
+    .variadic(),
+    prototype(
+        Builtin::StringConcat,
+        Some("string"),
+        "concat",
+        &[],
+        &[Type::String],
+        "The concatenation of the strings",
+    )
+    .variadic(),
+    prototype(
+        Builtin::BytesConcat,
+        Some("bytes"),
+        "concat",
+        &[],
+        &[Type::DynamicBytes],
+        "The concatenation of the bytes",
+    )
+    .variadic(),
+];
+
+/// Whether the name is one of the namespaces of builtin variables and
+/// functions, which cannot be used as a value
+pub fn is_builtin_namespace(name: &str) -> bool {
+    matches!(name, "msg" | "tx" | "block" | "abi")
+}
+
+/// The builtin function of the name, if any
+pub fn builtin_function(namespace: Option<&str>, name: &str) -> Option<&'static Prototype> {
+    BUILTIN_FUNCTIONS.iter().find(|p| p.namespace == namespace && p.name == name)
+}
+
+/// Resolve `namespace.name` if it is a builtin variable. Any other member of
+/// the builtin namespaces is an error.
+pub(crate) fn builtin_variable(
+    loc: &pt::Loc,
+    namespace: &str,
+    member: &pt::Identifier,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let prototype =
+        BUILTIN_VARIABLES.iter().find(|p| p.namespace == Some(namespace) && p.name == member.name);
+
+    match prototype {
+        Some(prototype) => Ok(Expression::Builtin {
+            loc: *loc,
+            tys: prototype.ret.to_vec(),
+            kind: prototype.builtin,
+            args: Vec::new(),
+        }),
+        None => {
+            diagnostics.push(Diagnostic::error(
+                member.loc,
+                format!("'{namespace}' has no member '{}'", member.name),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// Resolve a call of a builtin function. The arguments are cast to the types
+/// of the parameters, and the variadic ones keep their own types, except for
+/// the concatenations which cast them to their result.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn builtin_call(
+    loc: &pt::Loc,
+    prototype: &Prototype,
+    args: &[pt::Expression],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let name = match prototype.namespace {
+        Some(namespace) => format!("{namespace}.{}", prototype.name),
+        None => prototype.name.to_string(),
+    };
+    let count = prototype.params.len();
+    if args.len() < count || (!prototype.variadic && args.len() > count) {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!(
+                "builtin '{name}' expects {}{count} arguments, {} given",
+                if prototype.variadic { "at least " } else { "" },
+                args.len()
+            ),
+        ));
+        return Err(());
+    }
+
+    let mut resolved = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        let ty = match (prototype.params.get(i), prototype.builtin) {
+            (Some(ty), _) => Some(ty),
+            (None, Builtin::StringConcat | Builtin::BytesConcat) => Some(&prototype.ret[0]),
+            (None, _) => None,
+        };
+        let resolve_to = ty.map_or(ResolveTo::Unknown, ResolveTo::Type);
+        let expr = expression(arg, context, ctx, symtable, diagnostics, resolve_to)?;
+
+        resolved.push(match ty {
+            // `bytes.concat` takes `bytesN` as well as `bytes`
+            Some(Type::DynamicBytes)
+                if prototype.builtin == Builtin::BytesConcat &&
+                    matches!(expr.ty().deref_any(), Type::Bytes(_)) =>
+            {
+                expr
+            }
+            Some(ty) => expr.cast(&arg.loc(), ty, true, ctx, diagnostics)?,
+            None if matches!(expr, Expression::RationalNumberLiteral { .. }) => {
+                diagnostics.push(Diagnostic::type_error(
+                    arg.loc(),
+                    "rational numbers cannot be encoded, convert to an integer type",
+                ));
+                return Err(());
+            }
+            None => expr,
+        });
+    }
+
+    Ok(Expression::Builtin {
+        loc: *loc,
+        tys: prototype.ret.to_vec(),
+        kind: prototype.builtin,
+        args: resolved,
+    })
+}
+
+/// Resolve `abi.decode(data, (T1, T2))` into the values of the types
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn abi_decode(
+    loc: &pt::Loc,
+    args: &[pt::Expression],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let [data, types] = args else {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!("builtin 'abi.decode' expects 2 arguments, {} given", args.len()),
+        ));
+        return Err(());
+    };
+
+    let ty = ResolveTo::Type(&Type::DynamicBytes);
+    let data = expression(data, context, ctx, symtable, diagnostics, ty)?.cast(
+        &data.loc(),
+        &Type::DynamicBytes,
+        true,
+        ctx,
+        diagnostics,
+    )?;
+
+    let mut tys = Vec::new();
+    let mut resolve = |ty: &pt::Expression, ctx: &mut Context| {
+        let resolved = ctx.resolve_type(
+            context.no,
+            context.contract_no,
+            ResolveTypeContext::None,
+            ty,
+            diagnostics,
+        )?;
+        tys.push(resolved);
+        Ok::<_, ()>(())
+    };
+    match types {
+        pt::Expression::List(_, params) => {
+            for (param_loc, param) in params {
+                match param {
+                    Some(param) if param.name.is_none() && param.storage.is_none() => {
+                        resolve(&param.ty, ctx)?
+                    }
+                    _ => {
+                        diagnostics.push(Diagnostic::error(
+                            *param_loc,
+                            "the types to decode must be types only",
+                        ));
+                        return Err(());
+                    }
+                }
+            }
+        }
+        pt::Expression::Parenthesis(_, ty) => resolve(ty, ctx)?,
+        ty => resolve(ty, ctx)?,
+    }
+
+    Ok(Expression::Builtin { loc: *loc, tys, kind: Builtin::AbiDecode, args: vec![data] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+
+    fn id(name: &str) -> pt::Identifier {
+        pt::Identifier { loc: pt::Loc::Builtin, name: name.to_string() }
+    }
+
+    #[test]
+    fn test_builtin_tables() {
+        for prototype in BUILTIN_VARIABLES {
+            assert!(prototype.params.is_empty() && prototype.ret.len() == 1);
+            assert!(is_builtin_namespace(prototype.namespace.unwrap()));
+        }
+        assert_eq!(builtin_function(None, "keccak256").unwrap().builtin, Builtin::Keccak256);
+        assert_eq!(builtin_function(ABI, "encode").unwrap().builtin, Builtin::AbiEncode);
+        assert_eq!(
+            builtin_function(Some("bytes"), "concat").unwrap().builtin,
+            Builtin::BytesConcat
+        );
+        assert!(builtin_function(None, "encode").is_none());
+    }
+
+    #[test]
+    fn test_builtin_variable() {
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+
+        let expr = builtin_variable(&loc, "msg", &id("sender"), &mut diagnostics).unwrap();
+        assert_eq!(expr.ty(), Type::Address(false));
+        let expr = builtin_variable(&loc, "block", &id("timestamp"), &mut diagnostics).unwrap();
+        assert!(matches!(expr, Expression::Builtin { kind: Builtin::Timestamp, .. }));
+
+        assert!(builtin_variable(&loc, "tx", &id("sender"), &mut diagnostics).is_err());
+        assert_eq!(diagnostics.first_error(), "'tx' has no member 'sender'");
+    }
+}
@@ -0,0 +1,537 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversions between types. An implicit conversion never loses
+//! information: integers only widen, `bytesN` only grow and a contract only
+//! converts to its bases. Anything else has to be spelled out, e.g.
+//! `uint8(x)` or `address(token)`, and some conversions are not possible at
+//! all, e.g. from `bytes2` to `uint8`, whose sizes differ.
+
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink, ErrorType, Level},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Expression, Mutability, RetrieveType, Type},
+        context::Context,
+        contract::is_base,
+    },
+};
+
+impl Expression {
+    /// Cast from one type to another, which also automatically derefs any Type::Ref() type.
+    /// if the cast is explicit (e.g. bytes32(bar) then implicit should be set to false.
+    pub(crate) fn cast(
+        &self,
+        loc: &pt::Loc,
+        to: &Type,
+        implicit: bool,
+        ctx: &Context,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<Expression, ()> {
+        let from = self.ty();
+
+        // An error has already been reported
+        if from == *to || from == Type::Unresolved || *to == Type::Unresolved {
+            return Ok(self.clone());
+        }
+
+        if let Some(result) = self.cast_literal(loc, to, implicit, ctx, diagnostics) {
+            return result;
+        }
+
+        // A reference to a value of the type, e.g. a storage struct passed to
+        // a library function taking `S storage`, is the value itself
+        match to {
+            Type::StorageRef(_, ty) if **ty == *from.deref_any() => return Ok(self.clone()),
+            Type::Ref(ty) if **ty == from => return Ok(self.clone()),
+            _ => (),
+        }
+
+        match &from {
+            // A struct or fixed array in memory is a pointer, with or without
+            // the reference
+            Type::Ref(ty) if **ty == *to => Ok(self.clone()),
+            Type::Ref(ty) => {
+                Expression::Load { loc: *loc, ty: *ty.clone(), expr: Box::new(self.clone()) }.cast(
+                    loc,
+                    to,
+                    implicit,
+                    ctx,
+                    diagnostics,
+                )
+            }
+            Type::StorageRef(_, ty) => {
+                Expression::StorageLoad { loc: *loc, ty: *ty.clone(), expr: Box::new(self.clone()) }
+                    .cast(loc, to, implicit, ctx, diagnostics)
+            }
+            _ => self.cast_types(loc, &from, to, implicit, ctx, diagnostics),
+        }
+    }
+
+    /// Cast a value which is not a reference nor a literal
+    fn cast_types(
+        &self,
+        loc: &pt::Loc,
+        from: &Type,
+        to: &Type,
+        implicit: bool,
+        ctx: &Context,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<Expression, ()> {
+        let expr = Box::new(self.clone());
+        let from_str = from.to_string(ctx);
+        let error = |diagnostics: &mut dyn DiagnosticSink, message: String| {
+            let mut builder =
+                Diagnostic::builder(*loc, Level::Error).ty(ErrorType::CastError).message(message);
+            // An expression with an operator is shown with the types of its
+            // operands, e.g. the `int8 + int16` giving the `int16` converted
+            let summary = self.summary(ctx).to_string();
+            if summary != from_str {
+                builder = builder.note(self.loc(), format!("the value converted is '{summary}'"));
+            }
+            diagnostics.push(builder.build());
+            Err(())
+        };
+        let to_str = to.to_string(ctx);
+        let not_implicit = |diagnostics: &mut dyn DiagnosticSink| {
+            error(
+                diagnostics,
+                format!("implicit conversion from '{from_str}' to '{to_str}' not allowed"),
+            )
+        };
+        let not_possible = |diagnostics: &mut dyn DiagnosticSink| {
+            error(diagnostics, format!("conversion from '{from_str}' to '{to_str}' not possible"))
+        };
+        let truncate = |diagnostics: &mut dyn DiagnosticSink| {
+            error(
+                diagnostics,
+                format!("implicit conversion would truncate from '{from_str}' to '{to_str}'"),
+            )
+        };
+        let change_sign = |diagnostics: &mut dyn DiagnosticSink| {
+            error(
+                diagnostics,
+                format!("implicit conversion would change sign from '{from_str}' to '{to_str}'"),
+            )
+        };
+        let address = Type::Uint(ctx.address_length as u16 * 8);
+
+        match (from, to) {
+            // The value of a transfer is an unsigned integer of its own width
+            (Type::Value, _) => {
+                Expression::Cast { loc: *loc, to: Type::Uint(ctx.value_length as u16 * 8), expr }
+                    .cast(loc, to, implicit, ctx, diagnostics)
+            }
+            (_, Type::Value) => {
+                let value = Type::Uint(ctx.value_length as u16 * 8);
+                let expr = self.cast(loc, &value, implicit, ctx, diagnostics)?;
+                Ok(Expression::Cast { loc: *loc, to: to.clone(), expr: Box::new(expr) })
+            }
+
+            (Type::Uint(from_len), Type::Uint(to_len)) |
+            (Type::Int(from_len), Type::Int(to_len)) => match from_len.cmp(to_len) {
+                Ordering::Greater if implicit => truncate(diagnostics),
+                Ordering::Greater => Ok(Expression::Trunc { loc: *loc, to: to.clone(), expr }),
+                Ordering::Less if matches!(from, Type::Int(_)) => {
+                    Ok(Expression::SignExt { loc: *loc, to: to.clone(), expr })
+                }
+                Ordering::Less => Ok(Expression::ZeroExt { loc: *loc, to: to.clone(), expr }),
+                Ordering::Equal => Ok(self.clone()),
+            },
+            // Every unsigned value fits in a wider signed integer
+            (Type::Uint(from_len), Type::Int(to_len)) => match from_len.cmp(to_len) {
+                Ordering::Less => Ok(Expression::ZeroExt { loc: *loc, to: to.clone(), expr }),
+                Ordering::Greater if implicit => truncate(diagnostics),
+                Ordering::Greater => Ok(Expression::Trunc { loc: *loc, to: to.clone(), expr }),
+                Ordering::Equal if implicit => change_sign(diagnostics),
+                Ordering::Equal => Ok(Expression::Cast { loc: *loc, to: to.clone(), expr }),
+            },
+            (Type::Int(from_len), Type::Uint(to_len)) => match from_len.cmp(to_len) {
+                _ if implicit => change_sign(diagnostics),
+                Ordering::Less => Ok(Expression::SignExt { loc: *loc, to: to.clone(), expr }),
+                Ordering::Greater => Ok(Expression::Trunc { loc: *loc, to: to.clone(), expr }),
+                Ordering::Equal => Ok(Expression::Cast { loc: *loc, to: to.clone(), expr }),
+            },
+
+            // The bytes of a bytesN are left aligned: growing it pads on the
+            // right, shrinking it drops the trailing bytes
+            (Type::Bytes(from_len), Type::Bytes(to_len)) => match from_len.cmp(to_len) {
+                Ordering::Less => {
+                    let shift = (to_len - from_len) as u32 * 8;
+                    Ok(Expression::ShiftLeft {
+                        loc: *loc,
+                        ty: to.clone(),
+                        left: Box::new(Expression::ZeroExt { loc: *loc, to: to.clone(), expr }),
+                        right: Box::new(Expression::NumberLiteral {
+                            loc: *loc,
+                            ty: to.clone(),
+                            value: BigInt::from(shift),
+                        }),
+                    })
+                }
+                Ordering::Greater if implicit => truncate(diagnostics),
+                Ordering::Greater => {
+                    let shift = (from_len - to_len) as u32 * 8;
+                    let shifted = Expression::ShiftRight {
+                        loc: *loc,
+                        ty: from.clone(),
+                        left: expr,
+                        right: Box::new(Expression::NumberLiteral {
+                            loc: *loc,
+                            ty: from.clone(),
+                            value: BigInt::from(shift),
+                        }),
+                        sign: false,
+                    };
+                    Ok(Expression::Trunc { loc: *loc, to: to.clone(), expr: Box::new(shifted) })
+                }
+                Ordering::Equal => Ok(self.clone()),
+            },
+            // Only between the same sizes, e.g. bytes4 and uint32
+            (Type::Bytes(bytes), Type::Uint(bits)) | (Type::Uint(bits), Type::Bytes(bytes)) => {
+                if implicit {
+                    not_implicit(diagnostics)
+                } else if *bytes as u16 * 8 != *bits {
+                    not_possible(diagnostics)
+                } else {
+                    Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+                }
+            }
+
+            // An address is an unsigned integer, or bytes, of its length
+            (Type::Address(_), Type::Uint(_)) |
+            (Type::Uint(_), Type::Address(_)) |
+            (Type::Address(_), Type::Bytes(_)) |
+            (Type::Bytes(_), Type::Address(_)) => {
+                let other = if matches!(from, Type::Address(_)) { to } else { from };
+                let same_length = match other {
+                    Type::Bytes(n) => *n as usize == ctx.address_length,
+                    ty => *ty == address,
+                };
+                if implicit {
+                    not_implicit(diagnostics)
+                } else if !same_length {
+                    not_possible(diagnostics)
+                } else {
+                    Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+                }
+            }
+            // Anything can receive value sent to an address, the other way
+            // round needs `payable(a)`
+            (Type::Address(true), Type::Address(false)) => {
+                Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+            }
+            (Type::Address(false), Type::Address(true)) if implicit => not_implicit(diagnostics),
+            (Type::Address(false), Type::Address(true)) => {
+                Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+            }
+
+            // A contract is its address, and any of its bases
+            (Type::Contract(from_no), Type::Contract(to_no)) => {
+                if is_base(*to_no, *from_no, ctx) {
+                    Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+                } else if implicit {
+                    not_implicit(diagnostics)
+                } else {
+                    not_possible(diagnostics)
+                }
+            }
+            // A contract passes as its address, e.g. to a parameter of type
+            // `address`, but the address of a contract cannot receive value
+            // unless converted explicitly, and an address is only a contract
+            // when converted explicitly
+            (Type::Contract(_), Type::Address(false)) => {
+                Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+            }
+            (Type::Contract(_), Type::Address(_)) | (Type::Address(_), Type::Contract(_)) => {
+                if implicit {
+                    not_implicit(diagnostics)
+                } else {
+                    Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+                }
+            }
+
+            // An enum is its ordinal, in the smallest unsigned integer holding it
+            (Type::Enum(enum_no), Type::Uint(_) | Type::Int(_)) => {
+                if implicit {
+                    return not_implicit(diagnostics);
+                }
+                let ordinal = &ctx.enums[*enum_no].ty;
+                Expression::Cast { loc: *loc, to: ordinal.clone(), expr }.cast(
+                    loc,
+                    to,
+                    false,
+                    ctx,
+                    diagnostics,
+                )
+            }
+            (Type::Uint(_) | Type::Int(_), Type::Enum(enum_no)) => {
+                if implicit {
+                    return not_implicit(diagnostics);
+                }
+                let ordinal = &ctx.enums[*enum_no].ty;
+                let expr = self.cast(loc, ordinal, false, ctx, diagnostics)?;
+                Ok(Expression::Cast { loc: *loc, to: to.clone(), expr: Box::new(expr) })
+            }
+
+            // A user type has to be wrapped and unwrapped explicitly
+            (_, Type::UserType(type_no)) => {
+                let name = &ctx.user_types[*type_no].name;
+                error(
+                    diagnostics,
+                    format!("conversion from '{from_str}' to '{to_str}' not possible, use '{name}.wrap()'"),
+                )
+            }
+            (Type::UserType(type_no), _) => {
+                let name = &ctx.user_types[*type_no].name;
+                error(
+                    diagnostics,
+                    format!("conversion from '{from_str}' to '{to_str}' not possible, use '{name}.unwrap()'"),
+                )
+            }
+
+            (Type::String, Type::DynamicBytes) | (Type::DynamicBytes, Type::String) => {
+                if implicit {
+                    not_implicit(diagnostics)
+                } else {
+                    Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+                }
+            }
+            // `bytes4(data)` takes the first bytes, padded with zeros
+            (Type::DynamicBytes, Type::Bytes(_)) => {
+                if implicit {
+                    not_implicit(diagnostics)
+                } else {
+                    Ok(Expression::BytesCast {
+                        loc: *loc,
+                        from: from.clone(),
+                        to: to.clone(),
+                        expr,
+                    })
+                }
+            }
+
+            // A function converts to a less restrictive mutability, e.g. a
+            // pure function is a view one
+            (
+                Type::InternalFunction {
+                    mutability: from_mut,
+                    params: from_params,
+                    returns: from_returns,
+                },
+                Type::InternalFunction {
+                    mutability: to_mut,
+                    params: to_params,
+                    returns: to_returns,
+                },
+            ) |
+            (
+                Type::ExternalFunction {
+                    mutability: from_mut,
+                    params: from_params,
+                    returns: from_returns,
+                },
+                Type::ExternalFunction {
+                    mutability: to_mut,
+                    params: to_params,
+                    returns: to_returns,
+                },
+            ) => {
+                if from_params != to_params || from_returns != to_returns {
+                    return not_possible(diagnostics);
+                }
+                match (from_mut, to_mut) {
+                    (_, Mutability::Nonpayable(_)) |
+                    (Mutability::Pure(_), Mutability::View(_)) |
+                    (Mutability::Pure(_), Mutability::Pure(_)) |
+                    (Mutability::View(_), Mutability::View(_)) |
+                    (Mutability::Payable(_), Mutability::Payable(_)) => {
+                        Ok(Expression::Cast { loc: *loc, to: to.clone(), expr })
+                    }
+                    _ => error(
+                        diagnostics,
+                        format!(
+                            "function mutability '{from_mut}' cannot be converted to '{to_mut}'"
+                        ),
+                    ),
+                }
+            }
+
+            _ => not_possible(diagnostics),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::Diagnostics,
+        semantic::{ast::EnumDecl, layout::tests::contract},
+    };
+    use indexmap::IndexMap;
+
+    fn variable(ty: Type) -> Expression {
+        Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 }
+    }
+
+    #[test]
+    fn test_cast_integers() {
+        let ctx = Context::new();
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+        let mut cast = |from: Type, to: Type, implicit| {
+            variable(from).cast(&loc, &to, implicit, &ctx, &mut diagnostics)
+        };
+
+        assert!(matches!(
+            cast(Type::Uint(8), Type::Uint(256), true),
+            Ok(Expression::ZeroExt { .. })
+        ));
+        assert!(matches!(cast(Type::Int(8), Type::Int(64), true), Ok(Expression::SignExt { .. })));
+        assert!(matches!(cast(Type::Uint(8), Type::Int(16), true), Ok(Expression::ZeroExt { .. })));
+        assert!(cast(Type::Uint(64), Type::Uint(8), true).is_err());
+        assert!(matches!(cast(Type::Uint(64), Type::Uint(8), false), Ok(Expression::Trunc { .. })));
+        assert!(cast(Type::Int(8), Type::Uint(256), true).is_err());
+        assert!(matches!(cast(Type::Int(8), Type::Uint(8), false), Ok(Expression::Cast { .. })));
+        assert!(cast(Type::Uint(16), Type::Int(16), true).is_err());
+
+        assert!(matches!(
+            cast(Type::Bytes(2), Type::Bytes(4), true),
+            Ok(Expression::ShiftLeft { .. })
+        ));
+        assert!(cast(Type::Bytes(4), Type::Bytes(2), true).is_err());
+        assert!(matches!(
+            cast(Type::Bytes(4), Type::Bytes(2), false),
+            Ok(Expression::Trunc { .. })
+        ));
+        assert!(matches!(cast(Type::Bytes(4), Type::Uint(32), false), Ok(Expression::Cast { .. })));
+        assert!(cast(Type::Bytes(2), Type::Uint(8), false).is_err());
+        assert!(cast(Type::Bool, Type::Uint(8), false).is_err());
+
+        assert_eq!(
+            diagnostics.errors().iter().map(|error| error.message.as_str()).collect::<Vec<_>>(),
+            [
+                "implicit conversion would truncate from 'uint64' to 'uint8'",
+                "implicit conversion would change sign from 'int8' to 'uint256'",
+                "implicit conversion would change sign from 'uint16' to 'int16'",
+                "implicit conversion would truncate from 'bytes4' to 'bytes2'",
+                "conversion from 'bytes2' to 'uint8' not possible",
+                "conversion from 'bool' to 'uint8' not possible",
+            ]
+        );
+        assert!(diagnostics.iter().all(|error| error.notes.is_empty()));
+
+        // The value of an expression with an operator is shown with the types
+        // of its operands
+        let add = Expression::Add {
+            loc,
+            ty: Type::Int(16),
+            unchecked: false,
+            left: Box::new(variable(Type::Int(8))),
+            right: Box::new(variable(Type::Int(16))),
+        };
+        let mut diagnostics = Diagnostics::default();
+        assert!(add.cast(&loc, &Type::Int(8), true, &ctx, &mut diagnostics).is_err());
+        assert_eq!(
+            diagnostics.errors()[0].notes[0].message,
+            "the value converted is 'int8 + int16'"
+        );
+    }
+
+    #[test]
+    fn test_cast_addresses_and_contracts() {
+        let mut ctx = Context::new();
+        ctx.contracts.extend([
+            contract("Base", &[], Vec::new()),
+            contract("Token", &[0], Vec::new()),
+            contract("Other", &[], Vec::new()),
+        ]);
+
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+        let mut cast = |from: Type, to: Type, implicit| {
+            variable(from).cast(&loc, &to, implicit, &ctx, &mut diagnostics)
+        };
+
+        assert!(cast(Type::Contract(1), Type::Contract(0), true).is_ok());
+        assert!(cast(Type::Contract(0), Type::Contract(1), true).is_err());
+        assert!(cast(Type::Contract(1), Type::Contract(2), false).is_err());
+        assert!(cast(Type::Contract(1), Type::Address(false), true).is_ok());
+        assert!(cast(Type::Contract(1), Type::Address(false), false).is_ok());
+        assert!(cast(Type::Contract(1), Type::Address(true), true).is_err());
+        assert!(cast(Type::Contract(1), Type::Address(true), false).is_ok());
+        assert!(cast(Type::Address(false), Type::Contract(1), true).is_err());
+        assert!(cast(Type::Address(false), Type::Contract(1), false).is_ok());
+
+        assert!(cast(Type::Address(true), Type::Address(false), true).is_ok());
+        assert!(cast(Type::Address(false), Type::Address(true), true).is_err());
+        assert!(cast(Type::Address(false), Type::Address(true), false).is_ok());
+        assert!(cast(Type::Address(false), Type::Uint(160), false).is_ok());
+        assert!(cast(Type::Address(false), Type::Bytes(20), false).is_ok());
+        assert!(cast(Type::Address(false), Type::Uint(256), false).is_err());
+
+        // Storage is loaded before it is converted
+        let storage = Type::StorageRef(false, Box::new(Type::Uint(8)));
+        match cast(storage, Type::Uint(16), true) {
+            Ok(Expression::ZeroExt { expr, .. }) => {
+                assert!(matches!(*expr, Expression::StorageLoad { .. }))
+            }
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(
+            diagnostics.first_error(),
+            "implicit conversion from 'contract Base' to 'contract Token' not allowed"
+        );
+    }
+
+    #[test]
+    fn test_cast_enums() {
+        let mut ctx = Context::new();
+        ctx.enums.push(EnumDecl {
+            id: pt::Identifier { loc: pt::Loc::Builtin, name: "State".into() },
+            contract: None,
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(8),
+            values: IndexMap::from([
+                ("Open".into(), pt::Loc::Builtin),
+                ("Closed".into(), pt::Loc::Builtin),
+            ]),
+        });
+
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+        let mut cast = |expr: Expression, to: Type, implicit| {
+            expr.cast(&loc, &to, implicit, &ctx, &mut diagnostics)
+        };
+
+        assert!(cast(variable(Type::Enum(0)), Type::Uint(8), true).is_err());
+        assert!(cast(variable(Type::Enum(0)), Type::Uint(256), false).is_ok());
+        assert!(cast(variable(Type::Uint(256)), Type::Enum(0), false).is_ok());
+
+        let number = |value: i64| Expression::NumberLiteral {
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(8),
+            value: value.into(),
+        };
+        assert!(cast(number(1), Type::Enum(0), false).is_ok());
+        assert!(cast(number(2), Type::Enum(0), false).is_err());
+        assert!(diagnostics.contains_message("enum 'State' has no value with ordinal 2"));
+    }
+}
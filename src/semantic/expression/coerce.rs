@@ -0,0 +1,108 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The common type of two operands, which the binary operators, the
+//! conditional operator and array literals cast their operands to. A number
+//! literal takes the type of the other operand, integers and `bytesN` widen
+//! to the larger of the two, an unsigned integer widens to a larger signed
+//! one, addresses meet at `address` as do a contract and an address, and
+//! contracts at the base of the other. Otherwise one of the operands must
+//! convert implicitly to the type of the other.
+
+use crate::{
+    diagnostics::Diagnostics,
+    helpers::CodeLocation,
+    semantic::{
+        ast::{Expression, RetrieveType, Type},
+        context::Context,
+        contract::is_base,
+    },
+};
+
+/// The type both operands are cast to, or `None` if they have none. The
+/// callers report the error, as the message depends on the operator.
+pub(crate) fn coerce_types(left: &Expression, right: &Expression, ctx: &Context) -> Option<Type> {
+    let left_ty = left.ty().deref_any().clone();
+    let right_ty = right.ty().deref_any().clone();
+
+    let is_literal = |expr: &Expression| matches!(expr, Expression::NumberLiteral { .. });
+    let converts = |expr: &Expression, ty: &Type| {
+        expr.cast(&expr.loc(), ty, true, ctx, &mut Diagnostics::default()).is_ok()
+    };
+
+    match (&left_ty, &right_ty) {
+        _ if left_ty == right_ty => Some(left_ty),
+        _ if is_literal(right) && !is_literal(left) => Some(left_ty),
+        _ if is_literal(left) && !is_literal(right) => Some(right_ty),
+        (Type::Uint(l), Type::Uint(r)) => Some(Type::Uint(*l.max(r))),
+        (Type::Int(l), Type::Int(r)) => Some(Type::Int(*l.max(r))),
+        (Type::Uint(l), Type::Int(r)) if l < r => Some(Type::Int(*r)),
+        (Type::Int(l), Type::Uint(r)) if r < l => Some(Type::Int(*l)),
+        (Type::Uint(_) | Type::Int(_), Type::Uint(_) | Type::Int(_)) => None,
+        (Type::Bytes(l), Type::Bytes(r)) => Some(Type::Bytes(*l.max(r))),
+        // A contract passes as its address
+        (Type::Address(_), Type::Address(_) | Type::Contract(_)) |
+        (Type::Contract(_), Type::Address(_)) => Some(Type::Address(false)),
+        (Type::Contract(l), Type::Contract(r)) if is_base(*l, *r, ctx) => Some(left_ty),
+        (Type::Contract(l), Type::Contract(r)) if is_base(*r, *l, ctx) => Some(right_ty),
+        _ if converts(right, &left_ty) => Some(left_ty),
+        _ if converts(left, &right_ty) => Some(right_ty),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::ast as pt, semantic::layout::tests::contract};
+
+    fn variable(ty: Type) -> Expression {
+        Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 }
+    }
+
+    fn number(ty: Type, value: i64) -> Expression {
+        Expression::NumberLiteral { loc: pt::Loc::Builtin, ty, value: value.into() }
+    }
+
+    #[test]
+    fn test_coerce_types() {
+        let mut ctx = Context::new();
+        ctx.contracts.extend([
+            contract("Base", &[], Vec::new()),
+            contract("Token", &[0], Vec::new()),
+            contract("Other", &[], Vec::new()),
+        ]);
+        let coerce =
+            |left: Type, right: Type| coerce_types(&variable(left), &variable(right), &ctx);
+
+        assert_eq!(coerce(Type::Uint(8), Type::Uint(64)), Some(Type::Uint(64)));
+        assert_eq!(coerce(Type::Int(64), Type::Int(8)), Some(Type::Int(64)));
+        assert_eq!(coerce(Type::Uint(8), Type::Int(16)), Some(Type::Int(16)));
+        assert_eq!(coerce(Type::Int(16), Type::Uint(8)), Some(Type::Int(16)));
+        assert_eq!(coerce(Type::Uint(16), Type::Int(16)), None);
+        assert_eq!(coerce(Type::Bytes(2), Type::Bytes(4)), Some(Type::Bytes(4)));
+        assert_eq!(coerce(Type::Address(true), Type::Address(false)), Some(Type::Address(false)));
+        assert_eq!(coerce(Type::Contract(1), Type::Address(true)), Some(Type::Address(false)));
+        assert_eq!(coerce(Type::Contract(1), Type::Contract(0)), Some(Type::Contract(0)));
+        assert_eq!(coerce(Type::Contract(0), Type::Contract(1)), Some(Type::Contract(0)));
+        assert_eq!(coerce(Type::Contract(1), Type::Contract(2)), None);
+        assert_eq!(coerce(Type::Bool, Type::Uint(8)), None);
+
+        // A literal takes the type of the other operand, and two literals widen
+        let literal = number(Type::Uint(16), 300);
+        assert_eq!(coerce_types(&literal, &variable(Type::Uint(8)), &ctx), Some(Type::Uint(8)));
+        assert_eq!(coerce_types(&variable(Type::Int(8)), &literal, &ctx), Some(Type::Int(8)));
+        assert_eq!(coerce_types(&number(Type::Uint(8), 1), &literal, &ctx), Some(Type::Uint(16)));
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The comparison operators. Integers, `bytesN`, addresses and enums are
+//! ordered, booleans only compare for equality, and strings and `bytes`
+//! compare for equality by their length and contents.
+
+use std::fmt;
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Expression, RetrieveType, StringLocation, Type},
+        context::Context,
+        expression::{
+            coerce::coerce_types, resolve_expression::expression, ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// A comparison operator
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    More,
+    LessEqual,
+    MoreEqual,
+}
+
+impl Comparison {
+    /// Whether the operator orders its operands, rather than testing them for equality
+    pub fn is_ordering(self) -> bool {
+        !matches!(self, Comparison::Equal | Comparison::NotEqual)
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::More => ">",
+            Comparison::LessEqual => "<=",
+            Comparison::MoreEqual => ">=",
+        })
+    }
+}
+
+/// Resolve a comparison of two parsed expressions
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn comparison(
+    loc: &pt::Loc,
+    op: Comparison,
+    l: &pt::Expression,
+    r: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let left = expression(l, context, ctx, symtable, diagnostics, ResolveTo::Integer)?;
+    let right = expression(r, context, ctx, symtable, diagnostics, ResolveTo::Integer)?;
+
+    compare(loc, op, left, right, ctx, diagnostics)
+}
+
+/// Compare two resolved expressions, casting both to their common type
+pub(crate) fn compare(
+    loc: &pt::Loc,
+    op: Comparison,
+    left: Expression,
+    right: Expression,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let left_ty = left.ty().deref_any().clone();
+    let right_ty = right.ty().deref_any().clone();
+
+    // String literals are compared at compile time
+    if let (Expression::BytesLiteral { value: l, .. }, Expression::BytesLiteral { value: r, .. }) =
+        (&left, &right)
+    {
+        if !op.is_ordering() {
+            return Ok(Expression::BoolLiteral {
+                loc: *loc,
+                value: (l == r) == (op == Comparison::Equal),
+            });
+        }
+    }
+
+    if is_string(&left_ty) || is_string(&right_ty) {
+        return compare_strings(loc, op, left, &left_ty, right, &right_ty, ctx, diagnostics);
+    }
+
+    let ty = common_type(loc, op, &left, &left_ty, &right, &right_ty, ctx, diagnostics)?;
+    let left = Box::new(left.cast(&left.loc(), &ty, true, ctx, diagnostics)?);
+    let right = Box::new(right.cast(&right.loc(), &ty, true, ctx, diagnostics)?);
+    let loc = *loc;
+
+    Ok(match op {
+        Comparison::Equal => Expression::Equal { loc, left, right },
+        Comparison::NotEqual => Expression::NotEqual { loc, left, right },
+        Comparison::Less => Expression::Less { loc, left, right },
+        Comparison::More => Expression::More { loc, left, right },
+        Comparison::LessEqual => Expression::LessEqual { loc, left, right },
+        Comparison::MoreEqual => Expression::MoreEqual { loc, left, right },
+    })
+}
+
+fn is_string(ty: &Type) -> bool {
+    matches!(ty, Type::String | Type::DynamicBytes)
+}
+
+/// Compare strings or `bytes` by their length and contents, inlining literals
+/// into the comparison
+#[allow(clippy::too_many_arguments)]
+fn compare_strings(
+    loc: &pt::Loc,
+    op: Comparison,
+    left: Expression,
+    left_ty: &Type,
+    right: Expression,
+    right_ty: &Type,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    if op.is_ordering() {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!(
+                "operator '{op}' is not supported on type '{}', which can only be compared with '==' and '!='",
+                if is_string(left_ty) { left_ty } else { right_ty }.to_string(ctx)
+            ),
+        ));
+        return Err(());
+    }
+
+    let is_literal = |expr: &Expression| matches!(expr, Expression::BytesLiteral { .. });
+    if left_ty != right_ty && !is_literal(&left) && !is_literal(&right) {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!(
+                "cannot compare '{}' and '{}', convert one of them explicitly",
+                left_ty.to_string(ctx),
+                right_ty.to_string(ctx)
+            ),
+        ));
+        return Err(());
+    }
+
+    let ty = if is_string(left_ty) { left_ty } else { right_ty };
+    let mut location = |expr: Expression| match expr {
+        Expression::BytesLiteral { value, .. } => Ok(StringLocation::CompileTime(value)),
+        expr => {
+            let expr = expr.cast(&expr.loc(), ty, true, ctx, diagnostics)?;
+            Ok(StringLocation::RunTime(Box::new(expr)))
+        }
+    };
+    let equal =
+        Expression::StringCompare { loc: *loc, left: location(left)?, right: location(right)? };
+
+    Ok(match op {
+        Comparison::NotEqual => Expression::Not { loc: *loc, expr: Box::new(equal) },
+        _ => equal,
+    })
+}
+
+/// The type both operands of the comparison are cast to
+#[allow(clippy::too_many_arguments)]
+fn common_type(
+    loc: &pt::Loc,
+    op: Comparison,
+    left: &Expression,
+    left_ty: &Type,
+    right: &Expression,
+    right_ty: &Type,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Type, ()> {
+    for ty in [left_ty, right_ty] {
+        if let Some(reason) = incomparable(ty, op) {
+            diagnostics.push(Diagnostic::type_error(
+                *loc,
+                format!(
+                    "operator '{op}' is not supported on type '{}', {reason}",
+                    ty.to_string(ctx)
+                ),
+            ));
+            return Err(());
+        }
+    }
+
+    coerce_types(left, right, ctx).ok_or_else(|| {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!(
+                "cannot compare '{}' and '{}', convert one of them explicitly",
+                left_ty.to_string(ctx),
+                right_ty.to_string(ctx)
+            ),
+        ));
+    })
+}
+
+/// Why values of the type cannot be compared with the operator, if they cannot
+fn incomparable(ty: &Type, op: Comparison) -> Option<&'static str> {
+    match ty {
+        Type::Uint(_) | Type::Int(_) | Type::Bytes(_) | Type::Address(_) | Type::Enum(_) => None,
+        Type::Bool if !op.is_ordering() => None,
+        Type::Bool => Some("which can only be compared with '==' and '!='"),
+        Type::Array(..) | Type::Struct(_) => {
+            Some("compare the keccak256 hashes of their abi encodings instead")
+        }
+        Type::Mapping(_) => Some("as mappings cannot be compared"),
+        Type::Contract(_) => Some("convert the contracts to addresses to compare them"),
+        Type::UserType(_) => Some("unless an operator is defined for it with 'using for'"),
+        _ => Some("as its values cannot be compared"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diagnostics::Diagnostics, semantic::ast::ArrayLength};
+
+    fn variable(ty: Type) -> Expression {
+        Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 }
+    }
+
+    fn bytes(value: &str) -> Expression {
+        Expression::BytesLiteral {
+            loc: pt::Loc::Builtin,
+            ty: Type::Bytes(value.len() as u8),
+            value: value.as_bytes().to_vec(),
+        }
+    }
+
+    fn resolve(op: Comparison, left: Expression, right: Expression) -> Result<Expression, String> {
+        let ctx = Context::new();
+        let mut diagnostics = Diagnostics::default();
+        compare(&pt::Loc::Builtin, op, left, right, &ctx, &mut diagnostics)
+            .map_err(|_| diagnostics.first_error())
+    }
+
+    #[test]
+    fn test_compare_strings() {
+        let string = || variable(Type::String);
+
+        assert!(matches!(
+            resolve(Comparison::Equal, string(), string()),
+            Ok(Expression::StringCompare {
+                left: StringLocation::RunTime(_),
+                right: StringLocation::RunTime(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            resolve(Comparison::NotEqual, string(), bytes("abc")),
+            Ok(Expression::Not { expr, .. }) if matches!(*expr, Expression::StringCompare {
+                right: StringLocation::CompileTime(ref value),
+                ..
+            } if value == b"abc")
+        ));
+        assert!(matches!(
+            resolve(Comparison::Equal, variable(Type::DynamicBytes), variable(Type::DynamicBytes)),
+            Ok(Expression::StringCompare { .. })
+        ));
+
+        // Literals are compared at compile time
+        assert!(matches!(
+            resolve(Comparison::NotEqual, bytes("a"), bytes("b")),
+            Ok(Expression::BoolLiteral { value: true, .. })
+        ));
+
+        assert_eq!(
+            resolve(Comparison::Less, string(), string()).unwrap_err(),
+            "operator '<' is not supported on type 'string', which can only be compared with '==' and '!='"
+        );
+        assert_eq!(
+            resolve(Comparison::Equal, string(), variable(Type::DynamicBytes)).unwrap_err(),
+            "cannot compare 'string' and 'bytes', convert one of them explicitly"
+        );
+    }
+
+    #[test]
+    fn test_compare_values() {
+        let address = || variable(Type::Address(false));
+        assert!(matches!(
+            resolve(Comparison::Less, address(), address()),
+            Ok(Expression::Less { .. })
+        ));
+        assert!(matches!(
+            resolve(Comparison::MoreEqual, variable(Type::Uint(8)), variable(Type::Uint(8))),
+            Ok(Expression::MoreEqual { .. })
+        ));
+        assert!(matches!(
+            resolve(Comparison::Equal, variable(Type::Bool), variable(Type::Bool)),
+            Ok(Expression::Equal { .. })
+        ));
+
+        assert_eq!(
+            resolve(Comparison::More, variable(Type::Bool), variable(Type::Bool)).unwrap_err(),
+            "operator '>' is not supported on type 'bool', which can only be compared with '==' and '!='"
+        );
+        assert_eq!(
+            resolve(Comparison::Equal, variable(Type::Uint(8)), variable(Type::Int(8)))
+                .unwrap_err(),
+            "cannot compare 'uint8' and 'int8', convert one of them explicitly"
+        );
+
+        let array = || variable(Type::Array(Box::new(Type::Uint(256)), vec![ArrayLength::Dynamic]));
+        assert_eq!(
+            resolve(Comparison::Equal, array(), array()).unwrap_err(),
+            "operator '==' is not supported on type 'uint256[]', compare the keccak256 hashes of their abi encodings instead"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Restrictions on the initializer of a constant, which is evaluated at
+//! compile time: it cannot read state, the transaction or the block, call
+//! functions other than the pure builtins like `keccak256`, create contracts
+//! or assign.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    semantic::{
+        ast::{Builtin, Expression, Recurse},
+        context::Context,
+        expression::ExprContext,
+    },
+};
+
+impl Expression {
+    /// Check the expression can be evaluated at compile time, when resolved
+    /// in a constant context. Returns false if any errors were reported.
+    pub(crate) fn check_constant_context(
+        &self,
+        context: &ExprContext,
+        ctx: &Context,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> bool {
+        if !context.constant {
+            return true;
+        }
+
+        let errors = diagnostics.error_count();
+        self.recurse(&mut (ctx, &mut *diagnostics), check_constant_term);
+        diagnostics.error_count() == errors
+    }
+}
+
+/// Report the term if it cannot be evaluated at compile time. Terms which are
+/// reported are not recursed into, so `keccak256(msg.sender)` gives one error.
+fn check_constant_term(
+    expr: &Expression,
+    (ctx, diagnostics): &mut (&Context, &mut (dyn DiagnosticSink + '_)),
+) -> bool {
+    let message = match expr {
+        Expression::StorageVariable { contract_no, var_no, .. } => format!(
+            "cannot read state variable '{}' in a constant expression",
+            ctx.contracts[*contract_no].variables[*var_no].name
+        ),
+        Expression::Builtin { kind, .. } => match environment(kind) {
+            Some(name) => format!("'{name}' cannot be read in a constant expression"),
+            None if is_pure(kind) => return true,
+            None => "cannot call function in a constant expression".into(),
+        },
+        Expression::InternalFunctionCall { .. } |
+        Expression::ExternalFunctionCall { .. } |
+        Expression::ExternalFunctionCallRaw { .. } => {
+            "cannot call function in a constant expression".into()
+        }
+        Expression::Constructor { .. } => {
+            "cannot create contract with 'new' in a constant expression".into()
+        }
+        Expression::Assign { .. } |
+        Expression::PreIncrement { .. } |
+        Expression::PreDecrement { .. } |
+        Expression::PostIncrement { .. } |
+        Expression::PostDecrement { .. } => "cannot assign in a constant expression".into(),
+        _ => return true,
+    };
+
+    diagnostics.push(Diagnostic::type_error(expr.loc(), message));
+    false
+}
+
+/// The name of the builtin variable or function which reads the transaction
+/// or the block, like `msg.sender`
+fn environment(kind: &Builtin) -> Option<&'static str> {
+    Some(match kind {
+        Builtin::Sender => "msg.sender",
+        Builtin::Value => "msg.value",
+        Builtin::Signature => "msg.sig",
+        Builtin::Calldata => "msg.data",
+        Builtin::Gasprice => "tx.gasprice",
+        Builtin::Origin => "tx.origin",
+        Builtin::BlockNumber => "block.number",
+        Builtin::Timestamp => "block.timestamp",
+        Builtin::BaseFee => "block.basefee",
+        Builtin::PrevRandao => "block.prevrandao",
+        Builtin::BlockCoinbase => "block.coinbase",
+        Builtin::BlockDifficulty => "block.difficulty",
+        Builtin::GasLimit => "block.gaslimit",
+        Builtin::ChainId => "block.chainid",
+        Builtin::Slot => "block.slot",
+        Builtin::BlockHash => "blockhash",
+        Builtin::Gasleft => "gasleft",
+        _ => return None,
+    })
+}
+
+/// Whether the builtin only depends on its arguments, so it can be evaluated
+/// at compile time
+fn is_pure(kind: &Builtin) -> bool {
+    matches!(
+        kind,
+        Builtin::Keccak256 |
+            Builtin::Ripemd160 |
+            Builtin::Sha256 |
+            Builtin::Blake2_128 |
+            Builtin::Blake2_256 |
+            Builtin::AddMod |
+            Builtin::MulMod |
+            Builtin::AbiEncode |
+            Builtin::AbiEncodePacked |
+            Builtin::AbiEncodeWithSelector |
+            Builtin::AbiEncodeWithSignature |
+            Builtin::StringConcat |
+            Builtin::BytesConcat |
+            Builtin::UserTypeWrap |
+            Builtin::UserTypeUnwrap
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diagnostics::Diagnostics, parser::ast as pt, semantic::ast::Type};
+
+    fn builtin(kind: Builtin, args: Vec<Expression>) -> Expression {
+        Expression::Builtin { loc: pt::Loc::Builtin, tys: vec![Type::Uint(256)], kind, args }
+    }
+
+    fn check(expr: &Expression, ctx: &Context) -> Result<(), String> {
+        let context = ExprContext { constant: true, ..Default::default() };
+        let mut diagnostics = Diagnostics::default();
+        match expr.check_constant_context(&context, ctx, &mut diagnostics) {
+            true => Ok(()),
+            false => Err(diagnostics.first_error()),
+        }
+    }
+
+    #[test]
+    fn test_constant_context() {
+        let ctx = Context::new();
+        let literal = Expression::BytesLiteral {
+            loc: pt::Loc::Builtin,
+            ty: Type::DynamicBytes,
+            value: b"owner".to_vec(),
+        };
+
+        // `keccak256("owner")` is evaluated at compile time
+        assert!(check(&builtin(Builtin::Keccak256, vec![literal.clone()]), &ctx).is_ok());
+
+        let sender = builtin(Builtin::Sender, Vec::new());
+        let hash = builtin(Builtin::Keccak256, vec![sender.clone()]);
+        assert_eq!(
+            check(&hash, &ctx).unwrap_err(),
+            "'msg.sender' cannot be read in a constant expression"
+        );
+
+        let balance = builtin(Builtin::Balance, vec![literal]);
+        assert_eq!(
+            check(&balance, &ctx).unwrap_err(),
+            "cannot call function in a constant expression"
+        );
+
+        let var = Expression::Variable { loc: pt::Loc::Builtin, ty: Type::Uint(256), var_no: 0 };
+        let assign = Expression::Assign {
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(256),
+            left: Box::new(var.clone()),
+            right: Box::new(var),
+        };
+        assert_eq!(check(&assign, &ctx).unwrap_err(), "cannot assign in a constant expression");
+
+        // Outside a constant context anything goes
+        let mut diagnostics = Diagnostics::default();
+        assert!(sender.check_constant_context(&ExprContext::default(), &ctx, &mut diagnostics));
+    }
+}
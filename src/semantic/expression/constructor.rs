@@ -13,21 +13,341 @@
 // limitations under the License.
 
 use crate::{
-    diagnostics::Diagnostics,
+    diagnostics::{Diagnostic, DiagnosticSink, Level, Note},
+    helpers::CodeLocation,
     parser::ast as pt,
-    semantic::{ast::Expression, context::Context, expression::ExprContext, symtable::Symtable},
+    semantic::{
+        ast::{CallArgs, Expression},
+        context::Context,
+        contract::unimplemented_functions,
+        expression::{
+            function_call::{call_args, match_overload, Arguments},
+            ExprContext,
+        },
+        symtable::Symtable,
+    },
 };
 
 /// Try and find constructor for arguments
 #[allow(clippy::result_unit_err)]
 pub fn match_constructor_to_args(
-    _loc: &pt::Loc,
-    _args: &[pt::Expression],
-    _contract_no: usize,
-    _context: &mut ExprContext,
-    _ctx: &mut Context,
-    _symtable: &mut Symtable,
-    _diagnostics: &mut Diagnostics,
+    loc: &pt::Loc,
+    args: &[pt::Expression],
+    contract_no: usize,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
 ) -> Result<(Option<usize>, Vec<Expression>), ()> {
-    todo!()
+    let constructors = ctx.contracts[contract_no]
+        .functions
+        .iter()
+        .copied()
+        .filter(|function_no| ctx.functions[*function_no].is_constructor())
+        .collect::<Vec<_>>();
+
+    // Without a constructor, the default one takes no arguments
+    if constructors.is_empty() {
+        if !args.is_empty() {
+            diagnostics.push(Diagnostic::error(
+                *loc,
+                format!(
+                    "contract '{}' has no constructor, {} arguments given",
+                    ctx.contracts[contract_no].id,
+                    args.len()
+                ),
+            ));
+            return Err(());
+        }
+        return Ok((None, Vec::new()));
+    }
+
+    let params = constructors
+        .iter()
+        .map(|function_no| {
+            let func = &ctx.functions[*function_no];
+            func.params.iter().map(|param| (param.id.clone(), param.ty.clone())).collect()
+        })
+        .collect::<Vec<_>>();
+    let name = format!("{}.constructor", ctx.contracts[contract_no].id);
+    let (index, args) = match_overload(
+        loc,
+        &name,
+        &params,
+        Arguments::Positional(args),
+        context,
+        ctx,
+        symtable,
+        diagnostics,
+    )?;
+
+    Ok((Some(constructors[index]), args))
+}
+
+/// Resolve `new Contract(args)`, or `new Contract{value: v}(args)` to send
+/// value to the constructor
+pub(crate) fn new(
+    loc: &pt::Loc,
+    call: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let pt::Expression::FunctionCall(_, ty, args) = call else {
+        diagnostics.push(Diagnostic::error(*loc, "missing constructor arguments to new"));
+        return Err(());
+    };
+
+    let (ty, call_args) = match ty.as_ref() {
+        pt::Expression::FunctionCallBlock(_, ty, block) => {
+            (ty.as_ref(), call_args(block, context, ctx, symtable, diagnostics)?)
+        }
+        ty => (ty, CallArgs::default()),
+    };
+
+    // `new bytes(n)` and `new T[](n)` allocate arrays
+    let pt::Expression::Variable(id) = ty else {
+        diagnostics.push(Diagnostic::error(ty.loc(), "new of arrays is not supported"));
+        return Err(());
+    };
+    let name = pt::IdentifierPath { loc: id.loc, identifiers: vec![id.clone()] };
+    let contract_no = ctx.resolve_contract_with_namespace(context.no, &name, diagnostics)?;
+
+    check_instantiable(loc, contract_no, ctx, diagnostics)?;
+
+    let (constructor_no, args) =
+        match_constructor_to_args(loc, args, contract_no, context, ctx, symtable, diagnostics)?;
+    check_call_args(contract_no, constructor_no, &call_args, ctx, diagnostics)?;
+
+    if let Some(creator_no) = context.contract_no {
+        let creates = &mut ctx.contracts[creator_no].creates;
+        if !creates.contains(&contract_no) {
+            creates.push(contract_no);
+        }
+    }
+    if let Some(function_no) = context.function_no {
+        ctx.functions[function_no].creates.push((*loc, contract_no));
+    }
+
+    Ok(Expression::Constructor { loc: *loc, contract_no, constructor_no, args, call_args })
+}
+
+/// Check the call arguments of `new`: value can only be sent to a payable
+/// constructor, and the gas of the creation cannot be limited
+fn check_call_args(
+    contract_no: usize,
+    constructor_no: Option<usize>,
+    call_args: &CallArgs,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), ()> {
+    if let Some(gas) = &call_args.gas {
+        diagnostics.push(Diagnostic::error(gas.loc(), "'gas' cannot be given to 'new'"));
+        return Err(());
+    }
+
+    let Some(value) = &call_args.value else {
+        return Ok(());
+    };
+    match constructor_no.map(|function_no| &ctx.functions[function_no]) {
+        Some(constructor) if constructor.is_payable() => Ok(()),
+        constructor => {
+            let mut builder = Diagnostic::builder(value.loc(), Level::Error).message(format!(
+                "cannot send value to contract '{}' as its constructor is not payable",
+                ctx.contracts[contract_no].id
+            ));
+            if let Some(constructor) = constructor {
+                builder = builder.note(constructor.loc_prototype, "constructor defined here");
+            }
+            diagnostics.push(builder.build());
+            Err(())
+        }
+    }
+}
+
+/// Check the contract can be created with `new`
+pub(crate) fn check_instantiable(
+    loc: &pt::Loc,
+    contract_no: usize,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), ()> {
+    let contract = &ctx.contracts[contract_no];
+    if contract.instantiable {
+        return Ok(());
+    }
+
+    let kind = match contract.ty {
+        pt::ContractTy::Abstract(_) => "abstract contract",
+        pt::ContractTy::Interface(_) => "interface",
+        pt::ContractTy::Library(_) => "library",
+        pt::ContractTy::Contract(_) => {
+            let notes = unimplemented_functions(contract_no, ctx)
+                .into_iter()
+                .map(|function_no| {
+                    let func = &ctx.functions[function_no];
+                    Note {
+                        loc: func.loc_prototype,
+                        message: format!("function '{}' has no body", func.id),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            // Otherwise the contract has errors, which are reported already
+            if !notes.is_empty() {
+                diagnostics.push(
+                    Diagnostic::builder(*loc, Level::Error)
+                        .message(format!(
+                            "cannot create contract '{}' as it does not implement all its functions",
+                            contract.id
+                        ))
+                        .notes(notes)
+                        .build(),
+                );
+            }
+            return Err(());
+        }
+    };
+
+    diagnostics.push(Diagnostic::error(*loc, format!("cannot create {kind} '{}'", contract.id)));
+    Err(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::Diagnostics,
+        parser::parse_expression,
+        semantic::{
+            ast::{Function, Symbol},
+            contract::compute_instantiable,
+            layout::tests::contract,
+            symtable::Symtable,
+        },
+    };
+
+    fn function(ctx: &mut Context, contract_no: usize, name: &str, has_body: bool) {
+        let mut func = Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+            Some(contract_no),
+            Vec::new(),
+            pt::FunctionTy::Function,
+            None,
+            pt::Visibility::Public(None),
+            Vec::new(),
+            Vec::new(),
+            ctx,
+        );
+        func.has_body = has_body;
+        ctx.functions.push(func);
+        ctx.contracts[contract_no].functions.push(ctx.functions.len() - 1);
+    }
+
+    #[test]
+    fn test_instantiable() {
+        let mut ctx = Context::new();
+        ctx.contracts.push(contract("Shape", &[], Vec::new()));
+        ctx.contracts[0].ty = pt::ContractTy::Abstract(pt::Loc::Builtin);
+        function(&mut ctx, 0, "area", false);
+        function(&mut ctx, 0, "name", true);
+        ctx.contracts.push(contract("Square", &[0], Vec::new()));
+        function(&mut ctx, 1, "area", true);
+        ctx.contracts.push(contract("Circle", &[0], Vec::new()));
+
+        for contract_no in 0..3 {
+            compute_instantiable(contract_no, &mut ctx);
+        }
+        assert!(!ctx.contracts[0].instantiable);
+        assert!(ctx.contracts[1].instantiable);
+        assert!(!ctx.contracts[2].instantiable);
+        assert_eq!(
+            ctx.diagnostics.first_error(),
+            "contract 'Circle' should be marked 'abstract contract' since it does not implement \
+             1 inherited functions"
+        );
+
+        let check = |contract_no| {
+            let mut diagnostics = Diagnostics::default();
+            check_instantiable(&pt::Loc::Builtin, contract_no, &ctx, &mut diagnostics)
+                .map_err(|_| diagnostics)
+        };
+        assert!(check(1).is_ok());
+        assert_eq!(check(0).unwrap_err().first_error(), "cannot create abstract contract 'Shape'");
+
+        let diagnostics = check(2).unwrap_err();
+        assert_eq!(
+            diagnostics.first_error(),
+            "cannot create contract 'Circle' as it does not implement all its functions"
+        );
+        let notes = &diagnostics.iter().next().unwrap().notes;
+        assert_eq!(notes[0].message, "function 'area' has no body");
+    }
+
+    #[test]
+    fn test_new_with_value() {
+        let mut ctx = Context::new();
+        for (contract_no, name) in ["Vault", "Token", "Empty"].into_iter().enumerate() {
+            ctx.contracts.push(contract(name, &[], Vec::new()));
+            let id = pt::Identifier { loc: pt::Loc::Builtin, name: name.into() };
+            ctx.add_symbol(0, None, &id, Symbol::Contract(pt::Loc::Builtin, contract_no));
+        }
+        for (contract_no, mutability) in
+            [(0, Some(pt::Mutability::Payable(pt::Loc::Builtin))), (1, None)]
+        {
+            let constructor = Function::new(
+                pt::Loc::Builtin,
+                pt::Loc::Builtin,
+                pt::Identifier { loc: pt::Loc::Builtin, name: String::new() },
+                Some(contract_no),
+                Vec::new(),
+                pt::FunctionTy::Constructor,
+                mutability,
+                pt::Visibility::Public(None),
+                Vec::new(),
+                Vec::new(),
+                &ctx,
+            );
+            ctx.functions.push(constructor);
+            ctx.contracts[contract_no].functions.push(ctx.functions.len() - 1);
+        }
+
+        let mut resolve = |source: &str| {
+            let pt::Expression::New(loc, call) = parse_expression(source, 0).unwrap() else {
+                panic!("{source} is not a new expression");
+            };
+            let mut diagnostics = Diagnostics::default();
+            new(
+                &loc,
+                &call,
+                &mut ExprContext::default(),
+                &mut ctx,
+                &mut Symtable::default(),
+                &mut diagnostics,
+            )
+            .map_err(|_| diagnostics.first_error())
+        };
+
+        let Ok(Expression::Constructor { constructor_no, call_args, .. }) =
+            resolve("new Vault{value: 1}()")
+        else {
+            panic!("new Vault is not resolved");
+        };
+        assert_eq!(constructor_no, Some(0));
+        assert!(call_args.value.is_some());
+        assert!(resolve("new Token()").is_ok());
+
+        assert_eq!(
+            resolve("new Token{value: 1}()").unwrap_err(),
+            "cannot send value to contract 'Token' as its constructor is not payable"
+        );
+        assert_eq!(
+            resolve("new Empty{value: 1}()").unwrap_err(),
+            "cannot send value to contract 'Empty' as its constructor is not payable"
+        );
+        assert_eq!(resolve("new Vault{gas: 1}()").unwrap_err(), "'gas' cannot be given to 'new'");
+    }
 }
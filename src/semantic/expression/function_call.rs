@@ -0,0 +1,1061 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Function calls, `f(args)`, `f({name: arg})` and `f{value: v}(args)`. The
+//! callee is a type, for a conversion or a struct literal, a builtin, a
+//! function named directly or through a contract, a function of an instance
+//! of a contract, a function bound with `using`, a member of an array or
+//! address, or any other expression of a function type. Overloaded functions
+//! are resolved by the arguments which convert implicitly to the parameters.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics, ErrorType, Level, Note},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{ArrayLength, Builtin, CallArgs, CallTy, Expression, RetrieveType, Symbol, Type},
+        context::{Context, ResolveTypeContext},
+        eval::overflow_diagnostic,
+        expression::{
+            builtin::{abi_decode, builtin_call, builtin_function, is_builtin_namespace},
+            member::{external_functions, namespace, namespace_symbol, resolve_member, Namespace},
+            resolve_expression::expression,
+            selector::identifier_path,
+            ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+        using::bound_functions,
+    },
+};
+
+/// The arguments of a call, by position or by name
+#[derive(Clone, Copy)]
+pub(crate) enum Arguments<'a> {
+    Positional(&'a [pt::Expression]),
+    Named(&'a [pt::NamedArgument]),
+}
+
+impl Arguments<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Arguments::Positional(args) => args.len(),
+            Arguments::Named(args) => args.len(),
+        }
+    }
+}
+
+/// A function which may be called, with the expression naming it
+struct Candidate {
+    function_no: usize,
+    /// The contract instance for external calls
+    address: Option<Expression>,
+    /// The object bound to the first parameter
+    bound: Option<Expression>,
+    /// Whether the call goes through the most derived override
+    virtual_call: bool,
+}
+
+/// Resolve `callee(args)`, `callee({name: arg})` or, with the block,
+/// `callee{value: v}(args)`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn function_call(
+    loc: &pt::Loc,
+    callee: &pt::Expression,
+    args: Arguments,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let (callee, call_args) = match callee {
+        pt::Expression::FunctionCallBlock(_, callee, block) => {
+            let call_args = call_args(block, context, ctx, symtable, diagnostics)?;
+            (callee.as_ref(), Some(call_args))
+        }
+        callee => (callee, None),
+    };
+
+    if let Some(ty) = cast_type(callee, context, ctx) {
+        if let Some(call_args) = &call_args {
+            return no_call_args(call_args, diagnostics);
+        }
+        return type_call(loc, callee, ty, args, context, ctx, symtable, diagnostics);
+    }
+
+    if let Some(result) = builtin(loc, callee, args, context, ctx, symtable, diagnostics) {
+        if let Some(call_args) = &call_args {
+            return no_call_args(call_args, diagnostics);
+        }
+        return result;
+    }
+
+    if let Some(result) = user_type_call(loc, callee, args, context, ctx, symtable, diagnostics) {
+        if let Some(call_args) = &call_args {
+            return no_call_args(call_args, diagnostics);
+        }
+        return result;
+    }
+
+    let (candidates, object) = candidates(callee, context, ctx, symtable, diagnostics)?;
+    let name = match (callee, object) {
+        (pt::Expression::MemberAccess(_, _, member), Some(object)) if candidates.is_empty() => {
+            if let ("call" | "delegatecall" | "staticcall", Type::Address(_)) =
+                (member.name.as_str(), object.ty().deref_any())
+            {
+                return raw_call(
+                    loc,
+                    object,
+                    member,
+                    args,
+                    call_args.unwrap_or_default(),
+                    context,
+                    ctx,
+                    symtable,
+                    diagnostics,
+                );
+            }
+            if let (Some(call_args), false) = (&call_args, is_contract(&object)) {
+                return no_call_args(call_args, diagnostics);
+            }
+            if let ("push" | "pop" | "transfer" | "send", Arguments::Positional(args)) =
+                (member.name.as_str(), args)
+            {
+                return member_function_call(
+                    loc,
+                    object,
+                    member,
+                    args,
+                    context,
+                    ctx,
+                    symtable,
+                    diagnostics,
+                );
+            }
+            let function = resolve_member(&callee.loc(), object, member, ctx, diagnostics)?;
+            return function_pointer_call(
+                loc,
+                function,
+                args,
+                call_args,
+                context,
+                ctx,
+                symtable,
+                diagnostics,
+            );
+        }
+        (pt::Expression::Variable(id) | pt::Expression::MemberAccess(_, _, id), _)
+            if !candidates.is_empty() =>
+        {
+            id
+        }
+        _ => {
+            let function =
+                expression(callee, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+            return function_pointer_call(
+                loc,
+                function,
+                args,
+                call_args,
+                context,
+                ctx,
+                symtable,
+                diagnostics,
+            );
+        }
+    };
+
+    let params = candidates
+        .iter()
+        .map(|candidate| {
+            let func = &ctx.functions[candidate.function_no];
+            let skip = usize::from(candidate.bound.is_some());
+            func.params
+                .iter()
+                .skip(skip)
+                .map(|param| (param.id.clone(), param.ty.clone()))
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    let (index, mut resolved) =
+        match_overload(loc, &name.name, &params, args, context, ctx, symtable, diagnostics)?;
+    let candidate = candidates.into_iter().nth(index).unwrap();
+    let func = &ctx.functions[candidate.function_no];
+    let returns = returns(func.returns.iter().map(|param| param.ty.clone()).collect());
+    let path = pt::IdentifierPath { loc: name.loc, identifiers: vec![name.clone()] };
+
+    match candidate.address {
+        Some(address) => {
+            let call_args = call_args.unwrap_or_default();
+            if call_args.value.is_some() && !func.is_payable() {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    format!("sending value to function '{}' which is not payable", func.id),
+                ));
+                return Err(());
+            }
+            Ok(Expression::ExternalFunctionCall {
+                loc: *loc,
+                returns,
+                function: Box::new(Expression::ExternalFunction {
+                    loc: name.loc,
+                    ty: func.function_type(true),
+                    address: Box::new(address),
+                    function_no: candidate.function_no,
+                }),
+                args: resolved,
+                call_args,
+            })
+        }
+        None => {
+            if let Some(call_args) = &call_args {
+                return no_call_args(call_args, diagnostics);
+            }
+            if let Some(bound) = candidate.bound {
+                let ty = &func.params[0].ty;
+                resolved.insert(0, bound.cast(&bound.loc(), ty, true, ctx, diagnostics)?);
+            }
+            Ok(Expression::InternalFunctionCall {
+                loc: *loc,
+                returns,
+                function: Box::new(Expression::InternalFunction {
+                    loc: name.loc,
+                    id: path,
+                    ty: func.function_type(false),
+                    function_no: candidate.function_no,
+                    signature: candidate.virtual_call.then(|| func.signature.clone()),
+                }),
+                args: resolved,
+            })
+        }
+    }
+}
+
+/// The type named by the callee, for conversions and struct literals. A
+/// local variable hides a type of the same name.
+fn cast_type(callee: &pt::Expression, context: &ExprContext, ctx: &mut Context) -> Option<Type> {
+    match callee {
+        pt::Expression::Type(..) => (),
+        pt::Expression::Variable(_) | pt::Expression::MemberAccess(..) => {
+            let path = identifier_path(callee)?;
+            if is_local(context, &path.identifiers[0].name) {
+                return None;
+            }
+        }
+        _ => return None,
+    }
+
+    ctx.resolve_type(
+        context.no,
+        context.contract_no,
+        ResolveTypeContext::Casting,
+        callee,
+        &mut Diagnostics::default(),
+    )
+    .ok()
+}
+
+/// A conversion `T(x)`, or a struct literal `S(a, b)` or `S({a: x, b: y})`
+#[allow(clippy::too_many_arguments)]
+fn type_call(
+    loc: &pt::Loc,
+    callee: &pt::Expression,
+    ty: Type,
+    args: Arguments,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    if let Type::Struct(struct_ty) = &ty {
+        let decl = struct_ty.definition(ctx);
+        let name = decl.id.name.clone();
+        let fields = decl
+            .fields
+            .iter()
+            .map(|field| (field.id.clone(), field.ty.clone()))
+            .collect::<Vec<_>>();
+        let values =
+            resolve_arguments(loc, &name, &fields, args, context, ctx, symtable, diagnostics)?;
+        let id = identifier_path(callee)
+            .unwrap_or(pt::IdentifierPath { loc: callee.loc(), identifiers: Vec::new() });
+        let values = fields.into_iter().map(|(id, _)| id).zip(values).collect();
+        return Ok(Expression::StructLiteral { loc: *loc, id, ty, values });
+    }
+
+    let arg = match args {
+        Arguments::Positional([arg]) => arg,
+        _ => {
+            diagnostics.push(Diagnostic::error(
+                *loc,
+                format!(
+                    "conversion to '{}' expects 1 argument, {} given",
+                    ty.to_string(ctx),
+                    args.len()
+                ),
+            ));
+            return Err(());
+        }
+    };
+
+    let expr = expression(arg, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+    expr.cast(loc, &ty, false, ctx, diagnostics)
+}
+
+/// A call of a builtin function, if the callee names one
+#[allow(clippy::too_many_arguments)]
+fn builtin(
+    loc: &pt::Loc,
+    callee: &pt::Expression,
+    args: Arguments,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Option<Result<Expression, ()>> {
+    let (namespace, name) = match callee {
+        pt::Expression::Variable(id) => {
+            let is_symbol =
+                ctx.find_symbol(context.no, context.contract_no, &id.name, true).is_some();
+            if is_local(context, &id.name) || is_symbol {
+                return None;
+            }
+            (None, id)
+        }
+        pt::Expression::MemberAccess(_, object, id) => match object.as_ref() {
+            pt::Expression::Variable(ns)
+                if is_builtin_namespace(&ns.name) && !is_local(context, &ns.name) =>
+            {
+                (Some(ns.name.as_str()), id)
+            }
+            pt::Expression::Type(_, pt::Type::String) => (Some("string"), id),
+            pt::Expression::Type(_, pt::Type::DynamicBytes) => (Some("bytes"), id),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let Arguments::Positional(args) = args else {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            "builtin functions cannot be called with named arguments",
+        ));
+        return Some(Err(()));
+    };
+
+    if namespace == Some("abi") && name.name == "decode" {
+        return Some(abi_decode(loc, args, context, ctx, symtable, diagnostics));
+    }
+
+    match builtin_function(namespace, &name.name) {
+        Some(prototype) => {
+            Some(builtin_call(loc, prototype, args, context, ctx, symtable, diagnostics))
+        }
+        None if namespace.is_some() => {
+            diagnostics.push(Diagnostic::error(
+                name.loc,
+                format!("'{}' has no function '{}'", namespace.unwrap(), name.name),
+            ));
+            Some(Err(()))
+        }
+        None => None,
+    }
+}
+
+/// `T.wrap(v)` or `T.unwrap(t)` of a user defined value type `T`, if the
+/// callee names one
+#[allow(clippy::too_many_arguments)]
+fn user_type_call(
+    loc: &pt::Loc,
+    callee: &pt::Expression,
+    args: Arguments,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Option<Result<Expression, ()>> {
+    let pt::Expression::MemberAccess(_, object, member) = callee else {
+        return None;
+    };
+    let pt::Expression::Variable(id) = object.as_ref() else {
+        return None;
+    };
+    if is_local(context, &id.name) {
+        return None;
+    }
+    let Some(Symbol::UserType(_, type_no)) =
+        ctx.find_symbol(context.no, context.contract_no, &id.name, true)
+    else {
+        return None;
+    };
+
+    let ty = Type::UserType(*type_no);
+    let inner = ctx.user_types[*type_no].ty.clone();
+    let (kind, from, to) = match member.name.as_str() {
+        "wrap" => (Builtin::UserTypeWrap, inner, ty),
+        "unwrap" => (Builtin::UserTypeUnwrap, ty, inner),
+        _ => return None,
+    };
+
+    let Arguments::Positional([arg]) = args else {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!("'{}' expects 1 argument, {} given", member.name, args.len()),
+        ));
+        return Some(Err(()));
+    };
+
+    let expr = expression(arg, context, ctx, symtable, diagnostics, ResolveTo::Type(&from))
+        .and_then(|expr| expr.cast(&arg.loc(), &from, true, ctx, diagnostics));
+    Some(expr.map(|expr| Expression::Builtin { loc: *loc, tys: vec![to], kind, args: vec![expr] }))
+}
+
+/// The low level call of an address, `a.call(data)`, `a.delegatecall(data)`
+/// or `a.staticcall(data)`, which gives the success and the returned data.
+/// Only `call` can send value.
+#[allow(clippy::too_many_arguments)]
+fn raw_call(
+    loc: &pt::Loc,
+    address: Expression,
+    member: &pt::Identifier,
+    args: Arguments,
+    call_args: CallArgs,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let ty = match member.name.as_str() {
+        "delegatecall" => CallTy::Delegate,
+        "staticcall" => CallTy::Static,
+        _ => CallTy::Regular,
+    };
+
+    if let (Some(value), false) = (&call_args.value, ty == CallTy::Regular) {
+        diagnostics
+            .push(Diagnostic::error(value.loc(), format!("'{}' cannot send value", member.name)));
+        return Err(());
+    }
+
+    let Arguments::Positional([arg]) = args else {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!("'{}' expects 1 argument, {} given", member.name, args.len()),
+        ));
+        return Err(());
+    };
+
+    let data =
+        expression(arg, context, ctx, symtable, diagnostics, ResolveTo::Type(&Type::DynamicBytes))?
+            .cast(&arg.loc(), &Type::DynamicBytes, true, ctx, diagnostics)?;
+    let address = address.cast(&address.loc(), &Type::Address(false), true, ctx, diagnostics)?;
+
+    Ok(Expression::ExternalFunctionCallRaw {
+        loc: *loc,
+        ty,
+        address: Box::new(address),
+        args: Box::new(data),
+        call_args,
+    })
+}
+
+/// The functions the callee may name, none when it is not a function by
+/// name, such as a variable of a function type, and the object of a member
+/// access, which is resolved once
+fn candidates(
+    callee: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(Vec<Candidate>, Option<Expression>), ()> {
+    let direct = |function_no, virtual_call| Candidate {
+        function_no,
+        address: None,
+        bound: None,
+        virtual_call,
+    };
+
+    match callee {
+        pt::Expression::Variable(id) if is_local(context, &id.name) => Ok((Vec::new(), None)),
+        pt::Expression::Variable(id) => {
+            let functions = match ctx.find_symbol(context.no, context.contract_no, &id.name, true) {
+                Some(Symbol::Function(functions)) => functions
+                    .iter()
+                    .map(|(_, function_no)| {
+                        direct(*function_no, ctx.functions[*function_no].is_virtual)
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            Ok((functions, None))
+        }
+        pt::Expression::MemberAccess(_, object, member) => {
+            if let Some(namespace) = namespace(object, context, ctx) {
+                if matches!(namespace, Namespace::Builtin(_) | Namespace::Enum(_)) {
+                    return Ok((Vec::new(), None));
+                }
+                return match namespace_symbol(&namespace, member, context, ctx) {
+                    Some((Symbol::Function(functions), false)) => {
+                        let functions =
+                            functions.iter().map(|(_, function_no)| direct(*function_no, false));
+                        Ok((functions.collect(), None))
+                    }
+                    Some((Symbol::Function(_), true)) => {
+                        diagnostics.push(Diagnostic::error(
+                            member.loc,
+                            format!(
+                                "function '{}' of a contract which is not a base can only be called through an instance",
+                                member.name
+                            ),
+                        ));
+                        Err(())
+                    }
+                    _ => Ok((Vec::new(), None)),
+                };
+            }
+
+            let object =
+                expression(object, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+            let ty = object.ty().deref_any().clone();
+
+            if let Type::Contract(contract_no) = ty {
+                let functions = external_functions(ctx, contract_no, &member.name);
+                if !functions.is_empty() {
+                    let address = object.cast(&object.loc(), &ty, true, ctx, diagnostics)?;
+                    let functions = functions.into_iter().map(|function_no| Candidate {
+                        function_no,
+                        address: Some(address.clone()),
+                        bound: None,
+                        virtual_call: false,
+                    });
+                    return Ok((functions.collect(), None));
+                }
+            }
+
+            let bound = bound_functions(ctx, context.no, context.contract_no, &ty, &member.name);
+            let functions = bound.into_iter().map(|function_no| Candidate {
+                function_no,
+                address: None,
+                bound: Some(object.clone()),
+                virtual_call: false,
+            });
+            Ok((functions.collect(), Some(object)))
+        }
+        _ => Ok((Vec::new(), None)),
+    }
+}
+
+/// Resolve the arguments for each of the overloads, returning the index of
+/// the only one the arguments convert to
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn match_overload(
+    loc: &pt::Loc,
+    name: &str,
+    overloads: &[Vec<(Option<pt::Identifier>, Type)>],
+    args: Arguments,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(usize, Vec<Expression>), ()> {
+    if let [params] = overloads {
+        let resolved =
+            resolve_arguments(loc, name, params, args, context, ctx, symtable, diagnostics)?;
+        return Ok((0, resolved));
+    }
+
+    let mut matches = Vec::new();
+    // Why each overload does not match, at the argument which does not
+    let mut mismatches = Vec::new();
+    for (index, params) in overloads.iter().enumerate() {
+        let mut errors = Diagnostics::default();
+        match resolve_arguments(loc, name, params, args, context, ctx, symtable, &mut errors) {
+            Ok(resolved) => matches.push((index, resolved)),
+            Err(()) => {
+                let (loc, reason) = match errors.errors().first() {
+                    Some(error) => (error.loc, format!(": {}", error.message)),
+                    None => (*loc, String::new()),
+                };
+                let signature = overload_signature(name, params, ctx);
+                mismatches.push(Note { loc, message: format!("candidate '{signature}'{reason}") });
+            }
+        }
+    }
+
+    match matches.len() {
+        1 => Ok(matches.pop().unwrap()),
+        0 => {
+            diagnostics.push(
+                Diagnostic::builder(*loc, Level::Error)
+                    .ty(ErrorType::SyntaxError)
+                    .message(format!("no overload of function '{name}' matches the arguments"))
+                    .notes(mismatches)
+                    .build(),
+            );
+            Err(())
+        }
+        _ => {
+            let mut builder = Diagnostic::builder(*loc, Level::Error)
+                .ty(ErrorType::SyntaxError)
+                .message(format!("call to overloaded function '{name}' is ambiguous"));
+            for (index, _) in &matches {
+                let signature = overload_signature(name, &overloads[*index], ctx);
+                builder = builder.note(*loc, format!("candidate '{signature}'"));
+            }
+            diagnostics.push(builder.build());
+            Err(())
+        }
+    }
+}
+
+/// The name of an overload with the types of its parameters, as in `f(uint8,bool)`
+fn overload_signature(
+    name: &str,
+    params: &[(Option<pt::Identifier>, Type)],
+    ctx: &Context,
+) -> String {
+    let params = params.iter().map(|(_, ty)| ty.display(ctx).to_string()).collect::<Vec<_>>();
+    format!("{name}({})", params.join(","))
+}
+
+/// Resolve the arguments and convert them implicitly to the parameters. Named
+/// arguments are put in the order of the parameters.
+#[allow(clippy::too_many_arguments)]
+fn resolve_arguments(
+    loc: &pt::Loc,
+    name: &str,
+    params: &[(Option<pt::Identifier>, Type)],
+    args: Arguments,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Vec<Expression>, ()> {
+    if args.len() != params.len() {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!("function '{name}' expects {} arguments, {} given", params.len(), args.len()),
+        ));
+        return Err(());
+    }
+
+    let ordered = match args {
+        Arguments::Positional(args) => args.iter().collect::<Vec<_>>(),
+        Arguments::Named(args) => {
+            let mut ordered = Vec::new();
+            for (id, _) in params {
+                let param = id.as_ref().map(|id| id.name.as_str());
+                match args.iter().find(|arg| Some(arg.name.name.as_str()) == param) {
+                    Some(arg) => ordered.push(&arg.expr),
+                    None => {
+                        diagnostics.push(Diagnostic::error(
+                            *loc,
+                            format!(
+                                "missing argument '{}' to function '{name}'",
+                                param.unwrap_or("")
+                            ),
+                        ));
+                        return Err(());
+                    }
+                }
+            }
+            ordered
+        }
+    };
+
+    let mut resolved = Vec::new();
+    for (arg, (_, ty)) in ordered.into_iter().zip(params) {
+        let expr = expression(arg, context, ctx, symtable, diagnostics, ResolveTo::Type(ty))?;
+        let expr = expr.cast(&arg.loc(), ty, true, ctx, diagnostics)?;
+
+        // A literal which does not fit selects another overload
+        if let Expression::NumberLiteral { loc, ty, value } = &expr {
+            if let Some(diagnostic) = overflow_diagnostic(value, ty, *loc) {
+                diagnostics.push(diagnostic);
+                return Err(());
+            }
+        }
+        resolved.push(expr);
+    }
+
+    Ok(resolved)
+}
+
+/// Call an expression of a function type, or a member function of an array
+/// or address
+#[allow(clippy::too_many_arguments)]
+fn function_pointer_call(
+    loc: &pt::Loc,
+    function: Expression,
+    args: Arguments,
+    call_args: Option<CallArgs>,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let ty = function.ty().deref_any().clone();
+    let (params, returns_ty, external) = match ty {
+        Type::InternalFunction { params, returns, .. } => (params, returns, false),
+        Type::ExternalFunction { params, returns, .. } => (params, returns, true),
+        ty => {
+            diagnostics.push(Diagnostic::type_error(
+                function.loc(),
+                format!("expression of type '{}' is not a function", ty.to_string(ctx)),
+            ));
+            return Err(());
+        }
+    };
+
+    let params = params.into_iter().map(|ty| (None, ty)).collect::<Vec<_>>();
+    let args = resolve_arguments(loc, "", &params, args, context, ctx, symtable, diagnostics)?;
+    let function = function.cast(
+        &function.loc(),
+        &function.ty().deref_any().clone(),
+        true,
+        ctx,
+        diagnostics,
+    )?;
+
+    if external {
+        Ok(Expression::ExternalFunctionCall {
+            loc: *loc,
+            returns: returns(returns_ty),
+            function: Box::new(function),
+            args,
+            call_args: call_args.unwrap_or_default(),
+        })
+    } else {
+        if let Some(call_args) = &call_args {
+            return no_call_args(call_args, diagnostics);
+        }
+        Ok(Expression::InternalFunctionCall {
+            loc: *loc,
+            returns: returns(returns_ty),
+            function: Box::new(function),
+            args,
+        })
+    }
+}
+
+/// Resolve a member function of an array, `push` and `pop`, or an address,
+/// `transfer` and `send`, which is not named with a function of its own
+#[allow(clippy::too_many_arguments)]
+fn member_function_call(
+    loc: &pt::Loc,
+    object: Expression,
+    member: &pt::Identifier,
+    args: &[pt::Expression],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let object_ty = object.ty();
+    let ty = object_ty.deref_any().clone();
+    let dynamic = match &ty {
+        Type::Array(_, dims) => dims.last() == Some(&ArrayLength::Dynamic),
+        Type::DynamicBytes => true,
+        _ => false,
+    };
+
+    match (member.name.as_str(), args) {
+        ("push" | "pop", _) if dynamic && !object_ty.is_contract_storage() => {
+            diagnostics.push(Diagnostic::error(
+                *loc,
+                format!("'{}' is only available on storage arrays", member.name),
+            ));
+            Err(())
+        }
+        ("push", [] | [_]) if dynamic => {
+            let elem = ty.array_elem();
+            let mut resolved = vec![object];
+            if let [value] = args {
+                let expr =
+                    expression(value, context, ctx, symtable, diagnostics, ResolveTo::Type(&elem))?;
+                resolved.push(expr.cast(&value.loc(), &elem, true, ctx, diagnostics)?);
+            }
+            let ret =
+                if args.is_empty() { Type::StorageRef(false, Box::new(elem)) } else { Type::Void };
+            Ok(Expression::Builtin {
+                loc: *loc,
+                tys: vec![ret],
+                kind: Builtin::ArrayPush,
+                args: resolved,
+            })
+        }
+        ("pop", []) if dynamic => Ok(Expression::Builtin {
+            loc: *loc,
+            tys: vec![ty.array_elem()],
+            kind: Builtin::ArrayPop,
+            args: vec![object],
+        }),
+        ("transfer" | "send", [value]) if ty == Type::Address(true) => {
+            let expr = expression(
+                value,
+                context,
+                ctx,
+                symtable,
+                diagnostics,
+                ResolveTo::Type(&Type::Value),
+            )?;
+            let value = expr.cast(&value.loc(), &Type::Value, true, ctx, diagnostics)?;
+            let address = object.cast(&object.loc(), &ty, true, ctx, diagnostics)?;
+            let (kind, ret) = match member.name.as_str() {
+                "transfer" => (Builtin::PayableTransfer, Type::Void),
+                _ => (Builtin::PayableSend, Type::Bool),
+            };
+            Ok(Expression::Builtin { loc: *loc, tys: vec![ret], kind, args: vec![address, value] })
+        }
+        ("transfer" | "send", _) if ty == Type::Address(false) => {
+            diagnostics.push(Diagnostic::error(
+                *loc,
+                format!("'{}' is only available on 'address payable'", member.name),
+            ));
+            Err(())
+        }
+        _ => {
+            diagnostics.push(Diagnostic::error(
+                member.loc,
+                format!("'{}' has no member '{}'", ty.to_string(ctx), member.name),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// The call arguments in the block of `f{value: v, gas: g, salt: s}(args)`
+pub(crate) fn call_args(
+    block: &pt::Statement,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<CallArgs, ()> {
+    let pt::Statement::Args(_, args) = block else {
+        diagnostics.push(Diagnostic::error(block.loc(), "expected call arguments"));
+        return Err(());
+    };
+
+    let mut call_args = CallArgs::default();
+    for arg in args {
+        let (slot, ty) = match arg.name.name.as_str() {
+            "value" => (&mut call_args.value, Type::Value),
+            "gas" => (&mut call_args.gas, Type::Uint(64)),
+            "salt" => (&mut call_args.salt, Type::Bytes(32)),
+            name => {
+                diagnostics.push(Diagnostic::error(
+                    arg.name.loc,
+                    format!("'{name}' is not a valid call argument"),
+                ));
+                return Err(());
+            }
+        };
+        if slot.is_some() {
+            diagnostics.push(Diagnostic::error(
+                arg.loc,
+                format!("'{}' specified multiple times", arg.name.name),
+            ));
+            return Err(());
+        }
+        let expr =
+            expression(&arg.expr, context, ctx, symtable, diagnostics, ResolveTo::Type(&ty))?;
+        *slot = Some(Box::new(expr.cast(&arg.expr.loc(), &ty, true, ctx, diagnostics)?));
+    }
+
+    Ok(call_args)
+}
+
+/// Whether the expression is an instance of a contract
+fn is_contract(expr: &Expression) -> bool {
+    matches!(expr.ty().deref_any(), Type::Contract(_))
+}
+
+/// Whether the name is a local variable, which hides any other symbol
+fn is_local(context: &ExprContext, name: &str) -> bool {
+    context.active_scopes.iter().any(|scope| scope.names.contains_key(name))
+}
+
+/// Call arguments are only for external calls
+fn no_call_args(
+    call_args: &CallArgs,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let loc = [&call_args.value, &call_args.gas, &call_args.salt]
+        .into_iter()
+        .flatten()
+        .map(|arg| arg.loc())
+        .next()
+        .unwrap_or(pt::Loc::Builtin);
+    diagnostics.push(Diagnostic::error(loc, "call arguments can only be given to external calls"));
+    Err(())
+}
+
+/// The types a call returns, `void` when it returns nothing
+fn returns(tys: Vec<Type>) -> Vec<Type> {
+    if tys.is_empty() {
+        vec![Type::Void]
+    } else {
+        tys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: &str) -> pt::Expression {
+        pt::Expression::NumberLiteral(
+            pt::Loc::File(0, 0, 1),
+            value.to_string(),
+            String::new(),
+            None,
+        )
+    }
+
+    fn param(name: &str, ty: Type) -> (Option<pt::Identifier>, Type) {
+        (Some(pt::Identifier { loc: pt::Loc::Builtin, name: name.to_string() }), ty)
+    }
+
+    #[test]
+    fn test_match_overload() {
+        let mut ctx = Context::new();
+        let mut symtable = Symtable::default();
+        let mut context = ExprContext::default();
+        let overloads = [vec![param("a", Type::Uint(8))], vec![param("a", Type::Uint(16))]];
+        let mut resolve = |overloads: &[_], args, ctx: &mut Context| {
+            let mut diagnostics = Diagnostics::default();
+            let loc = pt::Loc::Builtin;
+            match_overload(
+                &loc,
+                "f",
+                overloads,
+                args,
+                &mut context,
+                ctx,
+                &mut symtable,
+                &mut diagnostics,
+            )
+            .map(|(index, _)| index)
+            .map_err(|_| diagnostics.first_error())
+        };
+
+        let args = [number("300")];
+        assert_eq!(resolve(&overloads, Arguments::Positional(&args), &mut ctx), Ok(1));
+        let args = [number("1")];
+        assert_eq!(
+            resolve(&overloads, Arguments::Positional(&args), &mut ctx).unwrap_err(),
+            "call to overloaded function 'f' is ambiguous"
+        );
+        let args = [number("-1")];
+        assert_eq!(
+            resolve(&overloads, Arguments::Positional(&args), &mut ctx).unwrap_err(),
+            "no overload of function 'f' matches the arguments"
+        );
+
+        // A single function reports why the arguments do not match
+        let args = [number("1"), number("2")];
+        assert_eq!(
+            resolve(&overloads[..1], Arguments::Positional(&args), &mut ctx).unwrap_err(),
+            "function 'f' expects 1 arguments, 2 given"
+        );
+
+        let params = [vec![param("a", Type::Uint(8)), param("b", Type::Bool)]];
+        let named = |name: &str, expr| pt::NamedArgument {
+            loc: pt::Loc::Builtin,
+            name: pt::Identifier { loc: pt::Loc::Builtin, name: name.to_string() },
+            expr,
+        };
+        let args = [
+            named("b", pt::Expression::BoolLiteral(pt::Loc::Builtin, true)),
+            named("a", number("7")),
+        ];
+        assert_eq!(resolve(&params, Arguments::Named(&args), &mut ctx), Ok(0));
+        let args = [named("a", number("7")), named("c", number("7"))];
+        assert_eq!(
+            resolve(&params, Arguments::Named(&args), &mut ctx).unwrap_err(),
+            "missing argument 'b' to function 'f'"
+        );
+    }
+
+    #[test]
+    fn test_overload_notes() {
+        let mut ctx = Context::new();
+        let mut symtable = Symtable::default();
+        let mut context = ExprContext::default();
+        let mut notes = |overloads: &[_], value| {
+            let mut diagnostics = Diagnostics::default();
+            let args = [number(value)];
+            let result = match_overload(
+                &pt::Loc::Builtin,
+                "f",
+                overloads,
+                Arguments::Positional(&args),
+                &mut context,
+                &mut ctx,
+                &mut symtable,
+                &mut diagnostics,
+            );
+            assert!(result.is_err());
+            let notes = &diagnostics.errors()[0].notes;
+            notes.iter().map(|note| note.message.clone()).collect::<Vec<_>>()
+        };
+
+        let overloads = [vec![param("a", Type::Uint(8))], vec![param("a", Type::Bool)]];
+        assert_eq!(
+            notes(&overloads, "300"),
+            [
+                "candidate 'f(uint8)': value 300 does not fit into type uint8.",
+                "candidate 'f(bool)': expected 'bool', found integer",
+            ]
+        );
+
+        let overloads = [vec![param("a", Type::Uint(8))], vec![param("a", Type::Uint(16))]];
+        assert_eq!(notes(&overloads, "1"), ["candidate 'f(uint8)'", "candidate 'f(uint16)'"]);
+    }
+
+    /// The errors of checking the contract with the function in the source
+    fn errors(src: &str) -> Vec<String> {
+        let contents = format!("type Price is uint128; contract C {{ {src} }}");
+        let input = crate::Input::Source { name: "test.sol".into(), contents };
+        let diagnostics = crate::check(&input, &crate::config::Config::default());
+        diagnostics.errors().into_iter().map(|error| error.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_user_type_call() {
+        let src = "function f(uint128 a) public pure returns (uint128) {
+            Price p = Price.wrap(a);
+            return Price.unwrap(p);
+        }";
+        assert!(errors(src).is_empty());
+
+        let src = "function f(uint256 a) public pure { Price.wrap(a); }";
+        assert_eq!(errors(src), ["implicit conversion would truncate from 'uint256' to 'uint128'"]);
+        let src = "function f() public pure { Price.wrap(); }";
+        assert_eq!(errors(src), ["'wrap' expects 1 argument, 0 given"]);
+    }
+
+    #[test]
+    fn test_raw_call() {
+        let src = "function f(address a) public returns (bool ok, bytes memory data) {
+            (ok, data) = a.call{value: 1}(\"\");
+            (ok, ) = a.staticcall(abi.encode(1));
+        }";
+        assert!(errors(src).is_empty());
+
+        let src = "function f(address a) public { a.delegatecall{value: 1}(\"\"); }";
+        assert_eq!(errors(src), ["'delegatecall' cannot send value"]);
+    }
+}
@@ -0,0 +1,916 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Number literals and their conversions. A literal takes the type it is
+//! resolved to when it fits, else the smallest integer type holding it. The
+//! special rules of Solidity are:
+//!
+//! - a decimal literal is never implicitly a `bytesN`, but a hex literal with exactly `N` bytes of
+//!   digits is;
+//! - the literal `0` converts to any `bytesN`;
+//! - a literal is implicitly an `address` only when it is an address literal with a valid EIP-55
+//!   checksum.
+
+use std::borrow::Cow;
+
+use num_bigint::{BigInt, Sign};
+use num_rational::Ratio;
+use num_traits::{Num, One, Zero};
+
+use crate::{
+    abi::ethereum::keccak256,
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{ArrayLength, Expression, RetrieveType, Type},
+        context::Context,
+        expression::{
+            coerce::coerce_types, resolve_expression::expression, strings::unescape, ExprContext,
+            ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// Largest exponent of a decimal literal, as larger ones overflow any type
+const MAX_EXPONENT: u32 = 1024;
+
+/// Resolve a decimal number literal, e.g. `1_000`, `5e18` or `2 days`
+pub(crate) fn number_literal(
+    loc: &pt::Loc,
+    integer: &str,
+    exp: &str,
+    unit: &Option<pt::Identifier>,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    if let Some((integer, fraction)) = integer.split_once('.') {
+        return rational_number_literal(
+            loc,
+            integer,
+            fraction,
+            exp,
+            unit,
+            ctx,
+            diagnostics,
+            resolve_to,
+        );
+    }
+
+    let mut value = BigInt::from_str_radix(integer, 10).unwrap();
+
+    if !exp.is_empty() {
+        let exp = exp.parse::<i64>().ok().filter(|exp| exp.unsigned_abs() <= MAX_EXPONENT as u64);
+        let Some(exp) = exp else {
+            diagnostics.push(Diagnostic::error(*loc, "exponent of number literal is too large"));
+            return Err(());
+        };
+        let power = BigInt::from(10).pow(exp.unsigned_abs() as u32);
+
+        if exp >= 0 {
+            value *= power;
+        } else if (&value % &power).is_zero() {
+            value /= power;
+        } else {
+            diagnostics.push(Diagnostic::error(*loc, "number literal is not an integer"));
+            return Err(());
+        }
+    }
+
+    if let Some(unit) = unit {
+        value *= unit_multiplier(unit, diagnostics)?;
+    }
+
+    bigint_to_expression(loc, &value, ctx, diagnostics, resolve_to, None)
+}
+
+/// Resolve a hex number literal, e.g. `0xff` or `0x00_01`
+pub(crate) fn hex_number_literal(
+    loc: &pt::Loc,
+    n: &str,
+    unit: &Option<pt::Identifier>,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    if unit.is_some() {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            "hexadecimal numbers cannot be used with unit denominations",
+        ));
+        return Err(());
+    }
+
+    // from_str_radix does not like the 0x prefix
+    let digits: String = n.chars().skip(2).filter(|c| *c != '_').collect();
+    let value = BigInt::from_str_radix(&digits, 16).unwrap_or_default();
+
+    // A hex literal is a bytesN when it has exactly N bytes of digits
+    if let ResolveTo::Type(Type::Bytes(length)) = resolve_to {
+        let expected = *length as usize * 2;
+        return if !value.is_zero() && digits.len() != expected {
+            diagnostics.push(Diagnostic::cast_error(
+                *loc,
+                format!("hex literal {n} must be {expected} digits for type 'bytes{length}'"),
+            ));
+            Err(())
+        } else {
+            Ok(Expression::NumberLiteral { loc: *loc, ty: Type::Bytes(*length), value })
+        };
+    }
+
+    bigint_to_expression(loc, &value, ctx, diagnostics, resolve_to, Some(digits.len()))
+}
+
+/// Resolve a number literal with a fraction, e.g. `1.5 ether` or `0.01e3`.
+/// It is an integer literal when the fraction cancels out, and else a
+/// rational one, which only converts to an integer type explicitly.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rational_number_literal(
+    loc: &pt::Loc,
+    integer: &str,
+    fraction: &str,
+    exp: &str,
+    unit: &Option<pt::Identifier>,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let integer = integer.replace('_', "");
+    let fraction = fraction.replace('_', "");
+    let digits = BigInt::from_str_radix(&format!("{integer}{fraction}"), 10).unwrap();
+    let mut value = Ratio::new(digits, BigInt::from(10).pow(fraction.len() as u32));
+
+    if !exp.is_empty() {
+        let exp = exp.parse::<i64>().ok().filter(|exp| exp.unsigned_abs() <= MAX_EXPONENT as u64);
+        let Some(exp) = exp else {
+            diagnostics.push(Diagnostic::error(*loc, "exponent of number literal is too large"));
+            return Err(());
+        };
+        let power = Ratio::from_integer(BigInt::from(10).pow(exp.unsigned_abs() as u32));
+        value = if exp >= 0 { value * power } else { value / power };
+    }
+
+    if let Some(unit) = unit {
+        value *= Ratio::from_integer(unit_multiplier(unit, diagnostics)?.into());
+    }
+
+    if value.is_integer() {
+        return bigint_to_expression(loc, &value.to_integer(), ctx, diagnostics, resolve_to, None);
+    }
+
+    Ok(Expression::RationalNumberLiteral { loc: *loc, ty: Type::Rational, value })
+}
+
+/// The value of one of the unit denomination
+fn unit_multiplier(unit: &pt::Identifier, diagnostics: &mut dyn DiagnosticSink) -> Result<u64, ()> {
+    match unit.name.as_str() {
+        "wei" | "seconds" => Ok(1),
+        "gwei" => Ok(1_000_000_000),
+        "ether" => Ok(1_000_000_000_000_000_000),
+        "minutes" => Ok(60),
+        "hours" => Ok(3_600),
+        "days" => Ok(86_400),
+        "weeks" => Ok(604_800),
+        // Reported by the legacy syntax checker
+        "years" => Ok(31_536_000),
+        name => {
+            diagnostics.push(Diagnostic::error(
+                unit.loc,
+                format!("'{name}' is not a valid unit denomination"),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// Resolve string literals, e.g. `"abc" 'def'`. The parts are concatenated.
+/// A string literal is a `string` unless it is resolved to `bytes` or a
+/// `bytesN`; only `unicode` literals may contain characters which are not
+/// ASCII.
+pub(crate) fn string_literal(
+    v: &[pt::StringLiteral],
+    no: usize,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let mut value = Vec::new();
+    let mut valid = true;
+
+    for literal in v {
+        if !literal.unicode && !literal.string.is_ascii() {
+            diagnostics.push(Diagnostic::error(
+                literal.loc,
+                "invalid characters in string literal, use a 'unicode' literal for characters which are not ASCII",
+            ));
+            valid = false;
+            continue;
+        }
+
+        // Skip the quote, or the `unicode` prefix and the quote
+        let start = literal.loc.start() + if literal.unicode { 8 } else { 1 };
+        let (ok, bytes) = unescape(&literal.string, start, no, diagnostics);
+        valid &= ok;
+        value.extend(bytes);
+    }
+
+    if !valid {
+        return Err(());
+    }
+
+    let loc = v[0].loc.with_end_from(&v[v.len() - 1].loc);
+    let ty = match resolve_to {
+        ResolveTo::Type(Type::DynamicBytes) => Type::DynamicBytes,
+        ResolveTo::Type(Type::Bytes(_)) => Type::Bytes(value.len().clamp(1, 32) as u8),
+        _ => Type::String,
+    };
+
+    Ok(Expression::BytesLiteral { loc, ty, value })
+}
+
+/// Resolve hex string literals, e.g. `hex"00ff" hex'01'`. The parts are
+/// concatenated into a `bytesN`, or a `bytes` when resolved to it or when
+/// longer than 32 bytes.
+pub(crate) fn hex_literal(
+    v: &[pt::HexLiteral],
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let mut value = Vec::new();
+    let mut valid = true;
+
+    for literal in v {
+        // The parser strips the prefix, quotes and separators
+        if literal.hex.len() % 2 != 0 {
+            diagnostics.push(Diagnostic::error(
+                literal.loc,
+                format!("hex string \"{}\" has an odd number of digits", literal.hex),
+            ));
+            valid = false;
+            continue;
+        }
+
+        value.extend(
+            (0..literal.hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&literal.hex[i..i + 2], 16).unwrap()),
+        );
+    }
+
+    if !valid {
+        return Err(());
+    }
+
+    let loc = v[0].loc.with_end_from(&v[v.len() - 1].loc);
+    let ty = match resolve_to {
+        ResolveTo::Type(Type::DynamicBytes) => Type::DynamicBytes,
+        _ if value.len() > 32 => Type::DynamicBytes,
+        _ => Type::Bytes(value.len().max(1) as u8),
+    };
+
+    Ok(Expression::BytesLiteral { loc, ty, value })
+}
+
+/// Resolve an address literal, e.g. `0x5B38Da6a701c568545dCfcB03FcB875f56beddC4`,
+/// which must have a valid checksum
+pub(crate) fn address_literal(
+    loc: &pt::Loc,
+    address: &str,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let expected = to_hexstr_eip55(address);
+    if expected != address {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!("address literal has incorrect checksum, expected '{expected}'"),
+        ));
+        return Err(());
+    }
+
+    let value = BigInt::from_str_radix(&address[2..], 16).unwrap();
+    Ok(Expression::NumberLiteral { loc: *loc, ty: Type::Address(false), value })
+}
+
+/// Resolve an array literal, `[a, b, c]`, a fixed length array in memory. The
+/// elements have the element type of the array the literal is resolved to,
+/// or else their common type, and are converted to it.
+pub(crate) fn array_literal(
+    loc: &pt::Loc,
+    exprs: &[pt::Expression],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    if exprs.is_empty() {
+        diagnostics.push(Diagnostic::error(*loc, "array literal must have at least one element"));
+        return Err(());
+    }
+
+    let hint = match resolve_to {
+        ResolveTo::Type(ty @ Type::Array(..)) => Some(ty.array_elem()),
+        _ => None,
+    };
+    let elem_resolve_to = hint.as_ref().map_or(ResolveTo::Unknown, ResolveTo::Type);
+    let mut values = Vec::new();
+    for expr in exprs {
+        values.push(expression(expr, context, ctx, symtable, diagnostics, elem_resolve_to)?);
+    }
+
+    let elem_ty = match hint {
+        Some(ty) => ty,
+        None => common_elem_type(&values, ctx, diagnostics)?,
+    };
+
+    let values = values
+        .into_iter()
+        .map(|value| value.cast(&value.loc(), &elem_ty, true, ctx, diagnostics))
+        .collect::<Result<Vec<_>, ()>>()?;
+
+    let length = ArrayLength::Fixed(BigInt::from(values.len()));
+    let (ty, dimensioctx) = match elem_ty {
+        Type::Array(elem, mut dims)
+            if dims.iter().all(|dim| matches!(dim, ArrayLength::Fixed(_))) =>
+        {
+            let mut dimensioctx = dims
+                .iter()
+                .map(|dim| match dim {
+                    ArrayLength::Fixed(n) => u32::try_from(n).unwrap_or(u32::MAX),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>();
+            dimensioctx.push(values.len() as u32);
+            dims.push(length);
+            (Type::Array(elem, dims), dimensioctx)
+        }
+        elem_ty => (Type::Array(Box::new(elem_ty), vec![length]), vec![values.len() as u32]),
+    };
+
+    Ok(Expression::ArrayLiteral { loc: *loc, ty, dimensioctx, values })
+}
+
+/// The common type of the elements of an array literal. The elements are
+/// coerced one after the other, the latest element of the common type
+/// standing for those before it, so that literals only take the type of an
+/// element which is not a literal.
+fn common_elem_type(
+    values: &[Expression],
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Type, ()> {
+    let is_literal = |expr: &Expression| matches!(expr, Expression::NumberLiteral { .. });
+    let mut common = Cow::Borrowed(&values[0]);
+
+    for value in &values[1..] {
+        let Some(ty) = coerce_types(&common, value, ctx) else {
+            diagnostics.push(Diagnostic::type_error(
+                value.loc(),
+                format!(
+                    "array literal element of type '{}' is incompatible with the elements of type '{}' before it",
+                    value.ty().deref_any().to_string(ctx),
+                    common.ty().deref_any().to_string(ctx)
+                ),
+            ));
+            return Err(());
+        };
+
+        if *value.ty().deref_any() == ty && (is_literal(&common) || !is_literal(value)) {
+            common = Cow::Borrowed(value);
+        } else if *common.ty().deref_any() != ty {
+            common = Cow::Owned(Expression::Variable { loc: value.loc(), ty, var_no: 0 });
+        }
+    }
+
+    Ok(common.ty().deref_any().clone())
+}
+
+/// The EIP-55 mixed case checksum encoding of the `0x` prefixed address
+pub fn to_hexstr_eip55(address: &str) -> String {
+    let digits = address.trim_start_matches("0x").to_ascii_lowercase();
+    let hash = keccak256(digits.as_bytes());
+
+    let checksummed: String = digits
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            // Uppercase the letters whose nibble of the hash is 8 or more
+            let byte = hash[i / 2];
+            let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0xf };
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    format!("0x{checksummed}")
+}
+
+/// Give the value the type it is resolved to, or else the smallest integer
+/// type which holds it. The length of the digits of hex literals sizes their
+/// type, so `0x0001` is a `uint16`.
+pub(crate) fn bigint_to_expression(
+    loc: &pt::Loc,
+    value: &BigInt,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+    hex_len: Option<usize>,
+) -> Result<Expression, ()> {
+    if let ResolveTo::Type(ty) = resolve_to {
+        match ty {
+            // The overflow is checked where the expression is converted, once
+            // it is folded, see `check_constant_overflow`
+            Type::Uint(_) | Type::Int(_) => {
+                return Ok(Expression::NumberLiteral {
+                    loc: *loc,
+                    ty: ty.clone(),
+                    value: value.clone(),
+                })
+            }
+            // The value sent with a call is an unsigned integer of its own width
+            Type::Value => {
+                return Ok(Expression::NumberLiteral {
+                    loc: *loc,
+                    ty: Type::Uint(ctx.value_length as u16 * 8),
+                    value: value.clone(),
+                })
+            }
+            Type::Bytes(_) if value.is_zero() => {
+                return Ok(Expression::NumberLiteral {
+                    loc: *loc,
+                    ty: ty.clone(),
+                    value: value.clone(),
+                })
+            }
+            Type::Bytes(length) => {
+                diagnostics.push(Diagnostic::cast_error(
+                    *loc,
+                    format!(
+                        "decimal literal is not implicitly convertible to 'bytes{length}', use a hex literal of {length} bytes"
+                    ),
+                ));
+                return Err(());
+            }
+            Type::Address(_) => {
+                diagnostics.push(Diagnostic::cast_error(
+                    *loc,
+                    format!(
+                        "number literal is not implicitly convertible to '{}', use an address literal with a valid checksum",
+                        ty.to_string(ctx)
+                    ),
+                ));
+                return Err(());
+            }
+            Type::Unresolved | Type::Rational => (),
+            _ => {
+                diagnostics.push(Diagnostic::cast_error(
+                    *loc,
+                    format!("expected '{}', found integer", ty.to_string(ctx)),
+                ));
+                return Err(());
+            }
+        }
+    }
+
+    // A negative value needs a bit for the sign, but -2^(n-1) fits in n bits
+    let bits = match hex_len {
+        Some(len) => len.div_ceil(2) as u64 * 8,
+        None if value.sign() == Sign::Minus => (value + 1u8).bits() + 1,
+        None => value.bits(),
+    };
+
+    if bits > 256 {
+        diagnostics.push(Diagnostic::error(*loc, format!("{value} is too large")));
+        return Err(());
+    }
+
+    let bits = bits.max(1).next_multiple_of(8) as u16;
+    let ty = if value.sign() == Sign::Minus { Type::Int(bits) } else { Type::Uint(bits) };
+
+    Ok(Expression::NumberLiteral { loc: *loc, ty, value: value.clone() })
+}
+
+impl Expression {
+    /// Cast a literal to another type, folding the conversion into a new literal.
+    /// Returns `None` when the expression is not a literal, or the conversion is
+    /// not specific to literals.
+    pub(crate) fn cast_literal(
+        &self,
+        loc: &pt::Loc,
+        to: &Type,
+        implicit: bool,
+        ctx: &Context,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Option<Result<Expression, ()>> {
+        let literal = |ty: &Type, value: BigInt| {
+            Some(Ok(Expression::NumberLiteral { loc: *loc, ty: ty.clone(), value }))
+        };
+        let error = |diagnostics: &mut dyn DiagnosticSink, message: String| {
+            diagnostics.push(Diagnostic::cast_error(*loc, message));
+            Some(Err(()))
+        };
+        // As in solc, a literal is only converted explicitly to the integer
+        // types it fits in, but for the two's complement of negative numbers
+        let not_fitting = |diagnostics: &mut dyn DiagnosticSink, value: &BigInt| {
+            error(
+                diagnostics,
+                format!(
+                    "explicit conversion of {value} to '{}' not allowed, as it does not fit",
+                    to.to_string(ctx)
+                ),
+            )
+        };
+
+        match (self, to) {
+            (Expression::NumberLiteral { ty: from, value, .. }, Type::Uint(bits))
+                if matches!(from, Type::Uint(_) | Type::Int(_)) =>
+            {
+                if value.sign() == Sign::Minus {
+                    if implicit {
+                        return error(
+                            diagnostics,
+                            format!(
+                                "implicit conversion cannot change negative number to '{}'",
+                                to.to_string(ctx)
+                            ),
+                        );
+                    }
+                    // The two's complement, truncated to the width
+                    let mut bytes = value.to_signed_bytes_le();
+                    bytes.resize(*bits as usize / 8, 0xff);
+                    literal(to, BigInt::from_bytes_le(Sign::Plus, &bytes))
+                } else if implicit && value.bits() > *bits as u64 {
+                    error(
+                        diagnostics,
+                        format!(
+                            "implicit conversion would truncate from '{}' to '{}'",
+                            from.to_string(ctx),
+                            to.to_string(ctx)
+                        ),
+                    )
+                } else if value.bits() > *bits as u64 {
+                    not_fitting(diagnostics, value)
+                } else {
+                    literal(to, value.clone())
+                }
+            }
+            (Expression::NumberLiteral { ty: from, value, .. }, Type::Int(bits))
+                if matches!(from, Type::Uint(_) | Type::Int(_)) =>
+            {
+                let min = -(BigInt::one() << (*bits - 1));
+                if implicit && value.bits() >= *bits as u64 {
+                    error(
+                        diagnostics,
+                        format!(
+                            "implicit conversion would truncate from '{}' to '{}'",
+                            from.to_string(ctx),
+                            to.to_string(ctx)
+                        ),
+                    )
+                } else if *value < min || *value >= -&min {
+                    not_fitting(diagnostics, value)
+                } else {
+                    literal(to, value.clone())
+                }
+            }
+            (Expression::NumberLiteral { ty: from, value, .. }, Type::Bytes(length))
+                if matches!(from, Type::Uint(_) | Type::Int(_)) =>
+            {
+                if value.is_zero() {
+                    literal(to, value.clone())
+                } else if implicit {
+                    error(
+                        diagnostics,
+                        format!(
+                            "implicit conversion from '{}' to 'bytes{length}' not allowed, use a hex literal of {length} bytes",
+                            from.to_string(ctx)
+                        ),
+                    )
+                } else if value.sign() == Sign::Minus {
+                    error(
+                        diagnostics,
+                        format!("negative number cannot be converted to type 'bytes{length}'"),
+                    )
+                } else if value.bits().div_ceil(8) != *length as u64 {
+                    error(
+                        diagnostics,
+                        format!(
+                            "number of {} bytes cannot be converted to type 'bytes{length}'",
+                            value.bits().div_ceil(8)
+                        ),
+                    )
+                } else {
+                    literal(to, value.clone())
+                }
+            }
+            (Expression::NumberLiteral { ty: from, value, .. }, Type::Address(_))
+                if matches!(from, Type::Uint(_) | Type::Int(_)) =>
+            {
+                if implicit {
+                    error(
+                        diagnostics,
+                        format!(
+                            "implicit conversion from '{}' to '{}' not allowed",
+                            from.to_string(ctx),
+                            to.to_string(ctx)
+                        ),
+                    )
+                } else if value.sign() == Sign::Minus ||
+                    value.bits() > ctx.address_length as u64 * 8
+                {
+                    error(
+                        diagnostics,
+                        format!(
+                            "number does not fit in an address of {} bytes",
+                            ctx.address_length
+                        ),
+                    )
+                } else {
+                    literal(to, value.clone())
+                }
+            }
+            (Expression::NumberLiteral { ty: from, value, .. }, Type::Enum(enum_no))
+                if matches!(from, Type::Uint(_) | Type::Int(_)) =>
+            {
+                let decl = &ctx.enums[*enum_no];
+                if implicit {
+                    error(
+                        diagnostics,
+                        format!(
+                            "implicit conversion from '{}' to '{}' not allowed",
+                            from.to_string(ctx),
+                            to.to_string(ctx)
+                        ),
+                    )
+                } else if value.sign() == Sign::Minus || *value >= BigInt::from(decl.values.len()) {
+                    error(
+                        diagnostics,
+                        format!("enum '{}' has no value with ordinal {value}", decl.id),
+                    )
+                } else {
+                    literal(to, value.clone())
+                }
+            }
+            // Literal strings can be implicitly lengthened, but not truncated
+            (Expression::BytesLiteral { value, .. }, Type::Bytes(length)) => {
+                if implicit && value.len() > *length as usize {
+                    error(
+                        diagnostics,
+                        format!("implicit conversion to 'bytes{length}' would truncate"),
+                    )
+                } else {
+                    let mut bytes = value.clone();
+                    bytes.resize(*length as usize, 0);
+                    Some(Ok(Expression::BytesLiteral { loc: *loc, ty: to.clone(), value: bytes }))
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+
+    fn number(value: i64) -> Expression {
+        Expression::NumberLiteral { loc: pt::Loc::Builtin, ty: Type::Uint(8), value: value.into() }
+    }
+
+    fn ty(expr: Result<Expression, ()>) -> Type {
+        match expr {
+            Ok(Expression::NumberLiteral { ty, .. }) => ty,
+            other => panic!("not a number literal: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_eip55() {
+        // The test vectors of EIP-55
+        for address in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            assert_eq!(to_hexstr_eip55(&address.to_lowercase()), address);
+        }
+    }
+
+    #[test]
+    fn test_address_literal() {
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+
+        let address = "0x5B38Da6a701c568545dCfcB03FcB875f56beddC4";
+        assert_eq!(ty(address_literal(&loc, address, &mut diagnostics)), Type::Address(false));
+        assert!(!diagnostics.any_errors());
+
+        let lowercase = address.to_lowercase();
+        assert!(address_literal(&loc, &lowercase, &mut diagnostics).is_err());
+        assert!(diagnostics.contains_message(&format!(
+            "address literal has incorrect checksum, expected '{address}'"
+        )));
+    }
+
+    #[test]
+    fn test_literal_types() {
+        let ctx = Context::new();
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+        let mut decimal = |integer, exp, resolve_to| {
+            number_literal(&loc, integer, exp, &None, &ctx, &mut diagnostics, resolve_to)
+        };
+
+        assert_eq!(ty(decimal("0", "", ResolveTo::Unknown)), Type::Uint(8));
+        assert_eq!(ty(decimal("256", "", ResolveTo::Unknown)), Type::Uint(16));
+        assert_eq!(ty(decimal("-128", "", ResolveTo::Unknown)), Type::Int(8));
+        assert_eq!(ty(decimal("-129", "", ResolveTo::Unknown)), Type::Int(16));
+        assert_eq!(ty(decimal("1", "18", ResolveTo::Unknown)), Type::Uint(64));
+        assert_eq!(ty(decimal("1", "", ResolveTo::Type(&Type::Int(64)))), Type::Int(64));
+        assert_eq!(ty(decimal("2500", "-2", ResolveTo::Unknown)), Type::Uint(8));
+        assert!(decimal("1", "78", ResolveTo::Unknown).is_err());
+        assert!(decimal("25", "-1", ResolveTo::Unknown).is_err());
+
+        // Only zero is a bytesN or address among the decimal literals
+        assert_eq!(ty(decimal("0", "", ResolveTo::Type(&Type::Bytes(4)))), Type::Bytes(4));
+        assert!(decimal("1", "", ResolveTo::Type(&Type::Bytes(1))).is_err());
+        assert!(decimal("0", "", ResolveTo::Type(&Type::Address(false))).is_err());
+
+        let mut hex =
+            |n, resolve_to| hex_number_literal(&loc, n, &None, &ctx, &mut diagnostics, resolve_to);
+        assert_eq!(ty(hex("0x0001", ResolveTo::Unknown)), Type::Uint(16));
+        assert_eq!(ty(hex("0x12_34", ResolveTo::Type(&Type::Bytes(2)))), Type::Bytes(2));
+        assert_eq!(ty(hex("0x00", ResolveTo::Type(&Type::Bytes(32)))), Type::Bytes(32));
+        assert!(hex("0x1234", ResolveTo::Type(&Type::Bytes(4))).is_err());
+        assert!(
+            diagnostics.contains_message("hex literal 0x1234 must be 8 digits for type 'bytes4'")
+        );
+        assert!(diagnostics.contains_message(
+            "decimal literal is not implicitly convertible to 'bytes1', use a hex literal of 1 bytes"
+        ));
+    }
+
+    #[test]
+    fn test_cast_literal() {
+        let ctx = Context::new();
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+        let mut cast = |expr: Expression, to: Type, implicit| {
+            expr.cast_literal(&loc, &to, implicit, &ctx, &mut diagnostics).unwrap()
+        };
+
+        assert_eq!(ty(cast(number(0), Type::Bytes(8), true)), Type::Bytes(8));
+        assert!(cast(number(1), Type::Bytes(1), true).is_err());
+        assert_eq!(ty(cast(number(1), Type::Bytes(1), false)), Type::Bytes(1));
+        assert!(cast(number(1), Type::Bytes(2), false).is_err());
+
+        assert!(cast(number(0), Type::Address(false), true).is_err());
+        assert_eq!(ty(cast(number(0), Type::Address(false), false)), Type::Address(false));
+
+        assert!(cast(number(-1), Type::Uint(8), true).is_err());
+        match cast(number(-1), Type::Uint(16), false) {
+            Ok(Expression::NumberLiteral { value, .. }) => assert_eq!(value, BigInt::from(0xffff)),
+            other => panic!("{other:?}"),
+        }
+        assert!(cast(number(256), Type::Uint(8), true).is_err());
+        assert!(cast(number(128), Type::Int(8), true).is_err());
+        assert!(cast(number(256), Type::Uint(8), false).is_err());
+        assert!(cast(number(128), Type::Int(8), false).is_err());
+        assert_eq!(ty(cast(number(-128), Type::Int(8), false)), Type::Int(8));
+
+        let string = Expression::BytesLiteral { loc, ty: Type::Bytes(2), value: b"ab".to_vec() };
+        match cast(string.clone(), Type::Bytes(4), true) {
+            Ok(Expression::BytesLiteral { value, .. }) => assert_eq!(value, b"ab\0\0"),
+            other => panic!("{other:?}"),
+        }
+        assert!(cast(string, Type::Bytes(1), true).is_err());
+
+        assert!(number(1).cast_literal(&loc, &Type::Bool, true, &ctx, &mut diagnostics).is_none());
+
+        let errors = diagnostics.errors();
+        assert!(errors.iter().any(|error| {
+            error.message == "explicit conversion of 128 to 'int8' not allowed, as it does not fit"
+        }));
+    }
+
+    #[test]
+    fn test_rational_literal() {
+        let ctx = Context::new();
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+        let mut rational = |integer, fraction, exp, unit: Option<&str>| {
+            let unit = unit.map(|name| pt::Identifier { loc, name: name.to_string() });
+            let resolve_to = ResolveTo::Unknown;
+            rational_number_literal(
+                &loc,
+                integer,
+                fraction,
+                exp,
+                &unit,
+                &ctx,
+                &mut diagnostics,
+                resolve_to,
+            )
+        };
+
+        // A fraction which cancels out is an integer
+        assert_eq!(ty(rational("1", "5", "", Some("ether"))), Type::Uint(64));
+        assert_eq!(ty(rational("0", "25", "2", None)), Type::Uint(8));
+        match rational("2", "5", "", None) {
+            Ok(Expression::RationalNumberLiteral { ty: Type::Rational, value, .. }) => {
+                assert_eq!(value, Ratio::new(BigInt::from(5), BigInt::from(2)))
+            }
+            other => panic!("{other:?}"),
+        }
+        assert!(rational("1", "5", "", Some("lightyears")).is_err());
+    }
+
+    #[test]
+    fn test_string_and_hex_literals() {
+        let mut diagnostics = Diagnostics::default();
+        let string = |string: &str, unicode| pt::StringLiteral {
+            loc: pt::Loc::File(0, 0, string.len() + 2),
+            unicode,
+            string: string.to_string(),
+        };
+        let bytes = |expr: Result<Expression, ()>| match expr {
+            Ok(Expression::BytesLiteral { ty, value, .. }) => (ty, value),
+            other => panic!("not a bytes literal: {other:?}"),
+        };
+
+        let parts = [string("ab", false), string("c\\n", false)];
+        let literal = string_literal(&parts, 0, &mut diagnostics, ResolveTo::Unknown);
+        assert_eq!(bytes(literal), (Type::String, b"abc\n".to_vec()));
+        let literal = string_literal(&parts, 0, &mut diagnostics, ResolveTo::Type(&Type::Bytes(8)));
+        assert_eq!(bytes(literal).0, Type::Bytes(4));
+        let literal = string_literal(&[string("€", true)], 0, &mut diagnostics, ResolveTo::Unknown);
+        assert_eq!(bytes(literal).1, "€".as_bytes());
+        assert!(
+            string_literal(&[string("€", false)], 0, &mut diagnostics, ResolveTo::Unknown).is_err()
+        );
+
+        let hex = |hex: &str| pt::HexLiteral { loc: pt::Loc::File(0, 0, 1), hex: hex.to_string() };
+        let literal = hex_literal(&[hex("00ff"), hex("01")], &mut diagnostics, ResolveTo::Unknown);
+        assert_eq!(bytes(literal), (Type::Bytes(3), vec![0, 0xff, 1]));
+        let resolve_to = ResolveTo::Type(&Type::DynamicBytes);
+        assert_eq!(
+            bytes(hex_literal(&[hex("")], &mut diagnostics, resolve_to)).0,
+            Type::DynamicBytes
+        );
+        assert!(hex_literal(&[hex("abc")], &mut diagnostics, ResolveTo::Unknown).is_err());
+
+        assert!(diagnostics.contains_message(
+            "invalid characters in string literal, use a 'unicode' literal for characters which are not ASCII"
+        ));
+        assert!(diagnostics.contains_message("hex string \"abc\" has an odd number of digits"));
+    }
+
+    #[test]
+    fn test_array_literal_elements() {
+        let ctx = Context::new();
+        let literal = |ty: Type, value: i64| Expression::NumberLiteral {
+            loc: pt::Loc::Builtin,
+            ty,
+            value: value.into(),
+        };
+        let variable = |ty: Type| Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 };
+        let common = |values: &[Expression]| {
+            let mut diagnostics = Diagnostics::default();
+            common_elem_type(values, &ctx, &mut diagnostics).map_err(|_| diagnostics.first_error())
+        };
+
+        // The literals widen, unless an element which is not a literal sets the type
+        assert_eq!(common(&[number(1), literal(Type::Uint(16), 300)]), Ok(Type::Uint(16)));
+        assert_eq!(
+            common(&[number(1), variable(Type::Uint(64)), variable(Type::Uint(32))]),
+            Ok(Type::Uint(64))
+        );
+        assert_eq!(
+            common(&[number(1), variable(Type::Uint(8)), literal(Type::Uint(16), 300)]),
+            Ok(Type::Uint(8))
+        );
+        assert_eq!(common(&[variable(Type::Uint(8)), variable(Type::Int(16))]), Ok(Type::Int(16)));
+        assert_eq!(
+            common(&[variable(Type::Uint(8)), variable(Type::Bool)]).unwrap_err(),
+            "array literal element of type 'bool' is incompatible with the elements of type 'uint8' before it"
+        );
+    }
+}
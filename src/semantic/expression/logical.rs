@@ -0,0 +1,206 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The boolean operators and the conditional operator. The operands of `!`,
+//! `&&` and `||` and the condition of `?:` must be `bool`; the operators on
+//! boolean literals are folded.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Expression, RetrieveType, Type},
+        context::Context,
+        expression::{
+            coerce::coerce_types, resolve_expression::expression, ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// Resolve `!expr`
+pub(crate) fn not(
+    loc: &pt::Loc,
+    e: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let expr = condition(e, context, ctx, symtable, diagnostics)?;
+
+    Ok(match expr {
+        Expression::BoolLiteral { value, .. } => {
+            Expression::BoolLiteral { loc: *loc, value: !value }
+        }
+        expr => Expression::Not { loc: *loc, expr: Box::new(expr) },
+    })
+}
+
+/// Resolve `left && right` or `left || right`. The right operand is only
+/// evaluated when the left one does not decide the result.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn and_or(
+    loc: &pt::Loc,
+    and: bool,
+    l: &pt::Expression,
+    r: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let left = condition(l, context, ctx, symtable, diagnostics)?;
+    let right = condition(r, context, ctx, symtable, diagnostics)?;
+
+    Ok(resolve_and_or(loc, and, left, right))
+}
+
+/// The conjunction or disjunction of two resolved booleans
+pub(crate) fn resolve_and_or(
+    loc: &pt::Loc,
+    and: bool,
+    left: Expression,
+    right: Expression,
+) -> Expression {
+    match (&left, &right) {
+        (Expression::BoolLiteral { value: l, .. }, Expression::BoolLiteral { value: r, .. }) => {
+            let value = if and { *l && *r } else { *l || *r };
+            Expression::BoolLiteral { loc: *loc, value }
+        }
+        _ if and => Expression::And { loc: *loc, left: Box::new(left), right: Box::new(right) },
+        _ => Expression::Or { loc: *loc, left: Box::new(left), right: Box::new(right) },
+    }
+}
+
+/// Resolve `cond ? true_option : false_option`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn conditional_operator(
+    loc: &pt::Loc,
+    c: &pt::Expression,
+    t: &pt::Expression,
+    f: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+    resolve_to: ResolveTo,
+) -> Result<Expression, ()> {
+    let cond = condition(c, context, ctx, symtable, diagnostics)?;
+    let true_option = expression(t, context, ctx, symtable, diagnostics, resolve_to)?;
+    let false_option = expression(f, context, ctx, symtable, diagnostics, resolve_to)?;
+
+    resolve_conditional_operator(loc, cond, true_option, false_option, ctx, diagnostics)
+}
+
+/// The conditional operator on resolved operands. Both options are cast to
+/// their common type.
+pub(crate) fn resolve_conditional_operator(
+    loc: &pt::Loc,
+    cond: Expression,
+    true_option: Expression,
+    false_option: Expression,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let Some(ty) = coerce_types(&true_option, &false_option, ctx) else {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!(
+                "true and false options of the conditional operator have incompatible types '{}' and '{}'",
+                true_option.ty().deref_any().to_string(ctx),
+                false_option.ty().deref_any().to_string(ctx)
+            ),
+        ));
+        return Err(());
+    };
+
+    let true_option = true_option.cast(&true_option.loc(), &ty, true, ctx, diagnostics)?;
+    let false_option = false_option.cast(&false_option.loc(), &ty, true, ctx, diagnostics)?;
+
+    Ok(Expression::ConditionalOperator {
+        loc: *loc,
+        ty,
+        cond: Box::new(cond),
+        true_option: Box::new(true_option),
+        false_option: Box::new(false_option),
+    })
+}
+
+/// Resolve an expression which must be a `bool`
+fn condition(
+    e: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let expr = expression(e, context, ctx, symtable, diagnostics, ResolveTo::Type(&Type::Bool))?;
+
+    expr.cast(&e.loc(), &Type::Bool, true, ctx, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+
+    fn boolean(value: bool) -> Expression {
+        Expression::BoolLiteral { loc: pt::Loc::Builtin, value }
+    }
+
+    fn variable(ty: Type) -> Expression {
+        Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 }
+    }
+
+    fn number(ty: Type, value: i64) -> Expression {
+        Expression::NumberLiteral { loc: pt::Loc::Builtin, ty, value: value.into() }
+    }
+
+    #[test]
+    fn test_fold_and_or() {
+        let loc = pt::Loc::Builtin;
+        assert_eq!(resolve_and_or(&loc, true, boolean(true), boolean(false)), boolean(false));
+        assert_eq!(resolve_and_or(&loc, false, boolean(true), boolean(false)), boolean(true));
+        assert!(matches!(
+            resolve_and_or(&loc, true, variable(Type::Bool), boolean(true)),
+            Expression::And { .. }
+        ));
+    }
+
+    #[test]
+    fn test_conditional_operator() {
+        let ctx = Context::new();
+        let conditional = |t, f| {
+            let mut diagnostics = Diagnostics::default();
+            let cond = variable(Type::Bool);
+            resolve_conditional_operator(&pt::Loc::Builtin, cond, t, f, &ctx, &mut diagnostics)
+                .map_err(|_| diagnostics.first_error())
+        };
+
+        // The literal takes the type of the other option, whichever it is
+        let expr = conditional(number(Type::Uint(8), 1), variable(Type::Uint(64))).unwrap();
+        assert!(matches!(expr, Expression::ConditionalOperator { ty: Type::Uint(64), .. }));
+        let expr = conditional(variable(Type::Uint(64)), number(Type::Uint(8), 1)).unwrap();
+        assert!(matches!(expr, Expression::ConditionalOperator { ty: Type::Uint(64), .. }));
+        let expr = conditional(variable(Type::Uint(8)), variable(Type::Uint(16))).unwrap();
+        assert!(matches!(expr, Expression::ConditionalOperator { ty: Type::Uint(16), .. }));
+
+        assert_eq!(
+            conditional(variable(Type::Bool), variable(Type::Uint(8))).unwrap_err(),
+            "true and false options of the conditional operator have incompatible types 'bool' and 'uint8'"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lvalues, the expressions which can be written: local variables, state
+//! variables which are not constant, nor immutable outside of a constructor,
+//! elements of arrays and mappings, struct members, the new element `push()`
+//! gives, and tuples of those. The same check applies to assignments, `delete`
+//! and `++`/`--`.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink, ErrorType, Level},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Builtin, Expression, RetrieveType, Statement, Variable},
+        context::Context,
+        expression::{resolve_expression::expression, ExprContext, ResolveTo},
+        symtable::Symtable,
+    },
+};
+
+/// How an lvalue is written, for the diagnostics
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Mutation {
+    Assign,
+    Delete,
+    IncrDecr,
+}
+
+impl Mutation {
+    fn verb(self) -> &'static str {
+        match self {
+            Mutation::Assign => "assign to",
+            Mutation::Delete => "delete",
+            Mutation::IncrDecr => "increment or decrement",
+        }
+    }
+}
+
+/// Resolve the expression as an lvalue
+pub(crate) fn lvalue(
+    expr: &pt::Expression,
+    mutation: Mutation,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let lvalue = std::mem::replace(&mut context.lvalue, true);
+    let var = expression(expr, context, ctx, symtable, diagnostics, ResolveTo::Unknown);
+    context.lvalue = lvalue;
+
+    let var = var?;
+    check_lvalue(&var, mutation, context, ctx, symtable, diagnostics)?;
+    Ok(var)
+}
+
+/// Resolve the statement `delete x`, which resets the lvalue to the default
+/// value of its type
+#[allow(clippy::result_unit_err)]
+pub fn delete(
+    loc: &pt::Loc,
+    expr: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let var = lvalue(expr, Mutation::Delete, context, ctx, symtable, diagnostics)?;
+    let ty = var.ty().deref_any().clone();
+
+    Ok(Statement::Delete(*loc, ty, var))
+}
+
+/// Check the expression can be written
+pub(crate) fn check_lvalue(
+    var: &Expression,
+    mutation: Mutation,
+    context: &ExprContext,
+    ctx: &Context,
+    symtable: &Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), ()> {
+    let verb = mutation.verb();
+    let error = |diagnostics: &mut dyn DiagnosticSink, message: String| {
+        diagnostics.push(Diagnostic::type_error(var.loc(), message));
+        Err(())
+    };
+    let declared = |diagnostics: &mut dyn DiagnosticSink, message: String, decl: &Variable| {
+        diagnostics.push(
+            Diagnostic::builder(var.loc(), Level::Error)
+                .ty(ErrorType::TypeError)
+                .message(message)
+                .note(decl.loc, format!("declaration of '{}'", decl.name))
+                .build(),
+        );
+        Err(())
+    };
+
+    match var {
+        Expression::ConstantVariable { contract_no, var_no, .. } => {
+            let decl = match contract_no {
+                Some(contract_no) => &ctx.contracts[*contract_no].variables[*var_no],
+                None => &ctx.constants[*var_no],
+            };
+            declared(diagnostics, format!("cannot {verb} constant variable '{}'", decl.name), decl)
+        }
+        Expression::StorageVariable { contract_no, var_no, .. } => {
+            let decl = &ctx.contracts[*contract_no].variables[*var_no];
+            let in_constructor =
+                context.function_no.is_some_and(|no| ctx.functions[no].is_constructor());
+            if decl.immutable && !in_constructor {
+                let message = format!(
+                    "cannot {verb} immutable variable '{}' outside of a constructor",
+                    decl.name
+                );
+                return declared(diagnostics, message, decl);
+            }
+            Ok(())
+        }
+        Expression::Variable { .. } |
+        Expression::Subscript { .. } |
+        Expression::StructMember { .. } |
+        Expression::NamedMember { .. } => match calldata_root(var, symtable) {
+            Some(name) => error(diagnostics, format!("calldata variable '{name}' is read-only")),
+            None => Ok(()),
+        },
+        // The elements of a tuple, as in `(a, b) = (b, a)`, are each checked
+        Expression::List { list, .. } => list
+            .iter()
+            .try_for_each(|var| check_lvalue(var, mutation, context, ctx, symtable, diagnostics)),
+        // `a.length` is a member, not a call, for arrays in memory as well
+        Expression::StorageArrayLength { .. } |
+        Expression::Builtin { kind: Builtin::ArrayLength, .. } => {
+            error(diagnostics, format!("cannot {verb} the length of an array, it is read-only"))
+        }
+        // `push()` without a value gives a reference to the new element
+        Expression::Builtin { kind: Builtin::ArrayPush, args, .. } if args.len() == 1 => Ok(()),
+        Expression::InternalFunctionCall { .. } |
+        Expression::ExternalFunctionCall { .. } |
+        Expression::Builtin { .. } => {
+            error(diagnostics, format!("cannot {verb} the result of a function call"))
+        }
+        _ => error(diagnostics, format!("cannot {verb} an expression which is not an lvalue")),
+    }
+}
+
+/// The name of the calldata variable the expression is an element of, if any
+fn calldata_root<'a>(expr: &Expression, symtable: &'a Symtable) -> Option<&'a str> {
+    match expr {
+        Expression::Variable { var_no, .. } => symtable
+            .vars
+            .get(var_no)
+            .filter(|var| matches!(var.storage_location, Some(pt::StorageLocation::Calldata(_))))
+            .map(|var| var.name.as_str()),
+        Expression::Subscript { array: expr, .. } |
+        Expression::NamedMember { array: expr, .. } |
+        Expression::StructMember { expr, .. } |
+        Expression::Load { expr, .. } => calldata_root(expr, symtable),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::Diagnostics,
+        semantic::{
+            ast::{ArrayLength, Type},
+            layout::tests::{contract, variable},
+        },
+    };
+
+    fn number(value: i64) -> Expression {
+        Expression::NumberLiteral { loc: pt::Loc::Builtin, ty: Type::Uint(8), value: value.into() }
+    }
+
+    #[test]
+    fn test_check_lvalue() {
+        let mut ctx = Context::new();
+        let mut symtable = Symtable::default();
+        let context = ExprContext::default();
+        let check = |var: &Expression, mutation, ctx: &Context, symtable: &Symtable| {
+            let mut diagnostics = Diagnostics::default();
+            check_lvalue(var, mutation, &context, ctx, symtable, &mut diagnostics)
+                .map_err(|_| diagnostics)
+        };
+
+        let mut constant = variable("MAX", Type::Uint(256), None);
+        constant.constant = true;
+        ctx.constants.push(constant);
+        let var = Expression::ConstantVariable {
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(256),
+            contract_no: None,
+            var_no: 0,
+        };
+        let diagnostics = check(&var, Mutation::Assign, &ctx, &symtable).unwrap_err();
+        assert_eq!(diagnostics.first_error(), "cannot assign to constant variable 'MAX'");
+        let notes = &diagnostics.iter().next().unwrap().notes;
+        assert_eq!(notes[0].message, "declaration of 'MAX'");
+
+        let mut owner = variable("owner", Type::Address(false), None);
+        owner.immutable = true;
+        ctx.contracts.push(contract("Vault", &[], vec![owner]));
+        let var = Expression::StorageVariable {
+            loc: pt::Loc::Builtin,
+            ty: Type::StorageRef(false, Box::new(Type::Address(false))),
+            contract_no: 0,
+            var_no: 0,
+        };
+        assert_eq!(
+            check(&var, Mutation::Delete, &ctx, &symtable).unwrap_err().first_error(),
+            "cannot delete immutable variable 'owner' outside of a constructor"
+        );
+
+        let array = Type::Array(Box::new(Type::Uint(256)), vec![ArrayLength::Dynamic]);
+        let mut ids = variable("ids", array.clone(), None);
+        ids.storage_location = Some(pt::StorageLocation::Calldata(pt::Loc::Builtin));
+        symtable.vars.insert(0, ids);
+        symtable.vars.insert(1, variable("local", array.clone(), None));
+        let element = |var_no| Expression::Subscript {
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(256),
+            array_ty: array.clone(),
+            array: Box::new(Expression::Variable {
+                loc: pt::Loc::Builtin,
+                ty: array.clone(),
+                var_no,
+            }),
+            index: Box::new(number(0)),
+        };
+        assert_eq!(
+            check(&element(0), Mutation::IncrDecr, &ctx, &symtable).unwrap_err().first_error(),
+            "calldata variable 'ids' is read-only"
+        );
+        assert!(check(&element(1), Mutation::Assign, &ctx, &symtable).is_ok());
+
+        // `(local[0], 1) = ...`
+        let tuple = Expression::List { loc: pt::Loc::Builtin, list: vec![element(1), number(1)] };
+        assert_eq!(
+            check(&tuple, Mutation::Assign, &ctx, &symtable).unwrap_err().first_error(),
+            "cannot assign to an expression which is not an lvalue"
+        );
+
+        let call = Expression::Builtin {
+            loc: pt::Loc::Builtin,
+            tys: vec![Type::Uint(256)],
+            kind: Builtin::Keccak256,
+            args: Vec::new(),
+        };
+        assert_eq!(
+            check(&call, Mutation::Assign, &ctx, &symtable).unwrap_err().first_error(),
+            "cannot assign to the result of a function call"
+        );
+    }
+
+    /// The errors of checking the contract with the function in the source
+    fn errors(src: &str) -> Vec<String> {
+        let contents = format!("contract C {{ uint[] s; {src} }}");
+        let input = crate::Input::Source { name: "test.sol".into(), contents };
+        let diagnostics = crate::check(&input, &crate::config::Config::default());
+        diagnostics.errors().into_iter().map(|error| error.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_push_lvalue() {
+        let src = "function f() public { s.push() += 1; s.push() = 2; s.push()++; }";
+        assert!(errors(src).is_empty(), "{:?}", errors(src));
+
+        // With a value, `push` gives nothing
+        let src = "function f() public { s.push(1) = 2; }";
+        assert_eq!(errors(src), ["cannot assign to the result of a function call"]);
+    }
+
+    #[test]
+    fn test_length_read_only() {
+        let src = "function f(uint[] memory a) public { a.length++; }";
+        assert_eq!(
+            errors(src),
+            ["cannot increment or decrement the length of an array, it is read-only"]
+        );
+        let src = "function f() public { s.length = 0; }";
+        assert_eq!(errors(src), ["cannot assign to the length of an array, it is read-only"]);
+        let src = "function f() public { delete s.length; }";
+        assert_eq!(errors(src), ["cannot delete the length of an array, it is read-only"]);
+    }
+}
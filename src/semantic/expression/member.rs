@@ -0,0 +1,576 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Member access, `a.b`. The left hand side is either a namespace, like an
+//! enum, a contract, an import or `msg`, or a value, whose members depend on
+//! its type: the fields of a struct, the length of an array, the balance of
+//! an address or the functions of a contract.
+
+use num_bigint::BigInt;
+use num_traits::One;
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    parser::ast as pt,
+    semantic::{
+        ast::{ArrayLength, Builtin, Expression, RetrieveType, Symbol, Type},
+        context::{Context, ResolveTypeContext},
+        expression::{
+            builtin::{builtin_variable, is_builtin_namespace},
+            resolve_expression::expression,
+            selector::{identifier_path, type_operand},
+            ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// What the left hand side of a member access names, when it is not a value
+pub(crate) enum Namespace {
+    /// `msg`, `tx`, `block` or `abi`
+    Builtin(String),
+    Enum(usize),
+    /// A contract, its symbols are looked up in it and its bases
+    Contract(usize),
+    /// `super`, the bases of the current contract
+    Super(usize),
+    /// An import, `import "a.sol" as A;`
+    Import(usize),
+}
+
+/// The namespace the expression names, if it is not a local variable nor
+/// anything else which is a value
+pub(crate) fn namespace(
+    expr: &pt::Expression,
+    context: &ExprContext,
+    ctx: &Context,
+) -> Option<Namespace> {
+    let path = identifier_path(expr)?;
+    let (first, rest) = path.identifiers.split_first()?;
+
+    let is_local = context.active_scopes.iter().any(|scope| scope.names.contains_key(&first.name));
+    if is_local {
+        return None;
+    }
+
+    let mut namespace = match ctx.find_symbol(context.no, context.contract_no, &first.name, true) {
+        Some(Symbol::Enum(_, enum_no)) => Namespace::Enum(*enum_no),
+        Some(Symbol::Contract(_, contract_no)) => Namespace::Contract(*contract_no),
+        Some(Symbol::Import(_, import_no)) => Namespace::Import(*import_no),
+        None if first.name == "super" => Namespace::Super(context.contract_no?),
+        None if is_builtin_namespace(&first.name) => Namespace::Builtin(first.name.clone()),
+        _ => return None,
+    };
+
+    for id in rest {
+        let symbol = match namespace {
+            Namespace::Contract(contract_no) => {
+                let no = ctx.contracts[contract_no].loc.no().unwrap_or(context.no);
+                ctx.find_symbol(no, Some(contract_no), &id.name, false)
+            }
+            Namespace::Import(import_no) => ctx.find_symbol(import_no, None, &id.name, true),
+            _ => return None,
+        };
+        namespace = match symbol {
+            Some(Symbol::Enum(_, enum_no)) => Namespace::Enum(*enum_no),
+            Some(Symbol::Contract(_, contract_no)) => Namespace::Contract(*contract_no),
+            Some(Symbol::Import(_, import_no)) => Namespace::Import(*import_no),
+            _ => return None,
+        };
+    }
+
+    Some(namespace)
+}
+
+/// The symbol of the member of the namespace, and whether it is a member of
+/// a contract other than the current one or its bases, which is only
+/// accessible through an instance
+pub(crate) fn namespace_symbol<'a>(
+    namespace: &Namespace,
+    member: &pt::Identifier,
+    context: &ExprContext,
+    ctx: &'a Context,
+) -> Option<(&'a Symbol, bool)> {
+    match namespace {
+        Namespace::Contract(contract_no) => {
+            let no = ctx.contracts[*contract_no].loc.no().unwrap_or(context.no);
+            let symbol = ctx.find_symbol(no, Some(*contract_no), &member.name, false)?;
+            let is_base = context
+                .contract_no
+                .is_some_and(|current| ctx.contract_bases(current).contains(contract_no));
+            Some((symbol, !is_base && !ctx.contracts[*contract_no].is_library()))
+        }
+        Namespace::Super(contract_no) => {
+            let mut bases = ctx.contract_bases(*contract_no);
+            bases.pop();
+            bases.into_iter().rev().find_map(|base_no| {
+                let no = ctx.contracts[base_no].loc.no().unwrap_or(context.no);
+                ctx.function_symbols
+                    .get(&(no, Some(base_no), member.name.clone()))
+                    .map(|symbol| (symbol, false))
+            })
+        }
+        Namespace::Import(import_no) => {
+            ctx.find_symbol(*import_no, None, &member.name, true).map(|symbol| (symbol, false))
+        }
+        Namespace::Builtin(_) | Namespace::Enum(_) => None,
+    }
+}
+
+/// Resolve `expr.member`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn member_access(
+    loc: &pt::Loc,
+    e: &pt::Expression,
+    member: &pt::Identifier,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    if let Some(operand) = type_operand(e) {
+        return type_member(loc, operand, member, context, ctx, diagnostics);
+    }
+
+    if let Some(namespace) = namespace(e, context, ctx) {
+        return namespace_member(loc, &namespace, member, context, ctx, diagnostics);
+    }
+
+    let object = expression(e, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+    resolve_member(loc, object, member, ctx, diagnostics)
+}
+
+/// A member of a namespace: a builtin variable, an enum value, or a constant,
+/// variable or function of a contract or import
+fn namespace_member(
+    loc: &pt::Loc,
+    namespace: &Namespace,
+    member: &pt::Identifier,
+    context: &ExprContext,
+    ctx: &mut Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    match namespace {
+        Namespace::Builtin(name) if name == "abi" => {
+            diagnostics.push(Diagnostic::error(
+                *loc,
+                format!("builtin 'abi.{}' must be called", member.name),
+            ));
+            return Err(());
+        }
+        Namespace::Builtin(name) => return builtin_variable(loc, name, member, diagnostics),
+        Namespace::Enum(enum_no) => {
+            let decl = &ctx.enums[*enum_no];
+            return match decl.values.get_index_of(&member.name) {
+                Some(value) => Ok(Expression::NumberLiteral {
+                    loc: *loc,
+                    ty: Type::Enum(*enum_no),
+                    value: BigInt::from(value),
+                }),
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        member.loc,
+                        format!("enum '{decl}' has no value '{}'", member.name),
+                    ));
+                    Err(())
+                }
+            };
+        }
+        _ => (),
+    }
+
+    let Some((symbol, instance_only)) = namespace_symbol(namespace, member, context, ctx) else {
+        diagnostics.push(Context::wrong_symbol(None, member));
+        return Err(());
+    };
+
+    match symbol.clone() {
+        Symbol::Variable(_, var_contract, var_no) => {
+            let var = match var_contract {
+                Some(contract_no) => &mut ctx.contracts[contract_no].variables[var_no],
+                None => &mut ctx.constants[var_no],
+            };
+            var.read = true;
+            match var_contract {
+                _ if var.constant => Ok(Expression::ConstantVariable {
+                    loc: *loc,
+                    ty: var.ty.clone(),
+                    contract_no: var_contract,
+                    var_no,
+                }),
+                Some(contract_no) if !instance_only => Ok(Expression::StorageVariable {
+                    loc: *loc,
+                    ty: Type::StorageRef(var.immutable, Box::new(var.ty.clone())),
+                    contract_no,
+                    var_no,
+                }),
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        *loc,
+                        format!(
+                            "state variable '{}' is only accessible through an instance of the contract",
+                            member.name
+                        ),
+                    ));
+                    Err(())
+                }
+            }
+        }
+        Symbol::Function(functions) => {
+            let function_no = match functions.as_slice() {
+                [(_, function_no)] => *function_no,
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        member.loc,
+                        format!("function '{}' is overloaded, call it to select one", member.name),
+                    ));
+                    return Err(());
+                }
+            };
+            let func = &ctx.functions[function_no];
+            let path = pt::IdentifierPath { loc: *loc, identifiers: vec![member.clone()] };
+            Ok(Expression::InternalFunction {
+                loc: *loc,
+                id: path,
+                ty: func.function_type(false),
+                function_no,
+                // Named through its contract, the function is not virtual
+                signature: None,
+            })
+        }
+        symbol => {
+            diagnostics.push(Context::wrong_symbol(Some(&symbol), member));
+            Err(())
+        }
+    }
+}
+
+/// A member of `type(X)`: the bounds of integers and enums, and the name of
+/// contracts. The id of an interface is resolved on its own.
+fn type_member(
+    loc: &pt::Loc,
+    operand: &pt::Expression,
+    member: &pt::Identifier,
+    context: &ExprContext,
+    ctx: &mut Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let ty = ctx.resolve_type(
+        context.no,
+        context.contract_no,
+        ResolveTypeContext::None,
+        operand,
+        diagnostics,
+    )?;
+
+    let literal =
+        |value: BigInt| Ok(Expression::NumberLiteral { loc: *loc, ty: ty.clone(), value });
+    match (&ty, member.name.as_str()) {
+        (Type::Uint(_), "min") => literal(BigInt::ZERO),
+        (Type::Uint(bits), "max") => literal((BigInt::one() << *bits) - 1),
+        (Type::Int(bits), "min") => literal(-(BigInt::one() << (*bits - 1))),
+        (Type::Int(bits), "max") => literal((BigInt::one() << (*bits - 1)) - 1),
+        (Type::Enum(_), "min") => literal(BigInt::ZERO),
+        (Type::Enum(enum_no), "max") => literal(BigInt::from(ctx.enums[*enum_no].values.len() - 1)),
+        (Type::Contract(contract_no), "name") => Ok(Expression::BytesLiteral {
+            loc: *loc,
+            ty: Type::String,
+            value: ctx.contracts[*contract_no].id.name.as_bytes().to_vec(),
+        }),
+        _ => {
+            diagnostics.push(Diagnostic::error(
+                member.loc,
+                format!("type '{}' has no member '{}'", ty.to_string(ctx), member.name),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// A member of a resolved value
+pub(crate) fn resolve_member(
+    loc: &pt::Loc,
+    object: Expression,
+    member: &pt::Identifier,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let object_ty = object.ty();
+    let storage = object_ty.is_contract_storage();
+    let ty = object_ty.deref_any().clone();
+
+    match (&ty, member.name.as_str()) {
+        (Type::Array(_, dims), "length") => {
+            return match dims.last() {
+                Some(ArrayLength::Fixed(length)) => Ok(Expression::NumberLiteral {
+                    loc: *loc,
+                    ty: Type::Uint(256),
+                    value: length.clone(),
+                }),
+                _ => Ok(array_length(loc, object, &ty, storage)),
+            };
+        }
+        (Type::DynamicBytes, "length") => return Ok(array_length(loc, object, &ty, storage)),
+        (Type::Bytes(length), "length") => {
+            return Ok(Expression::NumberLiteral {
+                loc: *loc,
+                ty: Type::Uint(8),
+                value: BigInt::from(*length),
+            });
+        }
+        (Type::Struct(struct_ty), _) => {
+            let decl = struct_ty.definition(ctx);
+            let field = decl
+                .fields
+                .iter()
+                .position(|field| field.id.as_ref().is_some_and(|id| id.name == member.name));
+            let Some(field) = field else {
+                diagnostics.push(Diagnostic::error(
+                    member.loc,
+                    format!("struct '{decl}' has no field '{}'", member.name),
+                ));
+                return Err(());
+            };
+
+            let field_ty = decl.fields[field].ty.clone();
+            let ty = if storage {
+                Type::StorageRef(false, Box::new(field_ty))
+            } else if field_ty.can_have_data_location() {
+                field_ty
+            } else {
+                Type::Ref(Box::new(field_ty))
+            };
+            return Ok(Expression::StructMember { loc: *loc, ty, expr: Box::new(object), field });
+        }
+        (Type::Address(_), "balance" | "code") => {
+            let address = object.cast(loc, &Type::Address(false), true, ctx, diagnostics)?;
+            let (kind, ty) = match member.name.as_str() {
+                "balance" => (Builtin::Balance, Type::Value),
+                _ => (Builtin::ContractCode, Type::DynamicBytes),
+            };
+            return Ok(Expression::Builtin { loc: *loc, tys: vec![ty], kind, args: vec![address] });
+        }
+        (Type::ExternalFunction { .. }, "address") => {
+            return Ok(Expression::Builtin {
+                loc: *loc,
+                tys: vec![Type::Address(false)],
+                kind: Builtin::ExternalFunctionAddress,
+                args: vec![object],
+            });
+        }
+        (Type::Contract(contract_no), _) => {
+            let functions = external_functions(ctx, *contract_no, &member.name);
+            if let [function_no] = functions.as_slice() {
+                let object = object.cast(loc, &ty, true, ctx, diagnostics)?;
+                return Ok(Expression::ExternalFunction {
+                    loc: *loc,
+                    ty: ctx.functions[*function_no].function_type(true),
+                    address: Box::new(object),
+                    function_no: *function_no,
+                });
+            }
+            if !functions.is_empty() {
+                diagnostics.push(Diagnostic::error(
+                    member.loc,
+                    format!("function '{}' is overloaded, call it to select one", member.name),
+                ));
+                return Err(());
+            }
+        }
+        _ => (),
+    }
+
+    diagnostics.push(Diagnostic::error(
+        member.loc,
+        format!("'{}' has no member '{}'", ty.to_string(ctx), member.name),
+    ));
+    Err(())
+}
+
+/// The length of a dynamic array, or `bytes`
+fn array_length(loc: &pt::Loc, array: Expression, ty: &Type, storage: bool) -> Expression {
+    if storage {
+        Expression::StorageArrayLength {
+            loc: *loc,
+            ty: Type::Uint(256),
+            array: Box::new(array),
+            elem_ty: ty.array_elem(),
+        }
+    } else {
+        Expression::Builtin {
+            loc: *loc,
+            tys: vec![Type::Uint(256)],
+            kind: Builtin::ArrayLength,
+            args: vec![array],
+        }
+    }
+}
+
+/// The functions of the name which can be called on an instance of the
+/// contract: its public and external functions and those of its bases, an
+/// override hiding the function it overrides
+pub(crate) fn external_functions(ctx: &Context, contract_no: usize, name: &str) -> Vec<usize> {
+    let mut functions: Vec<usize> = Vec::new();
+
+    for base_no in ctx.contract_bases(contract_no).into_iter().rev() {
+        for function_no in &ctx.contracts[base_no].functions {
+            let func = &ctx.functions[*function_no];
+            let hidden =
+                functions.iter().any(|other| ctx.functions[*other].signature == func.signature);
+            if func.id.name == name &&
+                func.ty == pt::FunctionTy::Function &&
+                func.is_public() &&
+                !hidden
+            {
+                functions.push(*function_no);
+            }
+        }
+    }
+
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::Diagnostics,
+        semantic::{
+            ast::{Function, Parameter, StructDecl, StructType},
+            layout::tests::contract,
+        },
+    };
+    use indexmap::IndexMap;
+
+    fn id(name: &str) -> pt::Identifier {
+        pt::Identifier { loc: pt::Loc::Builtin, name: name.to_string() }
+    }
+
+    fn variable(ty: Type) -> Expression {
+        Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 }
+    }
+
+    fn member(object: Expression, name: &str, ctx: &Context) -> Result<Expression, String> {
+        let mut diagnostics = Diagnostics::default();
+        resolve_member(&pt::Loc::Builtin, object, &id(name), ctx, &mut diagnostics)
+            .map_err(|_| diagnostics.first_error())
+    }
+
+    #[test]
+    fn test_value_members() {
+        let mut ctx = Context::new();
+        ctx.structs.push(StructDecl {
+            tags: Vec::new(),
+            id: id("Account"),
+            loc: pt::Loc::Builtin,
+            contract: None,
+            fields: vec![
+                Parameter { id: Some(id("owner")), ..Parameter::new_default(Type::Address(false)) },
+                Parameter { id: Some(id("tags")), ..Parameter::new_default(Type::String) },
+            ],
+            offsets: Vec::new(),
+            storage_offsets: Vec::new(),
+        });
+        let account = Type::Struct(StructType::UserDefined(0));
+        let storage = |ty: Type| Type::StorageRef(false, Box::new(ty));
+
+        let expr = member(variable(storage(account.clone())), "owner", &ctx).unwrap();
+        assert!(matches!(&expr, Expression::StructMember { field: 0, .. }));
+        assert_eq!(expr.ty(), storage(Type::Address(false)));
+        let expr = member(variable(account.clone()), "owner", &ctx).unwrap();
+        assert_eq!(expr.ty(), Type::Ref(Box::new(Type::Address(false))));
+        assert_eq!(member(variable(account.clone()), "tags", &ctx).unwrap().ty(), Type::String);
+        assert_eq!(
+            member(variable(account), "balance", &ctx).unwrap_err(),
+            "struct 'Account' has no field 'balance'"
+        );
+
+        let fixed = Type::Array(Box::new(Type::Bool), vec![ArrayLength::Fixed(4.into())]);
+        let dynamic = Type::Array(Box::new(Type::Bool), vec![ArrayLength::Dynamic]);
+        let expr = member(variable(fixed), "length", &ctx).unwrap();
+        assert!(matches!(expr, Expression::NumberLiteral { ref value, .. } if *value == 4.into()));
+        let expr = member(variable(storage(dynamic.clone())), "length", &ctx).unwrap();
+        assert!(matches!(expr, Expression::StorageArrayLength { elem_ty: Type::Bool, .. }));
+        let expr = member(variable(dynamic), "length", &ctx).unwrap();
+        assert!(matches!(expr, Expression::Builtin { kind: Builtin::ArrayLength, .. }));
+
+        let expr = member(variable(Type::Address(true)), "balance", &ctx).unwrap();
+        assert_eq!(expr.ty(), Type::Value);
+        assert_eq!(
+            member(variable(Type::Bool), "length", &ctx).unwrap_err(),
+            "'bool' has no member 'length'"
+        );
+    }
+
+    #[test]
+    fn test_contract_members() {
+        let mut ctx = Context::new();
+        ctx.contracts.push(contract("Token", &[], Vec::new()));
+        for (name, visibility) in
+            [("transfer", pt::Visibility::External(None)), ("mint", pt::Visibility::Internal(None))]
+        {
+            let func = Function::new(
+                pt::Loc::Builtin,
+                pt::Loc::Builtin,
+                id(name),
+                Some(0),
+                Vec::new(),
+                pt::FunctionTy::Function,
+                None,
+                visibility,
+                Vec::new(),
+                Vec::new(),
+                &ctx,
+            );
+            ctx.contracts[0].functions.push(ctx.functions.len());
+            ctx.functions.push(func);
+        }
+
+        let token = variable(Type::Contract(0));
+        let expr = member(token.clone(), "transfer", &ctx).unwrap();
+        assert!(matches!(
+            expr,
+            Expression::ExternalFunction { ty: Type::ExternalFunction { .. }, function_no: 0, .. }
+        ));
+        assert_eq!(
+            member(token, "mint", &ctx).unwrap_err(),
+            "'contract Token' has no member 'mint'"
+        );
+
+        ctx.enums.push(crate::semantic::ast::EnumDecl {
+            id: id("State"),
+            contract: None,
+            loc: pt::Loc::Builtin,
+            ty: Type::Uint(8),
+            values: IndexMap::from([
+                ("Open".to_string(), pt::Loc::Builtin),
+                ("Closed".to_string(), pt::Loc::Builtin),
+            ]),
+        });
+        let context = ExprContext::default();
+        let mut diagnostics = Diagnostics::default();
+        let closed = namespace_member(
+            &pt::Loc::Builtin,
+            &Namespace::Enum(0),
+            &id("Closed"),
+            &context,
+            &mut ctx,
+            &mut diagnostics,
+        )
+        .unwrap();
+        assert!(
+            matches!(closed, Expression::NumberLiteral { ty: Type::Enum(0), ref value, .. } if *value == 1.into())
+        );
+    }
+}
@@ -15,19 +15,34 @@
 use std::collections::HashMap;
 
 use crate::{
-    diagnostics::Diagnostics,
     parser::ast as pt,
     semantic::{
-        ast::{Expression, Type},
-        context::Context,
+        ast::Type,
         symtable::{LoopScopes, Symtable, VarScope},
     },
 };
 
+pub mod arithmetic;
+pub mod assign;
+pub mod builtin;
+pub mod cast;
+pub mod coerce;
+pub mod comparison;
+pub mod constant;
 pub mod constructor;
+pub mod function_call;
+pub mod literals;
+pub mod logical;
+pub mod lvalue;
+pub mod member;
+pub mod require;
 pub mod resolve_expression;
 pub mod retrieve_type;
+pub mod selector;
 pub mod strings;
+pub mod subscript;
+pub mod summary;
+pub mod variable;
 
 /// When resolving an expression, what type are we looking for
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -74,18 +89,3 @@ impl ExprContext {
         }
     }
 }
-
-impl Expression {
-    /// Cast from one type to another, which also automatically derefs any Type::Ref() type.
-    /// if the cast is explicit (e.g. bytes32(bar) then implicit should be set to false.
-    pub(crate) fn cast(
-        &self,
-        _loc: &pt::Loc,
-        _to: &Type,
-        _implicit: bool,
-        _ctx: &Context,
-        _diagnostics: &mut Diagnostics,
-    ) -> Result<Expression, ()> {
-        todo!()
-    }
-}
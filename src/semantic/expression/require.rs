@@ -0,0 +1,212 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `require(cond)`, `require(cond, "reason")` and, since Solidity 0.8.26,
+//! `require(cond, MyError(args))`. The reason is resolved to the data the call
+//! reverts with: a string is encoded as `Error(string)`, a custom error as its
+//! selector followed by its arguments.
+
+use crate::{
+    abi::ethereum,
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{Builtin, Expression, Type},
+        context::Context,
+        expression::{
+            resolve_expression::expression, selector::identifier_path, ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// Resolve the arguments of `require` into the condition and the revert data
+pub(crate) fn require(
+    loc: &pt::Loc,
+    args: &[pt::Expression],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let (cond, reason) = match args {
+        [cond] => (cond, None),
+        [cond, reason] => (cond, Some(reason)),
+        _ => {
+            diagnostics.push(Diagnostic::error(
+                *loc,
+                format!("require expects 1 or 2 arguments, {} given", args.len()),
+            ));
+            return Err(());
+        }
+    };
+
+    let cond = expression(cond, context, ctx, symtable, diagnostics, ResolveTo::Type(&Type::Bool))?
+        .cast(&cond.loc(), &Type::Bool, true, ctx, diagnostics)?;
+
+    let mut args = vec![cond];
+    if let Some(reason) = reason {
+        args.push(revert_data(reason, context, ctx, symtable, diagnostics)?);
+    }
+
+    Ok(Expression::Builtin { loc: *loc, tys: vec![Type::Void], kind: Builtin::Require, args })
+}
+
+/// The data for the reason of a revert, a custom error or a string
+fn revert_data(
+    reason: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    if let pt::Expression::FunctionCall(loc, callee, args) = reason {
+        let error_no = identifier_path(callee).and_then(|name| {
+            let mut errors = Diagnostics::default();
+            ctx.resolve_error_with_namespace(context.no, context.contract_no, &name, &mut errors)
+                .ok()
+        });
+
+        if let Some(error_no) = error_no {
+            return error_data(loc, error_no, args, context, ctx, symtable, diagnostics);
+        }
+    }
+
+    let loc = reason.loc();
+    let reason =
+        expression(reason, context, ctx, symtable, diagnostics, ResolveTo::Type(&Type::String))?
+            .cast(&loc, &Type::String, true, ctx, diagnostics)?;
+
+    Ok(encode_with_selector(loc, ethereum::selector("Error(string)"), vec![reason]))
+}
+
+/// The data of a custom error: its selector followed by the encoded arguments
+pub(crate) fn error_data(
+    loc: &pt::Loc,
+    error_no: usize,
+    args: &[pt::Expression],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let fields = ctx.errors[error_no].fields.clone();
+    if args.len() != fields.len() {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!(
+                "error '{}' has {} fields, {} arguments given",
+                ctx.errors[error_no].name,
+                fields.len(),
+                args.len()
+            ),
+        ));
+        return Err(());
+    }
+
+    let mut resolved = Vec::new();
+    for (arg, field) in args.iter().zip(&fields) {
+        let ty = &field.ty;
+        resolved.push(
+            expression(arg, context, ctx, symtable, diagnostics, ResolveTo::Type(ty))?.cast(
+                &arg.loc(),
+                ty,
+                true,
+                ctx,
+                diagnostics,
+            )?,
+        );
+    }
+
+    ctx.errors[error_no].used = true;
+    let selector = ctx.errors[error_no].selector(ctx);
+
+    Ok(encode_with_selector(*loc, selector, resolved))
+}
+
+fn encode_with_selector(loc: pt::Loc, selector: [u8; 4], args: Vec<Expression>) -> Expression {
+    let selector = Expression::BytesLiteral { loc, ty: Type::Bytes(4), value: selector.to_vec() };
+
+    Expression::Builtin {
+        loc,
+        tys: vec![Type::DynamicBytes],
+        kind: Builtin::AbiEncodeWithSelector,
+        args: std::iter::once(selector).chain(args).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::ast::{ErrorDecl, Parameter, RetrieveType};
+
+    fn number(value: &str) -> pt::Expression {
+        pt::Expression::NumberLiteral(pt::Loc::Builtin, value.into(), String::new(), None)
+    }
+
+    #[test]
+    fn test_error_data() {
+        let mut ctx = Context::new();
+        ctx.errors.push(ErrorDecl {
+            name: "Insufficient".into(),
+            fields: vec![Parameter::new_default(Type::Uint(256))],
+            ..Default::default()
+        });
+        let loc = pt::Loc::Builtin;
+        let mut context = ExprContext::default();
+        let mut symtable = Symtable::default();
+        let mut diagnostics = Diagnostics::default();
+
+        let data = error_data(
+            &loc,
+            0,
+            &[number("5")],
+            &mut context,
+            &mut ctx,
+            &mut symtable,
+            &mut diagnostics,
+        )
+        .unwrap();
+        assert!(ctx.errors[0].used);
+
+        let Expression::Builtin { kind: Builtin::AbiEncodeWithSelector, args, .. } = data else {
+            panic!("unexpected {data:?}");
+        };
+        assert_eq!(
+            args[0],
+            Expression::BytesLiteral {
+                loc,
+                ty: Type::Bytes(4),
+                value: ethereum::selector("Insufficient(uint256)").to_vec()
+            }
+        );
+        assert_eq!(args[1].ty(), Type::Uint(256));
+
+        let res = error_data(
+            &loc,
+            0,
+            &[number("5"), number("6")],
+            &mut context,
+            &mut ctx,
+            &mut symtable,
+            &mut diagnostics,
+        );
+        assert!(res.is_err());
+        assert_eq!(
+            diagnostics.first_error(),
+            "error 'Insufficient' has 1 fields, 2 arguments given"
+        );
+    }
+}
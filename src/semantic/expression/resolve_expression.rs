@@ -13,12 +13,29 @@
 // limitations under the License.
 
 use crate::{
-    diagnostics::Diagnostics,
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics},
     parser::ast as pt,
     semantic::{
         ast::Expression,
-        context::Context,
-        expression::{ExprContext, ResolveTo},
+        context::{Context, ResolveTypeContext},
+        expression::{
+            arithmetic::{arithmetic, bitwise_not, negate, power, shift, Arithmetic},
+            assign::{assign_expr, assign_single, incr_decr, AssignOp, IncrDecr},
+            comparison::{comparison, Comparison},
+            constructor::new,
+            function_call::{function_call, Arguments},
+            literals::{
+                address_literal, array_literal, hex_literal, hex_number_literal, number_literal,
+                rational_number_literal, string_literal,
+            },
+            logical::{and_or, conditional_operator, not},
+            member::member_access,
+            require::require,
+            selector::{interface_id, selector, type_operand},
+            subscript::array_subscript,
+            variable::variable,
+            ExprContext, ResolveTo,
+        },
         symtable::Symtable,
     },
 };
@@ -32,8 +49,216 @@ pub fn expression(
     context: &mut ExprContext,
     ctx: &mut Context,
     symtable: &mut Symtable,
-    diagnostics: &mut Diagnostics,
+    diagnostics: &mut dyn DiagnosticSink,
     resolve_to: ResolveTo,
 ) -> Result<Expression, ()> {
-    todo!()
+    if let Some((loc, op, l, r)) = AssignOp::from_pt(expr) {
+        return assign_expr(&loc, op, l, r, context, ctx, symtable, diagnostics);
+    }
+    if let Some((loc, op, e)) = IncrDecr::from_pt(expr) {
+        return incr_decr(&loc, op, e, context, ctx, symtable, diagnostics);
+    }
+    if let Some((loc, op, l, r)) = Arithmetic::from_pt(expr) {
+        return arithmetic(&loc, op, l, r, context, ctx, symtable, diagnostics, resolve_to);
+    }
+
+    match expr {
+        pt::Expression::NumberLiteral(loc, integer, exp, unit) => {
+            number_literal(loc, integer, exp, unit, ctx, diagnostics, resolve_to)
+        }
+        pt::Expression::Negate(loc, e) => {
+            negate(loc, e, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        pt::Expression::HexNumberLiteral(loc, n, unit) => {
+            hex_number_literal(loc, n, unit, ctx, diagnostics, resolve_to)
+        }
+        pt::Expression::RationalNumberLiteral(loc, integer, fraction, exp, unit) => {
+            rational_number_literal(loc, integer, fraction, exp, unit, ctx, diagnostics, resolve_to)
+        }
+        pt::Expression::AddressLiteral(loc, address) => address_literal(loc, address, diagnostics),
+        pt::Expression::BoolLiteral(loc, value) => {
+            Ok(Expression::BoolLiteral { loc: *loc, value: *value })
+        }
+        pt::Expression::StringLiteral(v) => string_literal(v, context.no, diagnostics, resolve_to),
+        pt::Expression::HexLiteral(v) => hex_literal(v, diagnostics, resolve_to),
+        pt::Expression::ArrayLiteral(loc, exprs) => {
+            array_literal(loc, exprs, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        pt::Expression::List(loc, list) => {
+            expression_list(loc, list, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::Parenthesis(_, e) => {
+            expression(e, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        pt::Expression::Variable(id) => variable(id, context, ctx, symtable, diagnostics),
+        pt::Expression::Type(loc, _) => {
+            // A type on its own, e.g. `uint;`, names nothing which has a value
+            let mut errors = Diagnostics::default();
+            let ty = ctx.resolve_type(
+                context.no,
+                context.contract_no,
+                ResolveTypeContext::None,
+                expr,
+                &mut errors,
+            );
+            let message = match ty {
+                Ok(ty) => format!("type '{}' cannot be used as a value", ty.to_string(ctx)),
+                Err(()) => "type cannot be used as a value".to_string(),
+            };
+            diagnostics.push(Diagnostic::error(*loc, message));
+            Err(())
+        }
+        pt::Expression::Assign(loc, l, r) => {
+            assign_single(loc, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::FunctionCall(loc, callee, args) if is_require(callee) => {
+            require(loc, args, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::MemberAccess(loc, e, member) if member.name == "selector" => {
+            selector(loc, e, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::MemberAccess(loc, e, member)
+            if member.name == "interfaceId" && type_operand(e).is_some() =>
+        {
+            interface_id(loc, e, context, ctx, diagnostics)
+        }
+        pt::Expression::MemberAccess(loc, e, member) => {
+            member_access(loc, e, member, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::ArraySubscript(loc, array, index) => {
+            array_subscript(loc, array, index, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::ArraySlice(loc, ..) => {
+            diagnostics.push(Diagnostic::error(*loc, "array slices are not supported"));
+            Err(())
+        }
+        pt::Expression::FunctionCall(loc, callee, args) => {
+            let args = Arguments::Positional(args);
+            function_call(loc, callee, args, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::NamedFunctionCall(loc, callee, args) => {
+            let args = Arguments::Named(args);
+            function_call(loc, callee, args, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::FunctionCallBlock(loc, ..) => {
+            diagnostics.push(Diagnostic::error(*loc, "expected function call arguments"));
+            Err(())
+        }
+        pt::Expression::Delete(loc, _) => {
+            diagnostics.push(Diagnostic::error(*loc, "'delete' cannot be used in an expression"));
+            Err(())
+        }
+        pt::Expression::New(loc, call) => new(loc, call, context, ctx, symtable, diagnostics),
+        pt::Expression::Power(loc, b, e) => {
+            power(loc, b, e, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        pt::Expression::ShiftLeft(loc, l, r) => {
+            shift(loc, true, l, r, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        pt::Expression::ShiftRight(loc, l, r) => {
+            shift(loc, false, l, r, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        pt::Expression::Equal(loc, l, r) => {
+            comparison(loc, Comparison::Equal, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::NotEqual(loc, l, r) => {
+            comparison(loc, Comparison::NotEqual, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::Less(loc, l, r) => {
+            comparison(loc, Comparison::Less, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::More(loc, l, r) => {
+            comparison(loc, Comparison::More, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::LessEqual(loc, l, r) => {
+            comparison(loc, Comparison::LessEqual, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::MoreEqual(loc, l, r) => {
+            comparison(loc, Comparison::MoreEqual, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::BitwiseNot(loc, e) => {
+            bitwise_not(loc, e, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        pt::Expression::UnaryPlus(loc, _) => {
+            diagnostics.push(Diagnostic::error(*loc, "unary plus is not supported"));
+            Err(())
+        }
+        pt::Expression::Not(loc, e) => not(loc, e, context, ctx, symtable, diagnostics),
+        pt::Expression::And(loc, l, r) => {
+            and_or(loc, true, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::Or(loc, l, r) => {
+            and_or(loc, false, l, r, context, ctx, symtable, diagnostics)
+        }
+        pt::Expression::ConditionalOperator(loc, c, t, f) => {
+            conditional_operator(loc, c, t, f, context, ctx, symtable, diagnostics, resolve_to)
+        }
+        // Handled before the match
+        pt::Expression::PreIncrement(..) |
+        pt::Expression::PreDecrement(..) |
+        pt::Expression::PostIncrement(..) |
+        pt::Expression::PostDecrement(..) |
+        pt::Expression::AssignOr(..) |
+        pt::Expression::AssignAnd(..) |
+        pt::Expression::AssignXor(..) |
+        pt::Expression::AssignShiftLeft(..) |
+        pt::Expression::AssignShiftRight(..) |
+        pt::Expression::AssignAdd(..) |
+        pt::Expression::AssignSubtract(..) |
+        pt::Expression::AssignMultiply(..) |
+        pt::Expression::AssignDivide(..) |
+        pt::Expression::AssignModulo(..) |
+        pt::Expression::Add(..) |
+        pt::Expression::Subtract(..) |
+        pt::Expression::Multiply(..) |
+        pt::Expression::Divide(..) |
+        pt::Expression::Modulo(..) |
+        pt::Expression::BitwiseOr(..) |
+        pt::Expression::BitwiseAnd(..) |
+        pt::Expression::BitwiseXor(..) => unreachable!(),
+    }
+}
+
+/// Resolve a list of expressions, `(a, b)`, whose elements cannot be omitted
+/// where a value is expected
+fn expression_list(
+    loc: &pt::Loc,
+    list: &pt::ParameterList,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let mut resolved = Vec::new();
+    for (param_loc, param) in list {
+        match param {
+            Some(pt::Parameter { ty, storage: None, name: None, .. }) => {
+                resolved.push(expression(
+                    ty,
+                    context,
+                    ctx,
+                    symtable,
+                    diagnostics,
+                    ResolveTo::Unknown,
+                )?);
+            }
+            Some(_) => {
+                diagnostics.push(Diagnostic::error(
+                    *param_loc,
+                    "declarations are not allowed in an expression",
+                ));
+                return Err(());
+            }
+            None => {
+                diagnostics.push(Diagnostic::error(*param_loc, "missing expression in list"));
+                return Err(());
+            }
+        }
+    }
+
+    Ok(Expression::List { loc: *loc, list: resolved })
+}
+
+fn is_require(callee: &pt::Expression) -> bool {
+    matches!(callee, pt::Expression::Variable(id) if id.name == "require")
 }
@@ -73,7 +73,8 @@ impl RetrieveType for Expression {
             Expression::ExternalFunction { ty, .. } |
             Expression::NamedMember { ty, .. } |
             Expression::StorageArrayLength { ty, .. } |
-            Expression::EventSelector { ty, .. } => ty.clone(),
+            Expression::EventSelector { ty, .. } |
+            Expression::InterfaceId { ty, .. } => ty.clone(),
             Expression::ExternalFunctionCallRaw { .. } => {
                 panic!("two return values");
             }
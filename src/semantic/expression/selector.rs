@@ -0,0 +1,285 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics},
+    parser::ast as pt,
+    semantic::{
+        ast::{Builtin, Expression, RetrieveType, Type},
+        context::Context,
+        expression::{resolve_expression::expression, ExprContext, ResolveTo},
+        symtable::Symtable,
+    },
+};
+
+/// Resolve `X.selector`, where `X` names an event or a function
+#[allow(clippy::result_unit_err)]
+pub fn selector(
+    loc: &pt::Loc,
+    expr: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    // Anything else is reported when resolved as a function
+    if let Some(name) = identifier_path(expr) {
+        let mut errors = Diagnostics::default();
+        if let Ok(events) =
+            ctx.resolve_event_with_namespace(context.no, context.contract_no, &name, &mut errors)
+        {
+            return event_selector(loc, &events, ctx, diagnostics);
+        }
+    }
+
+    let function = expression(expr, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+    function_selector(loc, function, ctx, diagnostics)
+}
+
+/// The selector of a function, of type `bytes4`. It is known for a function
+/// named in the source, and read from a function pointer otherwise.
+pub(crate) fn function_selector(
+    loc: &pt::Loc,
+    function: Expression,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    match &function {
+        Expression::InternalFunction { function_no, .. } |
+        Expression::ExternalFunction { function_no, .. } => {
+            let func = &ctx.functions[*function_no];
+            if !func.is_public() {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    format!("function '{}' is not public or external and has no selector", func.id),
+                ));
+                return Err(());
+            }
+            Ok(Expression::BytesLiteral { loc: *loc, ty: Type::Bytes(4), value: func.selector() })
+        }
+        _ if matches!(function.ty().deref_any(), Type::ExternalFunction { .. }) => {
+            Ok(Expression::Builtin {
+                loc: *loc,
+                tys: vec![Type::Bytes(4)],
+                kind: Builtin::FunctionSelector,
+                args: vec![function],
+            })
+        }
+        _ => {
+            diagnostics.push(Diagnostic::type_error(
+                *loc,
+                format!(
+                    "'selector' is not a member of '{}'",
+                    function.ty().deref_any().to_string(ctx)
+                ),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// The selector of an event, the first topic of its logs, of type `bytes32`
+pub(crate) fn event_selector(
+    loc: &pt::Loc,
+    events: &[(pt::Loc, usize)],
+    ctx: &mut Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let event_no = match events {
+        [(_, event_no)] => *event_no,
+        _ => {
+            let event = &ctx.events[events[0].1];
+            diagnostics.push(Diagnostic::error(
+                *loc,
+                format!("selector of overloaded event '{}' is ambiguous", event.id),
+            ));
+            return Err(());
+        }
+    };
+
+    let event = &mut ctx.events[event_no];
+    if event.anonymous {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!("anonymous event '{}' has no selector", event.id),
+        ));
+        return Err(());
+    }
+    event.used = true;
+
+    Ok(Expression::EventSelector { loc: *loc, ty: Type::Bytes(32), event_no })
+}
+
+/// Resolve `type(I).interfaceId`, the ERC-165 interface id of the interface `I`
+#[allow(clippy::result_unit_err)]
+pub fn interface_id(
+    loc: &pt::Loc,
+    expr: &pt::Expression,
+    context: &ExprContext,
+    ctx: &mut Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let Some(name) = type_operand(expr).and_then(identifier_path) else {
+        diagnostics.push(Diagnostic::error(*loc, "'interfaceId' expects the type of an interface"));
+        return Err(());
+    };
+
+    let contract_no = ctx.resolve_contract_with_namespace(context.no, &name, diagnostics)?;
+    contract_interface_id(loc, contract_no, ctx, diagnostics)
+}
+
+/// The interface id of a contract, of type `bytes4`, only interfaces have one
+pub(crate) fn contract_interface_id(
+    loc: &pt::Loc,
+    contract_no: usize,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let contract = &ctx.contracts[contract_no];
+    if !contract.is_interface() {
+        diagnostics.push(Diagnostic::error(
+            *loc,
+            format!(
+                "'interfaceId' is only available for interfaces, '{}' is a {}",
+                contract.id, contract.ty
+            ),
+        ));
+        return Err(());
+    }
+
+    Ok(Expression::InterfaceId { loc: *loc, ty: Type::Bytes(4), contract_no })
+}
+
+/// The operand of `type(X)`
+pub(super) fn type_operand(expr: &pt::Expression) -> Option<&pt::Expression> {
+    match expr {
+        pt::Expression::FunctionCall(_, callee, args) if args.len() == 1 => match callee.as_ref() {
+            pt::Expression::Variable(id) if id.name == "type" => Some(&args[0]),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The path of the names in `a.b.c`
+pub(crate) fn identifier_path(expr: &pt::Expression) -> Option<pt::IdentifierPath> {
+    match expr {
+        pt::Expression::Variable(id) => {
+            Some(pt::IdentifierPath { loc: id.loc, identifiers: vec![id.clone()] })
+        }
+        pt::Expression::MemberAccess(loc, expr, id) => {
+            let mut path = identifier_path(expr)?;
+            path.loc = *loc;
+            path.identifiers.push(id.clone());
+            Some(path)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abi::ethereum::keccak256,
+        semantic::{
+            ast::{EventDecl, Function, Parameter, RetrieveType},
+            layout::tests::contract,
+        },
+    };
+
+    fn event(name: &str, anonymous: bool) -> EventDecl {
+        EventDecl {
+            tags: Vec::new(),
+            id: pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+            loc: pt::Loc::Builtin,
+            contract: None,
+            fields: Vec::new(),
+            signature: String::new(),
+            anonymous,
+            used: false,
+        }
+    }
+
+    #[test]
+    fn test_event_selector() {
+        let mut ctx = Context::new();
+        ctx.events.push(event("Paused", false));
+        ctx.events.push(event("Sync", true));
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+
+        let selector = event_selector(&loc, &[(loc, 0)], &mut ctx, &mut diagnostics).unwrap();
+        assert!(ctx.events[0].used);
+        assert_eq!(
+            selector.eval_event_selector(&ctx),
+            Some(Expression::BytesLiteral {
+                loc,
+                ty: Type::Bytes(32),
+                value: keccak256(b"Paused()").to_vec()
+            })
+        );
+
+        assert!(event_selector(&loc, &[(loc, 1)], &mut ctx, &mut diagnostics).is_err());
+        assert_eq!(diagnostics.first_error(), "anonymous event 'Sync' has no selector");
+
+        let mut diagnostics = Diagnostics::default();
+        let overloads = [(loc, 0), (loc, 0)];
+        assert!(event_selector(&loc, &overloads, &mut ctx, &mut diagnostics).is_err());
+        assert_eq!(diagnostics.first_error(), "selector of overloaded event 'Paused' is ambiguous");
+    }
+
+    #[test]
+    fn test_interface_id() {
+        let mut ctx = Context::new();
+        let mut erc165 = contract("IERC165", &[], Vec::new());
+        erc165.ty = pt::ContractTy::Interface(pt::Loc::Builtin);
+        ctx.contracts.push(erc165);
+        ctx.contracts.push(contract("Token", &[0], Vec::new()));
+        let func = Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            pt::Identifier { loc: pt::Loc::Builtin, name: "supportsInterface".into() },
+            Some(0),
+            Vec::new(),
+            pt::FunctionTy::Function,
+            None,
+            pt::Visibility::External(None),
+            vec![Parameter::new_default(Type::Bytes(4))],
+            vec![Parameter::new_default(Type::Bool)],
+            &ctx,
+        );
+        ctx.functions.push(func);
+        ctx.contracts[0].functions.push(0);
+        let loc = pt::Loc::Builtin;
+        let mut diagnostics = Diagnostics::default();
+
+        let id = contract_interface_id(&loc, 0, &ctx, &mut diagnostics).unwrap();
+        assert_eq!(id.ty(), Type::Bytes(4));
+        assert_eq!(
+            id.eval_interface_id(&ctx),
+            Some(Expression::BytesLiteral {
+                loc,
+                ty: Type::Bytes(4),
+                value: vec![0x01, 0xff, 0xc9, 0xa7]
+            })
+        );
+
+        assert!(contract_interface_id(&loc, 1, &ctx, &mut diagnostics).is_err());
+        assert_eq!(
+            diagnostics.first_error(),
+            "'interfaceId' is only available for interfaces, 'Token' is a contract"
+        );
+    }
+}
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::{
-    diagnostics::{Diagnostic, Diagnostics},
+    diagnostics::{Diagnostic, DiagnosticSink},
     parser::ast as pt,
 };
 
@@ -22,7 +22,7 @@ pub(crate) fn unescape(
     literal: &str,
     start: usize,
     no: usize,
-    diagnostics: &mut Diagnostics,
+    diagnostics: &mut dyn DiagnosticSink,
 ) -> (bool, Vec<u8>) {
     let mut s: Vec<u8> = Vec::new();
     let mut indeces = literal.char_indices();
@@ -0,0 +1,243 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Index access, `a[i]`, on mappings, arrays, `bytes` and `bytesN`. The
+//! element of a storage array or mapping is a storage reference and that of a
+//! memory array a reference, so both can be assigned; the bytes of a `bytesN`
+//! are values. A constant index into a fixed length array is checked against
+//! its length.
+
+use num_bigint::BigInt;
+use num_traits::Signed;
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{ArrayLength, Expression, RetrieveType, Type},
+        context::Context,
+        expression::{resolve_expression::expression, ExprContext, ResolveTo},
+        symtable::Symtable,
+    },
+};
+
+/// Resolve `array[index]`
+pub(crate) fn array_subscript(
+    loc: &pt::Loc,
+    array: &pt::Expression,
+    index: &Option<Box<pt::Expression>>,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let Some(index) = index else {
+        diagnostics.push(Diagnostic::error(*loc, "index expression cannot be omitted"));
+        return Err(());
+    };
+
+    let array = expression(array, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+
+    // The index is read, even when the element is written
+    let lvalue = std::mem::replace(&mut context.lvalue, false);
+    let index = match array.ty().deref_any() {
+        Type::Mapping(mapping) => {
+            let key = mapping.key.as_ref().clone();
+            expression(index, context, ctx, symtable, diagnostics, ResolveTo::Type(&key))
+        }
+        _ => expression(index, context, ctx, symtable, diagnostics, ResolveTo::Integer),
+    };
+    context.lvalue = lvalue;
+
+    resolve_subscript(loc, array, index?, ctx, diagnostics)
+}
+
+/// The element of the resolved array, mapping or bytes at the resolved index
+pub(crate) fn resolve_subscript(
+    loc: &pt::Loc,
+    array: Expression,
+    index: Expression,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let array_ty = array.ty();
+    let storage = array_ty.is_contract_storage();
+    let deref_ty = array_ty.deref_any().clone();
+
+    let (elem_ty, length) = match &deref_ty {
+        Type::Mapping(mapping) => {
+            let index = index.cast(&index.loc(), &mapping.key, true, ctx, diagnostics)?;
+            return Ok(Expression::Subscript {
+                loc: *loc,
+                ty: Type::StorageRef(false, mapping.value.clone()),
+                array_ty,
+                array: Box::new(array),
+                index: Box::new(index),
+            });
+        }
+        Type::Array(_, dims) => {
+            let length = match dims.last() {
+                Some(ArrayLength::Fixed(length)) => Some(length.clone()),
+                _ => None,
+            };
+            (deref_ty.array_elem(), length)
+        }
+        Type::DynamicBytes => (Type::Bytes(1), None),
+        Type::Bytes(length) => (Type::Bytes(1), Some(BigInt::from(*length))),
+        Type::String => {
+            diagnostics.push(Diagnostic::type_error(
+                *loc,
+                "index access is not possible for 'string', convert it to 'bytes' first",
+            ));
+            return Err(());
+        }
+        ty => {
+            diagnostics.push(Diagnostic::type_error(
+                array.loc(),
+                format!("expression of type '{}' cannot be indexed", ty.to_string(ctx)),
+            ));
+            return Err(());
+        }
+    };
+
+    let index = index_operand(index, length.as_ref(), ctx, diagnostics)?;
+
+    // The bytes of a value are values, the elements of arrays are references
+    let ty = match (&deref_ty, storage) {
+        (Type::Bytes(_), _) => elem_ty,
+        (_, true) => Type::StorageRef(false, Box::new(elem_ty)),
+        (_, false) if elem_ty.can_have_data_location() => elem_ty,
+        (_, false) => Type::Ref(Box::new(elem_ty)),
+    };
+
+    Ok(Expression::Subscript {
+        loc: *loc,
+        ty,
+        array_ty,
+        array: Box::new(array),
+        index: Box::new(index),
+    })
+}
+
+/// Cast the index to an unsigned integer, checking a constant one is within
+/// the length
+fn index_operand(
+    index: Expression,
+    length: Option<&BigInt>,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    if let Expression::NumberLiteral { value, .. } = &index {
+        if value.is_negative() {
+            diagnostics.push(Diagnostic::type_error(index.loc(), "negative array index"));
+            return Err(());
+        }
+        if let Some(length) = length.filter(|length| value >= *length) {
+            diagnostics.push(Diagnostic::type_error(
+                index.loc(),
+                format!("array index {value} out of bounds for length {length}"),
+            ));
+            return Err(());
+        }
+        return index.cast(&index.loc(), &Type::Uint(256), true, ctx, diagnostics);
+    }
+
+    match index.ty().deref_any() {
+        ty @ Type::Uint(_) => {
+            let ty = ty.clone();
+            index.cast(&index.loc(), &ty, true, ctx, diagnostics)
+        }
+        ty => {
+            diagnostics.push(Diagnostic::type_error(
+                index.loc(),
+                format!("array index of type '{}' must be an unsigned integer", ty.to_string(ctx)),
+            ));
+            Err(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diagnostics::Diagnostics, semantic::ast::Mapping};
+
+    fn number(value: i64) -> Expression {
+        Expression::NumberLiteral { loc: pt::Loc::Builtin, ty: Type::Uint(8), value: value.into() }
+    }
+
+    fn variable(ty: Type) -> Expression {
+        Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 }
+    }
+
+    fn subscript(array: Expression, index: Expression) -> Result<Type, String> {
+        let ctx = Context::new();
+        let mut diagnostics = Diagnostics::default();
+        resolve_subscript(&pt::Loc::Builtin, array, index, &ctx, &mut diagnostics)
+            .map(|expr| expr.ty())
+            .map_err(|_| diagnostics.first_error())
+    }
+
+    #[test]
+    fn test_subscripts() {
+        let fixed = Type::Array(Box::new(Type::Uint(8)), vec![ArrayLength::Fixed(3.into())]);
+        let nested = Type::Array(
+            Box::new(Type::Uint(8)),
+            vec![ArrayLength::Fixed(3.into()), ArrayLength::Dynamic],
+        );
+        let storage = |ty: Type| Type::StorageRef(false, Box::new(ty));
+
+        assert_eq!(
+            subscript(variable(fixed.clone()), number(2)),
+            Ok(Type::Ref(Box::new(Type::Uint(8))))
+        );
+        assert_eq!(
+            subscript(variable(storage(fixed.clone())), number(2)),
+            Ok(storage(Type::Uint(8)))
+        );
+        assert_eq!(subscript(variable(storage(nested)), number(7)), Ok(storage(fixed.clone())));
+        assert_eq!(subscript(variable(Type::Bytes(4)), number(3)), Ok(Type::Bytes(1)));
+
+        let mapping = Type::Mapping(Mapping {
+            key: Box::new(Type::Address(false)),
+            key_name: None,
+            value: Box::new(Type::Uint(256)),
+            value_name: None,
+        });
+        let key = variable(Type::Address(false));
+        assert_eq!(subscript(variable(storage(mapping)), key), Ok(storage(Type::Uint(256))));
+
+        assert_eq!(
+            subscript(variable(fixed.clone()), number(3)).unwrap_err(),
+            "array index 3 out of bounds for length 3"
+        );
+        assert_eq!(
+            subscript(variable(fixed.clone()), number(-1)).unwrap_err(),
+            "negative array index"
+        );
+        assert_eq!(
+            subscript(variable(fixed), variable(Type::Int(8))).unwrap_err(),
+            "array index of type 'int8' must be an unsigned integer"
+        );
+        assert_eq!(
+            subscript(variable(Type::String), number(0)).unwrap_err(),
+            "index access is not possible for 'string', convert it to 'bytes' first"
+        );
+        assert_eq!(
+            subscript(variable(Type::Bool), number(0)).unwrap_err(),
+            "expression of type 'bool' cannot be indexed"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Short renderings of resolved expressions for diagnostics: the operator of
+//! the expression with the types of its operands, as in `uint8 + int16`,
+//! `-int8`, `uint8(int16)` for a conversion or `f(uint256,bool)` for a call.
+//! The operands are not rendered themselves, so a summary stays short however
+//! deep the expression is, and an expression without an operator, like a
+//! variable or a literal, is rendered as its type. Builtins are rendered by
+//! the names they are written with, as in `msg.sender` or `keccak256(bytes)`.
+
+use std::fmt;
+
+use crate::semantic::{
+    ast::{Builtin, CallTy, Expression, RetrieveType, StringLocation, Type},
+    context::Context,
+    expression::builtin::{BUILTIN_FUNCTIONS, BUILTIN_VARIABLES},
+    types::TypeDisplay,
+};
+
+/// Summary of an expression, see [`Expression::summary`]
+pub struct ExpressionSummary<'a> {
+    expr: &'a Expression,
+    ctx: Option<&'a Context>,
+}
+
+impl Expression {
+    /// Summarize the expression, with the names of the declarations its
+    /// types refer to
+    pub fn summary<'a>(&'a self, ctx: &'a Context) -> ExpressionSummary<'a> {
+        ExpressionSummary { expr: self, ctx: Some(ctx) }
+    }
+
+    /// Summarize the expression without a context, with the types displayed
+    /// as by [`Type::display_bare`]
+    pub fn summary_bare(&self) -> ExpressionSummary<'_> {
+        ExpressionSummary { expr: self, ctx: None }
+    }
+}
+
+impl ExpressionSummary<'_> {
+    fn ty<'b>(&'b self, ty: &'b Type) -> TypeDisplay<'b> {
+        match self.ctx {
+            Some(ctx) => ty.display(ctx),
+            None => ty.display_bare(),
+        }
+    }
+
+    /// The type of an operand, or the types of the values of a call or list
+    /// which does not have a single one
+    fn operand(&self, f: &mut fmt::Formatter<'_>, expr: &Expression) -> fmt::Result {
+        match expr {
+            Expression::ExternalFunctionCallRaw { .. } => f.write_str("(bool,bytes)"),
+            Expression::InternalFunctionCall { returns, .. } |
+            Expression::ExternalFunctionCall { returns, .. } |
+            Expression::Builtin { tys: returns, .. }
+                if returns.len() != 1 =>
+            {
+                f.write_str("(")?;
+                for (i, ty) in returns.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", self.ty(ty))?;
+                }
+                f.write_str(")")
+            }
+            Expression::List { list, .. } if list.len() != 1 => self.list(f, list),
+            _ => write!(f, "{}", self.ty(&expr.ty())),
+        }
+    }
+
+    fn list(&self, f: &mut fmt::Formatter<'_>, exprs: &[Expression]) -> fmt::Result {
+        f.write_str("(")?;
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            self.operand(f, expr)?;
+        }
+        f.write_str(")")
+    }
+
+    fn binary(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        left: &Expression,
+        op: &str,
+        right: &Expression,
+    ) -> fmt::Result {
+        self.operand(f, left)?;
+        write!(f, " {op} ")?;
+        self.operand(f, right)
+    }
+
+    fn prefix(&self, f: &mut fmt::Formatter<'_>, op: &str, expr: &Expression) -> fmt::Result {
+        f.write_str(op)?;
+        self.operand(f, expr)
+    }
+
+    fn postfix(&self, f: &mut fmt::Formatter<'_>, expr: &Expression, op: &str) -> fmt::Result {
+        self.operand(f, expr)?;
+        f.write_str(op)
+    }
+
+    fn string(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        string: &StringLocation<Expression>,
+    ) -> fmt::Result {
+        match string {
+            StringLocation::CompileTime(_) => f.write_str("string"),
+            StringLocation::RunTime(expr) => self.operand(f, expr),
+        }
+    }
+
+    /// A builtin by its name, with the types of its arguments when it is a
+    /// function, or its type when it is not declared as a builtin
+    fn builtin(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        expr: &Expression,
+        kind: Builtin,
+        args: &[Expression],
+    ) -> fmt::Result {
+        let variable = BUILTIN_VARIABLES.iter().find(|p| p.builtin == kind);
+        let function = BUILTIN_FUNCTIONS.iter().find(|p| p.builtin == kind);
+        let Some(prototype) = variable.or(function) else {
+            return self.operand(f, expr);
+        };
+
+        if let Some(namespace) = prototype.namespace {
+            write!(f, "{namespace}.")?;
+        }
+        f.write_str(prototype.name)?;
+        match function {
+            Some(_) => self.list(f, args),
+            None => Ok(()),
+        }
+    }
+
+    /// The function called, by its name when it is known
+    fn callee(&self, f: &mut fmt::Formatter<'_>, function: &Expression) -> fmt::Result {
+        match (function, self.ctx) {
+            (Expression::InternalFunction { id, .. }, _) => write!(f, "{id}"),
+            (Expression::ExternalFunction { address, function_no, .. }, Some(ctx)) => {
+                self.operand(f, address)?;
+                write!(f, ".{}", ctx.functions[*function_no].id)
+            }
+            (Expression::ExternalFunction { address, function_no, .. }, None) => {
+                self.operand(f, address)?;
+                write!(f, ".function #{function_no}")
+            }
+            _ => self.operand(f, function),
+        }
+    }
+}
+
+impl fmt::Display for ExpressionSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expr {
+            Expression::Add { left, right, .. } => self.binary(f, left, "+", right),
+            Expression::Subtract { left, right, .. } => self.binary(f, left, "-", right),
+            Expression::Multiply { left, right, .. } => self.binary(f, left, "*", right),
+            Expression::Divide { left, right, .. } => self.binary(f, left, "/", right),
+            Expression::Modulo { left, right, .. } => self.binary(f, left, "%", right),
+            Expression::Power { base, exp, .. } => self.binary(f, base, "**", exp),
+            Expression::BitwiseOr { left, right, .. } => self.binary(f, left, "|", right),
+            Expression::BitwiseAnd { left, right, .. } => self.binary(f, left, "&", right),
+            Expression::BitwiseXor { left, right, .. } => self.binary(f, left, "^", right),
+            Expression::ShiftLeft { left, right, .. } => self.binary(f, left, "<<", right),
+            Expression::ShiftRight { left, right, .. } => self.binary(f, left, ">>", right),
+            Expression::Assign { left, right, .. } => self.binary(f, left, "=", right),
+            Expression::More { left, right, .. } => self.binary(f, left, ">", right),
+            Expression::Less { left, right, .. } => self.binary(f, left, "<", right),
+            Expression::MoreEqual { left, right, .. } => self.binary(f, left, ">=", right),
+            Expression::LessEqual { left, right, .. } => self.binary(f, left, "<=", right),
+            Expression::Equal { left, right, .. } => self.binary(f, left, "==", right),
+            Expression::NotEqual { left, right, .. } => self.binary(f, left, "!=", right),
+            Expression::Or { left, right, .. } => self.binary(f, left, "||", right),
+            Expression::And { left, right, .. } => self.binary(f, left, "&&", right),
+            Expression::StringCompare { left, right, .. } => {
+                self.string(f, left)?;
+                f.write_str(" == ")?;
+                self.string(f, right)
+            }
+            Expression::Not { expr, .. } => self.prefix(f, "!", expr),
+            Expression::BitwiseNot { expr, .. } => self.prefix(f, "~", expr),
+            Expression::Negate { expr, .. } => self.prefix(f, "-", expr),
+            Expression::PreIncrement { expr, .. } => self.prefix(f, "++", expr),
+            Expression::PreDecrement { expr, .. } => self.prefix(f, "--", expr),
+            Expression::PostIncrement { expr, .. } => self.postfix(f, expr, "++"),
+            Expression::PostDecrement { expr, .. } => self.postfix(f, expr, "--"),
+            Expression::ZeroExt { to, expr, .. } |
+            Expression::SignExt { to, expr, .. } |
+            Expression::Trunc { to, expr, .. } |
+            Expression::CheckingTrunc { to, expr, .. } |
+            Expression::Cast { to, expr, .. } |
+            Expression::BytesCast { to, expr, .. } => {
+                write!(f, "{}(", self.ty(to))?;
+                self.postfix(f, expr, ")")
+            }
+            Expression::ConditionalOperator { cond, true_option, false_option, .. } => {
+                self.binary(f, cond, "?", true_option)?;
+                f.write_str(" : ")?;
+                self.operand(f, false_option)
+            }
+            Expression::Subscript { array, index, .. } => {
+                self.operand(f, array)?;
+                f.write_str("[")?;
+                self.postfix(f, index, "]")
+            }
+            Expression::NamedMember { array, name, .. } => {
+                self.postfix(f, array, &format!(".{name}"))
+            }
+            Expression::StructMember { expr, field, .. } => {
+                self.operand(f, expr)?;
+                match (expr.ty().deref_any(), self.ctx) {
+                    (Type::Struct(str_ty), Some(ctx)) => {
+                        write!(f, ".{}", str_ty.definition(ctx).fields[*field].name_as_str())
+                    }
+                    _ => write!(f, ".field #{field}"),
+                }
+            }
+            Expression::StorageArrayLength { array, .. } => self.postfix(f, array, ".length"),
+            Expression::InternalFunctionCall { function, args, .. } |
+            Expression::ExternalFunctionCall { function, args, .. } => {
+                self.callee(f, function)?;
+                self.list(f, args)
+            }
+            Expression::ExternalFunctionCallRaw { ty, address, args, .. } => {
+                self.operand(f, address)?;
+                match ty {
+                    CallTy::Regular => f.write_str(".call(")?,
+                    CallTy::Delegate => f.write_str(".delegatecall(")?,
+                    CallTy::Static => f.write_str(".staticcall(")?,
+                }
+                self.postfix(f, args, ")")
+            }
+            Expression::Constructor { contract_no, args, .. } => {
+                write!(f, "new {}", self.ty(&Type::Contract(*contract_no)))?;
+                self.list(f, args)
+            }
+            Expression::AllocDynamicBytes { ty, length, .. } => {
+                write!(f, "new {}(", self.ty(ty))?;
+                self.postfix(f, length, ")")
+            }
+            Expression::UserDefinedOperator { oper, args, .. } => match args.as_slice() {
+                [expr] => self.prefix(f, &oper.to_string(), expr),
+                [left, right] => self.binary(f, left, &oper.to_string(), right),
+                _ => self.list(f, args),
+            },
+            Expression::Builtin { kind, args, .. } => self.builtin(f, self.expr, *kind, args),
+            Expression::List { list, .. } => self.list(f, list),
+            expr => self.operand(f, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::ast as pt, semantic::ast::EnumDecl};
+
+    fn variable(ty: Type) -> Box<Expression> {
+        Box::new(Expression::Variable { loc: pt::Loc::Builtin, ty, var_no: 0 })
+    }
+
+    #[test]
+    fn test_summary() {
+        let loc = pt::Loc::Builtin;
+        let add = Expression::Add {
+            loc,
+            ty: Type::Int(16),
+            unchecked: false,
+            left: variable(Type::Uint(8)),
+            right: variable(Type::Int(16)),
+        };
+        assert_eq!(add.summary_bare().to_string(), "uint8 + int16");
+
+        let cast = Expression::Cast { loc, to: Type::Uint(8), expr: Box::new(add.clone()) };
+        assert_eq!(cast.summary_bare().to_string(), "uint8(int16)");
+
+        let negate = Expression::Negate {
+            loc,
+            ty: Type::Int(8),
+            unchecked: false,
+            expr: variable(Type::Int(8)),
+        };
+        assert_eq!(negate.summary_bare().to_string(), "-int8");
+
+        let call = Expression::InternalFunctionCall {
+            loc,
+            returns: Vec::new(),
+            function: Box::new(Expression::InternalFunction {
+                loc,
+                id: pt::IdentifierPath {
+                    loc,
+                    identifiers: vec![pt::Identifier { loc, name: "f".into() }],
+                },
+                ty: Type::Void,
+                function_no: 0,
+                signature: None,
+            }),
+            args: vec![*variable(Type::Uint(256)), Expression::BoolLiteral { loc, value: true }],
+        };
+        assert_eq!(call.summary_bare().to_string(), "f(uint256,bool)");
+
+        let sender = Expression::Builtin {
+            loc,
+            tys: vec![Type::Address(false)],
+            kind: Builtin::Sender,
+            args: Vec::new(),
+        };
+        assert_eq!(sender.summary_bare().to_string(), "msg.sender");
+
+        let hash = Expression::Builtin {
+            loc,
+            tys: vec![Type::Bytes(32)],
+            kind: Builtin::Keccak256,
+            args: vec![*variable(Type::DynamicBytes)],
+        };
+        assert_eq!(hash.summary_bare().to_string(), "keccak256(bytes)");
+
+        let equal =
+            Expression::Equal { loc, left: variable(Type::Enum(0)), right: variable(Type::Value) };
+        assert_eq!(equal.summary_bare().to_string(), "enum #0 == value");
+
+        let mut ctx = Context::new();
+        ctx.enums.push(EnumDecl {
+            id: pt::Identifier { loc, name: "Color".into() },
+            contract: None,
+            loc,
+            ty: Type::Uint(8),
+            values: Default::default(),
+        });
+        assert_eq!(equal.summary(&ctx).to_string(), "enum Color == uint128");
+        assert_eq!(variable(Type::Bool).summary(&ctx).to_string(), "bool");
+    }
+}
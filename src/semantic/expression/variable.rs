@@ -0,0 +1,233 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Identifiers used as values. A name is looked up in the scopes of the
+//! function, innermost first, then in the contract and its bases, and then in
+//! the file. State variables are storage references, which are loaded where
+//! their value is needed.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    parser::ast as pt,
+    semantic::{
+        ast::{Builtin, Expression, Symbol, Type},
+        context::Context,
+        expression::{builtin::is_builtin_namespace, ExprContext},
+        symtable::Symtable,
+    },
+};
+
+/// Resolve the identifier as a value
+pub(crate) fn variable(
+    id: &pt::Identifier,
+    context: &ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    if id.name == "this" {
+        return match context.contract_no {
+            Some(contract_no) => Ok(Expression::Builtin {
+                loc: id.loc,
+                tys: vec![Type::Contract(contract_no)],
+                kind: Builtin::GetAddress,
+                args: Vec::new(),
+            }),
+            None => {
+                diagnostics.push(Diagnostic::error(id.loc, "'this' used outside of a contract"));
+                Err(())
+            }
+        };
+    }
+
+    if let Some(var_no) =
+        context.active_scopes.iter().rev().find_map(|scope| scope.names.get(&id.name))
+    {
+        let var = symtable.vars.get_mut(var_no).unwrap();
+        if !context.lvalue {
+            var.read = true;
+        }
+        return Ok(Expression::Variable { loc: id.loc, ty: var.ty.clone(), var_no: *var_no });
+    }
+
+    let symbol = ctx.find_symbol(context.no, context.contract_no, &id.name, true).cloned();
+    match symbol {
+        Some(Symbol::Variable(_, Some(var_contract), var_no)) => {
+            let var = &mut ctx.contracts[var_contract].variables[var_no];
+            if matches!(var.visibility, pt::Visibility::Private(_)) &&
+                Some(var_contract) != context.contract_no
+            {
+                diagnostics.push(Diagnostic::error(
+                    id.loc,
+                    format!(
+                        "'{}' is private to contract '{}'",
+                        id.name, ctx.contracts[var_contract].id.name
+                    ),
+                ));
+                return Err(());
+            }
+            if !context.lvalue {
+                var.read = true;
+            }
+
+            Ok(if var.constant {
+                Expression::ConstantVariable {
+                    loc: id.loc,
+                    ty: var.ty.clone(),
+                    contract_no: Some(var_contract),
+                    var_no,
+                }
+            } else {
+                Expression::StorageVariable {
+                    loc: id.loc,
+                    ty: Type::StorageRef(var.immutable, Box::new(var.ty.clone())),
+                    contract_no: var_contract,
+                    var_no,
+                }
+            })
+        }
+        Some(Symbol::Variable(_, None, var_no)) => {
+            let var = &mut ctx.constants[var_no];
+            if !context.lvalue {
+                var.read = true;
+            }
+            Ok(Expression::ConstantVariable {
+                loc: id.loc,
+                ty: var.ty.clone(),
+                contract_no: None,
+                var_no,
+            })
+        }
+        Some(Symbol::Function(functions)) if functions.len() == 1 => {
+            let function_no = functions[0].1;
+            let func = &ctx.functions[function_no];
+            Ok(Expression::InternalFunction {
+                loc: id.loc,
+                id: pt::IdentifierPath { loc: id.loc, identifiers: vec![id.clone()] },
+                ty: func.function_type(false),
+                function_no,
+                // Virtual functions are called through the most derived contract
+                signature: func.is_virtual.then(|| func.signature.clone()),
+            })
+        }
+        Some(Symbol::Function(_)) => {
+            diagnostics.push(Diagnostic::error(
+                id.loc,
+                format!("function '{}' is overloaded, call it to select one", id.name),
+            ));
+            Err(())
+        }
+        None if is_builtin_namespace(&id.name) => {
+            diagnostics.push(Diagnostic::error(
+                id.loc,
+                format!("builtin '{}' cannot be used as a value", id.name),
+            ));
+            Err(())
+        }
+        symbol => {
+            diagnostics.push(Context::wrong_symbol(symbol.as_ref(), id));
+            Err(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::Diagnostics,
+        semantic::{
+            layout::tests::{contract, variable as state_variable},
+            symtable::VarScope,
+        },
+    };
+    use std::collections::HashMap;
+
+    fn id(name: &str) -> pt::Identifier {
+        pt::Identifier { loc: pt::Loc::Builtin, name: name.to_string() }
+    }
+
+    #[test]
+    fn test_resolve_variables() {
+        let mut ctx = Context::new();
+        let mut base = contract("Base", &[], vec![state_variable("secret", Type::Uint(8), None)]);
+        base.variables[0].visibility = pt::Visibility::Private(None);
+        ctx.contracts.push(base);
+        let mut token =
+            contract("Token", &[0], vec![state_variable("total", Type::Uint(256), None)]);
+        token.variables.push(state_variable("DECIMALS", Type::Uint(8), None));
+        token.variables[1].constant = true;
+        ctx.contracts.push(token);
+        for (contract_no, var_no, name) in [(0, 0, "secret"), (1, 0, "total"), (1, 1, "DECIMALS")] {
+            ctx.variable_symbols.insert(
+                (0, Some(contract_no), name.to_string()),
+                Symbol::Variable(pt::Loc::Builtin, Some(contract_no), var_no),
+            );
+        }
+
+        let mut symtable = Symtable::default();
+        let mut local = state_variable("amount", Type::Uint(64), None);
+        local.read = false;
+        symtable.vars.insert(0, local);
+        let context = ExprContext {
+            contract_no: Some(1),
+            active_scopes: vec![VarScope {
+                loc: None,
+                names: HashMap::from([("amount".to_string(), 0)]),
+            }],
+            ..Default::default()
+        };
+
+        let mut diagnostics = Diagnostics::default();
+        let mut resolve = |name: &str, ctx: &mut Context| {
+            variable(&id(name), &context, ctx, &mut symtable, &mut diagnostics)
+        };
+
+        let expr = resolve("amount", &mut ctx).unwrap();
+        assert_eq!(
+            expr,
+            Expression::Variable { loc: pt::Loc::Builtin, ty: Type::Uint(64), var_no: 0 }
+        );
+        let expr = resolve("total", &mut ctx).unwrap();
+        assert!(matches!(
+            expr,
+            Expression::StorageVariable {
+                ty: Type::StorageRef(false, _),
+                contract_no: 1,
+                var_no: 0,
+                ..
+            }
+        ));
+        assert!(ctx.contracts[1].variables[0].read);
+        let expr = resolve("DECIMALS", &mut ctx).unwrap();
+        assert!(matches!(expr, Expression::ConstantVariable { contract_no: Some(1), .. }));
+        let expr = resolve("this", &mut ctx).unwrap();
+        assert!(matches!(expr, Expression::Builtin { kind: Builtin::GetAddress, .. }));
+
+        assert!(resolve("secret", &mut ctx).is_err());
+        assert!(resolve("missing", &mut ctx).is_err());
+        assert!(resolve("msg", &mut ctx).is_err());
+        assert!(symtable.vars[&0].read);
+
+        let errors = diagnostics.errors().iter().map(|d| d.message.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "'secret' is private to contract 'Base'",
+                "'missing' not found",
+                "builtin 'msg' cannot be used as a value"
+            ]
+        );
+    }
+}
@@ -14,30 +14,125 @@
 
 use std::path::PathBuf;
 
+use crate::parser::ast as pt;
+
 /// Any Solidity file, either the main file or anything that was imported
 #[derive(Clone, Debug)]
 pub struct File {
     /// The on-disk filename
     pub path: PathBuf,
-    /// Used for offset to line-column conversions
+    /// Byte offsets of the starts of the lines after the first, used for
+    /// offset to line-column conversions
     pub line_starts: Vec<usize>,
+    /// Byte offsets and lengths of the characters which take more than one
+    /// byte in UTF-8, as columns count characters
+    pub multibyte_chars: Vec<(usize, usize)>,
     /// Indicates the file number in FileResolver.files
     pub cache_no: Option<usize>,
     /// Index into FileResolver.import_paths. This is `None` when this File was
     /// created not during `parse_and_resolve` (e.g., builtins)
     pub import_no: Option<usize>,
+    /// The parse tree of the file, without the items excluded by the
+    /// features. This is `None` until the file has been parsed.
+    pub tree: Option<pt::SourceUnit>,
 }
 
 impl File {
     pub fn new(path: PathBuf, contents: &str, cache_no: usize, import_no: Option<usize>) -> Self {
-        let mut line_starts = Vec::new();
+        let (line_starts, multibyte_chars) = index(contents);
+        Self { path, line_starts, multibyte_chars, cache_no: Some(cache_no), import_no, tree: None }
+    }
+
+    /// A builtin module, which is not in the cache of the resolver
+    pub fn builtin(path: PathBuf, contents: &str) -> Self {
+        let (line_starts, multibyte_chars) = index(contents);
+        Self { path, line_starts, multibyte_chars, cache_no: None, import_no: None, tree: None }
+    }
 
-        for (indice, c) in contents.char_indices() {
-            if c == '\n' {
-                line_starts.push(indice + 1);
+    /// Give the zero-based line and column of a byte offset, the column
+    /// counted in characters
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let start = self.line_start(line);
+
+        // The bytes after the first of each character before the offset
+        let from = self.multibyte_chars.partition_point(|(pos, _)| *pos < start);
+        let to = self.multibyte_chars.partition_point(|(pos, _)| *pos < offset);
+        let extra: usize = self.multibyte_chars[from..to].iter().map(|(_, len)| len - 1).sum();
+
+        (line, offset - start - extra)
+    }
+
+    /// Give the byte offset of a zero-based line and column, the column
+    /// counted in characters. Returns `None` if the line is past the end of
+    /// the file.
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        if line > self.line_starts.len() {
+            return None;
+        }
+
+        let start = self.line_start(line);
+        let mut offset = start + column;
+        let from = self.multibyte_chars.partition_point(|(pos, _)| *pos < start);
+        for (pos, len) in &self.multibyte_chars[from..] {
+            if *pos >= offset {
+                break;
             }
+            offset += len - 1;
+        }
+
+        Some(offset)
+    }
+
+    /// The byte offset of the start of the zero-based line
+    fn line_start(&self, line: usize) -> usize {
+        match line {
+            0 => 0,
+            _ => self.line_starts[line - 1],
         }
+    }
+}
+
+/// The byte offsets of the starts of the lines after the first, and the
+/// characters which take more than one byte
+fn index(contents: &str) -> (Vec<usize>, Vec<(usize, usize)>) {
+    let mut line_starts = Vec::new();
+    let mut multibyte_chars = Vec::new();
+
+    for (i, c) in contents.char_indices() {
+        match c {
+            '\n' => line_starts.push(i + 1),
+            c if c.len_utf8() > 1 => multibyte_chars.push((i, c.len_utf8())),
+            _ => (),
+        }
+    }
+
+    (line_starts, multibyte_chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multibyte_columns() {
+        let src = "// 🦀 crab\n/* 中文注释 */ uint x;\nuint y; // é\n";
+        let file = File::new("test.sol".into(), src, 0, None);
+
+        let x = src.find("x;").unwrap();
+        assert_eq!(file.line_column(x), (1, 16));
+        assert_eq!(file.offset(1, 16), Some(x));
+        let crab = src.find("crab").unwrap();
+        assert_eq!(file.line_column(crab), (0, 5));
+        assert_eq!(file.offset(0, 5), Some(crab));
+
+        // The characters of the lines before do not shift the columns
+        let y = src.find("y;").unwrap();
+        assert_eq!(file.line_column(y), (2, 5));
+        assert_eq!(file.offset(2, 5), Some(y));
+        assert_eq!(file.line_column(src.len() - 1), (2, 12));
 
-        Self { path, line_starts, cache_no: Some(cache_no), import_no }
+        assert_eq!(file.offset(3, 0), Some(src.len()));
+        assert_eq!(file.offset(4, 0), None);
     }
 }
@@ -16,14 +16,16 @@ use crate::{
     diagnostics::{Diagnostic, Diagnostics, Level},
     helpers::{CodeLocation, OptionalCodeLocation},
     parser::{
-        ast::{self as pt, FunctionDefinition, FunctionTy, Loc},
+        ast::{self as pt, FunctionTy, Loc},
         visitor::{Visitable, Visitor},
     },
     semantic::{
         ast::{ContractDefinition, Function, Parameter, ParameterAnnotation, Symbol, Type},
         context::{Context, ResolveTypeContext},
+        contract::is_base,
+        legacy,
         tag::resolve_tags,
-        visitor::{SemanticVisitable, SemanticVisitor},
+        visitor::SemanticVisitor,
     },
 };
 
@@ -43,7 +45,6 @@ pub struct FunctionResolver<'a> {
     contract_no: Option<usize>,
     returns_success: bool,
     returns: Vec<Parameter<Type>>,
-    resolve_bodies: Vec<(usize, Box<FunctionDefinition>)>,
 }
 
 impl<'a> FunctionResolver<'a> {
@@ -61,7 +62,6 @@ impl<'a> FunctionResolver<'a> {
             contract_no: None,
             returns_success: true,
             returns: Vec::new(),
-            resolve_bodies: Vec::new(),
         }
     }
 }
@@ -77,17 +77,29 @@ impl<'a> SemanticVisitor for FunctionResolver<'a> {
     ) -> Result<(), Self::Error> {
         if let pt::SourceUnitPart::FunctionDefinition(_) = part.part {
             self.ctx.reject(&part.annotations, "function");
-            part.visit(self)?;
+            part.part.visit(self)?;
         }
 
         Ok(())
     }
+
+    /// The functions of contracts are resolved with them, see
+    /// [`contract_function`]
+    fn visit_sema_contract(&mut self, _: &mut ContractDefinition) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 impl<'a> Visitor for FunctionResolver<'a> {
     type Error = FunctionResolverError;
 
     fn visit_function(&mut self, func: &mut pt::FunctionDefinition) -> Result<(), Self::Error> {
+        self.success = true;
+        self.mutability = None;
+        self.params_success = true;
+        self.params.clear();
+        self.returns_success = true;
+        self.returns.clear();
         self.func_ty.replace(func.ty);
 
         func.attributes.visit(self)?;
@@ -125,7 +137,7 @@ impl<'a> Visitor for FunctionResolver<'a> {
         };
 
         let doc = resolve_tags(
-            func.loc_prototype.no(),
+            self.no,
             "function",
             Some(&self.params),
             Some(&self.returns),
@@ -136,7 +148,7 @@ impl<'a> Visitor for FunctionResolver<'a> {
         let mut fdecl = Function::new(
             func.loc_prototype,
             func.loc,
-            name,
+            name.clone(),
             None,
             doc,
             func.ty,
@@ -149,8 +161,6 @@ impl<'a> Visitor for FunctionResolver<'a> {
 
         fdecl.has_body = true;
 
-        let id = func.name.as_ref().unwrap();
-
         if let Some(prev) = self.ctx.functions.iter().find(|f| fdecl.signature == f.signature) {
             self.ctx.diagnostics.push(
                 Diagnostic::builder(func.loc_prototype, Level::Error)
@@ -166,15 +176,13 @@ impl<'a> Visitor for FunctionResolver<'a> {
         self.ctx.functions.push(fdecl);
 
         if let Some(Symbol::Function(ref mut v)) =
-            self.ctx.function_symbols.get_mut(&(self.no, None, id.name.to_owned()))
+            self.ctx.function_symbols.get_mut(&(self.no, None, name.name.to_owned()))
         {
             v.push((func.loc_prototype, func_no));
         } else {
-            self.ctx.add_symbol(self.no, None, id, Symbol::Function(vec![(id.loc, func_no)]));
+            self.ctx.add_symbol(self.no, None, &name, Symbol::Function(vec![(name.loc, func_no)]));
         }
 
-        self.resolve_bodies.push((func_no, Box::new(func.clone())));
-
         Ok(())
     }
 
@@ -200,12 +208,8 @@ impl<'a> Visitor for FunctionResolver<'a> {
                     self.success = false;
                 }
 
+                // Reported with the legacy syntax
                 if let pt::Mutability::Constant(loc) = m {
-                    self.ctx.diagnostics.push(Diagnostic::warning(
-                        *loc,
-                        "'constant' is deprecated. Use 'view' instead",
-                    ));
-
                     self.mutability.replace(pt::Mutability::View(*loc));
                 } else {
                     self.mutability.replace(m.clone());
@@ -343,6 +347,7 @@ impl<'a> Visitor for FunctionResolver<'a> {
                     ty
                 };
 
+                let ty_id = self.ctx.type_arena.intern(&ty);
                 let annotation = parameter
                     .annotation
                     .as_ref()
@@ -352,6 +357,7 @@ impl<'a> Visitor for FunctionResolver<'a> {
                     loc: *loc,
                     id: parameter.name.clone(),
                     ty,
+                    ty_id: Some(ty_id),
                     ty_loc: Some(ty_loc),
                     indexed: false,
                     readonly: false,
@@ -457,10 +463,12 @@ impl<'a> Visitor for FunctionResolver<'a> {
                     }
                 };
 
+                let ty_id = self.ctx.type_arena.intern(&ty);
                 self.returns.push(Parameter {
                     loc: *loc,
                     id: parameter.name.clone(),
                     ty,
+                    ty_id: Some(ty_id),
                     ty_loc: Some(ty_loc),
                     indexed: false,
                     readonly: false,
@@ -477,13 +485,632 @@ impl<'a> Visitor for FunctionResolver<'a> {
     }
 }
 
-/// Resolve function declaration in a contract
+/// The attributes of a function in a contract
+#[derive(Default)]
+struct Attributes<'f> {
+    mutability: Option<pt::Mutability>,
+    visibility: Option<pt::Visibility>,
+    is_virtual: Option<Loc>,
+    is_override: Option<(Loc, Vec<usize>)>,
+    /// The modifiers, and for a constructor the base contracts given
+    /// arguments, which are resolved with the body
+    modifiers: Vec<&'f pt::Base>,
+}
+
+/// Resolve function declaration in a contract, giving its number unless it is
+/// invalid. The parameters are resolved as for free functions, and the
+/// function takes a symbol of the contract, shared with its overloads.
 pub fn contract_function(
-    _contract: &ContractDefinition,
-    _func: &pt::FunctionDefinition,
-    _annotations: &[pt::Annotation],
-    _no: usize,
-    _ctx: &mut Context,
+    contract: &ContractDefinition,
+    func: &pt::FunctionDefinition,
+    annotations: &[pt::Annotation],
+    no: usize,
+    ctx: &mut Context,
 ) -> Option<usize> {
-    todo!()
+    let contract_no = contract.contract_no;
+    ctx.reject(annotations, "function");
+
+    let (mut attrs, mut success) = function_attributes(func, contract_no, no, ctx);
+
+    let visibility = match (&attrs.visibility, func.ty) {
+        (Some(visibility), _) => visibility.clone(),
+        (None, FunctionTy::Modifier) => pt::Visibility::Internal(None),
+        (None, FunctionTy::Constructor) => pt::Visibility::Public(None),
+        (None, _) if ctx.contracts[contract_no].is_interface() => pt::Visibility::External(None),
+        (None, FunctionTy::Fallback | FunctionTy::Receive) => {
+            ctx.diagnostics.push(Diagnostic::error(
+                func.loc_prototype,
+                format!("{} function must be declared 'external'", func.ty),
+            ));
+            success = false;
+            pt::Visibility::External(None)
+        }
+        // Functions are public by default before 0.5.0
+        (None, FunctionTy::Function) if legacy::admits_legacy(ctx, no) => {
+            ctx.diagnostics.push(Diagnostic::warning(
+                func.loc_prototype,
+                "no visibility specified, defaulting to 'public'",
+            ));
+            pt::Visibility::Public(None)
+        }
+        (None, FunctionTy::Function) => {
+            ctx.diagnostics.push(Diagnostic::error(func.loc_prototype, "no visibility specified"));
+            success = false;
+            pt::Visibility::Public(None)
+        }
+    };
+
+    success &= check_function_kind(func, contract, &visibility, &attrs, ctx);
+
+    // Interfaces only declare functions, which the contracts implementing
+    // them override
+    if ctx.contracts[contract_no].is_interface() && func.ty == FunctionTy::Function {
+        attrs.is_virtual.get_or_insert(func.loc_prototype);
+    }
+
+    let (params, returns) = {
+        let mut resolver = FunctionResolver::new(ctx, no);
+        resolver.func_ty = Some(func.ty);
+        resolver.contract_no = Some(contract_no);
+        resolver.is_internal =
+            matches!(visibility, pt::Visibility::Internal(_) | pt::Visibility::Private(_));
+
+        for (loc, param) in &func.params {
+            let _ = resolver.visit_function_parameter(loc, param);
+        }
+        for (loc, param) in &func.returns {
+            let _ = resolver.visit_function_return(loc, param);
+        }
+
+        success &= resolver.params_success && resolver.returns_success;
+        (resolver.params, resolver.returns)
+    };
+
+    let name = match func.ty {
+        FunctionTy::Function | FunctionTy::Modifier => {
+            ctx.definition_name(&func.name, func.loc_prototype, "function")?
+        }
+        _ => pt::Identifier { loc: func.name_loc, name: String::new() },
+    };
+
+    if !success {
+        return None;
+    }
+
+    let mut fdecl = Function::new(
+        func.loc_prototype,
+        func.loc,
+        name.clone(),
+        Some(contract_no),
+        Vec::new(),
+        func.ty,
+        attrs.mutability,
+        visibility,
+        params,
+        returns,
+        ctx,
+    );
+    fdecl.is_virtual = attrs.is_virtual.is_some();
+    fdecl.is_override = attrs.is_override;
+    fdecl.has_body = func.body.is_some();
+
+    // There is a single constructor, receive and fallback function, and the
+    // overloads of a function differ in their parameters
+    let previous = ctx.contracts[contract_no].functions.iter().find(|function_no| {
+        let prev = &ctx.functions[**function_no];
+        match func.ty {
+            FunctionTy::Constructor | FunctionTy::Fallback | FunctionTy::Receive => {
+                prev.ty == func.ty
+            }
+            _ => prev.signature == fdecl.signature,
+        }
+    });
+    if let Some(prev) = previous {
+        let message = match func.ty {
+            FunctionTy::Function | FunctionTy::Modifier => {
+                format!("overloaded {} with this signature already exist", func.ty)
+            }
+            _ => format!("{} already defined", func.ty),
+        };
+        ctx.diagnostics.push(
+            Diagnostic::builder(func.loc_prototype, Level::Error)
+                .message(message)
+                .note(ctx.functions[*prev].loc_prototype, "location of previous definition")
+                .build(),
+        );
+        return None;
+    }
+
+    let function_no = ctx.functions.len();
+    ctx.functions.push(fdecl);
+    ctx.contracts[contract_no].functions.push(function_no);
+
+    // Before 0.5.0 a function named after its contract is its constructor,
+    // which does not shadow the contract
+    let legacy_constructor = func.ty == FunctionTy::Function &&
+        contract.name.as_ref().is_some_and(|id| id.name == name.name) &&
+        legacy::admits_legacy(ctx, no);
+
+    if !name.name.is_empty() {
+        let key = (no, Some(contract_no), name.name.to_owned());
+        if let Some(Symbol::Function(ref mut overloads)) = ctx.function_symbols.get_mut(&key) {
+            overloads.push((func.loc_prototype, function_no));
+        } else if legacy_constructor {
+            ctx.function_symbols.insert(key, Symbol::Function(vec![(name.loc, function_no)]));
+        } else {
+            ctx.add_symbol(
+                no,
+                Some(contract_no),
+                &name,
+                Symbol::Function(vec![(name.loc, function_no)]),
+            );
+        }
+    }
+
+    Some(function_no)
+}
+
+/// Check the modifiers of a function in a contract, once all the functions of
+/// the contract are declared: each names a modifier of the contract or its
+/// bases taking as many arguments as given. The base contracts which the
+/// attributes of a constructor give arguments are left to
+/// [`check_base_args`](super::contract::check_base_args).
+pub fn check_modifiers(
+    contract_no: usize,
+    function_no: usize,
+    func: &pt::FunctionDefinition,
+    no: usize,
+    ctx: &mut Context,
+) -> bool {
+    let mut valid = true;
+
+    for attribute in &func.attributes {
+        let pt::FunctionAttribute::BaseOrModifier(_, base) = attribute else {
+            continue;
+        };
+
+        if ctx.functions[function_no].is_constructor() {
+            let mut diagnostics = Diagnostics::default();
+            if let Ok(base_no) =
+                ctx.resolve_contract_with_namespace(no, &base.name, &mut diagnostics)
+            {
+                if !is_base(base_no, contract_no, ctx) {
+                    ctx.diagnostics.push(Diagnostic::error(
+                        base.loc,
+                        format!(
+                            "contract '{}' is not a base contract of '{}'",
+                            base.name, ctx.contracts[contract_no].id
+                        ),
+                    ));
+                    valid = false;
+                }
+                continue;
+            }
+        }
+
+        let mut diagnostics = Diagnostics::default();
+        let modifiers = ctx
+            .resolve_function_with_namespace(no, Some(contract_no), &base.name, &mut diagnostics)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, modifier_no)| ctx.functions[*modifier_no].ty == FunctionTy::Modifier)
+            .collect::<Vec<_>>();
+        let args = base.args.as_ref().map_or(0, Vec::len);
+
+        let Some((_, modifier_no)) = modifiers.first() else {
+            ctx.diagnostics
+                .push(Diagnostic::error(base.loc, format!("unknown modifier '{}'", base.name)));
+            valid = false;
+            continue;
+        };
+
+        if !modifiers
+            .iter()
+            .any(|(_, modifier_no)| ctx.functions[*modifier_no].params.len() == args)
+        {
+            let modifier = &ctx.functions[*modifier_no];
+            ctx.diagnostics.push(
+                Diagnostic::builder(base.loc, Level::Error)
+                    .message(format!(
+                        "modifier '{}' expects {} arguments, {} provided",
+                        base.name,
+                        modifier.params.len(),
+                        args
+                    ))
+                    .note(modifier.loc_prototype, "definition of modifier")
+                    .build(),
+            );
+            valid = false;
+        }
+    }
+
+    valid
+}
+
+/// Resolve the attributes of a function in a contract, with whether all of
+/// them are valid and given once. The attributes given twice keep the last
+/// one, so that the checks which follow do not report them again.
+fn function_attributes<'f>(
+    func: &'f pt::FunctionDefinition,
+    contract_no: usize,
+    no: usize,
+    ctx: &mut Context,
+) -> (Attributes<'f>, bool) {
+    let mut attrs = Attributes::default();
+    let mut success = true;
+
+    let redeclared = |loc: Loc, attr: String, prev: Loc, prev_attr: String, ctx: &mut Context| {
+        ctx.diagnostics.push(
+            Diagnostic::builder(loc, Level::Error)
+                .message(format!("function redeclared '{attr}'"))
+                .note(prev, format!("location of previous declaration of '{prev_attr}'"))
+                .build(),
+        );
+        false
+    };
+
+    for attribute in &func.attributes {
+        match attribute {
+            pt::FunctionAttribute::Immutable(loc) => {
+                ctx.diagnostics
+                    .push(Diagnostic::error(*loc, "function cannot be declared 'immutable'"));
+                success = false;
+            }
+            pt::FunctionAttribute::Mutability(m) => {
+                if let Some(prev) = &attrs.mutability {
+                    success = redeclared(m.loc(), m.to_string(), prev.loc(), prev.to_string(), ctx);
+                }
+
+                // Reported with the legacy syntax
+                attrs.mutability = Some(match m {
+                    pt::Mutability::Constant(loc) => pt::Mutability::View(*loc),
+                    m => m.clone(),
+                });
+            }
+            pt::FunctionAttribute::Visibility(v) => {
+                if let Some(prev) = &attrs.visibility {
+                    let (loc, prev_loc) = (v.loc_opt().unwrap(), prev.loc_opt().unwrap());
+                    success = redeclared(loc, v.to_string(), prev_loc, prev.to_string(), ctx);
+                }
+                attrs.visibility = Some(v.clone());
+            }
+            pt::FunctionAttribute::Virtual(loc) => {
+                if let Some(prev) = attrs.is_virtual {
+                    success = redeclared(*loc, "virtual".into(), prev, "virtual".into(), ctx);
+                }
+                attrs.is_virtual = Some(*loc);
+            }
+            pt::FunctionAttribute::Override(loc, bases) => {
+                if let Some((prev, _)) = &attrs.is_override {
+                    success = redeclared(*loc, "override".into(), *prev, "override".into(), ctx);
+                }
+
+                let mut list = Vec::new();
+                let mut diagnostics = Diagnostics::default();
+                for name in bases {
+                    let Ok(base_no) =
+                        ctx.resolve_contract_with_namespace(no, name, &mut diagnostics)
+                    else {
+                        continue;
+                    };
+
+                    if list.contains(&base_no) {
+                        diagnostics.push(Diagnostic::error(
+                            name.loc,
+                            format!("duplicate override '{name}'"),
+                        ));
+                    } else if !is_base(base_no, contract_no, ctx) {
+                        diagnostics.push(Diagnostic::error(
+                            name.loc,
+                            format!(
+                                "override '{}' is not a base contract of '{}'",
+                                name, ctx.contracts[contract_no].id
+                            ),
+                        ));
+                    } else {
+                        list.push(base_no);
+                    }
+                }
+                success &= !diagnostics.any_errors();
+                ctx.diagnostics.extend(diagnostics);
+
+                attrs.is_override = Some((*loc, list));
+            }
+            pt::FunctionAttribute::BaseOrModifier(_, base) => attrs.modifiers.push(base),
+            pt::FunctionAttribute::Error(_) => success = false,
+        }
+    }
+
+    (attrs, success)
+}
+
+/// Check the function is valid for its kind and the kind of its contract
+fn check_function_kind(
+    func: &pt::FunctionDefinition,
+    contract: &ContractDefinition,
+    visibility: &pt::Visibility,
+    attrs: &Attributes,
+    ctx: &mut Context,
+) -> bool {
+    let contract_no = contract.contract_no;
+    let mut errors = Vec::new();
+    let mut error = |loc: Loc, message: String| errors.push(Diagnostic::error(loc, message));
+    let loc = func.loc_prototype;
+    let mutability = attrs.mutability.as_ref();
+
+    match func.ty {
+        FunctionTy::Constructor => {
+            if ctx.contracts[contract_no].is_interface() || ctx.contracts[contract_no].is_library()
+            {
+                error(loc, format!("constructor not allowed in {}", contract.ty));
+            }
+            if let Some(m @ (pt::Mutability::Pure(_) | pt::Mutability::View(_))) = mutability {
+                error(m.loc(), format!("constructor cannot be declared '{m}'"));
+            }
+            if !func.returns.is_empty() {
+                error(loc, "constructor cannot have return values".into());
+            }
+            if let Some(v) = &attrs.visibility {
+                ctx.diagnostics.push(Diagnostic::warning(
+                    v.loc_opt().unwrap(),
+                    format!("'{v}': visibility for constructors is ignored"),
+                ));
+            }
+        }
+        FunctionTy::Fallback | FunctionTy::Receive => {
+            if !matches!(visibility, pt::Visibility::External(_)) {
+                error(
+                    visibility.loc_opt().unwrap_or(loc),
+                    format!("{} function must be declared 'external'", func.ty),
+                );
+            }
+            if func.ty == FunctionTy::Receive &&
+                !matches!(mutability, Some(pt::Mutability::Payable(_)))
+            {
+                error(loc, "receive function must be declared 'payable'".into());
+            }
+            if let Some(m @ (pt::Mutability::Pure(_) | pt::Mutability::View(_))) = mutability {
+                error(m.loc(), format!("{} function must not be declared '{m}'", func.ty));
+            }
+            if !func.params.is_empty() {
+                error(loc, format!("{} function cannot have parameters", func.ty));
+            }
+            if !func.returns.is_empty() {
+                error(loc, format!("{} function cannot have return values", func.ty));
+            }
+            if ctx.contracts[contract_no].is_library() {
+                error(loc, format!("{} function not allowed in a library", func.ty));
+            }
+        }
+        FunctionTy::Modifier => {
+            if let Some(v) = &attrs.visibility {
+                error(v.loc_opt().unwrap(), format!("'{v}': modifiers can not have visibility"));
+            }
+            if let Some(m) = mutability {
+                error(m.loc(), format!("modifier cannot be declared '{m}'"));
+            }
+            if !func.returns.is_empty() {
+                error(loc, "modifier cannot have return values".into());
+            }
+            if ctx.contracts[contract_no].is_interface() {
+                error(loc, "modifier not allowed in an interface".into());
+            }
+        }
+        FunctionTy::Function => {
+            if ctx.contracts[contract_no].is_interface() &&
+                !matches!(visibility, pt::Visibility::External(_))
+            {
+                error(
+                    visibility.loc_opt().unwrap_or(loc),
+                    "functions must be declared 'external' in an interface".into(),
+                );
+            }
+            if matches!(visibility, pt::Visibility::Internal(_) | pt::Visibility::Private(_)) {
+                if let Some(m @ pt::Mutability::Payable(_)) = mutability {
+                    error(m.loc(), "internal or private function cannot be payable".into());
+                }
+            }
+        }
+    }
+
+    if let Some(virtual_loc) = attrs.is_virtual {
+        if func.ty == FunctionTy::Constructor {
+            error(virtual_loc, "constructors cannot be declared 'virtual'".into());
+        } else if ctx.contracts[contract_no].is_library() {
+            error(virtual_loc, "functions in a library cannot be virtual".into());
+        } else if matches!(visibility, pt::Visibility::Private(_)) {
+            error(virtual_loc, "function marked 'virtual' cannot also be 'private'".into());
+        }
+    }
+    if let (FunctionTy::Constructor, Some((override_loc, _))) = (func.ty, &attrs.is_override) {
+        error(*override_loc, "constructors cannot override".into());
+    }
+
+    // A constructor in an interface is already reported
+    match &func.body {
+        Some(body)
+            if ctx.contracts[contract_no].is_interface() && func.ty != FunctionTy::Constructor =>
+        {
+            error(body.loc(), "function in an interface cannot have a body".into());
+        }
+        None if !ctx.contracts[contract_no].is_interface() => {
+            if attrs.is_virtual.is_none() && func.ty != FunctionTy::Constructor {
+                error(loc, "function with no body must be marked 'virtual'".into());
+            }
+            if let Some(modifier) = attrs.modifiers.first() {
+                error(modifier.loc, "function with no body cannot have modifiers".into());
+            }
+        }
+        _ => (),
+    }
+    if func.ty == FunctionTy::Constructor && func.body.is_none() {
+        error(loc, "constructor must have a body".into());
+    }
+
+    let valid = errors.is_empty();
+    ctx.diagnostics.append(&mut errors);
+    valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::parse,
+        semantic::{layout::tests::contract, pragma::PragmaResolver},
+    };
+
+    /// Declare the functions of the contracts of the source, giving the
+    /// function numbers of the last one
+    fn declare(src: &str) -> (Context, Vec<Option<usize>>) {
+        let mut ctx = Context::new();
+        let tree = parse(src, 0).unwrap();
+        let mut declared = Vec::new();
+
+        for part in &tree.0 {
+            if let pt::SourceUnitPart::PragmaDirective(pragma) = part {
+                PragmaResolver::new(&mut ctx).visit_pragma(pragma).unwrap();
+            }
+            let pt::SourceUnitPart::ContractDefinition(def) = part else { continue };
+            let contract_no = ctx.contracts.len();
+            let name = def.name.as_ref().unwrap();
+            let bases = (0..contract_no).collect::<Vec<_>>();
+            ctx.contracts.push(contract(&name.name, &bases, Vec::new()));
+            ctx.contracts[contract_no].ty = def.ty.clone();
+            ctx.add_symbol(0, None, name, Symbol::Contract(name.loc, contract_no));
+
+            let sema = ContractDefinition {
+                contract_no,
+                loc: def.loc,
+                ty: def.ty.clone(),
+                annotations: Vec::new(),
+                name: def.name.clone(),
+                base: def.base.clone(),
+                parts: Vec::new(),
+            };
+            declared = def
+                .parts
+                .iter()
+                .filter_map(|part| match part {
+                    pt::ContractPart::FunctionDefinition(func) => Some(func),
+                    _ => None,
+                })
+                .map(|func| contract_function(&sema, func, &[], 0, &mut ctx))
+                .collect();
+        }
+
+        (ctx, declared)
+    }
+
+    fn errors(ctx: &Context) -> Vec<&str> {
+        ctx.diagnostics.errors().iter().map(|error| error.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_contract_function() {
+        let (ctx, declared) = declare(
+            "contract Base { function f() public virtual {} }
+            contract Token is Base {
+                constructor() payable {}
+                function f() public override(Base) {}
+                function g(address to, uint256 amount) external view returns (bool) {}
+                function g(address to) internal {}
+                modifier onlyOwner() { _; }
+                receive() external payable {}
+                fallback() external {}
+            }",
+        );
+        assert_eq!(errors(&ctx), Vec::<&str>::new());
+        assert!(declared.iter().all(Option::is_some));
+
+        let function = |i: usize| &ctx.functions[declared[i].unwrap()];
+        assert!(function(0).is_constructor() && function(0).is_payable());
+        assert!(matches!(&function(1).is_override, Some((_, bases)) if bases == &[0]));
+        assert_eq!(function(2).signature, "g(address,uint256)");
+        assert_eq!(function(2).returns.len(), 1);
+        assert_eq!(function(3).signature, "g(address)");
+        assert!(matches!(function(4).visibility, pt::Visibility::Internal(_)));
+        assert_eq!(function(5).signature, "@receive");
+        assert_eq!(ctx.contracts[1].functions.len(), 7);
+
+        // The overloads share a symbol of the contract
+        match ctx.function_symbols.get(&(0, Some(1), "g".into())) {
+            Some(Symbol::Function(overloads)) => assert_eq!(overloads.len(), 2),
+            other => panic!("{other:?}"),
+        }
+
+        // Functions of interfaces are external and virtual
+        let (ctx, declared) = declare("interface I { function f() external; }");
+        let func = &ctx.functions[declared[0].unwrap()];
+        assert!(func.is_virtual && !func.has_body);
+    }
+
+    #[test]
+    fn test_invalid_contract_functions() {
+        let (ctx, declared) = declare(
+            "contract C {
+                constructor() view {}
+                function f() {}
+                function g() public public {}
+                function h() private virtual {}
+                function i() internal payable {}
+                function j() public;
+                receive() external {}
+                fallback(uint x) external {}
+                modifier m() public { _; }
+                function k(uint a) public {}
+                function k(uint b) public {}
+            }",
+        );
+        assert_eq!(
+            errors(&ctx),
+            [
+                "constructor cannot be declared 'view'",
+                "no visibility specified",
+                "function redeclared 'public'",
+                "function marked 'virtual' cannot also be 'private'",
+                "internal or private function cannot be payable",
+                "function with no body must be marked 'virtual'",
+                "receive function must be declared 'payable'",
+                "fallback function cannot have parameters",
+                "'public': modifiers can not have visibility",
+                "overloaded function with this signature already exist",
+            ]
+        );
+        assert_eq!(declared.iter().filter(|no| no.is_some()).count(), 1);
+
+        let (ctx, _) = declare(
+            "interface I {
+                constructor() {}
+                function f() public {}
+            }",
+        );
+        assert_eq!(
+            errors(&ctx),
+            [
+                "constructor not allowed in interface",
+                "functions must be declared 'external' in an interface",
+                "function in an interface cannot have a body",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_legacy_functions() {
+        let src = "contract C { function C() public {} function f() {} }";
+        let warnings = |ctx: &Context| {
+            ctx.diagnostics.warnings().iter().map(|w| w.message.clone()).collect::<Vec<_>>()
+        };
+
+        // Before 0.5.0 functions are public by default, and a function named
+        // after its contract is its constructor
+        let (ctx, declared) = declare(&format!("pragma solidity ^0.4.24; {src}"));
+        assert!(errors(&ctx).is_empty(), "{:?}", errors(&ctx));
+        assert_eq!(warnings(&ctx), ["no visibility specified, defaulting to 'public'"]);
+        assert!(ctx.functions[declared[1].unwrap()].is_public());
+
+        let (ctx, _) = declare(&format!("pragma solidity ^0.8.0; {src}"));
+        assert_eq!(errors(&ctx), ["no visibility specified"]);
+        assert_eq!(warnings(&ctx), ["'C' shadows a contract of the file"]);
+    }
 }
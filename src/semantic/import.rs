@@ -19,13 +19,14 @@ use super::{
     ast::{self, Symbol},
     context::Context,
     expression::strings::unescape,
-    visitor::{SemanticVisitable, SemanticVisitor},
+    visitor::SemanticVisitor,
 };
 
-use std::ffi::OsString;
+use std::{ffi::OsString, path::Path};
 
 use crate::{
-    diagnostics::Diagnostic,
+    diagnostics::{Diagnostic, ErrorType, Level},
+    helpers::CodeLocation,
     parser::{
         ast as pt,
         visitor::{Visitable, Visitor},
@@ -80,12 +81,8 @@ impl<'a> ImportResolver<'a> {
             return Err(ImportResolverError::EmptyImportPath);
         }
 
-        let (valid, bs) = unescape(
-            &filename.string,
-            filename.loc.start(),
-            filename.loc.no(),
-            &mut self.ctx.diagnostics,
-        );
+        let (valid, bs) =
+            unescape(&filename.string, filename.loc.start(), self.no, &mut self.ctx.diagnostics);
 
         if !valid {
             return Err(ImportResolverError::InvalidFilenameEncoding);
@@ -113,6 +110,18 @@ impl<'a> ImportResolver<'a> {
             return Ok(());
         }
 
+        if let Some(source) = self.resolver.get_builtin(Path::new(os_filename)) {
+            self.import_file_no = self.ctx.files.len();
+            let _ =
+                analyzer::analyze_builtin(Path::new(os_filename), &source, self.resolver, self.ctx);
+            if self.ctx.diagnostics.any_errors() {
+                return Err(ImportResolverError::FileResolutionFailed(
+                    "analysis failed".to_string(),
+                ));
+            }
+            return Ok(());
+        }
+
         match self.resolver.resolve(self.parent, os_filename) {
             Err(message) => {
                 self.ctx.diagnostics.push(Diagnostic::error(filename.loc, message.clone()));
@@ -142,7 +151,10 @@ impl<'a> ImportResolver<'a> {
         }
     }
 
-    /// Adds symbol to context if it doesn't already exist with the same definition
+    /// Adds an imported symbol to the file. A symbol reached through several
+    /// imports is only added once, and the overloads of a free function from
+    /// different files are merged. Any other symbol with the same name, in
+    /// either namespace, is a collision.
     fn add_symbol(
         &mut self,
         function: bool,
@@ -151,14 +163,43 @@ impl<'a> ImportResolver<'a> {
         symbol: Symbol,
     ) -> Result<(), ImportResolverError> {
         let filename = self.filename.as_ref().ok_or(ImportResolverError::MissingFilename)?;
-        let symbols = match function {
-            true => &self.ctx.function_symbols,
-            false => &self.ctx.variable_symbols,
+        let key = (self.no, contract_no, name.to_owned());
+
+        let (same, other) = match function {
+            true => (&mut self.ctx.function_symbols, &self.ctx.variable_symbols),
+            false => (&mut self.ctx.variable_symbols, &self.ctx.function_symbols),
         };
 
-        if symbols.get(&(self.no, contract_no, name.to_owned())) != Some(&symbol) {
-            let new_symbol = pt::Identifier { name, loc: filename.loc };
-            self.ctx.add_symbol(self.no, contract_no, &new_symbol, symbol);
+        let previous = match (same.get_mut(&key), &symbol) {
+            (Some(previous), _) if *previous == symbol => return Ok(()),
+            (Some(Symbol::Function(overloads)), Symbol::Function(imported)) => {
+                for overload in imported {
+                    if !overloads.contains(overload) {
+                        overloads.push(*overload);
+                    }
+                }
+                return Ok(());
+            }
+            (Some(previous), _) => Some(previous.loc()),
+            (None, _) => other.get(&key).map(Symbol::loc),
+        };
+
+        match previous {
+            Some(previous) => self.ctx.diagnostics.push(
+                Diagnostic::builder(filename.loc, Level::Error)
+                    .ty(ErrorType::DeclarationError)
+                    .message(format!(
+                        "'{name}' imported from '{}' conflicts with another definition",
+                        filename.string
+                    ))
+                    .note(previous, format!("previous definition of '{name}'"))
+                    .note(symbol.loc(), format!("imported definition of '{name}'"))
+                    .build(),
+            ),
+            None => {
+                let new_symbol = pt::Identifier { name, loc: filename.loc };
+                self.ctx.add_symbol(self.no, contract_no, &new_symbol, symbol);
+            }
         }
 
         Ok(())
@@ -191,7 +232,7 @@ impl<'a> SemanticVisitor for ImportResolver<'a> {
     ) -> Result<(), Self::Error> {
         if let pt::SourceUnitPart::ImportDirective(_) = &part.part {
             self.ctx.reject(&part.annotations, "import");
-            part.visit(self)?;
+            part.part.visit(self)?;
         }
 
         Ok(())
@@ -204,9 +245,17 @@ impl<'a> Visitor for ImportResolver<'a> {
     fn visit_import(&mut self, import: &mut pt::Import) -> Result<(), Self::Error> {
         self.process_filename(import)?;
         self.process_import_file_no()?;
-        import.visit(self)?;
 
-        Ok(())
+        // Dispatch to the kind of import, visiting the import again would recurse
+        match import {
+            pt::Import::Plain(path, loc) => self.visit_import_plain(*loc, path),
+            pt::Import::GlobalSymbol(path, alias, loc) => {
+                self.visit_import_global(*loc, path, alias)
+            }
+            pt::Import::Rename(path, imports, loc) => {
+                self.visit_import_renames(*loc, imports, path)
+            }
+        }
     }
 
     fn visit_import_plain(
@@ -318,3 +367,69 @@ fn osstring_from_vec(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_collisions() {
+        let mut ctx = Context::new();
+        let mut resolver = FileResolver::default();
+        let loc = |no, start| pt::Loc::File(no, start, start + 1);
+
+        // Both files export the same contract and an overload of 'f', and
+        // each exports its own 'Token'
+        let key = |name: &str| (0, None, name.to_owned());
+        ctx.variable_symbols.insert(key("Base"), Symbol::Contract(loc(3, 0), 0));
+        ctx.variable_symbols.insert(key("Token"), Symbol::Contract(loc(1, 0), 1));
+        ctx.function_symbols.insert(key("f"), Symbol::Function(vec![(loc(1, 5), 0)]));
+        ctx.function_symbols.insert(key("Pair"), Symbol::Function(vec![(loc(1, 9), 1)]));
+
+        let mut import = ImportResolver::new(&mut ctx, &mut resolver, None, 0);
+        import.filename.replace(pt::StringLiteral {
+            loc: loc(0, 20),
+            unicode: false,
+            string: "b.sol".into(),
+        });
+
+        import.add_symbol(false, None, "Base".into(), Symbol::Contract(loc(3, 0), 0)).unwrap();
+        import.add_symbol(true, None, "f".into(), Symbol::Function(vec![(loc(2, 5), 2)])).unwrap();
+        import.add_symbol(false, None, "Token".into(), Symbol::Contract(loc(2, 0), 3)).unwrap();
+        import.add_symbol(false, None, "Pair".into(), Symbol::Contract(loc(2, 9), 4)).unwrap();
+
+        assert_eq!(
+            ctx.function_symbols[&key("f")],
+            Symbol::Function(vec![(loc(1, 5), 0), (loc(2, 5), 2)])
+        );
+
+        let errors = ctx.diagnostics.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].message,
+            "'Token' imported from 'b.sol' conflicts with another definition"
+        );
+        assert_eq!(errors[0].notes[0].loc, loc(1, 0));
+        assert_eq!(
+            errors[1].message,
+            "'Pair' imported from 'b.sol' conflicts with another definition"
+        );
+        assert_eq!(errors[1].notes[1].loc, loc(2, 9));
+    }
+
+    #[test]
+    fn test_import_builtin() {
+        let mut ctx = Context::new();
+        let mut resolver = FileResolver::default();
+        resolver.add_builtin("hmt/builtins.sol", "pragma solidity ^0.8.0;");
+        resolver.set_file_contents("main.sol", "import \"hmt/builtins.sol\";".into());
+
+        let file = resolver.resolve(None, "main.sol".as_ref()).unwrap();
+        analyzer::analyze(&file, &mut resolver, &mut ctx).unwrap();
+
+        assert!(!ctx.diagnostics.any_errors());
+        assert_eq!(ctx.files.len(), 2);
+        assert_eq!(ctx.files[1].path, Path::new("hmt/builtins.sol"));
+        assert_eq!(ctx.files[1].cache_no, None);
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Interning of types. A [`Type`] is a tree of boxes, so comparing two of
+//! them walks both trees. The arena stores each distinct type
+//! once, as a node whose components are the handles of types interned before
+//! it, so two types are equal exactly when their handles are, and the
+//! components of a type are shared by all the types containing them.
+//!
+//! Types are interned once, when they are resolved: the parameters of
+//! functions keep the handles of their types, see
+//! [`Parameter::ty_id`](crate::semantic::ast::Parameter::ty_id), for the
+//! checks comparing them.
+
+use indexmap::IndexSet;
+
+use crate::semantic::ast::{ArrayLength, Mapping, Mutability, StructType, Type};
+
+/// The handle of an interned type, see [`TypeArena`]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub struct TypeId(u32);
+
+/// A type with the handles of its components in place of the boxes
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub enum TypeNode {
+    Address(bool),
+    Bool,
+    Int(u16),
+    Uint(u16),
+    Rational,
+    Bytes(u8),
+    DynamicBytes,
+    String,
+    Array(TypeId, Vec<ArrayLength>),
+    Enum(usize),
+    Struct(StructType),
+    /// The names of the key and value do not take part in the comparison of
+    /// mappings, so they are not kept
+    Mapping {
+        key: TypeId,
+        value: TypeId,
+    },
+    Contract(usize),
+    Ref(TypeId),
+    StorageRef(bool, TypeId),
+    InternalFunction {
+        mutability: Mutability,
+        params: Vec<TypeId>,
+        returns: Vec<TypeId>,
+    },
+    ExternalFunction {
+        mutability: Mutability,
+        params: Vec<TypeId>,
+        returns: Vec<TypeId>,
+    },
+    UserType(usize),
+    Value,
+    Void,
+    Unreachable,
+    Slice(TypeId),
+    Unresolved,
+    BufferPointer,
+    FunctionSelector,
+}
+
+/// The deduplicated arena of the types interned by the compilation
+#[derive(Debug, Default)]
+pub struct TypeArena {
+    nodes: IndexSet<TypeNode>,
+}
+
+impl TypeArena {
+    /// Intern the type and its components, giving the handle of the type
+    /// which was already interned if there is an equal one
+    pub fn intern(&mut self, ty: &Type) -> TypeId {
+        let node = match ty {
+            Type::Address(payable) => TypeNode::Address(*payable),
+            Type::Bool => TypeNode::Bool,
+            Type::Int(bits) => TypeNode::Int(*bits),
+            Type::Uint(bits) => TypeNode::Uint(*bits),
+            Type::Rational => TypeNode::Rational,
+            Type::Bytes(length) => TypeNode::Bytes(*length),
+            Type::DynamicBytes => TypeNode::DynamicBytes,
+            Type::String => TypeNode::String,
+            Type::Array(elem, dims) => TypeNode::Array(self.intern(elem), dims.clone()),
+            Type::Enum(enum_no) => TypeNode::Enum(*enum_no),
+            Type::Struct(struct_ty) => TypeNode::Struct(*struct_ty),
+            Type::Mapping(mapping) => TypeNode::Mapping {
+                key: self.intern(&mapping.key),
+                value: self.intern(&mapping.value),
+            },
+            Type::Contract(contract_no) => TypeNode::Contract(*contract_no),
+            Type::Ref(ty) => TypeNode::Ref(self.intern(ty)),
+            Type::StorageRef(immutable, ty) => TypeNode::StorageRef(*immutable, self.intern(ty)),
+            Type::InternalFunction { mutability, params, returns } => TypeNode::InternalFunction {
+                mutability: mutability.clone(),
+                params: self.intern_all(params),
+                returns: self.intern_all(returns),
+            },
+            Type::ExternalFunction { mutability, params, returns } => TypeNode::ExternalFunction {
+                mutability: mutability.clone(),
+                params: self.intern_all(params),
+                returns: self.intern_all(returns),
+            },
+            Type::UserType(type_no) => TypeNode::UserType(*type_no),
+            Type::Value => TypeNode::Value,
+            Type::Void => TypeNode::Void,
+            Type::Unreachable => TypeNode::Unreachable,
+            Type::Slice(ty) => TypeNode::Slice(self.intern(ty)),
+            Type::Unresolved => TypeNode::Unresolved,
+            Type::BufferPointer => TypeNode::BufferPointer,
+            Type::FunctionSelector => TypeNode::FunctionSelector,
+        };
+
+        self.insert(node)
+    }
+
+    /// Intern a node whose components are already interned
+    pub fn insert(&mut self, node: TypeNode) -> TypeId {
+        let (index, _) = self.nodes.insert_full(node);
+        TypeId(index as u32)
+    }
+
+    /// The node of an interned type
+    pub fn node(&self, id: TypeId) -> &TypeNode {
+        &self.nodes[id.0 as usize]
+    }
+
+    /// Build the type back from its handle. The key and value of mappings are
+    /// unnamed.
+    pub fn ty(&self, id: TypeId) -> Type {
+        match self.node(id) {
+            TypeNode::Address(payable) => Type::Address(*payable),
+            TypeNode::Bool => Type::Bool,
+            TypeNode::Int(bits) => Type::Int(*bits),
+            TypeNode::Uint(bits) => Type::Uint(*bits),
+            TypeNode::Rational => Type::Rational,
+            TypeNode::Bytes(length) => Type::Bytes(*length),
+            TypeNode::DynamicBytes => Type::DynamicBytes,
+            TypeNode::String => Type::String,
+            TypeNode::Array(elem, dims) => Type::Array(Box::new(self.ty(*elem)), dims.clone()),
+            TypeNode::Enum(enum_no) => Type::Enum(*enum_no),
+            TypeNode::Struct(struct_ty) => Type::Struct(*struct_ty),
+            TypeNode::Mapping { key, value } => Type::Mapping(Mapping {
+                key: Box::new(self.ty(*key)),
+                key_name: None,
+                value: Box::new(self.ty(*value)),
+                value_name: None,
+            }),
+            TypeNode::Contract(contract_no) => Type::Contract(*contract_no),
+            TypeNode::Ref(ty) => Type::Ref(Box::new(self.ty(*ty))),
+            TypeNode::StorageRef(immutable, ty) => {
+                Type::StorageRef(*immutable, Box::new(self.ty(*ty)))
+            }
+            TypeNode::InternalFunction { mutability, params, returns } => Type::InternalFunction {
+                mutability: mutability.clone(),
+                params: self.types(params),
+                returns: self.types(returns),
+            },
+            TypeNode::ExternalFunction { mutability, params, returns } => Type::ExternalFunction {
+                mutability: mutability.clone(),
+                params: self.types(params),
+                returns: self.types(returns),
+            },
+            TypeNode::UserType(type_no) => Type::UserType(*type_no),
+            TypeNode::Value => Type::Value,
+            TypeNode::Void => Type::Void,
+            TypeNode::Unreachable => Type::Unreachable,
+            TypeNode::Slice(ty) => Type::Slice(Box::new(self.ty(*ty))),
+            TypeNode::Unresolved => Type::Unresolved,
+            TypeNode::BufferPointer => Type::BufferPointer,
+            TypeNode::FunctionSelector => Type::FunctionSelector,
+        }
+    }
+
+    /// The number of distinct types interned
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn intern_all(&mut self, types: &[Type]) -> Vec<TypeId> {
+        types.iter().map(|ty| self.intern(ty)).collect()
+    }
+
+    fn types(&self, ids: &[TypeId]) -> Vec<Type> {
+        ids.iter().map(|id| self.ty(*id)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast as pt;
+
+    #[test]
+    fn test_interning() {
+        let mut arena = TypeArena::default();
+        let array = |elem: Type| Type::Array(Box::new(elem), vec![ArrayLength::Dynamic]);
+        let mapping = |key: Type, value: Type, name: &str| {
+            Type::Mapping(Mapping {
+                key: Box::new(key),
+                key_name: Some(pt::Identifier { loc: pt::Loc::Builtin, name: name.into() }),
+                value: Box::new(value),
+                value_name: None,
+            })
+        };
+
+        let a = arena.intern(&array(array(Type::Uint(256))));
+        let b = arena.intern(&array(array(Type::Uint(256))));
+        assert_eq!(a, b);
+        assert_ne!(a, arena.intern(&array(array(Type::Int(256)))));
+
+        // The components are shared: uint256, uint256[], uint256[][], int256,
+        // int256[] and int256[][]
+        assert_eq!(arena.len(), 6);
+        let elem = arena.intern(&array(Type::Uint(256)));
+        assert!(matches!(arena.node(a), TypeNode::Array(id, _) if *id == elem));
+
+        // The names of the key and value are not compared
+        let named = arena.intern(&mapping(Type::Address(false), Type::Bool, "owner"));
+        let other = arena.intern(&mapping(Type::Address(false), Type::Bool, "spender"));
+        assert_eq!(named, other);
+        assert_eq!(arena.ty(named), mapping(Type::Address(false), Type::Bool, "owner"));
+
+        let function = Type::InternalFunction {
+            mutability: Mutability::Pure(pt::Loc::Builtin),
+            params: vec![array(Type::Uint(256)), Type::Bool],
+            returns: vec![Type::String],
+        };
+        let id = arena.intern(&function);
+        assert_eq!(arena.ty(id), function);
+        assert_eq!(arena.intern(&function), id);
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The storage layout of a contract. The state variables of the contract and
+//! its bases get consecutive slots, the bases first in the order of
+//! inheritance. The `persistent`, `temporary` and `instance` storage classes
+//! are separate storages, so each class has a layout of its own, starting
+//! from slot 0. Constants and immutables are not stored.
+//!
+//! As in solc, values smaller than a slot are packed: such a value goes in the
+//! slot of the variable before it when it fits in the bytes left, from the low
+//! order end of the slot, and starts a new slot otherwise. Mappings, arrays,
+//! structs, strings and `bytes` always take whole slots, and the variable
+//! after them starts a new slot.
+
+use std::collections::HashMap;
+
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+
+use crate::semantic::{
+    ast::{ArrayLength, Contract, Layout, StorageClass, StructType, Type, Variable},
+    context::Context,
+};
+
+impl Variable {
+    /// The storage class of the state variable
+    pub fn storage_class(&self) -> StorageClass {
+        self.storage_type.as_ref().map_or(StorageClass::Persistent, StorageClass::from)
+    }
+}
+
+impl Type {
+    /// The number of bytes a value of the type takes when it is packed with
+    /// others in a slot, `None` for the types taking whole slots
+    pub fn storage_bytes(&self, ctx: &Context) -> Option<u8> {
+        match self {
+            Type::Bool | Type::Enum(_) => Some(1),
+            Type::Int(bits) | Type::Uint(bits) if *bits < 256 => Some((bits / 8) as u8),
+            Type::Address(_) | Type::Contract(_) => Some(20),
+            Type::Bytes(n) if *n < 32 => Some(*n),
+            Type::UserType(n) => ctx.user_types[*n].ty.storage_bytes(ctx),
+            _ => None,
+        }
+    }
+
+    /// The number of slots a state variable of the type takes. Mappings and
+    /// dynamic arrays take one slot, their elements are stored at hashes of it.
+    pub fn storage_slots(&self, ctx: &Context) -> BigInt {
+        match self {
+            Type::Array(elem, dims) => {
+                let mut slots = BigInt::one();
+                for dim in dims.iter().rev() {
+                    match dim {
+                        ArrayLength::Fixed(len) => slots *= len,
+                        ArrayLength::Dynamic | ArrayLength::AnyFixed => return slots,
+                    }
+                }
+                slots * elem.storage_slots(ctx)
+            }
+            Type::Struct(StructType::UserDefined(n)) => {
+                ctx.structs[*n].fields.iter().map(|field| field.ty.storage_slots(ctx)).sum()
+            }
+            _ => BigInt::one(),
+        }
+    }
+}
+
+impl Contract {
+    /// The slot of a state variable of the contract or of one of its bases
+    pub fn storage_slot(&self, contract_no: usize, var_no: usize) -> Option<&Layout> {
+        self.layout
+            .iter()
+            .find(|layout| layout.contract_no == contract_no && layout.var_no == var_no)
+    }
+}
+
+/// Assigns the slots of the variables of a storage class, in order
+#[derive(Debug, Default)]
+pub struct SlotAllocator {
+    /// The slot being filled
+    slot: BigInt,
+    /// The bytes of the slot already used
+    used: u8,
+}
+
+impl SlotAllocator {
+    /// The slot and offset of a value of `bytes` packed after the values
+    /// before it
+    pub fn packed(&mut self, bytes: u8) -> (BigInt, u8) {
+        if self.used + bytes > 32 {
+            self.next();
+        }
+        let offset = self.used;
+        self.used += bytes;
+        (self.slot.clone(), offset)
+    }
+
+    /// The first of `slots` whole slots
+    pub fn slots(&mut self, slots: &BigInt) -> BigInt {
+        if self.used > 0 {
+            self.next();
+        }
+        let slot = self.slot.clone();
+        self.slot += slots;
+        slot
+    }
+
+    /// The number of slots used so far
+    pub fn size(&self) -> BigInt {
+        match self.used {
+            0 => self.slot.clone(),
+            _ => &self.slot + 1,
+        }
+    }
+
+    fn next(&mut self) {
+        self.slot += 1;
+        self.used = 0;
+    }
+}
+
+/// Lay out the state variables of the contract, once the variables of the
+/// contract and its bases are resolved
+pub fn layout(contract_no: usize, ctx: &mut Context) {
+    let mut next: HashMap<StorageClass, SlotAllocator> = HashMap::new();
+    let mut layout = Vec::new();
+
+    for base_no in ctx.contract_bases(contract_no) {
+        for (var_no, var) in ctx.contracts[base_no].variables.iter().enumerate() {
+            if var.constant || var.immutable {
+                continue;
+            }
+
+            let storage_class = var.storage_class();
+            let slots = next.entry(storage_class).or_default();
+            let (slot, offset) = match var.ty.storage_bytes(ctx) {
+                Some(bytes) => slots.packed(bytes),
+                None => (slots.slots(&var.ty.storage_slots(ctx)), 0),
+            };
+
+            layout.push(Layout {
+                slot,
+                offset,
+                contract_no: base_no,
+                var_no,
+                ty: var.ty.clone(),
+                storage_class,
+            });
+        }
+    }
+
+    let contract = &mut ctx.contracts[contract_no];
+    contract.fixed_layout_size =
+        next.get(&StorageClass::Persistent).map_or_else(BigInt::zero, SlotAllocator::size);
+    contract.layout = layout;
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        parser::ast as pt,
+        semantic::ast::{Base, Parameter, StructDecl},
+    };
+
+    pub(crate) fn variable(
+        name: &str,
+        ty: Type,
+        storage_type: Option<pt::StorageType>,
+    ) -> Variable {
+        Variable {
+            tags: Vec::new(),
+            name: name.into(),
+            loc: pt::Loc::Builtin,
+            ty,
+            visibility: pt::Visibility::Internal(None),
+            constant: false,
+            immutable: false,
+            initializer: None,
+            assigned: false,
+            read: false,
+            storage_type,
+            storage_location: None,
+        }
+    }
+
+    pub(crate) fn contract(name: &str, bases: &[usize], variables: Vec<Variable>) -> Contract {
+        Contract {
+            tags: Vec::new(),
+            loc: pt::Loc::Builtin,
+            ty: pt::ContractTy::Contract(pt::Loc::Builtin),
+            id: pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+            bases: bases
+                .iter()
+                .map(|&contract_no| Base { loc: pt::Loc::Builtin, contract_no, constructor: None })
+                .collect(),
+            using: Vec::new(),
+            layout: Vec::new(),
+            fixed_layout_size: BigInt::zero(),
+            functions: Vec::new(),
+            all_functions: Default::default(),
+            virtual_functions: Default::default(),
+            yul_functions: Vec::new(),
+            variables,
+            creates: Vec::new(),
+            emits_events: Vec::new(),
+            initializer: Default::default(),
+            default_constructor: None,
+            code: Default::default(),
+            metadata: Default::default(),
+            instantiable: true,
+        }
+    }
+
+    #[test]
+    fn test_storage_slots() {
+        let mut ctx = Context::new();
+        ctx.structs.push(StructDecl {
+            tags: Vec::new(),
+            id: pt::Identifier { loc: pt::Loc::Builtin, name: "Point".into() },
+            loc: pt::Loc::Builtin,
+            contract: None,
+            fields: vec![Parameter::new_default(Type::Int(64)); 2],
+            offsets: Vec::new(),
+            storage_offsets: Vec::new(),
+        });
+        let point = Type::Struct(StructType::UserDefined(0));
+        let fixed = |n: u32| ArrayLength::Fixed(n.into());
+
+        assert_eq!(Type::Uint(8).storage_slots(&ctx), 1.into());
+        assert_eq!(point.storage_slots(&ctx), 2.into());
+        // `Point[3][4]`
+        let points = Type::Array(Box::new(point.clone()), vec![fixed(3), fixed(4)]);
+        assert_eq!(points.storage_slots(&ctx), 24.into());
+        // `Point[3][]` is stored at the hash of its slot
+        let points = Type::Array(Box::new(point), vec![fixed(3), ArrayLength::Dynamic]);
+        assert_eq!(points.storage_slots(&ctx), 1.into());
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut ctx = Context::new();
+        let temporary = || Some(pt::StorageType::Temporary(None));
+        let instance = Some(pt::StorageType::Instance(None));
+
+        let mut max = variable("MAX", Type::Uint(256), None);
+        max.constant = true;
+        let pair = Type::Array(Box::new(Type::Uint(256)), vec![ArrayLength::Fixed(2.into())]);
+        ctx.contracts.push(contract(
+            "Base",
+            &[],
+            vec![max, variable("pair", pair, None), variable("lock", Type::Bool, temporary())],
+        ));
+        ctx.contracts.push(contract(
+            "Token",
+            &[0],
+            vec![
+                variable("admin", Type::Address(false), instance),
+                variable("supply", Type::Uint(256), None),
+                variable("nonce", Type::Uint(64), temporary()),
+                variable("owner", Type::Address(false), None),
+                variable("paused", Type::Bool, None),
+                variable("id", Type::Bytes(16), None),
+            ],
+        ));
+
+        layout(1, &mut ctx);
+
+        let slots = ctx.contracts[1]
+            .layout
+            .iter()
+            .map(|layout| {
+                let var = &ctx.contracts[layout.contract_no].variables[layout.var_no];
+                (var.name.as_str(), layout.storage_class, layout.slot.clone(), layout.offset)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            slots,
+            [
+                ("pair", StorageClass::Persistent, 0.into(), 0),
+                ("lock", StorageClass::Temporary, 0.into(), 0),
+                ("admin", StorageClass::Instance, 0.into(), 0),
+                ("supply", StorageClass::Persistent, 2.into(), 0),
+                // Values smaller than a slot share it while they fit
+                ("nonce", StorageClass::Temporary, 0.into(), 1),
+                ("owner", StorageClass::Persistent, 3.into(), 0),
+                ("paused", StorageClass::Persistent, 3.into(), 20),
+                ("id", StorageClass::Persistent, 4.into(), 0),
+            ]
+        );
+        assert_eq!(ctx.contracts[1].fixed_layout_size, 5.into());
+        assert_eq!(ctx.contracts[1].storage_slot(0, 2).unwrap().slot, 0.into());
+        assert!(ctx.contracts[1].storage_slot(0, 0).is_none());
+    }
+}
@@ -0,0 +1,376 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deprecated syntax. Each construct is deprecated in a version of Solidity,
+//! from which the compiler warns about it, and some are removed in a later
+//! one, from which they are an error. The parser accepts them all, and a file
+//! is checked against the versions its version pragma admits: a construct is
+//! reported as deprecated when the file may be compiled by a version which
+//! deprecates it, and as an error naming the replacement when it may only be
+//! compiled by versions which removed it. A file without a version pragma is
+//! compiled by the latest version.
+
+use thiserror::Error;
+
+use super::{
+    ast::{Pragma, Version, VersionReq},
+    context::Context,
+};
+
+use crate::{
+    codegen::loops::{expressions, statements, visit},
+    diagnostics::Diagnostic,
+    parser::{ast as pt, visitor::Visitor},
+};
+
+/// A deprecated construct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deprecated {
+    NamedConstructor,
+    ConstantFunction,
+    Throw,
+    Var,
+    Years,
+    BlockDifficulty,
+    SelfDestruct,
+}
+
+/// When a construct was deprecated, and what replaces it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deprecation {
+    /// The construct, as named in diagnostics
+    pub syntax: &'static str,
+    pub deprecated_in: Version,
+    pub removed_in: Option<Version>,
+    /// What to do instead, as suggested in diagnostics
+    pub instead: &'static str,
+}
+
+const fn version(minor: u32, patch: u32) -> Version {
+    Version { major: 0, minor: Some(minor), patch: Some(patch), pre: None }
+}
+
+impl Deprecated {
+    pub fn deprecation(self) -> Deprecation {
+        let (syntax, deprecated_in, removed_in, instead) = match self {
+            Deprecated::NamedConstructor => (
+                "a function named after its contract as constructor",
+                version(4, 22),
+                Some(version(5, 0)),
+                "use 'constructor' instead",
+            ),
+            Deprecated::ConstantFunction => (
+                "'constant' on a function",
+                version(4, 17),
+                Some(version(5, 0)),
+                "use 'view' instead",
+            ),
+            Deprecated::Throw => {
+                ("'throw'", version(4, 13), Some(version(5, 0)), "use 'revert()' instead")
+            }
+            Deprecated::Var => {
+                ("'var'", version(4, 20), Some(version(5, 0)), "declare the type of the variable")
+            }
+            Deprecated::Years => (
+                "the 'years' unit",
+                version(4, 24),
+                Some(version(5, 0)),
+                "use '365 days' instead, years do not all have 365 days",
+            ),
+            Deprecated::BlockDifficulty => (
+                "'block.difficulty'",
+                version(8, 18),
+                None,
+                "use 'block.prevrandao' instead, it is the same value since the merge",
+            ),
+            Deprecated::SelfDestruct => (
+                "'selfdestruct'",
+                version(8, 18),
+                None,
+                "transfer the balance instead, since the Cancun upgrade it no longer deletes \
+                 the contract",
+            ),
+        };
+
+        Deprecation { syntax, deprecated_in, removed_in, instead }
+    }
+}
+
+/// Check for deprecated syntax
+pub struct LegacySyntaxChecker<'a> {
+    /// Shared context for diagnostics and state
+    ctx: &'a mut Context,
+    no: usize,
+}
+
+impl<'a> LegacySyntaxChecker<'a> {
+    /// Creates a new legacy syntax checker for the file, after its pragmas
+    /// are resolved
+    pub fn new(ctx: &'a mut Context, no: usize) -> Self {
+        Self { ctx, no }
+    }
+
+    fn function(&mut self, def: &pt::FunctionDefinition, contract: Option<&pt::Identifier>) {
+        if let (Some(id), Some(contract)) = (&def.name, contract) {
+            if def.ty == pt::FunctionTy::Function && id.name == contract.name {
+                self.report(id.loc, Deprecated::NamedConstructor);
+            }
+        }
+
+        for attr in &def.attributes {
+            if let pt::FunctionAttribute::Mutability(pt::Mutability::Constant(loc)) = attr {
+                self.report(*loc, Deprecated::ConstantFunction);
+            }
+        }
+
+        let mut worklist = def.body.iter().collect::<Vec<_>>();
+        while let Some(stmt) = worklist.pop() {
+            match stmt {
+                pt::Statement::Expression(loc, pt::Expression::Variable(id))
+                    if id.name == "throw" =>
+                {
+                    self.report(*loc, Deprecated::Throw);
+                }
+                pt::Statement::VariableDefinition(_, decl, _) => {
+                    if let pt::Expression::Variable(id) = &decl.ty {
+                        if id.name == "var" {
+                            self.report(id.loc, Deprecated::Var);
+                        }
+                    }
+                }
+                _ => (),
+            }
+            for expr in expressions(stmt) {
+                self.expression(expr);
+            }
+            worklist.extend(statements(stmt));
+        }
+    }
+
+    fn expression(&mut self, expr: &pt::Expression) {
+        let mut found = Vec::new();
+        visit(expr, &mut |expr| match expr {
+            pt::Expression::NumberLiteral(.., Some(unit)) |
+            pt::Expression::RationalNumberLiteral(.., Some(unit))
+                if unit.name == "years" =>
+            {
+                found.push((unit.loc, Deprecated::Years));
+            }
+            pt::Expression::MemberAccess(loc, base, member) if member.name == "difficulty" => {
+                if matches!(base.as_ref(), pt::Expression::Variable(id) if id.name == "block") {
+                    found.push((*loc, Deprecated::BlockDifficulty));
+                }
+            }
+            pt::Expression::FunctionCall(_, func, _) => {
+                if let pt::Expression::Variable(id) = func.as_ref() {
+                    if id.name == "selfdestruct" {
+                        found.push((id.loc, Deprecated::SelfDestruct));
+                    }
+                }
+            }
+            _ => (),
+        });
+
+        for (loc, deprecated) in found {
+            self.report(loc, deprecated);
+        }
+    }
+
+    fn report(&mut self, loc: pt::Loc, deprecated: Deprecated) {
+        let Deprecation { syntax, deprecated_in, removed_in, instead } = deprecated.deprecation();
+
+        let diagnostic = match removed_in {
+            Some(removed_in) if !self.admits(|req| req.admits_older(&removed_in), false) => {
+                Diagnostic::error(
+                    loc,
+                    format!("{syntax} is not supported since Solidity {removed_in}, {instead}"),
+                )
+            }
+            _ if self.admits(|req| req.admits_since(&deprecated_in), true) => Diagnostic::warning(
+                loc,
+                format!("{syntax} is deprecated since Solidity {deprecated_in}, {instead}"),
+            ),
+            _ => return,
+        };
+        self.ctx.diagnostics.push(diagnostic);
+    }
+
+    /// Whether a version pragma of the file admits versions for which the
+    /// predicate holds, or `default` when it has none
+    fn admits(&self, predicate: impl Fn(&VersionReq) -> bool, default: bool) -> bool {
+        admits(self.ctx, self.no, predicate, default)
+    }
+}
+
+/// Whether a version pragma of the file admits versions for which the
+/// predicate holds, or `default` when it has none
+fn admits(
+    ctx: &Context,
+    no: usize,
+    predicate: impl Fn(&VersionReq) -> bool,
+    default: bool,
+) -> bool {
+    let mut pragmas = ctx
+        .pragmas
+        .iter()
+        .filter_map(|pragma| match pragma {
+            Pragma::SolidityVersion { loc, versions } if loc.no() == Some(no) => Some(versions),
+            _ => None,
+        })
+        .peekable();
+
+    match pragmas.peek() {
+        None => default,
+        Some(_) => pragmas.into_iter().any(|versions| versions.iter().all(&predicate)),
+    }
+}
+
+/// Whether the file may be compiled by a version before 0.5.0, whose rules
+/// differ: functions are public by default, and a function named after its
+/// contract is its constructor
+pub(crate) fn admits_legacy(ctx: &Context, no: usize) -> bool {
+    admits(ctx, no, |req| req.admits_older(&version(5, 0)), false)
+}
+
+/// Internal error type for legacy syntax check logic
+#[derive(Debug, Error)]
+pub enum LegacySyntaxCheckerError {}
+
+impl<'a> Visitor for LegacySyntaxChecker<'a> {
+    type Error = LegacySyntaxCheckerError;
+
+    fn visit_source_unit(&mut self, source_unit: &mut pt::SourceUnit) -> Result<(), Self::Error> {
+        for part in &source_unit.0 {
+            match part {
+                pt::SourceUnitPart::FunctionDefinition(def) => self.function(def, None),
+                pt::SourceUnitPart::VariableDefinition(def) => {
+                    if let Some(expr) = &def.initializer {
+                        self.expression(expr);
+                    }
+                }
+                pt::SourceUnitPart::ContractDefinition(contract) => {
+                    for part in &contract.parts {
+                        match part {
+                            pt::ContractPart::FunctionDefinition(def) => {
+                                self.function(def, contract.name.as_ref())
+                            }
+                            pt::ContractPart::VariableDefinition(def) => {
+                                if let Some(expr) = &def.initializer {
+                                    self.expression(expr);
+                                }
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{parse, visitor::Visitable},
+        semantic::pragma::PragmaResolver,
+    };
+
+    fn check(source: &str) -> Vec<String> {
+        let mut ctx = Context::new();
+        let mut tree = parse(source, 0).unwrap();
+        for part in &tree.0 {
+            if let pt::SourceUnitPart::PragmaDirective(pragma) = part {
+                PragmaResolver::new(&mut ctx).visit_pragma(pragma).unwrap();
+            }
+        }
+        tree.visit(&mut LegacySyntaxChecker::new(&mut ctx, 0)).unwrap();
+        ctx.diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect()
+    }
+
+    #[test]
+    fn test_legacy_syntax() {
+        let source = "
+            contract Wallet {
+                function Wallet() public {}
+                function owner() public constant returns (address) {}
+                function withdraw() public {
+                    var amount = 1;
+                    if (amount == 0) { throw; }
+                }
+            }";
+
+        let errors = check(source);
+        assert_eq!(
+            errors,
+            [
+                "a function named after its contract as constructor is not supported since \
+                 Solidity 0.5.0, use 'constructor' instead",
+                "'constant' on a function is not supported since Solidity 0.5.0, use 'view' \
+                 instead",
+                "'throw' is not supported since Solidity 0.5.0, use 'revert()' instead",
+                "'var' is not supported since Solidity 0.5.0, declare the type of the variable",
+            ]
+        );
+
+        let warnings = check(&format!("pragma solidity ^0.4.24;\n{source}"));
+        assert_eq!(warnings.len(), 4);
+        assert_eq!(
+            warnings[2],
+            "'throw' is deprecated since Solidity 0.4.13, use 'revert()' instead"
+        );
+
+        let errors = check(&format!("pragma solidity >=0.4.22 || ^0.8.0;\n{source}"));
+        assert!(
+            errors[0].ends_with("is deprecated since Solidity 0.4.22, use 'constructor' instead")
+        );
+
+        let errors = check(&format!("pragma solidity ^0.8.0;\n{source}"));
+        assert!(errors.iter().all(|error| error.contains("not supported since Solidity 0.5.0")));
+    }
+
+    #[test]
+    fn test_deprecated_since() {
+        let source = "
+            contract Lottery {
+                uint constant PERIOD = 1 years;
+                function draw() public returns (uint) {
+                    selfdestruct(payable(msg.sender));
+                    return block.difficulty % 10;
+                }
+            }";
+
+        // Only the years are reported to a compiler before they were deprecated
+        let errors = check(source);
+        assert_eq!(
+            errors,
+            [
+                "the 'years' unit is not supported since Solidity 0.5.0, use '365 days' instead, \
+                 years do not all have 365 days",
+                "'block.difficulty' is deprecated since Solidity 0.8.18, use 'block.prevrandao' \
+                 instead, it is the same value since the merge",
+                "'selfdestruct' is deprecated since Solidity 0.8.18, transfer the balance \
+                 instead, since the Cancun upgrade it no longer deletes the contract",
+            ]
+        );
+        assert!(check(&format!("pragma solidity ^0.4.11;\n{source}"))[0].contains("deprecated"));
+        assert_eq!(check(&format!("pragma solidity >=0.4.11 <0.4.24;\n{source}")).len(), 0);
+        assert_eq!(check(&format!("pragma solidity ^0.8.0;\n{source}")).len(), 3);
+        assert_eq!(check(&format!("pragma solidity 0.8.17;\n{source}")).len(), 1);
+        assert_eq!(check(&format!("pragma solidity >=0.7.0 <0.8.18;\n{source}")).len(), 1);
+        assert_eq!(check(&format!("pragma solidity ~0.8;\n{source}")).len(), 3);
+    }
+}
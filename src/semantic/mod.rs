@@ -17,20 +17,27 @@ pub mod ast;
 pub mod collector;
 pub mod context;
 pub mod contract;
+pub mod dump;
 pub mod eval;
 pub mod expression;
 pub mod file;
 pub mod function;
 pub mod import;
+pub mod intern;
+pub mod layout;
+pub mod legacy;
 pub mod mutability;
+pub mod pass;
 pub mod pragma;
 pub mod semicolon;
+pub mod statement;
 pub mod symtable;
 pub mod tag;
 pub mod types;
 pub mod using;
 pub mod variable;
 pub mod visitor;
+pub mod yul;
 
 use self::context::Context;
 use crate::resolver::{FileResolver, ResolvedFile};
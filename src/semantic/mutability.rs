@@ -30,7 +30,7 @@ use bitflags::bitflags;
 pub fn check(ctx: &mut Context, no: usize) {
     if !ctx.diagnostics.any_errors() {
         for func in &ctx.functions {
-            if func.loc_prototype.try_no() != Some(no) || func.ty == pt::FunctionTy::Modifier {
+            if func.loc_prototype.no() != Some(no) || func.ty == pt::FunctionTy::Modifier {
                 continue;
             }
 
@@ -285,6 +285,8 @@ fn recurse_statements(stmts: &[Statement], state: &mut StateCheck) {
                 }
             }
             Statement::Break(_) | Statement::Continue(_) | Statement::Underscore(_) => (),
+            // The Yul of the block is not resolved, so its accesses are not known
+            Statement::Assembly(..) => (),
         }
     }
 }
@@ -349,6 +351,9 @@ fn read_expression(expr: &Expression, state: &mut StateCheck) -> bool {
             kind: Builtin::PayableSend | Builtin::PayableTransfer | Builtin::SelfDestruct,
             ..
         } => state.write(loc),
+        // As in solc, the call data is not state: `msg.data` and `msg.sig`
+        // may be read by pure functions
+        Expression::Builtin { kind: Builtin::Calldata | Builtin::Signature, .. } => (),
         Expression::Builtin { loc, kind: Builtin::Value, .. } => {
             // internal/private functions cannot be declared payable, so msg.value is only checked
             // as reading state in private/internal functions in solc.
@@ -406,6 +411,7 @@ fn write_expression(expr: &Expression, state: &mut StateCheck) -> bool {
             state.write(loc);
             return false;
         }
+        // Writes to transient storage, of `temporary` variables, are state writes too
         Expression::StorageVariable { loc, .. } => {
             state.data_account |= DataAccountUsage::WRITE;
             state.write(loc);
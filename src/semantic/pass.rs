@@ -0,0 +1,325 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The passes of the semantic analysis of a file, in the order they run. A
+//! [`PassManager`] selects which of them run, e.g. a language server may stop
+//! after the types are resolved, and times those which do.
+//!
+//! A pass reads what the passes it depends on put in the [`Context`], e.g.
+//! the bases are resolved on the contracts the types pass declared, and would
+//! index past the end of its vectors if they had not run. The manager keeps
+//! the [`ContextStage`] of each file, so that running a pass before its
+//! dependencies is an internal error naming them in debug builds.
+
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::{helpers::trace::span, semantic::context::Context};
+
+/// A pass of the semantic analysis, see [`analyze`](super::analyzer::analyze)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Pass {
+    Types,
+    Pragmas,
+    Legacy,
+    Imports,
+    Bases,
+    Functions,
+    Variables,
+    Using,
+    Contracts,
+    Creates,
+    Semicolons,
+    Mutability,
+}
+
+impl Pass {
+    /// Every pass, in the order they run
+    pub const ALL: [Pass; 12] = [
+        Pass::Types,
+        Pass::Pragmas,
+        Pass::Legacy,
+        Pass::Imports,
+        Pass::Bases,
+        Pass::Functions,
+        Pass::Variables,
+        Pass::Using,
+        Pass::Contracts,
+        Pass::Creates,
+        Pass::Semicolons,
+        Pass::Mutability,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Types => "types",
+            Pass::Pragmas => "pragmas",
+            Pass::Legacy => "legacy",
+            Pass::Imports => "imports",
+            Pass::Bases => "bases",
+            Pass::Functions => "functions",
+            Pass::Variables => "variables",
+            Pass::Using => "using",
+            Pass::Contracts => "contracts",
+            Pass::Creates => "creates",
+            Pass::Semicolons => "semicolons",
+            Pass::Mutability => "mutability",
+        }
+    }
+
+    /// The passes whose results this pass reads, which must run before it
+    /// over the same file
+    pub fn dependencies(self) -> &'static [Pass] {
+        match self {
+            Pass::Types | Pass::Pragmas | Pass::Semicolons => &[],
+            Pass::Legacy => &[Pass::Pragmas],
+            Pass::Imports => &[Pass::Types],
+            Pass::Bases => &[Pass::Imports],
+            Pass::Functions => &[Pass::Types, Pass::Bases],
+            Pass::Variables => &[Pass::Types, Pass::Functions],
+            Pass::Using => &[Pass::Functions, Pass::Variables],
+            Pass::Contracts => &[Pass::Bases, Pass::Functions, Pass::Variables, Pass::Using],
+            Pass::Creates => &[Pass::Contracts],
+            Pass::Mutability => &[Pass::Contracts],
+        }
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Pass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pass::ALL.into_iter().find(|pass| pass.name() == s).ok_or_else(|| {
+            let names = Pass::ALL.map(Pass::name).join(", ");
+            format!("unknown pass '{s}', expected one of {names}")
+        })
+    }
+}
+
+/// The passes which have run over a file, advanced by [`run`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContextStage {
+    /// A bit for each pass, by its position in [`Pass::ALL`]
+    done: u16,
+}
+
+impl ContextStage {
+    pub fn has_run(self, pass: Pass) -> bool {
+        self.done & Self::bit(pass) != 0
+    }
+
+    /// The first dependency of the pass which has not run, if any
+    pub fn missing(self, pass: Pass) -> Option<Pass> {
+        pass.dependencies().iter().copied().find(|dependency| !self.has_run(*dependency))
+    }
+
+    fn advance(&mut self, pass: Pass) {
+        self.done |= Self::bit(pass);
+    }
+
+    fn bit(pass: Pass) -> u16 {
+        1 << pass as u16
+    }
+}
+
+/// How long a pass took on a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassTiming {
+    pub pass: Pass,
+    pub file_no: usize,
+    pub elapsed: Duration,
+}
+
+/// Selects the passes to run and records how long they took. It is kept in
+/// the [`Context`], so the files analyzed for imports run the same passes.
+#[derive(Debug, Clone)]
+pub struct PassManager {
+    enabled: Vec<Pass>,
+    timings: Vec<PassTiming>,
+    /// The stage of each file, by its number
+    stages: Vec<ContextStage>,
+}
+
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl PassManager {
+    /// Run every pass
+    pub fn all() -> Self {
+        Self { enabled: Pass::ALL.to_vec(), timings: Vec::new(), stages: Vec::new() }
+    }
+
+    /// Run the passes up to the given one included, e.g. `until(Pass::Types)`
+    /// for a tool which only needs the declared types
+    pub fn until(last: Pass) -> Self {
+        Self {
+            enabled: Pass::ALL.into_iter().filter(|pass| *pass <= last).collect(),
+            timings: Vec::new(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Run the given passes, with the passes they depend on
+    pub fn only(passes: &[Pass]) -> Self {
+        let mut enabled = Vec::new();
+        let mut pending = passes.to_vec();
+        while let Some(pass) = pending.pop() {
+            if !enabled.contains(&pass) {
+                enabled.push(pass);
+                pending.extend(pass.dependencies());
+            }
+        }
+        enabled.sort();
+
+        Self { enabled, timings: Vec::new(), stages: Vec::new() }
+    }
+
+    pub fn is_enabled(&self, pass: Pass) -> bool {
+        self.enabled.contains(&pass)
+    }
+
+    /// The passes which have run over the file
+    pub fn stage(&self, file_no: usize) -> ContextStage {
+        self.stages.get(file_no).copied().unwrap_or_default()
+    }
+
+    /// The timings of the passes run so far, in the order they ran
+    pub fn timings(&self) -> &[PassTiming] {
+        &self.timings
+    }
+
+    /// Total time of each pass run over all files, in the order of the passes
+    pub fn totals(&self) -> Vec<(Pass, Duration)> {
+        Pass::ALL
+            .into_iter()
+            .filter(|pass| self.timings.iter().any(|timing| timing.pass == *pass))
+            .map(|pass| {
+                let timings = self.timings.iter().filter(|timing| timing.pass == pass);
+                (pass, timings.map(|timing| timing.elapsed).sum())
+            })
+            .collect()
+    }
+}
+
+/// Run a pass over a file if it is enabled, recording how long it took.
+/// The pass may analyze other files, like the imports, whose passes are
+/// recorded before it. The passes it depends on must have run over the file.
+pub fn run<T>(
+    ctx: &mut Context,
+    pass: Pass,
+    file_no: usize,
+    f: impl FnOnce(&mut Context) -> T,
+) -> Option<T> {
+    if !ctx.passes.is_enabled(pass) {
+        return None;
+    }
+
+    let missing = ctx.passes.stage(file_no).missing(pass);
+    debug_assert!(
+        missing.is_none(),
+        "internal error: pass '{pass}' runs over file {file_no} before pass '{}'",
+        missing.unwrap()
+    );
+
+    let _span = span!("pass", name = pass, file = file_no);
+    let start = Instant::now();
+    let result = f(ctx);
+    ctx.passes.timings.push(PassTiming { pass, file_no, elapsed: start.elapsed() });
+
+    let stages = &mut ctx.passes.stages;
+    if stages.len() <= file_no {
+        stages.resize(file_no + 1, ContextStage::default());
+    }
+    stages[file_no].advance(pass);
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pass_order() {
+        for (i, pass) in Pass::ALL.iter().enumerate() {
+            assert_eq!(pass.name().parse(), Ok(*pass));
+            for dependency in pass.dependencies() {
+                assert!(Pass::ALL[..i].contains(dependency), "{pass} runs before {dependency}");
+            }
+        }
+        assert!("typing".parse::<Pass>().is_err());
+    }
+
+    #[test]
+    fn test_select_passes() {
+        let passes = PassManager::until(Pass::Imports);
+        assert!(passes.is_enabled(Pass::Legacy));
+        assert!(!passes.is_enabled(Pass::Bases));
+
+        let passes = PassManager::only(&[Pass::Semicolons, Pass::Bases]);
+        assert_eq!(passes.enabled, [Pass::Types, Pass::Imports, Pass::Bases, Pass::Semicolons]);
+    }
+
+    #[test]
+    fn test_record_timings() {
+        let mut ctx = Context::new();
+        ctx.passes = PassManager::until(Pass::Types);
+
+        assert_eq!(run(&mut ctx, Pass::Types, 0, |_| 1), Some(1));
+        assert_eq!(run(&mut ctx, Pass::Types, 1, |_| 2), Some(2));
+        assert_eq!(run(&mut ctx, Pass::Pragmas, 0, |_| 3), None);
+
+        let files = ctx.passes.timings().iter().map(|timing| timing.file_no).collect::<Vec<_>>();
+        assert_eq!(files, [0, 1]);
+        assert_eq!(ctx.passes.totals().len(), 1);
+    }
+
+    #[test]
+    fn test_stages() {
+        let mut ctx = Context::new();
+        ctx.passes = PassManager::until(Pass::Bases);
+
+        run(&mut ctx, Pass::Types, 1, |_| ());
+        run(&mut ctx, Pass::Imports, 1, |_| ());
+        let stage = ctx.passes.stage(1);
+        assert!(stage.has_run(Pass::Imports));
+        assert!(!stage.has_run(Pass::Pragmas));
+        assert_eq!(stage.missing(Pass::Bases), None);
+        assert_eq!(stage.missing(Pass::Legacy), Some(Pass::Pragmas));
+        assert_eq!(ctx.passes.stage(0), ContextStage::default());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(
+        expected = "internal error: pass 'bases' runs over file 0 before pass 'imports'"
+    )]
+    fn test_out_of_order() {
+        let mut ctx = Context::new();
+        run(&mut ctx, Pass::Types, 0, |_| ());
+        run(&mut ctx, Pass::Bases, 0, |_| ());
+    }
+}
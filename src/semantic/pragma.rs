@@ -14,15 +14,15 @@
 
 use thiserror::Error;
 
-use super::{
-    context::Context,
-    visitor::{SemanticVisitable, SemanticVisitor},
-};
+use super::{context::Context, visitor::SemanticVisitor};
 
 use super::ast;
 use crate::{
     diagnostics::Diagnostic,
-    parser::{ast as pt, visitor::Visitor},
+    parser::{
+        ast as pt,
+        visitor::{Visitable, Visitor},
+    },
 };
 
 /// Resolve pragma from the parse tree
@@ -92,8 +92,32 @@ impl<'a> PragmaResolver<'a> {
         version: &[String],
     ) -> Result<ast::Version, PragmaResolverError> {
         let mut res = Vec::with_capacity(3);
+        let mut pre = None;
+
+        for (i, v) in version.iter().enumerate() {
+            // The pre-release and build metadata follow the last number
+            let v = match v.find(['-', '+']) {
+                Some(pos) if i == version.len() - 1 => {
+                    let (number, suffix) = v.split_at(pos);
+                    let release = suffix.strip_prefix('-').map(|rest| match rest.split_once('+') {
+                        Some((release, _)) => release,
+                        None => rest,
+                    });
+                    if let Some(release) = release {
+                        if release.split('.').any(str::is_empty) {
+                            self.ctx.diagnostics.push(Diagnostic::error(
+                                *loc,
+                                format!("'{release}' is not a valid pre-release"),
+                            ));
+                            return Err(PragmaResolverError::InvalidVersionComponent);
+                        }
+                        pre = Some(release.to_string());
+                    }
+                    number
+                }
+                _ => v.as_str(),
+            };
 
-        for v in version {
             if let Ok(v) = v.parse() {
                 res.push(v);
             } else {
@@ -112,7 +136,12 @@ impl<'a> PragmaResolver<'a> {
             return Err(PragmaResolverError::TooManyVersionComponents);
         }
 
-        Ok(ast::Version { major: res[0], minor: res.get(1).cloned(), patch: res.get(2).cloned() })
+        Ok(ast::Version {
+            major: res[0],
+            minor: res.get(1).cloned(),
+            patch: res.get(2).cloned(),
+            pre,
+        })
     }
 }
 
@@ -134,7 +163,7 @@ impl<'a> SemanticVisitor for PragmaResolver<'a> {
     ) -> Result<(), Self::Error> {
         if matches!(part.part, pt::SourceUnitPart::PragmaDirective(_)) {
             self.ctx.reject(&part.annotations, "pragma");
-            part.visit(self)?;
+            part.part.visit(self)?;
         }
 
         Ok(())
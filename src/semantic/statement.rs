@@ -0,0 +1,1338 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The statements of function bodies. The parameters and named returns of a
+//! function are declared in its outermost scope, blocks and `for` loops open
+//! scopes of their own, and each statement records whether the code after it
+//! can be reached, so that a function whose end is reachable returns.
+
+use std::sync::Arc;
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics, ErrorType, Level},
+    helpers::CodeLocation,
+    parser::ast as pt,
+    semantic::{
+        ast::{
+            CatchClause, DestructureField, Expression, InlineAssembly, Parameter, Recurse,
+            RetrieveType, Statement, TryCatch, Type,
+        },
+        context::{Context, ResolveTypeContext},
+        expression::{
+            function_call::{function_call, match_overload, Arguments},
+            lvalue::{delete, lvalue, Mutation},
+            resolve_expression::expression,
+            selector::identifier_path,
+            ExprContext, ResolveTo,
+        },
+        symtable::Symtable,
+    },
+};
+
+/// Resolve the body of the function into its statements, which are stored
+/// with the local variables on the function
+#[allow(clippy::result_unit_err)]
+pub fn resolve_function_body(
+    def: &pt::FunctionDefinition,
+    no: usize,
+    contract_no: Option<usize>,
+    function_no: usize,
+    ctx: &mut Context,
+) -> Result<(), ()> {
+    let Some(pt::Statement::Block { loc, statements: stmts, .. }) = &def.body else {
+        return Ok(());
+    };
+
+    let mut diagnostics = Diagnostics::default();
+    let mut symtable = Symtable::default();
+    let mut context =
+        ExprContext { no, contract_no, function_no: Some(function_no), ..Default::default() };
+    context.enter_scope();
+
+    let params = ctx.functions[function_no].params.clone();
+    for ((_, param), resolved) in def.params.iter().zip(params.iter()) {
+        let mut pos = None;
+        if let Some(pt::Parameter { name: Some(id), storage, .. }) = param {
+            check_shadowing(id, &context, ctx, &symtable, &mut diagnostics);
+            pos = symtable.add(
+                id,
+                resolved.ty.clone(),
+                storage.clone(),
+                &mut context,
+                &mut diagnostics,
+            );
+        }
+        symtable.arguments.push(pos);
+    }
+
+    // Unnamed return values are variables too, which a bare `return;` returns
+    let returns = ctx.functions[function_no].returns.clone();
+    for ((param_loc, param), resolved) in def.returns.iter().zip(returns.iter()) {
+        let (id, storage) = match param {
+            Some(pt::Parameter { name: Some(id), storage, .. }) => {
+                check_shadowing(id, &context, ctx, &symtable, &mut diagnostics);
+                (id.clone(), storage.clone())
+            }
+            param => (
+                pt::Identifier { loc: *param_loc, name: String::new() },
+                param.as_ref().and_then(|param| param.storage.clone()),
+            ),
+        };
+        if let Some(pos) =
+            symtable.add(&id, resolved.ty.clone(), storage, &mut context, &mut diagnostics)
+        {
+            symtable.returns.push(pos);
+        }
+    }
+
+    let modifiers = modifiers(def, &mut context, ctx, &mut symtable, &mut diagnostics);
+    let body = statements(stmts, &mut context, ctx, &mut symtable, &mut diagnostics);
+    context.leave_scope(&mut symtable, *loc);
+
+    let result = body.and_then(|mut body| {
+        if def.ty == pt::FunctionTy::Modifier {
+            let mut underscore = false;
+            for stmt in &body {
+                stmt.recurse(&mut underscore, |stmt, underscore| {
+                    *underscore |= matches!(stmt, Statement::Underscore(_));
+                    true
+                });
+            }
+            if !underscore {
+                diagnostics.push(Diagnostic::error(*loc, "modifier body does not contain '_'"));
+                return Err(());
+            }
+        } else if body.iter().all(Statement::reachable) {
+            // The values of named returns are returned on reaching the end
+            if returns.iter().any(|ret| ret.id.is_none()) {
+                diagnostics.push(Diagnostic::error(loc.end_range(), "missing return statement"));
+                return Err(());
+            }
+            body.push(Statement::Return(loc.end_range(), None));
+        }
+        Ok(body)
+    });
+
+    ctx.diagnostics.extend(diagnostics);
+
+    let body = result?;
+    let func = &mut ctx.functions[function_no];
+    func.modifiers = modifiers?;
+    func.body = body;
+    func.symtable = symtable;
+
+    Ok(())
+}
+
+/// Resolve the modifiers a function in a contract invokes into calls to them,
+/// their arguments seeing the parameters of the function. The attributes
+/// which do not name a modifier taking as many arguments, like the base
+/// contracts given to a constructor, are reported by
+/// [`check_modifiers`](super::function::check_modifiers).
+fn modifiers(
+    def: &pt::FunctionDefinition,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Vec<Expression>, ()> {
+    let mut resolved = Vec::new();
+    let mut broken = false;
+
+    for attribute in &def.attributes {
+        let pt::FunctionAttribute::BaseOrModifier(_, base) = attribute else {
+            continue;
+        };
+        let args = base.args.as_deref().unwrap_or_default();
+        let modifier = ctx
+            .resolve_function_with_namespace(
+                context.no,
+                context.contract_no,
+                &base.name,
+                &mut Diagnostics::default(),
+            )
+            .unwrap_or_default()
+            .into_iter()
+            .any(|(_, no)| {
+                let func = &ctx.functions[no];
+                func.ty == pt::FunctionTy::Modifier && func.params.len() == args.len()
+            });
+        if !modifier {
+            continue;
+        }
+
+        // `m` or `Base.m`, called as a function
+        let (first, rest) = base.name.identifiers.split_first().expect("a name");
+        let callee = rest.iter().fold(pt::Expression::Variable(first.clone()), |object, id| {
+            pt::Expression::MemberAccess(id.loc, Box::new(object), id.clone())
+        });
+        match function_call(
+            &base.loc,
+            &callee,
+            Arguments::Positional(args),
+            context,
+            ctx,
+            symtable,
+            diagnostics,
+        ) {
+            Ok(call) => resolved.push(call),
+            Err(()) => broken = true,
+        }
+    }
+
+    if broken {
+        Err(())
+    } else {
+        Ok(resolved)
+    }
+}
+
+/// Resolve the statements of a block in the current scope. A statement after
+/// one which does not complete is reported once.
+fn statements(
+    stmts: &[pt::Statement],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Vec<Statement>, ()> {
+    let mut resolved = Vec::new();
+    let mut broken = false;
+    let mut reported = false;
+
+    for stmt in stmts {
+        if !reported && resolved.last().is_some_and(|last: &Statement| !last.reachable()) {
+            diagnostics.push(Diagnostic::warning(stmt.loc(), "unreachable statement"));
+            reported = true;
+        }
+
+        match statement(stmt, context, ctx, symtable, diagnostics) {
+            Ok(stmt) => resolved.push(stmt),
+            Err(()) => broken = true,
+        }
+    }
+
+    if broken {
+        Err(())
+    } else {
+        Ok(resolved)
+    }
+}
+
+/// Resolve a single statement
+fn statement(
+    stmt: &pt::Statement,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    match stmt {
+        pt::Statement::Block { loc, unchecked, statements: stmts } => {
+            let outer = context.unchecked;
+            context.unchecked |= *unchecked;
+            context.enter_scope();
+            let resolved = statements(stmts, context, ctx, symtable, diagnostics);
+            context.leave_scope(symtable, *loc);
+            context.unchecked = outer;
+
+            Ok(Statement::Block { loc: *loc, unchecked: *unchecked, statements: resolved? })
+        }
+        pt::Statement::VariableDefinition(loc, decl, init) => {
+            variable_definition(loc, decl, init.as_ref(), context, ctx, symtable, diagnostics)
+        }
+        pt::Statement::Expression(loc, expr) => {
+            expression_statement(loc, expr, context, ctx, symtable, diagnostics)
+        }
+        pt::Statement::If(loc, cond, then, otherwise) => {
+            let cond = condition(cond, context, ctx, symtable, diagnostics);
+            let then = body(then, context, ctx, symtable, diagnostics);
+            let otherwise = otherwise
+                .as_ref()
+                .map(|otherwise| body(otherwise, context, ctx, symtable, diagnostics))
+                .transpose();
+            let (cond, then, otherwise) = (cond?, then?, otherwise?);
+
+            let reachable = then.reachable() || otherwise.as_ref().is_none_or(Statement::reachable);
+            Ok(Statement::If(*loc, reachable, cond, vec![then], otherwise.into_iter().collect()))
+        }
+        pt::Statement::While(loc, cond, stmt) => {
+            let cond = condition(cond, context, ctx, symtable, diagnostics);
+            context.loops.enter_scope();
+            let stmt = body(stmt, context, ctx, symtable, diagnostics);
+            let scope = context.loops.leave_scope();
+            let (cond, stmt) = (cond?, stmt?);
+
+            let reachable = scope.no_breaks > 0 || !is_true(&cond);
+            Ok(Statement::While(*loc, reachable, cond, vec![stmt]))
+        }
+        pt::Statement::DoWhile(loc, stmt, cond) => {
+            context.loops.enter_scope();
+            let stmt = body(stmt, context, ctx, symtable, diagnostics);
+            let scope = context.loops.leave_scope();
+            let cond = condition(cond, context, ctx, symtable, diagnostics);
+            let (stmt, cond) = (stmt?, cond?);
+
+            let reachable = scope.no_breaks > 0 ||
+                ((stmt.reachable() || scope.no_continues > 0) && !is_true(&cond));
+            Ok(Statement::DoWhile(*loc, reachable, vec![stmt], cond))
+        }
+        pt::Statement::For(loc, init, cond, next, stmt) => {
+            context.enter_scope();
+            let resolved =
+                for_loop(loc, init, cond, next, stmt, context, ctx, symtable, diagnostics);
+            context.leave_scope(symtable, *loc);
+            resolved
+        }
+        pt::Statement::Continue(loc) => {
+            if !context.loops.do_continue() {
+                diagnostics.push(Diagnostic::error(*loc, "continue statement not in loop"));
+                return Err(());
+            }
+            Ok(Statement::Continue(*loc))
+        }
+        pt::Statement::Break(loc) => {
+            if !context.loops.do_break() {
+                diagnostics.push(Diagnostic::error(*loc, "break statement not in loop"));
+                return Err(());
+            }
+            Ok(Statement::Break(*loc))
+        }
+        pt::Statement::Return(loc, value) => {
+            return_statement(loc, value.as_ref(), context, ctx, symtable, diagnostics)
+        }
+        pt::Statement::Revert(loc, path, args) => revert(
+            loc,
+            path.as_ref(),
+            Arguments::Positional(args),
+            context,
+            ctx,
+            symtable,
+            diagnostics,
+        ),
+        pt::Statement::RevertNamedArgs(loc, path, args) => {
+            revert(loc, path.as_ref(), Arguments::Named(args), context, ctx, symtable, diagnostics)
+        }
+        pt::Statement::Emit(loc, call) => emit(loc, call, context, ctx, symtable, diagnostics),
+        pt::Statement::Try(loc, call, returns, clauses) => {
+            try_catch(loc, call, returns, clauses, context, ctx, symtable, diagnostics)
+        }
+        pt::Statement::Assembly { loc, dialect, block, .. } => {
+            if let Some(dialect) = dialect.as_ref().filter(|dialect| dialect.string != "evmasm") {
+                diagnostics.push(Diagnostic::error(
+                    dialect.loc,
+                    format!("unsupported assembly dialect '{}'", dialect.string),
+                ));
+                return Err(());
+            }
+
+            // Inner scopes shadow the outer ones
+            let variables = context
+                .active_scopes
+                .iter()
+                .flat_map(|scope| scope.names.iter().map(|(name, var_no)| (name.clone(), *var_no)))
+                .collect();
+            let assembly = InlineAssembly { loc: *loc, block: block.clone(), variables };
+            Ok(Statement::Assembly(assembly, true))
+        }
+        pt::Statement::Args(loc, _) => {
+            diagnostics.push(Diagnostic::error(*loc, "expected a statement, not call arguments"));
+            Err(())
+        }
+        // Reported by the parser
+        pt::Statement::Error(_) => Err(()),
+    }
+}
+
+/// The body of an `if` or a loop, which cannot be a declaration on its own
+fn body(
+    stmt: &pt::Statement,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    if let pt::Statement::VariableDefinition(loc, ..) = stmt {
+        diagnostics
+            .push(Diagnostic::error(*loc, "variable declarations can only be used inside blocks"));
+        return Err(());
+    }
+
+    statement(stmt, context, ctx, symtable, diagnostics)
+}
+
+/// The condition of an `if` or a loop
+fn condition(
+    cond: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Expression, ()> {
+    let expr = expression(cond, context, ctx, symtable, diagnostics, ResolveTo::Type(&Type::Bool))?;
+    expr.cast(&cond.loc(), &Type::Bool, true, ctx, diagnostics)
+}
+
+/// A loop with a condition which is always true only ends with a `break`
+fn is_true(cond: &Expression) -> bool {
+    matches!(cond, Expression::BoolLiteral { value: true, .. })
+}
+
+/// Resolve `for (init; cond; next) body` in the scope of the loop, where the
+/// variable declared by `init` lives
+#[allow(clippy::too_many_arguments)]
+fn for_loop(
+    loc: &pt::Loc,
+    init: &Option<Box<pt::Statement>>,
+    cond: &Option<Box<pt::Expression>>,
+    next: &Option<Box<pt::Expression>>,
+    stmt: &Option<Box<pt::Statement>>,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let init =
+        init.as_ref().map(|init| statement(init, context, ctx, symtable, diagnostics)).transpose();
+    let cond =
+        cond.as_ref().map(|cond| condition(cond, context, ctx, symtable, diagnostics)).transpose();
+
+    context.loops.enter_scope();
+    let stmt =
+        stmt.as_ref().map(|stmt| body(stmt, context, ctx, symtable, diagnostics)).transpose();
+    let scope = context.loops.leave_scope();
+
+    let next = next
+        .as_ref()
+        .map(|next| expression(next, context, ctx, symtable, diagnostics, ResolveTo::Discard))
+        .transpose();
+    let (init, cond, stmt, next) = (init?, cond?, stmt?, next?);
+
+    let reachable = scope.no_breaks > 0 || !cond.as_ref().is_none_or(is_true);
+    Ok(Statement::For {
+        loc: *loc,
+        reachable,
+        init: init.into_iter().collect(),
+        cond,
+        next,
+        body: stmt.into_iter().collect(),
+    })
+}
+
+/// Resolve `ty name = init;`, declaring the variable after its initializer
+fn variable_definition(
+    loc: &pt::Loc,
+    decl: &pt::VariableDeclaration,
+    init: Option<&pt::Expression>,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    // A missing name is reported by the parser, and `var` by the legacy syntax
+    let Some(id) = &decl.name else {
+        return Err(());
+    };
+    if matches!(&decl.ty, pt::Expression::Variable(ty) if ty.name == "var") {
+        return Err(());
+    }
+
+    let ty = local_type(&decl.ty, &decl.storage, id, context, ctx, diagnostics)?;
+
+    let initializer = match init {
+        Some(init) => {
+            let expr = expression(
+                init,
+                context,
+                ctx,
+                symtable,
+                diagnostics,
+                ResolveTo::Type(ty.deref_any()),
+            )?;
+            let expr = expr.cast(&init.loc(), &ty, true, ctx, diagnostics)?;
+            expr.check_constant_overflow(diagnostics);
+            Some(expr)
+        }
+        None => None,
+    };
+
+    check_shadowing(id, context, ctx, symtable, diagnostics);
+    let pos = symtable.add(id, ty.clone(), decl.storage.clone(), context, diagnostics).ok_or(())?;
+    let var = &mut symtable.vars[&pos];
+    var.assigned = initializer.is_some();
+    var.initializer = initializer.clone();
+
+    Ok(Statement::VariableDecl(
+        *loc,
+        pos,
+        parameter(decl.loc, Some(id.clone()), ty, decl.ty.loc()),
+        initializer.map(Arc::new),
+    ))
+}
+
+/// The type of a local variable, a storage reference when it points into
+/// storage
+fn local_type(
+    ty: &pt::Expression,
+    storage: &Option<pt::StorageLocation>,
+    id: &pt::Identifier,
+    context: &ExprContext,
+    ctx: &mut Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Type, ()> {
+    let ty_loc = ty.loc();
+    let ty = ctx.resolve_type(
+        context.no,
+        context.contract_no,
+        ResolveTypeContext::None,
+        ty,
+        diagnostics,
+    )?;
+
+    if !ty.can_have_data_location() {
+        if let Some(storage) = storage {
+            diagnostics.push(Diagnostic::error(
+                storage.loc(),
+                format!(
+                    "data location '{storage}' can only be specified for array, struct or mapping"
+                ),
+            ));
+            return Err(());
+        }
+        return Ok(ty);
+    }
+
+    match storage {
+        Some(pt::StorageLocation::Storage(_)) => Ok(Type::StorageRef(false, Box::new(ty))),
+        Some(storage) if matches!(ty, Type::Mapping(_)) => {
+            diagnostics.push(Diagnostic::error(
+                storage.loc(),
+                format!("variable '{}' with mapping type must be of type 'storage'", id.name),
+            ));
+            Err(())
+        }
+        Some(_) => Ok(ty),
+        None => {
+            diagnostics.push(Diagnostic::error(
+                ty_loc,
+                format!(
+                    "data location must be 'memory', 'storage' or 'calldata' for variable '{}'",
+                    id.name
+                ),
+            ));
+            Err(())
+        }
+    }
+}
+
+/// Warn about a local variable which hides a variable of an enclosing scope,
+/// or a declaration of the contract or file
+fn check_shadowing(
+    id: &pt::Identifier,
+    context: &ExprContext,
+    ctx: &Context,
+    symtable: &Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) {
+    let Some((_, outer)) = context.active_scopes.split_last() else {
+        return;
+    };
+
+    let previous = match outer.iter().rev().find_map(|scope| scope.names.get(&id.name)) {
+        Some(var_no) => symtable.vars[var_no].loc,
+        None => match ctx.find_symbol(context.no, context.contract_no, &id.name, true) {
+            Some(symbol) => symbol.loc(),
+            None => return,
+        },
+    };
+
+    diagnostics.push(
+        Diagnostic::builder(id.loc, Level::Warning)
+            .ty(ErrorType::Warning)
+            .message(format!("declaration of '{}' shadows an existing declaration", id.name))
+            .note(previous, format!("previous declaration of '{}'", id.name))
+            .build(),
+    );
+}
+
+/// A parameter describing a local variable
+fn parameter(
+    loc: pt::Loc,
+    id: Option<pt::Identifier>,
+    ty: Type,
+    ty_loc: pt::Loc,
+) -> Parameter<Type> {
+    Parameter { loc, id, ty_loc: Some(ty_loc), ..Parameter::new_default(ty) }
+}
+
+/// Resolve an expression used as a statement, which may also be `_` in a
+/// modifier, `delete`, or an assignment to a list of variables
+fn expression_statement(
+    loc: &pt::Loc,
+    expr: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    match expr {
+        pt::Expression::Variable(id) if id.name == "_" => {
+            let modifier = context.function_no.is_some_and(|function_no| {
+                ctx.functions[function_no].ty == pt::FunctionTy::Modifier
+            });
+            if !modifier {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    "'_' can only be used in the body of a modifier",
+                ));
+                return Err(());
+            }
+            Ok(Statement::Underscore(*loc))
+        }
+        // Reported with the legacy syntax
+        pt::Expression::Variable(id) if id.name == "throw" => {
+            Ok(Statement::Revert { loc: *loc, error_no: None, args: Vec::new() })
+        }
+        pt::Expression::Delete(_, expr) => delete(loc, expr, context, ctx, symtable, diagnostics),
+        pt::Expression::Assign(_, left, right) => match left.as_ref() {
+            pt::Expression::List(_, list) => {
+                destructure(loc, list, right, context, ctx, symtable, diagnostics)
+            }
+            _ => expression_value(loc, expr, context, ctx, symtable, diagnostics),
+        },
+        _ => expression_value(loc, expr, context, ctx, symtable, diagnostics),
+    }
+}
+
+/// An expression whose value is discarded, which does not complete if it
+/// never returns, like `selfdestruct()`
+fn expression_value(
+    loc: &pt::Loc,
+    expr: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let expr = expression(expr, context, ctx, symtable, diagnostics, ResolveTo::Discard)?;
+    let reachable = !tys(&expr).contains(&Type::Unreachable);
+
+    Ok(Statement::Expression(*loc, reachable, expr))
+}
+
+/// The types of the values of an expression, which is a list for calls and
+/// tuples
+fn tys(expr: &Expression) -> Vec<Type> {
+    match expr {
+        Expression::Builtin { tys, .. } |
+        Expression::InternalFunctionCall { returns: tys, .. } |
+        Expression::ExternalFunctionCall { returns: tys, .. } => tys.clone(),
+        Expression::ExternalFunctionCallRaw { .. } => vec![Type::Bool, Type::DynamicBytes],
+        Expression::List { list, .. } => list.iter().map(RetrieveType::ty).collect(),
+        _ => vec![expr.ty()],
+    }
+}
+
+/// Check a value of one type converts implicitly to another, where there is
+/// no expression of its own to convert, like a value of a returned tuple
+fn check_conversion(
+    loc: &pt::Loc,
+    from: &Type,
+    to: &Type,
+    ctx: &Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), ()> {
+    let value = Expression::Variable { loc: *loc, ty: from.clone(), var_no: usize::MAX };
+    value.cast(loc, to, true, ctx, diagnostics).map(|_| ())
+}
+
+/// Resolve `(a, , uint c) = right;`, which assigns the values of a tuple to
+/// lvalues and newly declared variables, skipping the omitted ones
+fn destructure(
+    loc: &pt::Loc,
+    list: &pt::ParameterList,
+    right: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let right = expression(right, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+    let tys = tys(&right);
+
+    if tys.len() != list.len() {
+        diagnostics.push(Diagnostic::type_error(
+            *loc,
+            format!(
+                "destructuring assignment has {} values on the left and {} on the right",
+                list.len(),
+                tys.len()
+            ),
+        ));
+        return Err(());
+    }
+
+    let mut fields = Vec::new();
+    let mut broken = false;
+    for ((param_loc, param), ty) in list.iter().zip(&tys) {
+        match destructure_field(param_loc, param, ty, context, ctx, symtable, diagnostics) {
+            Ok(field) => fields.push(field),
+            Err(()) => broken = true,
+        }
+    }
+
+    if broken {
+        return Err(());
+    }
+
+    Ok(Statement::Destructure(*loc, fields, right))
+}
+
+/// One element on the left of a destructuring assignment, receiving a value
+/// of the type
+#[allow(clippy::too_many_arguments)]
+fn destructure_field(
+    loc: &pt::Loc,
+    param: &Option<pt::Parameter>,
+    ty: &Type,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<DestructureField, ()> {
+    match param {
+        None => Ok(DestructureField::None),
+        Some(pt::Parameter { ty: expr, storage: None, name: None, .. }) => {
+            let var = lvalue(expr, Mutation::Assign, context, ctx, symtable, diagnostics)?;
+            check_conversion(&expr.loc(), ty, var.ty().deref_any(), ctx, diagnostics)?;
+            Ok(DestructureField::Expression(var))
+        }
+        Some(pt::Parameter { ty: ty_expr, storage, name: Some(id), .. }) => {
+            let var_ty = local_type(ty_expr, storage, id, context, ctx, diagnostics)?;
+            check_conversion(loc, ty, &var_ty, ctx, diagnostics)?;
+
+            check_shadowing(id, context, ctx, symtable, diagnostics);
+            let pos = symtable.add(id, var_ty.clone(), storage.clone(), context, diagnostics);
+            let pos = pos.ok_or(())?;
+            symtable.vars[&pos].assigned = true;
+
+            Ok(DestructureField::VariableDecl(
+                pos,
+                parameter(*loc, Some(id.clone()), var_ty, ty_expr.loc()),
+            ))
+        }
+        Some(_) => {
+            diagnostics.push(Diagnostic::error(*loc, "variable declaration requires a name"));
+            Err(())
+        }
+    }
+}
+
+/// Resolve `return;` or `return value;`. A tuple returns the values of a
+/// function with more than one return value.
+fn return_statement(
+    loc: &pt::Loc,
+    value: Option<&pt::Expression>,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let function_no = context.function_no.expect("return outside a function");
+    let returns = ctx.functions[function_no].returns.clone();
+
+    let Some(value) = value else {
+        return Ok(Statement::Return(*loc, None));
+    };
+
+    let incorrect = |diagnostics: &mut dyn DiagnosticSink, count: usize| {
+        diagnostics.push(Diagnostic::type_error(
+            value.loc(),
+            format!(
+                "incorrect number of return values, expected {} but got {count}",
+                returns.len()
+            ),
+        ));
+        Err(())
+    };
+
+    let expr = match (returns.as_slice(), value) {
+        ([], _) => {
+            diagnostics.push(Diagnostic::type_error(value.loc(), "function has no return values"));
+            return Err(());
+        }
+        ([_], pt::Expression::List(_, list)) if list.len() != 1 => {
+            return incorrect(diagnostics, list.len());
+        }
+        ([ret], _) => {
+            let expr =
+                expression(value, context, ctx, symtable, diagnostics, ResolveTo::Type(&ret.ty))?;
+            let expr = expr.cast(&value.loc(), &ret.ty, true, ctx, diagnostics)?;
+            expr.check_constant_overflow(diagnostics);
+            expr
+        }
+        (_, pt::Expression::List(list_loc, list)) => {
+            if list.len() != returns.len() {
+                return incorrect(diagnostics, list.len());
+            }
+
+            let mut resolved = Vec::new();
+            for ((param_loc, param), ret) in list.iter().zip(returns.iter()) {
+                let Some(pt::Parameter { ty: expr, storage: None, name: None, .. }) = param else {
+                    diagnostics.push(Diagnostic::error(*param_loc, "expected a return value"));
+                    return Err(());
+                };
+                let value = expression(
+                    expr,
+                    context,
+                    ctx,
+                    symtable,
+                    diagnostics,
+                    ResolveTo::Type(&ret.ty),
+                )?;
+                let value = value.cast(&expr.loc(), &ret.ty, true, ctx, diagnostics)?;
+                value.check_constant_overflow(diagnostics);
+                resolved.push(value);
+            }
+            Expression::List { loc: *list_loc, list: resolved }
+        }
+        _ => {
+            // A call returning as many values
+            let expr = expression(value, context, ctx, symtable, diagnostics, ResolveTo::Unknown)?;
+            let tys = tys(&expr);
+            if tys.len() != returns.len() {
+                return incorrect(diagnostics, tys.len());
+            }
+            for (ty, ret) in tys.iter().zip(returns.iter()) {
+                check_conversion(&value.loc(), ty, &ret.ty, ctx, diagnostics)?;
+            }
+            expr
+        }
+    };
+
+    Ok(Statement::Return(*loc, Some(expr)))
+}
+
+/// Resolve `revert(reason);` or `revert Error(args);`
+fn revert(
+    loc: &pt::Loc,
+    path: Option<&pt::IdentifierPath>,
+    args: Arguments,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let Some(path) = path else {
+        let args = match args {
+            Arguments::Positional([]) => Vec::new(),
+            Arguments::Positional([reason]) => {
+                let expr = expression(
+                    reason,
+                    context,
+                    ctx,
+                    symtable,
+                    diagnostics,
+                    ResolveTo::Type(&Type::String),
+                )?;
+                vec![expr.cast(&reason.loc(), &Type::String, true, ctx, diagnostics)?]
+            }
+            Arguments::Positional(_) => {
+                diagnostics
+                    .push(Diagnostic::error(*loc, "revert takes at most one argument, the reason"));
+                return Err(());
+            }
+            Arguments::Named(_) => {
+                diagnostics.push(Diagnostic::error(
+                    *loc,
+                    "named arguments to revert require the name of an error",
+                ));
+                return Err(());
+            }
+        };
+        return Ok(Statement::Revert { loc: *loc, error_no: None, args });
+    };
+
+    let error_no =
+        ctx.resolve_error_with_namespace(context.no, context.contract_no, path, diagnostics)?;
+    let fields = ctx.errors[error_no]
+        .fields
+        .iter()
+        .map(|field| (field.id.clone(), field.ty.clone()))
+        .collect::<Vec<_>>();
+    let (_, args) = match_overload(
+        loc,
+        &path.to_string(),
+        &[fields],
+        args,
+        context,
+        ctx,
+        symtable,
+        diagnostics,
+    )?;
+    ctx.errors[error_no].used = true;
+
+    Ok(Statement::Revert { loc: *loc, error_no: Some(error_no), args })
+}
+
+/// Resolve `emit Event(args);`, choosing among the overloads of the event
+fn emit(
+    loc: &pt::Loc,
+    call: &pt::Expression,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let (callee, args) = match call {
+        pt::Expression::FunctionCall(_, callee, args) => (callee, Arguments::Positional(args)),
+        pt::Expression::NamedFunctionCall(_, callee, args) => (callee, Arguments::Named(args)),
+        _ => {
+            diagnostics.push(Diagnostic::error(call.loc(), "expected an event to emit"));
+            return Err(());
+        }
+    };
+    let Some(path) = identifier_path(callee) else {
+        diagnostics.push(Diagnostic::error(callee.loc(), "expected the name of an event"));
+        return Err(());
+    };
+
+    let events =
+        ctx.resolve_event_with_namespace(context.no, context.contract_no, &path, diagnostics)?;
+    let overloads = events
+        .iter()
+        .map(|(_, event_no)| {
+            ctx.events[*event_no]
+                .fields
+                .iter()
+                .map(|field| (field.id.clone(), field.ty.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let (index, args) = match_overload(
+        loc,
+        &path.to_string(),
+        &overloads,
+        args,
+        context,
+        ctx,
+        symtable,
+        diagnostics,
+    )?;
+
+    let event_no = events[index].1;
+    ctx.events[event_no].used = true;
+    if let Some(function_no) = context.function_no {
+        let emits = &mut ctx.functions[function_no].emits_events;
+        if !emits.contains(&event_no) {
+            emits.push(event_no);
+        }
+    }
+
+    Ok(Statement::Emit { loc: *loc, event_no, event_loc: callee.loc(), args })
+}
+
+/// Resolve `try call returns (..) { .. } catch ..`. The returned values are
+/// declared in the scope of the block run on success, and the parameter of
+/// each catch clause in the scope of its block.
+#[allow(clippy::too_many_arguments)]
+fn try_catch(
+    loc: &pt::Loc,
+    call: &pt::Expression,
+    returns: &Option<(pt::ParameterList, Box<pt::Statement>)>,
+    clauses: &[pt::CatchClause],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Statement, ()> {
+    let expr = expression(call, context, ctx, symtable, diagnostics, ResolveTo::Discard)?;
+    let call_returns = match &expr {
+        Expression::ExternalFunctionCall { returns, .. } => returns.clone(),
+        Expression::Constructor { contract_no, .. } => vec![Type::Contract(*contract_no)],
+        _ => {
+            diagnostics.push(Diagnostic::type_error(
+                call.loc(),
+                "try only supports external calls and contract creations",
+            ));
+            return Err(());
+        }
+    };
+
+    let mut broken = false;
+
+    let (returns, ok_stmt) = match returns {
+        Some((params, ok)) => {
+            context.enter_scope();
+            let resolved =
+                try_returns(params, ok, &call_returns, context, ctx, symtable, diagnostics);
+            context.leave_scope(symtable, ok.loc());
+            match resolved {
+                Ok(resolved) => resolved,
+                Err(()) => {
+                    broken = true;
+                    (Vec::new(), Vec::new())
+                }
+            }
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+    let mut reachable = ok_stmt.iter().all(Statement::reachable);
+
+    let mut errors = Vec::new();
+    let mut catch_all = None;
+    let mut seen: Vec<&str> = Vec::new();
+    for clause in clauses {
+        let (clause_loc, name, param, stmt) = match clause {
+            pt::CatchClause::Simple(loc, param, stmt) => (loc, "", param.as_ref(), stmt),
+            pt::CatchClause::Named(loc, id, param, stmt) => {
+                (loc, id.name.as_str(), Some(param), stmt)
+            }
+        };
+
+        let ty = match name {
+            "" => Type::DynamicBytes,
+            "Error" => Type::String,
+            "Panic" => Type::Uint(256),
+            _ => {
+                diagnostics.push(Diagnostic::error(
+                    *clause_loc,
+                    format!("only catch 'Error' and 'Panic' are supported, not '{name}'"),
+                ));
+                broken = true;
+                continue;
+            }
+        };
+        if seen.contains(&name) {
+            diagnostics.push(Diagnostic::error(*clause_loc, "duplicate catch clause"));
+            broken = true;
+            continue;
+        }
+        seen.push(name);
+
+        context.enter_scope();
+        let resolved = catch_clause(param, &ty, stmt, context, ctx, symtable, diagnostics);
+        context.leave_scope(symtable, *clause_loc);
+
+        match resolved {
+            Ok(clause) => {
+                reachable |= clause.stmt.iter().all(Statement::reachable);
+                match name {
+                    "" => catch_all = Some(clause),
+                    _ => errors.push(clause),
+                }
+            }
+            Err(()) => broken = true,
+        }
+    }
+
+    if broken {
+        return Err(());
+    }
+
+    Ok(Statement::TryCatch(*loc, reachable, TryCatch { expr, returns, ok_stmt, errors, catch_all }))
+}
+
+/// The values returned to a try statement and its block run on success
+#[allow(clippy::type_complexity)]
+fn try_returns(
+    params: &pt::ParameterList,
+    ok: &pt::Statement,
+    call_returns: &[Type],
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(Vec<(Option<usize>, Parameter<Type>)>, Vec<Statement>), ()> {
+    if params.len() != call_returns.len() {
+        diagnostics.push(Diagnostic::type_error(
+            params.first().map_or(ok.loc(), |(loc, _)| *loc),
+            format!(
+                "try returns list has {} values but the call returns {}",
+                params.len(),
+                call_returns.len()
+            ),
+        ));
+        return Err(());
+    }
+
+    let mut returns = Vec::new();
+    for ((param_loc, param), ret) in params.iter().zip(call_returns) {
+        let Some(param) = param else {
+            diagnostics.push(Diagnostic::error(*param_loc, "missing return type"));
+            return Err(());
+        };
+        let ty = catch_param(param, ret, context, ctx, diagnostics)?;
+
+        let pos = match &param.name {
+            Some(id) => {
+                check_shadowing(id, context, ctx, symtable, diagnostics);
+                symtable.add(id, ty.clone(), param.storage.clone(), context, diagnostics)
+            }
+            None => None,
+        };
+        returns.push((pos, parameter(*param_loc, param.name.clone(), ty, param.ty.loc())));
+    }
+
+    let ok_stmt = statement(ok, context, ctx, symtable, diagnostics)?;
+
+    Ok((returns, vec![ok_stmt]))
+}
+
+/// A catch clause, whose parameter receives the reason, the panic code or
+/// the data returned by the failing call
+fn catch_clause(
+    param: Option<&pt::Parameter>,
+    ty: &Type,
+    stmt: &pt::Statement,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    symtable: &mut Symtable,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<CatchClause, ()> {
+    let mut clause = CatchClause { param: None, param_pos: None, stmt: Vec::new() };
+
+    if let Some(param) = param {
+        let ty = catch_param(param, ty, context, ctx, diagnostics)?;
+        if let Some(id) = &param.name {
+            check_shadowing(id, context, ctx, symtable, diagnostics);
+            clause.param_pos =
+                symtable.add(id, ty.clone(), param.storage.clone(), context, diagnostics);
+        }
+        clause.param = Some(parameter(param.loc, param.name.clone(), ty, param.ty.loc()));
+    }
+
+    clause.stmt.push(statement(stmt, context, ctx, symtable, diagnostics)?);
+
+    Ok(clause)
+}
+
+/// The type of a parameter receiving a value of a try statement, which must
+/// be the type of the value
+fn catch_param(
+    param: &pt::Parameter,
+    expected: &Type,
+    context: &mut ExprContext,
+    ctx: &mut Context,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Type, ()> {
+    let id = param.name.clone().unwrap_or(pt::Identifier { loc: param.loc, name: String::new() });
+    let ty = local_type(&param.ty, &param.storage, &id, context, ctx, diagnostics)?;
+
+    if ty != *expected {
+        diagnostics.push(Diagnostic::type_error(
+            param.ty.loc(),
+            format!(
+                "expected a parameter of type '{}', not '{}'",
+                expected.to_string(ctx),
+                ty.to_string(ctx)
+            ),
+        ));
+        return Err(());
+    }
+
+    Ok(ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser, semantic::ast::Function};
+
+    /// Resolve the body of the first function of the contract in the source,
+    /// giving the context and the first error
+    fn resolve(src: &str) -> (Context, Result<(), String>) {
+        let tree = parser::parse(&format!("contract C {{ {src} }}"), 0).unwrap();
+        let Some(pt::SourceUnitPart::ContractDefinition(contract)) = tree.0.first() else {
+            panic!("no contract");
+        };
+        let Some(pt::ContractPart::FunctionDefinition(def)) = contract.parts.first() else {
+            panic!("no function");
+        };
+
+        let mut ctx = Context::new();
+        let mut diagnostics = Diagnostics::default();
+        let mut parameters = |list: &pt::ParameterList, ctx: &mut Context| {
+            list.iter()
+                .map(|(loc, param)| {
+                    let param = param.as_ref().unwrap();
+                    let ty = ctx
+                        .resolve_type(
+                            0,
+                            None,
+                            ResolveTypeContext::None,
+                            &param.ty,
+                            &mut diagnostics,
+                        )
+                        .unwrap();
+                    parameter(*loc, param.name.clone(), ty, param.ty.loc())
+                })
+                .collect::<Vec<_>>()
+        };
+        let params = parameters(&def.params, &mut ctx);
+        let returns = parameters(&def.returns, &mut ctx);
+        let func = Function::new(
+            def.loc_prototype,
+            def.loc,
+            def.name.clone().unwrap(),
+            None,
+            Vec::new(),
+            def.ty,
+            None,
+            pt::Visibility::Public(None),
+            params,
+            returns,
+            &ctx,
+        );
+        ctx.functions.push(func);
+
+        let result = resolve_function_body(def, 0, None, 0, &mut ctx);
+        let result = result.map_err(|()| ctx.diagnostics.first_error());
+        (ctx, result)
+    }
+
+    #[test]
+    fn test_resolve_body() {
+        let (ctx, result) = resolve(
+            "function f(uint a) public returns (uint b) {
+                uint c = a + 1;
+                if (c > 2) {
+                    return c;
+                }
+                for (uint i = 0; i < a; i++) {
+                    if (i == 3) continue;
+                    c += i;
+                }
+                b = c;
+            }",
+        );
+        assert_eq!(result, Ok(()));
+
+        let func = &ctx.functions[0];
+        let names = func.symtable.vars.values().map(|var| var.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "c", "i"]);
+        assert_eq!(func.symtable.arguments, [Some(0)]);
+        assert_eq!(func.symtable.returns, [1]);
+        assert!(matches!(func.body[0], Statement::VariableDecl(_, 2, _, Some(_))));
+        assert!(matches!(func.body[1], Statement::If(_, true, ..)));
+        assert!(matches!(func.body[2], Statement::For { reachable: true, .. }));
+        // The named return value is returned at the end
+        assert!(matches!(func.body.last(), Some(Statement::Return(_, None))));
+        assert!(ctx.diagnostics.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_reachability() {
+        // The loop never ends, so there is no missing return
+        let (_, result) = resolve("function f() public returns (uint) { while (true) {} }");
+        assert_eq!(result, Ok(()));
+
+        let (_, result) = resolve("function f() public returns (uint) { while (true) { break; } }");
+        assert_eq!(result.unwrap_err(), "missing return statement");
+
+        let (ctx, result) =
+            resolve("function f(bool x) public returns (uint) { if (x) { return 1; } else { return 2; } x = true; }");
+        assert_eq!(result, Ok(()));
+        assert!(ctx.diagnostics.warning_contains("unreachable statement"));
+
+        let (_, result) = resolve("modifier m() { _; }");
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_scopes() {
+        let (ctx, result) = resolve("function f(uint a) public { { uint a = 1; } }");
+        assert_eq!(result, Ok(()));
+        assert!(ctx
+            .diagnostics
+            .warning_contains("declaration of 'a' shadows an existing declaration"));
+
+        // The parameters are in the same scope as the body
+        let (_, result) = resolve("function f(uint a) public { uint a = 1; }");
+        assert_eq!(result.unwrap_err(), "'a' is already declared");
+
+        // A variable is only visible after its declaration, in its block
+        let (_, result) = resolve("function f() public { { uint x = 1; } x = 2; }");
+        assert_eq!(result.unwrap_err(), "'x' not found");
+    }
+
+    #[test]
+    fn test_statement_errors() {
+        let error = |src: &str| resolve(src).1.unwrap_err();
+
+        assert_eq!(error("function f() public { break; }"), "break statement not in loop");
+        assert_eq!(error("function f() public { continue; }"), "continue statement not in loop");
+        assert_eq!(
+            error("function f() public { _; }"),
+            "'_' can only be used in the body of a modifier"
+        );
+        assert_eq!(error("modifier m() { }"), "modifier body does not contain '_'");
+        assert_eq!(error("function f() public { return 1; }"), "function has no return values");
+        assert_eq!(
+            error("function f() public returns (uint) { return (1, 2); }"),
+            "incorrect number of return values, expected 1 but got 2"
+        );
+        assert_eq!(
+            error("function f() public returns (uint, bool) { return 1; }"),
+            "incorrect number of return values, expected 2 but got 1"
+        );
+        assert_eq!(
+            error("function f() public { string s; }"),
+            "data location must be 'memory', 'storage' or 'calldata' for variable 's'"
+        );
+        assert_eq!(
+            error("function f() public { uint memory x; }"),
+            "data location 'memory' can only be specified for array, struct or mapping"
+        );
+        assert_eq!(
+            error("function f() public { if (true) uint x = 1; }"),
+            "variable declarations can only be used inside blocks"
+        );
+        assert_eq!(
+            error("function f() public { (uint a, uint b) = (1, 2, 3); }"),
+            "destructuring assignment has 2 values on the left and 3 on the right"
+        );
+    }
+
+    #[test]
+    fn test_literal_overflow() {
+        let error = |src: &str| {
+            let (ctx, _) = resolve(src);
+            ctx.diagnostics.errors().iter().map(|error| error.message.clone()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            error("function f() public { uint8 x = 256; }"),
+            ["value 256 does not fit into type uint8."]
+        );
+        assert_eq!(
+            error("function f() public { uint8 x = 2**8; }"),
+            ["value 256 does not fit into type uint8."]
+        );
+        assert_eq!(
+            error("function f() public { int8 x = -129; }"),
+            ["value -129 does not fit into type int8."]
+        );
+        assert_eq!(
+            error("function f() public returns (uint8) { return 256; }"),
+            ["value 256 does not fit into type uint8."]
+        );
+        assert_eq!(
+            error("function f() public returns (bool, uint8) { return (true, 255 + 1); }"),
+            ["value 256 does not fit into type uint8."]
+        );
+        assert!(error("function f() public returns (uint8) { uint8 x = 255; return 2**8 - 1; }")
+            .is_empty());
+    }
+}
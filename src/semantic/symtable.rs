@@ -16,7 +16,14 @@ use std::collections::HashMap;
 
 use indexmap::IndexMap;
 
-use crate::{parser::ast as pt, semantic::ast::Variable};
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink, ErrorType, Level},
+    parser::ast as pt,
+    semantic::{
+        ast::{Type, Variable},
+        expression::ExprContext,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct VarScope {
@@ -32,12 +39,64 @@ pub struct Symtable {
     pub scopes: Vec<VarScope>,
 }
 
+impl Symtable {
+    /// Declare a local variable in the innermost active scope, giving its
+    /// number. A variable without a name, like an unnamed return value, can be
+    /// referred to by its number only. Returns `None` if the scope already
+    /// declares the name.
+    pub fn add(
+        &mut self,
+        id: &pt::Identifier,
+        ty: Type,
+        storage_location: Option<pt::StorageLocation>,
+        context: &mut ExprContext,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Option<usize> {
+        let scope = context.active_scopes.last_mut().expect("no active scope");
+
+        if let Some(prev) = scope.names.get(&id.name) {
+            diagnostics.push(
+                Diagnostic::builder(id.loc, Level::Error)
+                    .ty(ErrorType::DeclarationError)
+                    .message(format!("'{}' is already declared", id.name))
+                    .note(self.vars[prev].loc, format!("previous declaration of '{}'", id.name))
+                    .build(),
+            );
+            return None;
+        }
+
+        let pos = self.vars.len();
+        self.vars.insert(
+            pos,
+            Variable {
+                tags: Vec::new(),
+                name: id.name.clone(),
+                loc: id.loc,
+                ty,
+                visibility: pt::Visibility::Internal(None),
+                constant: false,
+                immutable: false,
+                initializer: None,
+                assigned: false,
+                read: false,
+                storage_type: None,
+                storage_location,
+            },
+        );
+
+        if !id.name.is_empty() {
+            scope.names.insert(id.name.clone(), pos);
+        }
+
+        Some(pos)
+    }
+}
+
 pub struct LoopScope {
     pub no_breaks: usize,
     pub no_continues: usize,
 }
 
-#[allow(dead_code)]
 pub struct LoopScopes(Vec<LoopScope>);
 
 impl Default for LoopScopes {
@@ -50,4 +109,36 @@ impl LoopScopes {
     pub fn new() -> Self {
         LoopScopes(Vec::new())
     }
+
+    /// Enter the body of a loop
+    pub fn enter_scope(&mut self) {
+        self.0.push(LoopScope { no_breaks: 0, no_continues: 0 })
+    }
+
+    /// Leave the body of a loop, giving the breaks and continues seen in it
+    pub fn leave_scope(&mut self) -> LoopScope {
+        self.0.pop().expect("not in a loop")
+    }
+
+    /// Count a `break` in the innermost loop, returning false outside a loop
+    pub fn do_break(&mut self) -> bool {
+        match self.0.last_mut() {
+            Some(scope) => {
+                scope.no_breaks += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Count a `continue` in the innermost loop, returning false outside a loop
+    pub fn do_continue(&mut self) -> bool {
+        match self.0.last_mut() {
+            Some(scope) => {
+                scope.no_continues += 1;
+                true
+            }
+            None => false,
+        }
+    }
 }
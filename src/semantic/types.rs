@@ -13,11 +13,14 @@
 // limitations under the License.
 
 use indexmap::IndexMap;
-use std::{collections::HashSet, fmt::Write};
+use num_bigint::BigInt;
+use num_traits::One;
+use std::{collections::HashSet, fmt, mem};
 use thiserror::Error;
 
 use crate::{
-    diagnostics::{Diagnostic, Level},
+    abi::ethereum,
+    diagnostics::{Diagnostic, Diagnostics, Level, Note},
     helpers::CodeLocation,
     parser::{
         ast as pt,
@@ -28,13 +31,21 @@ use crate::{
 
 use super::{
     ast::{
-        ContractDefinition, EnumDecl, ErrorDecl, EventDecl, SourceUnitPart, StructDecl, StructType,
-        Symbol, Type,
+        Contract, ContractDefinition, EnumDecl, ErrorDecl, EventDecl, Parameter, SourceUnit,
+        SourceUnitPart, StructDecl, StructType, Symbol, Type, UserTypeDecl,
     },
-    context::Context,
-    visitor::SemanticVisitor,
+    context::{Context, ResolveTypeContext},
+    tag::resolve_tags,
+    visitor::{SemanticVisitable, SemanticVisitor},
 };
 
+/// The size of a word of memory
+const WORD_SIZE: u32 = 32;
+
+/// The most indexed fields of an event, whose values are topics of its logs
+/// after the topic of its signature
+const MAX_INDEXED_FIELDS: usize = 3;
+
 /// List the types which should be resolved later
 #[derive(Default)]
 pub struct ResolveFields {
@@ -43,108 +54,152 @@ pub struct ResolveFields {
     errors: Vec<ResolveErrorFields>,
 }
 
-#[allow(dead_code)]
 struct ResolveEventFields {
     event_no: usize,
     pt: pt::EventDefinition,
+    contract: Option<usize>,
 }
 
-#[allow(dead_code)]
 struct ResolveErrorFields {
     error_no: usize,
     pt: pt::ErrorDefinition,
+    contract: Option<usize>,
 }
 
-#[allow(dead_code)]
 struct ResolveStructFields {
     struct_no: usize,
     pt: pt::StructDefinition,
     contract: Option<usize>,
 }
 
-impl Type {
-    pub fn to_string(&self, ctx: &Context) -> String {
-        match self {
-            Type::Bool => "bool".to_string(),
-            Type::Address(false) => "address".to_string(),
-            Type::Address(true) => "address payable".to_string(),
-            Type::Int(n) => format!("int{n}"),
-            Type::Uint(n) => format!("uint{n}"),
-            Type::Rational => "rational".to_string(),
-            Type::Value => format!("uint{}", ctx.value_length * 8),
-            Type::Bytes(n) => format!("bytes{n}"),
-            Type::String => "string".to_string(),
-            Type::DynamicBytes => "bytes".to_string(),
-            Type::Enum(n) => format!("enum {}", ctx.enums[*n]),
-            Type::Struct(str_ty) => format!("struct {}", str_ty.definition(ctx)),
-            Type::Array(ty, len) => format!(
-                "{}{}",
-                ty.to_string(ctx),
-                len.iter()
-                    .map(|len| match len {
-                        ArrayLength::Fixed(len) => format!("[{len}]"),
-                        _ => "[]".to_string(),
-                    })
-                    .collect::<String>()
-            ),
+/// Display of a type as written in Solidity, see [`Type::display`]
+pub struct TypeDisplay<'a> {
+    ty: &'a Type,
+    ctx: Option<&'a Context>,
+}
+
+impl TypeDisplay<'_> {
+    fn nested<'b>(&'b self, ty: &'b Type) -> TypeDisplay<'b> {
+        TypeDisplay { ty, ctx: self.ctx }
+    }
+
+    fn list(&self, f: &mut fmt::Formatter<'_>, tys: &[Type]) -> fmt::Result {
+        for (i, ty) in tys.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", self.nested(ty))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TypeDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ctx = self.ctx;
+        match self.ty {
+            Type::Bool => f.write_str("bool"),
+            Type::Address(false) => f.write_str("address"),
+            Type::Address(true) => f.write_str("address payable"),
+            Type::Int(n) => write!(f, "int{n}"),
+            Type::Uint(n) => write!(f, "uint{n}"),
+            Type::Rational => f.write_str("rational"),
+            Type::Value => match ctx {
+                Some(ctx) => write!(f, "uint{}", ctx.value_length * 8),
+                None => f.write_str("value"),
+            },
+            Type::Bytes(n) => write!(f, "bytes{n}"),
+            Type::String => f.write_str("string"),
+            Type::DynamicBytes => f.write_str("bytes"),
+            Type::Enum(n) => match ctx {
+                Some(ctx) => write!(f, "enum {}", ctx.enums[*n]),
+                None => write!(f, "enum #{n}"),
+            },
+            Type::Struct(str_ty) => match (ctx, str_ty) {
+                (Some(ctx), _) => write!(f, "struct {}", str_ty.definition(ctx)),
+                (None, StructType::UserDefined(n)) => write!(f, "struct #{n}"),
+            },
+            Type::Array(ty, len) => {
+                write!(f, "{}", self.nested(ty))?;
+                for len in len {
+                    match len {
+                        ArrayLength::Fixed(len) => write!(f, "[{len}]")?,
+                        _ => f.write_str("[]")?,
+                    }
+                }
+                Ok(())
+            }
             Type::Mapping(Mapping { key, key_name, value, value_name }) => {
-                format!(
-                    "mapping({}{}{} => {}{}{})",
-                    key.to_string(ctx),
-                    if key_name.is_some() { " " } else { "" },
-                    key_name.as_ref().map(|id| id.name.as_str()).unwrap_or(""),
-                    value.to_string(ctx),
-                    if value_name.is_some() { " " } else { "" },
-                    value_name.as_ref().map(|id| id.name.as_str()).unwrap_or(""),
-                )
+                write!(f, "mapping({}", self.nested(key))?;
+                if let Some(id) = key_name {
+                    write!(f, " {}", id.name)?;
+                }
+                write!(f, " => {}", self.nested(value))?;
+                if let Some(id) = value_name {
+                    write!(f, " {}", id.name)?;
+                }
+                f.write_str(")")
             }
             Type::ExternalFunction { params, mutability, returns } |
             Type::InternalFunction { params, mutability, returns } => {
-                let mut s = format!(
-                    "function({}) {}",
-                    params.iter().map(|ty| ty.to_string(ctx)).collect::<Vec<String>>().join(","),
-                    if matches!(self, Type::InternalFunction { .. }) {
-                        "internal"
-                    } else {
-                        "external"
-                    }
-                );
+                f.write_str("function(")?;
+                self.list(f, params)?;
+                match self.ty {
+                    Type::InternalFunction { .. } => f.write_str(") internal")?,
+                    _ => f.write_str(") external")?,
+                }
 
                 if !mutability.is_default() {
-                    write!(s, " {mutability}").unwrap();
+                    write!(f, " {mutability}")?;
                 }
 
                 if !returns.is_empty() {
-                    write!(
-                        s,
-                        " returns ({})",
-                        returns
-                            .iter()
-                            .map(|ty| ty.to_string(ctx))
-                            .collect::<Vec<String>>()
-                            .join(",")
-                    )
-                    .unwrap();
+                    f.write_str(" returns (")?;
+                    self.list(f, returns)?;
+                    f.write_str(")")?;
                 }
 
-                s
+                Ok(())
             }
-            Type::Contract(n) => format!("contract {}", ctx.contracts[*n].id),
-            Type::UserType(n) => format!("usertype {}", ctx.user_types[*n]),
-            Type::Ref(r) => r.to_string(ctx),
-            Type::StorageRef(_, ty) => {
-                format!("{} storage", ty.to_string(ctx))
-            }
-            Type::Void => "void".into(),
-            Type::Unreachable => "unreachable".into(),
+            Type::Contract(n) => match ctx {
+                Some(ctx) => write!(f, "contract {}", ctx.contracts[*n].id),
+                None => write!(f, "contract #{n}"),
+            },
+            Type::UserType(n) => match ctx {
+                Some(ctx) => write!(f, "usertype {}", ctx.user_types[*n]),
+                None => write!(f, "usertype #{n}"),
+            },
+            Type::Ref(r) => write!(f, "{}", self.nested(r)),
+            Type::StorageRef(_, ty) => write!(f, "{} storage", self.nested(ty)),
+            Type::Void => f.write_str("void"),
+            Type::Unreachable => f.write_str("unreachable"),
             // A slice of bytes1 is like bytes
-            Type::Slice(ty) if **ty == Type::Bytes(1) => "bytes".into(),
-            Type::Slice(ty) => format!("{}[]", ty.to_string(ctx)),
-            Type::Unresolved => "unresolved".into(),
-            Type::BufferPointer => "buffer_pointer".into(),
-            Type::FunctionSelector => "function_selector".into(),
+            Type::Slice(ty) if **ty == Type::Bytes(1) => f.write_str("bytes"),
+            Type::Slice(ty) => write!(f, "{}[]", self.nested(ty)),
+            Type::Unresolved => f.write_str("unresolved"),
+            Type::BufferPointer => f.write_str("buffer_pointer"),
+            Type::FunctionSelector => f.write_str("function_selector"),
         }
     }
+}
+
+impl Type {
+    pub fn to_string(&self, ctx: &Context) -> String {
+        self.display(ctx).to_string()
+    }
+
+    /// Display the type as written in Solidity, with the names of the
+    /// declarations it refers to
+    pub fn display<'a>(&'a self, ctx: &'a Context) -> TypeDisplay<'a> {
+        TypeDisplay { ty: self, ctx: Some(ctx) }
+    }
+
+    /// Display the type without a context, for the diagnostics of code which
+    /// has none: the declarations it refers to are given by number, as in
+    /// `enum #2`, and the type of values as `value`
+    pub fn display_bare(&self) -> TypeDisplay<'_> {
+        TypeDisplay { ty: self, ctx: None }
+    }
 
     /// Does the type contain any internal function type
     pub fn contains_internal_function(&self, ctx: &Context) -> bool {
@@ -153,10 +208,25 @@ impl Type {
 
     fn contains_internal_function_internal(
         &self,
-        _ctx: &Context,
-        _structs_visited: &mut HashSet<usize>,
+        ctx: &Context,
+        structs_visited: &mut HashSet<usize>,
     ) -> bool {
-        todo!()
+        match self {
+            Type::InternalFunction { .. } => true,
+            Type::Array(ty, _) | Type::Ref(ty) | Type::StorageRef(_, ty) => {
+                ty.contains_internal_function_internal(ctx, structs_visited)
+            }
+            Type::Mapping(mapping) => {
+                mapping.value.contains_internal_function_internal(ctx, structs_visited)
+            }
+            Type::Struct(StructType::UserDefined(struct_no)) => {
+                structs_visited.insert(*struct_no) &&
+                    ctx.structs[*struct_no].fields.iter().any(|field| {
+                        field.ty.contains_internal_function_internal(ctx, structs_visited)
+                    })
+            }
+            _ => false,
+        }
     }
 
     /// Does the type contain any builtin type
@@ -168,14 +238,32 @@ impl Type {
         self.contains_builtins_internal(ctx, builtin, &mut HashSet::new())
     }
 
-    #[allow(unused_variables)]
     fn contains_builtins_internal<'a>(
         &'a self,
         ctx: &'a Context,
         builtin: &StructType,
         structs_visited: &mut HashSet<usize>,
     ) -> Option<&'a Type> {
-        todo!()
+        match self {
+            Type::Array(ty, _) | Type::Ref(ty) | Type::StorageRef(_, ty) => {
+                ty.contains_builtins_internal(ctx, builtin, structs_visited)
+            }
+            Type::Mapping(mapping) => {
+                mapping.key.contains_builtins_internal(ctx, builtin, structs_visited).or_else(
+                    || mapping.value.contains_builtins_internal(ctx, builtin, structs_visited),
+                )
+            }
+            Type::Struct(ty) if ty == builtin => Some(self),
+            Type::Struct(StructType::UserDefined(struct_no)) => {
+                if !structs_visited.insert(*struct_no) {
+                    return None;
+                }
+                ctx.structs[*struct_no].fields.iter().find_map(|field| {
+                    field.ty.contains_builtins_internal(ctx, builtin, structs_visited)
+                })
+            }
+            _ => None,
+        }
     }
 
     /// Does the type contain any mapping type
@@ -183,18 +271,55 @@ impl Type {
         self.contains_mapping_internal(ctx, &mut HashSet::new())
     }
 
-    #[allow(unused_variables)]
     fn contains_mapping_internal(
         &self,
         ctx: &Context,
         structs_visited: &mut HashSet<usize>,
     ) -> bool {
-        todo!()
+        match self {
+            Type::Mapping(_) => true,
+            Type::Array(ty, _) | Type::Ref(ty) | Type::StorageRef(_, ty) => {
+                ty.contains_mapping_internal(ctx, structs_visited)
+            }
+            Type::Struct(StructType::UserDefined(struct_no)) => {
+                structs_visited.insert(*struct_no) &&
+                    ctx.structs[*struct_no]
+                        .fields
+                        .iter()
+                        .any(|field| field.ty.contains_mapping_internal(ctx, structs_visited))
+            }
+            _ => false,
+        }
+    }
+
+    /// Does this type fit into memory, whose offsets are 32 bit. A value takes
+    /// a word, as does a dynamic array, which points to its elements.
+    pub fn fits_in_memory(&self, ctx: &Context) -> bool {
+        self.memory_words(ctx).is_some_and(|words| words * WORD_SIZE <= BigInt::from(u32::MAX))
     }
 
-    /// Does this type fit into memory
-    pub fn fits_in_memory(&self, _ctx: &Context) -> bool {
-        todo!()
+    /// The words the type takes in memory, `None` for a struct of infinite size
+    fn memory_words(&self, ctx: &Context) -> Option<BigInt> {
+        match self {
+            Type::Array(elem, dims) => {
+                let mut words = BigInt::one();
+                for dim in dims.iter().rev() {
+                    match dim {
+                        ArrayLength::Fixed(len) => words *= len,
+                        ArrayLength::Dynamic | ArrayLength::AnyFixed => return Some(words),
+                    }
+                }
+                Some(words * elem.memory_words(ctx)?)
+            }
+            Type::Struct(StructType::UserDefined(struct_no)) => {
+                let fields = &ctx.structs[*struct_no].fields;
+                if fields.iter().any(|field| field.infinite_size) {
+                    return None;
+                }
+                fields.iter().map(|field| field.ty.memory_words(ctx)).sum()
+            }
+            _ => Some(BigInt::one()),
+        }
     }
 
     /// Can this type have a calldata, memory, or storage location. This is to be
@@ -215,6 +340,37 @@ impl Type {
     pub fn is_contract_storage(&self) -> bool {
         matches!(self, Type::StorageRef(..))
     }
+
+    /// The integer or `bytesN` type of the name, e.g. `uint` or `bytes32`,
+    /// which the lexer leaves as identifiers
+    pub fn elementary(name: &str) -> Option<Type> {
+        let bits = |size: &str| match size {
+            "" => Some(256),
+            size => size.parse().ok().filter(|bits| bits % 8 == 0 && (8..=256).contains(bits)),
+        };
+
+        if let Some(size) = name.strip_prefix("uint") {
+            bits(size).map(Type::Uint)
+        } else if let Some(size) = name.strip_prefix("int") {
+            bits(size).map(Type::Int)
+        } else {
+            let length = name.strip_prefix("bytes")?.parse().ok()?;
+            (1..=32).contains(&length).then_some(Type::Bytes(length))
+        }
+    }
+
+    /// The type of the elements of an array, which is an array itself for the
+    /// outer dimension of a multi-dimensional array, e.g. `uint[2]` of `uint[2][3]`
+    pub fn array_elem(&self) -> Type {
+        match self {
+            Type::Array(elem, dims) if dims.len() > 1 => {
+                Type::Array(elem.clone(), dims[..dims.len() - 1].to_vec())
+            }
+            Type::Array(elem, _) => *elem.clone(),
+            Type::DynamicBytes | Type::Bytes(_) => Type::Bytes(1),
+            _ => panic!("{self:?} is not an array"),
+        }
+    }
 }
 
 /// Resolve all the types we can find (enums, structs, contracts).
@@ -225,32 +381,48 @@ pub struct TypeResolver<'a> {
     ctx: &'a mut Context,
     no: usize,
     delay: ResolveFields,
-    part: Option<SourceUnitPart>,
+    /// The annotations of the part being visited, which types do not take
+    annotations: Vec<pt::Annotation>,
+    /// The contract whose parts are being visited
+    contract_no: Option<usize>,
 }
 
 impl<'a> TypeResolver<'a> {
     /// Creates a new type resolver with the given context
     pub fn new(ctx: &'a mut Context, no: usize) -> Self {
-        Self { ctx, no, delay: ResolveFields::default(), part: None }
+        Self {
+            ctx,
+            no,
+            delay: ResolveFields::default(),
+            annotations: Vec::new(),
+            contract_no: None,
+        }
+    }
+
+    /// The name of the contract being visited, which the declarations keep
+    fn contract_name(&self) -> Option<String> {
+        self.contract_no.map(|no| self.ctx.contracts[no].id.name.clone())
     }
 
     /// Parse enum declaration. If the declaration is invalid, it is still generated
     /// so that we can continue parsing, with errors recorded.
     fn enum_decl(&mut self, def: &pt::EnumDefinition, contract_no: Option<usize>) -> bool {
+        let Some(id) = self.ctx.definition_name(&def.name, def.loc, "enum") else {
+            return false;
+        };
         let mut valid = true;
 
         if def.values.is_empty() {
-            self.ctx.diagnostics.push(Diagnostic::error(
-                def.name.as_ref().unwrap().loc,
-                format!("enum '{}' has no fields", def.name.as_ref().unwrap().name),
-            ));
+            self.ctx
+                .diagnostics
+                .push(Diagnostic::error(id.loc, format!("enum '{}' has no fields", id.name)));
             valid = false;
         } else if def.values.len() > 256 {
             self.ctx.diagnostics.push(Diagnostic::error(
-                def.name.as_ref().unwrap().loc,
+                id.loc,
                 format!(
                     "enum '{}' has {} fields, which is more than the 256 limit",
-                    def.name.as_ref().unwrap().name,
+                    id.name,
                     def.values.len()
                 ),
             ));
@@ -261,10 +433,15 @@ impl<'a> TypeResolver<'a> {
         let mut entries: IndexMap<String, pt::Loc> = IndexMap::new();
 
         for e in def.values.iter() {
-            if let Some(prev) = entries.get(&e.as_ref().unwrap().name.to_string()) {
+            let Some(e) = self.ctx.definition_name(e, def.loc, "enum value") else {
+                valid = false;
+                continue;
+            };
+
+            if let Some(prev) = entries.get(&e.name) {
                 self.ctx.diagnostics.push(
-                    Diagnostic::builder(e.as_ref().unwrap().loc, Level::Error)
-                        .message(format!("duplicate enum value {}", e.as_ref().unwrap().name))
+                    Diagnostic::builder(e.loc, Level::Error)
+                        .message(format!("duplicate enum value {}", e.name))
                         .note(*prev, "location of previous definition")
                         .build(),
                 );
@@ -272,11 +449,11 @@ impl<'a> TypeResolver<'a> {
                 continue;
             }
 
-            entries.insert(e.as_ref().unwrap().name.to_string(), e.as_ref().unwrap().loc);
+            entries.insert(e.name, e.loc);
         }
 
         let decl = EnumDecl {
-            id: def.name.clone().unwrap(),
+            id: id.clone(),
             loc: def.loc,
             contract: match contract_no {
                 Some(c) => Some(self.ctx.contracts[c].id.name.to_owned()),
@@ -290,17 +467,234 @@ impl<'a> TypeResolver<'a> {
 
         self.ctx.enums.push(decl);
 
-        if !self.ctx.add_symbol(
-            self.no,
-            contract_no,
-            def.name.as_ref().unwrap(),
-            Symbol::Enum(def.name.as_ref().unwrap().loc, pos),
-        ) {
+        if !self.ctx.add_symbol(self.no, contract_no, &id, Symbol::Enum(id.loc, pos)) {
             valid = false;
         }
 
         valid
     }
+
+    /// Resolve the fields of the structs, events and errors of the file, once
+    /// the names of all its types are known
+    fn resolve_fields(&mut self) {
+        let delay = mem::take(&mut self.delay);
+
+        for def in &delay.structs {
+            let fields = self.fields(
+                def.contract,
+                def.pt.fields.iter().map(|field| (field.loc, &field.ty, &field.name, false)),
+            );
+            for field in &def.pt.fields {
+                if let Some(storage) = &field.storage {
+                    self.ctx.diagnostics.push(Diagnostic::error(
+                        storage.loc(),
+                        format!("storage location '{storage}' not allowed for struct field"),
+                    ));
+                }
+            }
+            if def.pt.fields.is_empty() {
+                let name = &self.ctx.structs[def.struct_no].id.name;
+                self.ctx.diagnostics.push(Diagnostic::error(
+                    def.pt.loc,
+                    format!("struct definition for '{name}' has no fields"),
+                ));
+            }
+            self.ctx.structs[def.struct_no].fields = fields;
+        }
+
+        // A struct may refer to those declared after it, so the recursive ones
+        // are only known once all of them are resolved
+        for def in &delay.structs {
+            check_recursive(def.struct_no, self.ctx);
+        }
+
+        for def in &delay.events {
+            let fields = self.fields(
+                def.contract,
+                def.pt
+                    .fields
+                    .iter()
+                    .map(|field| (field.loc, &field.ty, &field.name, field.indexed)),
+            );
+            self.check_event_fields(def.event_no, &fields);
+
+            let event = &self.ctx.events[def.event_no];
+            let signature = ethereum::signature(&event.id.name, &fields, self.ctx);
+            let event = &mut self.ctx.events[def.event_no];
+            event.signature = signature;
+            event.fields = fields;
+        }
+
+        for def in &delay.errors {
+            let fields = self.fields(
+                def.contract,
+                def.pt.fields.iter().map(|field| (field.loc, &field.ty, &field.name, false)),
+            );
+            for field in &fields {
+                if matches!(field.ty, Type::Mapping(_)) {
+                    self.ctx.diagnostics.push(Diagnostic::error(
+                        field.loc,
+                        "mapping type is not permitted as error field",
+                    ));
+                }
+            }
+            self.ctx.errors[def.error_no].fields = fields;
+        }
+    }
+
+    /// Resolve the types of fields, given by their location, type, name and
+    /// whether they are indexed. The fields whose type does not resolve are
+    /// left out, as are those with a duplicate name.
+    fn fields<'f>(
+        &mut self,
+        contract_no: Option<usize>,
+        fields: impl Iterator<Item = (pt::Loc, &'f pt::Expression, &'f Option<pt::Identifier>, bool)>,
+    ) -> Vec<Parameter<Type>> {
+        let mut resolved: Vec<Parameter<Type>> = Vec::new();
+
+        for (loc, ty, name, indexed) in fields {
+            let mut diagnostics = Diagnostics::default();
+            let Ok(resolved_ty) = self.ctx.resolve_type(
+                self.no,
+                contract_no,
+                ResolveTypeContext::None,
+                ty,
+                &mut diagnostics,
+            ) else {
+                self.ctx.diagnostics.extend(diagnostics);
+                continue;
+            };
+
+            if let Some(id) = name {
+                let previous = resolved
+                    .iter()
+                    .find_map(|field| field.id.as_ref().filter(|prev| prev.name == id.name));
+                if let Some(prev) = previous {
+                    self.ctx.diagnostics.push(
+                        Diagnostic::builder(id.loc, Level::Error)
+                            .message(format!("duplicate field '{}'", id.name))
+                            .note(prev.loc, "location of previous definition")
+                            .build(),
+                    );
+                    continue;
+                }
+            }
+
+            resolved.push(Parameter {
+                loc,
+                id: name.clone(),
+                ty: resolved_ty,
+                ty_id: None,
+                ty_loc: Some(ty.loc()),
+                indexed,
+                readonly: false,
+                infinite_size: false,
+                recursive: false,
+                annotation: None,
+            });
+        }
+
+        resolved
+    }
+
+    /// An event has at most three indexed fields, or four when anonymous as
+    /// the first topic is then free, and no field holding a mapping
+    fn check_event_fields(&mut self, event_no: usize, fields: &[Parameter<Type>]) {
+        let event = &self.ctx.events[event_no];
+        let allowed = MAX_INDEXED_FIELDS + usize::from(event.anonymous);
+        let indexed = fields.iter().filter(|field| field.indexed).count();
+
+        if indexed > allowed {
+            let message = format!(
+                "{}event '{}' has {indexed} indexed fields where only {allowed} are allowed",
+                if event.anonymous { "anonymous " } else { "" },
+                event.id.name,
+            );
+            let diagnostic = Diagnostic::builder(event.loc, Level::Error)
+                .message(message)
+                .notes(
+                    fields
+                        .iter()
+                        .filter(|field| field.indexed)
+                        .map(|field| Note { loc: field.loc, message: "indexed field".into() })
+                        .collect(),
+                )
+                .build();
+            self.ctx.diagnostics.push(diagnostic);
+        }
+
+        for field in fields {
+            if field.ty.contains_mapping(self.ctx) {
+                self.ctx.diagnostics.push(Diagnostic::error(
+                    field.loc,
+                    "mapping type is not permitted as event field",
+                ));
+            }
+        }
+    }
+}
+
+/// Mark the fields of a struct which contain the struct itself as recursive,
+/// and as of infinite size when nothing but structs and fixed length arrays
+/// lead back to it, which is an error: `struct S { S s; }` has no size, while
+/// `struct S { S[] s; }` and `struct S { mapping(uint => S) s; }` do.
+fn check_recursive(struct_no: usize, ctx: &mut Context) {
+    let mut infinite = Vec::new();
+
+    for i in 0..ctx.structs[struct_no].fields.len() {
+        let ty = &ctx.structs[struct_no].fields[i].ty;
+        let recursive = contains_struct(ty, struct_no, false, ctx, &mut HashSet::new());
+        let infinite_size =
+            recursive && contains_struct(ty, struct_no, true, ctx, &mut HashSet::new());
+
+        let field = &mut ctx.structs[struct_no].fields[i];
+        field.recursive = recursive;
+        field.infinite_size = infinite_size;
+        if infinite_size {
+            infinite.push(Note { loc: field.loc, message: "recursive field".into() });
+        }
+    }
+
+    if !infinite.is_empty() {
+        let def = &ctx.structs[struct_no];
+        ctx.diagnostics.push(
+            Diagnostic::builder(def.loc, Level::Error)
+                .message(format!("struct '{}' has infinite size", def.id.name))
+                .notes(infinite)
+                .build(),
+        );
+    }
+}
+
+/// Whether the type contains the struct, only through structs and fixed length
+/// arrays when `sized`
+fn contains_struct(
+    ty: &Type,
+    struct_no: usize,
+    sized: bool,
+    ctx: &Context,
+    visited: &mut HashSet<usize>,
+) -> bool {
+    match ty {
+        Type::Struct(StructType::UserDefined(no)) if *no == struct_no => true,
+        Type::Struct(StructType::UserDefined(no)) => {
+            visited.insert(*no) &&
+                ctx.structs[*no]
+                    .fields
+                    .iter()
+                    .any(|field| contains_struct(&field.ty, struct_no, sized, ctx, visited))
+        }
+        Type::Array(_, dims)
+            if sized && dims.iter().any(|dim| !matches!(dim, ArrayLength::Fixed(_))) =>
+        {
+            false
+        }
+        Type::Array(elem, _) => contains_struct(elem, struct_no, sized, ctx, visited),
+        Type::Mapping(mapping) => {
+            !sized && contains_struct(&mapping.value, struct_no, sized, ctx, visited)
+        }
+        _ => false,
+    }
 }
 
 /// Internal error type for type resolution logic
@@ -308,20 +702,58 @@ impl<'a> TypeResolver<'a> {
 pub enum TypeResolverError {}
 
 impl<'a> SemanticVisitor for TypeResolver<'a> {
+    fn visit_sema_source_unit(&mut self, source_unit: &mut SourceUnit) -> Result<(), Self::Error> {
+        source_unit.parts.visit(self)?;
+        source_unit.contracts.visit(self)?;
+        self.resolve_fields();
+
+        Ok(())
+    }
+
     fn visit_sema_source_unit_part(
         &mut self,
         part: &mut SourceUnitPart,
     ) -> Result<(), Self::Error> {
-        self.part = Some(part.clone());
+        self.annotations = part.annotations.clone();
         part.part.visit(self)?;
 
         Ok(())
     }
 
+    /// Declare the contract, then the types declared in it
     fn visit_sema_contract(
         &mut self,
-        _contract: &mut ContractDefinition,
+        contract: &mut ContractDefinition,
     ) -> Result<(), Self::Error> {
+        debug_assert_eq!(contract.contract_no, self.ctx.contracts.len());
+
+        // A contract without a name is still declared, to keep the numbers
+        // of the contracts after it
+        let id = self.ctx.definition_name(&contract.name, contract.loc, "contract");
+        let declared = id.as_ref().is_some_and(|id| {
+            self.ctx.add_symbol(self.no, None, id, Symbol::Contract(id.loc, contract.contract_no))
+        });
+        let id = id.unwrap_or(pt::Identifier { loc: contract.loc, name: String::new() });
+        let tags = resolve_tags(self.no, "contract", None, None, None, self.ctx);
+        self.ctx.contracts.push(Contract::new(id, contract.ty.clone(), tags, contract.loc));
+        if !declared {
+            return Ok(());
+        }
+
+        self.contract_no = Some(contract.contract_no);
+        for part in &mut contract.parts {
+            self.annotations = part.annotations.clone();
+            match &mut part.part {
+                pt::ContractPart::StructDefinition(def) => self.visit_struct(def)?,
+                pt::ContractPart::EventDefinition(def) => self.visit_event(def)?,
+                pt::ContractPart::ErrorDefinition(def) => self.visit_error(def)?,
+                pt::ContractPart::EnumDefinition(def) => self.visit_enum(def)?,
+                pt::ContractPart::TypeDefinition(def) => self.visit_type_definition(def)?,
+                _ => (),
+            }
+        }
+        self.contract_no = None;
+
         Ok(())
     }
 }
@@ -330,28 +762,31 @@ impl<'a> Visitor for TypeResolver<'a> {
     type Error = TypeResolverError;
 
     fn visit_enum(&mut self, def: &mut pt::EnumDefinition) -> Result<(), Self::Error> {
-        self.ctx.reject(&self.part.as_ref().unwrap().annotations, "enum");
-        let _ = self.enum_decl(def, None);
+        self.ctx.reject(&self.annotations, "enum");
+        let _ = self.enum_decl(def, self.contract_no);
 
         Ok(())
     }
 
     fn visit_struct(&mut self, def: &mut pt::StructDefinition) -> Result<(), Self::Error> {
-        self.ctx.reject(&self.part.as_ref().unwrap().annotations, "struct");
+        self.ctx.reject(&self.annotations, "struct");
+        let Some(id) = self.ctx.definition_name(&def.name, def.loc, "struct") else {
+            return Ok(());
+        };
 
         let struct_no = self.ctx.structs.len();
 
         if self.ctx.add_symbol(
             self.no,
-            None,
-            def.name.as_ref().unwrap(),
-            Symbol::Struct(def.name.as_ref().unwrap().loc, StructType::UserDefined(struct_no)),
+            self.contract_no,
+            &id,
+            Symbol::Struct(id.loc, StructType::UserDefined(struct_no)),
         ) {
             self.ctx.structs.push(StructDecl {
                 tags: Vec::new(),
-                id: def.name.clone().unwrap(),
-                loc: def.name.as_ref().unwrap().loc,
-                contract: None,
+                loc: id.loc,
+                id,
+                contract: self.contract_name(),
                 fields: Vec::new(),
                 offsets: Vec::new(),
                 storage_offsets: Vec::new(),
@@ -360,7 +795,7 @@ impl<'a> Visitor for TypeResolver<'a> {
             self.delay.structs.push(ResolveStructFields {
                 struct_no,
                 pt: def.clone(),
-                contract: None,
+                contract: self.contract_no,
             });
         }
 
@@ -368,37 +803,38 @@ impl<'a> Visitor for TypeResolver<'a> {
     }
 
     fn visit_event(&mut self, def: &mut pt::EventDefinition) -> Result<(), Self::Error> {
-        self.ctx.reject(&self.part.as_ref().unwrap().annotations, "event");
+        self.ctx.reject(&self.annotations, "event");
+        let Some(id) = self.ctx.definition_name(&def.name, def.loc, "event") else {
+            return Ok(());
+        };
 
         let event_no = self.ctx.events.len();
+        let symbol = Symbol::Event(vec![(id.loc, event_no)]);
 
-        if let Some(Symbol::Event(events)) = self.ctx.variable_symbols.get_mut(&(
-            self.no,
-            None,
-            def.name.as_ref().unwrap().name.to_owned(),
-        )) {
-            events.push((def.name.as_ref().unwrap().loc, event_no));
-        } else if !self.ctx.add_symbol(
-            self.no,
-            None,
-            def.name.as_ref().unwrap(),
-            Symbol::Event(vec![(def.name.as_ref().unwrap().loc, event_no)]),
-        ) {
+        if let Some(Symbol::Event(events)) =
+            self.ctx.variable_symbols.get_mut(&(self.no, self.contract_no, id.name.to_owned()))
+        {
+            events.push((id.loc, event_no));
+        } else if !self.ctx.add_symbol(self.no, self.contract_no, &id, symbol) {
             return Ok(());
         }
 
         self.ctx.events.push(EventDecl {
             tags: Vec::new(),
-            id: def.name.as_ref().unwrap().to_owned(),
+            id,
             loc: def.loc,
-            contract: None,
+            contract: self.contract_no,
             fields: Vec::new(),
             anonymous: def.anonymous,
             signature: String::new(),
             used: false,
         });
 
-        self.delay.events.push(ResolveEventFields { event_no, pt: def.clone() });
+        self.delay.events.push(ResolveEventFields {
+            event_no,
+            pt: def.clone(),
+            contract: self.contract_no,
+        });
 
         Ok(())
     }
@@ -423,46 +859,173 @@ impl<'a> Visitor for TypeResolver<'a> {
             }
         }
 
-        self.ctx.reject(&self.part.as_ref().unwrap().annotations, "error");
+        self.ctx.reject(&self.annotations, "error");
+        let Some(id) = self.ctx.definition_name(&def.name, def.loc, "error") else {
+            return Ok(());
+        };
 
         let error_no = self.ctx.errors.len();
 
-        if !self.ctx.add_symbol(
-            self.no,
-            None,
-            def.name.as_ref().unwrap(),
-            Symbol::Error(def.name.as_ref().unwrap().loc, error_no),
-        ) {
+        if !self.ctx.add_symbol(self.no, self.contract_no, &id, Symbol::Error(id.loc, error_no)) {
             return Ok(());
         }
 
         self.ctx.errors.push(ErrorDecl {
             tags: Vec::new(),
-            name: def.name.as_ref().unwrap().name.to_owned(),
-            loc: def.name.as_ref().unwrap().loc,
-            contract: None,
+            name: id.name,
+            loc: id.loc,
+            contract: self.contract_no,
             fields: Vec::new(),
             used: false,
         });
 
-        self.delay.errors.push(ResolveErrorFields { error_no, pt: def.clone() });
+        self.delay.errors.push(ResolveErrorFields {
+            error_no,
+            pt: def.clone(),
+            contract: self.contract_no,
+        });
 
         Ok(())
     }
 
     fn visit_type_definition(&mut self, ty: &mut pt::TypeDefinition) -> Result<(), Self::Error> {
-        self.ctx.reject(&self.part.as_ref().unwrap().annotations, "type");
-        type_decl(ty, self.no, None, self.ctx);
+        self.ctx.reject(&self.annotations, "type");
+        type_decl(ty, self.no, self.contract_no, self.ctx);
 
         Ok(())
     }
 }
 
-fn type_decl(
-    _def: &pt::TypeDefinition,
-    _no: usize,
-    _contract_no: Option<usize>,
-    _ctx: &mut Context,
-) {
-    todo!()
+/// Declare a user defined value type, `type Price is uint128;`, which can only
+/// wrap an elementary value type
+fn type_decl(def: &pt::TypeDefinition, no: usize, contract_no: Option<usize>, ctx: &mut Context) {
+    let mut diagnostics = Diagnostics::default();
+
+    let mut ty = match ctx.resolve_type(
+        no,
+        contract_no,
+        ResolveTypeContext::None,
+        &def.ty,
+        &mut diagnostics,
+    ) {
+        Ok(ty) => ty,
+        Err(()) => {
+            ctx.diagnostics.extend(diagnostics);
+            return;
+        }
+    };
+
+    // Other types would have to be resolved after all the others, and checked
+    // for cycles like `type A is B; type B is A;`
+    if !matches!(
+        ty,
+        Type::Address(_) | Type::Bool | Type::Int(_) | Type::Uint(_) | Type::Bytes(_) | Type::Value
+    ) {
+        ctx.diagnostics.push(Diagnostic::error(
+            def.ty.loc(),
+            format!("'{}' is not an elementary value type", ty.to_string(ctx)),
+        ));
+        ty = Type::Unresolved;
+    }
+
+    let type_no = ctx.user_types.len();
+    if !ctx.add_symbol(no, contract_no, &def.name, Symbol::UserType(def.name.loc, type_no)) {
+        return;
+    }
+
+    ctx.user_types.push(UserTypeDecl {
+        tags: Vec::new(),
+        loc: def.loc,
+        name: def.name.name.clone(),
+        ty,
+        contract: contract_no.map(|no| ctx.contracts[no].id.name.clone()),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::parse, semantic::collector::AnnotationCollector};
+
+    fn resolve(src: &str) -> Context {
+        let mut ctx = Context::new();
+        let mut ast = parse(src, 0).unwrap();
+        let mut collector = AnnotationCollector::new(&mut ctx);
+        ast.visit(&mut collector).unwrap();
+        let mut tree = collector.collect();
+        tree.visit(&mut TypeResolver::new(&mut ctx, 0)).unwrap();
+        ctx
+    }
+
+    #[test]
+    fn test_unnamed_definitions() {
+        let mut ctx = Context::new();
+        let loc = pt::Loc::File(0, 0, 10);
+        let id = |name: &str| Some(pt::Identifier { loc, name: name.into() });
+
+        // A definition without a name is skipped, rather than panicking
+        let def = pt::EnumDefinition { loc, name: None, values: vec![id("A")] };
+        assert!(!TypeResolver::new(&mut ctx, 0).enum_decl(&def, None));
+        assert!(ctx.enums.is_empty());
+        assert_eq!(ctx.diagnostics.first_error(), "internal error: enum without a name");
+
+        let mut ctx = Context::new();
+        let def = pt::EnumDefinition { loc, name: id("E"), values: vec![id("A"), None, id("B")] };
+        assert!(!TypeResolver::new(&mut ctx, 0).enum_decl(&def, None));
+        assert_eq!(ctx.enums[0].values.keys().collect::<Vec<_>>(), ["A", "B"]);
+        assert_eq!(ctx.diagnostics.first_error(), "internal error: enum value without a name");
+    }
+
+    #[test]
+    fn test_resolve_fields() {
+        // A struct may use one declared after it
+        let ctx = resolve(
+            "struct A { B b; A[] list; mapping(uint => A) map; }
+            struct B { uint8 x; bytes32[2] y; }
+            event Transfer(address indexed from, address indexed to, uint256 value);
+            error Insufficient(uint256 available, B required);",
+        );
+        assert!(!ctx.diagnostics.any_errors(), "{:?}", ctx.diagnostics.errors());
+
+        let a = &ctx.structs[0];
+        assert_eq!(a.fields[0].ty, Type::Struct(StructType::UserDefined(1)));
+        assert_eq!(
+            a.fields.iter().map(|f| (f.recursive, f.infinite_size)).collect::<Vec<_>>(),
+            [(false, false), (true, false), (true, false)]
+        );
+        assert_eq!(ctx.structs[1].fields[1].ty.to_string(&ctx), "bytes32[2]");
+
+        let event = &ctx.events[0];
+        assert_eq!(event.signature, "Transfer(address,address,uint256)");
+        assert_eq!(event.fields.iter().filter(|f| f.indexed).count(), 2);
+        assert_eq!(ctx.errors[0].fields[1].ty, Type::Struct(StructType::UserDefined(1)));
+
+        // Recursion through structs and fixed length arrays has no end
+        let ctx = resolve("struct S { T[2] t; } struct T { S s; uint x; }");
+        let errors = ctx.diagnostics.errors();
+        assert_eq!(errors[0].message, "struct 'S' has infinite size");
+        assert_eq!(errors[1].message, "struct 'T' has infinite size");
+        assert!(ctx.structs[1].fields[0].infinite_size);
+        assert!(!ctx.structs[1].fields[1].recursive);
+
+        let ctx = resolve(
+            "event E(uint indexed a, uint indexed b, uint indexed c, uint indexed d);
+            event F(uint indexed a, uint indexed b, uint indexed c, uint indexed d) anonymous;
+            event G(uint a, uint a);
+            struct S { uint memory x; }
+            error X(mapping(uint => bool) m);",
+        );
+        let errors = ctx.diagnostics.errors();
+        assert_eq!(
+            errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(),
+            [
+                "storage location 'memory' not allowed for struct field",
+                "event 'E' has 4 indexed fields where only 3 are allowed",
+                "duplicate field 'a'",
+                "mapping type is not permitted as error field",
+            ]
+        );
+        assert_eq!(errors[1].notes.len(), 4);
+        assert_eq!(ctx.events[2].fields.len(), 1);
+    }
 }
@@ -17,16 +17,15 @@ use thiserror::Error;
 use super::{context::Context, visitor::SemanticVisitor};
 
 use crate::{
-    diagnostics::{Diagnostic, Diagnostics, Level, Note},
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics, Level, Note},
     helpers::CodeLocation,
     parser::{
         ast::{self as pt},
-        visitor::Visitor,
+        visitor::{Visitable, Visitor},
     },
     semantic::{
-        ast::{Expression, Mutability, Type, Using, UsingFunction, UsingList},
+        ast::{ContractDefinition, Expression, Mutability, Type, Using, UsingFunction, UsingList},
         context::ResolveTypeContext,
-        visitor::SemanticVisitable,
     },
 };
 
@@ -48,7 +47,7 @@ impl<'a> UsingResolver<'a> {
     fn resolve_library(
         &mut self,
         library: &pt::IdentifierPath,
-        diagnostics: &mut Diagnostics,
+        diagnostics: &mut dyn DiagnosticSink,
     ) -> Result<UsingList, UsingResolverError> {
         if let Ok(library_no) =
             self.ctx.resolve_contract_with_namespace(self.no, library, diagnostics)
@@ -66,7 +65,6 @@ impl<'a> UsingResolver<'a> {
                 Err(UsingResolverError::Noops)
             }
         } else {
-            self.ctx.diagnostics.extend(diagnostics.clone());
             Err(UsingResolverError::Noops)
         }
     }
@@ -76,9 +74,10 @@ impl<'a> UsingResolver<'a> {
         using: &pt::Using,
         functions: &Vec<pt::UsingFunction>,
         ty: &Option<Type>,
-        diagnostics: &mut Diagnostics,
+        diagnostics: &mut dyn DiagnosticSink,
     ) -> Result<UsingList, UsingResolverError> {
         let mut res = Vec::new();
+        let ty_id = ty.as_ref().map(|ty| self.ctx.type_arena.intern(ty));
 
         for using_function in functions {
             let function_name = &using_function.path;
@@ -175,7 +174,7 @@ impl<'a> UsingResolver<'a> {
                     };
 
                     if func.params.len() != oper.args() ||
-                        func.params.iter().any(|param| param.ty != *ty)
+                        func.params.iter().any(|param| param.ty_id != ty_id)
                     {
                         diagnostics.push(
                             Diagnostic::builder( using_function.loc, Level::Error)
@@ -203,7 +202,7 @@ impl<'a> UsingResolver<'a> {
 
                             continue;
                         }
-                    } else if func.returns.len() != 1 || func.returns[0].ty != *ty {
+                    } else if func.returns.len() != 1 || func.returns[0].ty_id != ty_id {
                         diagnostics.push(
                             Diagnostic::builder( using_function.loc, Level::Error)
                                 .message(format!(
@@ -356,7 +355,7 @@ impl<'a> SemanticVisitor for UsingResolver<'a> {
     ) -> Result<(), Self::Error> {
         if let pt::SourceUnitPart::Using(_) = part.part {
             self.ctx.reject(&part.annotations, "using");
-            part.visit(self)?;
+            part.part.visit(self)?;
 
             if let Some(using) = self.finish() {
                 self.ctx.using.push(using);
@@ -364,6 +363,12 @@ impl<'a> SemanticVisitor for UsingResolver<'a> {
         }
         Ok(())
     }
+
+    /// The directives of contracts are resolved with them, see
+    /// [`ContractResolver`](super::contract::ContractResolver)
+    fn visit_sema_contract(&mut self, _: &mut ContractDefinition) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 impl<'a> Visitor for UsingResolver<'a> {
@@ -411,15 +416,30 @@ impl<'a> Visitor for UsingResolver<'a> {
                 ));
                 return Ok(());
             }
+            if let pt::UsingList::Functions(_) = &using.list {
+                self.ctx.diagnostics.push(Diagnostic::error(
+                    using.loc,
+                    "the type has to be specified explicitly when attaching specific functions",
+                ));
+                return Ok(());
+            }
             None
         };
 
         let list = match &using.list {
-            pt::UsingList::Library(library) => self.resolve_library(library, &mut diagnostics)?,
+            pt::UsingList::Library(library) => self.resolve_library(library, &mut diagnostics),
             pt::UsingList::Functions(functions) => {
-                self.resove_functions(using, functions, &ty, &mut diagnostics)?
+                self.resove_functions(using, functions, &ty, &mut diagnostics)
+            }
+            // The parser reported the syntax error
+            pt::UsingList::Error => return Ok(()),
+        };
+        let list = match list {
+            Ok(list) => list,
+            Err(e) => {
+                self.ctx.diagnostics.extend(diagnostics);
+                return Err(e);
             }
-            pt::UsingList::Error => unimplemented!(),
         };
 
         let mut file_no = Some(self.no);
@@ -452,3 +472,151 @@ pub(crate) fn user_defined_operator_binding<'a>(
         }
     })
 }
+
+/// The functions named `name` the using directives in scope bind to values of
+/// type `ty`, for resolving the member call `value.name(...)`.
+///
+/// The directives of the contract, then those of the file and the global ones,
+/// are searched. `using A for *` binds each function of the library `A` whose
+/// first parameter has the type of the value, references being compared by the
+/// type they reference.
+pub fn bound_functions(
+    ctx: &Context,
+    file_no: usize,
+    contract_no: Option<usize>,
+    ty: &Type,
+    name: &str,
+) -> Vec<usize> {
+    let ty = ty.deref_any();
+    let contract = contract_no.map(|contract_no| ctx.contracts[contract_no].using.iter());
+    let file = ctx.using.iter().filter(|using| using.file_no.is_none_or(|no| no == file_no));
+
+    let mut functions = Vec::new();
+
+    for using in contract.into_iter().flatten().chain(file) {
+        if using.ty.as_ref().is_some_and(|using_ty| using_ty.deref_any() != ty) {
+            continue;
+        }
+
+        let candidates = match &using.list {
+            UsingList::Library(library_no) => ctx.contracts[*library_no].functions.clone(),
+            UsingList::Functions(list) => list
+                .iter()
+                .filter(|using| using.oper.is_none())
+                .map(|using| using.function_no)
+                .collect(),
+        };
+
+        for function_no in candidates {
+            let func = &ctx.functions[function_no];
+            let bound = func.id.name == name &&
+                func.params.first().is_some_and(|param| param.ty.deref_any() == ty);
+            if bound && !functions.contains(&function_no) {
+                functions.push(function_no);
+            }
+        }
+    }
+
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::{
+        ast::{Function, Parameter},
+        layout::tests::contract,
+    };
+
+    fn function(ctx: &mut Context, contract_no: usize, name: &str, params: Vec<Type>) -> usize {
+        let func = Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            pt::Identifier { loc: pt::Loc::Builtin, name: name.into() },
+            Some(contract_no),
+            Vec::new(),
+            pt::FunctionTy::Function,
+            None,
+            pt::Visibility::Internal(None),
+            params.into_iter().map(Parameter::new_default).collect(),
+            Vec::new(),
+            ctx,
+        );
+        ctx.functions.push(func);
+        ctx.contracts[contract_no].functions.push(ctx.functions.len() - 1);
+        ctx.functions.len() - 1
+    }
+
+    #[test]
+    fn test_bound_functions() {
+        let mut ctx = Context::new();
+        let mut math = contract("Math", &[], Vec::new());
+        math.ty = pt::ContractTy::Library(pt::Loc::Builtin);
+        ctx.contracts.push(math);
+        let max_uint = function(&mut ctx, 0, "max", vec![Type::Uint(256), Type::Uint(256)]);
+        let max_int = function(&mut ctx, 0, "max", vec![Type::Int(256), Type::Int(256)]);
+        let len = function(&mut ctx, 0, "len", vec![Type::Ref(Box::new(Type::String))]);
+        function(&mut ctx, 0, "zero", Vec::new());
+
+        ctx.contracts.push(contract("Token", &[], Vec::new()));
+        ctx.contracts.push(contract("Vault", &[], Vec::new()));
+        // `using Math for *;` in Token
+        ctx.contracts[1].using.push(Using {
+            list: UsingList::Library(0),
+            ty: None,
+            file_no: Some(0),
+        });
+
+        assert_eq!(bound_functions(&ctx, 0, Some(1), &Type::Uint(256), "max"), [max_uint]);
+        assert_eq!(bound_functions(&ctx, 0, Some(1), &Type::Int(256), "max"), [max_int]);
+        assert_eq!(bound_functions(&ctx, 0, Some(1), &Type::String, "len"), [len]);
+        assert!(bound_functions(&ctx, 0, Some(1), &Type::Bool, "max").is_empty());
+        assert!(bound_functions(&ctx, 0, Some(1), &Type::Uint(256), "zero").is_empty());
+
+        // Not in another contract of the file
+        assert!(bound_functions(&ctx, 0, Some(2), &Type::Uint(256), "max").is_empty());
+
+        // A directive for a type only binds that type, once
+        ctx.contracts[1].using.push(Using {
+            list: UsingList::Library(0),
+            ty: Some(Type::Uint(256)),
+            file_no: Some(0),
+        });
+        ctx.using.push(Using {
+            list: UsingList::Library(0),
+            ty: Some(Type::Int(256)),
+            file_no: Some(0),
+        });
+        assert_eq!(bound_functions(&ctx, 0, Some(1), &Type::Uint(256), "max"), [max_uint]);
+        assert_eq!(bound_functions(&ctx, 0, Some(2), &Type::Int(256), "max"), [max_int]);
+        assert!(bound_functions(&ctx, 1, Some(2), &Type::Int(256), "max").is_empty());
+    }
+
+    #[test]
+    fn test_user_defined_operators() {
+        let errors = |src: &str| {
+            let contents = format!("type Price is uint128; {src}");
+            let input = crate::Input::Source { name: "test.sol".into(), contents };
+            let diagnostics = crate::check(&input, &crate::config::Config::default());
+            diagnostics.errors().into_iter().map(|error| error.message.clone()).collect::<Vec<_>>()
+        };
+
+        let src = "using {add as +, eq as ==} for Price global;
+            function add(Price a, Price b) pure returns (Price) { return a; }
+            function eq(Price a, Price b) pure returns (bool) { return true; }";
+        assert!(errors(src).is_empty());
+
+        let src = "using {add as +} for Price global;
+            function add(Price a, uint128 b) pure returns (Price) { return a; }";
+        assert_eq!(
+            errors(src),
+            ["user defined operator function for '+' must have 2 arguments of type usertype Price"]
+        );
+        let src = "using {add as +} for Price global;
+            function add(Price a, Price b) pure returns (uint128) { return 0; }";
+        assert_eq!(
+            errors(src),
+            ["user defined operator function for '+' must have single return type usertype Price"]
+        );
+    }
+}
@@ -15,22 +15,27 @@
 use crate::{
     diagnostics::{Diagnostic, Diagnostics, ErrorType, Level},
     helpers::{CodeLocation, OptionalCodeLocation},
-    parser::{ast as pt, visitor::Visitor},
+    parser::{
+        ast as pt,
+        visitor::{Visitable, Visitor},
+    },
     semantic::{
         ast::{
-            ContractDefinition, Expression, Function, Parameter, Statement, Symbol, Type, Variable,
+            ContractDefinition, Expression, Function, Parameter, RetrieveType, Statement,
+            StorageClass, Symbol, Type, Variable,
         },
         context::{Context, ResolveTypeContext},
         contract::is_base,
         expression::{resolve_expression::expression, ExprContext, ResolveTo},
         symtable::Symtable,
         tag::resolve_tags,
-        visitor::{SemanticVisitable, SemanticVisitor},
+        visitor::SemanticVisitor,
     },
 };
 use thiserror::Error;
 
-#[allow(dead_code)]
+/// The initializer of a state variable, resolved once all the declarations
+/// of the contract are known
 pub struct DelayedResolveInitializer {
     var_no: usize,
     contract_no: usize,
@@ -45,6 +50,8 @@ pub struct VariableResolver<'a> {
     contract: Option<ContractDefinition>,
     contract_no: Option<usize>,
     symtable: &'a mut Symtable,
+    /// The initializers of the state variables declared
+    delayed: Vec<DelayedResolveInitializer>,
 }
 
 impl<'a> VariableResolver<'a> {
@@ -56,7 +63,7 @@ impl<'a> VariableResolver<'a> {
         contract_no: Option<usize>,
         symtable: &'a mut Symtable,
     ) -> Self {
-        Self { ctx, no, contract, contract_no, symtable }
+        Self { ctx, no, contract, contract_no, symtable, delayed: Vec::new() }
     }
 }
 
@@ -74,11 +81,17 @@ impl<'a> SemanticVisitor for VariableResolver<'a> {
     ) -> Result<(), Self::Error> {
         if let pt::SourceUnitPart::VariableDefinition(_) = part.part {
             self.ctx.reject(&part.annotations, "variable");
-            part.visit(self)?;
+            part.part.visit(self)?;
         }
 
         Ok(())
     }
+
+    /// The state variables are resolved with their contract, see
+    /// [`contract_variables`]
+    fn visit_sema_contract(&mut self, _: &mut ContractDefinition) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 impl<'a> Visitor for VariableResolver<'a> {
@@ -88,6 +101,9 @@ impl<'a> Visitor for VariableResolver<'a> {
         &mut self,
         def: &mut pt::VariableDefinition,
     ) -> Result<(), Self::Error> {
+        let Some(id) = self.ctx.definition_name(&def.name, def.loc, "variable") else {
+            return Ok(());
+        };
         let mut attrs = def.attrs.clone();
         let mut ty = def.ty.clone();
 
@@ -257,10 +273,7 @@ impl<'a> Visitor for VariableResolver<'a> {
                     if storage_type.is_some() {
                         self.ctx.diagnostics.push(Diagnostic::error(
                             attr.loc(),
-                            format!(
-                                "mutliple storage type specifiers for '{}'",
-                                def.name.as_ref().unwrap().name
-                            ),
+                            format!("mutliple storage type specifiers for '{}'", id.name),
                         ));
                     } else {
                         storage_type = Some(s.clone());
@@ -279,6 +292,31 @@ impl<'a> Visitor for VariableResolver<'a> {
             }
         }
 
+        // Only state variables are stored, so only they have a storage class
+        if let Some(s) = &storage_type {
+            let kind = if self.contract_no.is_none() {
+                Some("global")
+            } else if constant {
+                Some("constant")
+            } else if has_immutable.is_some() {
+                Some("immutable")
+            } else {
+                None
+            };
+
+            if let Some(kind) = kind {
+                self.ctx.diagnostics.push(Diagnostic::error(
+                    s.loc_opt().unwrap_or(def.loc),
+                    format!(
+                        "{kind} variable '{}' is not stored and cannot be '{}'",
+                        id.name,
+                        StorageClass::from(s)
+                    ),
+                ));
+                storage_type = None;
+            }
+        }
+
         let visibility = match visibility {
             Some(v) => v,
             None => pt::Visibility::Internal(Some(def.ty.loc())),
@@ -298,7 +336,7 @@ impl<'a> Visitor for VariableResolver<'a> {
             if matches!(contract.ty, pt::ContractTy::Interface(_)) ||
                 (matches!(contract.ty, pt::ContractTy::Library(_)) && !constant)
             {
-                if contract.name.is_none() || def.name.is_none() {
+                if contract.name.is_none() {
                     return Ok(());
                 }
                 self.ctx.diagnostics.push(Diagnostic::error(
@@ -307,7 +345,7 @@ impl<'a> Visitor for VariableResolver<'a> {
                         "{} '{}' is not allowed to have contract variable '{}'",
                         contract.ty,
                         contract.name.as_ref().unwrap().name,
-                        def.name.as_ref().unwrap().name
+                        id.name
                     ),
                 ));
                 return Ok(());
@@ -360,13 +398,18 @@ impl<'a> Visitor for VariableResolver<'a> {
                     ResolveTo::Type(&ty),
                 ) {
                     Ok(res) => {
+                        let valid =
+                            res.check_constant_context(&context, self.ctx, &mut diagnostics);
+
                         // implicitly conversion to correct ty
                         match res.cast(&def.loc, &ty, true, self.ctx, &mut diagnostics) {
-                            Ok(res) => {
+                            Ok(res) if valid => {
                                 res.check_constant_overflow(&mut diagnostics);
-                                Some(res)
+                                res.eval_event_selector(self.ctx)
+                                    .or_else(|| res.eval_interface_id(self.ctx))
+                                    .or(Some(res))
                             }
-                            Err(_) => None,
+                            _ => None,
                         }
                     }
                     Err(()) => None,
@@ -390,7 +433,7 @@ impl<'a> Visitor for VariableResolver<'a> {
         let bases = self.contract_no.map(|contract_no| self.ctx.contract_bases(contract_no));
 
         let tags = resolve_tags(
-            def.name.as_ref().unwrap().loc.no(),
+            self.no,
             if self.contract_no.is_none() { "global variable" } else { "state variable" },
             None,
             None,
@@ -399,7 +442,7 @@ impl<'a> Visitor for VariableResolver<'a> {
         );
 
         let sdecl = Variable {
-            name: def.name.as_ref().unwrap().name.to_string(),
+            name: id.name.to_string(),
             loc: def.loc,
             tags,
             visibility: visibility.clone(),
@@ -410,6 +453,7 @@ impl<'a> Visitor for VariableResolver<'a> {
             initializer,
             read: matches!(visibility, pt::Visibility::Public(_)),
             storage_type,
+            storage_location: None,
         };
 
         let var_no = if let Some(contract_no) = self.contract_no {
@@ -422,10 +466,20 @@ impl<'a> Visitor for VariableResolver<'a> {
             var_no
         };
 
+        if let (Some(contract_no), false, Some(initializer)) =
+            (self.contract_no, constant, &def.initializer)
+        {
+            self.delayed.push(DelayedResolveInitializer {
+                var_no,
+                contract_no,
+                initializer: initializer.clone(),
+            });
+        }
+
         let success = self.ctx.add_symbol(
             self.no,
             self.contract_no,
-            def.name.as_ref().unwrap(),
+            &id,
             Symbol::Variable(def.loc, self.contract_no, var_no),
         );
 
@@ -457,7 +511,7 @@ impl<'a> Visitor for VariableResolver<'a> {
                 let mut params = Vec::new();
                 let param = collect_parameters(
                     &ty,
-                    &def.name,
+                    &None,
                     &mut symtable,
                     &mut context,
                     &mut params,
@@ -476,18 +530,17 @@ impl<'a> Visitor for VariableResolver<'a> {
                     );
                 }
 
-                let (body, returns) =
-                    accessor_body(expr, param, constant, &mut symtable, &mut context, self.ctx);
+                let (body, returns) = accessor_body(expr, param, constant, self.ctx);
 
                 let mut func = Function::new(
-                    def.name.as_ref().unwrap().loc,
-                    def.name.as_ref().unwrap().loc,
-                    def.name.as_ref().unwrap().clone(),
+                    id.loc,
+                    id.loc,
+                    id.clone(),
                     Some(contract_no),
                     Vec::new(),
                     pt::FunctionTy::Function,
                     // accessors for constant variables have view mutability
-                    Some(pt::Mutability::View(def.name.as_ref().unwrap().loc)),
+                    Some(pt::Mutability::View(id.loc)),
                     pt::Visibility::External(None),
                     params,
                     returns,
@@ -510,10 +563,9 @@ impl<'a> Visitor for VariableResolver<'a> {
                 // we already have a symbol for
                 let symbol = Symbol::Function(vec![(def.loc, func_no)]);
 
-                self.ctx.function_symbols.insert(
-                    (def.loc.no(), Some(contract_no), def.name.as_ref().unwrap().name.to_owned()),
-                    symbol,
-                );
+                self.ctx
+                    .function_symbols
+                    .insert((self.no, Some(contract_no), id.name.to_owned()), symbol);
             }
         }
 
@@ -523,25 +575,79 @@ impl<'a> Visitor for VariableResolver<'a> {
     }
 }
 
+/// Resolve the state variables of the contract, giving the initializers of
+/// those which are not constant, to be resolved once the functions of the
+/// contract are known
 pub fn contract_variables(
-    _def: &ContractDefinition,
-    _no: usize,
-    _ctx: &mut Context,
+    def: &ContractDefinition,
+    no: usize,
+    ctx: &mut Context,
 ) -> Vec<DelayedResolveInitializer> {
-    todo!()
+    let contract = ContractDefinition {
+        contract_no: def.contract_no,
+        loc: def.loc,
+        ty: def.ty.clone(),
+        annotations: Vec::new(),
+        name: def.name.clone(),
+        base: Vec::new(),
+        parts: Vec::new(),
+    };
+    let mut symtable = Symtable::default();
+    let mut resolver =
+        VariableResolver::new(ctx, no, Some(contract), Some(def.contract_no), &mut symtable);
+
+    for part in &def.parts {
+        if let pt::ContractPart::VariableDefinition(var) = &part.part {
+            resolver.ctx.reject(&part.annotations, "variable");
+            let mut var = var.as_ref().clone();
+            // Only the accessors of the variables fail, with their diagnostic
+            let _ = resolver.visit_var_definition(&mut var);
+        }
+    }
+
+    resolver.delayed
 }
 
+/// Resolve the initializers of the state variables, converted to the types of
+/// the variables
 pub fn resolve_initializers(
-    _initializers: &[DelayedResolveInitializer],
-    _no: usize,
-    _ctx: &mut Context,
+    initializers: &[DelayedResolveInitializer],
+    no: usize,
+    ctx: &mut Context,
 ) {
-    todo!()
+    let mut symtable = Symtable::default();
+    let mut diagnostics = Diagnostics::default();
+
+    for DelayedResolveInitializer { var_no, contract_no, initializer } in initializers {
+        let ty = ctx.contracts[*contract_no].variables[*var_no].ty.clone();
+        let mut context = ExprContext { no, contract_no: Some(*contract_no), ..Default::default() };
+        context.enter_scope();
+
+        let Ok(res) = expression(
+            initializer,
+            &mut context,
+            ctx,
+            &mut symtable,
+            &mut diagnostics,
+            ResolveTo::Type(&ty),
+        ) else {
+            continue;
+        };
+        if let Ok(res) = res.cast(&initializer.loc(), &ty, true, ctx, &mut diagnostics) {
+            res.check_constant_overflow(&mut diagnostics);
+            ctx.contracts[*contract_no].variables[*var_no].initializer = Some(res);
+        }
+    }
+
+    ctx.diagnostics.extend(diagnostics);
 }
 
-#[allow(unused_variables)]
-#[allow(clippy::ptr_arg)]
-/// For accessor functions, create the parameter list and the return expression
+/// For accessor functions, create the parameter list and the return expression.
+/// Each key of a mapping and each dimension of an array adds a parameter, the
+/// key or index, and subscripts the expression with it; the parameter returned
+/// is the value left once all of them are subscripted. As in the ABI of solc,
+/// the keys and the value are named as in the mapping, and are otherwise
+/// unnamed, the value included.
 fn collect_parameters(
     ty: &Type,
     name: &Option<pt::Identifier>,
@@ -551,18 +657,290 @@ fn collect_parameters(
     expr: &mut Expression,
     ctx: &mut Context,
 ) -> Option<Parameter<Type>> {
-    todo!()
+    let loc = pt::Loc::Implicit;
+
+    let (id, arg_ty, elem_ty, elem_name) = match ty {
+        Type::Mapping(mapping) => (
+            mapping.key_name.clone().unwrap_or(pt::Identifier { loc, name: String::new() }),
+            mapping.key.as_ref().clone(),
+            mapping.value.as_ref().clone(),
+            mapping.value_name.clone(),
+        ),
+        Type::Array(..) => {
+            let index = pt::Identifier { loc, name: String::new() };
+            (index, Type::Uint(256), ty.array_elem(), name.clone())
+        }
+        _ => {
+            return Some(Parameter {
+                loc: name.as_ref().map_or(loc, |name| name.loc),
+                id: name.clone(),
+                ..Parameter::new_default(ty.clone())
+            });
+        }
+    };
+
+    let var_no = symtable.add(&id, arg_ty.clone(), None, context, &mut ctx.diagnostics)?;
+    symtable.arguments.push(Some(var_no));
+
+    let array = expr.clone();
+    *expr = Expression::Subscript {
+        loc,
+        ty: Type::StorageRef(false, Box::new(elem_ty.clone())),
+        array_ty: array.ty(),
+        array: Box::new(array),
+        index: Box::new(Expression::Variable { loc, ty: arg_ty.clone(), var_no }),
+    };
+
+    params.push(Parameter { loc: id.loc, id: Some(id), ..Parameter::new_default(arg_ty) });
+
+    collect_parameters(&elem_ty, &elem_name, symtable, context, params, expr, ctx)
 }
 
-#[allow(unused_variables)]
 /// Build up an ast for the implict accessor function for public state variables.
+/// A struct is returned as the list of its members, leaving out the mappings
+/// and arrays, as solc does.
 fn accessor_body(
     expr: Expression,
     param: Parameter<Type>,
     constant: bool,
-    symtable: &mut Symtable,
-    context: &mut ExprContext,
-    ctx: &mut Context,
+    ctx: &Context,
 ) -> (Vec<Statement>, Vec<Parameter<Type>>) {
-    todo!()
+    let loc = pt::Loc::Implicit;
+
+    if let Type::Struct(struct_ty) = &param.ty {
+        let mut list = Vec::new();
+        let mut returns = Vec::new();
+
+        for (field, member) in struct_ty.definition(ctx).fields.iter().enumerate() {
+            if matches!(member.ty, Type::Mapping(_) | Type::Array(..)) {
+                continue;
+            }
+
+            let ty = member.ty.clone();
+            list.push(Expression::StorageLoad {
+                loc,
+                ty: ty.clone(),
+                expr: Box::new(Expression::StructMember {
+                    loc,
+                    ty: Type::StorageRef(false, Box::new(ty.clone())),
+                    expr: Box::new(expr.clone()),
+                    field,
+                }),
+            });
+
+            returns.push(Parameter {
+                loc: member.loc,
+                id: member.id.clone(),
+                ..Parameter::new_default(ty)
+            });
+        }
+
+        return (vec![Statement::Return(loc, Some(Expression::List { loc, list }))], returns);
+    }
+
+    let value = if constant {
+        expr
+    } else {
+        Expression::StorageLoad { loc, ty: param.ty.clone(), expr: Box::new(expr) }
+    };
+
+    (vec![Statement::Return(loc, Some(value))], vec![param])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abi::ethereum,
+        helpers::json::Json,
+        semantic::{
+            ast::{ArrayLength, Mapping, StructDecl, StructType},
+            layout::tests::contract,
+        },
+    };
+
+    fn id(name: &str) -> pt::Identifier {
+        pt::Identifier { loc: pt::Loc::Builtin, name: name.into() }
+    }
+
+    fn storage_variable(ty: &Type) -> Expression {
+        Expression::StorageVariable {
+            loc: pt::Loc::Implicit,
+            ty: Type::StorageRef(false, Box::new(ty.clone())),
+            contract_no: 0,
+            var_no: 0,
+        }
+    }
+
+    #[test]
+    fn test_accessor_parameters() {
+        let mut ctx = Context::new();
+        let matrix = Type::Array(
+            Box::new(Type::Uint(64)),
+            vec![ArrayLength::Dynamic, ArrayLength::Fixed(3.into())],
+        );
+        let ty = Type::Mapping(Mapping {
+            key: Box::new(Type::Address(false)),
+            key_name: Some(id("owner")),
+            value: Box::new(matrix),
+            value_name: Some(id("amount")),
+        });
+
+        let mut symtable = Symtable::default();
+        let mut context = ExprContext::default();
+        context.enter_scope();
+        let mut params = Vec::new();
+        let mut expr = storage_variable(&ty);
+        let param = collect_parameters(
+            &ty,
+            &Some(id("balances")),
+            &mut symtable,
+            &mut context,
+            &mut params,
+            &mut expr,
+            &mut ctx,
+        )
+        .unwrap();
+
+        // The outer dimension of an array is indexed first
+        let params =
+            params.iter().map(|param| (param.name_as_str(), &param.ty)).collect::<Vec<_>>();
+        assert_eq!(
+            params,
+            [("owner", &Type::Address(false)), ("", &Type::Uint(256)), ("", &Type::Uint(256))]
+        );
+        assert_eq!(symtable.arguments, [Some(0), Some(1), Some(2)]);
+        assert_eq!((param.name_as_str(), &param.ty), ("amount", &Type::Uint(64)));
+        assert_eq!(expr.ty(), Type::StorageRef(false, Box::new(Type::Uint(64))));
+
+        let (body, returns) = accessor_body(expr, param, false, &ctx);
+        assert_eq!(returns.len(), 1);
+        assert!(matches!(
+            &body[..],
+            [Statement::Return(_, Some(Expression::StorageLoad { ty: Type::Uint(64), .. }))]
+        ));
+    }
+
+    #[test]
+    fn test_nested_mapping_accessor() {
+        let mut ctx = Context::new();
+        ctx.contracts.push(contract("Allowances", &[], Vec::new()));
+        // `mapping(address owner => mapping(uint => uint)) public m`
+        let inner = Type::Mapping(Mapping {
+            key: Box::new(Type::Uint(256)),
+            key_name: None,
+            value: Box::new(Type::Uint(256)),
+            value_name: None,
+        });
+        let ty = Type::Mapping(Mapping {
+            key: Box::new(Type::Address(false)),
+            key_name: Some(id("owner")),
+            value: Box::new(inner),
+            value_name: None,
+        });
+
+        let mut symtable = Symtable::default();
+        let mut context = ExprContext::default();
+        context.enter_scope();
+        let mut params = Vec::new();
+        let mut expr = storage_variable(&ty);
+        let param = collect_parameters(
+            &ty,
+            &None,
+            &mut symtable,
+            &mut context,
+            &mut params,
+            &mut expr,
+            &mut ctx,
+        )
+        .unwrap();
+
+        // The inner key subscripts the element of the outer one
+        let Expression::Subscript { array, index, .. } = &expr else { panic!("{expr:?}") };
+        assert!(matches!(index.as_ref(), Expression::Variable { var_no: 1, .. }));
+        assert!(matches!(
+            array.as_ref(),
+            Expression::Subscript { array, index, .. }
+                if matches!(index.as_ref(), Expression::Variable { var_no: 0, .. }) &&
+                    matches!(array.as_ref(), Expression::StorageVariable { .. })
+        ));
+
+        let (body, returns) = accessor_body(expr, param, false, &ctx);
+        let mut func = Function::new(
+            pt::Loc::Builtin,
+            pt::Loc::Builtin,
+            id("m"),
+            Some(0),
+            Vec::new(),
+            pt::FunctionTy::Function,
+            Some(pt::Mutability::View(pt::Loc::Builtin)),
+            pt::Visibility::External(None),
+            params,
+            returns,
+            &ctx,
+        );
+        func.body = body;
+        assert_eq!(func.signature, "m(address,uint256)");
+
+        // The keys and the value are named as in the mapping
+        ctx.functions.push(func);
+        ctx.contracts[0].all_functions.insert(0, 0);
+        let abi = ethereum::gen_abi(0, &ctx);
+        let entry = &abi.as_array().unwrap()[0];
+        let names = |key: &str| {
+            let params = entry.get(key).and_then(|params| params.as_array()).unwrap();
+            params
+                .iter()
+                .map(|param| {
+                    let field = |name: &str| param.get(name).and_then(Json::as_str).unwrap();
+                    (field("name").to_owned(), field("type").to_owned())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names("inputs"),
+            [("owner".to_owned(), "address".to_owned()), (String::new(), "uint256".to_owned())]
+        );
+        assert_eq!(names("outputs"), [(String::new(), "uint256".to_owned())]);
+    }
+
+    #[test]
+    fn test_struct_accessor() {
+        let mut ctx = Context::new();
+        ctx.structs.push(StructDecl {
+            tags: Vec::new(),
+            id: id("Account"),
+            loc: pt::Loc::Builtin,
+            contract: None,
+            fields: vec![
+                Parameter { id: Some(id("owner")), ..Parameter::new_default(Type::Address(false)) },
+                Parameter {
+                    id: Some(id("history")),
+                    ..Parameter::new_default(Type::Array(
+                        Box::new(Type::Uint(256)),
+                        vec![ArrayLength::Dynamic],
+                    ))
+                },
+                Parameter { id: Some(id("name")), ..Parameter::new_default(Type::String) },
+            ],
+            offsets: Vec::new(),
+            storage_offsets: Vec::new(),
+        });
+        let account = Type::Struct(StructType::UserDefined(0));
+
+        let (body, returns) =
+            accessor_body(storage_variable(&account), Parameter::new_default(account), false, &ctx);
+
+        // The arrays and mappings of the struct are left out
+        let returns = returns.iter().map(|param| param.name_as_str()).collect::<Vec<_>>();
+        assert_eq!(returns, ["owner", "name"]);
+        let [Statement::Return(_, Some(Expression::List { list, .. }))] = &body[..] else {
+            panic!("not a return of a list");
+        };
+        assert!(matches!(
+            &list[1],
+            Expression::StorageLoad { expr, .. }
+                if matches!(expr.as_ref(), Expression::StructMember { field: 2, .. })
+        ));
+    }
 }
@@ -0,0 +1,371 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The builtin functions of Yul, as in the EVM dialect of solc. A target only
+//! provides some of them, e.g. native code has no gas or block, and a builtin
+//! it does not provide is reported where it is called.
+
+use crate::{
+    diagnostics::{Diagnostic, DiagnosticSink},
+    parser::ast as pt,
+    target::Target,
+};
+
+/// A builtin function, the index of its prototype in [`YUL_BUILTINS`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YulBuiltin {
+    Stop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    SDiv,
+    Mod,
+    SMod,
+    Exp,
+    Not,
+    Lt,
+    Gt,
+    Slt,
+    Sgt,
+    Eq,
+    IsZero,
+    And,
+    Or,
+    Xor,
+    Byte,
+    Shl,
+    Shr,
+    Sar,
+    AddMod,
+    MulMod,
+    SignExtend,
+    Keccak256,
+    Pc,
+    Pop,
+    MLoad,
+    MStore,
+    MStore8,
+    MSize,
+    MCopy,
+    SLoad,
+    SStore,
+    TLoad,
+    TStore,
+    Gas,
+    Address,
+    Balance,
+    SelfBalance,
+    Caller,
+    CallValue,
+    CallDataLoad,
+    CallDataSize,
+    CallDataCopy,
+    CodeSize,
+    CodeCopy,
+    ExtCodeSize,
+    ExtCodeCopy,
+    ExtCodeHash,
+    ReturnDataSize,
+    ReturnDataCopy,
+    Create,
+    Create2,
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Return,
+    Revert,
+    SelfDestruct,
+    Invalid,
+    Log0,
+    Log1,
+    Log2,
+    Log3,
+    Log4,
+    ChainId,
+    BaseFee,
+    BlobBaseFee,
+    BlobHash,
+    Origin,
+    GasPrice,
+    BlockHash,
+    Coinbase,
+    Timestamp,
+    Number,
+    Difficulty,
+    PrevRandao,
+    GasLimit,
+    DataSize,
+    DataOffset,
+    DataCopy,
+    LinkerSymbol,
+    MemoryGuard,
+    LoadImmutable,
+    SetImmutable,
+}
+
+/// The signature of a builtin, whose parameters and results are all words
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YulBuiltinPrototype {
+    pub builtin: YulBuiltin,
+    pub name: &'static str,
+    pub params: usize,
+    pub returns: usize,
+    /// Whether the builtin ends the call, like `return` or `revert`
+    pub stops: bool,
+    /// Whether the first argument must be a string literal, like the name of
+    /// a data object
+    pub literal_arg: bool,
+    /// The targets which provide the builtin
+    pub targets: &'static [Target],
+    pub description: &'static str,
+}
+
+const ALL: &[Target] = Target::ALL;
+const EVM: &[Target] = &[Target::Evm];
+
+const fn prototype(
+    builtin: YulBuiltin,
+    name: &'static str,
+    params: usize,
+    returns: usize,
+    targets: &'static [Target],
+    description: &'static str,
+) -> YulBuiltinPrototype {
+    YulBuiltinPrototype {
+        builtin,
+        name,
+        params,
+        returns,
+        stops: false,
+        literal_arg: false,
+        targets,
+        description,
+    }
+}
+
+impl YulBuiltinPrototype {
+    const fn stops(self) -> Self {
+        Self { stops: true, ..self }
+    }
+
+    const fn literal_arg(self) -> Self {
+        Self { literal_arg: true, ..self }
+    }
+
+    /// Whether the target provides the builtin
+    pub fn is_available(&self, target: Target) -> bool {
+        self.targets.contains(&target)
+    }
+}
+
+/// The prototypes of the builtins, in the order of [`YulBuiltin`]
+pub const YUL_BUILTINS: &[YulBuiltinPrototype] = &[
+    prototype(YulBuiltin::Stop, "stop", 0, 0, ALL, "End the call successfully without returning data").stops(),
+    prototype(YulBuiltin::Add, "add", 2, 1, ALL, "x + y"),
+    prototype(YulBuiltin::Sub, "sub", 2, 1, ALL, "x - y"),
+    prototype(YulBuiltin::Mul, "mul", 2, 1, ALL, "x * y"),
+    prototype(YulBuiltin::Div, "div", 2, 1, ALL, "x / y, or 0 if y is 0"),
+    prototype(YulBuiltin::SDiv, "sdiv", 2, 1, ALL, "x / y for signed numbers in two's complement, or 0 if y is 0"),
+    prototype(YulBuiltin::Mod, "mod", 2, 1, ALL, "x % y, or 0 if y is 0"),
+    prototype(YulBuiltin::SMod, "smod", 2, 1, ALL, "x % y for signed numbers in two's complement, or 0 if y is 0"),
+    prototype(YulBuiltin::Exp, "exp", 2, 1, ALL, "x to the power of y"),
+    prototype(YulBuiltin::Not, "not", 1, 1, ALL, "Bitwise not of x"),
+    prototype(YulBuiltin::Lt, "lt", 2, 1, ALL, "1 if x < y, 0 otherwise"),
+    prototype(YulBuiltin::Gt, "gt", 2, 1, ALL, "1 if x > y, 0 otherwise"),
+    prototype(YulBuiltin::Slt, "slt", 2, 1, ALL, "1 if x < y for signed numbers in two's complement, 0 otherwise"),
+    prototype(YulBuiltin::Sgt, "sgt", 2, 1, ALL, "1 if x > y for signed numbers in two's complement, 0 otherwise"),
+    prototype(YulBuiltin::Eq, "eq", 2, 1, ALL, "1 if x == y, 0 otherwise"),
+    prototype(YulBuiltin::IsZero, "iszero", 1, 1, ALL, "1 if x == 0, 0 otherwise"),
+    prototype(YulBuiltin::And, "and", 2, 1, ALL, "Bitwise and of x and y"),
+    prototype(YulBuiltin::Or, "or", 2, 1, ALL, "Bitwise or of x and y"),
+    prototype(YulBuiltin::Xor, "xor", 2, 1, ALL, "Bitwise xor of x and y"),
+    prototype(YulBuiltin::Byte, "byte", 2, 1, ALL, "The nth byte of x, where the most significant byte is the 0th"),
+    prototype(YulBuiltin::Shl, "shl", 2, 1, ALL, "Logical shift left y by x bits"),
+    prototype(YulBuiltin::Shr, "shr", 2, 1, ALL, "Logical shift right y by x bits"),
+    prototype(YulBuiltin::Sar, "sar", 2, 1, ALL, "Signed arithmetic shift right y by x bits"),
+    prototype(YulBuiltin::AddMod, "addmod", 3, 1, ALL, "(x + y) % m with arbitrary precision arithmetic, or 0 if m is 0"),
+    prototype(YulBuiltin::MulMod, "mulmod", 3, 1, ALL, "(x * y) % m with arbitrary precision arithmetic, or 0 if m is 0"),
+    prototype(YulBuiltin::SignExtend, "signextend", 2, 1, ALL, "Sign extend from the (i*8+7)th bit counting from the least significant"),
+    prototype(YulBuiltin::Keccak256, "keccak256", 2, 1, EVM, "keccak256 hash of the memory from p to p + n"),
+    prototype(YulBuiltin::Pc, "pc", 0, 1, EVM, "The current position in the code"),
+    prototype(YulBuiltin::Pop, "pop", 1, 0, ALL, "Discard the value x"),
+    prototype(YulBuiltin::MLoad, "mload", 1, 1, ALL, "The word of memory from p to p + 32"),
+    prototype(YulBuiltin::MStore, "mstore", 2, 0, ALL, "Store the word v in memory from p to p + 32"),
+    prototype(YulBuiltin::MStore8, "mstore8", 2, 0, ALL, "Store the least significant byte of v in memory at p"),
+    prototype(YulBuiltin::MSize, "msize", 0, 1, ALL, "The size of the memory, the largest accessed index"),
+    prototype(YulBuiltin::MCopy, "mcopy", 3, 0, ALL, "Copy s bytes of memory from f to t"),
+    prototype(YulBuiltin::SLoad, "sload", 1, 1, ALL, "The storage word at the key p"),
+    prototype(YulBuiltin::SStore, "sstore", 2, 0, ALL, "Store the word v at the storage key p"),
+    prototype(YulBuiltin::TLoad, "tload", 1, 1, ALL, "The transient storage word at the key p"),
+    prototype(YulBuiltin::TStore, "tstore", 2, 0, ALL, "Store the word v at the transient storage key p"),
+    prototype(YulBuiltin::Gas, "gas", 0, 1, EVM, "The gas still available to the execution"),
+    prototype(YulBuiltin::Address, "address", 0, 1, EVM, "The address of the current contract"),
+    prototype(YulBuiltin::Balance, "balance", 1, 1, EVM, "The balance of the address a"),
+    prototype(YulBuiltin::SelfBalance, "selfbalance", 0, 1, EVM, "The balance of the current contract"),
+    prototype(YulBuiltin::Caller, "caller", 0, 1, ALL, "The sender of the call"),
+    prototype(YulBuiltin::CallValue, "callvalue", 0, 1, ALL, "The value sent with the call"),
+    prototype(YulBuiltin::CallDataLoad, "calldataload", 1, 1, ALL, "The word of the call data starting at p"),
+    prototype(YulBuiltin::CallDataSize, "calldatasize", 0, 1, ALL, "The size of the call data in bytes"),
+    prototype(YulBuiltin::CallDataCopy, "calldatacopy", 3, 0, ALL, "Copy s bytes of the call data at f to memory at t"),
+    prototype(YulBuiltin::CodeSize, "codesize", 0, 1, EVM, "The size of the code of the current contract"),
+    prototype(YulBuiltin::CodeCopy, "codecopy", 3, 0, EVM, "Copy s bytes of the code at f to memory at t"),
+    prototype(YulBuiltin::ExtCodeSize, "extcodesize", 1, 1, EVM, "The size of the code at the address a"),
+    prototype(YulBuiltin::ExtCodeCopy, "extcodecopy", 4, 0, EVM, "Like codecopy, of the code at the address a"),
+    prototype(YulBuiltin::ExtCodeHash, "extcodehash", 1, 1, EVM, "The hash of the code at the address a"),
+    prototype(YulBuiltin::ReturnDataSize, "returndatasize", 0, 1, ALL, "The size of the data returned by the last call"),
+    prototype(YulBuiltin::ReturnDataCopy, "returndatacopy", 3, 0, ALL, "Copy s bytes of the data returned by the last call at f to memory at t"),
+    prototype(YulBuiltin::Create, "create", 3, 1, ALL, "Create a contract with the code in memory from p to p + n, sending the value v, and give its address"),
+    prototype(YulBuiltin::Create2, "create2", 4, 1, EVM, "Like create, at an address derived from the salt s"),
+    prototype(YulBuiltin::Call, "call", 7, 1, ALL, "Call the contract at the address a with the input in memory, sending the value v, and give 1 on success"),
+    prototype(YulBuiltin::CallCode, "callcode", 7, 1, EVM, "Like call, running the code of a in the current contract"),
+    prototype(YulBuiltin::DelegateCall, "delegatecall", 6, 1, EVM, "Like callcode, keeping the caller and value"),
+    prototype(YulBuiltin::StaticCall, "staticcall", 6, 1, EVM, "Like call without a value, the callee cannot modify state"),
+    prototype(YulBuiltin::Return, "return", 2, 0, ALL, "End the call successfully, returning the memory from p to p + s").stops(),
+    prototype(YulBuiltin::Revert, "revert", 2, 0, ALL, "End the call, undoing its changes, with the memory from p to p + s as the reason").stops(),
+    prototype(YulBuiltin::SelfDestruct, "selfdestruct", 1, 0, EVM, "Destroy the current contract, sending its balance to the address a").stops(),
+    prototype(YulBuiltin::Invalid, "invalid", 0, 0, ALL, "End the call with an invalid instruction").stops(),
+    prototype(YulBuiltin::Log0, "log0", 2, 0, ALL, "Log the memory from p to p + s without topics"),
+    prototype(YulBuiltin::Log1, "log1", 3, 0, ALL, "Log the memory from p to p + s with the topic t1"),
+    prototype(YulBuiltin::Log2, "log2", 4, 0, ALL, "Log the memory from p to p + s with the topics t1 and t2"),
+    prototype(YulBuiltin::Log3, "log3", 5, 0, ALL, "Log the memory from p to p + s with the topics t1 to t3"),
+    prototype(YulBuiltin::Log4, "log4", 6, 0, ALL, "Log the memory from p to p + s with the topics t1 to t4"),
+    prototype(YulBuiltin::ChainId, "chainid", 0, 1, EVM, "The identifier of the chain"),
+    prototype(YulBuiltin::BaseFee, "basefee", 0, 1, EVM, "The base fee of the current block"),
+    prototype(YulBuiltin::BlobBaseFee, "blobbasefee", 0, 1, EVM, "The blob base fee of the current block"),
+    prototype(YulBuiltin::BlobHash, "blobhash", 1, 1, EVM, "The versioned hash of the ith blob of the transaction"),
+    prototype(YulBuiltin::Origin, "origin", 0, 1, EVM, "The sender of the transaction"),
+    prototype(YulBuiltin::GasPrice, "gasprice", 0, 1, EVM, "The gas price of the transaction"),
+    prototype(YulBuiltin::BlockHash, "blockhash", 1, 1, EVM, "The hash of the block number b, for the last 256 blocks"),
+    prototype(YulBuiltin::Coinbase, "coinbase", 0, 1, EVM, "The beneficiary of the current block"),
+    prototype(YulBuiltin::Timestamp, "timestamp", 0, 1, EVM, "The timestamp of the current block in seconds"),
+    prototype(YulBuiltin::Number, "number", 0, 1, EVM, "The number of the current block"),
+    prototype(YulBuiltin::Difficulty, "difficulty", 0, 1, EVM, "The difficulty of the current block, before the merge"),
+    prototype(YulBuiltin::PrevRandao, "prevrandao", 0, 1, EVM, "The randomness of the beacon chain"),
+    prototype(YulBuiltin::GasLimit, "gaslimit", 0, 1, EVM, "The gas limit of the current block"),
+    prototype(YulBuiltin::DataSize, "datasize", 1, 1, ALL, "The size of the data object named x").literal_arg(),
+    prototype(YulBuiltin::DataOffset, "dataoffset", 1, 1, ALL, "The offset of the data object named x").literal_arg(),
+    prototype(YulBuiltin::DataCopy, "datacopy", 3, 0, ALL, "Copy s bytes of the data at f to memory at t"),
+    prototype(YulBuiltin::LinkerSymbol, "linkersymbol", 1, 1, EVM, "The address of the library named x, set by the linker").literal_arg(),
+    prototype(YulBuiltin::MemoryGuard, "memoryguard", 1, 1, ALL, "The size x, promising the memory below it is only used by the code").literal_arg(),
+    prototype(YulBuiltin::LoadImmutable, "loadimmutable", 1, 1, EVM, "The value of the immutable variable named x").literal_arg(),
+    prototype(YulBuiltin::SetImmutable, "setimmutable", 3, 0, EVM, "Set the immutable variable named x in the code at the offset o to the value v").literal_arg(),
+];
+
+impl YulBuiltin {
+    /// The builtin of the given name, if any
+    pub fn from_name(name: &str) -> Option<Self> {
+        YUL_BUILTINS.iter().find(|prototype| prototype.name == name).map(|p| p.builtin)
+    }
+
+    pub fn prototype(self) -> &'static YulBuiltinPrototype {
+        &YUL_BUILTINS[self as usize]
+    }
+}
+
+/// Resolve a call of a builtin, giving `None` if the name is not a builtin.
+/// A builtin the target does not provide, called with the wrong number of
+/// arguments or without the literal it expects is an error.
+#[allow(clippy::result_unit_err)]
+pub fn resolve_builtin_call(
+    call: &pt::YulFunctionCall,
+    target: Target,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Option<YulBuiltin>, ()> {
+    let Some(builtin) = YulBuiltin::from_name(&call.id.name) else {
+        return Ok(None);
+    };
+    let prototype = builtin.prototype();
+
+    let message = if !prototype.is_available(target) {
+        format!("builtin '{}' is not available for target {target}", prototype.name)
+    } else if call.arguments.len() != prototype.params {
+        format!(
+            "builtin '{}' expects {} arguments, {} given",
+            prototype.name,
+            prototype.params,
+            call.arguments.len()
+        )
+    } else if prototype.literal_arg &&
+        !matches!(call.arguments.first(), Some(pt::YulExpression::StringLiteral(..)))
+    {
+        format!("the first argument of builtin '{}' must be a string literal", prototype.name)
+    } else {
+        return Ok(Some(builtin));
+    };
+
+    diagnostics.push(Diagnostic::error(call.loc, message));
+    Err(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+
+    #[test]
+    fn test_builtin_table() {
+        for (i, prototype) in YUL_BUILTINS.iter().enumerate() {
+            assert_eq!(prototype.builtin as usize, i, "{} is out of order", prototype.name);
+            assert_eq!(YulBuiltin::from_name(prototype.name), Some(prototype.builtin));
+            assert!(prototype.is_available(Target::Evm));
+        }
+        assert_eq!(YulBuiltin::from_name("sha3"), None);
+    }
+
+    #[test]
+    fn test_resolve_builtin_call() {
+        let call = |name: &str, arguments: Vec<pt::YulExpression>| pt::YulFunctionCall {
+            loc: pt::Loc::Builtin,
+            id: pt::Identifier::new(name),
+            arguments,
+        };
+        let word = || pt::YulExpression::Variable(pt::Identifier::new("x"));
+        let mut diagnostics = Diagnostics::default();
+
+        let sstore = call("sstore", vec![word(), word()]);
+        assert_eq!(
+            resolve_builtin_call(&sstore, Target::Native, &mut diagnostics),
+            Ok(Some(YulBuiltin::SStore))
+        );
+        let helper = call("helper", vec![]);
+        assert_eq!(resolve_builtin_call(&helper, Target::Native, &mut diagnostics), Ok(None));
+        assert!(!diagnostics.any_errors());
+
+        let selfbalance = call("selfbalance", vec![]);
+        assert_eq!(resolve_builtin_call(&selfbalance, Target::Native, &mut diagnostics), Err(()));
+        assert_eq!(
+            diagnostics.first_error(),
+            "builtin 'selfbalance' is not available for target native"
+        );
+        assert_eq!(
+            resolve_builtin_call(&selfbalance, Target::Evm, &mut diagnostics),
+            Ok(Some(YulBuiltin::SelfBalance))
+        );
+
+        let mut diagnostics = Diagnostics::default();
+        let add = call("add", vec![word()]);
+        assert_eq!(resolve_builtin_call(&add, Target::Native, &mut diagnostics), Err(()));
+        assert_eq!(diagnostics.first_error(), "builtin 'add' expects 2 arguments, 1 given");
+
+        let mut diagnostics = Diagnostics::default();
+        let datasize = call("datasize", vec![word()]);
+        assert_eq!(resolve_builtin_call(&datasize, Target::Native, &mut diagnostics), Err(()));
+        assert_eq!(
+            diagnostics.first_error(),
+            "the first argument of builtin 'datasize' must be a string literal"
+        );
+    }
+}
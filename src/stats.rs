@@ -0,0 +1,241 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Size statistics of a compilation, as printed by `check --stats`: the
+//! tokens and parse tree nodes of the files, the declarations the semantic
+//! analysis resolved and the symbols it defined, and the peak memory use.
+
+use std::fmt;
+
+use crate::{
+    codegen::loops,
+    lexer::Lexer,
+    parser::{
+        self,
+        ast::{ContractPart, Expression, SourceUnitPart, Statement},
+    },
+    semantic::context::Context,
+};
+
+/// Sizes of the parse trees of the files
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TreeStats {
+    pub tokens: usize,
+    /// Source unit and contract parts, like contracts and functions
+    pub parts: usize,
+    pub statements: usize,
+    pub expressions: usize,
+}
+
+impl TreeStats {
+    /// Lex and parse the source, counting nothing but the tokens when it has
+    /// syntax errors
+    pub fn of_source(source: &str) -> Self {
+        let mut stats = Self { tokens: Lexer::new(source).count(), ..Self::default() };
+
+        if let Ok(tree) = parser::parse(source, 0) {
+            for part in &tree.0 {
+                stats.parts += 1;
+                match part {
+                    SourceUnitPart::ContractDefinition(contract) => {
+                        for part in &contract.parts {
+                            stats.parts += 1;
+                            match part {
+                                ContractPart::FunctionDefinition(func) => {
+                                    stats.add_body(func.body.as_ref())
+                                }
+                                ContractPart::VariableDefinition(var) => {
+                                    stats.add_expression(var.initializer.as_ref())
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                    SourceUnitPart::FunctionDefinition(func) => stats.add_body(func.body.as_ref()),
+                    SourceUnitPart::VariableDefinition(var) => {
+                        stats.add_expression(var.initializer.as_ref())
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// All the nodes, not counting tokens
+    pub fn nodes(&self) -> usize {
+        self.parts + self.statements + self.expressions
+    }
+
+    fn add_body(&mut self, body: Option<&Statement>) {
+        let mut worklist = Vec::from_iter(body);
+        while let Some(stmt) = worklist.pop() {
+            self.statements += 1;
+            for expr in loops::expressions(stmt) {
+                self.add_expression(Some(expr));
+            }
+            worklist.extend(loops::statements(stmt));
+        }
+    }
+
+    fn add_expression(&mut self, expr: Option<&Expression>) {
+        if let Some(expr) = expr {
+            loops::visit(expr, &mut |_| self.expressions += 1);
+        }
+    }
+}
+
+impl std::ops::AddAssign for TreeStats {
+    fn add_assign(&mut self, other: Self) {
+        self.tokens += other.tokens;
+        self.parts += other.parts;
+        self.statements += other.statements;
+        self.expressions += other.expressions;
+    }
+}
+
+/// Statistics of a compilation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub files: usize,
+    pub tree: TreeStats,
+    /// The number of each kind of declaration in the context
+    pub declarations: Vec<(&'static str, usize)>,
+    pub function_symbols: usize,
+    pub variable_symbols: usize,
+    /// Peak resident set size in bytes, where the platform reports it
+    pub peak_rss: Option<u64>,
+}
+
+impl Stats {
+    /// Collect the statistics of an analyzed context, given the sources of
+    /// its files
+    pub fn collect<'a>(ctx: &Context, sources: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut tree = TreeStats::default();
+        for source in sources {
+            tree += TreeStats::of_source(source);
+        }
+
+        Self {
+            files: ctx.files.len(),
+            tree,
+            declarations: vec![
+                ("contracts", ctx.contracts.len()),
+                ("functions", ctx.functions.len()),
+                ("structs", ctx.structs.len()),
+                ("enums", ctx.enums.len()),
+                ("events", ctx.events.len()),
+                ("errors", ctx.errors.len()),
+                ("user types", ctx.user_types.len()),
+                ("constants", ctx.constants.len()),
+                ("using", ctx.using.len()),
+            ],
+            function_symbols: ctx.function_symbols.len(),
+            variable_symbols: ctx.variable_symbols.len(),
+            peak_rss: peak_rss(),
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tree = &self.tree;
+        writeln!(f, "{:<20}{:>10}", "files", self.files)?;
+        writeln!(f, "{:<20}{:>10}", "tokens", tree.tokens)?;
+        writeln!(f, "{:<20}{:>10}", "tree nodes", tree.nodes())?;
+        writeln!(f, "  {:<18}{:>10}", "parts", tree.parts)?;
+        writeln!(f, "  {:<18}{:>10}", "statements", tree.statements)?;
+        writeln!(f, "  {:<18}{:>10}", "expressions", tree.expressions)?;
+        writeln!(f, "declarations")?;
+        for (kind, count) in &self.declarations {
+            writeln!(f, "  {kind:<18}{count:>10}")?;
+        }
+        writeln!(f, "symbols")?;
+        writeln!(f, "  {:<18}{:>10}", "functions", self.function_symbols)?;
+        writeln!(f, "  {:<18}{:>10}", "variables", self.variable_symbols)?;
+        match self.peak_rss {
+            Some(bytes) => write!(f, "{:<20}{:>7} KiB", "peak memory", bytes / 1024),
+            None => write!(f, "{:<20}{:>10}", "peak memory", "unknown"),
+        }
+    }
+}
+
+/// The peak resident set size of the process, from `/proc` on Linux
+pub fn peak_rss() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find_map(|line| line.strip_prefix("VmHWM:"))?;
+    let kib = line.trim().strip_suffix("kB")?.trim().parse::<u64>().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_stats() {
+        let source = r#"
+uint constant K = 1 + 2;
+
+contract C {
+    uint x = K * 2;
+
+    function f(uint a) public {
+        if (a > 0) {
+            x = a;
+        } else {
+            g(a, 1);
+        }
+    }
+
+    function g(uint a, uint b) internal;
+}
+"#;
+        let stats = TreeStats::of_source(source);
+        assert_eq!(
+            stats,
+            TreeStats {
+                tokens: 61,
+                // The constant, the contract, its variable and functions
+                parts: 5,
+                // The body, the if, both branches and their statements
+                statements: 6,
+                // `1 + 2`, `K * 2`, `a > 0`, `x = a` and `g(a, 1)`
+                expressions: 3 + 3 + 3 + 3 + 4,
+            }
+        );
+        assert_eq!(stats.nodes(), 27);
+
+        // The tokens of a source with syntax errors are still counted
+        assert_eq!(
+            TreeStats::of_source("contract {"),
+            TreeStats { tokens: 2, ..Default::default() }
+        );
+    }
+
+    #[test]
+    fn test_stats_display() {
+        let ctx = Context::new();
+        let stats = Stats::collect(&ctx, ["pragma solidity ^0.8.0;"]);
+        // The version is a single token
+        assert_eq!(stats.tree.tokens, 5);
+
+        let text = stats.to_string();
+        assert!(
+            text.starts_with("files                        0\ntokens                       5\n")
+        );
+        assert!(text.contains("  contracts                  0\n"));
+    }
+}
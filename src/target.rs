@@ -0,0 +1,45 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The platforms a contract is written for. They differ in what a contract
+//! can reach, e.g. the Yul builtins of inline assembly, unlike the target
+//! triple which only selects the machine code.
+
+use strum::{Display, EnumString};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Target {
+    /// Native code calling the host functions of the runtime, see [`crate::runtime`]
+    #[default]
+    Native,
+    /// The Ethereum Virtual Machine, for analyzing contracts written for solc
+    Evm,
+}
+
+/// Most bytes of code of a contract deployed on Ethereum, see EIP-170
+pub const EVM_CODE_SIZE_LIMIT: usize = 0x6000;
+
+impl Target {
+    pub const ALL: &'static [Target] = &[Target::Native, Target::Evm];
+
+    /// The size above which the code of a contract is warned about, when the
+    /// platform has a limit
+    pub fn code_size_limit(self) -> Option<usize> {
+        match self {
+            Target::Native => None,
+            Target::Evm => Some(EVM_CODE_SIZE_LIMIT),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The contracts vendored for the integration tests
+pub fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+/// The Solidity files under the directory, sorted
+pub fn sources(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(sources(&path));
+        } else if path.extension().is_some_and(|ext| ext == "sol") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Compare the output to the golden file, a missing file being empty. With
+/// `UPDATE_GOLDEN=1` in the environment the file is written instead, and the
+/// change is left for review in the diff.
+pub fn assert_golden(golden: &Path, actual: &str) {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(golden, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(golden).unwrap_or_default();
+    assert!(
+        actual == expected,
+        "output differs from {}, run with UPDATE_GOLDEN=1 to update it\n\
+         --- expected\n{expected}\n--- actual\n{actual}",
+        golden.display()
+    );
+}
+
+/// Remove the ANSI escape sequences coloring terminal output
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        // A control sequence ends with a letter
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,52 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integration tests on the contracts vendored in `tests/corpus`.
+//!
+//! Each contract has a golden file next to it, with the extension
+//! `.diagnostics`, listing the diagnostics of checking it, one per line. Run
+//! the tests with `UPDATE_GOLDEN=1` to write them after an intended change.
+
+use std::fs;
+
+use common::{corpus_dir, sources};
+use hmt_frontend_solidity::{check, config::Config, parser, Input};
+
+mod common;
+
+#[test]
+fn test_corpus_parses() {
+    let files = sources(&corpus_dir());
+    assert!(!files.is_empty());
+
+    for path in files {
+        let contents = fs::read_to_string(&path).unwrap();
+        if let Err(errors) = parser::parse(&contents, 0) {
+            panic!("{} does not parse: {errors:?}", path.display());
+        }
+    }
+}
+
+#[test]
+fn test_corpus_diagnostics() {
+    for path in sources(&corpus_dir()) {
+        let diagnostics = check(&Input::Path(path.clone()), &Config::default());
+        let actual = diagnostics
+            .iter()
+            .map(|diagnostic| format!("{}: {}\n", diagnostic.level, diagnostic.message))
+            .collect::<String>();
+
+        common::assert_golden(&path.with_extension("diagnostics"), &actual);
+    }
+}
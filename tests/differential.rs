@@ -0,0 +1,368 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential parsing against solc, to find the gaps of the grammar.
+//!
+//! The contracts of `tests/corpus` and the snippets below are parsed by the
+//! parser and by solc, through its standard JSON interface stopped after
+//! parsing. Both must accept or reject each source alike, and the sources
+//! both accept must have the same outline: the definitions they contain, by
+//! kind and name, in order. The reference is the `solc` on the path, or the
+//! one `SOLC` names, so the comparisons are ignored unless asked for with
+//! `just test-differential`, which CI runs with a pinned solc.
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use common::{corpus_dir, sources};
+use hmt_frontend_solidity::{
+    helpers::json::Json,
+    parser::{self, ast as pt},
+};
+
+mod common;
+
+/// Sources exercising the corners of the grammar, and whether they are valid
+const SNIPPETS: &[(&str, bool, &str)] = &[
+    (
+        "value_types.sol",
+        true,
+        "type Price is uint128;
+         using {add as +} for Price global;
+         function add(Price a, Price b) pure returns (Price) {
+             return Price.wrap(Price.unwrap(a) + Price.unwrap(b));
+         }",
+    ),
+    (
+        "special_functions.sol",
+        true,
+        "abstract contract C {
+             mapping(address owner => uint balance) balances;
+             error Empty();
+             event Paid(address indexed from, uint value) anonymous;
+             constructor() payable {}
+             receive() external payable {}
+             fallback(bytes calldata) external returns (bytes memory) { revert Empty(); }
+             modifier only() virtual;
+         }",
+    ),
+    (
+        "statements.sol",
+        true,
+        "library L {
+             function f(uint[] memory xs) internal pure returns (uint sum) {
+                 unchecked { for (uint i; i < xs.length; ++i) sum += xs[i]; }
+                 (uint a, , uint b) = (1, 2, 0x1_000);
+                 do { sum += a * b; } while (false);
+                 sum = sum > 0 ? sum : type(uint).max;
+             }
+         }",
+    ),
+    ("missing_semicolon.sol", false, "contract C { uint x }"),
+    ("missing_expression.sol", false, "contract C { function f() public { uint x = ; } }"),
+    ("unnamed_contract.sol", false, "contract { }"),
+];
+
+/// What a parser makes of a source
+#[derive(Debug, PartialEq)]
+enum Parsed {
+    /// The outline of the source
+    Accepted(Vec<String>),
+    /// The first error
+    Rejected(String),
+}
+
+impl Parsed {
+    /// Whether the parsers agree, the outlines being compared only when both
+    /// accept the source
+    fn agrees(&self, other: &Parsed) -> bool {
+        match (self, other) {
+            (Parsed::Accepted(a), Parsed::Accepted(b)) => a == b,
+            (Parsed::Rejected(_), Parsed::Rejected(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The outline of a parse tree, see [`reference_outline`] for solc's
+fn outline(unit: &pt::SourceUnit) -> Vec<String> {
+    let mut outline = Vec::new();
+
+    for part in unit.iter() {
+        match part {
+            pt::SourceUnitPart::ContractDefinition(contract) => {
+                let name = contract.name.as_ref().map_or("", |id| &id.name);
+                let kind = match contract.ty {
+                    pt::ContractTy::Abstract(_) | pt::ContractTy::Contract(_) => "contract",
+                    pt::ContractTy::Interface(_) => "interface",
+                    pt::ContractTy::Library(_) => "library",
+                };
+                outline.push(format!("{kind} {name}"));
+
+                for part in &contract.parts {
+                    let definition = match part {
+                        pt::ContractPart::StructDefinition(def) => named("struct", &def.name),
+                        pt::ContractPart::EventDefinition(def) => named("event", &def.name),
+                        pt::ContractPart::EnumDefinition(def) => named("enum", &def.name),
+                        pt::ContractPart::ErrorDefinition(def) => named("error", &def.name),
+                        pt::ContractPart::VariableDefinition(def) => named("variable", &def.name),
+                        pt::ContractPart::FunctionDefinition(def) => function(def),
+                        pt::ContractPart::TypeDefinition(def) => format!("type {}", def.name.name),
+                        pt::ContractPart::Using(_) => "using".into(),
+                        pt::ContractPart::Annotation(_) | pt::ContractPart::StraySemicolon(_) => {
+                            continue
+                        }
+                    };
+                    outline.push(format!("{name}.{definition}"));
+                }
+            }
+            pt::SourceUnitPart::PragmaDirective(_) => outline.push("pragma".into()),
+            pt::SourceUnitPart::ImportDirective(_) => outline.push("import".into()),
+            pt::SourceUnitPart::EnumDefinition(def) => outline.push(named("enum", &def.name)),
+            pt::SourceUnitPart::StructDefinition(def) => outline.push(named("struct", &def.name)),
+            pt::SourceUnitPart::EventDefinition(def) => outline.push(named("event", &def.name)),
+            pt::SourceUnitPart::ErrorDefinition(def) => outline.push(named("error", &def.name)),
+            pt::SourceUnitPart::FunctionDefinition(def) => outline.push(function(def)),
+            pt::SourceUnitPart::VariableDefinition(def) => {
+                outline.push(named("variable", &def.name))
+            }
+            pt::SourceUnitPart::TypeDefinition(def) => {
+                outline.push(format!("type {}", def.name.name))
+            }
+            pt::SourceUnitPart::Using(_) => outline.push("using".into()),
+            pt::SourceUnitPart::Annotation(_) | pt::SourceUnitPart::StraySemicolon(_) => (),
+        }
+    }
+
+    outline
+}
+
+fn named(kind: &str, name: &Option<pt::Identifier>) -> String {
+    format!("{kind} {}", name.as_ref().map_or("", |id| &id.name))
+}
+
+/// The functions without a name are known by their kind
+fn function(def: &pt::FunctionDefinition) -> String {
+    match def.ty {
+        pt::FunctionTy::Constructor => "constructor".into(),
+        pt::FunctionTy::Fallback => "fallback".into(),
+        pt::FunctionTy::Receive => "receive".into(),
+        pt::FunctionTy::Function => named("function", &def.name),
+        pt::FunctionTy::Modifier => named("modifier", &def.name),
+    }
+}
+
+/// The outline of a source unit of solc's AST, as [`outline`] gives it
+fn reference_outline(unit: &Json) -> Vec<String> {
+    let mut outline = Vec::new();
+
+    for node in nodes(unit) {
+        if node_type(node) == "ContractDefinition" {
+            let name = string(node, "name");
+            outline.push(format!("{} {name}", string(node, "contractKind")));
+            outline.extend(nodes(node).iter().map(|part| format!("{name}.{}", definition(part))));
+        } else {
+            outline.push(definition(node));
+        }
+    }
+
+    outline
+}
+
+/// A definition of solc's AST, by kind and name
+fn definition(node: &Json) -> String {
+    let kind = match node_type(node) {
+        "PragmaDirective" => return "pragma".into(),
+        "ImportDirective" => return "import".into(),
+        "UsingForDirective" => return "using".into(),
+        "FunctionDefinition" => match string(node, "kind") {
+            "function" | "freeFunction" => "function",
+            kind => return kind.into(),
+        },
+        "ModifierDefinition" => "modifier",
+        "EnumDefinition" => "enum",
+        "StructDefinition" => "struct",
+        "EventDefinition" => "event",
+        "ErrorDefinition" => "error",
+        "VariableDeclaration" => "variable",
+        "UserDefinedValueTypeDefinition" => "type",
+        other => other,
+    };
+    format!("{kind} {}", string(node, "name"))
+}
+
+fn nodes(node: &Json) -> &[Json] {
+    node.get("nodes").and_then(Json::as_array).unwrap_or_default()
+}
+
+fn node_type(node: &Json) -> &str {
+    string(node, "nodeType")
+}
+
+fn string<'a>(node: &'a Json, key: &str) -> &'a str {
+    node.get(key).and_then(Json::as_str).unwrap_or_default()
+}
+
+/// Parse the sources with the parser, by name
+fn parse(sources: &[(String, String)]) -> BTreeMap<String, Parsed> {
+    sources
+        .iter()
+        .map(|(name, contents)| {
+            let parsed = match parser::parse(contents, 0) {
+                Ok(unit) => Parsed::Accepted(outline(&unit)),
+                Err(errors) => Parsed::Rejected(errors[0].message.clone()),
+            };
+            (name.clone(), parsed)
+        })
+        .collect()
+}
+
+/// Parse the sources together with solc, by name, so that they can import
+/// each other
+fn reference(sources: &[(String, String)]) -> BTreeMap<String, Parsed> {
+    let solc = env::var("SOLC").unwrap_or_else(|_| "solc".into());
+    let mut child = Command::new(&solc)
+        .arg("--standard-json")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|err| {
+            panic!("cannot run '{solc}', put solc on the path or set SOLC: {err}")
+        });
+
+    let input = Json::object([
+        ("language", Json::String("Solidity".into())),
+        (
+            "sources",
+            Json::object(sources.iter().map(|(name, contents)| {
+                (name.clone(), Json::object([("content", Json::String(contents.clone()))]))
+            })),
+        ),
+        (
+            "settings",
+            Json::object([
+                ("stopAfter", Json::String("parsing".into())),
+                (
+                    "outputSelection",
+                    Json::object([(
+                        "*",
+                        Json::object([("", Json::Array(vec![Json::String("ast".into())]))]),
+                    )]),
+                ),
+            ]),
+        ),
+    ]);
+    child.stdin.take().unwrap().write_all(input.to_string().as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let output = Json::parse(&String::from_utf8_lossy(&output.stdout)).unwrap();
+
+    // The errors are those of the source they are located in
+    let mut errors = BTreeMap::new();
+    for error in output.get("errors").and_then(Json::as_array).unwrap_or_default() {
+        if string(error, "severity") == "error" {
+            let file = error.get("sourceLocation").map_or("", |loc| string(loc, "file"));
+            errors.entry(file.to_string()).or_insert_with(|| string(error, "message").to_string());
+        }
+    }
+
+    sources
+        .iter()
+        .map(|(name, _)| {
+            let ast = output.get("sources").and_then(|sources| sources.get(name)?.get("ast"));
+            let parsed = match (errors.get(name), ast) {
+                (None, Some(ast)) => Parsed::Accepted(reference_outline(ast)),
+                (Some(error), _) => Parsed::Rejected(error.clone()),
+                (None, None) => Parsed::Rejected("no AST".into()),
+            };
+            (name.clone(), parsed)
+        })
+        .collect()
+}
+
+/// Compare the parses of the sources, failing with all the disagreements
+fn assert_agree(sources: &[(String, String)]) {
+    let expected = reference(sources);
+
+    let disagreements = parse(sources)
+        .into_iter()
+        .filter(|(name, actual)| !actual.agrees(&expected[name]))
+        .map(|(name, actual)| {
+            format!("{name}:\n  solc:   {:?}\n  parser: {actual:?}", expected[&name])
+        })
+        .collect::<Vec<_>>();
+    assert!(disagreements.is_empty(), "the parsers disagree on\n{}", disagreements.join("\n"));
+}
+
+#[test]
+#[ignore = "needs solc, run with `just test-differential`"]
+fn test_corpus_differential() {
+    let dir = corpus_dir();
+    let sources = sources(&dir)
+        .into_iter()
+        .map(|path| {
+            let name = path.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/");
+            (name, fs::read_to_string(&path).unwrap())
+        })
+        .collect::<Vec<_>>();
+
+    assert_agree(&sources);
+}
+
+#[test]
+#[ignore = "needs solc, run with `just test-differential`"]
+fn test_snippets_differential() {
+    // Each on its own, as solc gives no tree at all when a source is invalid
+    for &(name, _, contents) in SNIPPETS {
+        assert_agree(&[(name.to_string(), contents.to_string())]);
+    }
+}
+
+#[test]
+fn test_snippets() {
+    for &(name, valid, contents) in SNIPPETS {
+        let sources = [(name.to_string(), contents.to_string())];
+        let accepted = matches!(parse(&sources)[name], Parsed::Accepted(_));
+        assert_eq!(accepted, valid, "{name}");
+    }
+}
+
+#[test]
+fn test_outline() {
+    let source = "pragma solidity ^0.8.0;
+        contract C is B {
+            uint public x;
+            constructor() {}
+            function f() external {}
+            modifier m() { _; }
+        }
+        error E();";
+    let unit = parser::parse(source, 0).unwrap();
+
+    assert_eq!(
+        outline(&unit),
+        [
+            "pragma",
+            "contract C",
+            "C.variable x",
+            "C.constructor",
+            "C.function f",
+            "C.modifier m",
+            "error E"
+        ]
+    );
+}
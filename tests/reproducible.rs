@@ -0,0 +1,89 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reproducibility of the object files: compiling the same sources in two
+//! processes, which seed their hash maps differently, gives the same bytes.
+
+use std::{env, fs, path::Path, process::Command};
+
+/// Contracts with dispatchers, storage, modifiers, and internal and external
+/// calls
+const SOURCE: &str = r#"
+contract Counter {
+    uint count;
+
+    function add(uint n) external returns (uint) { count += n; return count; }
+}
+
+contract Bank {
+    uint8 decimals = 18;
+    uint total;
+    bool open;
+    mapping(address => uint) balances;
+    mapping(address => mapping(uint64 => uint)) allowance;
+    Counter counter;
+
+    modifier whenOpen() { require(open, "closed"); _; }
+
+    constructor() { open = true; }
+
+    function deposit(address to, uint amount) public whenOpen {
+        balances[to] += amount;
+        total = fee(total + amount);
+    }
+
+    function approve(address spender, uint64 id, uint amount) public {
+        allowance[spender][id] = amount;
+    }
+
+    function balanceOf(address who) public view returns (uint) { return balances[who]; }
+
+    function bump(uint n) public returns (uint v) {
+        try counter.add(n) returns (uint r) { v = r; } catch Error(string memory) { v = 1; } catch { v = 2; }
+    }
+
+    function fee(uint a) internal pure returns (uint) { return a - a / 100; }
+}
+"#;
+
+/// Compile the source into an object file per contract in the directory
+fn compile(source: &Path, out_dir: &Path) {
+    let status = Command::new(env!("CARGO_BIN_EXE_hmt-frontend-solidity"))
+        .arg("compile")
+        .arg(source)
+        .arg("--out-dir")
+        .arg(out_dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling {} failed", source.display());
+}
+
+#[test]
+fn test_reproducible_objects() {
+    let dir = env::temp_dir().join(format!("hmt-reproducible-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("Bank.sol");
+    fs::write(&source, SOURCE).unwrap();
+
+    let [first, second] = ["first", "second"].map(|name| dir.join(name));
+    compile(&source, &first);
+    compile(&source, &second);
+
+    for contract in ["Counter", "Bank"] {
+        let object = |dir: &Path| fs::read(dir.join(contract).with_extension("o")).unwrap();
+        assert!(object(&first) == object(&second), "the objects of `{contract}` differ");
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,82 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of the output shown to users, in `tests/snapshots`, so changes
+//! of wording or layout show up in review. Run the tests with
+//! `UPDATE_GOLDEN=1` to accept a change.
+
+use std::path::Path;
+
+use hmt_frontend_solidity::{config::Config, diagnostics::Diagnostic, lint, parser};
+
+mod common;
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(name);
+    common::assert_golden(&path.with_extension("snap"), actual);
+}
+
+/// The reports of the diagnostics, as the CLI prints them without colors
+fn render(diagnostics: &[Diagnostic], source: &str) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| common::strip_ansi(&diagnostic.render(source).unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_parse_errors() {
+    let source = "contract C {\n    function f(uint x public {}\n}\n";
+    let diagnostics = parser::parse(source, 0).unwrap_err();
+    assert_snapshot("unrecognized_token", &render(&diagnostics, source));
+
+    let source = "contract C {\n    uint x;\n";
+    let diagnostics = parser::parse(source, 0).unwrap_err();
+    assert_snapshot("unexpected_eof", &render(&diagnostics, source));
+}
+
+#[test]
+fn test_lint_warnings() {
+    let source = "\
+contract C {
+    address[] holders;
+
+    function pay() public {
+        for (uint i = 0; i < holders.length; i++) {}
+    }
+}
+";
+    let tree = parser::parse(source, 0).unwrap();
+    let diagnostics = lint::run(&tree, &Config::default());
+    assert!(!diagnostics.is_empty());
+    assert_snapshot("unbounded_loop", &render(&diagnostics, source));
+}
+
+/// The parse tree printed by `ast` and `compile --print-ast`
+#[test]
+fn test_ast_dump() {
+    let source = "\
+pragma solidity ^0.8.0;
+
+contract C {
+    uint x;
+
+    function f(uint a) public view returns (uint) {
+        return a + x;
+    }
+}
+";
+    let tree = parser::parse(source, 0).unwrap();
+    assert_snapshot("ast_dump", &format!("{tree:#?}\n"));
+}